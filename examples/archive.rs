@@ -0,0 +1,78 @@
+//! Coordinator storage archive
+//!
+//! Exports all the stored data of the configured client chain genesis hash
+//! into an archive file, or imports an archive file into the configured
+//! storage. Imports are only allowed into storage without any requests for the
+//! archive genesis hash. Storage and client chain are configured as for the
+//! coordinator, via config/default.toml and CO_ environment variables.
+//!
+//! cargo run --example archive -- export coordinator.archive
+//! cargo run --example archive -- import coordinator.archive
+
+extern crate bitcoin;
+extern crate coordinator;
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::{env, process};
+
+use bitcoin::hashes::{hex::FromHex, sha256d};
+
+use coordinator::archive::{export_archive, import_archive, ArchiveSummary};
+use coordinator::config::Config;
+use coordinator::error::{CError, Result};
+use coordinator::interfaces::storage::MongoStorage;
+
+/// Export the configured genesis hash data into an archive file
+fn export(config: &Config, path: &str) -> Result<ArchiveSummary> {
+    let storage = MongoStorage::new(config.storage.clone())?;
+    let genesis_hash = sha256d::Hash::from_hex(&config.clientchain.genesis_hash)?;
+    let file = File::create(path).map_err(|e| CError::Generic(format!("failed creating {}: {}", path, e)))?;
+    let mut writer = BufWriter::new(file);
+    let summary = export_archive(&storage, genesis_hash, &mut writer)?;
+    writer
+        .flush()
+        .map_err(|e| CError::Generic(format!("failed writing {}: {}", path, e)))?;
+    Ok(summary)
+}
+
+/// Import an archive file into the configured storage
+fn import(config: &Config, path: &str) -> Result<ArchiveSummary> {
+    let storage = MongoStorage::new(config.storage.clone())?;
+    let file = File::open(path).map_err(|e| CError::Generic(format!("failed opening {}: {}", path, e)))?;
+    import_archive(&storage, &mut BufReader::new(file))
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 || (args[1] != "export" && args[1] != "import") {
+        eprintln!("usage: archive <export|import> <archive file>");
+        process::exit(2);
+    }
+    let config = match Config::new() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("config failure: {}", e);
+            process::exit(2);
+        }
+    };
+
+    let result = if args[1] == "export" {
+        export(&config, &args[2])
+    } else {
+        import(&config, &args[2])
+    };
+    match result {
+        Ok(summary) => println!(
+            "{} {} requests in {} records for genesis hash {}",
+            if args[1] == "export" { "exported" } else { "imported" },
+            summary.num_requests,
+            summary.num_records,
+            summary.genesis_hash
+        ),
+        Err(e) => {
+            eprintln!("{} failure: {}", args[1], e);
+            process::exit(1);
+        }
+    }
+}
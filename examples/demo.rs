@@ -29,6 +29,7 @@ use ocean_rpc::RpcApi;
 use coordinator::coordinator as coordinator_main;
 use coordinator::interfaces::clientchain::get_first_unspent;
 use coordinator::util::ocean::OceanClient;
+use coordinator::util::scheduler::{JobStatus, Schedule, Scheduler};
 
 /// Demo coordinator with listener and challenge service running
 /// mock implementation for service chain interface and ocean
@@ -60,21 +61,25 @@ fn main() {
         .list_unspent(None, None, None, None, Some("CBT"))
         .unwrap();
     let addr = unspent[0].address.clone();
-    thread::spawn(move || loop {
-        thread::sleep(time::Duration::from_secs(10));
-        if let Err(e) = client_rpc_clone.clone().client.generate(1) {
-            error!("{}", e);
-        }
-        if let Err(e) = client_rpc_clone.send_to_address(
-            &addr,
-            Amount::from_btc(100.0).unwrap(),
-            Some(""),
-            Some(""),
-            Some(true),
-            Some("CBT"),
-        ) {
-            error!("{}", e);
-        }
+    thread::spawn(move || {
+        let mut scheduler = Scheduler::new();
+        let _ = scheduler.add("generate", Schedule::Delayed(time::Duration::from_secs(10)), || {
+            if let Err(e) = client_rpc_clone.clone().client.generate(1) {
+                error!("{}", e);
+            }
+            if let Err(e) = client_rpc_clone.send_to_address(
+                &addr,
+                Amount::from_btc(100.0).unwrap(),
+                Some(""),
+                Some(""),
+                Some(true),
+                Some("CBT"),
+            ) {
+                error!("{}", e);
+            }
+            Ok(JobStatus::Continue)
+        });
+        scheduler.run(None).unwrap();
     });
 
     let genesis_hash = sha256d::Hash::from_hex(&config.clientchain.genesis_hash).unwrap();
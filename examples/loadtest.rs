@@ -0,0 +1,410 @@
+//! Guardnode simulation load test of the coordinator listener
+//!
+//! Spawns a number of simulated guardnodes that post a mix of valid and
+//! invalid challenge proofs at a configurable rate to a listener and reports
+//! the acceptance latency and the distribution of errors returned.
+//!
+//! By default an in-process listener is run with a challenge state containing
+//! the bids of the simulated guardnodes. A running listener can be targeted
+//! instead via --host, in which case only proofs of guardnodes whose bids are
+//! part of the listener challenge state are accepted.
+//!
+//! ```text
+//! cargo run --release --example loadtest -- --guardnodes 500 --rate 2 --invalid 20 --duration 30
+//! ```
+
+extern crate bitcoin;
+extern crate coordinator;
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::str::FromStr;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use std::{env, mem, process, thread};
+
+use bitcoin::consensus::encode::serialize;
+use bitcoin::hashes::{hex::FromHex, hex::ToHex, sha256d, Hash};
+use bitcoin::secp256k1::{All, Message, PublicKey, Secp256k1, SecretKey};
+
+use coordinator::challenger::{ChallengeResponse, ChallengeState, LatestChallenge};
+use coordinator::interfaces::bid::{Bid, BidSet};
+use coordinator::interfaces::request::{PaymentState, Request};
+use coordinator::interfaces::response::LatencyPercentiles;
+use coordinator::listener::{run_listener, ListenerContext};
+use coordinator::registry::ChallengeRegistry;
+use coordinator::util::handler::Handle;
+
+/// Load test options
+struct Options {
+    /// Listener host to target, running an in-process listener if not set
+    host: Option<String>,
+    /// Number of simulated guardnodes
+    guardnodes: u32,
+    /// Proofs posted per second by each guardnode
+    rate: f64,
+    /// Percentage of invalid proofs posted
+    invalid: u32,
+    /// Duration of the load test in seconds
+    duration: u64,
+    /// Challenge hash that valid proofs are signed for
+    challenge: sha256d::Hash,
+    /// Verify threads of the in-process listener
+    verify_threads: usize,
+    /// Verify queue size of the in-process listener
+    verify_queue: usize,
+    /// Late proofs queue size of the in-process listener
+    late_queue: usize,
+}
+
+impl Options {
+    /// Parse options from the command line arguments
+    fn from_args() -> Options {
+        let mut options = Options {
+            host: None,
+            guardnodes: 200,
+            rate: 1.0,
+            invalid: 20,
+            duration: 30,
+            challenge: sha256d::Hash::from_slice(&[0x04; 32]).unwrap(),
+            verify_threads: 2,
+            verify_queue: 1000,
+            late_queue: 100,
+        };
+        let args: Vec<String> = env::args().skip(1).collect();
+        for pair in args.chunks(2) {
+            let (arg, value) = match pair {
+                [arg, value] => (arg.as_str(), value.as_str()),
+                _ => usage(&format!("missing value for {}", pair[0])),
+            };
+            match arg {
+                "--host" => options.host = Some(value.to_owned()),
+                "--guardnodes" => options.guardnodes = parse(arg, value),
+                "--rate" => options.rate = parse(arg, value),
+                "--invalid" => options.invalid = parse::<u32>(arg, value).min(100),
+                "--duration" => options.duration = parse(arg, value),
+                "--challenge" => {
+                    options.challenge = sha256d::Hash::from_hex(value)
+                        .unwrap_or_else(|_| usage(&format!("invalid value for {}: {}", arg, value)))
+                }
+                "--verify-threads" => options.verify_threads = parse(arg, value),
+                "--verify-queue" => options.verify_queue = parse(arg, value),
+                "--late-queue" => options.late_queue = parse(arg, value),
+                _ => usage(&format!("unknown argument {}", arg)),
+            }
+        }
+        options
+    }
+}
+
+/// Parse the value of an argument or exit with usage
+fn parse<T: FromStr>(arg: &str, value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| usage(&format!("invalid value for {}: {}", arg, value)))
+}
+
+/// Print usage and exit
+fn usage(err: &str) -> ! {
+    eprintln!("{}", err);
+    eprintln!(
+        "usage: loadtest [--host <listener host>] [--guardnodes <num>] [--rate <proofs per sec>] [--invalid \
+         <percent>] [--duration <secs>] [--challenge <hash>] [--verify-threads <num>] [--verify-queue <size>] \
+         [--late-queue <size>]"
+    );
+    process::exit(1)
+}
+
+/// Kinds of proofs posted by simulated guardnodes
+#[derive(Clone, Copy)]
+enum ProofKind {
+    /// Valid proof of the guardnode bid
+    Valid,
+    /// Proof signed with a key other than the bid key
+    BadSig,
+    /// Proof of a bid that does not exist
+    BadBid,
+    /// Proof for a hash other than the challenge hash
+    BadHash,
+    /// Body that is not valid json
+    BadJson,
+}
+
+/// Simulated guardnode with a deterministic bid key
+struct Guardnode {
+    /// Bid txid of the guardnode
+    txid: sha256d::Hash,
+    /// Bid secret key of the guardnode
+    key: SecretKey,
+    /// Bid public key of the guardnode
+    pubkey: PublicKey,
+}
+
+impl Guardnode {
+    /// Generate the simulated guardnode with index i
+    fn new(secp: &Secp256k1<All>, i: u32) -> Guardnode {
+        let key = SecretKey::from_slice(&sha256d::Hash::hash(format!("guardnode-key-{}", i).as_bytes())[..]).unwrap();
+        Guardnode {
+            txid: sha256d::Hash::hash(format!("guardnode-bid-{}", i).as_bytes()),
+            key,
+            pubkey: PublicKey::from_secret_key(secp, &key),
+        }
+    }
+
+    /// Generate the body of a proof of the given kind for a challenge hash
+    fn proof(&self, secp: &Secp256k1<All>, kind: ProofKind, challenge: &sha256d::Hash) -> String {
+        let (txid, key, hash) = match kind {
+            ProofKind::Valid => (self.txid, self.key, *challenge),
+            ProofKind::BadSig => (self.txid, SecretKey::from_slice(&[0xaa; 32]).unwrap(), *challenge),
+            ProofKind::BadBid => (sha256d::Hash::hash(&self.txid[..]), self.key, *challenge),
+            ProofKind::BadHash => (self.txid, self.key, sha256d::Hash::hash(&challenge[..])),
+            ProofKind::BadJson => return "{\"txid\": ".to_owned(),
+        };
+        let sig = secp.sign(&Message::from_slice(&serialize(&hash)).unwrap(), &key);
+        format!(
+            r#"{{"txid":"{}","pubkey":"{}","hash":"{}","sig":"{}"}}"#,
+            txid,
+            self.pubkey,
+            hash,
+            sig.serialize_der().to_hex()
+        )
+    }
+}
+
+/// Result of a posted proof
+struct ProofResult {
+    /// Whether the proof was expected to be accepted
+    valid: bool,
+    /// Http status returned or None if the request failed
+    status: Option<u16>,
+    /// Error returned by the listener or the http request
+    error: String,
+    /// Round-trip latency of the request
+    latency_ms: u64,
+}
+
+/// Post a proof to the listener returning the http status and response body
+fn post_proof(host: &str, body: &str) -> std::io::Result<(u16, String)> {
+    let mut stream = TcpStream::connect(host)?;
+    stream.write_all(
+        format!(
+            "POST /challengeproof HTTP/1.0\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: \
+             {}\r\n\r\n{}",
+            host,
+            body.len(),
+            body
+        )
+        .as_bytes(),
+    )?;
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response)?;
+    let status = response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .unwrap_or(0);
+    let body = match response.find("\r\n\r\n") {
+        Some(i) => response[i + 4..].to_owned(),
+        None => String::new(),
+    };
+    Ok((status, body))
+}
+
+/// Run a simulated guardnode posting proofs at the configured rate until the
+/// deadline, sending the result of each proof to the results channel
+fn run_guardnode(i: u32, host: String, options: Arc<Options>, deadline: Instant, results: Sender<ProofResult>) {
+    let secp = Secp256k1::new();
+    let guardnode = Guardnode::new(&secp, i);
+    let interval = Duration::from_micros((1_000_000.0 / options.rate) as u64);
+    // xorshift seeded per guardnode to pick the proof kinds
+    let mut state = 0x9e37_79b9_7f4a_7c15u64 ^ (i as u64 + 1);
+    let mut next = Instant::now() + Duration::from_micros(state % interval.as_micros().max(1) as u64);
+    while next < deadline {
+        let now = Instant::now();
+        if next > now {
+            thread::sleep(next - now);
+        }
+        next += interval;
+
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let kind = if (state % 100) as u32 >= options.invalid {
+            ProofKind::Valid
+        } else {
+            match (state >> 8) % 4 {
+                0 => ProofKind::BadSig,
+                1 => ProofKind::BadBid,
+                2 => ProofKind::BadHash,
+                _ => ProofKind::BadJson,
+            }
+        };
+        let body = guardnode.proof(&secp, kind, &options.challenge);
+        let sent = Instant::now();
+        let (status, error) = match post_proof(&host, &body) {
+            Ok((status, body)) => (Some(status), body),
+            Err(e) => (None, e.to_string()),
+        };
+        let result = ProofResult {
+            valid: match kind {
+                ProofKind::Valid => true,
+                _ => false,
+            },
+            status,
+            error,
+            latency_ms: sent.elapsed().as_millis() as u64,
+        };
+        if results.send(result).is_err() {
+            break;
+        }
+    }
+}
+
+/// Run an in-process listener with a challenge state for the bids of the
+/// simulated guardnodes, counting the responses forwarded to the challenger
+fn run_local_listener(host: &String, options: &Options) -> (Handle, Receiver<ChallengeResponse>) {
+    let secp = Secp256k1::new();
+    let mut bids = BidSet::new();
+    for i in 0..options.guardnodes {
+        let guardnode = Guardnode::new(&secp, i);
+        let _ = bids.insert(Bid {
+            txid: guardnode.txid,
+            pubkey: guardnode.pubkey,
+            payment: None,
+        });
+    }
+    let request_txid = sha256d::Hash::from_slice(&[0xff; 32]).unwrap();
+    let challenge = Arc::new(RwLock::new(Some(ChallengeState {
+        request: Request {
+            txid: request_txid,
+            start_blockheight: 0,
+            end_blockheight: 0,
+            genesis_blockhash: sha256d::Hash::from_slice(&[0; 32]).unwrap(),
+            fee_percentage: 0,
+            num_tickets: options.guardnodes,
+            start_blockheight_clientchain: 0,
+            end_blockheight_clientchain: 0,
+            is_payment_complete: false,
+            payment_state: PaymentState::Pending,
+            response_summary: None,
+            fees: vec![],
+            fee_filter: None,
+            failure: None,
+        },
+        bids: Arc::new(bids),
+        spilled_bids: None,
+        latest_challenge: Arc::new(LatestChallenge::new(Some(options.challenge))),
+        payload: None,
+        commit_reveal: false,
+    })));
+    // the request stays registered for the lifetime of the load test
+    let registry = Arc::new(ChallengeRegistry::new());
+    mem::forget(registry.register(request_txid, challenge));
+    let (resp_tx, resp_rx) = channel();
+    let handle = run_listener(
+        host,
+        registry,
+        resp_tx,
+        options.verify_threads,
+        options.verify_queue,
+        options.late_queue,
+        ListenerContext::disabled(),
+    );
+    // wait for the listener to bind
+    thread::sleep(Duration::from_millis(500));
+    (handle, resp_rx)
+}
+
+fn main() {
+    let options = Arc::new(Options::from_args());
+    let host = options.host.clone().unwrap_or("127.0.0.1:9997".to_owned());
+    let local = match options.host {
+        Some(_) => None,
+        None => Some(run_local_listener(&host, &options)),
+    };
+
+    println!(
+        "running {} guardnodes at {} proofs/sec each with {}% invalid proofs against {} for {}s",
+        options.guardnodes, options.rate, options.invalid, host, options.duration
+    );
+    let start = Instant::now();
+    let deadline = start + Duration::from_secs(options.duration);
+    let (results_tx, results_rx) = channel();
+    let mut guardnodes = vec![];
+    for i in 0..options.guardnodes {
+        let (host, options, results_tx) = (host.clone(), options.clone(), results_tx.clone());
+        guardnodes.push(
+            thread::Builder::new()
+                .name(format!("guardnode-{}", i))
+                .stack_size(256 * 1024)
+                .spawn(move || run_guardnode(i, host, options, deadline, results_tx))
+                .expect("failed spawning guardnode thread"),
+        );
+    }
+    drop(results_tx);
+
+    let mut accepted_ms = vec![];
+    let mut rejected_ms = vec![];
+    let mut unexpected = 0;
+    let mut errors: BTreeMap<String, u32> = BTreeMap::new();
+    for result in results_rx.iter() {
+        let accepted = result.status == Some(200);
+        if accepted != result.valid {
+            unexpected += 1;
+        }
+        if accepted {
+            accepted_ms.push(result.latency_ms);
+            continue;
+        }
+        rejected_ms.push(result.latency_ms);
+        // group errors by status and the error code before any details
+        let code = result.error.split(':').next().unwrap_or("").trim().to_owned();
+        let key = match result.status {
+            Some(status) => format!("{} {}", status, code),
+            None => format!("request failed {}", code),
+        };
+        *errors.entry(key).or_insert(0) += 1;
+    }
+    for guardnode in guardnodes {
+        let _ = guardnode.join();
+    }
+    let elapsed = start.elapsed().as_millis().max(1) as f64 / 1000.0;
+
+    let total = accepted_ms.len() + rejected_ms.len();
+    println!(
+        "sent {} proofs in {:.1}s ({:.1} proofs/sec)",
+        total,
+        elapsed,
+        total as f64 / elapsed
+    );
+    println!(
+        "accepted {}, rejected {}, unexpected outcome {}",
+        accepted_ms.len(),
+        rejected_ms.len(),
+        unexpected
+    );
+    for (name, samples) in &[("accepted", &accepted_ms), ("rejected", &rejected_ms)] {
+        let latency = LatencyPercentiles::from_samples(samples);
+        println!(
+            "{} latency ms: p50 {} p90 {} p99 {} max {}",
+            name, latency.p50, latency.p90, latency.p99, latency.max
+        );
+    }
+    println!("errors:");
+    for (error, count) in &errors {
+        println!("  {:>8} {}", count, error);
+    }
+
+    if let Some((handle, resp_rx)) = local {
+        let responses: Vec<ChallengeResponse> = resp_rx.try_iter().collect();
+        let bids: HashSet<sha256d::Hash> = responses.iter().map(|resp| resp.1.txid).collect();
+        println!(
+            "challenger received {} responses from {} bids",
+            responses.len(),
+            bids.len()
+        );
+        handle.stop();
+    }
+}
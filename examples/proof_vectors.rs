@@ -0,0 +1,54 @@
+//! Challenge proof test vectors
+//!
+//! Prints the canonical challenge proof test vectors as json, for testing
+//! guardnode implementations against, or verifies the signature of a json
+//! challenge proof read from a file as the listener does.
+//!
+//! cargo run --example proof_vectors
+//! cargo run --example proof_vectors -- verify proof.json
+
+extern crate coordinator;
+extern crate serde_json;
+
+use std::fs::File;
+use std::{env, process};
+
+use serde_json::Value;
+
+use coordinator::proof_vectors::{proof_vectors, verify_proof};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    match args.len() {
+        1 => match proof_vectors() {
+            Ok(vectors) => println!("{}", serde_json::to_string_pretty(&vectors).unwrap()),
+            Err(e) => {
+                eprintln!("failed generating vectors: {}", e);
+                process::exit(1);
+            }
+        },
+        3 if args[1] == "verify" => {
+            let proof: Value = match File::open(&args[2])
+                .map_err(|e| e.to_string())
+                .and_then(|file| serde_json::from_reader(file).map_err(|e| e.to_string()))
+            {
+                Ok(proof) => proof,
+                Err(e) => {
+                    eprintln!("failed reading {}: {}", args[2], e);
+                    process::exit(2);
+                }
+            };
+            match verify_proof(proof) {
+                Ok(()) => println!("valid proof"),
+                Err(e) => {
+                    println!("invalid proof: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        _ => {
+            eprintln!("usage: proof_vectors [verify <proof file>]");
+            process::exit(2);
+        }
+    }
+}
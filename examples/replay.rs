@@ -0,0 +1,65 @@
+//! Coordinator journal replay
+//!
+//! Replays a coordinator journal checking that each recorded decision is
+//! reproduced from its recorded inputs. Challenge proof signatures are verified
+//! again, saved responses are checked against the accepted proofs and bid
+//! payment amounts are calculated again from their payment basis. Exits with a
+//! non zero code if any decision could not be reproduced.
+//!
+//! cargo run --example replay -- coordinator.journal
+
+extern crate coordinator;
+
+use std::collections::BTreeMap;
+use std::{env, process};
+
+use coordinator::journal::{replay, Journal, JournalEvent};
+
+/// Name of the event type of a journal event
+fn event_name(event: &JournalEvent) -> &'static str {
+    match event {
+        JournalEvent::ChallengeIssued { .. } => "challenge_issued",
+        JournalEvent::ProofAccepted { .. } => "proof_accepted",
+        JournalEvent::ProofRejected { .. } => "proof_rejected",
+        JournalEvent::ResponseSaved { .. } => "response_saved",
+        JournalEvent::PaymentComputed { .. } => "payment_computed",
+        JournalEvent::BidKeyRotated { .. } => "bid_key_rotated",
+    }
+}
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: replay <journal file>");
+            process::exit(2);
+        }
+    };
+    let entries = match Journal::read(&path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    };
+
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for entry in entries.iter() {
+        *counts.entry(event_name(&entry.event)).or_insert(0) += 1;
+    }
+    println!("{} journal entries", entries.len());
+    for (name, count) in counts.iter() {
+        println!("  {:<20} {}", name, count);
+    }
+
+    let discrepancies = replay(&entries);
+    if discrepancies.len() == 0 {
+        println!("all decisions reproduced");
+        return;
+    }
+    println!("{} decisions not reproduced", discrepancies.len());
+    for discrepancy in discrepancies {
+        println!("  entry {}: {}", discrepancy.seq, discrepancy.reason);
+    }
+    process::exit(1);
+}
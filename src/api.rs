@@ -2,45 +2,216 @@
 //!
 //! Api interface for external requests to the coordinator
 
-use std::net::ToSocketAddrs;
-use std::str;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, ToSocketAddrs};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Sender, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use base64::decode as b64decode;
-use bitcoin::hashes::sha256d;
-use hyper::{Body, Request, StatusCode};
-use jsonrpc_http_server::jsonrpc_core::{Error, ErrorCode, IoHandler, Params, Value};
+use bitcoin::hashes::{hex::FromHex, sha256d};
+use bitcoin::{Amount, PublicKey};
+use futures::sync::oneshot;
+use futures::{future::Either, Future, Stream};
+use hyper::{Body, Method, Request, StatusCode};
+use jsonrpc_http_server::jsonrpc_core::middleware::{Middleware, NoopCallFuture};
+use jsonrpc_http_server::jsonrpc_core::{
+    self as rpc, Error, ErrorCode, FutureResponse, MetaIoHandler, Metadata, Params, Value,
+};
 use jsonrpc_http_server::{
-    hyper::header, AccessControlAllowOrigin, CloseHandle, DomainsValidation, Response, ServerBuilder,
+    hyper::header, AccessControlAllowOrigin, CloseHandle, DomainsValidation, RequestMiddlewareAction, Response,
+    ServerBuilder,
 };
+use ocean::{Address, AddressParams};
 use serde::{Deserialize, Serialize};
 
-use crate::config::ApiConfig;
-use crate::interfaces::response::Response as RequestResponse;
+use crate::audit::{
+    run_audit_bundle, AuditBundleProgress, AuditBundleState, AuditBundleStatus, AuditBundleSummary, AuditBundles,
+    AuditSigner,
+};
+use crate::auth::{auth_provider, basic_credentials, AuthProvider};
+use crate::blacklist::Blacklist;
+use crate::challenger::{
+    challenge_schedule, refresh_bids, BidRefresh, ChallengeResponse, ChallengeState, ScheduledChallenge,
+};
+use crate::config::{ApiConfig, Config, TenantConfig};
+use crate::connectivity::DegradedStatus;
+use crate::coverage::{ChallengeCoverage, CoverageGap};
+use crate::dead_letter::DeadLetter;
+use crate::error::Result;
+use crate::interfaces::clientchain::{ChallengeTx, ClientChain};
+use crate::interfaces::response::{
+    BidLatency, BidProof, BidReconciliation, ChallengeStats, HeightStats, LatencyPercentiles,
+    Response as RequestResponse, ResponseReconciliation, ResponseSnapshot, ResponseSummary,
+};
+use crate::interfaces::service::Service;
 use crate::interfaces::storage::Storage;
-use crate::interfaces::{bid::Bid, request::Request as ServiceRequest};
+use crate::interfaces::{
+    bid::{
+        Bid, BidBlacklisting, BidKeyRotation, BidPayment, BidPaymentBasis, BidPaymentTx, BidRefund, BidRefundState,
+        PayoutAddressType, BID_PAYMENT_FORMULA_VERSION, BID_PAYMENT_LATENCY_FORMULA_VERSION,
+    },
+    request::{
+        AssetFees, BlockFees, FeeFilter, FeePool, OceanRequest, OceanRequestBids, PaymentState,
+        Request as ServiceRequest, RequestAnnotation, RequestCursor, RequestDeposit, RequestFull, RequestRejection,
+    },
+};
+use crate::journal::{Journal, JournalEvent, JournalProof};
+use crate::merkle::{InclusionProof, MerkleStep, ResponseMerkleTree};
+use crate::monitor::{BalanceAlert, BalanceStatus};
+use crate::payments::{estimate_earnings, payment_schedule, EarningsEstimate, PaymentMode, EARNINGS_SAMPLE_REQUESTS};
+use crate::proof::{check_challenge_proof, ChallengeProof, PROOF_V2_SIGTYPE, PROOF_VERSIONS};
+use crate::proof_policy::IpRange;
+use crate::rerun::{rerun_response, BidRerun, RerunReport, RerunSource};
+use crate::rotation::{rotate_bid_key, KeyRotation, KeyRotationPolicy};
+use crate::stall::ClientChainStall;
+use crate::util::compression::{compress, negotiate, Encoding};
+use crate::util::hash_order::HashOrder;
+use crate::util::ocean::{rpc_stats, RpcMethodStats, RpcStats};
+use crate::util::rate_limit::{Quota, RateLimiter};
+use crate::util::schema::schema_of;
+
+/// Api call metadata containing the tenant scope of the caller. Callers with
+/// no tenant scope have access to the requests of all tenants
+#[derive(Clone, Debug)]
+struct ApiMeta {
+    tenant: Option<sha256d::Hash>,
+}
+
+impl ApiMeta {
+    /// Return the meta of admin callers with access to all tenants
+    fn admin() -> ApiMeta {
+        ApiMeta { tenant: None }
+    }
+
+    /// Return the meta of callers with no access, scoped to the null genesis
+    /// hash that no client chain has, so that tenant scoped calls find no
+    /// requests and admin calls are rejected
+    fn no_access() -> ApiMeta {
+        ApiMeta {
+            tenant: Some(sha256d::Hash::default()),
+        }
+    }
+}
+
+/// Deny by default, i.e. callers are not authorized unless scoped explicitly
+impl Default for ApiMeta {
+    fn default() -> ApiMeta {
+        ApiMeta::no_access()
+    }
+}
+
+impl Metadata for ApiMeta {}
+
+/// Check whether a request is within the tenant scope of the caller
+fn in_scope(tenant: &Option<sha256d::Hash>, request: &ServiceRequest) -> bool {
+    match tenant {
+        Some(genesis_hash) => *genesis_hash == request.genesis_blockhash,
+        None => true,
+    }
+}
+
+/// Name of the optional param of api calls overriding the byte order of the
+/// hashes in the call params and response
+pub const HASH_ORDER_PARAM: &str = "hash_order";
+
+/// Run an api call in the hash byte order set by the call params, or the
+/// default order if not set. Hashes in the params are converted to rpc byte
+/// order before the call and hashes in the response, including responses json
+/// encoded as a string, are converted from rpc byte order after the call
+fn with_hash_order<F>(params: Params, default: HashOrder, call: F) -> futures::Finished<Value, Error>
+where
+    F: FnOnce(Params) -> futures::Finished<Value, Error>,
+{
+    let (hash_order, params) = match params {
+        Params::Map(mut map) => {
+            let hash_order = match map.remove(HASH_ORDER_PARAM) {
+                Some(Value::String(order)) => match HashOrder::from_str(&order) {
+                    Ok(hash_order) => hash_order,
+                    Err(e) => return futures::failed(Error::invalid_params(e.to_string())),
+                },
+                Some(_) => return futures::failed(Error::invalid_params("`hash_order` should be a string")),
+                None => default,
+            };
+            match hash_order.to_rpc(Value::Object(map)) {
+                Value::Object(map) => (hash_order, Params::Map(map)),
+                _ => unreachable!(),
+            }
+        }
+        Params::Array(items) => (
+            default,
+            Params::Array(items.into_iter().map(|item| default.to_rpc(item)).collect()),
+        ),
+        Params::None => (default, Params::None),
+    };
+    if hash_order == HashOrder::Rpc {
+        return call(params);
+    }
+    match call(params).wait() {
+        Ok(Value::String(res)) => match serde_json::from_str::<Value>(&res) {
+            Ok(res) => futures::finished(Value::String(hash_order.from_rpc(res).to_string())),
+            Err(_) => futures::finished(Value::String(res)),
+        },
+        Ok(res) => futures::finished(hash_order.from_rpc(res)),
+        Err(e) => futures::failed(e),
+    }
+}
 
 #[derive(Deserialize, Debug)]
 struct GetRequestParams {
     txid: sha256d::Hash,
 }
 
-#[derive(Serialize, Debug)]
-struct GetRequestResponse {
-    request: ServiceRequest,
-    bids: Vec<Bid>,
+/// Get request response, shared with the api client
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct GetRequestResponse {
+    /// Service request
+    pub request: ServiceRequest,
+    /// Request winning bids
+    pub bids: Vec<Bid>,
+    /// Operator annotation of the request, if any, only returned to callers
+    /// without a tenant scope
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotation: Option<RequestAnnotation>,
+}
+
+/// Get the operator annotation of a request for a caller, which is only
+/// returned to callers without a tenant scope as annotations are internal to
+/// the coordinator operator
+fn caller_annotation(
+    tenant: &Option<sha256d::Hash>,
+    storage: &dyn Storage,
+    request_hash: sha256d::Hash,
+) -> Option<RequestAnnotation> {
+    match tenant {
+        Some(_) => None,
+        None => storage.get_request_annotation(request_hash).unwrap(),
+    }
 }
 
-/// Get request RPC call returning corresponding request if it exists
-fn get_request(params: Params, storage: Arc<dyn Storage>) -> futures::Finished<Value, Error> {
+/// Get request RPC call returning corresponding request if it exists and is
+/// within the tenant scope of the caller, along with its operator annotation
+/// for callers without a tenant scope
+fn get_request(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
     let try_parse = params.parse::<GetRequestResponsesParams>();
     match try_parse {
         Ok(parse) => {
             let request_get = storage.get_request(parse.txid).unwrap();
-            if let Some(request) = request_get {
+            if let Some(request) = request_get.filter(|request| in_scope(&tenant, request)) {
                 let bids = storage.get_bids(request.txid).unwrap();
-                let res_serialized = serde_json::to_string(&GetRequestResponse { request, bids }).unwrap();
+                let annotation = caller_annotation(&tenant, storage.as_ref(), request.txid);
+                let res_serialized = serde_json::to_string(&GetRequestResponse {
+                    request,
+                    bids,
+                    annotation,
+                })
+                .unwrap();
                 return futures::finished(Value::String(res_serialized));
             } else {
                 return futures::failed(Error {
@@ -54,64 +225,378 @@ fn get_request(params: Params, storage: Arc<dyn Storage>) -> futures::Finished<V
     }
 }
 
-#[derive(Deserialize, Debug)]
+/// Export request response, shared with the api client
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ExportRequestResponse {
+    /// Request as returned by the ocean getrequests rpc
+    pub request: OceanRequest,
+    /// Request and bids as returned by the ocean getrequestbids rpc
+    pub request_bids: OceanRequestBids,
+}
+
+/// Export request RPC call returning a stored request and its bids in the
+/// shape of the ocean getrequests entry and getrequestbids result for the
+/// request, so that they can be compared against the service chain as is.
+/// Hashes are always in the rpc byte order used by ocean
+fn export_request(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    let try_parse = params.parse::<GetRequestParams>();
+    match try_parse {
+        Ok(parse) => {
+            let request_get = storage.get_request(parse.txid).unwrap();
+            if let Some(request) = request_get.filter(|request| in_scope(&tenant, request)) {
+                let bids = storage.get_bids(request.txid).unwrap();
+                let res_serialized = serde_json::to_string(&ExportRequestResponse {
+                    request: OceanRequest::from_request(&request),
+                    request_bids: OceanRequestBids::from_request(&request, &bids),
+                })
+                .unwrap();
+                return futures::finished(Value::String(res_serialized));
+            } else {
+                return futures::failed(Error {
+                    code: ErrorCode::InvalidParams,
+                    message: "Invalid params: `txid` does not exist.".to_string(),
+                    data: None,
+                });
+            }
+        }
+        Err(e) => return futures::failed(e),
+    }
+}
+
+/// Audit bundle response, shared with the api client
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct AuditBundleResponse {
+    /// Status of the audit bundle of the request
+    pub status: AuditBundleStatus,
+    /// Base64 encoded bundle, once complete
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<String>,
+}
+
+/// Get the txid of the params of an audit bundle RPC call if the request is
+/// stored and in the scope of the caller
+fn audit_bundle_txid(
+    params: Params,
+    tenant: &Option<sha256d::Hash>,
+    storage: &dyn Storage,
+) -> std::result::Result<sha256d::Hash, Error> {
+    let parse = params.parse::<GetRequestParams>()?;
+    match storage.get_request(parse.txid).unwrap() {
+        Some(ref request) if in_scope(tenant, request) => Ok(request.txid),
+        _ => Err(Error {
+            code: ErrorCode::InvalidParams,
+            message: "Invalid params: `txid` does not exist.".to_string(),
+            data: None,
+        }),
+    }
+}
+
+/// Create audit bundle RPC call starting the generation of the audit bundle of
+/// a request in the background, unless already being generated, and returning
+/// the bundle status. The bundle is downloaded with getauditbundle once
+/// complete
+fn create_audit_bundle<D: Storage + Send + Sync + 'static>(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<D>,
+    bundles: Arc<AuditBundles>,
+) -> futures::Finished<Value, Error> {
+    let txid = match audit_bundle_txid(params, &tenant, storage.as_ref()) {
+        Ok(txid) => txid,
+        Err(e) => return futures::failed(e),
+    };
+    let status = run_audit_bundle(bundles, storage, txid);
+    let res_serialized = serde_json::to_string(&AuditBundleResponse { status, bundle: None }).unwrap();
+    futures::finished(Value::String(res_serialized))
+}
+
+/// Get audit bundle RPC call returning the generation progress of the audit
+/// bundle of a request, along with the base64 encoded bundle once complete
+fn get_audit_bundle(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+    bundles: &AuditBundles,
+) -> futures::Finished<Value, Error> {
+    let txid = match audit_bundle_txid(params, &tenant, storage.as_ref()) {
+        Ok(txid) => txid,
+        Err(e) => return futures::failed(e),
+    };
+    match bundles.status(txid) {
+        Some(status) => {
+            let bundle = bundles.bundle(txid).map(|bundle| base64::encode(&bundle[..]));
+            let res_serialized = serde_json::to_string(&AuditBundleResponse { status, bundle }).unwrap();
+            futures::finished(Value::String(res_serialized))
+        }
+        None => futures::failed(Error {
+            code: ErrorCode::InvalidParams,
+            message: "Invalid params: no audit bundle of `txid`, create it with createauditbundle.".to_string(),
+            data: None,
+        }),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
 struct GetRequestsParams {
-    page: u64,
+    page: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
 }
 
-#[derive(Serialize, Debug)]
-struct GetRequestsResponse {
-    requests: Vec<GetRequestResponse>,
-    pages: u64,
+/// Sort order of requests by the time they were last updated in storage
+static API_REQUESTS_SORT_UPDATED: &str = "updated";
+
+/// Get requests response, shared with the api client
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct GetRequestsResponse {
+    /// Requests of the page along with their bids
+    pub requests: Vec<GetRequestResponse>,
+    /// Total number of pages
+    pub pages: u64,
+    /// Cursor of the next page when paging by cursor, unset on the last page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 /// Default limit on the number of requests returned
 static API_REQUESTS_LIMIT: u64 = 10;
 
-/// Get requests RPC call returning all stored requests
-fn get_requests(params: Params, storage: Arc<dyn Storage>) -> futures::Finished<Value, Error> {
-    let mut page = 1;
-    if let Ok(requests_params) = params.parse::<GetRequestsParams>() {
-        page = requests_params.page;
+/// Get requests RPC call returning all stored requests within the tenant scope
+/// of the caller. Pages by number follow the order requests were stored in, or
+/// the order of the time requests were last updated, most recent first, when
+/// sorted by `updated`, while pages by cursor follow the order of requests by
+/// start height and txid and stay stable while new or backfilled requests are
+/// stored. Callers without a tenant scope can filter requests by label, paging
+/// by number through the requests annotated with the label in the order of
+/// start height and txid, and get the operator annotation of each request
+fn get_requests(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    let requests_params = params.parse::<GetRequestsParams>().unwrap_or_default();
+    if let Some(label) = requests_params.label {
+        if tenant.is_some() {
+            return futures::failed(Error {
+                code: ErrorCode::InvalidRequest,
+                message: "Invalid request: request labels not available to tenants.".to_string(),
+                data: None,
+            });
+        }
+        if requests_params.cursor.is_some() || requests_params.sort.is_some() {
+            return futures::failed(Error {
+                code: ErrorCode::InvalidParams,
+                message: "Invalid params: `label` can not be used with `cursor` or `sort`.".to_string(),
+                data: None,
+            });
+        }
+        let mut requests: Vec<ServiceRequest> = storage
+            .get_labelled_requests(&label)
+            .unwrap()
+            .into_iter()
+            .filter_map(|request_hash| storage.get_request(request_hash).unwrap())
+            .collect();
+        requests
+            .sort_by(|a, b| (a.start_blockheight, a.txid.to_string()).cmp(&(b.start_blockheight, b.txid.to_string())));
+        let pages = (requests.len() as f64 / API_REQUESTS_LIMIT as f64).ceil() as u64;
+        let skip = (requests_params.page.unwrap_or(1).max(1) - 1) * API_REQUESTS_LIMIT;
+        let mut response = GetRequestsResponse {
+            requests: vec![],
+            pages,
+            next_cursor: None,
+        };
+        for request in requests
+            .into_iter()
+            .skip(skip as usize)
+            .take(API_REQUESTS_LIMIT as usize)
+        {
+            let bids = storage.get_bids(request.txid).unwrap();
+            let annotation = caller_annotation(&tenant, storage.as_ref(), request.txid);
+            response.requests.push(GetRequestResponse {
+                request,
+                bids,
+                annotation,
+            })
+        }
+        return futures::finished(Value::String(serde_json::to_string(&response).unwrap()));
     }
-    let pages = (storage.get_requests_count().unwrap() as f64 / API_REQUESTS_LIMIT as f64).ceil() as u64;
-    let requests = storage
-        .get_requests(
-            None,
-            Some(API_REQUESTS_LIMIT as i64),
-            Some(((page - 1) * API_REQUESTS_LIMIT) as i64),
-        )
-        .unwrap();
+    let sort_updated = match requests_params.sort.as_ref().map(|sort| sort.as_str()) {
+        None => false,
+        Some(sort) if sort == API_REQUESTS_SORT_UPDATED => true,
+        Some(_) => {
+            return futures::failed(Error {
+                code: ErrorCode::InvalidParams,
+                message: format!("Invalid params: `sort` must be `{}`.", API_REQUESTS_SORT_UPDATED),
+                data: None,
+            })
+        }
+    };
+    if sort_updated && requests_params.cursor.is_some() {
+        return futures::failed(Error {
+            code: ErrorCode::InvalidParams,
+            message: "Invalid params: `cursor` can not be used with `sort`.".to_string(),
+            data: None,
+        });
+    }
+    let pages = (storage.get_requests_count(tenant).unwrap() as f64 / API_REQUESTS_LIMIT as f64).ceil() as u64;
+    let (requests, next_cursor) = match requests_params.cursor {
+        Some(cursor) => {
+            let after = if cursor.is_empty() {
+                None
+            } else {
+                match cursor.parse::<RequestCursor>() {
+                    Ok(after) => Some(after),
+                    Err(_) => {
+                        return futures::failed(Error {
+                            code: ErrorCode::InvalidParams,
+                            message: "Invalid params: `cursor` is not a valid requests cursor.".to_string(),
+                            data: None,
+                        })
+                    }
+                }
+            };
+            // fetch one request past the page to tell whether there is a next page
+            let mut requests = storage
+                .get_requests_page(tenant, after.as_ref(), Some(API_REQUESTS_LIMIT as i64 + 1), None)
+                .unwrap();
+            let mut next_cursor = None;
+            if requests.len() as u64 > API_REQUESTS_LIMIT {
+                requests.truncate(API_REQUESTS_LIMIT as usize);
+                next_cursor = requests.last().map(|request| RequestCursor::at(request).to_string());
+            }
+            (requests, next_cursor)
+        }
+        None if sort_updated => {
+            let page = requests_params.page.unwrap_or(1);
+            let requests = storage
+                .get_requests_recent(
+                    tenant,
+                    Some(API_REQUESTS_LIMIT as i64),
+                    Some(((page - 1) * API_REQUESTS_LIMIT) as i64),
+                )
+                .unwrap();
+            (requests, None)
+        }
+        None => {
+            let page = requests_params.page.unwrap_or(1);
+            let requests = storage
+                .get_requests(
+                    None,
+                    tenant,
+                    Some(API_REQUESTS_LIMIT as i64),
+                    Some(((page - 1) * API_REQUESTS_LIMIT) as i64),
+                )
+                .unwrap();
+            (requests, None)
+        }
+    };
     let mut response = GetRequestsResponse {
         requests: vec![],
         pages,
+        next_cursor,
     };
     for request in requests {
         let bids = storage.get_bids(request.txid).unwrap();
-        response.requests.push(GetRequestResponse { request, bids })
+        let annotation = caller_annotation(&tenant, storage.as_ref(), request.txid);
+        response.requests.push(GetRequestResponse {
+            request,
+            bids,
+            annotation,
+        })
     }
     return futures::finished(Value::String(serde_json::to_string(&response).unwrap()));
 }
 
-#[derive(Deserialize, Debug)]
+/// Get requests full response, shared with the api client
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct GetRequestsFullResponse {
+    /// Requests of the page along with their bids and responses
+    pub requests: Vec<RequestFull>,
+    /// Total number of pages
+    pub pages: u64,
+}
+
+/// Get requests full RPC call returning stored requests within the tenant scope
+/// of the caller along with their bids and responses, fetched in bulk
+fn get_requests_full(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    let mut page = 1;
+    if let Ok(requests_params) = params.parse::<GetRequestsParams>() {
+        page = requests_params.page.unwrap_or(1);
+    }
+    let pages = (storage.get_requests_count(tenant).unwrap() as f64 / API_REQUESTS_LIMIT as f64).ceil() as u64;
+    let requests = storage
+        .get_requests_full(
+            tenant,
+            Some(API_REQUESTS_LIMIT as i64),
+            Some(((page - 1) * API_REQUESTS_LIMIT) as i64),
+        )
+        .unwrap();
+    let response = GetRequestsFullResponse { requests, pages };
+    return futures::finished(Value::String(serde_json::to_string(&response).unwrap()));
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 struct GetRequestResponsesParams {
     txid: sha256d::Hash,
 }
 
-#[derive(Serialize, Debug)]
-struct GetRequestResponseResponse {
-    response: RequestResponse,
+/// Get request response response, shared with the api client
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct GetRequestResponseResponse {
+    /// Request challenge response
+    pub response: RequestResponse,
+    /// Response summary of requests with compacted responses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<ResponseSummary>,
 }
 
 /// Get requests responses RPC call returning all responses for a specific
-/// request transaction id hash
-fn get_request_response(params: Params, storage: Arc<dyn Storage>) -> futures::Finished<Value, Error> {
+/// request transaction id hash. For callers with a tenant scope the request
+/// is also required to belong to the tenant. For requests with compacted
+/// responses the response summary is returned along with the total number of
+/// challenges, as per bid responses are no longer available
+fn get_request_response(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
     let try_parse = params.parse::<GetRequestResponsesParams>();
     match try_parse {
         Ok(parse) => {
+            let request_get = storage.get_request(parse.txid).unwrap();
+            if tenant.is_some() && !request_get.as_ref().map_or(false, |request| in_scope(&tenant, request)) {
+                return futures::failed(Error {
+                    code: ErrorCode::InvalidParams,
+                    message: "Invalid params: `txid` does not exist.".to_string(),
+                    data: None,
+                });
+            }
             let response_get = storage.get_response(parse.txid).unwrap();
+            let summary = request_get.and_then(|request| request.response_summary);
             if let Some(response) = response_get {
-                let res_serialized = serde_json::to_string(&GetRequestResponseResponse { response }).unwrap();
+                let res_serialized = serde_json::to_string(&GetRequestResponseResponse { response, summary }).unwrap();
+                return futures::finished(Value::String(res_serialized));
+            } else if let Some(summary) = summary {
+                let mut response = RequestResponse::new();
+                response.num_challenges = summary.num_challenges;
+                let res_serialized = serde_json::to_string(&GetRequestResponseResponse {
+                    response,
+                    summary: Some(summary),
+                })
+                .unwrap();
                 return futures::finished(Value::String(res_serialized));
             } else {
                 return futures::failed(Error {
@@ -125,276 +610,5334 @@ fn get_request_response(params: Params, storage: Arc<dyn Storage>) -> futures::F
     }
 }
 
-/// Do basic authorization on incoming request by parsing the AUTHORIZATION
-/// header decoding username/password and comparing with config
-fn authorize(our_auth: &str, request: &Request<Body>) -> bool {
-    let auth = request
-        .headers()
-        .get(header::AUTHORIZATION)
-        .map(|h| h.to_str().unwrap_or("").to_owned());
-    if let Some(auth_basic) = auth {
-        let auth_parts: Vec<&str> = auth_basic.split(" ").collect();
-        if auth_parts.len() == 2 {
-            if auth_parts[0] == "Basic" {
-                if let Ok(auth_part_up) = &b64decode(auth_parts[1]) {
-                    if let Ok(auth_part_up_utf8) = str::from_utf8(&auth_part_up) {
-                        return our_auth == auth_part_up_utf8;
-                    }
+#[derive(Serialize, Debug)]
+struct VerifyRequestResponseResponse {
+    valid: bool,
+    hashes: Vec<sha256d::Hash>,
+}
+
+/// Verify request response RPC call checking the stored response of a request
+/// against its integrity hash chain. The chain of hashes is returned so that
+/// callers can detect retroactive edits by comparing against hashes they
+/// received earlier
+fn verify_request_response(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    let try_parse = params.parse::<GetRequestResponsesParams>();
+    match try_parse {
+        Ok(parse) => {
+            if tenant.is_some() {
+                let request_get = storage.get_request(parse.txid).unwrap();
+                if !request_get.map_or(false, |request| in_scope(&tenant, &request)) {
+                    return futures::failed(Error {
+                        code: ErrorCode::InvalidParams,
+                        message: "Invalid params: `txid` does not exist.".to_string(),
+                        data: None,
+                    });
+                }
+            }
+            match storage.get_response(parse.txid).unwrap() {
+                Some(response) => {
+                    let hashes = storage.get_response_hashes(parse.txid).unwrap();
+                    let res_serialized = serde_json::to_string(&VerifyRequestResponseResponse {
+                        valid: response.verify_integrity(&hashes),
+                        hashes,
+                    })
+                    .unwrap();
+                    return futures::finished(Value::String(res_serialized));
+                }
+                None => {
+                    return futures::failed(Error {
+                        code: ErrorCode::InvalidParams,
+                        message: "Invalid params: `txid` does not exist.".to_string(),
+                        data: None,
+                    })
                 }
             }
         }
+        Err(e) => return futures::failed(e),
     }
-    false
 }
 
-/// Run Api RPC server for external requests that require information from the
-/// coordinator. Data returned to the caller are drawn from the storage
-/// interface which is shared with the main coordinator process
-pub fn run_api_server<D: Storage + Send + Sync + 'static>(config: &ApiConfig, storage: Arc<D>) -> CloseHandle {
-    let mut io = IoHandler::default();
-    let storage_ref = storage.clone();
-    io.add_method("getrequestresponse", move |params: Params| {
-        get_request_response(params, storage_ref.clone())
-    });
-    let storage_ref = storage.clone();
-    io.add_method("getrequest", move |params: Params| {
-        get_request(params, storage_ref.clone())
-    });
-    io.add_method("getrequests", move |params: Params| {
-        get_requests(params, storage.clone())
-    });
+#[derive(Serialize, Debug)]
+struct RerunRequestResponseResponse {
+    report: RerunReport,
+}
 
-    let addr: Vec<_> = config
-        .host
-        .to_socket_addrs()
-        .expect("Unable to resolve domain")
-        .collect();
+/// Rerun request response RPC call recomputing the stored response of a
+/// request from its per challenge records, or from the journal if the request
+/// has no records, and returning a report of the differences for resolving
+/// disputed results. The stored response is not modified. Only available to
+/// callers without a tenant scope
+fn rerun_request_response(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+    journal_path: &Option<String>,
+) -> futures::Finished<Value, Error> {
+    if tenant.is_some() {
+        return futures::failed(Error {
+            code: ErrorCode::InvalidRequest,
+            message: "Invalid request: response reruns not available to tenants.".to_string(),
+            data: None,
+        });
+    }
+    let parse = match params.parse::<GetRequestResponsesParams>() {
+        Ok(parse) => parse,
+        Err(e) => return futures::failed(e),
+    };
+    match rerun_response(&*storage, parse.txid, journal_path) {
+        Ok(Some(report)) => {
+            let res_serialized = serde_json::to_string(&RerunRequestResponseResponse { report }).unwrap();
+            futures::finished(Value::String(res_serialized))
+        }
+        Ok(None) => futures::failed(Error {
+            code: ErrorCode::InvalidParams,
+            message: "Invalid params: `txid` does not exist.".to_string(),
+            data: None,
+        }),
+        Err(e) => {
+            warn!("rerun request response error: {}", e);
+            futures::failed(Error::internal_error())
+        }
+    }
+}
 
-    let our_auth = format! {"{}:{}", config.user, config.pass};
-    let server = ServerBuilder::new(io)
-        .cors(DomainsValidation::AllowOnly(vec![AccessControlAllowOrigin::Null]))
-        .request_middleware(move |request: Request<Body>| {
-            if our_auth != "" && !authorize(&our_auth, &request) {
-                return Response {
-                    code: StatusCode::UNAUTHORIZED,
-                    content_type: header::HeaderValue::from_str("text/plain").unwrap(),
-                    content: "Bad Authorization Attempt".to_string(),
+#[derive(Serialize, Deserialize, Debug)]
+struct GetRequestLogsParams {
+    txid: sha256d::Hash,
+}
+
+#[derive(Serialize, Debug)]
+struct GetRequestLogsResponse {
+    logs: Vec<String>,
+}
+
+/// Get request logs RPC call returning the most recent stored log lines for a
+/// specific request transaction id hash. For callers with a tenant scope the
+/// request is also required to belong to the tenant
+fn get_request_logs(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    let try_parse = params.parse::<GetRequestLogsParams>();
+    match try_parse {
+        Ok(parse) => {
+            if tenant.is_some() {
+                let request_get = storage.get_request(parse.txid).unwrap();
+                if !request_get.map_or(false, |request| in_scope(&tenant, &request)) {
+                    return futures::failed(Error {
+                        code: ErrorCode::InvalidParams,
+                        message: "Invalid params: `txid` does not exist.".to_string(),
+                        data: None,
+                    });
                 }
-                .into();
             }
-            request.into()
-        })
-        .threads(2)
-        .start_http(&addr[0])
-        .expect("api error");
+            let logs = storage.get_request_logs(parse.txid).unwrap();
+            let res_serialized = serde_json::to_string(&GetRequestLogsResponse { logs }).unwrap();
+            return futures::finished(Value::String(res_serialized));
+        }
+        Err(e) => return futures::failed(e),
+    }
+}
 
-    let close_handle = server.close_handle();
-    let _ = thread::spawn(move || server.wait());
-    close_handle // handler to stop the server from the main thread
+#[derive(Serialize, Debug)]
+struct GetUnverifiedRequestsResponse {
+    deposits: Vec<RequestDeposit>,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Get unverified requests RPC call returning the fee deposits of requests
+/// within the tenant scope of the caller that were refused challenging as the
+/// fee promised was not found locked on the service chain
+fn get_unverified_requests(
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    let deposits = storage.get_request_deposits(Some(false), tenant).unwrap();
+    let res_serialized = serde_json::to_string(&GetUnverifiedRequestsResponse { deposits }).unwrap();
+    futures::finished(Value::String(res_serialized))
+}
 
-    use std::collections::HashSet;
+#[derive(Serialize, Debug)]
+struct GetRejectedRequestsResponse {
+    rejections: Vec<RequestRejection>,
+}
 
-    use futures::Future;
+/// Get rejected requests RPC call returning the requests within the tenant
+/// scope of the caller that were refused challenging as their parameters
+/// failed validation, along with the reason for each rejection
+fn get_rejected_requests(tenant: Option<sha256d::Hash>, storage: Arc<dyn Storage>) -> futures::Finished<Value, Error> {
+    let rejections = storage.get_request_rejections(tenant).unwrap();
+    let res_serialized = serde_json::to_string(&GetRejectedRequestsResponse { rejections }).unwrap();
+    futures::finished(Value::String(res_serialized))
+}
 
-    use crate::interfaces::mocks::storage::MockStorage;
-    use crate::util::testing::{gen_challenge_state, gen_dummy_hash, setup_logger};
+#[derive(Deserialize, Serialize, Debug)]
+struct GetDeadLettersParams {
+    limit: Option<u64>,
+}
 
-    #[test]
-    fn get_request_test() {
-        setup_logger();
-        let storage = Arc::new(MockStorage::new());
-        let dummy_hash = gen_dummy_hash(1);
+#[derive(Serialize, Debug)]
+struct GetDeadLettersResponse {
+    letters: Vec<DeadLetter>,
+}
 
-        // no such request
-        let s = format!(r#"{{"txid": "{}"}}"#, dummy_hash.to_string());
-        let params: Params = serde_json::from_str(&s).unwrap();
-        let resp = get_request(params, storage.clone());
-        assert_eq!(
-            "Invalid params: `txid` does not exist.",
-            resp.wait().unwrap_err().message
-        );
+/// Default limit on the number of dead letters returned
+static API_DEAD_LETTERS_LIMIT: u64 = 100;
 
-        // save actual state
-        let state = gen_challenge_state(&dummy_hash);
-        storage
-            .save_challenge_request_state(&state.request, &state.bids)
-            .unwrap();
-        let s = format!(r#"{{"txid": "{}"}}"#, dummy_hash.to_string());
-        let params: Params = serde_json::from_str(&s).unwrap();
-        let resp = get_request(params, storage.clone());
-        assert_eq!(
-            format!(
-                r#"{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}}"#,
-                dummy_hash.to_string()
-            ),
-            resp.wait().unwrap()
-        );
+/// Get dead letters RPC call returning the most recent challenge proof
+/// submissions that the listener could not parse, up to the limit, with their
+/// rejection reason and redacted payload. Only available to callers without a
+/// tenant scope as the submissions could not be attributed to a tenant
+fn get_dead_letters(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    if tenant.is_some() {
+        return futures::failed(Error {
+            code: ErrorCode::InvalidRequest,
+            message: "Invalid request: dead letters not available to tenants.".to_string(),
+            data: None,
+        });
     }
+    let mut limit = API_DEAD_LETTERS_LIMIT;
+    if let Ok(GetDeadLettersParams {
+        limit: Some(params_limit),
+    }) = params.parse::<GetDeadLettersParams>()
+    {
+        limit = params_limit;
+    }
+    let letters = storage.get_dead_letters(limit).unwrap();
+    let res_serialized = serde_json::to_string(&GetDeadLettersResponse { letters }).unwrap();
+    futures::finished(Value::String(res_serialized))
+}
 
-    #[test]
-    fn get_requests_test() {
-        setup_logger();
-        let storage = Arc::new(MockStorage::new());
-        let dummy_hash = gen_dummy_hash(1);
-        let _params = Params::None;
+/// Max number of labels of a request annotation
+const API_ANNOTATION_MAX_LABELS: usize = 16;
 
-        let s_p1 = format!(r#"{{"page": 1}}"#);
-        let params_p1: Params = serde_json::from_str(&s_p1).unwrap();
-        let s_m1 = format!(r#"{{"page": -1}}"#);
-        let params_m1: Params = serde_json::from_str(&s_m1).unwrap();
-        let s_p2 = format!(r#"{{"page": 2}}"#);
-        let params_p2: Params = serde_json::from_str(&s_p2).unwrap();
-        let s_p5 = format!(r#"{{"page": 5}}"#);
-        let params_p5: Params = serde_json::from_str(&s_p5).unwrap();
+/// Max length in chars of each label of a request annotation
+const API_ANNOTATION_MAX_LABEL_LEN: usize = 64;
 
-        // no requests
-        let resp = get_requests(Params::None, storage.clone());
-        assert_eq!(r#"{"requests":[],"pages":0}"#, resp.wait().unwrap());
-        let resp = get_requests(params_p1.clone(), storage.clone());
-        assert_eq!(r#"{"requests":[],"pages":0}"#, resp.wait().unwrap());
-        let resp = get_requests(params_m1.clone(), storage.clone());
-        assert_eq!(r#"{"requests":[],"pages":0}"#, resp.wait().unwrap());
-        let resp = get_requests(params_p2.clone(), storage.clone());
-        assert_eq!(r#"{"requests":[],"pages":0}"#, resp.wait().unwrap());
-        let resp = get_requests(params_p5.clone(), storage.clone());
-        assert_eq!(r#"{"requests":[],"pages":0}"#, resp.wait().unwrap());
+/// Max length in chars of the notes of a request annotation
+const API_ANNOTATION_MAX_NOTES_LEN: usize = 4096;
 
-        // save actual state for 1 request
-        let state = gen_challenge_state(&dummy_hash);
-        storage
-            .save_challenge_request_state(&state.request, &state.bids)
-            .unwrap();
-        let resp_1 = format!(
-            r#"{{"requests":[{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}}],"pages":1}}"#,
-            dummy_hash.to_string()
-        );
-        let resp = get_requests(Params::None, storage.clone());
-        assert_eq!(resp_1, resp.wait().unwrap());
-        let resp = get_requests(params_p1.clone(), storage.clone());
-        assert_eq!(resp_1, resp.wait().unwrap());
-        let resp = get_requests(params_m1.clone(), storage.clone());
-        assert_eq!(resp_1, resp.wait().unwrap());
-        let resp = get_requests(params_p2.clone(), storage.clone());
-        assert_eq!(r#"{"requests":[],"pages":1}"#, resp.wait().unwrap());
-        let resp = get_requests(params_p5.clone(), storage.clone());
-        assert_eq!(r#"{"requests":[],"pages":1}"#, resp.wait().unwrap());
+#[derive(Deserialize, Serialize, Debug)]
+struct SetRequestAnnotationParams {
+    txid: sha256d::Hash,
+    #[serde(default)]
+    labels: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+}
 
-        // save actual state for another request (2 total)
-        let dummy_hash2 = gen_dummy_hash(2);
-        let state2 = gen_challenge_state(&dummy_hash2);
-        storage
-            .save_challenge_request_state(&state2.request, &state2.bids)
+/// Error of the request annotation RPC calls for callers with a tenant scope
+fn annotation_tenant_error() -> Error {
+    Error {
+        code: ErrorCode::InvalidRequest,
+        message: "Invalid request: request annotations not available to tenants.".to_string(),
+        data: None,
+    }
+}
+
+/// Set request annotation RPC call replacing the labels and notes of an
+/// existing request with the ones given, returning the stored annotation.
+/// Labels are trimmed and deduplicated and empty labels dropped. Only available
+/// to callers without a tenant scope as annotations are internal to the
+/// coordinator operator
+fn set_request_annotation(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    if tenant.is_some() {
+        return futures::failed(annotation_tenant_error());
+    }
+    let parse = match params.parse::<SetRequestAnnotationParams>() {
+        Ok(parse) => parse,
+        Err(e) => return futures::failed(e),
+    };
+    if storage.get_request(parse.txid).unwrap().is_none() {
+        return futures::failed(Error {
+            code: ErrorCode::InvalidParams,
+            message: "Invalid params: `txid` does not exist.".to_string(),
+            data: None,
+        });
+    }
+    let mut labels: Vec<String> = vec![];
+    for label in parse.labels.iter().map(|label| label.trim()) {
+        if label.chars().count() > API_ANNOTATION_MAX_LABEL_LEN {
+            return futures::failed(Error {
+                code: ErrorCode::InvalidParams,
+                message: format!(
+                    "Invalid params: `labels` must be at most {} chars each.",
+                    API_ANNOTATION_MAX_LABEL_LEN
+                ),
+                data: None,
+            });
+        }
+        if !label.is_empty() && !labels.iter().any(|existing| existing == label) {
+            labels.push(label.to_owned());
+        }
+    }
+    if labels.len() > API_ANNOTATION_MAX_LABELS {
+        return futures::failed(Error {
+            code: ErrorCode::InvalidParams,
+            message: format!(
+                "Invalid params: `labels` must be at most {}.",
+                API_ANNOTATION_MAX_LABELS
+            ),
+            data: None,
+        });
+    }
+    if let Some(notes) = &parse.notes {
+        if notes.chars().count() > API_ANNOTATION_MAX_NOTES_LEN {
+            return futures::failed(Error {
+                code: ErrorCode::InvalidParams,
+                message: format!(
+                    "Invalid params: `notes` must be at most {} chars.",
+                    API_ANNOTATION_MAX_NOTES_LEN
+                ),
+                data: None,
+            });
+        }
+    }
+    let annotation = RequestAnnotation {
+        labels,
+        notes: parse.notes,
+    };
+    storage.save_request_annotation(parse.txid, &annotation).unwrap();
+    futures::finished(Value::String(serde_json::to_string(&annotation).unwrap()))
+}
+
+/// Get request annotation RPC call returning the labels and notes of a
+/// request, which are empty if the request has not been annotated. Only
+/// available to callers without a tenant scope
+fn get_request_annotation(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    if tenant.is_some() {
+        return futures::failed(annotation_tenant_error());
+    }
+    let parse = match params.parse::<GetRequestResponsesParams>() {
+        Ok(parse) => parse,
+        Err(e) => return futures::failed(e),
+    };
+    if storage.get_request(parse.txid).unwrap().is_none() {
+        return futures::failed(Error {
+            code: ErrorCode::InvalidParams,
+            message: "Invalid params: `txid` does not exist.".to_string(),
+            data: None,
+        });
+    }
+    let annotation = storage.get_request_annotation(parse.txid).unwrap().unwrap_or_default();
+    futures::finished(Value::String(serde_json::to_string(&annotation).unwrap()))
+}
+
+#[derive(Serialize, Debug)]
+struct GetBidRefundsResponse {
+    refunds: Vec<BidRefund>,
+}
+
+/// Get bid refunds RPC call returning the refunds of the expired bid locks of
+/// requests within the tenant scope of the caller, including the unsigned
+/// refund transactions exported for bid owners to sign
+fn get_bid_refunds(tenant: Option<sha256d::Hash>, storage: Arc<dyn Storage>) -> futures::Finished<Value, Error> {
+    let refunds = storage.get_bid_refunds(tenant).unwrap();
+    let res_serialized = serde_json::to_string(&GetBidRefundsResponse { refunds }).unwrap();
+    futures::finished(Value::String(res_serialized))
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct GetBlacklistParams {
+    history: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct GetBlacklistResponse {
+    blacklistings: Vec<BidBlacklisting>,
+}
+
+/// Get blacklist RPC call returning the bids within the tenant scope of the
+/// caller that are blacklisted after repeated invalid proofs. If history is
+/// set all stored blacklistings are returned, including expired ones
+fn get_blacklist(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    blacklist: &Blacklist,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    let mut history = false;
+    if let Ok(blacklist_params) = params.parse::<GetBlacklistParams>() {
+        history = blacklist_params.history;
+    }
+    let blacklistings = if history {
+        storage.get_bid_blacklistings(tenant).unwrap()
+    } else {
+        blacklist.blacklistings(tenant)
+    };
+    let res_serialized = serde_json::to_string(&GetBlacklistResponse { blacklistings }).unwrap();
+    futures::finished(Value::String(res_serialized))
+}
+
+#[derive(Serialize, Debug)]
+struct GetWalletStatusResponse {
+    status: Option<BalanceStatus>,
+}
+
+/// Get wallet status RPC call returning the latest wallet balances, projected
+/// consumption and low balance alerts of the balance monitor. Only available
+/// to callers without a tenant scope as the wallet is shared by all tenants
+fn get_wallet_status(
+    tenant: Option<sha256d::Hash>,
+    status: &Arc<RwLock<Option<BalanceStatus>>>,
+) -> futures::Finished<Value, Error> {
+    if tenant.is_some() {
+        return futures::failed(Error {
+            code: ErrorCode::InvalidRequest,
+            message: "Invalid request: wallet status not available to tenants.".to_string(),
+            data: None,
+        });
+    }
+    let status = status.read().unwrap().clone();
+    let res_serialized = serde_json::to_string(&GetWalletStatusResponse { status }).unwrap();
+    futures::finished(Value::String(res_serialized))
+}
+
+#[derive(Serialize, Debug)]
+struct GetChainStatusResponse {
+    degraded: Option<DegradedStatus>,
+}
+
+/// Get chain status RPC call returning the degraded status of the coordinator
+/// while the service or client chain is unreachable and challenging is paused,
+/// or null if both chains are reachable
+fn get_chain_status(degraded_status: &Arc<RwLock<Option<DegradedStatus>>>) -> futures::Finished<Value, Error> {
+    let degraded = degraded_status.read().unwrap().clone();
+    let res_serialized = serde_json::to_string(&GetChainStatusResponse { degraded }).unwrap();
+    futures::finished(Value::String(res_serialized))
+}
+
+/// Features of the coordinator enabled by its config
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CoordinatorFeatures {
+    /// Bids are paid, as opposed to payments only being calculated
+    pub payments: bool,
+    /// Bids are paid at the end of each payment epoch instead of at the end
+    /// of each request
+    pub payment_epochs: bool,
+    /// Type of the addresses that bids are paid to
+    pub payout_address_type: String,
+    /// Challenges are chained off the output of the previous challenge
+    pub chain_challenges: bool,
+    /// Responses are reconciled against proofs published on the client chain
+    pub reconciliation: bool,
+    /// Coordinator decisions are journaled for replay
+    pub journal: bool,
+    /// Bids repeatedly sending invalid proofs are temporarily blacklisted
+    pub blacklist: bool,
+}
+
+/// Coordinator version, enabled features and supported formats, allowing
+/// guardnodes and tooling to negotiate compatible behaviour
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CoordinatorInfo {
+    /// Coordinator semantic version
+    pub version: String,
+    /// Enabled features
+    pub features: CoordinatorFeatures,
+    /// Supported challenge proof versions
+    pub proof_versions: Vec<u64>,
+    /// Supported signature types of v2 challenge proofs
+    pub proof_sigtypes: Vec<String>,
+    /// Scheme of the challenge payload that challenge proofs commit to
+    pub proof_payload: String,
+    /// Version of the bid payment formula
+    pub payment_formula_version: u32,
+    /// Default byte order of hashes in api params and responses
+    pub hash_order: String,
+    /// Client chain name
+    pub chain: String,
+    /// Genesis hashes of the client chains served
+    pub genesis_hashes: Vec<String>,
+}
+
+impl CoordinatorInfo {
+    /// Build the coordinator info from the coordinator config. The genesis
+    /// hashes served are the client chain genesis hash and those of tenants
+    pub fn from_config(config: &Config) -> CoordinatorInfo {
+        let clientchain = config.tenant_clientchain();
+        let mut genesis_hashes = vec![clientchain.genesis_hash.clone()];
+        for tenant in config.tenants.iter() {
+            if !genesis_hashes.contains(&tenant.genesis_hash) {
+                genesis_hashes.push(tenant.genesis_hash.clone());
+            }
+        }
+        CoordinatorInfo {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            features: CoordinatorFeatures {
+                payments: clientchain.payment_addr.is_some()
+                    || clientchain.payment_mode == PaymentMode::WatchOnly.as_str(),
+                payment_epochs: config.payment_epoch.is_some(),
+                payout_address_type: clientchain.payment_address_type.clone(),
+                chain_challenges: clientchain.chain_challenges,
+                reconciliation: config.reconciliation,
+                journal: config.journal_path.is_some(),
+                blacklist: config.listener_blacklist_strikes > 0,
+            },
+            proof_versions: PROOF_VERSIONS.to_vec(),
+            proof_sigtypes: vec![PROOF_V2_SIGTYPE.to_owned()],
+            proof_payload: clientchain.challenge_payload.clone(),
+            payment_formula_version: if config.latency_weights.len() > 0 {
+                BID_PAYMENT_LATENCY_FORMULA_VERSION
+            } else {
+                BID_PAYMENT_FORMULA_VERSION
+            },
+            hash_order: config.api.hash_order.clone(),
+            chain: clientchain.chain,
+            genesis_hashes,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct GetInfoResponse {
+    info: CoordinatorInfo,
+}
+
+/// Get info RPC call returning the coordinator version, enabled features and
+/// supported formats. For callers with a tenant scope only the genesis hash of
+/// the tenant is returned
+fn get_info(tenant: Option<sha256d::Hash>, info: &CoordinatorInfo) -> futures::Finished<Value, Error> {
+    let mut info = info.clone();
+    if let Some(tenant) = tenant {
+        info.genesis_hashes
+            .retain(|genesis_hash| *genesis_hash == tenant.to_string());
+    }
+    let res_serialized = serde_json::to_string(&GetInfoResponse { info }).unwrap();
+    futures::finished(Value::String(res_serialized))
+}
+
+#[derive(Serialize, Debug)]
+struct GetApiStatsResponse {
+    stats: ApiStats,
+    rate_limits: RateLimitStats,
+}
+
+/// Get api stats RPC call returning the api worker pool stats, including the
+/// number of calls pending a worker thread, for tuning the api threads and
+/// queue, and the number of calls rejected by the rate limits. Only available
+/// to callers without a tenant scope
+fn get_api_stats(
+    tenant: Option<sha256d::Hash>,
+    pool: &ApiPool,
+    rate_limits: &ApiRateLimits,
+) -> futures::Finished<Value, Error> {
+    if tenant.is_some() {
+        return futures::failed(Error {
+            code: ErrorCode::InvalidRequest,
+            message: "Invalid request: api stats not available to tenants.".to_string(),
+            data: None,
+        });
+    }
+    let res_serialized = serde_json::to_string(&GetApiStatsResponse {
+        stats: pool.stats(),
+        rate_limits: rate_limits.stats(),
+    })
+    .unwrap();
+    futures::finished(Value::String(res_serialized))
+}
+
+#[derive(Serialize, Debug)]
+struct GetRpcStatsResponse {
+    slow_call_ms: u64,
+    methods: BTreeMap<String, RpcMethodStats>,
+}
+
+/// Get rpc stats RPC call returning the number of calls, failures and slow
+/// calls and the call durations of each service and client chain rpc method
+/// called by the coordinator, for diagnosing stalls on slow node calls. Only
+/// available to callers without a tenant scope
+fn get_rpc_stats(tenant: Option<sha256d::Hash>, stats: &RpcStats) -> futures::Finished<Value, Error> {
+    if tenant.is_some() {
+        return futures::failed(Error {
+            code: ErrorCode::InvalidRequest,
+            message: "Invalid request: rpc stats not available to tenants.".to_string(),
+            data: None,
+        });
+    }
+    let res_serialized = serde_json::to_string(&GetRpcStatsResponse {
+        slow_call_ms: stats.slow_call_ms(),
+        methods: stats.methods(),
+    })
+    .unwrap();
+    futures::finished(Value::String(res_serialized))
+}
+
+#[derive(Serialize, Debug)]
+struct ChallengesPausedResponse {
+    paused: bool,
+}
+
+/// Pause and resume challenges RPC calls setting the flag that the challenger
+/// checks before issuing each challenge, so that challenge broadcasting can be
+/// halted without stopping the coordinator and losing the challenge state.
+/// Only available to callers without a tenant scope as challenges are issued
+/// for all tenants
+fn set_challenges_paused(
+    tenant: Option<sha256d::Hash>,
+    challenges_paused: &AtomicBool,
+    paused: bool,
+) -> futures::Finished<Value, Error> {
+    if tenant.is_some() {
+        return futures::failed(Error {
+            code: ErrorCode::InvalidRequest,
+            message: "Invalid request: challenge pausing not available to tenants.".to_string(),
+            data: None,
+        });
+    }
+    challenges_paused.store(paused, Ordering::SeqCst);
+    info!("challenges {}", if paused { "paused" } else { "resumed" });
+    let res_serialized = serde_json::to_string(&ChallengesPausedResponse { paused }).unwrap();
+    futures::finished(Value::String(res_serialized))
+}
+
+#[derive(Serialize, Debug)]
+struct RefreshBidsResponse {
+    refresh: BidRefresh,
+}
+
+/// Refresh bids RPC call re-fetching the winning bids of the active request
+/// from the service chain and applying any changes immediately, returning the
+/// bids added and removed. Only available to callers without a tenant scope
+/// as the active request may belong to any tenant
+fn refresh_bids_call(
+    tenant: Option<sha256d::Hash>,
+    challenge: &RwLock<Option<ChallengeState>>,
+    service: Arc<dyn Service>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    if tenant.is_some() {
+        return futures::failed(Error {
+            code: ErrorCode::InvalidRequest,
+            message: "Invalid request: bid refresh not available to tenants.".to_string(),
+            data: None,
+        });
+    }
+    match refresh_bids(service.as_ref(), storage.as_ref(), challenge) {
+        Ok(Some(refresh)) => {
+            let res_serialized = serde_json::to_string(&RefreshBidsResponse { refresh }).unwrap();
+            futures::finished(Value::String(res_serialized))
+        }
+        Ok(None) => futures::failed(Error {
+            code: ErrorCode::InvalidRequest,
+            message: "Invalid request: no active request.".to_string(),
+            data: None,
+        }),
+        Err(e) => {
+            warn!("refresh bids error: {}", e);
+            futures::failed(Error::internal_error())
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GetChallengeScheduleParams {
+    txid: sha256d::Hash,
+}
+
+#[derive(Serialize, Debug)]
+struct GetChallengeScheduleResponse {
+    service_height: u64,
+    schedule: Vec<ScheduledChallenge>,
+}
+
+/// Get challenge schedule RPC call returning the projected remaining challenges
+/// of an upcoming or active request, with the expected service chain heights
+/// and approximate timestamps, so that guardnode operators can plan around
+/// them. Requests are looked up in storage and then on the service chain
+fn get_challenge_schedule(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+    service: Arc<dyn Service>,
+    challenge_frequency: u64,
+    block_time: u64,
+) -> futures::Finished<Value, Error> {
+    let try_parse = params.parse::<GetChallengeScheduleParams>();
+    match try_parse {
+        Ok(parse) => {
+            let mut request_get = storage.get_request(parse.txid).unwrap();
+            if request_get.is_none() {
+                match service.get_requests() {
+                    Ok(requests) => {
+                        request_get = requests
+                            .unwrap_or(vec![])
+                            .into_iter()
+                            .find(|request| request.txid == parse.txid)
+                    }
+                    Err(e) => {
+                        warn!("get challenge schedule error: {}", e);
+                        return futures::failed(Error::internal_error());
+                    }
+                }
+            }
+            match request_get.filter(|request| in_scope(&tenant, request)) {
+                Some(request) => {
+                    let service_height = match service.get_blockheight() {
+                        Ok(height) => height,
+                        Err(e) => {
+                            warn!("get challenge schedule error: {}", e);
+                            return futures::failed(Error::internal_error());
+                        }
+                    };
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or(0);
+                    let schedule = challenge_schedule(&request, service_height, challenge_frequency, block_time, now);
+                    let res_serialized = serde_json::to_string(&GetChallengeScheduleResponse {
+                        service_height,
+                        schedule,
+                    })
+                    .unwrap();
+                    return futures::finished(Value::String(res_serialized));
+                }
+                None => {
+                    return futures::failed(Error {
+                        code: ErrorCode::InvalidParams,
+                        message: "Invalid params: `txid` does not exist.".to_string(),
+                        data: None,
+                    })
+                }
+            }
+        }
+        Err(e) => return futures::failed(e),
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct GetRequestPaymentsResponse {
+    schedule: Vec<u32>,
+    epochs: Vec<ResponseSnapshot>,
+}
+
+/// Get request payments RPC call returning the payment schedule of a request,
+/// as the service chain heights that bids are paid at, and the history of
+/// paid and unpaid epochs for requests paid per epoch. For callers with a
+/// tenant scope the request is also required to belong to the tenant
+fn get_request_payments(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+    payment_epoch: Option<u64>,
+) -> futures::Finished<Value, Error> {
+    let try_parse = params.parse::<GetRequestResponsesParams>();
+    match try_parse {
+        Ok(parse) => {
+            let request_get = storage.get_request(parse.txid).unwrap();
+            if let Some(request) = request_get.filter(|request| in_scope(&tenant, request)) {
+                let res_serialized = serde_json::to_string(&GetRequestPaymentsResponse {
+                    schedule: payment_schedule(&request, payment_epoch),
+                    epochs: storage.get_response_snapshots(request.txid).unwrap(),
+                })
+                .unwrap();
+                return futures::finished(Value::String(res_serialized));
+            } else {
+                return futures::failed(Error {
+                    code: ErrorCode::InvalidParams,
+                    message: "Invalid params: `txid` does not exist.".to_string(),
+                    data: None,
+                });
+            }
+        }
+        Err(e) => return futures::failed(e),
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct GetRequestLatencyResponse {
+    verify: LatencyPercentiles,
+    proof: LatencyPercentiles,
+    bids: Vec<BidLatency>,
+}
+
+/// Get request latency RPC call returning the percentiles of the challenge
+/// round-trip latencies of a request in milliseconds, from the broadcast of
+/// each challenge to its confirmed verification and to each proof arrival,
+/// along with the proof latency percentiles of each bid.
+/// For callers with a tenant scope the request is also required to belong to
+/// the tenant
+fn get_request_latency(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    let try_parse = params.parse::<GetRequestResponsesParams>();
+    match try_parse {
+        Ok(parse) => {
+            if tenant.is_some() {
+                let request_get = storage.get_request(parse.txid).unwrap();
+                if !request_get.map_or(false, |request| in_scope(&tenant, &request)) {
+                    return futures::failed(Error {
+                        code: ErrorCode::InvalidParams,
+                        message: "Invalid params: `txid` does not exist.".to_string(),
+                        data: None,
+                    });
+                }
+            }
+            match storage.get_challenge_latency(parse.txid).unwrap() {
+                Some(latency) => {
+                    let (verify, proof) = latency.percentiles();
+                    let res_serialized = serde_json::to_string(&GetRequestLatencyResponse {
+                        verify,
+                        proof,
+                        bids: latency.bid_percentiles(),
+                    })
+                    .unwrap();
+                    return futures::finished(Value::String(res_serialized));
+                }
+                None => {
+                    return futures::failed(Error {
+                        code: ErrorCode::InvalidParams,
+                        message: "Invalid params: `txid` does not exist.".to_string(),
+                        data: None,
+                    })
+                }
+            }
+        }
+        Err(e) => return futures::failed(e),
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct GetRequestCoverageResponse {
+    coverage: ChallengeCoverage,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stalls: Vec<ClientChainStall>,
+}
+
+/// Get request coverage RPC call returning the fraction of the challenges
+/// scheduled over the request service period that were issued on time, along
+/// with the gaps of service chain heights without a challenge and the client
+/// chain stall periods that challenges were skipped for.
+/// For callers with a tenant scope the request is also required to belong to
+/// the tenant
+fn get_request_coverage(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    let try_parse = params.parse::<GetRequestResponsesParams>();
+    match try_parse {
+        Ok(parse) => {
+            if tenant.is_some() {
+                let request_get = storage.get_request(parse.txid).unwrap();
+                if !request_get.map_or(false, |request| in_scope(&tenant, &request)) {
+                    return futures::failed(Error {
+                        code: ErrorCode::InvalidParams,
+                        message: "Invalid params: `txid` does not exist.".to_string(),
+                        data: None,
+                    });
+                }
+            }
+            match storage.get_challenge_coverage(parse.txid).unwrap() {
+                Some(coverage) => {
+                    let stalls = storage.get_clientchain_stalls(parse.txid).unwrap();
+                    let res_serialized =
+                        serde_json::to_string(&GetRequestCoverageResponse { coverage, stalls }).unwrap();
+                    return futures::finished(Value::String(res_serialized));
+                }
+                None => {
+                    return futures::failed(Error {
+                        code: ErrorCode::InvalidParams,
+                        message: "Invalid params: `txid` does not exist.".to_string(),
+                        data: None,
+                    })
+                }
+            }
+        }
+        Err(e) => return futures::failed(e),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GetRequestResponseProofParams {
+    txid: sha256d::Hash,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bid: Option<sha256d::Hash>,
+}
+
+/// Response of the getrequestresponseproof RPC call
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetRequestResponseProofResponse {
+    /// Merkle root of the per bid response counts, None if there are no bids
+    pub root: Option<sha256d::Hash>,
+    /// Number of bids committed to by the merkle root
+    pub num_leaves: u32,
+    /// Inclusion proof of the response count of the requested bid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<InclusionProof>,
+}
+
+/// Get request response proof RPC call returning the merkle root of the per
+/// bid response counts of a request and, if a bid is specified, the inclusion
+/// proof of the response count of the bid, so that guardnodes can verify their
+/// response counts independently of the coordinator.
+/// For callers with a tenant scope the request is also required to belong to
+/// the tenant
+fn get_request_response_proof(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    let try_parse = params.parse::<GetRequestResponseProofParams>();
+    match try_parse {
+        Ok(parse) => {
+            if tenant.is_some() {
+                let request_get = storage.get_request(parse.txid).unwrap();
+                if !request_get.map_or(false, |request| in_scope(&tenant, &request)) {
+                    return futures::failed(Error {
+                        code: ErrorCode::InvalidParams,
+                        message: "Invalid params: `txid` does not exist.".to_string(),
+                        data: None,
+                    });
+                }
+            }
+            let response = match storage.get_response(parse.txid).unwrap() {
+                Some(response) => response,
+                None => {
+                    return futures::failed(Error {
+                        code: ErrorCode::InvalidParams,
+                        message: "Invalid params: `txid` does not exist.".to_string(),
+                        data: None,
+                    })
+                }
+            };
+            let tree = ResponseMerkleTree::new(&response);
+            let proof = match parse.bid {
+                Some(bid) => match tree.proof(&bid) {
+                    Some(proof) => Some(proof),
+                    None => {
+                        return futures::failed(Error {
+                            code: ErrorCode::InvalidParams,
+                            message: "Invalid params: `bid` does not exist.".to_string(),
+                            data: None,
+                        })
+                    }
+                },
+                None => None,
+            };
+            let res_serialized = serde_json::to_string(&GetRequestResponseProofResponse {
+                root: tree.root(),
+                num_leaves: tree.num_leaves(),
+                proof,
+            })
+            .unwrap();
+            return futures::finished(Value::String(res_serialized));
+        }
+        Err(e) => return futures::failed(e),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GetBidActivityParams {
+    txid: sha256d::Hash,
+    bid: sha256d::Hash,
+}
+
+/// Activity of a bid on a single challenge of a request
+#[derive(Serialize, Debug)]
+struct BidChallengeActivity {
+    challenge: sha256d::Hash,
+    height: u64,
+    responded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    clientchain_height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+struct GetBidActivityResponse {
+    responses: u32,
+    missed: u32,
+    activity: Vec<BidChallengeActivity>,
+}
+
+/// Get bid activity RPC call returning the timeline of the challenges of a
+/// request in the order of the service chain height they were issued at, with
+/// the client chain height and unix timestamp in ms that the proof of the bid
+/// was accepted at for each challenge the bid responded to, so that guardnode
+/// operators can tell exactly which challenges they missed. Not available once
+/// the responses of the request are compacted.
+/// For callers with a tenant scope the request is also required to belong to
+/// the tenant
+fn get_bid_activity(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    let parse = match params.parse::<GetBidActivityParams>() {
+        Ok(parse) => parse,
+        Err(e) => return futures::failed(e),
+    };
+    let request = match storage
+        .get_request(parse.txid)
+        .unwrap()
+        .filter(|request| in_scope(&tenant, request))
+    {
+        Some(request) => request,
+        None => {
+            return futures::failed(Error {
+                code: ErrorCode::InvalidParams,
+                message: "Invalid params: `txid` does not exist.".to_string(),
+                data: None,
+            })
+        }
+    };
+    if storage.get_bid(parse.txid, parse.bid).unwrap().is_none() {
+        return futures::failed(Error {
+            code: ErrorCode::InvalidParams,
+            message: "Invalid params: `bid` does not exist.".to_string(),
+            data: None,
+        });
+    }
+    if request.response_summary.is_some() {
+        return futures::failed(Error {
+            code: ErrorCode::InvalidRequest,
+            message: "Invalid request: responses of the request have been compacted.".to_string(),
+            data: None,
+        });
+    }
+    let proofs: HashMap<sha256d::Hash, BidProof> = storage
+        .get_bid_proofs(parse.txid, parse.bid)
+        .unwrap()
+        .into_iter()
+        .map(|proof| (proof.challenge, proof))
+        .collect();
+    let mut response = GetBidActivityResponse {
+        responses: 0,
+        missed: 0,
+        activity: vec![],
+    };
+    for stats in storage.get_challenge_stats(parse.txid).unwrap() {
+        let proof = proofs.get(&stats.challenge);
+        if proof.is_some() {
+            response.responses += 1;
+        } else {
+            response.missed += 1;
+        }
+        response.activity.push(BidChallengeActivity {
+            challenge: stats.challenge,
+            height: stats.height,
+            responded: proof.is_some(),
+            clientchain_height: proof.map(|proof| proof.clientchain_height),
+            timestamp: proof.map(|proof| proof.timestamp),
+        });
+    }
+    futures::finished(Value::String(serde_json::to_string(&response).unwrap()))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GetRequestHeightStatsParams {
+    txid: sha256d::Hash,
+    start_height: Option<u64>,
+    end_height: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+struct GetRequestHeightStatsResponse {
+    heights: Vec<HeightStats>,
+}
+
+/// Get request height stats RPC call returning the responses to the challenges
+/// of a request aggregated by the service chain height that the challenges
+/// were issued at, optionally within a range of heights, so that the response
+/// coverage over the request period can be checked. Heights without any
+/// challenge are not listed. For callers with a tenant scope the request is
+/// also required to belong to the tenant
+fn get_request_height_stats(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    let try_parse = params.parse::<GetRequestHeightStatsParams>();
+    match try_parse {
+        Ok(parse) => {
+            let request_get = storage.get_request(parse.txid).unwrap();
+            if request_get.filter(|request| in_scope(&tenant, request)).is_none() {
+                return futures::failed(Error {
+                    code: ErrorCode::InvalidParams,
+                    message: "Invalid params: `txid` does not exist.".to_string(),
+                    data: None,
+                });
+            }
+            let stats: Vec<ChallengeStats> = storage
+                .get_challenge_stats(parse.txid)
+                .unwrap()
+                .into_iter()
+                .filter(|stats| {
+                    parse.start_height.map_or(true, |start| stats.height >= start)
+                        && parse.end_height.map_or(true, |end| stats.height <= end)
+                })
+                .collect();
+            let res_serialized = serde_json::to_string(&GetRequestHeightStatsResponse {
+                heights: HeightStats::from_challenges(&stats),
+            })
+            .unwrap();
+            futures::finished(Value::String(res_serialized))
+        }
+        Err(e) => futures::failed(e),
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct GetRequestReconciliationResponse {
+    reconciliation: ResponseReconciliation,
+}
+
+/// Get request reconciliation RPC call returning the reconciliation of the
+/// response of a request against the challenge proofs published by guardnodes
+/// on the client chain, flagging bids whose responses diverge. For callers
+/// with a tenant scope the request is also required to belong to the tenant
+fn get_request_reconciliation(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    let try_parse = params.parse::<GetRequestResponsesParams>();
+    match try_parse {
+        Ok(parse) => {
+            if tenant.is_some() {
+                let request_get = storage.get_request(parse.txid).unwrap();
+                if !request_get.map_or(false, |request| in_scope(&tenant, &request)) {
+                    return futures::failed(Error {
+                        code: ErrorCode::InvalidParams,
+                        message: "Invalid params: `txid` does not exist.".to_string(),
+                        data: None,
+                    });
+                }
+            }
+            match storage.get_response_reconciliation(parse.txid).unwrap() {
+                Some(reconciliation) => {
+                    let res_serialized =
+                        serde_json::to_string(&GetRequestReconciliationResponse { reconciliation }).unwrap();
+                    return futures::finished(Value::String(res_serialized));
+                }
+                None => {
+                    return futures::failed(Error {
+                        code: ErrorCode::InvalidParams,
+                        message: "Invalid params: `txid` does not exist.".to_string(),
+                        data: None,
+                    })
+                }
+            }
+        }
+        Err(e) => return futures::failed(e),
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct GetFeePoolResponse {
+    fee_pool: FeePool,
+}
+
+/// Get fee pool RPC call returning the fee pool of a finished request; the
+/// client chain fees accrued over the request per block and per fee asset and
+/// the share allocated to guardnodes, as calculated once for the payment of the
+/// request. For callers with a tenant scope the request is also required to
+/// belong to the tenant
+fn get_fee_pool(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    let try_parse = params.parse::<GetRequestResponsesParams>();
+    match try_parse {
+        Ok(parse) => {
+            let request_get = storage.get_request(parse.txid).unwrap();
+            if !request_get.map_or(false, |request| in_scope(&tenant, &request)) {
+                return futures::failed(Error {
+                    code: ErrorCode::InvalidParams,
+                    message: "Invalid params: `txid` does not exist.".to_string(),
+                    data: None,
+                });
+            }
+            match storage.get_fee_pool(parse.txid).unwrap() {
+                Some(fee_pool) => {
+                    let res_serialized = serde_json::to_string(&GetFeePoolResponse { fee_pool }).unwrap();
+                    futures::finished(Value::String(res_serialized))
+                }
+                None => futures::failed(Error {
+                    code: ErrorCode::InvalidParams,
+                    message: "Invalid params: fee pool of `txid` not calculated yet.".to_string(),
+                    data: None,
+                }),
+            }
+        }
+        Err(e) => futures::failed(e),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GetChallengeTxParams {
+    hash: sha256d::Hash,
+}
+
+#[derive(Serialize, Debug)]
+struct GetChallengeTxResponse {
+    challenge: ChallengeTx,
+}
+
+/// Get challenge tx RPC call returning the raw and decoded challenge
+/// transaction for a specific challenge hash as fetched from the client chain
+fn get_challenge_tx(params: Params, clientchain: Arc<dyn ClientChain>) -> futures::Finished<Value, Error> {
+    let try_parse = params.parse::<GetChallengeTxParams>();
+    match try_parse {
+        Ok(parse) => match clientchain.get_challenge_tx(&parse.hash) {
+            Ok(challenge) => {
+                let res_serialized = serde_json::to_string(&GetChallengeTxResponse { challenge }).unwrap();
+                return futures::finished(Value::String(res_serialized));
+            }
+            Err(e) => {
+                warn!("get challenge tx error: {}", e);
+                return futures::failed(Error {
+                    code: ErrorCode::InvalidParams,
+                    message: "Invalid params: `hash` does not exist.".to_string(),
+                    data: None,
+                });
+            }
+        },
+        Err(e) => return futures::failed(e),
+    }
+}
+
+/// Submit challenge proof RPC call accepting the same fields as the listener
+/// /challengeproof request. The proof goes through the same validation and
+/// if the signature is valid it is forwarded to the challenger. Proofs
+/// accepted or rejected are recorded in the journal and invalid signatures
+/// count as strikes towards blacklisting the proof bid
+fn submit_challenge_proof(
+    params: Params,
+    challenge: &Arc<RwLock<Option<ChallengeState>>>,
+    challenge_resp: &Mutex<Sender<ChallengeResponse>>,
+    journal: &Journal,
+    blacklist: &Blacklist,
+) -> futures::Finished<Value, Error> {
+    let try_parse = params.parse::<Value>();
+    match try_parse {
+        Ok(parse) => match check_challenge_proof(parse.clone(), challenge)
+            .and_then(|proof| blacklist.check_proof(&proof, challenge).map(|request| (proof, request)))
+        {
+            Ok((proof, request)) => {
+                if let Err(e) = ChallengeProof::verify(&proof) {
+                    let _ = blacklist.strike(&proof.bid.txid, &request);
+                    let reason = format!("bad-sig: {}", e);
+                    journal.record(JournalEvent::ProofRejected {
+                        proof: Some(JournalProof::from_proof(&proof)),
+                        reason: reason.clone(),
+                        policies: vec![],
+                    });
+                    return futures::failed(Error {
+                        code: ErrorCode::InvalidParams,
+                        message: reason,
+                        data: None,
+                    });
+                }
+                blacklist.clear_strikes(&proof.bid.txid);
+                journal.record(JournalEvent::ProofAccepted {
+                    proof: JournalProof::from_proof(&proof),
+                });
+                if let Err(_) = challenge_resp
+                    .lock()
+                    .unwrap()
+                    .send(ChallengeResponse(proof.hash, proof.bid))
+                {
+                    return futures::failed(Error::internal_error());
+                }
+                return futures::finished(Value::Bool(true));
+            }
+            Err(e) => {
+                journal.record(JournalEvent::ProofRejected {
+                    proof: ChallengeProof::from_json(parse)
+                        .ok()
+                        .map(|proof| JournalProof::from_proof(&proof)),
+                    reason: e.clone(),
+                    policies: vec![],
+                });
+                return futures::failed(Error {
+                    code: ErrorCode::InvalidParams,
+                    message: e,
+                    data: None,
+                });
+            }
+        },
+        Err(e) => return futures::failed(e),
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct RotateBidKeyResponse {
+    rotation: BidKeyRotation,
+}
+
+/// Rotate bid key RPC call replacing the pubkey of a bid of the request being
+/// challenged, signed as required by the key rotation policy. Rotations under
+/// the admin policy are only accepted from callers without a tenant scope and
+/// tenants can only rotate the keys of bids of their own requests. Accepted
+/// rotations are stored and recorded in the journal
+fn rotate_bid_key_call(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    policy: KeyRotationPolicy,
+    challenge: &Arc<RwLock<Option<ChallengeState>>>,
+    journal: &Journal,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    let rotation = match params.parse::<Value>().map(KeyRotation::from_json) {
+        Ok(Ok(rotation)) => rotation,
+        Ok(Err(e)) => {
+            return futures::failed(Error {
+                code: ErrorCode::InvalidParams,
+                message: format!("Invalid params: bad key rotation: {}", e),
+                data: None,
+            })
+        }
+        Err(e) => return futures::failed(e),
+    };
+    let out_of_scope = challenge.read().unwrap().as_ref().map_or(false, |ch| {
+        ch.request.txid == rotation.request && !in_scope(&tenant, &ch.request)
+    });
+    if out_of_scope {
+        return futures::failed(Error {
+            code: ErrorCode::InvalidParams,
+            message: "bad-request".to_string(),
+            data: None,
+        });
+    }
+    match rotate_bid_key(rotation, policy, tenant.is_none(), challenge, &*storage) {
+        Ok(rotation) => {
+            journal.record(JournalEvent::BidKeyRotated {
+                rotation: rotation.clone(),
+            });
+            let res_serialized = serde_json::to_string(&RotateBidKeyResponse { rotation }).unwrap();
+            futures::finished(Value::String(res_serialized))
+        }
+        Err(e) => futures::failed(Error {
+            code: ErrorCode::InvalidParams,
+            message: e,
+            data: None,
+        }),
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct GetBidKeyRotationsResponse {
+    rotations: Vec<BidKeyRotation>,
+}
+
+/// Get bid key rotations RPC call returning the audit trail of the bid key
+/// rotations of a request, with the signatures of each rotation, in the order
+/// that they were made. For callers with a tenant scope the request is also
+/// required to belong to the tenant
+fn get_bid_key_rotations(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+) -> futures::Finished<Value, Error> {
+    let try_parse = params.parse::<GetRequestResponsesParams>();
+    match try_parse {
+        Ok(parse) => {
+            let request_get = storage.get_request(parse.txid).unwrap();
+            if !request_get.map_or(false, |request| in_scope(&tenant, &request)) {
+                return futures::failed(Error {
+                    code: ErrorCode::InvalidParams,
+                    message: "Invalid params: `txid` does not exist.".to_string(),
+                    data: None,
+                });
+            }
+            let rotations = storage.get_bid_key_rotations(parse.txid).unwrap();
+            let res_serialized = serde_json::to_string(&GetBidKeyRotationsResponse { rotations }).unwrap();
+            futures::finished(Value::String(res_serialized))
+        }
+        Err(e) => futures::failed(e),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct EstimateEarningsParams {
+    txid: sha256d::Hash,
+    response_rate: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+struct EstimateEarningsResponse {
+    estimate: EarningsEstimate,
+}
+
+/// Estimate earnings RPC call returning the expected payout of a hypothetical
+/// bid on an active or upcoming request, from the fees accrued per service
+/// chain block by the latest finished requests of the same client chain and
+/// the number of bids on the request, so that guardnode operators can price
+/// their bids. The bid is assumed to respond to every challenge unless a
+/// response rate between 0 and 1 is set. Requests are looked up in storage and
+/// then on the service chain. For callers with a tenant scope the request is
+/// also required to belong to the tenant
+fn estimate_earnings_call(
+    params: Params,
+    tenant: Option<sha256d::Hash>,
+    storage: Arc<dyn Storage>,
+    service: Arc<dyn Service>,
+    fee_percentage: Option<u32>,
+) -> futures::Finished<Value, Error> {
+    let parse = match params.parse::<EstimateEarningsParams>() {
+        Ok(parse) => parse,
+        Err(e) => return futures::failed(e),
+    };
+    let response_rate = parse.response_rate.unwrap_or(1.0);
+    if !(response_rate >= 0.0 && response_rate <= 1.0) {
+        return futures::failed(Error {
+            code: ErrorCode::InvalidParams,
+            message: "Invalid params: `response_rate` should be between 0 and 1.".to_string(),
+            data: None,
+        });
+    }
+
+    let mut stored = true;
+    let mut request_get = storage.get_request(parse.txid).unwrap();
+    if request_get.is_none() {
+        stored = false;
+        match service.get_requests() {
+            Ok(requests) => {
+                request_get = requests
+                    .unwrap_or(vec![])
+                    .into_iter()
+                    .find(|request| request.txid == parse.txid)
+            }
+            Err(e) => {
+                warn!("estimate earnings error: {}", e);
+                return futures::failed(Error::internal_error());
+            }
+        }
+    }
+    let request = match request_get.filter(|request| in_scope(&tenant, request)) {
+        Some(request) => request,
+        None => {
+            return futures::failed(Error {
+                code: ErrorCode::InvalidParams,
+                message: "Invalid params: `txid` does not exist.".to_string(),
+                data: None,
+            })
+        }
+    };
+    // bids only queried for requests within the tenant scope of the caller
+    let num_bids = if stored {
+        Some(storage.get_bids(request.txid).unwrap().len())
+    } else {
+        None
+    };
+    let (service_height, num_bids) = match (service.get_blockheight(), num_bids) {
+        (Ok(height), Some(num_bids)) => (height, num_bids),
+        (Ok(height), None) => match service.get_request_bids(&request.txid) {
+            Ok(bids) => (height, bids.map_or(0, |bids| bids.len())),
+            Err(e) => {
+                warn!("estimate earnings error: {}", e);
+                return futures::failed(Error::internal_error());
+            }
+        },
+        (Err(e), _) => {
+            warn!("estimate earnings error: {}", e);
+            return futures::failed(Error::internal_error());
+        }
+    };
+    if service_height >= request.end_blockheight as u64 {
+        return futures::failed(Error {
+            code: ErrorCode::InvalidParams,
+            message: "Invalid params: `txid` has finished.".to_string(),
+            data: None,
+        });
+    }
+
+    // fee pools of the latest finished requests of the client chain
+    let genesis = Some(request.genesis_blockhash);
+    let skip = (storage.get_requests_count(genesis).unwrap() - EARNINGS_SAMPLE_REQUESTS).max(0);
+    let samples: Vec<(ServiceRequest, FeePool)> = storage
+        .get_requests(None, genesis, None, Some(skip))
+        .unwrap()
+        .into_iter()
+        .filter(|sample| sample.txid != request.txid)
+        .filter_map(|sample| {
+            let pool = storage.get_fee_pool(sample.txid).unwrap();
+            pool.map(|pool| (sample, pool))
+        })
+        .collect();
+    let estimate = estimate_earnings(
+        &request,
+        num_bids,
+        &samples,
+        fee_percentage.unwrap_or(request.fee_percentage),
+        response_rate,
+    );
+    let res_serialized = serde_json::to_string(&EstimateEarningsResponse { estimate }).unwrap();
+    futures::finished(Value::String(res_serialized))
+}
+
+/// Api method description with the json schemas of its params and response
+#[derive(Serialize, Debug)]
+struct ApiMethod {
+    name: &'static str,
+    description: &'static str,
+    params: Value,
+    response: Value,
+}
+
+impl ApiMethod {
+    /// Return new ApiMethod with schemas generated from sample params and
+    /// response values
+    fn new<P: Serialize, R: Serialize>(
+        name: &'static str,
+        description: &'static str,
+        params: &P,
+        response: &R,
+    ) -> ApiMethod {
+        ApiMethod {
+            name,
+            description,
+            params: schema_of(params),
+            response: schema_of(response),
+        }
+    }
+}
+
+/// List methods response. Method responses, apart from submitchallengeproof,
+/// are returned json encoded as a string
+#[derive(Serialize, Debug)]
+struct ListMethodsResponse {
+    methods: Vec<ApiMethod>,
+}
+
+/// Sample hash used in the samples of api types
+fn sample_hash() -> sha256d::Hash {
+    sha256d::Hash::from_hex(&"00".repeat(32)).unwrap()
+}
+
+/// Sample audit bundle status in a generation state
+fn sample_audit_bundle_status(state: AuditBundleState) -> AuditBundleStatus {
+    AuditBundleStatus {
+        txid: sample_hash(),
+        state,
+        progress: AuditBundleProgress {
+            section: "signature".to_owned(),
+            sections_done: 6,
+            sections_total: 6,
+            num_records: 12,
+        },
+        summary: Some(AuditBundleSummary {
+            txid: sample_hash(),
+            num_records: 12,
+            digest: sample_hash(),
+            pubkey: Some(format!("02{}", "00".repeat(32))),
+            signature: Some("3044".to_owned()),
+        }),
+        error: None,
+    }
+}
+
+/// Sample bid with all optional fields set
+fn sample_bid() -> Bid {
+    let pubkey = PublicKey::from_str("026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3").unwrap();
+    Bid {
+        txid: sample_hash(),
+        pubkey: pubkey.key,
+        payment: Some(BidPayment {
+            txs: vec![BidPaymentTx {
+                txid: sample_hash(),
+                amount: Amount::from_sat(1),
+                confirmations: 1,
+            }],
+            address: Address::p2pkh(&pubkey, None, &AddressParams::ELEMENTS),
+            address_type: PayoutAddressType::P2pkh,
+            amount: Amount::from_sat(1),
+            intent: Some(String::new()),
+            basis: Some(BidPaymentBasis {
+                formula_version: BID_PAYMENT_FORMULA_VERSION,
+                fees_amount: Amount::from_sat(1),
+                fee_percentage: 1,
+                num_bids: 1,
+                num_challenges: 1,
+                num_responses: 1,
+                latency_weighting: None,
+            }),
+        }),
+    }
+}
+
+/// Sample bid key rotation signed by both keys
+fn sample_bid_key_rotation() -> BidKeyRotation {
+    let pubkey = sample_bid().pubkey;
+    BidKeyRotation {
+        txid: sample_hash(),
+        request: sample_hash(),
+        genesis_blockhash: sample_hash(),
+        old_pubkey: pubkey,
+        new_pubkey: pubkey,
+        sig: Some(String::new()),
+        new_sig: Some(String::new()),
+        policy: KeyRotationPolicy::BothKeys.as_str().to_owned(),
+        time: 1,
+    }
+}
+
+/// Sample response with a bid response and challenge
+fn sample_response() -> RequestResponse {
+    let mut response = RequestResponse::new();
+    response.num_challenges = 1;
+    let _ = response.bid_responses.insert(sample_hash(), 1);
+    response.challenges.push(sample_hash());
+    response
+}
+
+/// Sample request with all optional fields set
+fn sample_request() -> ServiceRequest {
+    ServiceRequest {
+        txid: sample_hash(),
+        start_blockheight: 1,
+        end_blockheight: 1,
+        genesis_blockhash: sample_hash(),
+        fee_percentage: 1,
+        num_tickets: 1,
+        start_blockheight_clientchain: 1,
+        end_blockheight_clientchain: 1,
+        is_payment_complete: false,
+        payment_state: PaymentState::Pending,
+        response_summary: Some(sample_response().summary()),
+        fees: vec![AssetFees {
+            asset: String::from("CBT"),
+            amount: Amount::from_sat(1),
+        }],
+        fee_filter: Some(FeeFilter {
+            exclude_addresses: vec![],
+            exclude_script_types: vec![String::from("nulldata")],
+            include_addresses: vec![],
+        }),
+        failure: None,
+    }
+}
+
+/// Sample request annotation with a label and notes
+fn sample_annotation() -> RequestAnnotation {
+    RequestAnnotation {
+        labels: vec![String::from("label")],
+        notes: Some(String::new()),
+    }
+}
+
+/// Describe the available api methods. Param and response schemas are
+/// generated from samples of the serde types used by each method, so that
+/// they are kept in sync with the types as these change
+fn api_methods() -> Vec<ApiMethod> {
+    let txid_params = GetRequestResponsesParams { txid: sample_hash() };
+    let page_params = GetRequestsParams {
+        page: Some(1),
+        cursor: None,
+        sort: None,
+        label: None,
+    };
+    let cursor_params = GetRequestsParams {
+        page: None,
+        cursor: Some(RequestCursor::at(&sample_request()).to_string()),
+        sort: None,
+        label: None,
+    };
+    let no_params = serde_json::json!({});
+    vec![
+        ApiMethod::new(
+            "getrequest",
+            "Get a request and its bids",
+            &txid_params,
+            &GetRequestResponse {
+                request: sample_request(),
+                bids: vec![sample_bid()],
+                annotation: Some(sample_annotation()),
+            },
+        ),
+        ApiMethod::new(
+            "exportrequest",
+            "Export a request and its bids as returned by the ocean getrequests and getrequestbids rpcs",
+            &txid_params,
+            &ExportRequestResponse {
+                request: OceanRequest::from_request(&sample_request()),
+                request_bids: OceanRequestBids::from_request(&sample_request(), &[sample_bid()]),
+            },
+        ),
+        ApiMethod::new(
+            "createauditbundle",
+            "Start generating the signed audit bundle of a request in the background, returning the bundle progress",
+            &txid_params,
+            &AuditBundleResponse {
+                status: sample_audit_bundle_status(AuditBundleState::Running),
+                bundle: None,
+            },
+        ),
+        ApiMethod::new(
+            "getauditbundle",
+            "Get the progress of the audit bundle of a request, along with the base64 encoded bundle once complete",
+            &txid_params,
+            &AuditBundleResponse {
+                status: sample_audit_bundle_status(AuditBundleState::Complete),
+                bundle: Some(base64::encode(&[0u8; 4])),
+            },
+        ),
+        ApiMethod::new(
+            "getrequests",
+            "Get a page of requests and their bids, by page number or by cursor, or by page number of the most \
+             recently updated requests with sort `updated`, or by page number of the requests with a `label` if not a \
+             tenant",
+            &cursor_params,
+            &GetRequestsResponse {
+                requests: vec![GetRequestResponse {
+                    request: sample_request(),
+                    bids: vec![sample_bid()],
+                    annotation: Some(sample_annotation()),
+                }],
+                pages: 1,
+                next_cursor: Some(RequestCursor::at(&sample_request()).to_string()),
+            },
+        ),
+        ApiMethod::new(
+            "getrequestsfull",
+            "Get a page of requests with their bids and responses",
+            &page_params,
+            &GetRequestsFullResponse {
+                requests: vec![RequestFull {
+                    request: sample_request(),
+                    bids: vec![sample_bid()],
+                    response: Some(sample_response()),
+                }],
+                pages: 1,
+            },
+        ),
+        ApiMethod::new(
+            "getrequestresponse",
+            "Get the challenge responses of a request",
+            &txid_params,
+            &GetRequestResponseResponse {
+                response: sample_response(),
+                summary: Some(sample_response().summary()),
+            },
+        ),
+        ApiMethod::new(
+            "verifyrequestresponse",
+            "Verify the response of a request against its integrity hash chain",
+            &txid_params,
+            &VerifyRequestResponseResponse {
+                valid: true,
+                hashes: vec![sample_hash()],
+            },
+        ),
+        ApiMethod::new(
+            "getrequestlogs",
+            "Get the most recent log lines of a request",
+            &GetRequestLogsParams { txid: sample_hash() },
+            &GetRequestLogsResponse {
+                logs: vec![String::new()],
+            },
+        ),
+        ApiMethod::new(
+            "getrequestpayments",
+            "Get the payment schedule and payment epochs of a request",
+            &txid_params,
+            &GetRequestPaymentsResponse {
+                schedule: vec![1],
+                epochs: vec![ResponseSnapshot {
+                    epoch: 0,
+                    service_height: 1,
+                    clientchain_height: 1,
+                    response: sample_response(),
+                    bids: vec![sample_bid()],
+                    is_payment_complete: false,
+                }],
+            },
+        ),
+        ApiMethod::new(
+            "getrequestlatency",
+            "Get the challenge round-trip latency percentiles of a request in ms",
+            &txid_params,
+            &GetRequestLatencyResponse {
+                verify: LatencyPercentiles::from_samples(&[1]),
+                proof: LatencyPercentiles::from_samples(&[1]),
+                bids: vec![BidLatency {
+                    txid: sample_hash(),
+                    proof: LatencyPercentiles::from_samples(&[1]),
+                }],
+            },
+        ),
+        ApiMethod::new(
+            "getrequestcoverage",
+            "Get the fraction of the scheduled challenges of a request issued on time, the gaps without a challenge \
+             and the client chain stalls that challenges were skipped for",
+            &txid_params,
+            &GetRequestCoverageResponse {
+                coverage: ChallengeCoverage {
+                    frequency: 1,
+                    height: 1,
+                    scheduled: 1,
+                    issued: 0,
+                    coverage: 0.0,
+                    gaps: vec![CoverageGap {
+                        start_height: 1,
+                        end_height: 1,
+                        missed: 1,
+                    }],
+                },
+                stalls: vec![ClientChainStall {
+                    clientchain_height: 1,
+                    start_height: 1,
+                    end_height: 1,
+                    start_time: 0,
+                    end_time: 0,
+                    missed_blocks: 0,
+                }],
+            },
+        ),
+        ApiMethod::new(
+            "getrequestresponseproof",
+            "Get the merkle root of the per bid response counts of a request and the inclusion proof of a bid",
+            &GetRequestResponseProofParams {
+                txid: sample_hash(),
+                bid: Some(sample_hash()),
+            },
+            &GetRequestResponseProofResponse {
+                root: Some(sample_hash()),
+                num_leaves: 1,
+                proof: Some(InclusionProof {
+                    bid: sample_hash(),
+                    count: 1,
+                    index: 0,
+                    path: vec![MerkleStep {
+                        hash: sample_hash(),
+                        left: true,
+                    }],
+                }),
+            },
+        ),
+        ApiMethod::new(
+            "getbidactivity",
+            "Get the challenges of a request that a bid responded to or missed, with the client chain height and time \
+             of each accepted proof",
+            &GetBidActivityParams {
+                txid: sample_hash(),
+                bid: sample_hash(),
+            },
+            &GetBidActivityResponse {
+                responses: 1,
+                missed: 0,
+                activity: vec![BidChallengeActivity {
+                    challenge: sample_hash(),
+                    height: 1,
+                    responded: true,
+                    clientchain_height: Some(1),
+                    timestamp: Some(0),
+                }],
+            },
+        ),
+        ApiMethod::new(
+            "getrequestheightstats",
+            "Get the challenge responses of a request per service chain height, optionally within a height range",
+            &GetRequestHeightStatsParams {
+                txid: sample_hash(),
+                start_height: Some(1),
+                end_height: Some(1),
+            },
+            &GetRequestHeightStatsResponse {
+                heights: vec![HeightStats {
+                    height: 1,
+                    num_challenges: 1,
+                    num_bids: 1,
+                    num_responses: 1,
+                    response_rate: 1.0,
+                }],
+            },
+        ),
+        ApiMethod::new(
+            "getrequestreconciliation",
+            "Get the reconciliation of a request response against proofs published on the client chain",
+            &txid_params,
+            &GetRequestReconciliationResponse {
+                reconciliation: ResponseReconciliation {
+                    clientchain_height: 1,
+                    bids: vec![BidReconciliation::new(sample_hash(), 1, 1)],
+                },
+            },
+        ),
+        ApiMethod::new(
+            "getfeepool",
+            "Get the client chain fees of a finished request per block and per asset and the guardnode share",
+            &txid_params,
+            &GetFeePoolResponse {
+                fee_pool: FeePool::new(
+                    1,
+                    2,
+                    vec![BlockFees {
+                        height: 1,
+                        fees: vec![AssetFees {
+                            asset: "CBT".to_owned(),
+                            amount: Amount::from_sat(1000),
+                        }],
+                    }],
+                    50,
+                    Some(FeeFilter {
+                        exclude_addresses: vec![],
+                        exclude_script_types: vec!["nulldata".to_owned()],
+                        include_addresses: vec![],
+                    }),
+                ),
+            },
+        ),
+        ApiMethod::new(
+            "rerunrequestresponse",
+            "Recompute the response of a request from its challenge records or the journal and report differences",
+            &txid_params,
+            &RerunRequestResponseResponse {
+                report: RerunReport {
+                    request: sample_hash(),
+                    source: RerunSource::Records,
+                    stored_challenges: 2,
+                    recomputed_challenges: 3,
+                    missing_challenges: vec![sample_hash()],
+                    unknown_challenges: vec![],
+                    bids: vec![BidRerun {
+                        txid: sample_hash(),
+                        stored_responses: 2,
+                        recomputed_responses: 3,
+                    }],
+                },
+            },
+        ),
+        ApiMethod::new(
+            "estimateearnings",
+            "Estimate the payout of a hypothetical bid on an active or upcoming request from recent fee accrual",
+            &EstimateEarningsParams {
+                txid: sample_hash(),
+                response_rate: Some(0.9),
+            },
+            &EstimateEarningsResponse {
+                estimate: EarningsEstimate {
+                    sample_requests: 10,
+                    fee_rate: Amount::from_sat(1000),
+                    blocks: 60,
+                    fees_amount: Amount::from_sat(60000),
+                    fee_percentage: 50,
+                    num_bids: 3,
+                    response_rate: 0.9,
+                    amount: Amount::from_sat(9000),
+                },
+            },
+        ),
+        ApiMethod::new(
+            "getunverifiedrequests",
+            "Get the fee deposits of requests refused as their fee was not locked",
+            &no_params,
+            &GetUnverifiedRequestsResponse {
+                deposits: vec![RequestDeposit {
+                    txid: sample_hash(),
+                    genesis_blockhash: sample_hash(),
+                    promised: Amount::from_sat(1),
+                    locked: Amount::from_sat(1),
+                }],
+            },
+        ),
+        ApiMethod::new(
+            "getrejectedrequests",
+            "Get the requests rejected as their parameters failed validation",
+            &no_params,
+            &GetRejectedRequestsResponse {
+                rejections: vec![RequestRejection {
+                    txid: sample_hash(),
+                    genesis_blockhash: sample_hash(),
+                    reason: String::new(),
+                }],
+            },
+        ),
+        ApiMethod::new(
+            "getdeadletters",
+            "Get the most recent challenge proof submissions that could not be parsed, not available to tenants",
+            &GetDeadLettersParams { limit: Some(1) },
+            &GetDeadLettersResponse {
+                letters: vec![DeadLetter {
+                    time: 0,
+                    reason: String::new(),
+                    remote_addr: Some(String::new()),
+                    route: Some(sample_hash()),
+                    size: 0,
+                    payload: String::new(),
+                    truncated: false,
+                }],
+            },
+        ),
+        ApiMethod::new(
+            "setrequestannotation",
+            "Set the labels and notes of a request, not available to tenants",
+            &SetRequestAnnotationParams {
+                txid: sample_hash(),
+                labels: sample_annotation().labels,
+                notes: sample_annotation().notes,
+            },
+            &sample_annotation(),
+        ),
+        ApiMethod::new(
+            "getrequestannotation",
+            "Get the labels and notes of a request, not available to tenants",
+            &txid_params,
+            &sample_annotation(),
+        ),
+        ApiMethod::new(
+            "getbidrefunds",
+            "Get the refunds of expired bid locks, with unsigned refund transactions if exported",
+            &no_params,
+            &GetBidRefundsResponse {
+                refunds: vec![BidRefund {
+                    txid: sample_hash(),
+                    request: sample_hash(),
+                    genesis_blockhash: sample_hash(),
+                    lock_height: 1,
+                    amount: Amount::from_sat(1),
+                    address: String::new(),
+                    state: BidRefundState::Exported,
+                    refund_txid: None,
+                    tx_hex: String::new(),
+                }],
+            },
+        ),
+        ApiMethod::new(
+            "getblacklist",
+            "Get the bids blacklisted after repeated invalid proofs, or all stored blacklistings if history is set",
+            &GetBlacklistParams { history: false },
+            &GetBlacklistResponse {
+                blacklistings: vec![BidBlacklisting {
+                    txid: sample_hash(),
+                    request: sample_hash(),
+                    genesis_blockhash: sample_hash(),
+                    strikes: 1,
+                    since: 1,
+                    until: 1,
+                }],
+            },
+        ),
+        ApiMethod::new(
+            "getchallengeschedule",
+            "Get the projected remaining challenges of a request",
+            &GetChallengeScheduleParams { txid: sample_hash() },
+            &GetChallengeScheduleResponse {
+                service_height: 1,
+                schedule: vec![ScheduledChallenge {
+                    height: 1,
+                    timestamp: 1,
+                }],
+            },
+        ),
+        ApiMethod::new(
+            "getchallengetx",
+            "Get the raw and decoded challenge transaction of a challenge hash",
+            &GetChallengeTxParams { hash: sample_hash() },
+            &GetChallengeTxResponse {
+                challenge: ChallengeTx {
+                    hex: String::new(),
+                    decoded: serde_json::json!({}),
+                },
+            },
+        ),
+        ApiMethod::new(
+            "getwalletstatus",
+            "Get the latest wallet balance status, not available to tenants",
+            &no_params,
+            &GetWalletStatusResponse {
+                status: Some(BalanceStatus {
+                    challenge_balance: Amount::from_sat(1),
+                    challenge_projected: Amount::from_sat(1),
+                    payment_balance: Amount::from_sat(1),
+                    payment_projected: Amount::from_sat(1),
+                    alerts: vec![BalanceAlert::LowChallengeAsset],
+                }),
+            },
+        ),
+        ApiMethod::new(
+            "getchainstatus",
+            "Get the degraded status of the coordinator while the service or client chain is unreachable",
+            &no_params,
+            &GetChainStatusResponse {
+                degraded: Some(DegradedStatus {
+                    since: 1,
+                    error: String::new(),
+                    service_reachable: false,
+                    clientchain_reachable: true,
+                    checks: 1,
+                }),
+            },
+        ),
+        ApiMethod::new(
+            "getapistats",
+            "Get the api worker pool stats, including the number of calls pending a worker thread, and rate limit \
+             stats",
+            &no_params,
+            &GetApiStatsResponse {
+                stats: ApiStats::default(),
+                rate_limits: RateLimitStats::default(),
+            },
+        ),
+        ApiMethod::new(
+            "getrpcstats",
+            "Get the call counts, failures, slow calls and durations of each node rpc method called",
+            &no_params,
+            &GetRpcStatsResponse {
+                slow_call_ms: 1,
+                methods: vec![(String::from("getblockcount"), RpcMethodStats::default())]
+                    .into_iter()
+                    .collect(),
+            },
+        ),
+        ApiMethod::new(
+            "getinfo",
+            "Get the coordinator version, enabled features and supported formats",
+            &no_params,
+            &GetInfoResponse {
+                info: CoordinatorInfo {
+                    version: String::new(),
+                    features: CoordinatorFeatures {
+                        payments: true,
+                        payment_epochs: true,
+                        payout_address_type: String::new(),
+                        chain_challenges: true,
+                        reconciliation: true,
+                        journal: true,
+                        blacklist: true,
+                    },
+                    proof_versions: vec![1],
+                    proof_sigtypes: vec![String::new()],
+                    proof_payload: String::new(),
+                    payment_formula_version: 1,
+                    hash_order: String::new(),
+                    chain: String::new(),
+                    genesis_hashes: vec![String::new()],
+                },
+            },
+        ),
+        ApiMethod::new(
+            "pausechallenges",
+            "Pause issuing challenges until resumed, not available to tenants",
+            &no_params,
+            &ChallengesPausedResponse { paused: true },
+        ),
+        ApiMethod::new(
+            "resumechallenges",
+            "Resume issuing challenges after a pause, not available to tenants",
+            &no_params,
+            &ChallengesPausedResponse { paused: false },
+        ),
+        ApiMethod::new(
+            "refreshbids",
+            "Re-fetch the winning bids of the active request from the service chain and apply any changes, not \
+             available to tenants",
+            &no_params,
+            &RefreshBidsResponse {
+                refresh: BidRefresh {
+                    request: sample_hash(),
+                    added: vec![sample_hash()],
+                    removed: vec![],
+                    num_bids: 1,
+                },
+            },
+        ),
+        ApiMethod::new(
+            "submitchallengeproof",
+            "Submit a v1 or v2 challenge proof for the active challenge",
+            &serde_json::json!({"txid": "", "pubkey": "", "hash": "", "sig": ""}),
+            &true,
+        ),
+        ApiMethod::new(
+            "rotatebidkey",
+            "Rotate the key of a bid of the active request, signed as required by the key rotation policy",
+            &serde_json::json!({"request": "", "txid": "", "pubkey": "", "sig": "", "new_sig": ""}),
+            &RotateBidKeyResponse {
+                rotation: sample_bid_key_rotation(),
+            },
+        ),
+        ApiMethod::new(
+            "getbidkeyrotations",
+            "Get the bid key rotations of a request along with their signatures",
+            &txid_params,
+            &GetBidKeyRotationsResponse {
+                rotations: vec![sample_bid_key_rotation()],
+            },
+        ),
+        ApiMethod::new(
+            "listmethods",
+            "List the available api methods with their param and response schemas",
+            &no_params,
+            &ListMethodsResponse {
+                methods: vec![ApiMethod {
+                    name: "",
+                    description: "",
+                    params: serde_json::json!({}),
+                    response: serde_json::json!({}),
+                }],
+            },
+        ),
+    ]
+}
+
+/// List methods RPC call returning the available api methods with the json
+/// schemas of their params and responses, for generating client libraries
+fn list_methods() -> futures::Finished<Value, Error> {
+    let res_serialized = serde_json::to_string(&ListMethodsResponse { methods: api_methods() }).unwrap();
+    futures::finished(Value::String(res_serialized))
+}
+
+/// Do basic authorization on incoming request by parsing the AUTHORIZATION
+/// header decoding username/password and comparing with config
+fn authorize(our_auth: &str, request: &Request<Body>) -> bool {
+    basic_credentials(authorization_header(request).as_ref().map(|h| h.as_str())).map_or(false, |up| our_auth == up)
+}
+
+/// Get the AUTHORIZATION header value of an incoming request, if any
+fn authorization_header(request: &Request<Body>) -> Option<String> {
+    request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .map(|h| h.to_str().unwrap_or("").to_owned())
+}
+
+/// Api authorization for admin and tenant credentials. Admin callers are
+/// authenticated by the auth provider set in the config and have access to all
+/// requests, while tenant basic credentials are limited to the requests of the
+/// tenant client chain
+struct ApiAuth {
+    admin: Box<dyn AuthProvider>,
+    tenants: Vec<(String, sha256d::Hash)>,
+}
+
+impl ApiAuth {
+    /// Return new ApiAuth from the api config and tenant configs
+    fn new(config: &ApiConfig, tenants: &[TenantConfig]) -> Result<ApiAuth> {
+        Ok(ApiAuth {
+            admin: auth_provider(config)?,
+            tenants: tenants
+                .iter()
+                .filter(|tenant| tenant.api_user.len() > 0 && tenant.api_pass.len() > 0)
+                .filter_map(|tenant| {
+                    sha256d::Hash::from_hex(&tenant.genesis_hash)
+                        .ok()
+                        .map(|genesis_hash| (format! {"{}:{}", tenant.api_user, tenant.api_pass}, genesis_hash))
+                })
+                .collect(),
+        })
+    }
+
+    /// Return the scope of an incoming request or None if the request is
+    /// unauthorized
+    fn scope(&self, request: &Request<Body>) -> Option<ApiMeta> {
+        for (tenant_auth, genesis_hash) in self.tenants.iter() {
+            if authorize(tenant_auth, request) {
+                return Some(ApiMeta {
+                    tenant: Some(*genesis_hash),
+                });
+            }
+        }
+        if self
+            .admin
+            .authenticate(authorization_header(request).as_ref().map(|h| h.as_str()))
+        {
+            return Some(ApiMeta::admin());
+        }
+        None
+    }
+}
+
+/// Rate limits of api calls per api key, with overrides for tenant api keys,
+/// and per client ip
+struct ApiRateLimits {
+    /// Reverse proxies trusted to forward the client ip of api calls
+    trusted_proxies: Vec<IpRange>,
+    /// Max number of calls per window of each api key; unlimited if 0
+    key_limit: u64,
+    /// Max number of calls per window of the api keys of tenants
+    tenant_limits: HashMap<sha256d::Hash, u64>,
+    /// Max number of calls per window of each client ip; unlimited if 0
+    ip_limit: u64,
+    /// Calls by api key
+    keys: RateLimiter,
+    /// Calls by client ip
+    ips: RateLimiter,
+}
+
+/// Api rate limit stats
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+pub struct RateLimitStats {
+    /// Number of calls rejected by the api key limit
+    pub key_limited: u64,
+    /// Number of calls rejected by the client ip limit
+    pub ip_limited: u64,
+    /// Number of api keys tracked
+    pub keys: u64,
+    /// Number of client ips tracked
+    pub ips: u64,
+}
+
+impl ApiRateLimits {
+    /// Return new ApiRateLimits from the api config and tenant configs
+    fn new(config: &ApiConfig, tenants: &[TenantConfig]) -> ApiRateLimits {
+        let window = Duration::from_secs(config.rate_limit_window);
+        ApiRateLimits {
+            key_limit: config.rate_limit_key,
+            tenant_limits: tenants
+                .iter()
+                .filter_map(
+                    |tenant| match (sha256d::Hash::from_hex(&tenant.genesis_hash), tenant.api_rate_limit) {
+                        (Ok(genesis_hash), Some(limit)) => Some((genesis_hash, limit)),
+                        _ => None,
+                    },
+                )
+                .collect(),
+            ip_limit: config.rate_limit_ip,
+            trusted_proxies: config
+                .trusted_proxies
+                .iter()
+                .filter_map(|proxy| IpRange::from_str(proxy).ok())
+                .collect(),
+            keys: RateLimiter::new(window),
+            ips: RateLimiter::new(window),
+        }
+    }
+
+    /// Count an authorized call against the limits of its api key and client
+    /// ip, returning the quota closest to its limit, or None if the call is
+    /// not limited. Calls without a client ip are limited by api key only.
+    /// Returns the quota of the limit reached if the call is rejected
+    fn check(&self, request: &Request<Body>, meta: &ApiMeta) -> std::result::Result<Option<Quota>, Quota> {
+        let key_limit = meta
+            .tenant
+            .and_then(|tenant| self.tenant_limits.get(&tenant).cloned())
+            .unwrap_or(self.key_limit);
+        let mut quota: Option<Quota> = None;
+        if key_limit > 0 {
+            let key = authorization_header(request).unwrap_or_default();
+            quota = Some(self.keys.check(&key, key_limit)?);
+        }
+        if self.ip_limit > 0 {
+            if let Some(ip) = client_ip(request, &self.trusted_proxies) {
+                let ip_quota = self.ips.check(&ip, self.ip_limit)?;
+                if quota
+                    .as_ref()
+                    .map_or(true, |quota| ip_quota.remaining < quota.remaining)
+                {
+                    quota = Some(ip_quota);
+                }
+            }
+        }
+        Ok(quota)
+    }
+
+    /// Get the rate limit stats
+    fn stats(&self) -> RateLimitStats {
+        RateLimitStats {
+            key_limited: self.keys.limited(),
+            ip_limited: self.ips.limited(),
+            keys: self.keys.callers(),
+            ips: self.ips.callers(),
+        }
+    }
+}
+
+/// Get the client ip of a request forwarded by a trusted reverse proxy, i.e.
+/// the right-most hop of the X-Forwarded-For header that is not a trusted
+/// proxy, or else the X-Real-IP header. Hops left of the first untrusted hop
+/// are set by the caller and not used. Forwarded headers are ignored if no
+/// proxies are trusted, as the api server does not expose the connection peer
+/// address to check that calls come from a proxy
+fn client_ip(request: &Request<Body>, trusted_proxies: &[IpRange]) -> Option<String> {
+    if trusted_proxies.is_empty() {
+        return None;
+    }
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_owned())
+    };
+    let trusted = |hop: &str| {
+        IpAddr::from_str(hop)
+            .map(|addr| trusted_proxies.iter().any(|proxy| proxy.contains(&addr)))
+            .unwrap_or(false)
+    };
+    let ip = match header("x-forwarded-for") {
+        Some(forwarded) => forwarded
+            .rsplit(',')
+            .map(|hop| hop.trim())
+            .find(|hop| !trusted(hop))
+            .map(|hop| hop.to_owned()),
+        None => header("x-real-ip").map(|ip| ip.trim().to_owned()),
+    };
+    ip.filter(|ip| !ip.is_empty())
+}
+
+/// Add the rate limit quota headers to an api response
+fn add_quota_headers(response: &mut hyper::Response<Body>, quota: &Quota) {
+    let headers = response.headers_mut();
+    for (name, value) in vec![
+        ("x-ratelimit-limit", quota.limit),
+        ("x-ratelimit-remaining", quota.remaining),
+        ("x-ratelimit-reset", quota.reset),
+    ] {
+        let _ = headers.insert(header::HeaderName::from_static(name), header::HeaderValue::from(value));
+    }
+}
+
+/// Response to an api call rejected by a rate limit, with the quota headers
+/// and the seconds until the limit resets
+fn rate_limited_response(quota: &Quota, allow_origin: Option<String>) -> hyper::Response<Body> {
+    let mut builder = hyper::Response::builder();
+    let _ = builder
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .header(header::RETRY_AFTER, quota.reset.to_string().as_str());
+    if let Some(origin) = allow_origin {
+        let _ = builder.header(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.as_str());
+    }
+    let mut response = builder.body(Body::from("Too Many Requests")).unwrap();
+    add_quota_headers(&mut response, quota);
+    response
+}
+
+/// Error code of api calls rejected as the api queue is full
+const API_ERROR_QUEUE_FULL: i64 = -32000;
+/// Error code of api calls timed out waiting for a worker thread
+const API_ERROR_TIMEOUT: i64 = -32001;
+/// Error code of api calls with responses above the max response size
+const API_ERROR_RESPONSE_TOO_LARGE: i64 = -32002;
+/// Error code of batch requests with more calls than the max batch size
+const API_ERROR_BATCH_TOO_LARGE: i64 = -32003;
+
+/// Api call queued for a worker thread
+struct ApiJob {
+    /// Api method name
+    method: &'static str,
+    /// Api call
+    call: Box<dyn FnOnce() -> futures::Finished<Value, Error> + Send>,
+    /// Time the call was queued
+    queued: Instant,
+    /// Channel to return the call result to the api server
+    result: oneshot::Sender<std::result::Result<Value, Error>>,
+}
+
+/// Api worker pool stats
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+pub struct ApiStats {
+    /// Number of worker threads
+    pub threads: u64,
+    /// Max number of calls pending a worker thread
+    pub queue_size: u64,
+    /// Number of calls pending a worker thread
+    pub queue_depth: u64,
+    /// Max number of calls pending a worker thread at any time
+    pub max_queue_depth: u64,
+    /// Number of calls completed
+    pub completed: u64,
+    /// Number of calls rejected as the queue was full
+    pub rejected: u64,
+    /// Number of calls timed out waiting for a worker thread
+    pub timed_out: u64,
+}
+
+/// Pool of threads running api calls away from the api server threads. Calls
+/// are passed through a bounded queue so that under heavy load excess calls
+/// are rejected, and calls that waited for a worker thread longer than the
+/// request timeout are failed instead of being run
+struct ApiPool {
+    /// Bounded queue of api calls shared by all pool threads
+    queue: Mutex<SyncSender<ApiJob>>,
+    /// Pool stats
+    stats: Arc<Mutex<ApiStats>>,
+}
+
+impl ApiPool {
+    /// Spawn an api pool with the given number of threads, queue size and
+    /// optional request timeout. Pool threads exit once the pool is dropped
+    fn new(num_threads: usize, queue_size: usize, timeout: Option<Duration>) -> ApiPool {
+        let (queue_tx, queue_rx) = sync_channel::<ApiJob>(queue_size);
+        let queue_rx = Arc::new(Mutex::new(queue_rx));
+        let stats = Arc::new(Mutex::new(ApiStats {
+            threads: num_threads as u64,
+            queue_size: queue_size as u64,
+            ..ApiStats::default()
+        }));
+        for i in 0..num_threads {
+            let queue_rx = queue_rx.clone();
+            let stats = stats.clone();
+            let _ = thread::Builder::new()
+                .name(format!("api-worker-{}", i))
+                .spawn(move || loop {
+                    // lock only for the duration of the receive
+                    let job = queue_rx.lock().unwrap().recv();
+                    match job {
+                        Ok(job) => {
+                            let waited = job.queued.elapsed();
+                            stats.lock().unwrap().queue_depth -= 1;
+                            if timeout.map_or(false, |timeout| waited > timeout) {
+                                warn!("api call {} timed out after {}ms", job.method, waited.as_millis());
+                                stats.lock().unwrap().timed_out += 1;
+                                let _ = job.result.send(Err(Error {
+                                    code: ErrorCode::ServerError(API_ERROR_TIMEOUT),
+                                    message: "Request timed out.".to_string(),
+                                    data: None,
+                                }));
+                                continue;
+                            }
+                            let result = (job.call)().wait();
+                            stats.lock().unwrap().completed += 1;
+                            let _ = job.result.send(result);
+                        }
+                        Err(_) => break,
+                    }
+                });
+        }
+        ApiPool {
+            queue: Mutex::new(queue_tx),
+            stats,
+        }
+    }
+
+    /// Queue an api call and return a future resolving to the call result once
+    /// the call has been run. If the queue is full the call is rejected
+    /// immediately
+    fn call<F>(&self, method: &'static str, call: F) -> Box<dyn Future<Item = Value, Error = Error> + Send>
+    where
+        F: FnOnce() -> futures::Finished<Value, Error> + Send + 'static,
+    {
+        let (result_tx, result_rx) = oneshot::channel();
+        self.stats.lock().unwrap().queue_depth += 1;
+        let queued = self.queue.lock().unwrap().try_send(ApiJob {
+            method,
+            call: Box::new(call),
+            queued: Instant::now(),
+            result: result_tx,
+        });
+        {
+            let mut stats = self.stats.lock().unwrap();
+            if let Err(e) = queued {
+                stats.queue_depth -= 1;
+                let message = match e {
+                    TrySendError::Full(_) => {
+                        stats.rejected += 1;
+                        "Api queue full."
+                    }
+                    TrySendError::Disconnected(_) => "Api workers down.",
+                };
+                warn!("api call {} rejected: {}", method, message);
+                return Box::new(futures::failed(Error {
+                    code: ErrorCode::ServerError(API_ERROR_QUEUE_FULL),
+                    message: message.to_string(),
+                    data: None,
+                }));
+            }
+            stats.max_queue_depth = stats.max_queue_depth.max(stats.queue_depth);
+        }
+        Box::new(result_rx.then(|result| match result {
+            Ok(result) => result,
+            Err(_) => Err(Error::internal_error()),
+        }))
+    }
+
+    /// Get the pool stats
+    fn stats(&self) -> ApiStats {
+        self.stats.lock().unwrap().clone()
+    }
+}
+
+/// Replace an api response larger than max_size bytes with response too large
+/// errors for each call, so that a single call cannot produce an unbounded
+/// response. Responses are not limited if max_size is 0
+fn limit_response(response: rpc::Response, max_size: usize) -> rpc::Response {
+    if max_size == 0 {
+        return response;
+    }
+    let size = serde_json::to_string(&response).map(|s| s.len()).unwrap_or(0);
+    if size <= max_size {
+        return response;
+    }
+    let too_large = |output: rpc::Output| {
+        let err = Error {
+            code: ErrorCode::ServerError(API_ERROR_RESPONSE_TOO_LARGE),
+            message: format!(
+                "Response of {} bytes exceeds the max response size of {} bytes",
+                size, max_size
+            ),
+            data: None,
+        };
+        rpc::Output::from(Err(err), output.id().clone(), output.version())
+    };
+    match response {
+        rpc::Response::Single(output) => rpc::Response::Single(too_large(output)),
+        rpc::Response::Batch(outputs) => rpc::Response::Batch(outputs.into_iter().map(too_large).collect()),
+    }
+}
+
+/// Get the error response to a batch request of more than max_size calls, or
+/// None if the request is not limited. Batches are not limited if max_size is
+/// 0
+fn limit_batch(request: &rpc::Request, max_size: usize) -> Option<rpc::Response> {
+    match request {
+        rpc::Request::Batch(calls) if max_size > 0 && calls.len() > max_size => {
+            let err = Error {
+                code: ErrorCode::ServerError(API_ERROR_BATCH_TOO_LARGE),
+                message: format!(
+                    "Batch of {} calls exceeds the max batch size of {} calls",
+                    calls.len(),
+                    max_size
+                ),
+                data: None,
+            };
+            Some(rpc::Response::Single(rpc::Output::from(
+                Err(err),
+                rpc::Id::Null,
+                Some(rpc::Version::V2),
+            )))
+        }
+        _ => None,
+    }
+}
+
+/// Api handler middleware limiting the number of calls of batch requests and
+/// the size of api responses
+struct ApiLimits {
+    /// Max number of calls of a batch request; not limited if 0
+    max_batch_size: usize,
+    /// Max size of a response in bytes; not limited if 0
+    max_response_size: usize,
+}
+
+impl Middleware<ApiMeta> for ApiLimits {
+    type Future = FutureResponse;
+    type CallFuture = NoopCallFuture;
+
+    fn on_request<F, X>(&self, request: rpc::Request, meta: ApiMeta, next: F) -> Either<Self::Future, X>
+    where
+        F: FnOnce(rpc::Request, ApiMeta) -> X + Send,
+        X: Future<Item = Option<rpc::Response>, Error = ()> + Send + 'static,
+    {
+        if let Some(response) = limit_batch(&request, self.max_batch_size) {
+            return Either::A(Box::new(futures::finished(Some(response))));
+        }
+        let max_size = self.max_response_size;
+        Either::A(Box::new(next(request, meta).map(move |response| {
+            response.map(|response| limit_response(response, max_size))
+        })))
+    }
+}
+
+/// Api server handler middleware forwarding all api calls to the api handler,
+/// which is shared with the compressed response path of the server
+struct SharedIo(Arc<MetaIoHandler<ApiMeta, ApiLimits>>);
+
+impl Middleware<ApiMeta> for SharedIo {
+    type Future = FutureResponse;
+    type CallFuture = NoopCallFuture;
+
+    fn on_request<F, X>(&self, request: rpc::Request, meta: ApiMeta, _next: F) -> Either<Self::Future, X>
+    where
+        F: FnOnce(rpc::Request, ApiMeta) -> X + Send,
+        X: Future<Item = Option<rpc::Response>, Error = ()> + Send + 'static,
+    {
+        Either::A(Box::new(self.0.handle_rpc_request(request, meta)))
+    }
+}
+
+/// Api method handler running all api calls via the api pool
+struct ApiIoHandler {
+    /// Handler of the api server
+    io: MetaIoHandler<ApiMeta, ApiLimits>,
+    /// Pool running the api calls
+    pool: Arc<ApiPool>,
+}
+
+impl ApiIoHandler {
+    /// Create an api method handler running calls via the pool, with batch
+    /// requests limited to max_batch_size calls and responses limited to
+    /// max_response_size bytes
+    fn new(pool: Arc<ApiPool>, max_batch_size: usize, max_response_size: usize) -> ApiIoHandler {
+        ApiIoHandler {
+            io: MetaIoHandler::with_middleware(ApiLimits {
+                max_batch_size,
+                max_response_size,
+            }),
+            pool,
+        }
+    }
+
+    /// Add an api method with access to the call metadata
+    fn add_method_with_meta<F>(&mut self, name: &'static str, method: F)
+    where
+        F: Fn(Params, ApiMeta) -> futures::Finished<Value, Error> + Send + Sync + 'static,
+    {
+        let pool = self.pool.clone();
+        let method = Arc::new(method);
+        self.io
+            .add_method_with_meta(name, move |params: Params, meta: ApiMeta| {
+                let method = method.clone();
+                pool.call(name, move || method(params, meta))
+            });
+    }
+
+    /// Add an api method
+    fn add_method<F>(&mut self, name: &'static str, method: F)
+    where
+        F: Fn(Params) -> futures::Finished<Value, Error> + Send + Sync + 'static,
+    {
+        self.add_method_with_meta(name, move |params: Params, _meta: ApiMeta| method(params));
+    }
+}
+
+/// Get the origins allowed by the api cors policy. Only the null origin is
+/// allowed if no origins are set and any origin if all are allowed
+fn cors_origins(config: &ApiConfig) -> DomainsValidation<AccessControlAllowOrigin> {
+    if config.cors_allow_all {
+        return DomainsValidation::AllowOnly(vec![AccessControlAllowOrigin::Any]);
+    }
+    let mut origins: Vec<AccessControlAllowOrigin> = config
+        .cors_origins
+        .iter()
+        .map(|origin| AccessControlAllowOrigin::from(origin.as_str()))
+        .collect();
+    if origins.len() == 0 {
+        origins.push(AccessControlAllowOrigin::Null);
+    }
+    DomainsValidation::AllowOnly(origins)
+}
+
+/// Get the allowed origin of api responses to a request from the origin,
+/// following the api cors policy
+fn cors_allow_origin(allow_all: bool, origins: &[String], origin: Option<&str>) -> Option<String> {
+    if allow_all {
+        return Some("*".to_owned());
+    }
+    let origin = origin?;
+    if origins.iter().any(|allowed| allowed == origin) || (origins.is_empty() && origin == "null") {
+        Some(origin.to_owned())
+    } else {
+        None
+    }
+}
+
+/// Run an api call of a request directly via the api handler, so that the
+/// response can carry headers the server does not set. The response content is
+/// compressed with the negotiated encoding, if any, unless the content is
+/// smaller than the min size in bytes, and the response carries the rate limit
+/// quota of the call, if any. The response also carries the allowed origin of
+/// the request, as it bypasses the cors handling of the server
+fn direct_response(
+    io: Arc<MetaIoHandler<ApiMeta, ApiLimits>>,
+    request: Request<Body>,
+    meta: ApiMeta,
+    compression: Option<(Encoding, usize)>,
+    quota: Option<Quota>,
+    allow_origin: Option<String>,
+) -> impl Future<Item = hyper::Response<Body>, Error = hyper::Error> + Send {
+    request.into_body().concat2().and_then(move |body| {
+        io.handle_request(&String::from_utf8_lossy(&body), meta)
+            .then(move |res| {
+                let content = res.ok().and_then(|content| content).unwrap_or_default();
+                let mut builder = hyper::Response::builder();
+                let _ = builder
+                    .status(StatusCode::OK)
+                    .header(header::CONTENT_TYPE, "application/json; charset=utf-8");
+                if compression.is_some() {
+                    let _ = builder.header(header::VARY, "accept-encoding");
+                }
+                if let Some(origin) = allow_origin {
+                    let _ = builder.header(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.as_str());
+                }
+                let body = match compression {
+                    Some((encoding, min_size)) if content.len() >= min_size => {
+                        match compress(encoding, content.as_bytes()) {
+                            Ok(compressed) => {
+                                let _ = builder.header(header::CONTENT_ENCODING, encoding.name());
+                                compressed
+                            }
+                            Err(e) => {
+                                warn!("{}", e);
+                                content.into_bytes()
+                            }
+                        }
+                    }
+                    _ => content.into_bytes(),
+                };
+                let mut response = builder.body(Body::from(body)).unwrap();
+                if let Some(quota) = &quota {
+                    add_quota_headers(&mut response, quota);
+                }
+                Ok(response)
+            })
+    })
+}
+
+/// Dependencies of the api server, shared with the rest of the coordinator or
+/// drawn from its config
+pub struct ApiContext<D, K, T> {
+    /// Storage interface that api reads are served from
+    pub storage: Arc<D>,
+    /// Client chain interface that challenge transactions are drawn from
+    pub clientchain: Arc<K>,
+    /// Service chain interface that challenge schedules are projected from
+    pub service: Arc<T>,
+    /// Challenge frequency in service chain blocks
+    pub challenge_frequency: u64,
+    /// Service chain block time in seconds
+    pub block_time: u64,
+    /// Payment epoch in service chain blocks, if payments are batched
+    pub payment_epoch: Option<u64>,
+    /// Fee percentage of the client chain, if payments are made
+    pub fee_percentage: Option<u32>,
+    /// Shared challenge state that submitted proofs are checked against
+    pub challenge: Arc<RwLock<Option<ChallengeState>>>,
+    /// Channel forwarding submitted proofs to the challenger
+    pub challenge_resp: Sender<ChallengeResponse>,
+    /// Wallet status of the balance monitor
+    pub wallet_status: Arc<RwLock<Option<BalanceStatus>>>,
+    /// Flag pausing challenges
+    pub challenges_paused: Arc<AtomicBool>,
+    /// Degraded status set while the chains are unreachable
+    pub degraded_status: Arc<RwLock<Option<DegradedStatus>>>,
+    /// Journal recording submitted proofs
+    pub journal: Arc<Journal>,
+    /// Path of the journal file that responses are rerun from
+    pub journal_path: Option<String>,
+    /// Blacklist of bids sending invalid proofs
+    pub blacklist: Arc<Blacklist>,
+    /// Policy of bid key rotations
+    pub key_rotation: KeyRotationPolicy,
+    /// Signer of audit bundles, if configured
+    pub audit_signer: Option<AuditSigner>,
+    /// Coordinator info returned by getinfo
+    pub info: CoordinatorInfo,
+}
+
+/// Run Api RPC server for external requests that require information from the
+/// coordinator, drawn from the storage, chains and shared state of the api
+/// context. Callers are authenticated and scoped to their tenant, and calls are
+/// rate limited and run by a pool of worker threads as set in the api config
+pub fn run_api_server<
+    D: Storage + Send + Sync + 'static,
+    K: ClientChain + Send + Sync + 'static,
+    T: Service + Send + Sync + 'static,
+>(
+    config: &ApiConfig,
+    tenants: &[TenantConfig],
+    context: ApiContext<D, K, T>,
+) -> Result<CloseHandle> {
+    let ApiContext {
+        storage,
+        clientchain,
+        service,
+        challenge_frequency,
+        block_time,
+        payment_epoch,
+        fee_percentage,
+        challenge,
+        challenge_resp,
+        wallet_status,
+        challenges_paused,
+        degraded_status,
+        journal,
+        journal_path,
+        blacklist,
+        key_rotation,
+        audit_signer,
+        info,
+    } = context;
+    let auth = Arc::new(ApiAuth::new(config, tenants)?);
+    let audit_bundles = Arc::new(AuditBundles::new(audit_signer));
+    let rate_limits = Arc::new(ApiRateLimits::new(config, tenants));
+    let hash_order = HashOrder::from_str(&config.hash_order)?;
+    let pool = Arc::new(ApiPool::new(
+        config.threads as usize,
+        config.queue as usize,
+        if config.request_timeout > 0 {
+            Some(Duration::from_secs(config.request_timeout))
+        } else {
+            None
+        },
+    ));
+    let mut io = ApiIoHandler::new(
+        pool.clone(),
+        config.max_batch_size as usize,
+        config.max_response_size as usize,
+    );
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getrequestresponse", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_request_response(params, meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("verifyrequestresponse", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            verify_request_response(params, meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getrequest", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_request(params, meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("exportrequest", move |params: Params, meta: ApiMeta| {
+        export_request(params, meta.tenant, storage_ref.clone())
+    });
+    let storage_ref = storage.clone();
+    let bundles_ref = audit_bundles.clone();
+    io.add_method_with_meta("createauditbundle", move |params: Params, meta: ApiMeta| {
+        create_audit_bundle(params, meta.tenant, storage_ref.clone(), bundles_ref.clone())
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getauditbundle", move |params: Params, meta: ApiMeta| {
+        get_audit_bundle(params, meta.tenant, storage_ref.clone(), &audit_bundles)
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getrequestlogs", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_request_logs(params, meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getrequests", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_requests(params, meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getrequestsfull", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_requests_full(params, meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getrequestpayments", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_request_payments(params, meta.tenant, storage_ref.clone(), payment_epoch)
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getrequestlatency", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_request_latency(params, meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getrequestcoverage", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_request_coverage(params, meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getrequestresponseproof", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_request_response_proof(params, meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getbidactivity", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_bid_activity(params, meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getrequestheightstats", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_request_height_stats(params, meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getrequestreconciliation", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_request_reconciliation(params, meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getfeepool", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_fee_pool(params, meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("rerunrequestresponse", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            rerun_request_response(params, meta.tenant, storage_ref.clone(), &journal_path)
+        })
+    });
+    let storage_ref = storage.clone();
+    let service_ref = service.clone();
+    io.add_method_with_meta("estimateearnings", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            estimate_earnings_call(
+                params,
+                meta.tenant,
+                storage_ref.clone(),
+                service_ref.clone(),
+                fee_percentage,
+            )
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getbidkeyrotations", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_bid_key_rotations(params, meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    let challenge_ref = challenge.clone();
+    let journal_ref = journal.clone();
+    io.add_method_with_meta("rotatebidkey", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            rotate_bid_key_call(
+                params,
+                meta.tenant,
+                key_rotation,
+                &challenge_ref,
+                &journal_ref,
+                storage_ref.clone(),
+            )
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getunverifiedrequests", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |_params| {
+            get_unverified_requests(meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getrejectedrequests", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |_params| {
+            get_rejected_requests(meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getdeadletters", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_dead_letters(params, meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("setrequestannotation", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            set_request_annotation(params, meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getrequestannotation", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_request_annotation(params, meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    io.add_method_with_meta("getbidrefunds", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |_params| {
+            get_bid_refunds(meta.tenant, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    let blacklist_ref = blacklist.clone();
+    io.add_method_with_meta("getblacklist", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_blacklist(params, meta.tenant, &blacklist_ref, storage_ref.clone())
+        })
+    });
+    let storage_ref = storage.clone();
+    let service_ref = service.clone();
+    let challenge_ref = challenge.clone();
+    io.add_method_with_meta("refreshbids", move |_params: Params, meta: ApiMeta| {
+        refresh_bids_call(meta.tenant, &challenge_ref, service_ref.clone(), storage_ref.clone())
+    });
+    io.add_method_with_meta("getchallengeschedule", move |params: Params, meta: ApiMeta| {
+        with_hash_order(params, hash_order, |params| {
+            get_challenge_schedule(
+                params,
+                meta.tenant,
+                storage.clone(),
+                service.clone(),
+                challenge_frequency,
+                block_time,
+            )
+        })
+    });
+    io.add_method("getchallengetx", move |params: Params| {
+        with_hash_order(params, hash_order, |params| {
+            get_challenge_tx(params, clientchain.clone())
+        })
+    });
+    io.add_method_with_meta("getwalletstatus", move |_params: Params, meta: ApiMeta| {
+        get_wallet_status(meta.tenant, &wallet_status)
+    });
+    io.add_method("getchainstatus", move |_params: Params| {
+        get_chain_status(&degraded_status)
+    });
+    io.add_method_with_meta("getinfo", move |_params: Params, meta: ApiMeta| {
+        get_info(meta.tenant, &info)
+    });
+    let paused_ref = challenges_paused.clone();
+    io.add_method_with_meta("pausechallenges", move |_params: Params, meta: ApiMeta| {
+        set_challenges_paused(meta.tenant, &paused_ref, true)
+    });
+    io.add_method_with_meta("resumechallenges", move |_params: Params, meta: ApiMeta| {
+        set_challenges_paused(meta.tenant, &challenges_paused, false)
+    });
+    let challenge_resp = Mutex::new(challenge_resp);
+    io.add_method("submitchallengeproof", move |params: Params| {
+        with_hash_order(params, hash_order, |params| {
+            submit_challenge_proof(params, &challenge, &challenge_resp, &journal, &blacklist)
+        })
+    });
+    io.add_method_with_meta("getapistats", move |_params: Params, meta: ApiMeta| {
+        get_api_stats(meta.tenant, &pool, &rate_limits)
+    });
+    io.add_method_with_meta("getrpcstats", move |_params: Params, meta: ApiMeta| {
+        get_rpc_stats(meta.tenant, rpc_stats())
+    });
+    io.add_method("listmethods", |_params: Params| list_methods());
+    let io = Arc::new(io.io);
+
+    let addr: Vec<_> = config
+        .host
+        .to_socket_addrs()
+        .expect("Unable to resolve domain")
+        .collect();
+
+    let compression_min_size = if config.compression {
+        Some(config.compression_min_size as usize)
+    } else {
+        None
+    };
+    let (cors_allow_all, allowed_origins) = (config.cors_allow_all, config.cors_origins.clone());
+    let rate_limits_ref = rate_limits.clone();
+    let auth_ref = auth.clone();
+    let server_io = MetaIoHandler::with_middleware(SharedIo(io.clone()));
+    let server = ServerBuilder::with_meta_extractor(server_io, move |request: &Request<Body>| {
+        auth_ref.scope(request).unwrap_or_else(ApiMeta::no_access)
+    })
+    .cors(cors_origins(config))
+    .request_middleware(move |request: Request<Body>| {
+        // cors preflight requests carry no credentials and are answered by the
+        // server without calling any api method
+        if request.method() == Method::OPTIONS {
+            return request.into();
+        }
+        let meta = match auth.scope(&request) {
+            Some(meta) => meta,
+            None => {
+                return Response {
+                    code: StatusCode::UNAUTHORIZED,
+                    content_type: header::HeaderValue::from_str("text/plain").unwrap(),
+                    content: "Bad Authorization Attempt".to_string(),
+                }
+                .into()
+            }
+        };
+        let origin = request
+            .headers()
+            .get(header::ORIGIN)
+            .and_then(|value| value.to_str().ok());
+        let allow_origin = cors_allow_origin(cors_allow_all, &allowed_origins, origin);
+        let quota = match rate_limits_ref.check(&request, &meta) {
+            Ok(quota) => quota,
+            Err(quota) => {
+                return RequestMiddlewareAction::Respond {
+                    should_validate_hosts: true,
+                    response: Box::new(futures::finished(rate_limited_response(&quota, allow_origin))),
+                }
+            }
+        };
+        // calls accepting a compressed response or carrying a rate limit quota
+        // are run directly, as the server only sends plain responses without
+        // custom headers
+        let encoding = request
+            .headers()
+            .get(header::ACCEPT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .and_then(negotiate);
+        let compression = match (encoding, compression_min_size) {
+            (Some(encoding), Some(min_size)) => Some((encoding, min_size)),
+            _ => None,
+        };
+        if (compression.is_some() || quota.is_some()) && request.method() == Method::POST {
+            return RequestMiddlewareAction::Respond {
+                should_validate_hosts: true,
+                response: Box::new(direct_response(
+                    io.clone(),
+                    request,
+                    meta,
+                    compression,
+                    quota,
+                    allow_origin,
+                )),
+            };
+        }
+        request.into()
+    })
+    .keep_alive(config.keep_alive)
+    .threads(config.threads as usize)
+    .start_http(&addr[0])
+    .expect("api error");
+
+    let close_handle = server.close_handle();
+    let _ = thread::spawn(move || server.wait());
+    Ok(close_handle) // handler to stop the server from the main thread
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+    use std::io::Read;
+    use std::str;
+    use std::sync::mpsc::{channel, Receiver, TryRecvError};
+
+    use bitcoin::consensus::serialize;
+    use bitcoin::hashes::hex::ToHex;
+    use bitcoin::secp256k1::{self, Message, Secp256k1, SecretKey};
+
+    use crate::audit::verify_audit_bundle;
+    use crate::interfaces::mocks::clientchain::MockClientChain;
+    use crate::interfaces::mocks::service::MockService;
+    use crate::interfaces::mocks::storage::MockStorage;
+    use crate::interfaces::response::{ChallengeLatency, ChallengeRecord};
+    use crate::interfaces::storage::STORAGE_DEAD_LETTERS_LIMIT;
+    use crate::util::testing::{gen_challenge_state, gen_challenge_state_with_challenge, gen_dummy_hash, setup_logger};
+
+    #[test]
+    fn get_request_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+
+        // no such request
+        let s = format!(r#"{{"txid": "{}"}}"#, dummy_hash.to_string());
+        let params: Params = serde_json::from_str(&s).unwrap();
+        let resp = get_request(params, None, storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // save actual state
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let s = format!(r#"{{"txid": "{}"}}"#, dummy_hash.to_string());
+        let params: Params = serde_json::from_str(&s).unwrap();
+        let resp = get_request(params, None, storage.clone());
+        assert_eq!(
+            format!(
+                r#"{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false,"payment_state":"pending"}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}}"#,
+                dummy_hash.to_string()
+            ),
+            resp.wait().unwrap()
+        );
+    }
+
+    #[test]
+    fn export_request_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, dummy_hash)).unwrap();
+
+        // no such request
+        let resp = export_request(params.clone(), None, storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // request and bids in the ocean rpc shape
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let resp = export_request(params.clone(), None, storage.clone());
+        let request = format!(
+            r#""genesisBlock":"0000000000000000000000000000000000000000000000000000000000000000","startBlockHeight":2,"numTickets":10,"decayConst":null,"startPrice":null,"auctionPrice":null,"feePercentage":5,"endBlockHeight":5,"txid":"{}""#,
+            dummy_hash
+        );
+        assert_eq!(
+            format!(
+                r#"{{"request":{{{}}},"request_bids":{{{},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","feePubKey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3"}}]}}}}"#,
+                request, request
+            ),
+            resp.wait().unwrap()
+        );
+
+        // request of another tenant
+        let resp = export_request(params, Some(gen_dummy_hash(9)), storage.clone());
+        assert!(resp.wait().is_err());
+    }
+
+    #[test]
+    fn get_audit_bundle_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let signer = AuditSigner::from_hex(&"11".repeat(32)).unwrap();
+        let pubkey = signer.public_key();
+        let bundles = AuditBundles::new(Some(signer));
+        let dummy_hash = gen_dummy_hash(1);
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, dummy_hash)).unwrap();
+
+        // no such request
+        let resp = get_audit_bundle(params.clone(), None, storage.clone(), &bundles);
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // no bundle of the request
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let resp = get_audit_bundle(params.clone(), None, storage.clone(), &bundles);
+        assert_eq!(
+            "Invalid params: no audit bundle of `txid`, create it with createauditbundle.",
+            resp.wait().unwrap_err().message
+        );
+
+        // bundle in progress and complete
+        let _ = bundles.start(dummy_hash).unwrap();
+        let resp = get_audit_bundle(params.clone(), None, storage.clone(), &bundles);
+        let value: Value = serde_json::from_str(resp.wait().unwrap().as_str().unwrap()).unwrap();
+        assert_eq!("running", value["status"]["state"]);
+        assert_eq!("request", value["status"]["progress"]["section"]);
+        assert!(value.get("bundle").is_none());
+        bundles.generate(storage.as_ref(), dummy_hash);
+        let resp = get_audit_bundle(params.clone(), None, storage.clone(), &bundles);
+        let value: AuditBundleResponse = serde_json::from_str(resp.wait().unwrap().as_str().unwrap()).unwrap();
+        assert_eq!(AuditBundleState::Complete, value.status.state);
+        let bundle = base64::decode(&value.bundle.unwrap()).unwrap();
+        assert_eq!(
+            value.status.summary.unwrap(),
+            verify_audit_bundle(&bundle, &pubkey).unwrap()
+        );
+
+        // tenant scope
+        let resp = get_audit_bundle(params.clone(), Some(gen_dummy_hash(9)), storage.clone(), &bundles);
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn with_hash_order_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let bid_txid = "1234567890000000000000000000000000000000000000000000000000000000";
+        let bid_txid_internal = "0000000000000000000000000000000000000000000000000000009078563412";
+
+        // default order used if not set by the call
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, dummy_hash)).unwrap();
+        let resp = with_hash_order(params.clone(), HashOrder::Rpc, |params| {
+            get_request(params, None, storage.clone())
+        });
+        assert!(resp.wait().unwrap().as_str().unwrap().contains(bid_txid));
+        let resp = with_hash_order(params, HashOrder::Internal, |params| {
+            get_request(params, None, storage.clone())
+        });
+        assert!(resp.wait().unwrap().as_str().unwrap().contains(bid_txid_internal));
+
+        // order set by the call overrides default order
+        let params: Params =
+            serde_json::from_str(&format!(r#"{{"txid": "{}", "hash_order": "rpc"}}"#, dummy_hash)).unwrap();
+        let resp = with_hash_order(params, HashOrder::Internal, |params| {
+            get_request(params, None, storage.clone())
+        });
+        assert!(resp.wait().unwrap().as_str().unwrap().contains(bid_txid));
+
+        // hashes in params are converted to rpc order
+        let params: Params = serde_json::from_str(&format!(
+            r#"{{"txid": "{}", "hash_order": "internal"}}"#,
+            bid_txid_internal
+        ))
+        .unwrap();
+        let resp = with_hash_order(params, HashOrder::Rpc, |params| {
+            assert_eq!(
+                Some(&Value::from(bid_txid)),
+                params.parse::<Value>().unwrap().get("txid")
+            );
+            futures::finished(Value::Bool(true))
+        });
+        assert_eq!(Value::Bool(true), resp.wait().unwrap());
+
+        // invalid order
+        let params: Params = serde_json::from_str(r#"{"hash_order": "reversed"}"#).unwrap();
+        let resp = with_hash_order(params, HashOrder::Rpc, |_params| futures::finished(Value::Null));
+        assert!(resp.wait().unwrap_err().message.contains("unknown hash order"));
+    }
+
+    #[test]
+    fn get_requests_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+        let _params = Params::None;
+
+        let s_p1 = format!(r#"{{"page": 1}}"#);
+        let params_p1: Params = serde_json::from_str(&s_p1).unwrap();
+        let s_m1 = format!(r#"{{"page": -1}}"#);
+        let params_m1: Params = serde_json::from_str(&s_m1).unwrap();
+        let s_p2 = format!(r#"{{"page": 2}}"#);
+        let params_p2: Params = serde_json::from_str(&s_p2).unwrap();
+        let s_p5 = format!(r#"{{"page": 5}}"#);
+        let params_p5: Params = serde_json::from_str(&s_p5).unwrap();
+
+        // no requests
+        let resp = get_requests(Params::None, None, storage.clone());
+        assert_eq!(r#"{"requests":[],"pages":0}"#, resp.wait().unwrap());
+        let resp = get_requests(params_p1.clone(), None, storage.clone());
+        assert_eq!(r#"{"requests":[],"pages":0}"#, resp.wait().unwrap());
+        let resp = get_requests(params_m1.clone(), None, storage.clone());
+        assert_eq!(r#"{"requests":[],"pages":0}"#, resp.wait().unwrap());
+        let resp = get_requests(params_p2.clone(), None, storage.clone());
+        assert_eq!(r#"{"requests":[],"pages":0}"#, resp.wait().unwrap());
+        let resp = get_requests(params_p5.clone(), None, storage.clone());
+        assert_eq!(r#"{"requests":[],"pages":0}"#, resp.wait().unwrap());
+
+        // save actual state for 1 request
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let resp_1 = format!(
+            r#"{{"requests":[{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false,"payment_state":"pending"}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}}],"pages":1}}"#,
+            dummy_hash.to_string()
+        );
+        let resp = get_requests(Params::None, None, storage.clone());
+        assert_eq!(resp_1, resp.wait().unwrap());
+        let resp = get_requests(params_p1.clone(), None, storage.clone());
+        assert_eq!(resp_1, resp.wait().unwrap());
+        let resp = get_requests(params_m1.clone(), None, storage.clone());
+        assert_eq!(resp_1, resp.wait().unwrap());
+        let resp = get_requests(params_p2.clone(), None, storage.clone());
+        assert_eq!(r#"{"requests":[],"pages":1}"#, resp.wait().unwrap());
+        let resp = get_requests(params_p5.clone(), None, storage.clone());
+        assert_eq!(r#"{"requests":[],"pages":1}"#, resp.wait().unwrap());
+
+        // save actual state for another request (2 total)
+        let dummy_hash2 = gen_dummy_hash(2);
+        let state2 = gen_challenge_state(&dummy_hash2);
+        storage
+            .save_challenge_request_state(&state2.request, &state2.bids)
+            .unwrap();
+        let resp_2 = format!(
+            r#"{{"requests":[{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false,"payment_state":"pending"}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false,"payment_state":"pending"}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}}],"pages":1}}"#,
+            dummy_hash.to_string(),
+            dummy_hash2.to_string()
+        );
+        let resp = get_requests(Params::None, None, storage.clone());
+        assert_eq!(resp_2, resp.wait().unwrap());
+        let resp = get_requests(params_p1.clone(), None, storage.clone());
+        assert_eq!(resp_2, resp.wait().unwrap());
+        let resp = get_requests(params_m1.clone(), None, storage.clone());
+        assert_eq!(resp_2, resp.wait().unwrap());
+        let resp = get_requests(params_p2.clone(), None, storage.clone());
+        assert_eq!(r#"{"requests":[],"pages":1}"#, resp.wait().unwrap());
+        let resp = get_requests(params_p5.clone(), None, storage.clone());
+        assert_eq!(r#"{"requests":[],"pages":1}"#, resp.wait().unwrap());
+
+        // save actual state for 10 more requests (12 total)
+        for i in 3..=12 {
+            let dummy_hashi = gen_dummy_hash(i);
+            let statei = gen_challenge_state(&dummy_hashi);
+            storage
+                .save_challenge_request_state(&statei.request, &statei.bids)
+                .unwrap();
+        }
+        let resp_10 = format!(
+            r#"{{"requests":[{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false,"payment_state":"pending"}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false,"payment_state":"pending"}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false,"payment_state":"pending"}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false,"payment_state":"pending"}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false,"payment_state":"pending"}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false,"payment_state":"pending"}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false,"payment_state":"pending"}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false,"payment_state":"pending"}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false,"payment_state":"pending"}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false,"payment_state":"pending"}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}}],"pages":2}}"#,
+            gen_dummy_hash(1).to_string(),
+            gen_dummy_hash(2).to_string(),
+            gen_dummy_hash(3).to_string(),
+            gen_dummy_hash(4).to_string(),
+            gen_dummy_hash(5).to_string(),
+            gen_dummy_hash(6).to_string(),
+            gen_dummy_hash(7).to_string(),
+            gen_dummy_hash(8).to_string(),
+            gen_dummy_hash(9).to_string(),
+            gen_dummy_hash(10).to_string(),
+        );
+        let resp_12 = format!(
+            r#"{{"requests":[{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false,"payment_state":"pending"}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false,"payment_state":"pending"}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}}],"pages":2}}"#,
+            gen_dummy_hash(11).to_string(),
+            gen_dummy_hash(12).to_string(),
+        );
+        let resp = get_requests(Params::None, None, storage.clone());
+        assert_eq!(resp_10, resp.wait().unwrap());
+        let resp = get_requests(params_p1.clone(), None, storage.clone());
+        assert_eq!(resp_10, resp.wait().unwrap());
+        let resp = get_requests(params_m1.clone(), None, storage.clone());
+        assert_eq!(resp_10, resp.wait().unwrap());
+        let resp = get_requests(params_p2.clone(), None, storage.clone());
+        assert_eq!(resp_12, resp.wait().unwrap());
+        let resp = get_requests(params_p5.clone(), None, storage.clone());
+        assert_eq!(r#"{"requests":[],"pages":2}"#, resp.wait().unwrap());
+
+        // pages by cursor in order of start height and txid, stable while a
+        // request at an earlier height is backfilled between pages
+        let txids_by_cursor = |cursor: &str| -> (Vec<sha256d::Hash>, Option<String>) {
+            let params: Params = serde_json::from_str(&format!(r#"{{"cursor": "{}"}}"#, cursor)).unwrap();
+            let resp: GetRequestsResponse = serde_json::from_str(
+                get_requests(params, None, storage.clone())
+                    .wait()
+                    .unwrap()
+                    .as_str()
+                    .unwrap(),
+            )
+            .unwrap();
+            (
+                resp.requests.iter().map(|request| request.request.txid).collect(),
+                resp.next_cursor,
+            )
+        };
+        let mut sorted: Vec<sha256d::Hash> = (1..=12).map(gen_dummy_hash).collect();
+        sorted.sort_by_key(|txid| txid.to_string());
+        let (first, next_cursor) = txids_by_cursor("");
+        assert_eq!(sorted[..10].to_vec(), first);
+        let next_cursor = next_cursor.unwrap();
+
+        let mut backfilled = gen_challenge_state(&gen_dummy_hash(13));
+        backfilled.request.start_blockheight = 1;
+        storage
+            .save_challenge_request_state(&backfilled.request, &backfilled.bids)
+            .unwrap();
+        let (second, last_cursor) = txids_by_cursor(&next_cursor);
+        assert_eq!(sorted[10..].to_vec(), second);
+        assert_eq!(None, last_cursor);
+        assert_eq!(gen_dummy_hash(13), txids_by_cursor("").0[0]);
+
+        // invalid cursor
+        let params: Params = serde_json::from_str(r#"{"cursor": "notacursor"}"#).unwrap();
+        let resp = get_requests(params, None, storage.clone());
+        assert_eq!(
+            "Invalid params: `cursor` is not a valid requests cursor.",
+            resp.wait().unwrap_err().message
+        );
+
+        // pages by number of the most recently updated requests first, with a
+        // request completed after all other requests were stored coming first
+        let mut completed = gen_challenge_state(&gen_dummy_hash(2)).request;
+        completed.is_payment_complete = true;
+        storage.update_request(&completed).unwrap();
+        let txids_by_update = |page: u64| -> Vec<sha256d::Hash> {
+            let params: Params = serde_json::from_str(&format!(r#"{{"page": {}, "sort": "updated"}}"#, page)).unwrap();
+            let resp: GetRequestsResponse = serde_json::from_str(
+                get_requests(params, None, storage.clone())
+                    .wait()
+                    .unwrap()
+                    .as_str()
+                    .unwrap(),
+            )
+            .unwrap();
+            assert_eq!(2, resp.pages);
+            resp.requests.iter().map(|request| request.request.txid).collect()
+        };
+        let mut recent = vec![gen_dummy_hash(2), gen_dummy_hash(13)];
+        recent.extend((3..=12).rev().map(gen_dummy_hash));
+        recent.push(gen_dummy_hash(1));
+        assert_eq!(recent[..10].to_vec(), txids_by_update(1));
+        assert_eq!(recent[10..].to_vec(), txids_by_update(2));
+
+        // invalid sort and sort with cursor
+        let params: Params = serde_json::from_str(r#"{"sort": "created"}"#).unwrap();
+        let resp = get_requests(params, None, storage.clone());
+        assert_eq!(
+            "Invalid params: `sort` must be `updated`.",
+            resp.wait().unwrap_err().message
+        );
+        let params: Params = serde_json::from_str(r#"{"cursor": "", "sort": "updated"}"#).unwrap();
+        let resp = get_requests(params, None, storage.clone());
+        assert_eq!(
+            "Invalid params: `cursor` can not be used with `sort`.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn get_request_response_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+        let dummy_hash_bid = gen_dummy_hash(2);
+
+        // no such request
+        let s = format!(r#"{{"txid": "{}"}}"#, dummy_hash.to_string());
+        let params: Params = serde_json::from_str(&s).unwrap();
+        let resp = get_request_response(params, None, storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        let mut dummy_response_set = HashSet::new();
+        let _ = dummy_response_set.insert(dummy_hash_bid);
+        let mut dummy_response = RequestResponse::new();
+        dummy_response.update(&dummy_response_set);
+        let _ = storage.save_response(dummy_hash, &dummy_response);
+
+        // invalid key
+        let s = format!(r#"{{"hash": "{}"}}"#, dummy_hash.to_string());
+        let params: Params = serde_json::from_str(&s).unwrap();
+        let resp = get_request_response(params, None, storage.clone());
+        assert_eq!(
+            "Invalid params: missing field `txid`.",
+            resp.wait().unwrap_err().message
+        );
+
+        // invalid value
+        let s = format!(r#"{{"txid": "{}a"}}"#, dummy_hash.to_string());
+        let params: Params = serde_json::from_str(&s).unwrap();
+        let resp = get_request_response(params, None, storage.clone());
+        assert_eq!(
+            "Invalid params: odd hex string length 65.",
+            resp.wait().unwrap_err().message
+        );
+
+        // valid key and value
+        let s = format!(r#"{{"txid": "{}"}}"#, dummy_hash.to_string());
+        let params: Params = serde_json::from_str(&s).unwrap();
+        let resp = get_request_response(params, None, storage.clone());
+        assert_eq!(
+            format!(
+                r#"{{"response":{{"num_challenges":1,"bid_responses":{{"{}":1}}}}}}"#,
+                dummy_hash_bid.to_string()
+            ),
+            resp.wait().unwrap()
+        );
+
+        // compacted response
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        storage.compact_response(dummy_hash, &dummy_response.summary()).unwrap();
+        let s = format!(r#"{{"txid": "{}"}}"#, dummy_hash.to_string());
+        let params: Params = serde_json::from_str(&s).unwrap();
+        let resp = get_request_response(params, None, storage.clone());
+        assert_eq!(
+            r#"{"response":{"num_challenges":1,"bid_responses":{}},"summary":{"num_challenges":1,"num_bids":1,"num_responses":1,"response_rate":1.0}}"#,
+            resp.wait().unwrap()
+        );
+    }
+
+    #[test]
+    fn verify_request_response_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+        let s = format!(r#"{{"txid": "{}"}}"#, dummy_hash.to_string());
+        let params: Params = serde_json::from_str(&s).unwrap();
+
+        // no such response
+        let resp = verify_request_response(params.clone(), None, storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // response updates chained
+        let mut dummy_response = RequestResponse::new();
+        dummy_response.num_challenges = 1;
+        storage.save_response(dummy_hash, &dummy_response).unwrap();
+        let hash_first = dummy_response.integrity_hash(None);
+        let _ = dummy_response.bid_responses.insert(gen_dummy_hash(2), 1);
+        storage.save_response(dummy_hash, &dummy_response).unwrap();
+        let hash_second = dummy_response.integrity_hash(Some(hash_first));
+        let resp = verify_request_response(params.clone(), None, storage.clone());
+        assert_eq!(
+            format!(r#"{{"valid":true,"hashes":["{}","{}"]}}"#, hash_first, hash_second),
+            resp.wait().unwrap()
+        );
+
+        // response edited without updating the hash chain
+        let _ = storage.challenge_responses.borrow_mut()[0].insert("num_challenges", 5);
+        let resp = verify_request_response(params.clone(), None, storage.clone());
+        assert_eq!(
+            format!(r#"{{"valid":false,"hashes":["{}","{}"]}}"#, hash_first, hash_second),
+            resp.wait().unwrap()
+        );
+
+        // request not in tenant scope
+        let resp = verify_request_response(params, Some(gen_dummy_hash(9)), storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn rerun_request_response_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, dummy_hash)).unwrap();
+
+        // no such response
+        let resp = rerun_request_response(params.clone(), None, storage.clone(), &None);
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // response without records and no journal
+        let mut dummy_response = RequestResponse::new();
+        dummy_response.update(&vec![gen_dummy_hash(2)].into_iter().collect());
+        storage.save_response(dummy_hash, &dummy_response).unwrap();
+        let resp = rerun_request_response(params.clone(), None, storage.clone(), &None);
+        assert_eq!(Error::internal_error(), resp.wait().unwrap_err());
+
+        // response update lost after saving its record
+        storage
+            .save_challenge_record(
+                dummy_hash,
+                &ChallengeRecord::new(gen_dummy_hash(6), &vec![gen_dummy_hash(2)].into_iter().collect()),
+            )
+            .unwrap();
+        let resp = rerun_request_response(params.clone(), None, storage.clone(), &None);
+        let resp: Value = serde_json::from_str(resp.wait().unwrap().as_str().unwrap()).unwrap();
+        assert_eq!("records", resp["report"]["source"]);
+        assert_eq!(1, resp["report"]["stored_challenges"]);
+        assert_eq!(1, resp["report"]["recomputed_challenges"]);
+        assert_eq!(
+            serde_json::json!([gen_dummy_hash(6).to_string()]),
+            resp["report"]["missing_challenges"]
+        );
+        assert_eq!(serde_json::json!([]), resp["report"]["bids"]);
+
+        // not available to tenants
+        let resp = rerun_request_response(params, Some(gen_dummy_hash(9)), storage.clone(), &None);
+        assert_eq!(
+            "Invalid request: response reruns not available to tenants.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn get_request_logs_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+        let s = format!(r#"{{"txid": "{}"}}"#, dummy_hash.to_string());
+        let params: Params = serde_json::from_str(&s).unwrap();
+
+        // no logs
+        let resp = get_request_logs(params.clone(), None, storage.clone());
+        assert_eq!(r#"{"logs":[]}"#, resp.wait().unwrap());
+
+        // logs stored
+        storage
+            .save_request_logs(dummy_hash, &vec!["log1".to_owned(), "log2".to_owned()])
+            .unwrap();
+        let resp = get_request_logs(params.clone(), None, storage.clone());
+        assert_eq!(r#"{"logs":["log1","log2"]}"#, resp.wait().unwrap());
+
+        // request not in tenant scope
+        let resp = get_request_logs(params.clone(), Some(gen_dummy_hash(9)), storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let resp = get_request_logs(params.clone(), Some(gen_dummy_hash(9)), storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // request in tenant scope
+        let resp = get_request_logs(params, Some(gen_dummy_hash(0)), storage.clone());
+        assert_eq!(r#"{"logs":["log1","log2"]}"#, resp.wait().unwrap());
+    }
+
+    #[test]
+    fn get_requests_full_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+        let dummy_hash2 = gen_dummy_hash(2);
+        let params_p2: Params = serde_json::from_str(r#"{"page": 2}"#).unwrap();
+
+        // no requests
+        let resp = get_requests_full(Params::None, None, storage.clone());
+        assert_eq!(r#"{"requests":[],"pages":0}"#, resp.wait().unwrap());
+
+        // request with response and request without response
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let mut dummy_response = RequestResponse::new();
+        dummy_response.num_challenges = 2;
+        let _ = dummy_response.bid_responses.insert(gen_dummy_hash(5), 1);
+        storage.save_response(dummy_hash, &dummy_response).unwrap();
+        let state2 = gen_challenge_state(&dummy_hash2);
+        storage
+            .save_challenge_request_state(&state2.request, &state2.bids)
+            .unwrap();
+
+        let request_json = |txid: &sha256d::Hash| {
+            format!(
+                r#"{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false,"payment_state":"pending"}}"#,
+                txid
+            )
+        };
+        let bids_json = r#"[{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}]"#;
+        let resp_full = format!(
+            r#"{{"requests":[{{"request":{},"bids":{},"response":{{"num_challenges":2,"bid_responses":{{"{}":1}}}}}},{{"request":{},"bids":{},"response":null}}],"pages":1}}"#,
+            request_json(&dummy_hash),
+            bids_json,
+            gen_dummy_hash(5),
+            request_json(&dummy_hash2),
+            bids_json
+        );
+        let resp = get_requests_full(Params::None, None, storage.clone());
+        assert_eq!(resp_full, resp.wait().unwrap());
+        let resp = get_requests_full(Params::None, Some(gen_dummy_hash(0)), storage.clone());
+        assert_eq!(resp_full, resp.wait().unwrap());
+        let resp = get_requests_full(params_p2, None, storage.clone());
+        assert_eq!(r#"{"requests":[],"pages":1}"#, resp.wait().unwrap());
+
+        // other tenant
+        let resp = get_requests_full(Params::None, Some(gen_dummy_hash(9)), storage.clone());
+        assert_eq!(r#"{"requests":[],"pages":0}"#, resp.wait().unwrap());
+    }
+
+    #[test]
+    fn get_request_latency_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, dummy_hash)).unwrap();
+
+        // no latency for request
+        let resp = get_request_latency(params.clone(), None, storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // latency for request
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let mut latency = ChallengeLatency::new();
+        latency.verify_ms = vec![1000, 3000, 2000];
+        latency.add_proof(gen_dummy_hash(3), 40);
+        storage.save_challenge_latency(dummy_hash, &latency).unwrap();
+        let resp = get_request_latency(params.clone(), None, storage.clone());
+        assert_eq!(
+            format!(
+                r#"{{"verify":{{"count":3,"p50":2000,"p90":3000,"p99":3000,"max":3000}},"proof":{{"count":1,"p50":40,"p90":40,"p99":40,"max":40}},"bids":[{{"txid":"{}","proof":{{"count":1,"p50":40,"p90":40,"p99":40,"max":40}}}}]}}"#,
+                gen_dummy_hash(3)
+            ),
+            resp.wait().unwrap()
+        );
+
+        // tenant scope
+        let resp = get_request_latency(params.clone(), Some(gen_dummy_hash(0)), storage.clone());
+        assert!(resp.wait().is_ok());
+        let resp = get_request_latency(params, Some(gen_dummy_hash(9)), storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn get_request_coverage_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, dummy_hash)).unwrap();
+
+        // no coverage for request
+        let resp = get_request_coverage(params.clone(), None, storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // coverage for request
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let mut request = state.request.clone();
+        request.start_blockheight = 10;
+        request.end_blockheight = 17;
+        let coverage = ChallengeCoverage::new(&request, &[10, 16], 2, 20);
+        storage.save_challenge_coverage(dummy_hash, &coverage).unwrap();
+        let resp = get_request_coverage(params.clone(), None, storage.clone());
+        assert_eq!(
+            r#"{"coverage":{"frequency":2,"height":20,"scheduled":4,"issued":2,"coverage":0.5,"gaps":[{"start_height":12,"end_height":15,"missed":2}]}}"#,
+            resp.wait().unwrap()
+        );
+
+        // coverage with client chain stalls
+        let stall = ClientChainStall {
+            clientchain_height: 50,
+            start_height: 12,
+            end_height: 15,
+            start_time: 0,
+            end_time: 300,
+            missed_blocks: 4,
+        };
+        storage.save_clientchain_stall(dummy_hash, &stall).unwrap();
+        let resp = get_request_coverage(params.clone(), None, storage.clone());
+        assert_eq!(
+            r#"{"coverage":{"frequency":2,"height":20,"scheduled":4,"issued":2,"coverage":0.5,"gaps":[{"start_height":12,"end_height":15,"missed":2}]},"stalls":[{"clientchain_height":50,"start_height":12,"end_height":15,"start_time":0,"end_time":300,"missed_blocks":4}]}"#,
+            resp.wait().unwrap()
+        );
+
+        // tenant scope
+        let resp = get_request_coverage(params.clone(), Some(gen_dummy_hash(0)), storage.clone());
+        assert!(resp.wait().is_ok());
+        let resp = get_request_coverage(params, Some(gen_dummy_hash(9)), storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn get_bid_activity_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+        let state = gen_challenge_state(&dummy_hash);
+        let bid = state.bids.iter().next().unwrap().txid;
+        let params: Params =
+            serde_json::from_str(&format!(r#"{{"txid": "{}", "bid": "{}"}}"#, dummy_hash, bid)).unwrap();
+
+        // no such request or bid
+        let resp = get_bid_activity(params.clone(), None, storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let other_params: Params = serde_json::from_str(&format!(
+            r#"{{"txid": "{}", "bid": "{}"}}"#,
+            dummy_hash,
+            gen_dummy_hash(5)
+        ))
+        .unwrap();
+        let resp = get_bid_activity(other_params, None, storage.clone());
+        assert_eq!(
+            "Invalid params: `bid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // no challenges yet
+        let resp = get_bid_activity(params.clone(), None, storage.clone());
+        assert_eq!(r#"{"responses":0,"missed":0,"activity":[]}"#, resp.wait().unwrap());
+
+        // challenges responded to and missed in the order of height
+        for (challenge, height) in vec![(gen_dummy_hash(7), 3), (gen_dummy_hash(6), 2), (gen_dummy_hash(8), 4)] {
+            storage
+                .save_challenge_stats(
+                    dummy_hash,
+                    &ChallengeStats {
+                        challenge,
+                        height,
+                        num_bids: 1,
+                        num_responses: 1,
+                        amount_tag: None,
+                        wallet: None,
+                    },
+                )
+                .unwrap();
+        }
+        storage
+            .save_bid_proofs(
+                dummy_hash,
+                &[
+                    BidProof {
+                        bid,
+                        challenge: gen_dummy_hash(6),
+                        clientchain_height: 20,
+                        timestamp: 1000,
+                    },
+                    BidProof {
+                        bid,
+                        challenge: gen_dummy_hash(8),
+                        clientchain_height: 22,
+                        timestamp: 3000,
+                    },
+                ],
+            )
+            .unwrap();
+        let resp = get_bid_activity(params.clone(), None, storage.clone());
+        assert_eq!(
+            format!(
+                r#"{{"responses":2,"missed":1,"activity":[{{"challenge":"{}","height":2,"responded":true,"clientchain_height":20,"timestamp":1000}},{{"challenge":"{}","height":3,"responded":false}},{{"challenge":"{}","height":4,"responded":true,"clientchain_height":22,"timestamp":3000}}]}}"#,
+                gen_dummy_hash(6),
+                gen_dummy_hash(7),
+                gen_dummy_hash(8)
+            ),
+            resp.wait().unwrap()
+        );
+
+        // tenant scope
+        let resp = get_bid_activity(params.clone(), Some(gen_dummy_hash(9)), storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // not available once the responses are compacted
+        storage
+            .compact_response(dummy_hash, &RequestResponse::new().summary())
+            .unwrap();
+        assert!(storage.get_bid_proofs(dummy_hash, bid).unwrap().is_empty());
+        let resp = get_bid_activity(params, None, storage.clone());
+        assert_eq!(
+            "Invalid request: responses of the request have been compacted.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn get_request_response_proof_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, dummy_hash)).unwrap();
+
+        // no response for request
+        let resp = get_request_response_proof(params.clone(), None, storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // root without proof
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let mut response = RequestResponse::new();
+        let _ = response.bid_responses.insert(gen_dummy_hash(2), 3);
+        let _ = response.bid_responses.insert(gen_dummy_hash(3), 1);
+        storage.save_response(dummy_hash, &response).unwrap();
+        let root = ResponseMerkleTree::new(&response).root().unwrap();
+        let resp = get_request_response_proof(params.clone(), None, storage.clone());
+        let proof_resp: GetRequestResponseProofResponse =
+            serde_json::from_str(resp.wait().unwrap().as_str().unwrap()).unwrap();
+        assert_eq!(Some(root), proof_resp.root);
+        assert_eq!(2, proof_resp.num_leaves);
+        assert!(proof_resp.proof.is_none());
+
+        // proof of bid
+        let bid_params: Params = serde_json::from_str(&format!(
+            r#"{{"txid": "{}", "bid": "{}"}}"#,
+            dummy_hash,
+            gen_dummy_hash(3)
+        ))
+        .unwrap();
+        let resp = get_request_response_proof(bid_params.clone(), None, storage.clone());
+        let proof_resp: GetRequestResponseProofResponse =
+            serde_json::from_str(resp.wait().unwrap().as_str().unwrap()).unwrap();
+        let proof = proof_resp.proof.unwrap();
+        assert_eq!(1, proof.count);
+        assert!(proof.verify(&root));
+
+        // unknown bid
+        let unknown_params: Params = serde_json::from_str(&format!(
+            r#"{{"txid": "{}", "bid": "{}"}}"#,
+            dummy_hash,
+            gen_dummy_hash(4)
+        ))
+        .unwrap();
+        let resp = get_request_response_proof(unknown_params, None, storage.clone());
+        assert_eq!(
+            "Invalid params: `bid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // tenant scope
+        let resp = get_request_response_proof(bid_params.clone(), Some(gen_dummy_hash(0)), storage.clone());
+        assert!(resp.wait().is_ok());
+        let resp = get_request_response_proof(bid_params, Some(gen_dummy_hash(9)), storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn get_request_height_stats_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, dummy_hash)).unwrap();
+
+        // request does not exist
+        let resp = get_request_height_stats(params.clone(), None, storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // no stats for request
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let resp = get_request_height_stats(params.clone(), None, storage.clone());
+        assert_eq!(r#"{"heights":[]}"#, resp.wait().unwrap());
+
+        // stats for request
+        for (i, height, num_responses) in vec![(2, 5, 1), (3, 5, 2), (4, 7, 0)] {
+            storage
+                .save_challenge_stats(
+                    dummy_hash,
+                    &ChallengeStats {
+                        challenge: gen_dummy_hash(i),
+                        height,
+                        num_bids: 2,
+                        num_responses,
+                        amount_tag: None,
+                        wallet: None,
+                    },
+                )
+                .unwrap();
+        }
+        let resp = get_request_height_stats(params.clone(), None, storage.clone());
+        assert_eq!(
+            r#"{"heights":[{"height":5,"num_challenges":2,"num_bids":2,"num_responses":3,"response_rate":0.75},{"height":7,"num_challenges":1,"num_bids":2,"num_responses":0,"response_rate":0.0}]}"#,
+            resp.wait().unwrap()
+        );
+
+        // height range
+        let range_params: Params = serde_json::from_str(&format!(
+            r#"{{"txid": "{}", "start_height": 6, "end_height": 10}}"#,
+            dummy_hash
+        ))
+        .unwrap();
+        let resp = get_request_height_stats(range_params, None, storage.clone());
+        assert_eq!(
+            r#"{"heights":[{"height":7,"num_challenges":1,"num_bids":2,"num_responses":0,"response_rate":0.0}]}"#,
+            resp.wait().unwrap()
+        );
+
+        // tenant scope
+        let resp = get_request_height_stats(params.clone(), Some(gen_dummy_hash(0)), storage.clone());
+        assert!(resp.wait().is_ok());
+        let resp = get_request_height_stats(params, Some(gen_dummy_hash(9)), storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn get_fee_pool_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, dummy_hash)).unwrap();
+
+        // no request
+        let resp = get_fee_pool(params.clone(), None, storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // request without fee pool
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let resp = get_fee_pool(params.clone(), None, storage.clone());
+        assert_eq!(
+            "Invalid params: fee pool of `txid` not calculated yet.",
+            resp.wait().unwrap_err().message
+        );
+
+        // request fee pool
+        let pool = FeePool::new(
+            10,
+            12,
+            vec![BlockFees {
+                height: 11,
+                fees: vec![AssetFees {
+                    asset: "CBT".to_owned(),
+                    amount: Amount::from_sat(2000000),
+                }],
+            }],
+            5,
+            None,
+        );
+        storage.save_fee_pool(dummy_hash, &pool).unwrap();
+        let resp = get_fee_pool(params.clone(), None, storage.clone());
+        assert_eq!(
+            r#"{"fee_pool":{"start_height":10,"end_height":12,"blocks":[{"height":11,"fees":[{"asset":"CBT","amount":0.02}]}],"fees":[{"asset":"CBT","amount":0.02}],"total":0.02,"fee_percentage":5,"guardnode_amount":0.001,"fee_filter":null}}"#,
+            resp.wait().unwrap()
+        );
+
+        // tenant scope
+        let resp = get_fee_pool(params.clone(), Some(gen_dummy_hash(0)), storage.clone());
+        assert!(resp.wait().is_ok());
+        let resp = get_fee_pool(params, Some(gen_dummy_hash(9)), storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn estimate_earnings_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let service = Arc::new(MockService::new());
+        let estimate_amount = |value: Value| -> (u64, Amount) {
+            let value: Value = serde_json::from_str(value.as_str().unwrap()).unwrap();
+            let estimate = &value["estimate"];
+            (
+                estimate["num_bids"].as_u64().unwrap(),
+                Amount::from_btc(estimate["amount"].as_f64().unwrap()).unwrap(),
+            )
+        };
+
+        // finished request with fees of 1000 sat per service chain block
+        let finished_hash = gen_dummy_hash(2);
+        let finished = gen_challenge_state(&finished_hash);
+        storage
+            .save_challenge_request_state(&finished.request, &finished.bids)
+            .unwrap();
+        let pool = FeePool::new(
+            10,
+            12,
+            vec![BlockFees {
+                height: 11,
+                fees: vec![AssetFees {
+                    asset: "CBT".to_owned(),
+                    amount: Amount::from_sat(3000),
+                }],
+            }],
+            5,
+            None,
+        );
+        storage.save_fee_pool(finished_hash, &pool).unwrap();
+
+        // upcoming request on the service chain with three bids
+        let upcoming_txid = service.request.borrow().txid;
+        let params: Params =
+            serde_json::from_str(&format!(r#"{{"txid": "{}", "response_rate": 0.5}}"#, upcoming_txid)).unwrap();
+        let resp = estimate_earnings_call(params, None, storage.clone(), service.clone(), Some(50));
+        assert_eq!((4, Amount::from_sat(187)), estimate_amount(resp.wait().unwrap()));
+
+        // active request in storage with a single bid
+        let dummy_hash = gen_dummy_hash(1);
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, dummy_hash)).unwrap();
+        let resp = estimate_earnings_call(params.clone(), None, storage.clone(), service.clone(), None);
+        assert_eq!((2, Amount::from_sat(75)), estimate_amount(resp.wait().unwrap()));
+
+        // tenant scope
+        let resp = estimate_earnings_call(
+            params.clone(),
+            Some(gen_dummy_hash(9)),
+            storage.clone(),
+            service.clone(),
+            None,
+        );
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // bad response rate
+        let bad_params: Params =
+            serde_json::from_str(&format!(r#"{{"txid": "{}", "response_rate": 1.5}}"#, dummy_hash)).unwrap();
+        let resp = estimate_earnings_call(bad_params, None, storage.clone(), service.clone(), None);
+        assert_eq!(
+            "Invalid params: `response_rate` should be between 0 and 1.",
+            resp.wait().unwrap_err().message
+        );
+
+        // finished request
+        let _ = service.height.replace(5);
+        let resp = estimate_earnings_call(params, None, storage.clone(), service.clone(), None);
+        assert_eq!("Invalid params: `txid` has finished.", resp.wait().unwrap_err().message);
+    }
+
+    #[test]
+    fn get_request_reconciliation_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, dummy_hash)).unwrap();
+
+        // no reconciliation for request
+        let resp = get_request_reconciliation(params.clone(), None, storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // reconciliation for request
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let reconciliation = ResponseReconciliation {
+            clientchain_height: 10,
+            bids: vec![BidReconciliation::new(gen_dummy_hash(3), 2, 1)],
+        };
+        storage
+            .save_response_reconciliation(dummy_hash, &reconciliation)
+            .unwrap();
+        let resp = get_request_reconciliation(params.clone(), None, storage.clone());
+        assert_eq!(
+            format!(
+                r#"{{"reconciliation":{{"clientchain_height":10,"bids":[{{"txid":"{}","listener_responses":2,"onchain_responses":1,"is_divergent":true}}]}}}}"#,
+                gen_dummy_hash(3)
+            ),
+            resp.wait().unwrap()
+        );
+
+        // tenant scope
+        let resp = get_request_reconciliation(params.clone(), Some(gen_dummy_hash(0)), storage.clone());
+        assert!(resp.wait().is_ok());
+        let resp = get_request_reconciliation(params, Some(gen_dummy_hash(9)), storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn get_unverified_requests_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+
+        // no deposits
+        let resp = get_unverified_requests(None, storage.clone());
+        assert_eq!(r#"{"deposits":[]}"#, resp.wait().unwrap());
+
+        // verified and unverified deposits
+        let mut deposit = RequestDeposit {
+            txid: gen_dummy_hash(1),
+            genesis_blockhash: gen_dummy_hash(0),
+            promised: Amount::from_btc(1.0).unwrap(),
+            locked: Amount::from_btc(1.0).unwrap(),
+        };
+        storage.save_request_deposit(&deposit).unwrap();
+        deposit.txid = gen_dummy_hash(2);
+        deposit.locked = Amount::from_btc(0.5).unwrap();
+        storage.save_request_deposit(&deposit).unwrap();
+        let expected = format!(
+            r#"{{"deposits":[{{"txid":"{}","genesis_blockhash":"{}","promised":1.0,"locked":0.5}}]}}"#,
+            gen_dummy_hash(2),
+            gen_dummy_hash(0)
+        );
+        let resp = get_unverified_requests(None, storage.clone());
+        assert_eq!(expected, resp.wait().unwrap());
+        let resp = get_unverified_requests(Some(gen_dummy_hash(0)), storage.clone());
+        assert_eq!(expected, resp.wait().unwrap());
+
+        // other tenant
+        let resp = get_unverified_requests(Some(gen_dummy_hash(9)), storage.clone());
+        assert_eq!(r#"{"deposits":[]}"#, resp.wait().unwrap());
+    }
+
+    #[test]
+    fn get_rejected_requests_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+
+        // no rejections
+        let resp = get_rejected_requests(None, storage.clone());
+        assert_eq!(r#"{"rejections":[]}"#, resp.wait().unwrap());
+
+        // rejected request
+        let rejection = RequestRejection {
+            txid: gen_dummy_hash(1),
+            genesis_blockhash: gen_dummy_hash(0),
+            reason: "no tickets".to_owned(),
+        };
+        storage.save_request_rejection(&rejection).unwrap();
+        let expected = format!(
+            r#"{{"rejections":[{{"txid":"{}","genesis_blockhash":"{}","reason":"no tickets"}}]}}"#,
+            gen_dummy_hash(1),
+            gen_dummy_hash(0)
+        );
+        let resp = get_rejected_requests(None, storage.clone());
+        assert_eq!(expected, resp.wait().unwrap());
+        let resp = get_rejected_requests(Some(gen_dummy_hash(0)), storage.clone());
+        assert_eq!(expected, resp.wait().unwrap());
+
+        // other tenant
+        let resp = get_rejected_requests(Some(gen_dummy_hash(9)), storage.clone());
+        assert_eq!(r#"{"rejections":[]}"#, resp.wait().unwrap());
+    }
+
+    #[test]
+    fn get_dead_letters_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+
+        // no dead letters
+        let resp = get_dead_letters(Params::None, None, storage.clone());
+        assert_eq!(r#"{"letters":[]}"#, resp.wait().unwrap());
+
+        // most recent dead letters first, up to the limit
+        for reason in vec!["bad-json-data", "bad-proof-data"] {
+            storage
+                .save_dead_letter(&DeadLetter::new(b"{}", reason, None, None, 1024))
+                .unwrap();
+        }
+        let resp: Value = serde_json::from_str(
+            get_dead_letters(Params::None, None, storage.clone())
+                .wait()
+                .unwrap()
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap();
+        let letters = resp["letters"].as_array().unwrap();
+        assert_eq!(2, letters.len());
+        assert_eq!("bad-proof-data", letters[0]["reason"]);
+        assert_eq!("{}", letters[0]["payload"]);
+        let params: Params = serde_json::from_str(r#"{"limit": 1}"#).unwrap();
+        let resp: Value = serde_json::from_str(
+            get_dead_letters(params, None, storage.clone())
+                .wait()
+                .unwrap()
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(1, resp["letters"].as_array().unwrap().len());
+
+        // storage keeps only the most recent dead letters
+        for _ in 0..STORAGE_DEAD_LETTERS_LIMIT {
+            storage
+                .save_dead_letter(&DeadLetter::new(b"{}", "bad-json-data", None, None, 1024))
+                .unwrap();
+        }
+        assert_eq!(STORAGE_DEAD_LETTERS_LIMIT, storage.dead_letters.borrow().len());
+
+        // not available to tenants
+        let resp = get_dead_letters(Params::None, Some(gen_dummy_hash(0)), storage.clone());
+        assert_eq!(
+            "Invalid request: dead letters not available to tenants.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn request_annotation_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let tenant = Some(gen_dummy_hash(9));
+        for i in 1..4 {
+            let state = gen_challenge_state(&gen_dummy_hash(i));
+            storage
+                .save_challenge_request_state(&state.request, &state.bids)
+                .unwrap();
+        }
+        let txid_params: Params =
+            serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, gen_dummy_hash(1).to_string())).unwrap();
+
+        // no annotation yet
+        let resp = get_request_annotation(txid_params.clone(), None, storage.clone());
+        assert_eq!(r#"{"labels":[]}"#, resp.wait().unwrap());
+        let resp = get_request(txid_params.clone(), None, storage.clone());
+        assert!(!resp.wait().unwrap().as_str().unwrap().contains("annotation"));
+
+        // labels are trimmed and deduplicated
+        for i in vec![3, 1] {
+            let params: Params = serde_json::from_str(&format!(
+                r#"{{"txid": "{}", "labels": [" disputed", "disputed", "", "vip"], "notes": "guardnode offline"}}"#,
+                gen_dummy_hash(i).to_string()
+            ))
             .unwrap();
-        let resp_2 = format!(
-            r#"{{"requests":[{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}}],"pages":1}}"#,
-            dummy_hash.to_string(),
-            dummy_hash2.to_string()
+            let resp = set_request_annotation(params, None, storage.clone());
+            assert_eq!(
+                r#"{"labels":["disputed","vip"],"notes":"guardnode offline"}"#,
+                resp.wait().unwrap()
+            );
+        }
+        let resp = get_request_annotation(txid_params.clone(), None, storage.clone());
+        assert_eq!(
+            r#"{"labels":["disputed","vip"],"notes":"guardnode offline"}"#,
+            resp.wait().unwrap()
+        );
+        let resp: Value = serde_json::from_str(
+            get_request(txid_params.clone(), None, storage.clone())
+                .wait()
+                .unwrap()
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!("disputed", resp["annotation"]["labels"][0]);
+
+        // requests filtered by label in the order of start height and txid
+        let params: Params = serde_json::from_str(r#"{"label": "disputed"}"#).unwrap();
+        let resp: Value = serde_json::from_str(
+            get_requests(params.clone(), None, storage.clone())
+                .wait()
+                .unwrap()
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap();
+        let requests = resp["requests"].as_array().unwrap();
+        assert_eq!(2, requests.len());
+        assert_eq!(1, resp["pages"]);
+        assert_eq!(gen_dummy_hash(1).to_string(), requests[0]["request"]["txid"]);
+        assert_eq!(gen_dummy_hash(3).to_string(), requests[1]["request"]["txid"]);
+        assert_eq!("guardnode offline", requests[0]["annotation"]["notes"]);
+        let resp = get_requests(
+            serde_json::from_str(r#"{"label": "disputed", "page": 2}"#).unwrap(),
+            None,
+            storage.clone(),
+        );
+        assert_eq!(r#"{"requests":[],"pages":1}"#, resp.wait().unwrap());
+        let resp = get_requests(
+            serde_json::from_str(r#"{"label": "other"}"#).unwrap(),
+            None,
+            storage.clone(),
+        );
+        assert_eq!(r#"{"requests":[],"pages":0}"#, resp.wait().unwrap());
+        let resp = get_requests(
+            serde_json::from_str(r#"{"label": "disputed", "sort": "updated"}"#).unwrap(),
+            None,
+            storage.clone(),
+        );
+        assert_eq!(
+            "Invalid params: `label` can not be used with `cursor` or `sort`.",
+            resp.wait().unwrap_err().message
+        );
+
+        // replacing the annotation clears the notes and the removed labels
+        let params: Params = serde_json::from_str(&format!(
+            r#"{{"txid": "{}", "labels": ["vip"]}}"#,
+            gen_dummy_hash(1).to_string()
+        ))
+        .unwrap();
+        let resp = set_request_annotation(params, None, storage.clone());
+        assert_eq!(r#"{"labels":["vip"]}"#, resp.wait().unwrap());
+        assert_eq!(
+            vec![gen_dummy_hash(3)],
+            storage.get_labelled_requests("disputed").unwrap()
+        );
+
+        // invalid annotations
+        let params: Params =
+            serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, gen_dummy_hash(5).to_string())).unwrap();
+        let resp = set_request_annotation(params, None, storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+        let params: Params = serde_json::from_str(&format!(
+            r#"{{"txid": "{}", "labels": ["{}"]}}"#,
+            gen_dummy_hash(1).to_string(),
+            "a".repeat(API_ANNOTATION_MAX_LABEL_LEN + 1)
+        ))
+        .unwrap();
+        let resp = set_request_annotation(params, None, storage.clone());
+        assert_eq!(
+            "Invalid params: `labels` must be at most 64 chars each.",
+            resp.wait().unwrap_err().message
+        );
+        let labels: Vec<String> = (0..API_ANNOTATION_MAX_LABELS + 1).map(|i| i.to_string()).collect();
+        let params: Params = serde_json::from_str(
+            &serde_json::json!({"txid": gen_dummy_hash(1).to_string(), "labels": labels}).to_string(),
+        )
+        .unwrap();
+        let resp = set_request_annotation(params, None, storage.clone());
+        assert_eq!(
+            "Invalid params: `labels` must be at most 16.",
+            resp.wait().unwrap_err().message
+        );
+        let params: Params = serde_json::from_str(
+            &serde_json::json!({"txid": gen_dummy_hash(1).to_string(), "notes": "a".repeat(API_ANNOTATION_MAX_NOTES_LEN + 1)})
+                .to_string(),
+        )
+        .unwrap();
+        let resp = set_request_annotation(params, None, storage.clone());
+        assert_eq!(
+            "Invalid params: `notes` must be at most 4096 chars.",
+            resp.wait().unwrap_err().message
+        );
+
+        // not available to tenants
+        let resp = set_request_annotation(txid_params.clone(), tenant, storage.clone());
+        assert_eq!(
+            "Invalid request: request annotations not available to tenants.",
+            resp.wait().unwrap_err().message
+        );
+        let resp = get_request_annotation(txid_params.clone(), tenant, storage.clone());
+        assert_eq!(
+            "Invalid request: request annotations not available to tenants.",
+            resp.wait().unwrap_err().message
+        );
+        let resp = get_requests(
+            serde_json::from_str(r#"{"label": "vip"}"#).unwrap(),
+            tenant,
+            storage.clone(),
+        );
+        assert_eq!(
+            "Invalid request: request labels not available to tenants.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn get_wallet_status_test() {
+        setup_logger();
+        let status = Arc::new(RwLock::new(None));
+
+        // no status yet
+        let resp = get_wallet_status(None, &status);
+        assert_eq!(r#"{"status":null}"#, resp.wait().unwrap());
+
+        // status with alert
+        *status.write().unwrap() = Some(BalanceStatus {
+            challenge_balance: Amount::from_btc(1.0).unwrap(),
+            challenge_projected: Amount::from_btc(0.5).unwrap(),
+            payment_balance: Amount::from_btc(2.0).unwrap(),
+            payment_projected: Amount::from_btc(3.0).unwrap(),
+            alerts: vec![BalanceAlert::LowPaymentAsset],
+        });
+        let resp = get_wallet_status(None, &status);
+        assert_eq!(
+            r#"{"status":{"challenge_balance":1.0,"challenge_projected":0.5,"payment_balance":2.0,"payment_projected":3.0,"alerts":["LowPaymentAsset"]}}"#,
+            resp.wait().unwrap()
+        );
+
+        // tenant scope
+        let resp = get_wallet_status(Some(gen_dummy_hash(0)), &status);
+        assert_eq!(
+            "Invalid request: wallet status not available to tenants.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn get_info_test() {
+        setup_logger();
+        let mut config = Config::default();
+        config.clientchain.genesis_hash = gen_dummy_hash(0).to_string();
+        config.clientchain.chain = "ocean_main".to_owned();
+        config.payment_epoch = Some(10);
+        config.tenants = vec![TenantConfig {
+            genesis_hash: gen_dummy_hash(1).to_string(),
+            api_user: "tenant".to_owned(),
+            api_pass: "pass".to_owned(),
+            asset: None,
+            asset_key: None,
+            payment_asset: None,
+            fee_assets: None,
+            fee_percentage: None,
+            challenge_payload: None,
+            challenge_commit_reveal: None,
+            api_rate_limit: None,
+        }];
+        let info = CoordinatorInfo::from_config(&config);
+        assert_eq!(env!("CARGO_PKG_VERSION"), info.version);
+        assert!(!info.features.payments);
+        assert!(info.features.payment_epochs);
+        assert_eq!("p2pkh", info.features.payout_address_type);
+        assert_eq!(vec![1, 2], info.proof_versions);
+        assert_eq!("none", info.proof_payload);
+        assert_eq!(
+            vec![gen_dummy_hash(0).to_string(), gen_dummy_hash(1).to_string()],
+            info.genesis_hashes
+        );
+
+        let resp: Value = serde_json::from_str(get_info(None, &info).wait().unwrap().as_str().unwrap()).unwrap();
+        assert_eq!("ocean_main", resp["info"]["chain"]);
+        assert_eq!(2, resp["info"]["genesis_hashes"].as_array().unwrap().len());
+        assert_eq!(serde_json::json!(["ecdsa"]), resp["info"]["proof_sigtypes"]);
+
+        // tenant scope
+        let resp: Value = serde_json::from_str(
+            get_info(Some(gen_dummy_hash(1)), &info)
+                .wait()
+                .unwrap()
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::json!([gen_dummy_hash(1).to_string()]),
+            resp["info"]["genesis_hashes"]
+        );
+    }
+
+    #[test]
+    fn api_pool_test() {
+        setup_logger();
+        let pool = ApiPool::new(1, 1, Some(Duration::from_millis(50)));
+        let resp = pool.call("test", || futures::finished(Value::from(1)));
+        assert_eq!(Value::from(1), resp.wait().unwrap());
+
+        // block the worker thread until released
+        let (started_tx, started_rx) = channel();
+        let (release_tx, release_rx) = channel::<()>();
+        let blocked = pool.call("blocked", move || {
+            started_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+            futures::finished(Value::from(2))
+        });
+        started_rx.recv().unwrap();
+
+        // queued call and call rejected as the queue is full
+        let queued = pool.call("queued", || futures::finished(Value::from(3)));
+        let rejected = pool.call("rejected", || futures::finished(Value::from(4)));
+        assert_eq!("Api queue full.", rejected.wait().unwrap_err().message);
+        assert_eq!(1, pool.stats().queue_depth);
+
+        // queued call times out waiting for the worker thread
+        thread::sleep(Duration::from_millis(60));
+        release_tx.send(()).unwrap();
+        assert_eq!(Value::from(2), blocked.wait().unwrap());
+        let err = queued.wait().unwrap_err();
+        assert_eq!(ErrorCode::ServerError(API_ERROR_TIMEOUT), err.code);
+        assert_eq!("Request timed out.", err.message);
+
+        assert_eq!(
+            ApiStats {
+                threads: 1,
+                queue_size: 1,
+                queue_depth: 0,
+                max_queue_depth: 1,
+                completed: 2,
+                rejected: 1,
+                timed_out: 1,
+            },
+            pool.stats()
+        );
+
+        // admin only stats
+        let rate_limits = ApiRateLimits::new(&ApiConfig::default(), &[]);
+        let resp = get_api_stats(None, &pool, &rate_limits);
+        let resp: Value = serde_json::from_str(resp.wait().unwrap().as_str().unwrap()).unwrap();
+        assert_eq!(2, resp["stats"]["completed"]);
+        let resp = get_api_stats(Some(gen_dummy_hash(0)), &pool, &rate_limits);
+        assert_eq!(
+            "Invalid request: api stats not available to tenants.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn get_rpc_stats_test() {
+        setup_logger();
+        let stats = RpcStats::new(100);
+        let _ = stats.record("getblockcount", 150, true);
+        let _ = stats.record("getblockcount", 50, false);
+
+        let resp = get_rpc_stats(None, &stats);
+        assert_eq!(
+            r#"{"slow_call_ms":100,"methods":{"getblockcount":{"calls":2,"errors":1,"slow":1,"total_ms":200,"max_ms":150}}}"#,
+            resp.wait().unwrap()
+        );
+
+        // admin only stats
+        let resp = get_rpc_stats(Some(gen_dummy_hash(0)), &stats);
+        assert_eq!(
+            "Invalid request: rpc stats not available to tenants.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn get_chain_status_test() {
+        setup_logger();
+        let status = Arc::new(RwLock::new(None));
+        let resp = get_chain_status(&status);
+        assert_eq!(r#"{"degraded":null}"#, resp.wait().unwrap());
+
+        *status.write().unwrap() = Some(DegradedStatus {
+            since: 100,
+            error: "connection refused".to_owned(),
+            service_reachable: false,
+            clientchain_reachable: true,
+            checks: 3,
+        });
+        let resp = get_chain_status(&status);
+        assert_eq!(
+            r#"{"degraded":{"since":100,"error":"connection refused","service_reachable":false,"clientchain_reachable":true,"checks":3}}"#,
+            resp.wait().unwrap()
+        );
+    }
+
+    #[test]
+    fn set_challenges_paused_test() {
+        setup_logger();
+        let paused = AtomicBool::new(false);
+
+        let resp = set_challenges_paused(None, &paused, true);
+        assert_eq!(r#"{"paused":true}"#, resp.wait().unwrap());
+        assert!(paused.load(Ordering::SeqCst));
+
+        let resp = set_challenges_paused(None, &paused, false);
+        assert_eq!(r#"{"paused":false}"#, resp.wait().unwrap());
+        assert!(!paused.load(Ordering::SeqCst));
+
+        // tenant scope
+        let resp = set_challenges_paused(Some(gen_dummy_hash(0)), &paused, true);
+        assert_eq!(
+            "Invalid request: challenge pausing not available to tenants.",
+            resp.wait().unwrap_err().message
+        );
+        assert!(!paused.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn refresh_bids_call_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let service = Arc::new(MockService::new());
+        let challenge = RwLock::new(None);
+
+        // no active request
+        let resp = refresh_bids_call(None, &challenge, service.clone(), storage.clone());
+        assert_eq!("Invalid request: no active request.", resp.wait().unwrap_err().message);
+
+        // new bid of the active request added
+        let state = gen_challenge_state(&gen_dummy_hash(1));
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        *challenge.write().unwrap() = Some(state.clone());
+        let resp = refresh_bids_call(None, &challenge, service.clone(), storage.clone());
+        let value: Value = serde_json::from_str(resp.wait().unwrap().as_str().unwrap()).unwrap();
+        assert_eq!(state.request.txid.to_string(), value["refresh"]["request"]);
+        assert_eq!(1, value["refresh"]["added"].as_array().unwrap().len());
+        assert_eq!(0, value["refresh"]["removed"].as_array().unwrap().len());
+        assert_eq!(2, value["refresh"]["num_bids"]);
+        assert_eq!(2, challenge.read().unwrap().as_ref().unwrap().num_bids());
+
+        // tenant scope
+        let resp = refresh_bids_call(Some(gen_dummy_hash(0)), &challenge, service.clone(), storage.clone());
+        assert_eq!(
+            "Invalid request: bid refresh not available to tenants.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn get_request_payments_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, dummy_hash)).unwrap();
+
+        // no such request
+        let resp = get_request_payments(params.clone(), None, storage.clone(), Some(1));
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // request paid at the end
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let resp = get_request_payments(params.clone(), None, storage.clone(), None);
+        assert_eq!(r#"{"schedule":[5],"epochs":[]}"#, resp.wait().unwrap());
+
+        // request paid per epoch
+        let mut response = RequestResponse::new();
+        response.num_challenges = 1;
+        storage
+            .save_response_snapshot(
+                dummy_hash,
+                &ResponseSnapshot {
+                    epoch: 0,
+                    service_height: 3,
+                    clientchain_height: 10,
+                    response,
+                    bids: vec![],
+                    is_payment_complete: true,
+                },
+            )
+            .unwrap();
+        let resp = get_request_payments(params.clone(), None, storage.clone(), Some(1));
+        assert_eq!(
+            r#"{"schedule":[3,4,5],"epochs":[{"epoch":0,"service_height":3,"clientchain_height":10,"response":{"num_challenges":1,"bid_responses":{}},"bids":[],"is_payment_complete":true}]}"#,
+            resp.wait().unwrap()
+        );
+
+        // tenant scope
+        let resp = get_request_payments(params, Some(gen_dummy_hash(9)), storage.clone(), Some(1));
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn get_challenge_schedule_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let mut service = MockService::new();
+        let schedule_heights = |value: Value| -> (u64, Vec<u64>) {
+            let value: Value = serde_json::from_str(value.as_str().unwrap()).unwrap();
+            let heights = value["schedule"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|challenge| challenge["height"].as_u64().unwrap())
+                .collect();
+            (value["service_height"].as_u64().unwrap(), heights)
+        };
+
+        // upcoming request on the service chain
+        let upcoming_txid = service.request.borrow().txid;
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, upcoming_txid)).unwrap();
+        let service_ref = Arc::new(service);
+        let resp = get_challenge_schedule(params.clone(), None, storage.clone(), service_ref.clone(), 2, 60);
+        assert_eq!((0, vec![2, 4]), schedule_heights(resp.wait().unwrap()));
+
+        // active request in storage
+        let dummy_hash = gen_dummy_hash(1);
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let stored_params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, dummy_hash)).unwrap();
+        let _ = service_ref.height.replace(3);
+        let resp = get_challenge_schedule(stored_params.clone(), None, storage.clone(), service_ref.clone(), 1, 60);
+        assert_eq!((3, vec![3, 4, 5]), schedule_heights(resp.wait().unwrap()));
+
+        // tenant scope
+        let resp = get_challenge_schedule(
+            stored_params.clone(),
+            Some(gen_dummy_hash(9)),
+            storage.clone(),
+            service_ref.clone(),
+            1,
+            60,
+        );
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // no such request
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, gen_dummy_hash(7))).unwrap();
+        let resp = get_challenge_schedule(params, None, storage.clone(), service_ref.clone(), 1, 60);
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+
+        // service failure
+        service = MockService::new();
+        service.return_err = true;
+        let resp = get_challenge_schedule(stored_params, None, storage.clone(), Arc::new(service), 1, 60);
+        assert_eq!(Error::internal_error(), resp.wait().unwrap_err());
+    }
+
+    #[test]
+    fn submit_challenge_proof_test() {
+        setup_logger();
+        let (resp_tx, resp_rx): (Sender<ChallengeResponse>, Receiver<ChallengeResponse>) = channel();
+        let resp_tx = Mutex::new(resp_tx);
+        let chl_hash = gen_dummy_hash(8);
+        let _challenge_state = gen_challenge_state_with_challenge(&gen_dummy_hash(1), &chl_hash);
+        let bid = _challenge_state.bids.iter().next().unwrap().clone();
+        let challenge_state = Arc::new(RwLock::new(Some(_challenge_state)));
+        let blacklist = Blacklist::new(2, 600);
+
+        let secp = Secp256k1::new();
+        let sign = |key: u8| {
+            secp.sign(
+                &Message::from_slice(&serialize(&chl_hash)).unwrap(),
+                &SecretKey::from_slice(&[key; 32]).unwrap(),
+            )
+            .serialize_der()
+            .to_hex()
+        };
+        let proof_params = |hash: &sha256d::Hash, sig: &str| -> Params {
+            serde_json::from_str(&format!(
+                r#"{{"txid": "{}", "pubkey": "{}", "hash": "{}", "sig": "{}"}}"#,
+                bid.txid, bid.pubkey, hash, sig
+            ))
+            .unwrap()
+        };
+
+        // missing proof data
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, bid.txid)).unwrap();
+        let resp = submit_challenge_proof(params, &challenge_state, &resp_tx, &Journal::disabled(), &blacklist);
+        assert!(resp.wait().unwrap_err().message.contains("bad-proof-data"));
+
+        // bad hash
+        let resp = submit_challenge_proof(
+            proof_params(&gen_dummy_hash(9), &sign(0xaa)),
+            &challenge_state,
+            &resp_tx,
+            &Journal::disabled(),
+            &blacklist,
+        );
+        assert_eq!("bad-hash", resp.wait().unwrap_err().message);
+
+        // bad sig
+        let resp = submit_challenge_proof(
+            proof_params(&chl_hash, &sign(0xbb)),
+            &challenge_state,
+            &resp_tx,
+            &Journal::disabled(),
+            &blacklist,
         );
-        let resp = get_requests(Params::None, storage.clone());
-        assert_eq!(resp_2, resp.wait().unwrap());
-        let resp = get_requests(params_p1.clone(), storage.clone());
-        assert_eq!(resp_2, resp.wait().unwrap());
-        let resp = get_requests(params_m1.clone(), storage.clone());
-        assert_eq!(resp_2, resp.wait().unwrap());
-        let resp = get_requests(params_p2.clone(), storage.clone());
-        assert_eq!(r#"{"requests":[],"pages":1}"#, resp.wait().unwrap());
-        let resp = get_requests(params_p5.clone(), storage.clone());
-        assert_eq!(r#"{"requests":[],"pages":1}"#, resp.wait().unwrap());
+        assert!(resp.wait().unwrap_err().message.contains("bad-sig"));
+        assert!(resp_rx.try_recv() == Err(TryRecvError::Empty)); // check receiver empty
 
-        // save actual state for 10 more requests (12 total)
-        for i in 3..=12 {
-            let dummy_hashi = gen_dummy_hash(i);
-            let statei = gen_challenge_state(&dummy_hashi);
-            storage
-                .save_challenge_request_state(&statei.request, &statei.bids)
-                .unwrap();
+        // valid proof forwarded to the challenger
+        let resp = submit_challenge_proof(
+            proof_params(&chl_hash, &sign(0xaa)),
+            &challenge_state,
+            &resp_tx,
+            &Journal::disabled(),
+            &blacklist,
+        );
+        assert_eq!(Value::Bool(true), resp.wait().unwrap());
+        assert!(resp_rx.try_recv() == Ok(ChallengeResponse(chl_hash, bid.clone())));
+
+        // no active challenge
+        let latest_challenge = challenge_state
+            .read()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .latest_challenge
+            .clone();
+        latest_challenge.clear();
+        let resp = submit_challenge_proof(
+            proof_params(&chl_hash, &sign(0xaa)),
+            &challenge_state,
+            &resp_tx,
+            &Journal::disabled(),
+            &blacklist,
+        );
+        assert_eq!("no-active-challenge", resp.wait().unwrap_err().message);
+        assert!(resp_rx.try_recv() == Err(TryRecvError::Empty)); // check receiver empty
+        latest_challenge.set(chl_hash, Instant::now());
+
+        // repeated bad sigs blacklist the bid, the valid proof having reset
+        // the strikes of the earlier bad sig
+        for _ in 0..2 {
+            let resp = submit_challenge_proof(
+                proof_params(&chl_hash, &sign(0xbb)),
+                &challenge_state,
+                &resp_tx,
+                &Journal::disabled(),
+                &blacklist,
+            );
+            assert!(resp.wait().unwrap_err().message.contains("bad-sig"));
         }
-        let resp_10 = format!(
-            r#"{{"requests":[{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}}],"pages":2}}"#,
-            gen_dummy_hash(1).to_string(),
-            gen_dummy_hash(2).to_string(),
-            gen_dummy_hash(3).to_string(),
-            gen_dummy_hash(4).to_string(),
-            gen_dummy_hash(5).to_string(),
-            gen_dummy_hash(6).to_string(),
-            gen_dummy_hash(7).to_string(),
-            gen_dummy_hash(8).to_string(),
-            gen_dummy_hash(9).to_string(),
-            gen_dummy_hash(10).to_string(),
+        let resp = submit_challenge_proof(
+            proof_params(&chl_hash, &sign(0xaa)),
+            &challenge_state,
+            &resp_tx,
+            &Journal::disabled(),
+            &blacklist,
         );
-        let resp_12 = format!(
-            r#"{{"requests":[{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}},{{"request":{{"txid":"{}","start_blockheight":2,"end_blockheight":5,"genesis_blockhash":"0000000000000000000000000000000000000000000000000000000000000000","fee_percentage":5,"num_tickets":10,"start_blockheight_clientchain":0,"end_blockheight_clientchain":0,"is_payment_complete":false}},"bids":[{{"txid":"1234567890000000000000000000000000000000000000000000000000000000","pubkey":"026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3","payment":null}}]}}],"pages":2}}"#,
-            gen_dummy_hash(11).to_string(),
-            gen_dummy_hash(12).to_string(),
+        assert!(resp.wait().unwrap_err().message.contains("bid-blacklisted"));
+        assert!(resp_rx.try_recv() == Err(TryRecvError::Empty)); // check receiver empty
+    }
+
+    #[test]
+    fn rotate_bid_key_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let _challenge_state = gen_challenge_state(&gen_dummy_hash(1));
+        storage
+            .save_challenge_request_state(&_challenge_state.request, &_challenge_state.bids)
+            .unwrap();
+        let bid = _challenge_state.bids.iter().next().unwrap().clone();
+        let challenge_state = Arc::new(RwLock::new(Some(_challenge_state)));
+        let new_pubkey = "0268680737c76dabb801cb2204f57dbe4e4579e4f710cd67dc1b4227592c81e9b5";
+
+        let message = KeyRotation::signed_message(
+            &gen_dummy_hash(1),
+            &bid.txid,
+            &secp256k1::PublicKey::from_str(new_pubkey).unwrap(),
+        )
+        .unwrap();
+        let sig = Secp256k1::new()
+            .sign(&message, &SecretKey::from_slice(&[0xaa; 32]).unwrap())
+            .serialize_der()
+            .to_hex();
+        let params: Params = serde_json::from_str(&format!(
+            r#"{{"request": "{}", "txid": "{}", "pubkey": "{}", "sig": "{}"}}"#,
+            gen_dummy_hash(1),
+            bid.txid,
+            new_pubkey,
+            sig
+        ))
+        .unwrap();
+
+        // bad params
+        let bad_params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, bid.txid)).unwrap();
+        let resp = rotate_bid_key_call(
+            bad_params,
+            None,
+            KeyRotationPolicy::OldKey,
+            &challenge_state,
+            &Journal::disabled(),
+            storage.clone(),
         );
-        let resp = get_requests(Params::None, storage.clone());
-        assert_eq!(resp_10, resp.wait().unwrap());
-        let resp = get_requests(params_p1.clone(), storage.clone());
-        assert_eq!(resp_10, resp.wait().unwrap());
-        let resp = get_requests(params_m1.clone(), storage.clone());
-        assert_eq!(resp_10, resp.wait().unwrap());
-        let resp = get_requests(params_p2.clone(), storage.clone());
-        assert_eq!(resp_12, resp.wait().unwrap());
-        let resp = get_requests(params_p5.clone(), storage.clone());
-        assert_eq!(r#"{"requests":[],"pages":2}"#, resp.wait().unwrap());
+        assert!(resp.wait().unwrap_err().message.contains("bad key rotation"));
+
+        // disabled or out of tenant scope
+        let resp = rotate_bid_key_call(
+            params.clone(),
+            None,
+            KeyRotationPolicy::Disabled,
+            &challenge_state,
+            &Journal::disabled(),
+            storage.clone(),
+        );
+        assert_eq!("key-rotation-disabled", resp.wait().unwrap_err().message);
+        let resp = rotate_bid_key_call(
+            params.clone(),
+            Some(gen_dummy_hash(9)),
+            KeyRotationPolicy::OldKey,
+            &challenge_state,
+            &Journal::disabled(),
+            storage.clone(),
+        );
+        assert_eq!("bad-request", resp.wait().unwrap_err().message);
+
+        // rotation by the tenant of the request
+        let resp = rotate_bid_key_call(
+            params,
+            Some(gen_dummy_hash(0)),
+            KeyRotationPolicy::OldKey,
+            &challenge_state,
+            &Journal::disabled(),
+            storage.clone(),
+        );
+        let rotation: Value = serde_json::from_str(resp.wait().unwrap().as_str().unwrap()).unwrap();
+        assert_eq!(new_pubkey, rotation["rotation"]["new_pubkey"]);
+        assert_eq!(bid.pubkey.to_string(), rotation["rotation"]["old_pubkey"]);
+        assert_eq!("old-key", rotation["rotation"]["policy"]);
+        assert!(challenge_state
+            .read()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .bids
+            .iter()
+            .any(|bid| bid.pubkey.to_string() == new_pubkey));
+
+        // audit trail
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, gen_dummy_hash(1))).unwrap();
+        let resp = get_bid_key_rotations(params.clone(), None, storage.clone());
+        let rotations: Value = serde_json::from_str(resp.wait().unwrap().as_str().unwrap()).unwrap();
+        assert_eq!(1, rotations["rotations"].as_array().unwrap().len());
+        assert_eq!(rotation["rotation"], rotations["rotations"][0]);
+        let resp = get_bid_key_rotations(params.clone(), Some(gen_dummy_hash(9)), storage.clone());
+        assert!(resp.wait().is_err());
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, gen_dummy_hash(9))).unwrap();
+        let resp = get_bid_key_rotations(params, None, storage.clone());
+        assert!(resp.wait().is_err());
     }
 
     #[test]
-    fn get_request_response_test() {
+    fn get_bid_refunds_test() {
         setup_logger();
         let storage = Arc::new(MockStorage::new());
-        let dummy_hash = gen_dummy_hash(1);
-        let dummy_hash_bid = gen_dummy_hash(2);
 
-        // no such request
-        let s = format!(r#"{{"txid": "{}"}}"#, dummy_hash.to_string());
-        let params: Params = serde_json::from_str(&s).unwrap();
-        let resp = get_request_response(params, storage.clone());
+        // no refunds
+        let resp = get_bid_refunds(None, storage.clone());
+        assert_eq!(r#"{"refunds":[]}"#, resp.wait().unwrap());
+
+        // exported refund
+        let refund = BidRefund {
+            txid: gen_dummy_hash(1),
+            request: gen_dummy_hash(2),
+            genesis_blockhash: gen_dummy_hash(0),
+            lock_height: 10,
+            amount: Amount::from_btc(1.0).unwrap(),
+            address: "2dt74vJqpNTM12pbiNpHGvry7tyvUu9tJR8".to_owned(),
+            state: BidRefundState::Exported,
+            refund_txid: None,
+            tx_hex: "0200".to_owned(),
+        };
+        storage.save_bid_refund(&refund).unwrap();
+        let expected = format!(
+            r#"{{"refunds":[{{"txid":"{}","request":"{}","genesis_blockhash":"{}","lock_height":10,"amount":1.0,"address":"2dt74vJqpNTM12pbiNpHGvry7tyvUu9tJR8","state":"exported","refund_txid":null,"tx_hex":"0200"}}]}}"#,
+            gen_dummy_hash(1),
+            gen_dummy_hash(2),
+            gen_dummy_hash(0)
+        );
+        let resp = get_bid_refunds(Some(gen_dummy_hash(0)), storage.clone());
+        assert_eq!(expected, resp.wait().unwrap());
+
+        // other tenant
+        let resp = get_bid_refunds(Some(gen_dummy_hash(9)), storage.clone());
+        assert_eq!(r#"{"refunds":[]}"#, resp.wait().unwrap());
+    }
+
+    #[test]
+    fn get_blacklist_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let blacklist = Blacklist::new(1, 600);
+        let request = gen_challenge_state(&gen_dummy_hash(1)).request;
+
+        // no blacklistings
+        let resp = get_blacklist(Params::None, None, &blacklist, storage.clone());
+        assert_eq!(r#"{"blacklistings":[]}"#, resp.wait().unwrap());
+
+        // active blacklisting
+        let blacklisting = blacklist.strike(&gen_dummy_hash(5), &request).unwrap();
+        let resp = get_blacklist(Params::None, None, &blacklist, storage.clone());
         assert_eq!(
-            "Invalid params: `txid` does not exist.",
-            resp.wait().unwrap_err().message
+            serde_json::to_string(&GetBlacklistResponse {
+                blacklistings: vec![blacklisting.clone()]
+            })
+            .unwrap(),
+            resp.wait().unwrap()
         );
 
-        let mut dummy_response_set = HashSet::new();
-        let _ = dummy_response_set.insert(dummy_hash_bid);
-        let mut dummy_response = RequestResponse::new();
-        dummy_response.update(&dummy_response_set);
-        let _ = storage.save_response(dummy_hash, &dummy_response);
+        // tenant scope
+        let resp = get_blacklist(Params::None, Some(gen_dummy_hash(9)), &blacklist, storage.clone());
+        assert_eq!(r#"{"blacklistings":[]}"#, resp.wait().unwrap());
 
-        // invalid key
-        let s = format!(r#"{{"hash": "{}"}}"#, dummy_hash.to_string());
-        let params: Params = serde_json::from_str(&s).unwrap();
-        let resp = get_request_response(params, storage.clone());
+        // stored history including expired blacklistings
+        let mut expired = blacklisting.clone();
+        expired.since = 1;
+        expired.until = 2;
+        storage.save_bid_blacklisting(&expired).unwrap();
+        let params: Params = serde_json::from_str(r#"{"history": true}"#).unwrap();
+        let resp = get_blacklist(params, Some(request.genesis_blockhash), &blacklist, storage.clone());
         assert_eq!(
-            "Invalid params: missing field `txid`.",
-            resp.wait().unwrap_err().message
+            serde_json::to_string(&GetBlacklistResponse {
+                blacklistings: vec![expired]
+            })
+            .unwrap(),
+            resp.wait().unwrap()
         );
+    }
 
-        // invalid value
-        let s = format!(r#"{{"txid": "{}a"}}"#, dummy_hash.to_string());
+    #[test]
+    fn get_challenge_tx_test() {
+        setup_logger();
+        let mut clientchain = MockClientChain::new();
+        let dummy_hash = gen_dummy_hash(1);
+
+        // invalid key
+        let s = format!(r#"{{"txid": "{}"}}"#, dummy_hash.to_string());
         let params: Params = serde_json::from_str(&s).unwrap();
-        let resp = get_request_response(params, storage.clone());
+        let resp = get_challenge_tx(params, Arc::new(MockClientChain::new()));
         assert_eq!(
-            "Invalid params: odd hex string length 65.",
+            "Invalid params: missing field `hash`.",
             resp.wait().unwrap_err().message
         );
 
         // valid key and value
-        let s = format!(r#"{{"txid": "{}"}}"#, dummy_hash.to_string());
+        let s = format!(r#"{{"hash": "{}"}}"#, dummy_hash.to_string());
         let params: Params = serde_json::from_str(&s).unwrap();
-        let resp = get_request_response(params, storage.clone());
+        let resp = get_challenge_tx(params.clone(), Arc::new(MockClientChain::new()));
         assert_eq!(
             format!(
-                r#"{{"response":{{"num_challenges":1,"bid_responses":{{"{}":1}}}}}}"#,
-                dummy_hash_bid.to_string()
+                r#"{{"challenge":{{"hex":"00","decoded":{{"txid":"{}"}}}}}}"#,
+                dummy_hash.to_string()
             ),
             resp.wait().unwrap()
         );
+
+        // clientchain failure
+        clientchain.return_err = true;
+        let resp = get_challenge_tx(params, Arc::new(clientchain));
+        assert_eq!(
+            "Invalid params: `hash` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+    }
+
+    #[test]
+    fn list_methods_test() {
+        setup_logger();
+        let resp: Value = serde_json::from_str(list_methods().wait().unwrap().as_str().unwrap()).unwrap();
+        let methods = resp["methods"].as_array().unwrap();
+        let names: HashSet<&str> = methods.iter().map(|method| method["name"].as_str().unwrap()).collect();
+        assert_eq!(methods.len(), names.len());
+        assert_eq!(40, names.len());
+        assert!(names.contains("listmethods"));
+
+        let get_request = methods.iter().find(|method| method["name"] == "getrequest").unwrap();
+        assert_eq!("string", get_request["params"]["properties"]["txid"]["type"]);
+        let request = &get_request["response"]["properties"]["request"];
+        assert_eq!("integer", request["properties"]["start_blockheight"]["type"]);
+        assert_eq!(
+            "number",
+            request["properties"]["response_summary"]["properties"]["response_rate"]["type"]
+        );
+        let bid = &get_request["response"]["properties"]["bids"]["items"];
+        assert_eq!("number", bid["properties"]["payment"]["properties"]["amount"]["type"]);
+        let basis = &bid["properties"]["payment"]["properties"]["basis"];
+        assert_eq!(6, basis["required"].as_array().unwrap().len());
+        assert_eq!("number", basis["properties"]["fees_amount"]["type"]);
+        let payment_tx = &bid["properties"]["payment"]["properties"]["txs"]["items"];
+        assert_eq!("number", payment_tx["properties"]["amount"]["type"]);
+        assert_eq!("integer", payment_tx["properties"]["confirmations"]["type"]);
+
+        let get_request_response = methods
+            .iter()
+            .find(|method| method["name"] == "getrequestresponse")
+            .unwrap();
+        let bid_responses = &get_request_response["response"]["properties"]["response"]["properties"]["bid_responses"];
+        assert_eq!("integer", bid_responses["additionalProperties"]["type"]);
+
+        let submit = methods
+            .iter()
+            .find(|method| method["name"] == "submitchallengeproof")
+            .unwrap();
+        assert_eq!("boolean", submit["response"]["type"]);
+        assert_eq!(4, submit["params"]["required"].as_array().unwrap().len());
     }
 
     #[test]
@@ -449,4 +5992,501 @@ mod tests {
             .unwrap();
         assert_eq!(true, authorize(our_auth, &request));
     }
+
+    #[test]
+    fn tenant_scope_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let dummy_hash = gen_dummy_hash(1);
+        let tenant_hash = gen_dummy_hash(9);
+
+        // request with zero genesis is not in scope of the tenant
+        let state = gen_challenge_state(&dummy_hash);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let _ = storage.save_response(dummy_hash, &RequestResponse::new());
+
+        let s = format!(r#"{{"txid": "{}"}}"#, dummy_hash.to_string());
+        let params: Params = serde_json::from_str(&s).unwrap();
+        let resp = get_request(params.clone(), Some(tenant_hash), storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+        let resp = get_request_response(params.clone(), Some(tenant_hash), storage.clone());
+        assert_eq!(
+            "Invalid params: `txid` does not exist.",
+            resp.wait().unwrap_err().message
+        );
+        let resp = get_requests(Params::None, Some(tenant_hash), storage.clone());
+        assert_eq!(r#"{"requests":[],"pages":0}"#, resp.wait().unwrap());
+
+        // request in scope of the tenant
+        let dummy_hash2 = gen_dummy_hash(2);
+        let mut state2 = gen_challenge_state(&dummy_hash2);
+        state2.request.genesis_blockhash = tenant_hash;
+        storage
+            .save_challenge_request_state(&state2.request, &state2.bids)
+            .unwrap();
+        let _ = storage.save_response(dummy_hash2, &RequestResponse::new());
+
+        let s = format!(r#"{{"txid": "{}"}}"#, dummy_hash2.to_string());
+        let params: Params = serde_json::from_str(&s).unwrap();
+        assert!(get_request(params.clone(), Some(tenant_hash), storage.clone())
+            .wait()
+            .is_ok());
+        assert!(get_request_response(params.clone(), Some(tenant_hash), storage.clone())
+            .wait()
+            .is_ok());
+        let resp: serde_json::Value = serde_json::from_str(
+            &get_requests(Params::None, Some(tenant_hash), storage.clone())
+                .wait()
+                .unwrap()
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(1, resp["requests"].as_array().unwrap().len());
+        assert_eq!(1, resp["pages"]);
+
+        // no tenant scope has access to all requests
+        let resp: serde_json::Value = serde_json::from_str(
+            &get_requests(Params::None, None, storage.clone())
+                .wait()
+                .unwrap()
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(2, resp["requests"].as_array().unwrap().len());
+    }
+
+    #[test]
+    fn tenant_bids_isolation_test() {
+        setup_logger();
+        let storage = Arc::new(MockStorage::new());
+        let (tenant_a, tenant_b) = (gen_dummy_hash(9), gen_dummy_hash(8));
+        let (request_a, request_b) = (gen_dummy_hash(1), gen_dummy_hash(2));
+        for (request_hash, tenant_hash) in [(request_a, tenant_a), (request_b, tenant_b)].iter() {
+            let mut state = gen_challenge_state(request_hash);
+            state.request.genesis_blockhash = *tenant_hash;
+            storage
+                .save_challenge_request_state(&state.request, &state.bids)
+                .unwrap();
+            let _ = storage.save_response(*request_hash, &RequestResponse::new());
+        }
+        let bid = storage.get_bids(request_b).unwrap()[0].txid;
+
+        // tenant a can not read the bids of the requests of tenant b
+        let params: Params = serde_json::from_str(&format!(r#"{{"txid": "{}"}}"#, request_b)).unwrap();
+        let bid_params: Params =
+            serde_json::from_str(&format!(r#"{{"txid": "{}", "bid": "{}"}}"#, request_b, bid)).unwrap();
+        for resp in vec![
+            get_request(params.clone(), Some(tenant_a), storage.clone()),
+            export_request(params.clone(), Some(tenant_a), storage.clone()),
+            get_request_response(params.clone(), Some(tenant_a), storage.clone()),
+            get_bid_key_rotations(params.clone(), Some(tenant_a), storage.clone()),
+            get_bid_activity(bid_params.clone(), Some(tenant_a), storage.clone()),
+        ] {
+            assert_eq!(
+                "Invalid params: `txid` does not exist.",
+                resp.wait().unwrap_err().message
+            );
+        }
+        assert!(get_request(params.clone(), Some(tenant_b), storage.clone())
+            .wait()
+            .is_ok());
+
+        // and only list the requests and bids of tenant a
+        let resp: GetRequestsResponse = serde_json::from_str(
+            &get_requests(Params::None, Some(tenant_a), storage.clone())
+                .wait()
+                .unwrap()
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            vec![request_a],
+            resp.requests
+                .iter()
+                .map(|request| request.request.txid)
+                .collect::<Vec<_>>()
+        );
+        let resp: GetRequestsFullResponse = serde_json::from_str(
+            &get_requests_full(Params::None, Some(tenant_a), storage.clone())
+                .wait()
+                .unwrap()
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            vec![request_a],
+            resp.requests
+                .iter()
+                .map(|request| request.request.txid)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn cors_origins_test() {
+        let origins = |config: &ApiConfig| match cors_origins(config) {
+            DomainsValidation::AllowOnly(origins) => origins,
+            DomainsValidation::Disabled => panic!("cors validation disabled"),
+        };
+
+        // null origin only by default
+        let mut config = ApiConfig::default();
+        assert_eq!(vec![AccessControlAllowOrigin::Null], origins(&config));
+
+        config.cors_origins = vec!["https://explorer.example.com".to_owned(), "null".to_owned()];
+        assert_eq!(
+            vec![
+                AccessControlAllowOrigin::from("https://explorer.example.com"),
+                AccessControlAllowOrigin::Null
+            ],
+            origins(&config)
+        );
+
+        // any origin in the allow all mode
+        config.cors_allow_all = true;
+        assert_eq!(vec![AccessControlAllowOrigin::Any], origins(&config));
+    }
+
+    #[test]
+    fn cors_allow_origin_test() {
+        let explorer = "https://explorer.example.com";
+        assert_eq!(Some("null".to_owned()), cors_allow_origin(false, &[], Some("null")));
+        assert_eq!(None, cors_allow_origin(false, &[], Some(explorer)));
+        assert_eq!(None, cors_allow_origin(false, &[], None));
+        let origins = vec![explorer.to_owned()];
+        assert_eq!(
+            Some(explorer.to_owned()),
+            cors_allow_origin(false, &origins, Some(explorer))
+        );
+        assert_eq!(None, cors_allow_origin(false, &origins, Some("null")));
+        assert_eq!(Some("*".to_owned()), cors_allow_origin(true, &[], None));
+    }
+
+    #[test]
+    fn direct_response_test() {
+        setup_logger();
+        let mut io = ApiIoHandler::new(Arc::new(ApiPool::new(1, 1, None)), 0, 2000);
+        io.add_method("getitems", |params: Params| {
+            let (count,): (usize,) = params.parse().unwrap();
+            futures::finished(Value::from(vec!["item"; count]))
+        });
+        let io = Arc::new(io.io);
+        let call = |count: usize| {
+            let body = format!(r#"{{"jsonrpc":"2.0","method":"getitems","params":[{}],"id":1}}"#, count);
+            Request::builder().method("POST").body(Body::from(body)).unwrap()
+        };
+        let read_body = |resp: hyper::Response<Body>| resp.into_body().concat2().wait().unwrap().to_vec();
+
+        // small responses are not compressed
+        let gzip = Some((Encoding::Gzip, 100));
+        let resp = direct_response(io.clone(), call(2), ApiMeta::admin(), gzip, None, None)
+            .wait()
+            .unwrap();
+        assert_eq!(StatusCode::OK, resp.status());
+        assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(
+            r#"{"jsonrpc":"2.0","result":["item","item"],"id":1}"#,
+            str::from_utf8(&read_body(resp)).unwrap().trim()
+        );
+
+        // large responses are compressed with the negotiated encoding
+        let resp = direct_response(
+            io.clone(),
+            call(100),
+            ApiMeta::admin(),
+            gzip,
+            None,
+            Some("null".to_owned()),
+        )
+        .wait()
+        .unwrap();
+        assert_eq!("gzip", resp.headers().get(header::CONTENT_ENCODING).unwrap());
+        assert_eq!("null", resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap());
+        let compressed = read_body(resp);
+        let mut content = String::new();
+        let _ = flate2::read::GzDecoder::new(&compressed[..])
+            .read_to_string(&mut content)
+            .unwrap();
+        let content: Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(100, content["result"].as_array().unwrap().len());
+
+        // responses above the max response size are replaced by an error
+        let resp = direct_response(io.clone(), call(1000), ApiMeta::admin(), gzip, None, None)
+            .wait()
+            .unwrap();
+        let compressed = read_body(resp);
+        let mut content = String::new();
+        let _ = flate2::read::GzDecoder::new(&compressed[..])
+            .read_to_string(&mut content)
+            .unwrap();
+        let content: Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(API_ERROR_RESPONSE_TOO_LARGE, content["error"]["code"].as_i64().unwrap());
+        assert_eq!(1, content["id"]);
+
+        // uncompressed responses with the rate limit quota of the call
+        let quota = Quota {
+            limit: 10,
+            remaining: 9,
+            reset: 60,
+        };
+        let resp = direct_response(io.clone(), call(100), ApiMeta::admin(), None, Some(quota), None)
+            .wait()
+            .unwrap();
+        assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+        assert_eq!("10", resp.headers().get("x-ratelimit-limit").unwrap());
+        assert_eq!("9", resp.headers().get("x-ratelimit-remaining").unwrap());
+        assert_eq!("60", resp.headers().get("x-ratelimit-reset").unwrap());
+        let content: Value = serde_json::from_slice(&read_body(resp)).unwrap();
+        assert_eq!(100, content["result"].as_array().unwrap().len());
+    }
+
+    #[test]
+    fn batch_request_test() {
+        setup_logger();
+        let mut io = ApiIoHandler::new(Arc::new(ApiPool::new(1, 3, None)), 3, 0);
+        io.add_method("getitems", |params: Params| {
+            let (count,): (usize,) = params.parse().unwrap();
+            futures::finished(Value::from(vec!["item"; count]))
+        });
+        let call = |id: usize, method: &str| {
+            format!(
+                r#"{{"jsonrpc":"2.0","method":"{}","params":[{}],"id":{}}}"#,
+                method, id, id
+            )
+        };
+
+        // per call results of batches up to the max batch size
+        let batch = format!(
+            "[{},{},{}]",
+            call(1, "getitems"),
+            call(2, "getitems"),
+            call(3, "unknown")
+        );
+        let resp: Value = serde_json::from_str(&io.io.handle_request_sync(&batch, ApiMeta::admin()).unwrap()).unwrap();
+        let outputs = resp.as_array().unwrap();
+        assert_eq!(3, outputs.len());
+        assert_eq!(serde_json::json!(["item"]), outputs[0]["result"]);
+        assert_eq!(serde_json::json!(["item", "item"]), outputs[1]["result"]);
+        assert_eq!(-32601, outputs[2]["error"]["code"]);
+        assert_eq!(3, outputs[2]["id"]);
+
+        // larger batches are rejected
+        let batch = format!(
+            "[{},{},{},{}]",
+            call(1, "getitems"),
+            call(2, "getitems"),
+            call(3, "getitems"),
+            call(4, "getitems")
+        );
+        let resp: Value = serde_json::from_str(&io.io.handle_request_sync(&batch, ApiMeta::admin()).unwrap()).unwrap();
+        assert_eq!(API_ERROR_BATCH_TOO_LARGE, resp["error"]["code"].as_i64().unwrap());
+        assert_eq!(
+            "Batch of 4 calls exceeds the max batch size of 3 calls",
+            resp["error"]["message"]
+        );
+        assert_eq!(Value::Null, resp["id"]);
+
+        // single calls are not limited
+        let resp: Value = serde_json::from_str(
+            &io.io
+                .handle_request_sync(&call(4, "getitems"), ApiMeta::admin())
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(4, resp["result"].as_array().unwrap().len());
+    }
+
+    #[test]
+    fn api_rate_limits_test() {
+        setup_logger();
+        let tenant_hash = gen_dummy_hash(9);
+        let mut config = ApiConfig::default();
+        config.rate_limit_key = 2;
+        config.rate_limit_ip = 3;
+        config.trusted_proxies = vec!["10.0.0.2".to_owned()];
+        let tenant = TenantConfig {
+            genesis_hash: tenant_hash.to_string(),
+            api_user: "tenant".to_owned(),
+            api_pass: "pass".to_owned(),
+            asset: None,
+            asset_key: None,
+            payment_asset: None,
+            fee_assets: None,
+            fee_percentage: None,
+            challenge_payload: None,
+            challenge_commit_reveal: None,
+            api_rate_limit: Some(0),
+        };
+        let limits = ApiRateLimits::new(&config, &[tenant]);
+        let request_with = |auth: &str, ip: Option<&str>| -> Request<Body> {
+            let mut builder = Request::builder();
+            let _ = builder.header(header::AUTHORIZATION, auth);
+            if let Some(ip) = ip {
+                let _ = builder.header("x-forwarded-for", ip);
+            }
+            builder.body(Body::from("")).unwrap()
+        };
+        let admin = ApiMeta::admin();
+        let tenant = ApiMeta {
+            tenant: Some(tenant_hash),
+        };
+
+        // limited by api key
+        let quota = limits.check(&request_with("key", None), &admin).unwrap().unwrap();
+        assert_eq!((2, 1), (quota.limit, quota.remaining));
+        let _ = limits.check(&request_with("key", None), &admin).unwrap();
+        assert_eq!(
+            0,
+            limits.check(&request_with("key", None), &admin).unwrap_err().remaining
+        );
+
+        // and by client ip, reporting the quota closest to its limit
+        let quota = limits
+            .check(&request_with("other", Some("10.0.0.1, 10.0.0.2")), &admin)
+            .unwrap()
+            .unwrap();
+        assert_eq!((2, 1), (quota.limit, quota.remaining));
+        let _ = limits.check(&request_with("third", Some("10.0.0.1")), &admin).unwrap();
+
+        // tenant keys without a limit are still limited by client ip
+        assert_eq!(None, limits.check(&request_with("tenant", None), &tenant).unwrap());
+        let quota = limits
+            .check(&request_with("tenant", Some("10.0.0.1")), &tenant)
+            .unwrap()
+            .unwrap();
+        assert_eq!((3, 0), (quota.limit, quota.remaining));
+        let quota = limits
+            .check(&request_with("tenant", Some("10.0.0.1")), &tenant)
+            .unwrap_err();
+        assert_eq!((3, 0), (quota.limit, quota.remaining));
+
+        assert_eq!(
+            RateLimitStats {
+                key_limited: 1,
+                ip_limited: 1,
+                keys: 3,
+                ips: 1,
+            },
+            limits.stats()
+        );
+    }
+
+    #[test]
+    fn client_ip_test() {
+        let request_with = |headers: &[(&str, &str)]| -> Request<Body> {
+            let mut builder = Request::builder();
+            for (name, value) in headers {
+                let _ = builder.header(*name, *value);
+            }
+            builder.body(Body::from("")).unwrap()
+        };
+        let trusted: Vec<IpRange> = vec![
+            IpRange::from_str("10.0.0.0/8").unwrap(),
+            IpRange::from_str("192.168.1.1").unwrap(),
+        ];
+
+        // forwarded headers ignored without trusted proxies
+        let forwarded = request_with(&[("x-forwarded-for", "1.2.3.4")]);
+        assert_eq!(None, client_ip(&forwarded, &[]));
+        assert_eq!(Some("1.2.3.4".to_owned()), client_ip(&forwarded, &trusted));
+
+        // right-most untrusted hop, ignoring hops spoofed by the caller
+        let spoofed = request_with(&[("x-forwarded-for", "9.9.9.9, 1.2.3.4, 10.1.2.3, 192.168.1.1")]);
+        assert_eq!(Some("1.2.3.4".to_owned()), client_ip(&spoofed, &trusted));
+        let proxies_only = request_with(&[("x-forwarded-for", "10.0.0.1, 192.168.1.1")]);
+        assert_eq!(None, client_ip(&proxies_only, &trusted));
+
+        // real ip header of the proxy if not forwarded
+        let real_ip = request_with(&[("x-real-ip", " 5.6.7.8 ")]);
+        assert_eq!(Some("5.6.7.8".to_owned()), client_ip(&real_ip, &trusted));
+        assert_eq!(None, client_ip(&request_with(&[]), &trusted));
+    }
+
+    #[test]
+    fn api_auth_scope_test() {
+        setup_logger();
+        let tenant_hash = gen_dummy_hash(9);
+        let tenant = TenantConfig {
+            genesis_hash: tenant_hash.to_string(),
+            api_user: "tenant".to_owned(),
+            api_pass: "pass".to_owned(),
+            asset: None,
+            asset_key: None,
+            payment_asset: None,
+            fee_assets: None,
+            fee_percentage: None,
+            challenge_payload: None,
+            challenge_commit_reveal: None,
+            api_rate_limit: None,
+        };
+
+        let request_with = |auth: &str| -> Request<Body> {
+            Request::builder()
+                .header(header::AUTHORIZATION, format!("Basic {}", base64::encode(auth)))
+                .body(Body::from(""))
+                .unwrap()
+        };
+
+        let mut config = ApiConfig::default();
+        config.user = "user".to_owned();
+        config.pass = "pass".to_owned();
+        let auth = ApiAuth::new(&config, &[tenant.clone()]).unwrap();
+        assert_eq!(None, auth.scope(&request_with("user:wrong")).map(|meta| meta.tenant));
+        assert_eq!(
+            Some(None),
+            auth.scope(&request_with("user:pass")).map(|meta| meta.tenant)
+        );
+        assert_eq!(
+            Some(Some(tenant_hash)),
+            auth.scope(&request_with("tenant:pass")).map(|meta| meta.tenant)
+        );
+
+        // token auth provider for admin callers
+        config.auth_provider = "token".to_owned();
+        config.auth_tokens = vec!["token".to_owned()];
+        let auth = ApiAuth::new(&config, &[tenant.clone()]).unwrap();
+        assert_eq!(None, auth.scope(&request_with("user:pass")).map(|meta| meta.tenant));
+        let request = Request::builder()
+            .header(header::AUTHORIZATION, "Bearer token")
+            .body(Body::from(""))
+            .unwrap();
+        assert_eq!(Some(None), auth.scope(&request).map(|meta| meta.tenant));
+        assert_eq!(
+            Some(Some(tenant_hash)),
+            auth.scope(&request_with("tenant:pass")).map(|meta| meta.tenant)
+        );
+
+        // unknown auth provider
+        config.auth_provider = "unknown".to_owned();
+        assert!(ApiAuth::new(&config, &[]).is_err());
+
+        // empty admin and tenant credentials authorize no caller
+        config.auth_provider = "basic".to_owned();
+        config.user = "".to_owned();
+        config.pass = "".to_owned();
+        let mut empty_tenant = tenant.clone();
+        empty_tenant.api_user = "".to_owned();
+        empty_tenant.api_pass = "".to_owned();
+        let auth = ApiAuth::new(&config, &[empty_tenant]).unwrap();
+        assert!(auth.scope(&request_with(":")).is_none());
+        let request = Request::builder().body(Body::from("")).unwrap();
+        assert!(auth.scope(&request).is_none());
+
+        // unauthorized callers fall back to no access
+        let meta = ApiMeta::default();
+        assert!(!in_scope(
+            &meta.tenant,
+            &gen_challenge_state(&gen_dummy_hash(1)).request
+        ));
+        assert!(meta.tenant.is_some());
+    }
 }
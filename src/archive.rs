@@ -0,0 +1,416 @@
+//! Archive
+//!
+//! Export of all the stored data of a client chain genesis hash into a portable
+//! archive and import of an archive into the storage of a fresh deployment, for
+//! environment migrations and disaster recovery. Archives are a stream of bson
+//! records, each holding a document of a storage collection in the format of
+//! doc_format, starting with a header record
+
+use std::io::{BufRead, Write};
+
+use bitcoin::hashes::{hex::FromHex, sha256d};
+use mongodb::{decode_document, encode_document, ordered::OrderedDocument, Bson};
+
+use crate::error::{CError, Result};
+use crate::interfaces::bid::BidSet;
+use crate::interfaces::request::Request;
+use crate::interfaces::storage::Storage;
+use crate::util::doc_format::*;
+
+/// Archive format version written in the archive header
+pub const ARCHIVE_VERSION: i32 = 1;
+
+/// Collection name of the archive header record
+pub const ARCHIVE_HEADER: &str = "Header";
+
+/// Collection names of the archive records
+const ARCHIVE_REQUEST: &str = "Request";
+const ARCHIVE_BID: &str = "Bid";
+const ARCHIVE_RESPONSE: &str = "Response";
+const ARCHIVE_RESPONSE_SNAPSHOT: &str = "ResponseSnapshot";
+const ARCHIVE_CHALLENGE_LATENCY: &str = "ChallengeLatency";
+const ARCHIVE_RESPONSE_RECONCILIATION: &str = "ResponseReconciliation";
+const ARCHIVE_FEE_POOL: &str = "FeePool";
+const ARCHIVE_BID_KEY_ROTATION: &str = "BidKeyRotation";
+const ARCHIVE_REQUEST_LOGS: &str = "RequestLogs";
+const ARCHIVE_REQUEST_ANNOTATION: &str = "RequestAnnotation";
+const ARCHIVE_REQUEST_DEPOSIT: &str = "RequestDeposit";
+const ARCHIVE_REQUEST_REJECTION: &str = "RequestRejection";
+const ARCHIVE_BID_BLACKLISTING: &str = "BidBlacklisting";
+
+/// Summary of an exported or imported archive
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveSummary {
+    /// Client chain genesis hash of the archived data
+    pub genesis_hash: sha256d::Hash,
+    /// Number of requests archived
+    pub num_requests: u32,
+    /// Total number of records archived, excluding the header
+    pub num_records: u32,
+}
+
+/// Writer of archive records counting the records written
+pub struct ArchiveWriter<'a, W: Write + 'a> {
+    writer: &'a mut W,
+    num_records: u32,
+}
+
+impl<'a, W: Write> ArchiveWriter<'a, W> {
+    /// Create a writer of archive records into a writer
+    pub fn new(writer: &'a mut W) -> ArchiveWriter<'a, W> {
+        ArchiveWriter { writer, num_records: 0 }
+    }
+
+    /// Number of records written, excluding the header
+    pub fn num_records(&self) -> u32 {
+        self.num_records
+    }
+
+    /// Write a record of a collection document, along with the request txid
+    /// that the document belongs to, if any
+    pub fn write(&mut self, collection: &str, txid: Option<&sha256d::Hash>, doc: OrderedDocument) -> Result<()> {
+        let mut record = doc! {
+            "collection": collection,
+            "doc": doc,
+        };
+        if let Some(txid) = txid {
+            let _ = record.insert("txid", txid.to_string());
+        }
+        encode_document(&mut *self.writer, &record)
+            .map_err(|e| CError::Generic(format!("failed writing archive record: {}", e)))?;
+        if collection != ARCHIVE_HEADER {
+            self.num_records += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Export all the requests of a client chain genesis hash into an archive,
+/// along with their bids, responses, response snapshots, challenge latencies,
+/// reconciliations, fee pools, logs and annotations, as well as the request
+/// deposits and rejections
+pub fn export_archive<D: Storage, W: Write>(
+    storage: &D,
+    genesis_hash: sha256d::Hash,
+    writer: &mut W,
+) -> Result<ArchiveSummary> {
+    info!("Exporting archive for genesis hash {}", genesis_hash);
+    let mut archive = ArchiveWriter::new(writer);
+    archive.write(
+        ARCHIVE_HEADER,
+        None,
+        doc! {
+            "version": ARCHIVE_VERSION,
+            "genesis_hash": genesis_hash.to_string(),
+        },
+    )?;
+
+    let requests = storage.get_requests(None, Some(genesis_hash), None, None)?;
+    for request in requests.iter() {
+        let txid = &request.txid;
+        let request_id = Bson::String(txid.to_string());
+        archive.write(ARCHIVE_REQUEST, Some(txid), request_to_doc(request))?;
+        for bid in storage.get_bids(*txid)? {
+            archive.write(ARCHIVE_BID, Some(txid), bid_to_doc(&request_id, &bid))?;
+        }
+        if let Some(response) = storage.get_response(*txid)? {
+            archive.write(ARCHIVE_RESPONSE, Some(txid), response_to_doc(&request_id, &response))?;
+        }
+        for snapshot in storage.get_response_snapshots(*txid)? {
+            archive.write(
+                ARCHIVE_RESPONSE_SNAPSHOT,
+                Some(txid),
+                response_snapshot_to_doc(txid, &snapshot),
+            )?;
+        }
+        if let Some(latency) = storage.get_challenge_latency(*txid)? {
+            archive.write(
+                ARCHIVE_CHALLENGE_LATENCY,
+                Some(txid),
+                challenge_latency_to_doc(txid, &latency),
+            )?;
+        }
+        if let Some(reconciliation) = storage.get_response_reconciliation(*txid)? {
+            archive.write(
+                ARCHIVE_RESPONSE_RECONCILIATION,
+                Some(txid),
+                response_reconciliation_to_doc(txid, &reconciliation),
+            )?;
+        }
+        if let Some(pool) = storage.get_fee_pool(*txid)? {
+            archive.write(ARCHIVE_FEE_POOL, Some(txid), fee_pool_to_doc(txid, &pool))?;
+        }
+        for rotation in storage.get_bid_key_rotations(*txid)? {
+            archive.write(ARCHIVE_BID_KEY_ROTATION, Some(txid), bid_key_rotation_to_doc(&rotation))?;
+        }
+        let logs = storage.get_request_logs(*txid)?;
+        if logs.len() > 0 {
+            let logs: Vec<Bson> = logs.into_iter().map(Bson::String).collect();
+            archive.write(ARCHIVE_REQUEST_LOGS, Some(txid), doc! {"logs": logs})?;
+        }
+        if let Some(annotation) = storage.get_request_annotation(*txid)? {
+            archive.write(
+                ARCHIVE_REQUEST_ANNOTATION,
+                Some(txid),
+                request_annotation_to_doc(txid, &annotation),
+            )?;
+        }
+    }
+    for deposit in storage.get_request_deposits(None, Some(genesis_hash))? {
+        archive.write(ARCHIVE_REQUEST_DEPOSIT, None, request_deposit_to_doc(&deposit))?;
+    }
+    for rejection in storage.get_request_rejections(Some(genesis_hash))? {
+        archive.write(ARCHIVE_REQUEST_REJECTION, None, request_rejection_to_doc(&rejection))?;
+    }
+    for blacklisting in storage.get_bid_blacklistings(Some(genesis_hash))? {
+        archive.write(ARCHIVE_BID_BLACKLISTING, None, bid_blacklisting_to_doc(&blacklisting))?;
+    }
+
+    let summary = ArchiveSummary {
+        genesis_hash,
+        num_requests: requests.len() as u32,
+        num_records: archive.num_records,
+    };
+    info!(
+        "Exported {} requests in {} records",
+        summary.num_requests, summary.num_records
+    );
+    Ok(summary)
+}
+
+/// Read the next archive record, returning None at the end of the archive
+pub fn read_record<R: BufRead>(reader: &mut R) -> Result<Option<(String, Option<sha256d::Hash>, OrderedDocument)>> {
+    let is_end = reader
+        .fill_buf()
+        .map_err(|e| CError::Generic(format!("failed reading archive: {}", e)))?
+        .is_empty();
+    if is_end {
+        return Ok(None);
+    }
+    let record = decode_document(reader).map_err(|e| CError::Generic(format!("invalid archive record: {}", e)))?;
+    let invalid = || CError::Generic(format!("invalid archive record: {}", record));
+    let collection = record.get_str("collection").map_err(|_| invalid())?.to_owned();
+    let txid = match record.get_str("txid") {
+        Ok(txid) => Some(sha256d::Hash::from_hex(txid)?),
+        Err(_) => None,
+    };
+    let doc = record.get_document("doc").map_err(|_| invalid())?.clone();
+    Ok(Some((collection, txid, doc)))
+}
+
+/// Import an archive into storage. The storage is required to not have any
+/// requests for the archive genesis hash, so that archives are only restored
+/// into fresh deployments. Bids are imported along with their request, while
+/// imported responses start a new response integrity hash chain
+pub fn import_archive<D: Storage, R: BufRead>(storage: &D, reader: &mut R) -> Result<ArchiveSummary> {
+    let genesis_hash = match read_record(reader)? {
+        Some((ref collection, _, ref header)) if collection == ARCHIVE_HEADER => {
+            let version = header.get_i32("version").unwrap_or(0);
+            if version != ARCHIVE_VERSION {
+                return Err(CError::Generic(format!("unsupported archive version {}", version)).into());
+            }
+            sha256d::Hash::from_hex(header.get_str("genesis_hash").unwrap_or(""))?
+        }
+        _ => return Err(CError::Generic("missing archive header".to_owned()).into()),
+    };
+    if storage.get_requests_count(Some(genesis_hash))? > 0 {
+        return Err(CError::Generic(format!(
+            "storage already has requests for genesis hash {}",
+            genesis_hash
+        ))
+        .into());
+    }
+    info!("Importing archive for genesis hash {}", genesis_hash);
+
+    let mut summary = ArchiveSummary {
+        genesis_hash,
+        num_requests: 0,
+        num_records: 0,
+    };
+    // requests are saved once all their bids are read
+    let mut pending: Option<(Request, BidSet)> = None;
+    while let Some((collection, txid, doc)) = read_record(reader)? {
+        summary.num_records += 1;
+        if collection == ARCHIVE_BID {
+            match pending.as_mut() {
+                Some((_, bids)) => {
+                    let _ = bids.insert(doc_to_bid(&doc)?);
+                }
+                None => return Err(CError::Generic("archive bid without request".to_owned()).into()),
+            }
+            continue;
+        }
+        if let Some((request, bids)) = pending.take() {
+            storage.save_challenge_request_state(&request, &bids)?;
+        }
+        let request_txid = || txid.ok_or_else(|| CError::Generic(format!("archive {} without txid", collection)));
+        match collection.as_str() {
+            ARCHIVE_REQUEST => {
+                summary.num_requests += 1;
+                pending = Some((doc_to_request(&doc), BidSet::new()));
+            }
+            ARCHIVE_RESPONSE => storage.save_response(request_txid()?, &doc_to_response(&doc))?,
+            ARCHIVE_RESPONSE_SNAPSHOT => {
+                storage.save_response_snapshot(request_txid()?, &doc_to_response_snapshot(&doc)?)?
+            }
+            ARCHIVE_CHALLENGE_LATENCY => {
+                storage.save_challenge_latency(request_txid()?, &doc_to_challenge_latency(&doc))?
+            }
+            ARCHIVE_RESPONSE_RECONCILIATION => {
+                storage.save_response_reconciliation(request_txid()?, &doc_to_response_reconciliation(&doc))?
+            }
+            ARCHIVE_FEE_POOL => storage.save_fee_pool(request_txid()?, &doc_to_fee_pool(&doc))?,
+            ARCHIVE_BID_KEY_ROTATION => storage.save_bid_key_rotation(&doc_to_bid_key_rotation(&doc))?,
+            ARCHIVE_REQUEST_LOGS => {
+                let logs: Vec<String> = doc
+                    .get_array("logs")
+                    .map_err(|_| CError::Generic("invalid archive request logs".to_owned()))?
+                    .iter()
+                    .filter_map(|log| log.as_str().map(String::from))
+                    .collect();
+                storage.save_request_logs(request_txid()?, &logs)?
+            }
+            ARCHIVE_REQUEST_ANNOTATION => {
+                storage.save_request_annotation(request_txid()?, &doc_to_request_annotation(&doc))?
+            }
+            ARCHIVE_REQUEST_DEPOSIT => storage.save_request_deposit(&doc_to_request_deposit(&doc))?,
+            ARCHIVE_REQUEST_REJECTION => storage.save_request_rejection(&doc_to_request_rejection(&doc))?,
+            ARCHIVE_BID_BLACKLISTING => storage.save_bid_blacklisting(&doc_to_bid_blacklisting(&doc))?,
+            _ => warn!("Skipping archive record of unknown collection {}", collection),
+        }
+    }
+    if let Some((request, bids)) = pending.take() {
+        storage.save_challenge_request_state(&request, &bids)?;
+    }
+
+    info!(
+        "Imported {} requests from {} records",
+        summary.num_requests, summary.num_records
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+
+    use bitcoin::Amount;
+
+    use crate::interfaces::bid::BidBlacklisting;
+    use crate::interfaces::mocks::storage::MockStorage;
+    use crate::interfaces::request::{FeePool, RequestAnnotation, RequestDeposit, RequestRejection};
+    use crate::interfaces::response::{BidReconciliation, ChallengeLatency, Response, ResponseReconciliation};
+    use crate::util::testing::{gen_challenge_state, gen_dummy_hash, setup_logger};
+
+    #[test]
+    fn export_import_archive_test() {
+        setup_logger();
+        let storage = MockStorage::new();
+        let genesis_hash = gen_dummy_hash(0);
+        let txid = gen_dummy_hash(1);
+
+        let state = gen_challenge_state(&txid);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let mut response = Response::new();
+        response.num_challenges = 2;
+        response.challenges = vec![gen_dummy_hash(6), gen_dummy_hash(7)];
+        let _ = response.bid_responses.insert(state.bids.iter().next().unwrap().txid, 1);
+        storage.save_response(txid, &response).unwrap();
+        let mut latency = ChallengeLatency::new();
+        latency.verify_ms = vec![100, 200];
+        storage.save_challenge_latency(txid, &latency).unwrap();
+        let reconciliation = ResponseReconciliation {
+            clientchain_height: 10,
+            bids: vec![BidReconciliation::new(gen_dummy_hash(3), 1, 1)],
+        };
+        storage.save_response_reconciliation(txid, &reconciliation).unwrap();
+        let pool = FeePool::new(10, 20, vec![], 50, None);
+        storage.save_fee_pool(txid, &pool).unwrap();
+        storage
+            .save_request_logs(txid, &["log a".to_owned(), "log b".to_owned()])
+            .unwrap();
+        let annotation = RequestAnnotation {
+            labels: vec!["trial".to_owned()],
+            notes: Some("client X trial".to_owned()),
+        };
+        storage.save_request_annotation(txid, &annotation).unwrap();
+        let deposit = RequestDeposit {
+            txid,
+            genesis_blockhash: genesis_hash,
+            promised: Amount::from_sat(10),
+            locked: Amount::from_sat(10),
+        };
+        storage.save_request_deposit(&deposit).unwrap();
+        let rejection = RequestRejection {
+            txid: gen_dummy_hash(2),
+            genesis_blockhash: genesis_hash,
+            reason: "no tickets".to_owned(),
+        };
+        storage.save_request_rejection(&rejection).unwrap();
+        let blacklisting = BidBlacklisting {
+            txid: gen_dummy_hash(3),
+            request: txid,
+            genesis_blockhash: genesis_hash,
+            strikes: 5,
+            since: 1600000000,
+            until: 1600003600,
+        };
+        storage.save_bid_blacklisting(&blacklisting).unwrap();
+
+        // export
+        let mut archive = vec![];
+        let summary = export_archive(&storage, genesis_hash, &mut archive).unwrap();
+        assert_eq!(
+            ArchiveSummary {
+                genesis_hash,
+                num_requests: 1,
+                num_records: 11,
+            },
+            summary
+        );
+
+        // import into fresh storage
+        let restored = MockStorage::new();
+        let summary = import_archive(&restored, &mut Cursor::new(archive.clone())).unwrap();
+        assert_eq!(1, summary.num_requests);
+        assert_eq!(11, summary.num_records);
+        assert_eq!(
+            storage.get_requests(None, Some(genesis_hash), None, None).unwrap(),
+            restored.get_requests(None, Some(genesis_hash), None, None).unwrap()
+        );
+        assert_eq!(storage.get_bids(txid).unwrap(), restored.get_bids(txid).unwrap());
+        assert_eq!(Some(response), restored.get_response(txid).unwrap());
+        assert_eq!(Some(latency), restored.get_challenge_latency(txid).unwrap());
+        assert_eq!(
+            Some(reconciliation),
+            restored.get_response_reconciliation(txid).unwrap()
+        );
+        assert_eq!(Some(pool), restored.get_fee_pool(txid).unwrap());
+        assert_eq!(vec!["log a", "log b"], restored.get_request_logs(txid).unwrap());
+        assert_eq!(Some(annotation), restored.get_request_annotation(txid).unwrap());
+        assert_eq!(
+            vec![deposit],
+            restored.get_request_deposits(None, Some(genesis_hash)).unwrap()
+        );
+        assert_eq!(
+            vec![rejection],
+            restored.get_request_rejections(Some(genesis_hash)).unwrap()
+        );
+        assert_eq!(
+            vec![blacklisting],
+            restored.get_bid_blacklistings(Some(genesis_hash)).unwrap()
+        );
+
+        // storage already holding the genesis hash requests
+        assert!(import_archive(&restored, &mut Cursor::new(archive.clone())).is_err());
+
+        // invalid archives
+        let restored = MockStorage::new();
+        assert!(import_archive(&restored, &mut Cursor::new(vec![])).is_err());
+        assert!(import_archive(&restored, &mut Cursor::new(archive[..archive.len() - 1].to_vec())).is_err());
+    }
+}
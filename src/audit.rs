@@ -0,0 +1,776 @@
+//! Audit
+//!
+//! Per request audit bundles packaging everything stored about a request for
+//! external audit, i.e. the request, its bids and their payments, challenge
+//! records, bid proofs, responses along with their integrity hash chain and
+//! merkle root, latencies, stalls, reconciliation, fee pool, key rotations and
+//! logs. Bundles use the archive record format and end with a signature record
+//! holding the digest of all the preceding bytes of the bundle, signed by the
+//! configured audit signing key if any. Bundles are generated server side in
+//! the background, reporting their progress per section of the bundle
+
+use std::io::{self, Cursor, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use bitcoin::hashes::hex::{FromHex, ToHex};
+use bitcoin::hashes::{sha256, sha256d, Hash, HashEngine};
+use bitcoin::secp256k1::{Message, PublicKey, Secp256k1, SecretKey, Signature};
+use mongodb::Bson;
+use serde::{Deserialize, Serialize};
+
+use crate::archive::{read_record, ArchiveWriter, ARCHIVE_HEADER};
+use crate::config::Config;
+use crate::error::{CError, Error, Result};
+use crate::interfaces::storage::Storage;
+use crate::merkle::ResponseMerkleTree;
+use crate::util::doc_format::*;
+
+/// Audit bundle format version written in the bundle header
+pub const AUDIT_BUNDLE_VERSION: i32 = 1;
+
+/// Max number of audit bundles kept in memory, dropping the earliest finished
+/// bundles first
+pub const AUDIT_BUNDLES_MAX: usize = 8;
+
+/// Sections of an audit bundle in the order they are written
+const AUDIT_BUNDLE_SECTIONS: [&str; 6] = ["request", "bids", "challenges", "response", "logs", "signature"];
+
+/// Collection names of the audit bundle records
+const AUDIT_REQUEST: &str = "Request";
+const AUDIT_REQUEST_ANNOTATION: &str = "RequestAnnotation";
+const AUDIT_REQUEST_DEPOSIT: &str = "RequestDeposit";
+const AUDIT_BID: &str = "Bid";
+const AUDIT_BID_KEY_ROTATION: &str = "BidKeyRotation";
+const AUDIT_BID_BLACKLISTING: &str = "BidBlacklisting";
+const AUDIT_BID_REFUND: &str = "BidRefund";
+const AUDIT_PAYMENT_LIABILITY: &str = "PaymentLiability";
+const AUDIT_FEE_POOL: &str = "FeePool";
+const AUDIT_CHALLENGE_RECORD: &str = "ChallengeRecord";
+const AUDIT_CHALLENGE_STATS: &str = "ChallengeStats";
+const AUDIT_BID_PROOF: &str = "BidProof";
+const AUDIT_CHALLENGE_LATENCY: &str = "ChallengeLatency";
+const AUDIT_CHALLENGE_COVERAGE: &str = "ChallengeCoverage";
+const AUDIT_CLIENTCHAIN_STALL: &str = "ClientChainStall";
+const AUDIT_RESPONSE: &str = "Response";
+const AUDIT_RESPONSE_ATTESTATION: &str = "ResponseAttestation";
+const AUDIT_RESPONSE_SNAPSHOT: &str = "ResponseSnapshot";
+const AUDIT_RESPONSE_RECONCILIATION: &str = "ResponseReconciliation";
+const AUDIT_REQUEST_LOGS: &str = "RequestLogs";
+const AUDIT_SIGNATURE: &str = "Signature";
+
+/// Signer of audit bundle digests with the configured audit signing key
+pub struct AuditSigner {
+    /// Signing key
+    secret_key: SecretKey,
+    /// Public key of the signing key
+    public_key: PublicKey,
+}
+
+impl AuditSigner {
+    /// Create a signer from a hex secp256k1 secret key
+    pub fn from_hex(key: &str) -> Result<AuditSigner> {
+        let secret_key = Vec::<u8>::from_hex(key.trim())
+            .ok()
+            .and_then(|bytes| SecretKey::from_slice(&bytes).ok())
+            .ok_or_else(|| {
+                Error::from(CError::Generic(
+                    "invalid audit signing key, expected 64 hex characters".to_owned(),
+                ))
+            })?;
+        Ok(AuditSigner {
+            public_key: PublicKey::from_secret_key(&Secp256k1::new(), &secret_key),
+            secret_key,
+        })
+    }
+
+    /// Get the signer of the audit signing key set in the config, or None if
+    /// bundles are not signed
+    pub fn from_config(config: &Config) -> Result<Option<AuditSigner>> {
+        match &config.audit_signing_key {
+            Some(key) => Ok(Some(AuditSigner::from_hex(key)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Public key that bundles are signed with
+    pub fn public_key(&self) -> PublicKey {
+        self.public_key
+    }
+
+    /// Sign the digest of a bundle
+    pub fn sign(&self, digest: &sha256d::Hash) -> Result<Signature> {
+        Ok(Secp256k1::new().sign(&Message::from_slice(&digest[..])?, &self.secret_key))
+    }
+}
+
+/// Progress of writing an audit bundle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditBundleProgress {
+    /// Section of the bundle being written
+    pub section: String,
+    /// Number of sections written
+    pub sections_done: u32,
+    /// Total number of sections
+    pub sections_total: u32,
+    /// Number of records written, excluding the header
+    pub num_records: u32,
+}
+
+/// Get the progress of a bundle given the number of sections written
+fn section_progress(sections_done: usize, num_records: u32) -> AuditBundleProgress {
+    AuditBundleProgress {
+        section: AUDIT_BUNDLE_SECTIONS[sections_done.min(AUDIT_BUNDLE_SECTIONS.len() - 1)].to_owned(),
+        sections_done: sections_done as u32,
+        sections_total: AUDIT_BUNDLE_SECTIONS.len() as u32,
+        num_records,
+    }
+}
+
+/// Summary of a written or verified audit bundle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditBundleSummary {
+    /// Request txid
+    pub txid: sha256d::Hash,
+    /// Number of records, excluding the header and signature records
+    pub num_records: u32,
+    /// Digest of the bundle bytes preceding the signature record
+    pub digest: sha256d::Hash,
+    /// Hex public key that the digest is signed with, if signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pubkey: Option<String>,
+    /// Hex DER signature of the digest, if signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// Writer forwarding the bytes written to an inner writer while hashing them
+struct DigestWriter<'a, W: Write + 'a> {
+    writer: &'a mut W,
+    engine: sha256::HashEngine,
+}
+
+impl<'a, W: Write> Write for DigestWriter<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        self.engine.input(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Export the audit bundle of a request, reporting the progress at the start
+/// of each section. The bundle ends with the digest of the preceding bundle
+/// bytes, along with its signature if a signer is set
+pub fn export_audit_bundle<D: Storage + ?Sized, W: Write>(
+    storage: &D,
+    txid: sha256d::Hash,
+    signer: Option<&AuditSigner>,
+    writer: &mut W,
+    progress: &mut dyn FnMut(&AuditBundleProgress),
+) -> Result<AuditBundleSummary> {
+    let request = match storage.get_request(txid)? {
+        Some(request) => request,
+        None => return Err(CError::Generic(format!("request {} not found", txid)).into()),
+    };
+    info!("Exporting audit bundle for request {}", txid);
+    let request_id = Bson::String(txid.to_string());
+    let genesis_hash = request.genesis_blockhash;
+    let mut digest_writer = DigestWriter {
+        writer,
+        engine: sha256d::Hash::engine(),
+    };
+    let num_records = {
+        let mut bundle = ArchiveWriter::new(&mut digest_writer);
+
+        progress(&section_progress(0, bundle.num_records()));
+        bundle.write(
+            ARCHIVE_HEADER,
+            Some(&txid),
+            doc! {
+                "version": AUDIT_BUNDLE_VERSION,
+                "genesis_hash": genesis_hash.to_string(),
+            },
+        )?;
+        bundle.write(AUDIT_REQUEST, Some(&txid), request_to_doc(&request))?;
+        if let Some(annotation) = storage.get_request_annotation(txid)? {
+            bundle.write(
+                AUDIT_REQUEST_ANNOTATION,
+                Some(&txid),
+                request_annotation_to_doc(&txid, &annotation),
+            )?;
+        }
+        for deposit in storage.get_request_deposits(None, Some(genesis_hash))? {
+            if deposit.txid == txid {
+                bundle.write(AUDIT_REQUEST_DEPOSIT, Some(&txid), request_deposit_to_doc(&deposit))?;
+            }
+        }
+
+        progress(&section_progress(1, bundle.num_records()));
+        let bids = storage.get_bids(txid)?;
+        for bid in bids.iter() {
+            // bids written in plaintext, as bundles are read without the field cipher
+            bundle.write(AUDIT_BID, Some(&txid), bid_to_doc_with_cipher(&request_id, bid, None))?;
+        }
+        for rotation in storage.get_bid_key_rotations(txid)? {
+            bundle.write(AUDIT_BID_KEY_ROTATION, Some(&txid), bid_key_rotation_to_doc(&rotation))?;
+        }
+        for blacklisting in storage.get_bid_blacklistings(Some(genesis_hash))? {
+            if blacklisting.request == txid {
+                bundle.write(
+                    AUDIT_BID_BLACKLISTING,
+                    Some(&txid),
+                    bid_blacklisting_to_doc(&blacklisting),
+                )?;
+            }
+        }
+        for refund in storage.get_bid_refunds(Some(genesis_hash))? {
+            if refund.request == txid {
+                bundle.write(AUDIT_BID_REFUND, Some(&txid), bid_refund_to_doc(&refund))?;
+            }
+        }
+        for liability in storage.get_payment_liabilities(Some(genesis_hash))? {
+            if liability.request == txid {
+                bundle.write(
+                    AUDIT_PAYMENT_LIABILITY,
+                    Some(&txid),
+                    payment_liability_to_doc(&liability),
+                )?;
+            }
+        }
+        if let Some(pool) = storage.get_fee_pool(txid)? {
+            bundle.write(AUDIT_FEE_POOL, Some(&txid), fee_pool_to_doc(&txid, &pool))?;
+        }
+
+        progress(&section_progress(2, bundle.num_records()));
+        for record in storage.get_challenge_records(txid)? {
+            bundle.write(
+                AUDIT_CHALLENGE_RECORD,
+                Some(&txid),
+                challenge_record_to_doc(&txid, &record),
+            )?;
+        }
+        for stats in storage.get_challenge_stats(txid)? {
+            bundle.write(
+                AUDIT_CHALLENGE_STATS,
+                Some(&txid),
+                challenge_stats_to_doc(&txid, &stats),
+            )?;
+        }
+        for bid in bids.iter() {
+            for proof in storage.get_bid_proofs(txid, bid.txid)? {
+                bundle.write(AUDIT_BID_PROOF, Some(&txid), bid_proof_to_doc(&txid, &proof))?;
+            }
+        }
+        if let Some(latency) = storage.get_challenge_latency(txid)? {
+            bundle.write(
+                AUDIT_CHALLENGE_LATENCY,
+                Some(&txid),
+                challenge_latency_to_doc(&txid, &latency),
+            )?;
+        }
+        if let Some(coverage) = storage.get_challenge_coverage(txid)? {
+            bundle.write(
+                AUDIT_CHALLENGE_COVERAGE,
+                Some(&txid),
+                challenge_coverage_to_doc(&txid, &coverage),
+            )?;
+        }
+        for stall in storage.get_clientchain_stalls(txid)? {
+            bundle.write(
+                AUDIT_CLIENTCHAIN_STALL,
+                Some(&txid),
+                clientchain_stall_to_doc(&txid, &stall),
+            )?;
+        }
+
+        progress(&section_progress(3, bundle.num_records()));
+        if let Some(response) = storage.get_response(txid)? {
+            bundle.write(AUDIT_RESPONSE, Some(&txid), response_to_doc(&request_id, &response))?;
+            let hashes = storage.get_response_hashes(txid)?;
+            let verified = response.verify_integrity(&hashes);
+            let hashes: Vec<Bson> = hashes.iter().map(|hash| Bson::String(hash.to_string())).collect();
+            let mut attestation = doc! {
+                "hashes": hashes,
+                "integrity_verified": verified,
+            };
+            if let Some(root) = ResponseMerkleTree::new(&response).root() {
+                let _ = attestation.insert("merkle_root", root.to_string());
+            }
+            bundle.write(AUDIT_RESPONSE_ATTESTATION, Some(&txid), attestation)?;
+        }
+        for snapshot in storage.get_response_snapshots(txid)? {
+            bundle.write(
+                AUDIT_RESPONSE_SNAPSHOT,
+                Some(&txid),
+                response_snapshot_to_doc(&txid, &snapshot),
+            )?;
+        }
+        if let Some(reconciliation) = storage.get_response_reconciliation(txid)? {
+            bundle.write(
+                AUDIT_RESPONSE_RECONCILIATION,
+                Some(&txid),
+                response_reconciliation_to_doc(&txid, &reconciliation),
+            )?;
+        }
+
+        progress(&section_progress(4, bundle.num_records()));
+        let logs = storage.get_request_logs(txid)?;
+        if logs.len() > 0 {
+            let logs: Vec<Bson> = logs.into_iter().map(Bson::String).collect();
+            bundle.write(AUDIT_REQUEST_LOGS, Some(&txid), doc! {"logs": logs})?;
+        }
+
+        progress(&section_progress(5, bundle.num_records()));
+        bundle.num_records()
+    };
+
+    let DigestWriter { writer, engine } = digest_writer;
+    let digest = sha256d::Hash::from_engine(engine);
+    let mut summary = AuditBundleSummary {
+        txid,
+        num_records,
+        digest,
+        pubkey: None,
+        signature: None,
+    };
+    let mut signature_doc = doc! {"digest": digest.to_string()};
+    if let Some(signer) = signer {
+        let pubkey = signer.public_key().serialize()[..].to_hex();
+        let signature = signer.sign(&digest)?.serialize_der().to_hex();
+        let _ = signature_doc.insert("pubkey", pubkey.clone());
+        let _ = signature_doc.insert("signature", signature.clone());
+        summary.pubkey = Some(pubkey);
+        summary.signature = Some(signature);
+    }
+    ArchiveWriter::new(writer).write(AUDIT_SIGNATURE, Some(&txid), signature_doc)?;
+    progress(&section_progress(AUDIT_BUNDLE_SECTIONS.len(), num_records));
+
+    info!(
+        "Exported audit bundle for request {} in {} records with digest {}",
+        txid, num_records, digest
+    );
+    Ok(summary)
+}
+
+/// Verify an audit bundle, checking that the digest of the signature record
+/// matches the preceding bundle bytes and that the digest is signed by the
+/// expected public key, returning the bundle summary. Unsigned bundles and
+/// bundles signed by any other key fail verification
+pub fn verify_audit_bundle(bundle: &[u8], pubkey: &PublicKey) -> Result<AuditBundleSummary> {
+    let invalid = |reason: &str| Error::from(CError::Generic(format!("invalid audit bundle: {}", reason)));
+    let mut reader = Cursor::new(bundle);
+    let txid = match read_record(&mut reader)? {
+        Some((ref collection, Some(txid), ref header)) if collection == ARCHIVE_HEADER => {
+            let version = header.get_i32("version").unwrap_or(0);
+            if version != AUDIT_BUNDLE_VERSION {
+                return Err(invalid(&format!("unsupported version {}", version)));
+            }
+            txid
+        }
+        _ => return Err(invalid("missing header")),
+    };
+
+    let mut num_records = 0;
+    loop {
+        let position = reader.position() as usize;
+        let (collection, _, doc) = match read_record(&mut reader)? {
+            Some(record) => record,
+            None => return Err(invalid("missing signature record")),
+        };
+        if collection != AUDIT_SIGNATURE {
+            num_records += 1;
+            continue;
+        }
+        if reader.position() as usize != bundle.len() {
+            return Err(invalid("records after the signature record"));
+        }
+        let digest = sha256d::Hash::hash(&bundle[..position]);
+        if doc
+            .get_str("digest")
+            .ok()
+            .and_then(|hash| sha256d::Hash::from_hex(hash).ok())
+            != Some(digest)
+        {
+            return Err(invalid("digest does not match the bundle"));
+        }
+        let (bundle_pubkey, signature) = match (doc.get_str("pubkey"), doc.get_str("signature")) {
+            (Ok(bundle_pubkey), Ok(signature)) => (bundle_pubkey, signature),
+            _ => return Err(invalid("bundle is unsigned")),
+        };
+        if PublicKey::from_slice(&Vec::<u8>::from_hex(bundle_pubkey)?)? != *pubkey {
+            return Err(invalid(&format!("bundle signed by unexpected key {}", bundle_pubkey)));
+        }
+        let sig = Signature::from_der(&Vec::<u8>::from_hex(signature)?)?;
+        if Secp256k1::new()
+            .verify(&Message::from_slice(&digest[..])?, &sig, pubkey)
+            .is_err()
+        {
+            return Err(invalid("signature does not match the digest"));
+        }
+        return Ok(AuditBundleSummary {
+            txid,
+            num_records,
+            digest,
+            pubkey: Some(bundle_pubkey.to_owned()),
+            signature: Some(signature.to_owned()),
+        });
+    }
+}
+
+/// State of an audit bundle generated in the background
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuditBundleState {
+    /// Bundle being generated
+    Running,
+    /// Bundle generated and available for download
+    Complete,
+    /// Bundle generation failed
+    Failed,
+}
+
+/// Status of an audit bundle generated in the background
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditBundleStatus {
+    /// Request txid
+    pub txid: sha256d::Hash,
+    /// Generation state
+    pub state: AuditBundleState,
+    /// Progress of the bundle generation
+    pub progress: AuditBundleProgress,
+    /// Summary of the bundle, once generated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<AuditBundleSummary>,
+    /// Error that the generation failed with, if failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Audit bundle generated in the background along with its status
+struct AuditBundleJob {
+    status: AuditBundleStatus,
+    bundle: Option<Arc<Vec<u8>>>,
+}
+
+/// Audit bundles generated in the background by request, keeping up to
+/// AUDIT_BUNDLES_MAX bundles in memory for download
+pub struct AuditBundles {
+    /// Signer of the bundles, if bundles are signed
+    signer: Option<AuditSigner>,
+    /// Bundles by order of generation
+    jobs: Mutex<Vec<AuditBundleJob>>,
+}
+
+impl AuditBundles {
+    /// Create audit bundles signed by an optional signer
+    pub fn new(signer: Option<AuditSigner>) -> AuditBundles {
+        AuditBundles {
+            signer,
+            jobs: Mutex::new(vec![]),
+        }
+    }
+
+    /// Start the bundle of a request, replacing any previous bundle of the
+    /// request, and return the bundle status, or None if the bundle of the
+    /// request is already being generated
+    pub fn start(&self, txid: sha256d::Hash) -> Option<AuditBundleStatus> {
+        let mut jobs = self.jobs.lock().unwrap();
+        if jobs
+            .iter()
+            .any(|job| job.status.txid == txid && job.status.state == AuditBundleState::Running)
+        {
+            return None;
+        }
+        jobs.retain(|job| job.status.txid != txid);
+        while jobs.len() >= AUDIT_BUNDLES_MAX {
+            match jobs
+                .iter()
+                .position(|job| job.status.state != AuditBundleState::Running)
+            {
+                Some(pos) => {
+                    let _ = jobs.remove(pos);
+                }
+                None => break,
+            }
+        }
+        let status = AuditBundleStatus {
+            txid,
+            state: AuditBundleState::Running,
+            progress: section_progress(0, 0),
+            summary: None,
+            error: None,
+        };
+        jobs.push(AuditBundleJob {
+            status: status.clone(),
+            bundle: None,
+        });
+        Some(status)
+    }
+
+    /// Generate the started bundle of a request, updating its progress per
+    /// section and keeping the bundle once complete
+    pub fn generate<D: Storage + ?Sized>(&self, storage: &D, txid: sha256d::Hash) {
+        let mut bundle = vec![];
+        let result = export_audit_bundle(storage, txid, self.signer.as_ref(), &mut bundle, &mut |progress| {
+            self.update(txid, |job| job.status.progress = progress.clone())
+        });
+        self.update(txid, |job| match result {
+            Ok(summary) => {
+                job.status.state = AuditBundleState::Complete;
+                job.status.summary = Some(summary);
+                job.bundle = Some(Arc::new(bundle));
+            }
+            Err(e) => {
+                warn!("audit bundle of request {} failed: {}", txid, e);
+                job.status.state = AuditBundleState::Failed;
+                job.status.error = Some(e.to_string());
+            }
+        });
+    }
+
+    /// Update the bundle of a request, if still kept
+    fn update<F: FnOnce(&mut AuditBundleJob)>(&self, txid: sha256d::Hash, update: F) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.iter_mut().find(|job| job.status.txid == txid) {
+            update(job);
+        }
+    }
+
+    /// Get the status of the bundle of a request, if any
+    pub fn status(&self, txid: sha256d::Hash) -> Option<AuditBundleStatus> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.iter()
+            .find(|job| job.status.txid == txid)
+            .map(|job| job.status.clone())
+    }
+
+    /// Get the generated bundle of a request, if complete
+    pub fn bundle(&self, txid: sha256d::Hash) -> Option<Arc<Vec<u8>>> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.iter()
+            .find(|job| job.status.txid == txid)
+            .and_then(|job| job.bundle.clone())
+    }
+}
+
+/// Generate the bundle of a request in a separate thread, unless the bundle of
+/// the request is already being generated, returning the bundle status
+pub fn run_audit_bundle<D: Storage + Send + Sync + 'static>(
+    bundles: Arc<AuditBundles>,
+    storage: Arc<D>,
+    txid: sha256d::Hash,
+) -> AuditBundleStatus {
+    match bundles.start(txid) {
+        Some(status) => {
+            let _ = thread::spawn(move || bundles.generate(storage.as_ref(), txid));
+            status
+        }
+        None => bundles.status(txid).unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::interfaces::mocks::storage::MockStorage;
+    use crate::interfaces::request::RequestAnnotation;
+    use crate::interfaces::response::{ChallengeRecord, Response};
+    use crate::util::testing::{gen_challenge_state, gen_dummy_hash, setup_logger};
+
+    /// Storage with a request, its bids, challenge records, response and logs
+    fn gen_audit_storage(txid: &sha256d::Hash) -> MockStorage {
+        let storage = MockStorage::new();
+        let state = gen_challenge_state(txid);
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let bid = state.bids.iter().next().unwrap().txid;
+        let record = ChallengeRecord {
+            challenge: gen_dummy_hash(6),
+            responders: vec![bid],
+        };
+        storage.save_challenge_record(*txid, &record).unwrap();
+        let mut response = Response::new();
+        response.num_challenges = 1;
+        response.challenges = vec![gen_dummy_hash(6)];
+        let _ = response.bid_responses.insert(bid, 1);
+        storage.save_response(*txid, &response).unwrap();
+        storage.save_request_logs(*txid, &["log a".to_owned()]).unwrap();
+        storage
+            .save_request_annotation(
+                *txid,
+                &RequestAnnotation {
+                    labels: vec!["audit".to_owned()],
+                    notes: None,
+                },
+            )
+            .unwrap();
+        storage
+    }
+
+    #[test]
+    fn audit_signer_test() {
+        assert!(AuditSigner::from_hex("abcd").is_err());
+        assert!(AuditSigner::from_hex(&"00".repeat(32)).is_err());
+        let signer = AuditSigner::from_hex(&"11".repeat(32)).unwrap();
+        let other = AuditSigner::from_hex(&format!(" {} ", "11".repeat(32))).unwrap();
+        assert_eq!(signer.public_key(), other.public_key());
+
+        let mut config = Config::default();
+        assert!(AuditSigner::from_config(&config).unwrap().is_none());
+        config.audit_signing_key = Some("11".repeat(32));
+        assert!(AuditSigner::from_config(&config).unwrap().is_some());
+    }
+
+    #[test]
+    fn export_verify_audit_bundle_test() {
+        setup_logger();
+        let txid = gen_dummy_hash(1);
+        let storage = gen_audit_storage(&txid);
+        let signer = AuditSigner::from_hex(&"11".repeat(32)).unwrap();
+
+        // signed bundle with progress reported per section
+        let mut bundle = vec![];
+        let mut sections = vec![];
+        let summary = export_audit_bundle(&storage, txid, Some(&signer), &mut bundle, &mut |progress| {
+            sections.push((progress.section.clone(), progress.sections_done))
+        })
+        .unwrap();
+        assert_eq!(txid, summary.txid);
+        // request, annotation, bid, challenge record, response, attestation, logs
+        assert_eq!(7, summary.num_records);
+        assert_eq!(Some(signer.public_key().serialize()[..].to_hex()), summary.pubkey);
+        assert_eq!(
+            vec![
+                ("request".to_owned(), 0),
+                ("bids".to_owned(), 1),
+                ("challenges".to_owned(), 2),
+                ("response".to_owned(), 3),
+                ("logs".to_owned(), 4),
+                ("signature".to_owned(), 5),
+                ("signature".to_owned(), 6),
+            ],
+            sections
+        );
+        assert_eq!(summary, verify_audit_bundle(&bundle, &signer.public_key()).unwrap());
+
+        // bid payments written in plaintext
+        let mut reader = Cursor::new(&bundle[..]);
+        let mut bids = vec![];
+        while let Some((collection, _, doc)) = read_record(&mut reader).unwrap() {
+            if collection == AUDIT_BID {
+                bids.push(doc_to_bid_with_cipher(&doc, None).unwrap());
+            }
+        }
+        assert_eq!(storage.get_bids(txid).unwrap(), bids);
+
+        // attestation of the response integrity hash chain and merkle root
+        let mut reader = Cursor::new(&bundle[..]);
+        let mut attestation = None;
+        while let Some((collection, _, doc)) = read_record(&mut reader).unwrap() {
+            if collection == AUDIT_RESPONSE_ATTESTATION {
+                attestation = Some(doc);
+            }
+        }
+        let attestation = attestation.unwrap();
+        assert!(attestation.get_bool("integrity_verified").unwrap());
+        assert_eq!(1, attestation.get_array("hashes").unwrap().len());
+        let response = storage.get_response(txid).unwrap().unwrap();
+        assert_eq!(
+            ResponseMerkleTree::new(&response).root().unwrap().to_string(),
+            attestation.get_str("merkle_root").unwrap()
+        );
+
+        // tampered bundles
+        let mut tampered = bundle.clone();
+        let last = tampered.len() / 2;
+        tampered[last] ^= 1;
+        assert!(verify_audit_bundle(&tampered, &signer.public_key()).is_err());
+        assert!(verify_audit_bundle(&bundle[..bundle.len() - 1], &signer.public_key()).is_err());
+
+        // bundle re-signed by another key
+        let other = AuditSigner::from_hex(&"22".repeat(32)).unwrap();
+        let mut resigned = vec![];
+        let _ = export_audit_bundle(&storage, txid, Some(&other), &mut resigned, &mut |_| {}).unwrap();
+        assert!(verify_audit_bundle(&resigned, &other.public_key()).is_ok());
+        assert_eq!(
+            format!(
+                "coordinator error: generic Error: invalid audit bundle: bundle signed by unexpected key {}",
+                other.public_key().serialize()[..].to_hex()
+            ),
+            verify_audit_bundle(&resigned, &signer.public_key())
+                .unwrap_err()
+                .to_string()
+        );
+
+        // unsigned bundle
+        let mut unsigned = vec![];
+        let summary = export_audit_bundle(&storage, txid, None, &mut unsigned, &mut |_| {}).unwrap();
+        assert_eq!(None, summary.signature);
+        assert_eq!(
+            "coordinator error: generic Error: invalid audit bundle: bundle is unsigned",
+            verify_audit_bundle(&unsigned, &signer.public_key())
+                .unwrap_err()
+                .to_string()
+        );
+
+        // unknown request
+        assert!(export_audit_bundle(&storage, gen_dummy_hash(2), None, &mut vec![], &mut |_| {}).is_err());
+    }
+
+    #[test]
+    fn audit_bundles_test() {
+        setup_logger();
+        let txid = gen_dummy_hash(1);
+        let storage = gen_audit_storage(&txid);
+        let bundles = AuditBundles::new(AuditSigner::from_hex(&"11".repeat(32)).ok());
+        assert_eq!(None, bundles.status(txid));
+
+        // started bundle not restarted until generated
+        let status = bundles.start(txid).unwrap();
+        assert_eq!(AuditBundleState::Running, status.state);
+        assert_eq!(0, status.progress.sections_done);
+        assert_eq!(Some(status), bundles.status(txid));
+        assert_eq!(None, bundles.start(txid));
+        assert_eq!(None, bundles.bundle(txid));
+
+        bundles.generate(&storage, txid);
+        let status = bundles.status(txid).unwrap();
+        assert_eq!(AuditBundleState::Complete, status.state);
+        assert_eq!(6, status.progress.sections_done);
+        assert_eq!(7, status.progress.num_records);
+        let bundle = bundles.bundle(txid).unwrap();
+        assert_eq!(
+            status.summary.unwrap(),
+            verify_audit_bundle(&bundle, &AuditSigner::from_hex(&"11".repeat(32)).unwrap().public_key()).unwrap()
+        );
+
+        // failed bundle of an unknown request
+        let unknown = gen_dummy_hash(2);
+        let _ = bundles.start(unknown).unwrap();
+        bundles.generate(&storage, unknown);
+        let status = bundles.status(unknown).unwrap();
+        assert_eq!(AuditBundleState::Failed, status.state);
+        assert_eq!(
+            Some(format!(
+                "coordinator error: generic Error: request {} not found",
+                unknown
+            )),
+            status.error
+        );
+        assert!(bundles.bundle(unknown).is_none());
+
+        // earliest finished bundles dropped first
+        for i in 0..AUDIT_BUNDLES_MAX {
+            let _ = bundles.start(gen_dummy_hash(10 + i as u8)).unwrap();
+        }
+        assert_eq!(None, bundles.status(txid));
+        assert_eq!(None, bundles.status(unknown));
+        assert!(bundles.status(gen_dummy_hash(10)).is_some());
+    }
+}
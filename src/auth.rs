@@ -0,0 +1,276 @@
+//! Auth
+//!
+//! Authentication providers used by the api to authenticate callers from the
+//! value of the authorization header of incoming requests
+
+use std::collections::{HashMap, HashSet};
+use std::str;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use base64::{decode as b64decode, encode as b64encode};
+
+use crate::config::ApiConfig;
+use crate::error::InputErrorType::MissingArgument;
+use crate::error::{CError, Result};
+use crate::util::http;
+
+/// Duration that active tokens returned by the introspection endpoint are
+/// cached for before being introspected again
+pub const AUTH_INTROSPECTION_CACHE_SECS: u64 = 60;
+
+/// Authentication provider trait defining how api callers are authenticated
+pub trait AuthProvider: Send + Sync {
+    /// Check whether the authorization header value of a request, if any,
+    /// authenticates the caller
+    fn authenticate(&self, authorization: Option<&str>) -> bool;
+}
+
+/// Get the value of an authorization header for a scheme, e.g. Basic or Bearer
+fn scheme_value<'a>(authorization: Option<&'a str>, scheme: &str) -> Option<&'a str> {
+    let auth_parts: Vec<&str> = authorization?.split(" ").collect();
+    if auth_parts.len() == 2 && auth_parts[0] == scheme {
+        return Some(auth_parts[1]);
+    }
+    None
+}
+
+/// Get the user:pass credentials of a Basic authorization header value
+pub fn basic_credentials(authorization: Option<&str>) -> Option<String> {
+    let auth_part = b64decode(scheme_value(authorization, "Basic")?).ok()?;
+    str::from_utf8(&auth_part).ok().map(|up| up.to_owned())
+}
+
+/// Basic authentication against a single user:pass credential
+pub struct BasicAuthProvider {
+    /// Expected user:pass credentials, or None if no caller is authenticated
+    credentials: Option<String>,
+}
+
+impl BasicAuthProvider {
+    /// Return new BasicAuthProvider from user and pass. An empty user or pass
+    /// authenticates no caller
+    pub fn new(user: &str, pass: &str) -> BasicAuthProvider {
+        BasicAuthProvider {
+            credentials: if user.len() == 0 || pass.len() == 0 {
+                None
+            } else {
+                Some(format!("{}:{}", user, pass))
+            },
+        }
+    }
+}
+
+impl AuthProvider for BasicAuthProvider {
+    fn authenticate(&self, authorization: Option<&str>) -> bool {
+        match &self.credentials {
+            Some(credentials) => basic_credentials(authorization).map_or(false, |up| up == *credentials),
+            None => false,
+        }
+    }
+}
+
+/// Bearer token authentication against a static list of tokens
+pub struct TokenAuthProvider {
+    /// Accepted tokens
+    tokens: HashSet<String>,
+}
+
+impl TokenAuthProvider {
+    /// Return new TokenAuthProvider from a list of accepted tokens
+    pub fn new(tokens: &[String]) -> TokenAuthProvider {
+        TokenAuthProvider {
+            tokens: tokens.iter().cloned().collect(),
+        }
+    }
+}
+
+impl AuthProvider for TokenAuthProvider {
+    fn authenticate(&self, authorization: Option<&str>) -> bool {
+        scheme_value(authorization, "Bearer").map_or(false, |token| self.tokens.contains(token))
+    }
+}
+
+/// Percent encode a form value leaving only unreserved characters as is
+fn form_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Bearer token authentication against an OAuth2 token introspection endpoint
+/// (RFC 7662). The api user and pass are used as the client credentials of the
+/// introspection request and active tokens are cached for a short duration
+pub struct OAuth2AuthProvider {
+    /// Introspection endpoint url
+    url: String,
+    /// Basic authorization header value of the introspection request
+    client_auth: String,
+    /// Active tokens with the time they were introspected
+    cache: Mutex<HashMap<String, Instant>>,
+}
+
+impl OAuth2AuthProvider {
+    /// Return new OAuth2AuthProvider from the introspection endpoint url and
+    /// the client credentials
+    pub fn new(url: &str, client_id: &str, client_secret: &str) -> OAuth2AuthProvider {
+        OAuth2AuthProvider {
+            url: url.to_owned(),
+            client_auth: format!("Basic {}", b64encode(&format!("{}:{}", client_id, client_secret))),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Introspect a token returning whether the token is active
+    fn introspect(&self, token: &str) -> Result<bool> {
+        let resp = http::post(
+            &self.url,
+            "application/x-www-form-urlencoded",
+            Some(&self.client_auth),
+            &format!("token={}", form_encode(token)),
+        )?;
+        let resp_json: serde_json::Value =
+            serde_json::from_str(&resp).map_err(|e| CError::Generic(format!("bad introspection response: {}", e)))?;
+        Ok(resp_json["active"].as_bool().unwrap_or(false))
+    }
+}
+
+impl AuthProvider for OAuth2AuthProvider {
+    fn authenticate(&self, authorization: Option<&str>) -> bool {
+        let token = match scheme_value(authorization, "Bearer") {
+            Some(token) => token,
+            None => return false,
+        };
+        let cache_duration = Duration::from_secs(AUTH_INTROSPECTION_CACHE_SECS);
+        {
+            let mut cache = self.cache.lock().unwrap();
+            cache.retain(|_, introspected| introspected.elapsed() < cache_duration);
+            if cache.contains_key(token) {
+                return true;
+            }
+        }
+        match self.introspect(token) {
+            Ok(true) => {
+                let _ = self.cache.lock().unwrap().insert(token.to_owned(), Instant::now());
+                true
+            }
+            Ok(false) => false,
+            Err(e) => {
+                warn!("token introspection failed: {}", e);
+                false
+            }
+        }
+    }
+}
+
+/// Get the authentication provider set in the api config. Supported providers
+/// are basic (default), token and oauth2
+pub fn auth_provider(config: &ApiConfig) -> Result<Box<dyn AuthProvider>> {
+    match config.auth_provider.as_str() {
+        "" | "basic" => Ok(Box::new(BasicAuthProvider::new(&config.user, &config.pass))),
+        "token" => {
+            if config.auth_tokens.len() == 0 {
+                return Err(CError::InputError(MissingArgument, "api.auth_tokens".into()).into());
+            }
+            Ok(Box::new(TokenAuthProvider::new(&config.auth_tokens)))
+        }
+        "oauth2" => match &config.auth_introspection_url {
+            Some(url) => Ok(Box::new(OAuth2AuthProvider::new(url, &config.user, &config.pass))),
+            None => Err(CError::InputError(MissingArgument, "api.auth_introspection_url".into()).into()),
+        },
+        provider => Err(CError::Generic(format!("unknown api auth provider: {}", provider)).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::util::testing::{serve_once, setup_logger};
+
+    #[test]
+    fn basic_auth_provider_test() {
+        setup_logger();
+        let provider = BasicAuthProvider::new("user", "pass");
+        let basic = |up: &str| format!("Basic {}", b64encode(up));
+
+        assert!(!provider.authenticate(None));
+        assert!(!provider.authenticate(Some(&basic("user:wrong"))));
+        assert!(!provider.authenticate(Some(&format!("Bearer {}", b64encode("user:pass")))));
+        assert!(!provider.authenticate(Some("Basic user:pass")));
+        assert!(provider.authenticate(Some(&basic("user:pass"))));
+
+        // empty credentials authenticate no caller
+        let provider = BasicAuthProvider::new("", "");
+        assert!(!provider.authenticate(None));
+        assert!(!provider.authenticate(Some(&basic(":"))));
+        assert!(!BasicAuthProvider::new("user", "").authenticate(Some(&basic("user:"))));
+    }
+
+    #[test]
+    fn token_auth_provider_test() {
+        setup_logger();
+        let provider = TokenAuthProvider::new(&["token1".to_owned(), "token2".to_owned()]);
+
+        assert!(!provider.authenticate(None));
+        assert!(!provider.authenticate(Some("Bearer token3")));
+        assert!(!provider.authenticate(Some("Basic token1")));
+        assert!(provider.authenticate(Some("Bearer token1")));
+        assert!(provider.authenticate(Some("Bearer token2")));
+    }
+
+    #[test]
+    fn oauth2_auth_provider_test() {
+        setup_logger();
+        assert_eq!("a%2Bb%3D%20c-_.~", form_encode("a+b= c-_.~"));
+
+        // inactive token
+        let (url, handle) = serve_once("HTTP/1.0 200 OK\r\n\r\n{\"active\":false}");
+        let provider = OAuth2AuthProvider::new(&url, "client", "secret");
+        assert!(!provider.authenticate(Some("Bearer token")));
+        let request = handle.join().unwrap();
+        assert!(request.contains(&format!("Authorization: Basic {}\r\n", b64encode("client:secret"))));
+        assert!(request.ends_with("\r\n\r\ntoken=token"));
+
+        // active token introspected once and then cached
+        let (url, handle) = serve_once("HTTP/1.0 200 OK\r\n\r\n{\"active\":true}");
+        let provider = OAuth2AuthProvider::new(&url, "client", "secret");
+        assert!(provider.authenticate(Some("Bearer token")));
+        let _ = handle.join().unwrap();
+        assert!(provider.authenticate(Some("Bearer token")));
+
+        // introspection failure
+        let (url, _) = serve_once("HTTP/1.0 500 Internal Server Error\r\n\r\n");
+        let provider = OAuth2AuthProvider::new(&url, "client", "secret");
+        assert!(!provider.authenticate(Some("Bearer token")));
+        assert!(!provider.authenticate(None));
+    }
+
+    #[test]
+    fn auth_provider_test() {
+        setup_logger();
+        let mut config = ApiConfig::default();
+        config.user = "user".to_owned();
+        config.pass = "pass".to_owned();
+        assert!(auth_provider(&config)
+            .unwrap()
+            .authenticate(Some(&format!("Basic {}", b64encode("user:pass")))));
+
+        config.auth_provider = "token".to_owned();
+        assert!(auth_provider(&config).is_err());
+        config.auth_tokens = vec!["token".to_owned()];
+        assert!(auth_provider(&config).unwrap().authenticate(Some("Bearer token")));
+
+        config.auth_provider = "oauth2".to_owned();
+        assert!(auth_provider(&config).is_err());
+        config.auth_introspection_url = Some("http://localhost/introspect".to_owned());
+        assert!(auth_provider(&config).is_ok());
+
+        config.auth_provider = "ldap".to_owned();
+        assert!(auth_provider(&config).is_err());
+    }
+}
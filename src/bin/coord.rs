@@ -4,13 +4,160 @@
 
 #[macro_use]
 extern crate log;
+extern crate bitcoin;
 extern crate coordinator;
 extern crate env_logger;
+extern crate serde_json;
 
 use std::env;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
 use std::process;
+use std::str::FromStr;
+
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::hashes::sha256d;
+use bitcoin::secp256k1::PublicKey;
+
+/// Check the config and any reachable nodes, printing a report of the checks
+/// performed and exiting with a non-zero code on failure
+fn check_config() {
+    env::set_var("RUST_LOG", "error");
+    env_logger::init();
+    match coordinator::config::Config::new() {
+        Ok(config) => {
+            let mut report = coordinator::config_check::check_config(&config);
+            coordinator::config_check::check_nodes(&config, &mut report);
+            println!("{}", report);
+            process::exit(if report.is_ok() { 0 } else { 1 });
+        }
+        Err(e) => {
+            println!("[FAIL] config: {}", e);
+            println!("       hint: check config/default.toml and the CO_ environment variables");
+            process::exit(1);
+        }
+    }
+}
+
+/// Recompute the stored response of a request from its challenge records or
+/// the journal, printing the report of the differences and exiting with a
+/// non-zero code if the stored response diverges or cannot be recomputed
+fn rerun_response(txid: Option<String>) {
+    env::set_var("RUST_LOG", "error");
+    env_logger::init();
+    let txid = match txid.as_ref().and_then(|txid| sha256d::Hash::from_hex(txid).ok()) {
+        Some(txid) => txid,
+        None => {
+            println!("usage: coord --rerun-response <request_txid>");
+            process::exit(1);
+        }
+    };
+    let report = coordinator::config::Config::new().and_then(|config| {
+        let storage = coordinator::interfaces::storage::MongoStorage::new(config.storage.clone())?;
+        coordinator::rerun::rerun_response(&storage, txid, &config.journal_path)
+    });
+    match report {
+        Ok(Some(report)) => {
+            println!("{}", serde_json::to_string_pretty(&report).unwrap());
+            process::exit(if report.matches() { 0 } else { 1 });
+        }
+        Ok(None) => println!("request {} has no stored response", txid),
+        Err(e) => println!("rerun failure: {}", e),
+    }
+    process::exit(1);
+}
+
+/// Export the signed audit bundle of a request into a file, printing the
+/// progress of each section and the bundle summary and exiting with a non-zero
+/// code on failure
+fn export_audit_bundle(txid: Option<String>, path: Option<String>) {
+    env::set_var("RUST_LOG", "error");
+    env_logger::init();
+    let (txid, path) = match (txid.as_ref().and_then(|txid| sha256d::Hash::from_hex(txid).ok()), path) {
+        (Some(txid), Some(path)) => (txid, path),
+        _ => {
+            println!("usage: coord --export-audit-bundle <request_txid> <bundle file>");
+            process::exit(1);
+        }
+    };
+    let summary = coordinator::config::Config::new().and_then(|config| {
+        let storage = coordinator::interfaces::storage::MongoStorage::new(config.storage.clone())?;
+        let signer = coordinator::audit::AuditSigner::from_config(&config)?;
+        let file = File::create(&path)
+            .map_err(|e| coordinator::error::CError::Generic(format!("failed creating {}: {}", path, e)))?;
+        let mut writer = BufWriter::new(file);
+        let summary =
+            coordinator::audit::export_audit_bundle(&storage, txid, signer.as_ref(), &mut writer, &mut |progress| {
+                println!(
+                    "[{}/{}] {} ({} records)",
+                    progress.sections_done, progress.sections_total, progress.section, progress.num_records
+                )
+            })?;
+        writer
+            .flush()
+            .map_err(|e| coordinator::error::CError::Generic(format!("failed writing {}: {}", path, e)))?;
+        Ok(summary)
+    });
+    match summary {
+        Ok(summary) => {
+            println!("{}", serde_json::to_string_pretty(&summary).unwrap());
+            process::exit(0);
+        }
+        Err(e) => println!("audit bundle failure: {}", e),
+    }
+    process::exit(1);
+}
+
+/// Verify an audit bundle file against the expected public key of the audit
+/// signing key, printing the bundle summary and exiting with a non-zero code if
+/// the bundle is unsigned, signed by another key or tampered with
+fn verify_audit_bundle(path: Option<String>, pubkey: Option<String>) {
+    env::set_var("RUST_LOG", "error");
+    env_logger::init();
+    let (path, pubkey) = match (
+        path,
+        pubkey.as_ref().and_then(|pubkey| PublicKey::from_str(pubkey).ok()),
+    ) {
+        (Some(path), Some(pubkey)) => (path, pubkey),
+        _ => {
+            println!("usage: coord --verify-audit-bundle <bundle file> <audit public key>");
+            process::exit(1);
+        }
+    };
+    let mut bundle = vec![];
+    let summary = File::open(&path)
+        .and_then(|mut file| file.read_to_end(&mut bundle))
+        .map_err(|e| {
+            coordinator::error::Error::from(coordinator::error::CError::Generic(format!(
+                "failed reading {}: {}",
+                path, e
+            )))
+        })
+        .and_then(|_| coordinator::audit::verify_audit_bundle(&bundle, &pubkey));
+    match summary {
+        Ok(summary) => {
+            println!("{}", serde_json::to_string_pretty(&summary).unwrap());
+            process::exit(0);
+        }
+        Err(e) => println!("audit bundle failure: {}", e),
+    }
+    process::exit(1);
+}
 
 fn main() {
+    if env::args().any(|arg| arg == "--check-config") {
+        check_config();
+    }
+    if let Some(pos) = env::args().position(|arg| arg == "--rerun-response") {
+        rerun_response(env::args().nth(pos + 1));
+    }
+    if let Some(pos) = env::args().position(|arg| arg == "--export-audit-bundle") {
+        export_audit_bundle(env::args().nth(pos + 1), env::args().nth(pos + 2));
+    }
+    if let Some(pos) = env::args().position(|arg| arg == "--verify-audit-bundle") {
+        verify_audit_bundle(env::args().nth(pos + 1), env::args().nth(pos + 2));
+    }
+
     // Fetch config which is set from default values in config
     // and any values overriden by the corresponding env variable
     match coordinator::config::Config::new() {
@@ -19,8 +166,12 @@ fn main() {
             // info, warning, debug, error, coordinator(for all)
             env::set_var("RUST_LOG", &config.log_level);
             env::set_var("RUST_BACKTRACE", "1");
-            // Init env logger with value set from config
-            env_logger::init();
+            // Init request logger with value set from config
+            coordinator::util::logger::init();
+            // reload rotated rpc credentials on SIGHUP
+            if let Err(e) = coordinator::util::ocean::reload_credentials_on_sighup() {
+                error!("{}", e);
+            }
             if let Err(e) = coordinator::coordinator::run(config) {
                 error!("daemon failure: {}", e);
             }
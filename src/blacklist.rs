@@ -0,0 +1,239 @@
+//! Blacklist
+//!
+//! Strike based temporary blacklist of bids repeatedly submitting challenge
+//! proofs with invalid signatures, so that the listener stops spending time
+//! verifying their proofs until a cool-down period has passed
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bitcoin::hashes::sha256d;
+
+use crate::challenger::ChallengeState;
+use crate::error::Result;
+use crate::interfaces::bid::BidBlacklisting;
+use crate::interfaces::request::Request;
+use crate::interfaces::storage::Storage;
+use crate::proof::ChallengeProof;
+
+/// Blacklist state of strikes and active blacklistings by bid txid
+struct BlacklistState {
+    /// Consecutive invalid proofs of bids that are not blacklisted
+    strikes: HashMap<sha256d::Hash, u32>,
+    /// Blacklistings of bids, removed once expired
+    blacklistings: HashMap<sha256d::Hash, BidBlacklisting>,
+}
+
+/// Blacklist shared between the listener and the api. Each invalid proof
+/// signature of a bid counts as a strike and a bid reaching the strike
+/// threshold is blacklisted for the cool-down period, while a valid proof
+/// resets the strikes of the bid. Blacklistings are logged and persisted in
+/// storage, if set, so that they survive restarts
+pub struct Blacklist {
+    /// Number of invalid proofs that blacklist a bid; 0 disables blacklisting
+    threshold: u32,
+    /// Blacklisting duration in seconds
+    cooldown: u64,
+    /// Strikes and blacklistings
+    state: Mutex<BlacklistState>,
+    /// Storage persisting blacklistings; None if not persisted
+    storage: Option<Arc<dyn Storage + Send + Sync>>,
+}
+
+/// Get the current unix timestamp in seconds
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl Blacklist {
+    /// Create a blacklist with the strike threshold and cool-down in seconds,
+    /// without persisting blacklistings
+    pub fn new(threshold: u32, cooldown: u64) -> Blacklist {
+        Blacklist {
+            threshold,
+            cooldown,
+            state: Mutex::new(BlacklistState {
+                strikes: HashMap::new(),
+                blacklistings: HashMap::new(),
+            }),
+            storage: None,
+        }
+    }
+
+    /// Blacklist that never blacklists any bids
+    pub fn disabled() -> Blacklist {
+        Blacklist::new(0, 0)
+    }
+
+    /// Create a blacklist persisting blacklistings in storage, restoring any
+    /// stored blacklistings that are still active
+    pub fn with_storage(threshold: u32, cooldown: u64, storage: Arc<dyn Storage + Send + Sync>) -> Result<Blacklist> {
+        let mut blacklist = Blacklist::new(threshold, cooldown);
+        let now = now();
+        {
+            let state = blacklist.state.get_mut().unwrap();
+            for blacklisting in storage.get_bid_blacklistings(None)? {
+                if blacklisting.is_active(now) {
+                    info!(
+                        "Restored blacklisting of bid {} until {}",
+                        blacklisting.txid, blacklisting.until
+                    );
+                    let _ = state.blacklistings.insert(blacklisting.txid, blacklisting);
+                }
+            }
+        }
+        blacklist.storage = Some(storage);
+        Ok(blacklist)
+    }
+
+    /// Get the active blacklisting of a bid, if any
+    pub fn check(&self, bid: &sha256d::Hash) -> Option<BidBlacklisting> {
+        let mut state = self.state.lock().unwrap();
+        let active = match state.blacklistings.get(bid) {
+            Some(blacklisting) => blacklisting.is_active(now()),
+            None => return None,
+        };
+        if !active {
+            let _ = state.blacklistings.remove(bid);
+            return None;
+        }
+        state.blacklistings.get(bid).cloned()
+    }
+
+    /// Check that the bid of a challenge proof is not blacklisted, returning
+    /// the request of the active challenge to record any strikes against or
+    /// the rejection reason
+    pub fn check_proof(
+        &self,
+        proof: &ChallengeProof,
+        challenge: &Arc<RwLock<Option<ChallengeState>>>,
+    ) -> std::result::Result<Request, String> {
+        let request = match *challenge.read().unwrap() {
+            Some(ref state) => state.request.clone(),
+            None => return Err("no-active-challenge".to_owned()),
+        };
+        if let Some(blacklisting) = self.check(&proof.bid.txid) {
+            return Err(format!("bid-blacklisted: until {}", blacklisting.until));
+        }
+        Ok(request)
+    }
+
+    /// Record an invalid proof of a request bid. Returns the new blacklisting
+    /// if the bid reached the strike threshold
+    pub fn strike(&self, bid: &sha256d::Hash, request: &Request) -> Option<BidBlacklisting> {
+        if self.threshold == 0 {
+            return None;
+        }
+        let blacklisting = {
+            let mut state = self.state.lock().unwrap();
+            let strikes = {
+                let strikes = state.strikes.entry(*bid).or_insert(0);
+                *strikes += 1;
+                *strikes
+            };
+            if strikes < self.threshold {
+                return None;
+            }
+            let _ = state.strikes.remove(bid);
+            let since = now();
+            let blacklisting = BidBlacklisting {
+                txid: *bid,
+                request: request.txid,
+                genesis_blockhash: request.genesis_blockhash,
+                strikes,
+                since,
+                until: since + self.cooldown,
+            };
+            let _ = state.blacklistings.insert(*bid, blacklisting.clone());
+            blacklisting
+        };
+        warn!(
+            "Blacklisted bid {} after {} invalid proofs until {}",
+            bid, blacklisting.strikes, blacklisting.until
+        );
+        if let Some(storage) = &self.storage {
+            if let Err(e) = storage.save_bid_blacklisting(&blacklisting) {
+                warn!("failed storing blacklisting of bid {}: {}", bid, e);
+            }
+        }
+        Some(blacklisting)
+    }
+
+    /// Reset the strikes of a bid after a valid proof
+    pub fn clear_strikes(&self, bid: &sha256d::Hash) {
+        let _ = self.state.lock().unwrap().strikes.remove(bid);
+    }
+
+    /// Get the active blacklistings, with an optional genesis hash
+    pub fn blacklistings(&self, genesis: Option<sha256d::Hash>) -> Vec<BidBlacklisting> {
+        let now = now();
+        let mut blacklistings: Vec<BidBlacklisting> = self
+            .state
+            .lock()
+            .unwrap()
+            .blacklistings
+            .values()
+            .filter(|blacklisting| blacklisting.is_active(now))
+            .filter(|blacklisting| genesis.map_or(true, |hash| blacklisting.genesis_blockhash == hash))
+            .cloned()
+            .collect();
+        blacklistings.sort_by_key(|blacklisting| blacklisting.since);
+        blacklistings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::util::testing::{gen_challenge_state, gen_dummy_hash, setup_logger};
+
+    #[test]
+    fn blacklist_test() {
+        setup_logger();
+        let request = gen_challenge_state(&gen_dummy_hash(1)).request;
+        let bid = gen_dummy_hash(5);
+
+        // disabled blacklist never blacklists
+        let blacklist = Blacklist::disabled();
+        for _ in 0..10 {
+            assert_eq!(None, blacklist.strike(&bid, &request));
+        }
+        assert_eq!(None, blacklist.check(&bid));
+
+        // strikes below the threshold and reset by valid proofs
+        let blacklist = Blacklist::new(3, 600);
+        assert_eq!(None, blacklist.strike(&bid, &request));
+        assert_eq!(None, blacklist.strike(&bid, &request));
+        blacklist.clear_strikes(&bid);
+        assert_eq!(None, blacklist.strike(&bid, &request));
+        assert_eq!(None, blacklist.strike(&bid, &request));
+        assert_eq!(None, blacklist.check(&bid));
+
+        // threshold reached
+        let blacklisting = blacklist.strike(&bid, &request).unwrap();
+        assert_eq!(bid, blacklisting.txid);
+        assert_eq!(request.txid, blacklisting.request);
+        assert_eq!(request.genesis_blockhash, blacklisting.genesis_blockhash);
+        assert_eq!(3, blacklisting.strikes);
+        assert_eq!(blacklisting.since + 600, blacklisting.until);
+        assert_eq!(Some(blacklisting.clone()), blacklist.check(&bid));
+        assert_eq!(None, blacklist.check(&gen_dummy_hash(6)));
+        assert_eq!(vec![blacklisting.clone()], blacklist.blacklistings(None));
+        assert_eq!(
+            vec![blacklisting],
+            blacklist.blacklistings(Some(request.genesis_blockhash))
+        );
+        assert_eq!(0, blacklist.blacklistings(Some(gen_dummy_hash(9))).len());
+
+        // blacklisting without cool-down expires immediately
+        let blacklist = Blacklist::new(1, 0);
+        assert!(blacklist.strike(&bid, &request).is_some());
+        assert_eq!(None, blacklist.check(&bid));
+        assert_eq!(0, blacklist.blacklistings(None).len());
+    }
+}
@@ -3,26 +3,42 @@
 //! Methods and models for fetching, structuring, storing and running challenge
 //! requests
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{Receiver, RecvTimeoutError};
-use std::sync::{Arc, RwLock};
-use std::{thread, time};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time;
 
 use bitcoin::hashes::sha256d;
+use serde::Serialize;
 
+use crate::clock::Clock;
+use crate::config::ChallengeTiming;
+use crate::coverage::ChallengeCoverage;
 use crate::error::{CError, Error, Result};
-use crate::interfaces::clientchain::ClientChain;
+use crate::interfaces::clientchain::{ChallengeCommitment, ClientChain, SentChallenge};
 use crate::interfaces::service::Service;
 use crate::interfaces::storage::Storage;
 use crate::interfaces::{
     bid::{Bid, BidSet},
-    request::Request,
-    response::Response,
+    request::{Request, RequestFailure, RequestRejection},
+    response::{BidProof, ChallengeLatency, ChallengeRecord, ChallengeStats, Response, ResponseSnapshot},
 };
+use crate::journal::{Journal, JournalEvent};
+use crate::payload::ChallengePayload;
+use crate::stall::{ClientChainStall, StallDetector, StallStatus};
+use crate::util::logger::flush_request_logs;
+use crate::util::scheduler::{JobStatus, Schedule, Scheduler};
+use crate::watchdog::Heartbeat;
 
 /// Verify attempt interval to client in ms
 pub const CHALLENGER_VERIFY_INTERVAL: u64 = 100;
 
+/// Max interval in ms of waiting for challenge responses before checking the
+/// response deadline on the clock again
+pub const CHALLENGER_RESPONSE_WAIT_INTERVAL: u64 = 100;
+
 /// Attempts to verify that a challenge has been included in the client chain
 /// This makes attempts every CHALLENGER_VERIFY_INTERVAL ms and for the verify
 /// duration specified, which is variable in order to allow easy configuration
@@ -30,11 +46,12 @@ fn verify_challenge<K: ClientChain>(
     hash: &sha256d::Hash,
     clientchain: &K,
     verify_duration: time::Duration,
+    clock: &dyn Clock,
 ) -> Result<()> {
     info! {"verifying challenge hash: {}", hash}
-    let start_time = time::Instant::now();
+    let start_time = clock.now();
     loop {
-        let now = time::Instant::now();
+        let now = clock.now();
         if start_time + verify_duration > now {
             if clientchain.verify_challenge(&hash)? {
                 info! {"challenge verified"}
@@ -45,34 +62,80 @@ fn verify_challenge<K: ClientChain>(
         }
         // This will potentially be replaced by subscribing to the ocean node
         // for transaction updates but this is good enough for now
-        thread::sleep(std::time::Duration::from_millis(CHALLENGER_VERIFY_INTERVAL))
+        clock.sleep(time::Duration::from_millis(CHALLENGER_VERIFY_INTERVAL))
     }
     Err(Error::from(CError::UnverifiedChallenge))
 }
 
+/// Commit to a challenge on the client chain and reveal it once the
+/// commitment has been included in the client chain, so that the challenge
+/// can not be known before a block committing to it. The latest challenge
+/// holds the commitment while the challenge is pending reveal, rejecting any
+/// proofs received meanwhile. The unspent reserved by a challenge that is not
+/// revealed is released. Returns the revealed challenge and commitment or None
+/// if the challenge is skipped by the pre-flight check
+fn send_committed_challenge<K: ClientChain>(
+    clientchain: &K,
+    request: &sha256d::Hash,
+    sequence: u32,
+    latest_challenge: &LatestChallenge,
+    verify_duration: time::Duration,
+    clock: &dyn Clock,
+) -> Result<Option<(SentChallenge, Option<ChallengeCommitment>)>> {
+    let committed = match clientchain.commit_challenge(request, sequence)? {
+        Some(committed) => committed,
+        None => return Ok(None),
+    };
+    info! {"challenge committed to: {}", committed.commitment.hash}
+    latest_challenge.commit(committed.commitment);
+    if let Err(e) = verify_challenge(&committed.commitment.txid, clientchain, verify_duration, clock)
+        .and_then(|()| clientchain.reveal_challenge(&committed))
+    {
+        latest_challenge.clear();
+        clientchain.release_challenge(&committed.challenge.txid);
+        return Err(e);
+    }
+    info! {"challenge revealed: {}", committed.challenge.txid}
+    Ok(Some((committed.challenge, Some(committed.commitment))))
+}
+
 /// Get responses to the challenge by reading data from the channel receiver
 /// Channel is read for a configurable duration and then the method returns
-/// all the responses that have been received for a specific challenge hash
+/// all the responses that have been received for a specific challenge hash.
+/// The deadline is kept on the clock, waiting on the channel for up to
+/// CHALLENGER_RESPONSE_WAIT_INTERVAL ms at a time. The latency of the first
+/// response of each bid since the challenge was sent is added to the proof
+/// latency samples of the request and of the bid, and the unix time in ms that
+/// it was accepted at to the accepted times by bid
 fn get_challenge_response(
     challenge_hash: &sha256d::Hash,
     verify_rx: &Receiver<ChallengeResponse>,
     get_duration: time::Duration,
+    sent_time: time::Instant,
+    latency: &mut ChallengeLatency,
+    accepted: &mut HashMap<sha256d::Hash, u64>,
+    clock: &dyn Clock,
 ) -> Result<ChallengeResponseIds> {
     let mut responses = ChallengeResponseIds::new();
 
-    let start_time = time::Instant::now();
+    let start_time = clock.now();
     loop {
-        let now = time::Instant::now();
+        let now = clock.now();
         if start_time + get_duration > now {
-            let duration = start_time + get_duration - now;
-            match verify_rx.recv_timeout(duration) {
+            let wait =
+                (start_time + get_duration - now).min(time::Duration::from_millis(CHALLENGER_RESPONSE_WAIT_INTERVAL));
+            match verify_rx.recv_timeout(clock.timeout(wait)) {
                 Ok(resp) => {
                     if resp.0 == *challenge_hash {
                         // filter old invalid/responses
-                        let _ = responses.insert(resp.1.txid);
+                        if responses.insert(resp.1.txid) {
+                            let elapsed = clock.now().duration_since(sent_time);
+                            latency.add_proof(resp.1.txid, elapsed.as_millis() as u64);
+                            let _ = accepted.insert(resp.1.txid, clock.unix_time().as_millis() as u64);
+                        }
                     }
                 }
-                Err(RecvTimeoutError::Timeout) => {} // ignore timeout - it's allowed
+                Err(RecvTimeoutError::Timeout) => clock.timed_out(wait), // ignore timeout - it's allowed
                 Err(RecvTimeoutError::Disconnected) => {
                     return Err(Error::from(CError::ReceiverDisconnected));
                 }
@@ -85,61 +148,414 @@ fn get_challenge_response(
     Ok(responses)
 }
 
-/// Run challenge for a specific request on the client chain. On each new
-/// service height send a challenge on the client chain continuing until active
-/// request expires (end_blockheight). For each challenge, verify it has been
-/// included to the client chain and then fetch all challenge responses for a
-/// specified time duration. These responses are then stored via the storage
-/// interface
+/// Get the stored response of a specific request, applying any per challenge
+/// records of the request that are missing from the aggregate response, e.g.
+/// after an interrupted response update. The recovered response is stored
+pub fn recover_response<D: Storage + ?Sized>(storage: &D, request_hash: sha256d::Hash) -> Result<Response> {
+    let mut response = storage.get_response(request_hash)?.unwrap_or(Response::new());
+    let records = storage.get_challenge_records(request_hash)?;
+    let applied = response.apply_records(&records);
+    if applied > 0 {
+        warn! {"Recovered {} challenges of request {} response from challenge records", applied, request_hash};
+        storage.save_response(request_hash, &response)?;
+    }
+    Ok(response)
+}
+
+/// Record the failure of challenging a request on the stored request, along
+/// with the service chain height and the number of challenges issued before
+/// the failure. The height is left out if the service chain fails too
+pub fn record_request_failure<T: Service, D: Storage + ?Sized>(
+    service: &T,
+    storage: &D,
+    request: &mut Request,
+    err: &Error,
+) -> Result<()> {
+    let challenges = recover_response(storage, request.txid)?.challenges.len() as u32;
+    let failure = RequestFailure::new(err, service.get_blockheight().ok(), challenges);
+    warn! {"Request {} failed after {} challenges: {}", request.txid, failure.challenges, failure.message};
+    request.failure = Some(failure);
+    storage.update_request(request)
+}
+
+/// Get the duration that responses to a challenge issued at a service chain
+/// height are collected for. No further challenge is due before the end of the
+/// request after its final challenge, which gets the grace period on top of
+/// the challenge duration
+fn response_window(
+    request: &Request,
+    challenge_height: u64,
+    challenge_frequency: u64,
+    challenge_duration: time::Duration,
+    grace_period: time::Duration,
+) -> time::Duration {
+    if challenge_height + challenge_frequency.max(1) > request.end_blockheight as u64 {
+        info! {"final challenge, collecting responses for a {} sec grace period", grace_period.as_secs()}
+        challenge_duration + grace_period
+    } else {
+        challenge_duration
+    }
+}
+
+/// Settings of running a challenge request
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChallengeRequestSettings {
+    /// Challenge timings of the request
+    pub timing: ChallengeTiming,
+    /// Duration that responses to the final challenge of the request are
+    /// collected for after the request ends
+    pub grace_period: time::Duration,
+    /// Number of service chain blocks between challenges
+    pub challenge_frequency: u64,
+    /// Number of service chain blocks of each payment epoch, if the response
+    /// is snapshotted per epoch
+    pub payment_epoch: Option<u64>,
+}
+
+/// Settings and stall detector of a challenge request, along with the flags,
+/// journal, heartbeat and clock that the challenger shares with the rest of the
+/// coordinator
+#[derive(Clone)]
+pub struct ChallengerContext<'a> {
+    /// Settings of running the request
+    pub settings: ChallengeRequestSettings,
+    /// Detector of client chain stalls while the request is challenged
+    pub stall_detector: StallDetector,
+    /// Flag pausing challenges
+    pub paused: &'a AtomicBool,
+    /// Flag stopping the challenger
+    pub stopped: &'a AtomicBool,
+    /// Journal recording challenges issued and responses saved
+    pub journal: &'a Journal,
+    /// Heartbeat beaten on each refresh
+    pub heartbeat: &'a Heartbeat,
+    /// Clock timing refreshes, challenge verification and response collection
+    pub clock: &'a dyn Clock,
+}
+
+/// Run challenge for a specific request on the client chain until the request
+/// ends or the challenger is stopped. On each new service chain height a
+/// challenge is sent, verified on the client chain and its responses collected
+/// for the challenge duration. Responses are recorded per challenge and applied
+/// to the stored response, along with the challenge stats, latencies and
+/// coverage of the request
 pub fn run_challenge_request<T: Service, K: ClientChain, D: Storage>(
     service: &T,
     clientchain: &K,
     challenge_state: Arc<RwLock<Option<ChallengeState>>>,
     verify_rx: &Receiver<ChallengeResponse>,
     storage: Arc<D>,
-    verify_duration: time::Duration,
-    challenge_duration: time::Duration,
-    challenge_frequency: u64,
-    refresh_delay: time::Duration,
+    context: ChallengerContext,
 ) -> Result<()> {
-    let request = challenge_state.read().unwrap().as_ref().unwrap().request.clone(); // clone as const and drop mutex
-    let mut response = storage.get_response(request.txid)?.unwrap_or(Response::new());
+    let ChallengerContext {
+        settings:
+            ChallengeRequestSettings {
+                timing:
+                    ChallengeTiming {
+                        challenge_duration,
+                        verify_duration,
+                        refresh_delay,
+                    },
+                grace_period,
+                challenge_frequency,
+                payment_epoch,
+            },
+        mut stall_detector,
+        paused,
+        stopped,
+        journal,
+        heartbeat,
+        clock,
+    } = context;
+    // clone request as const and share the latest challenge holder so that
+    // challenges are issued and ended without the state write lock
+    let (request, latest_challenge, commit_reveal) = {
+        let ch_lock = challenge_state.read().unwrap();
+        let ch = ch_lock.as_ref().unwrap();
+        (ch.request.clone(), ch.latest_challenge.clone(), ch.commit_reveal)
+    };
+    let mut response = recover_response(storage.as_ref(), request.txid)?;
+    let mut latency = storage
+        .get_challenge_latency(request.txid)?
+        .unwrap_or(ChallengeLatency::new());
     info! {"Running challenge request: {:?}", request.txid};
     let mut prev_challenge_height: u64 = 0;
-    loop {
+    let mut epoch = match payment_epoch {
+        Some(_) => storage.get_response_snapshots(request.txid)?.len() as u64,
+        None => 0,
+    };
+    // challenge on every refresh until the request ends
+    let mut scheduler = Scheduler::with_clock(clock);
+    let _ = scheduler.add("challenge", Schedule::Interval(refresh_delay), || {
+        heartbeat.beat("challenge refresh");
+        if stopped.load(Ordering::SeqCst) {
+            info! {"Challenge request stopped"}
+            return Ok(JobStatus::Done);
+        }
         let challenge_height = service.get_blockheight()?;
         info! {"service chain height: {}", challenge_height}
         if (request.end_blockheight as u64) < challenge_height {
-            break;
-        } else if (challenge_height - prev_challenge_height) < challenge_frequency {
+            if let Some(stall) = stall_detector.end(challenge_height, clock) {
+                storage.save_clientchain_stall(request.txid, &stall)?;
+            }
+            update_challenge_coverage(storage.as_ref(), &request, challenge_frequency, challenge_height)?;
+            return Ok(JobStatus::Done);
+        }
+
+        // skip challenges that can not be included while the client chain is
+        // stalled
+        if stall_detector.is_enabled() {
+            let clientchain_height = clientchain.get_blockheight()?;
+            match stall_detector.check(clientchain_height, challenge_height, clock) {
+                StallStatus::Stalled => {
+                    info! {"Client chain stalled, sleeping for {} sec...",time::Duration::as_secs(&refresh_delay)}
+                    return Ok(JobStatus::Continue);
+                }
+                StallStatus::Resumed(stall) => {
+                    storage.save_clientchain_stall(request.txid, &stall)?;
+                    account_clientchain_stall(storage.as_ref(), &challenge_state, &stall, clientchain_height)?;
+                }
+                StallStatus::Producing => {}
+            }
+        }
+
+        if (challenge_height - prev_challenge_height) < challenge_frequency {
             info! {"Sleeping for {} sec...",time::Duration::as_secs(&refresh_delay)}
-            thread::sleep(refresh_delay);
-            continue;
+            return Ok(JobStatus::Continue);
+        } else if paused.load(Ordering::SeqCst) {
+            info! {"Challenges paused, sleeping for {} sec...",time::Duration::as_secs(&refresh_delay)}
+            return Ok(JobStatus::Continue);
         }
 
-        info! {"sending challenge..."}
-        let challenge_hash = clientchain.send_challenge()?;
-        challenge_state.write().unwrap().as_mut().unwrap().latest_challenge = Some(challenge_hash);
+        // check again as the stopped flag may be set while the service chain
+        // is slow to respond
+        if stopped.load(Ordering::SeqCst) {
+            info! {"Challenge request stopped"}
+            return Ok(JobStatus::Done);
+        }
 
-        if let Err(e) = verify_challenge(&challenge_hash, clientchain, verify_duration) {
-            challenge_state.write().unwrap().as_mut().unwrap().latest_challenge = None; // stop receiving responses
+        info! {"sending challenge..."}
+        let sequence = response.challenges.len() as u32;
+        let sent = if commit_reveal {
+            send_committed_challenge(
+                clientchain,
+                &request.txid,
+                sequence,
+                &latest_challenge,
+                verify_duration,
+                clock,
+            )?
+        } else {
+            clientchain.send_challenge(&request.txid, sequence)?.map(|sent| (sent, None))
+        };
+        let (challenge_hash, amount_tag, wallet, commitment) = match sent {
+            Some((sent, commitment)) => (sent.txid, sent.amount_tag, sent.wallet, commitment),
+            None => {
+                info! {"Challenge skipped by pre-flight check, sleeping for {} sec...",time::Duration::as_secs(&refresh_delay)}
+                return Ok(JobStatus::Continue);
+            }
+        };
+        let sent_time = clock.now();
+        match commitment {
+            Some(_) => latest_challenge.reveal(challenge_hash, sent_time)?,
+            None => latest_challenge.set(challenge_hash, sent_time),
+        }
+        journal.record(JournalEvent::ChallengeIssued {
+            request: request.txid,
+            challenge: challenge_hash,
+            height: challenge_height,
+            amount_tag,
+            wallet: wallet.clone(),
+            commitment,
+        });
+
+        if let Err(e) = verify_challenge(&challenge_hash, clientchain, verify_duration, clock) {
+            latest_challenge.clear(); // stop receiving responses
+            clientchain.release_challenge(&challenge_hash);
             return Err(e);
         }
+        latency
+            .verify_ms
+            .push(clock.now().duration_since(sent_time).as_millis() as u64);
+        let clientchain_height = clientchain.get_blockheight()?;
 
         info! {"fetching responses..."}
-        response.update(&get_challenge_response(
+        response.challenges.push(challenge_hash);
+        let mut accepted = HashMap::new();
+        let challenge_response = get_challenge_response(
             &challenge_hash,
             &verify_rx,
-            challenge_duration,
-        )?);
-        storage.save_response(request.txid, &response)?;
-        challenge_state.write().unwrap().as_mut().unwrap().latest_challenge = None; // stop receiving responses
+            response_window(
+                &request,
+                challenge_height,
+                challenge_frequency,
+                challenge_duration,
+                grace_period,
+            ),
+            sent_time,
+            &mut latency,
+            &mut accepted,
+            clock,
+        )?;
+        if stopped.load(Ordering::SeqCst) {
+            info! {"Challenge request stopped, discarding responses to challenge {}", challenge_hash}
+            latest_challenge.clear();
+            return Ok(JobStatus::Done);
+        }
+        storage.save_challenge_record(request.txid, &ChallengeRecord::new(challenge_hash, &challenge_response))?;
+        let proofs: Vec<BidProof> = accepted
+            .into_iter()
+            .map(|(bid, timestamp)| BidProof {
+                bid,
+                challenge: challenge_hash,
+                clientchain_height,
+                timestamp,
+            })
+            .collect();
+        storage.save_bid_proofs(request.txid, &proofs)?;
+        let num_bids = challenge_state.read().unwrap().as_ref().unwrap().num_bids();
+        storage.save_challenge_stats(
+            request.txid,
+            &ChallengeStats {
+                challenge: challenge_hash,
+                height: challenge_height,
+                num_bids: num_bids as u32,
+                num_responses: challenge_response.len() as u32,
+                amount_tag,
+                wallet,
+            },
+        )?;
+        response.update(&challenge_response);
+        storage.update_response(request.txid, challenge_hash, &challenge_response, &response)?;
+        journal.record(JournalEvent::ResponseSaved {
+            request: request.txid,
+            challenge: challenge_hash,
+            bids: challenge_response.into_iter().collect(),
+            num_challenges: response.num_challenges,
+        });
+        storage.save_challenge_latency(request.txid, &latency)?;
+        update_challenge_coverage(storage.as_ref(), &request, challenge_frequency, challenge_height)?;
+        if let Some(epoch_length) = payment_epoch {
+            let epoch_end = request.start_blockheight as u64 + (epoch + 1) * epoch_length.max(1);
+            if challenge_height >= epoch_end && challenge_height < request.end_blockheight as u64 {
+                info! {"storing response snapshot for epoch {}", epoch}
+                let bids = challenge_state.read().unwrap().as_ref().unwrap().all_bids()?;
+                storage.save_response_snapshot(
+                    request.txid,
+                    &ResponseSnapshot {
+                        epoch: epoch as u32,
+                        service_height: challenge_height as u32,
+                        clientchain_height: clientchain.get_blockheight()?,
+                        response: response.clone(),
+                        bids: bids.into_iter().collect(),
+                        is_payment_complete: false,
+                    },
+                )?;
+                epoch += 1;
+            }
+        }
+        flush_request_logs(storage.as_ref()); // store request logs after each challenge
+        latest_challenge.clear(); // stop receiving responses
         prev_challenge_height = challenge_height; // update prev height
-    }
+        Ok(JobStatus::Continue)
+    });
+    let res = scheduler.run(None);
+    // release any unspents still reserved by challenges of the request
+    clientchain.release_request(&request.txid);
+    res?;
     info! {"Challenge request ended"}
     Ok(())
 }
 
+/// Take the client chain blocks missed during a stall off the client chain end
+/// height of the request being challenged and store the request, as done for
+/// client chain delays on restart, keeping the end height above the current
+/// client chain height as the request is still running
+fn account_clientchain_stall<D: Storage + ?Sized>(
+    storage: &D,
+    challenge_state: &RwLock<Option<ChallengeState>>,
+    stall: &ClientChainStall,
+    clientchain_height: u32,
+) -> Result<()> {
+    let request = {
+        let mut ch_lock = challenge_state.write().unwrap();
+        let ch = ch_lock.as_mut().unwrap();
+        if ch.request.end_blockheight_clientchain == 0 {
+            return Ok(()); // end height not set yet
+        }
+        ch.request.end_blockheight_clientchain = ch
+            .request
+            .end_blockheight_clientchain
+            .saturating_sub(stall.missed_blocks)
+            .max(clientchain_height + 1);
+        ch.request.clone()
+    };
+    info!(
+        "Request client chain end height updated to {} after {} blocks missed during stall",
+        request.end_blockheight_clientchain, stall.missed_blocks
+    );
+    storage.update_request(&request)
+}
+
+/// Compute and store the challenge coverage of a request up to a service chain
+/// height from the heights of the challenges issued so far
+fn update_challenge_coverage<D: Storage + ?Sized>(
+    storage: &D,
+    request: &Request,
+    challenge_frequency: u64,
+    height: u64,
+) -> Result<()> {
+    let heights: Vec<u64> = storage
+        .get_challenge_stats(request.txid)?
+        .iter()
+        .map(|stats| stats.height)
+        .collect();
+    storage.save_challenge_coverage(
+        request.txid,
+        &ChallengeCoverage::new(request, &heights, challenge_frequency, height),
+    )
+}
+
+/// Projected challenge of a request at a service chain height along with the
+/// approximate unix timestamp the height is expected to be reached
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScheduledChallenge {
+    /// Expected service chain height of the challenge
+    pub height: u64,
+    /// Approximate unix timestamp of the challenge in seconds
+    pub timestamp: u64,
+}
+
+/// Project the remaining challenges of an upcoming or active request. The first
+/// challenge is issued at the request start height and one more every
+/// challenge frequency blocks until the request end height, as done when
+/// running the challenge request. Timestamps are approximated from the current
+/// service height and time using the service chain block time
+pub fn challenge_schedule(
+    request: &Request,
+    service_height: u64,
+    challenge_frequency: u64,
+    block_time: u64,
+    now: u64,
+) -> Vec<ScheduledChallenge> {
+    let frequency = challenge_frequency.max(1);
+    let start = request.start_blockheight as u64;
+    let end = request.end_blockheight as u64;
+    let mut height = start;
+    if service_height > start {
+        height = start + (service_height - start + frequency - 1) / frequency * frequency;
+    }
+    let mut schedule = vec![];
+    while height <= end {
+        schedule.push(ScheduledChallenge {
+            height,
+            timestamp: now + (height - service_height.min(height)) * block_time,
+        });
+        height += frequency;
+    }
+    schedule
+}
+
 /// Update challenge state request with client chain start and end block
 /// heights and store challenge state
 /// If request already stored set challenge state request to request in
@@ -237,6 +653,140 @@ impl Eq for ChallengeResponse {}
 /// Type defining a set of Challenge Responses Ids
 pub type ChallengeResponseIds = HashSet<sha256d::Hash>;
 
+/// Check that a bid is one of the stored winning bids of a request, matching
+/// the bid txid and pubkey
+pub fn is_stored_bid<D: Storage + ?Sized>(storage: &D, request_hash: sha256d::Hash, bid: &Bid) -> bool {
+    match storage.get_bid(request_hash, bid.txid) {
+        Ok(Some(stored)) => stored.pubkey == bid.pubkey,
+        Ok(None) => false,
+        Err(e) => {
+            warn!("spilled bid {} lookup failed: {}", bid.txid, e);
+            false
+        }
+    }
+}
+
+/// Remove all but the max number of bids with the lowest txids from a bid
+/// set, returning the number of bids removed
+fn trim_bids(bids: &mut BidSet, max_bids: usize) -> usize {
+    if bids.len() <= max_bids {
+        return 0;
+    }
+    let mut txids: Vec<sha256d::Hash> = bids.iter().map(|bid| bid.txid).collect();
+    txids.sort();
+    let hot: HashSet<sha256d::Hash> = txids.into_iter().take(max_bids).collect();
+    let num_bids = bids.len();
+    bids.retain(|bid| hot.contains(&bid.txid));
+    num_bids - bids.len()
+}
+
+/// Winning bids of a challenge request that are not kept in memory and are
+/// paged from storage on demand
+#[derive(Clone)]
+pub struct SpilledBids {
+    /// Number of bids spilled
+    pub count: usize,
+    /// Storage the spilled bids are paged from
+    storage: Arc<dyn Storage + Send + Sync>,
+}
+
+impl fmt::Debug for SpilledBids {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SpilledBids {{ count: {} }}", self.count)
+    }
+}
+
+impl SpilledBids {
+    /// Check that a bid is one of the spilled bids of a request
+    pub fn contains(&self, request_hash: sha256d::Hash, bid: &Bid) -> bool {
+        is_stored_bid(self.storage.as_ref(), request_hash, bid)
+    }
+}
+
+/// Holder of the latest challenge txid hash in the client chain and the time
+/// that it was sent at, along with the commitment to the challenge if it was
+/// committed to before being revealed. The holder is shared by the challenger
+/// setting it and the listener checking proofs against it, so that issuing and
+/// ending challenges never takes the challenge state write lock that proof
+/// checks would queue behind
+#[derive(Debug, Default)]
+pub struct LatestChallenge {
+    inner: Mutex<(
+        Option<sha256d::Hash>,
+        Option<time::Instant>,
+        Option<ChallengeCommitment>,
+    )>,
+}
+
+impl LatestChallenge {
+    /// Create a new holder with an optional challenge hash
+    pub fn new(hash: Option<sha256d::Hash>) -> LatestChallenge {
+        LatestChallenge {
+            inner: Mutex::new((hash, None, None)),
+        }
+    }
+
+    /// Set the latest challenge hash and the time it was sent at
+    pub fn set(&self, hash: sha256d::Hash, sent_time: time::Instant) {
+        *self.inner.lock().unwrap() = (Some(hash), Some(sent_time), None);
+    }
+
+    /// Set the commitment to the next challenge, clearing the latest
+    /// challenge hash so that no proofs are received until it is revealed
+    pub fn commit(&self, commitment: ChallengeCommitment) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.0 = None;
+        inner.2 = Some(commitment);
+    }
+
+    /// Set the latest challenge hash revealed for the commitment and the time
+    /// it was revealed at, failing if the hash does not open the commitment
+    pub fn reveal(&self, hash: sha256d::Hash, reveal_time: time::Instant) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.2 {
+            Some(commitment) if commitment.opens(&hash) => {
+                inner.0 = Some(hash);
+                inner.1 = Some(reveal_time);
+                Ok(())
+            }
+            _ => Err(Error::from(CError::Generic(format!(
+                "challenge {} does not open the challenge commitment",
+                hash
+            )))),
+        }
+    }
+
+    /// Clear the latest challenge hash and any commitment to stop receiving
+    /// responses, keeping the time that it was sent at
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.0 = None;
+        inner.2 = None;
+    }
+
+    /// Get the latest challenge hash, if responses are being received
+    pub fn hash(&self) -> Option<sha256d::Hash> {
+        self.inner.lock().unwrap().0
+    }
+
+    /// Get the time that the latest challenge was sent at
+    pub fn sent_time(&self) -> Option<time::Instant> {
+        self.inner.lock().unwrap().1
+    }
+
+    /// Get the commitment to the latest challenge, if it was committed to
+    pub fn commitment(&self) -> Option<ChallengeCommitment> {
+        self.inner.lock().unwrap().2
+    }
+
+    /// Check whether the latest challenge has been committed to but not
+    /// revealed yet
+    pub fn is_pending_reveal(&self) -> bool {
+        let inner = self.inner.lock().unwrap();
+        inner.0.is_none() && inner.2.is_some()
+    }
+}
+
 /// Mainstains challenge state with information on
 /// challenge requests and bids as well as the
 /// latest challenge hash in the client chain
@@ -244,10 +794,134 @@ pub type ChallengeResponseIds = HashSet<sha256d::Hash>;
 pub struct ChallengeState {
     /// Service Request for issuing challenges
     pub request: Request,
-    /// Request winning bids that respond to challenges
-    pub bids: BidSet,
-    /// Latest challenge txid hash in the client chain
-    pub latest_challenge: Option<sha256d::Hash>,
+    /// Request winning bids kept in memory that respond to challenges. The
+    /// bids are immutable while challenging so that proof checks can take a
+    /// reference and release the state lock before looking bids up
+    pub bids: Arc<BidSet>,
+    /// Request winning bids spilled to storage, if the bids did not fit in
+    /// memory
+    pub spilled_bids: Option<SpilledBids>,
+    /// Latest challenge in the client chain, shared with any clones of the
+    /// state
+    pub latest_challenge: Arc<LatestChallenge>,
+    /// Payload of the request challenges that proofs commit to, if any
+    pub payload: Option<ChallengePayload>,
+    /// Flag to commit to each challenge of the request on the client chain
+    /// before revealing it
+    pub commit_reveal: bool,
+}
+
+impl ChallengeState {
+    /// Keep at most the max number of bids in memory, spilling the rest to the
+    /// storage that the request bids have already been stored to. Returns the
+    /// number of bids spilled
+    pub fn spill_bids(&mut self, max_bids: usize, storage: Arc<dyn Storage + Send + Sync>) -> usize {
+        let count = trim_bids(Arc::make_mut(&mut self.bids), max_bids);
+        if count > 0 {
+            self.spilled_bids = Some(SpilledBids { count, storage });
+        }
+        count
+    }
+
+    /// Get the number of winning bids of the request, including any spilled
+    /// bids
+    pub fn num_bids(&self) -> usize {
+        self.bids.len() + self.spilled_bids.as_ref().map_or(0, |spilled| spilled.count)
+    }
+
+    /// Get all winning bids of the request, paging any spilled bids from
+    /// storage
+    pub fn all_bids(&self) -> Result<BidSet> {
+        let mut bids = (*self.bids).clone();
+        if let Some(spilled) = &self.spilled_bids {
+            let hot: HashSet<sha256d::Hash> = self.bids.iter().map(|bid| bid.txid).collect();
+            for bid in spilled.storage.get_bids(self.request.txid)? {
+                if !hot.contains(&bid.txid) {
+                    let _ = bids.insert(bid);
+                }
+            }
+        }
+        Ok(bids)
+    }
+}
+
+/// Changes to the winning bids of the active request applied by a bid refresh
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BidRefresh {
+    /// Request txid
+    pub request: sha256d::Hash,
+    /// Txids of the bids added
+    pub added: Vec<sha256d::Hash>,
+    /// Txids of the bids removed
+    pub removed: Vec<sha256d::Hash>,
+    /// Number of winning bids after the refresh
+    pub num_bids: usize,
+}
+
+/// Re-fetch the winning bids of the active request from the service chain and
+/// apply any differences to the challenge state and storage. New bids are
+/// stored and accepted in challenge proofs from then on, while bids no longer
+/// reported by the service chain are removed from both. The service chain is
+/// queried without holding the state lock. Returns None if no request is
+/// active or the request ended during the refresh
+pub fn refresh_bids<T: Service + ?Sized, D: Storage + ?Sized>(
+    service: &T,
+    storage: &D,
+    challenge_state: &RwLock<Option<ChallengeState>>,
+) -> Result<Option<BidRefresh>> {
+    // page any spilled bids from a copy of the state, without holding the lock
+    let state = match challenge_state.read().unwrap().as_ref() {
+        Some(ch) => ch.clone(),
+        None => return Ok(None),
+    };
+    let request = state.request.clone();
+    let current = state.all_bids()?;
+    let fetched = get_request_bids(&request, service)?;
+    let current_txids: HashSet<sha256d::Hash> = current.iter().map(|bid| bid.txid).collect();
+    let fetched_txids: HashSet<sha256d::Hash> = fetched.iter().map(|bid| bid.txid).collect();
+    let added: BidSet = fetched
+        .into_iter()
+        .filter(|bid| !current_txids.contains(&bid.txid))
+        .collect();
+    let removed: HashSet<sha256d::Hash> = current_txids.difference(&fetched_txids).cloned().collect();
+    let mut removed_txids: Vec<sha256d::Hash> = removed.iter().cloned().collect();
+    removed_txids.sort();
+    storage.save_challenge_request_state(&request, &added)?;
+    storage.remove_bids(request.txid, &removed_txids)?;
+
+    let mut ch_lock = challenge_state.write().unwrap();
+    let ch = match ch_lock.as_mut() {
+        Some(ch) => ch,
+        None => return Ok(None),
+    };
+    if ch.request.txid != request.txid {
+        return Ok(None);
+    }
+    let bids = Arc::make_mut(&mut ch.bids);
+    let num_hot = bids.len();
+    bids.retain(|bid| !removed.contains(&bid.txid));
+    // the state may have changed since it was read so never count below zero
+    let removed_spilled = removed.len().saturating_sub(num_hot - bids.len());
+    bids.extend(added.iter().cloned());
+    let num_spilled = ch
+        .spilled_bids
+        .as_ref()
+        .map_or(0, |spilled| spilled.count)
+        .saturating_sub(removed_spilled);
+    if num_spilled == 0 {
+        ch.spilled_bids = None;
+    } else if let Some(spilled) = ch.spilled_bids.as_mut() {
+        spilled.count = num_spilled;
+    }
+    let mut added_txids: Vec<sha256d::Hash> = added.iter().map(|bid| bid.txid).collect();
+    added_txids.sort();
+    info! {"Refreshed bids of request {}: {} added, {} removed", request.txid, added_txids.len(), removed_txids.len()}
+    Ok(Some(BidRefresh {
+        request: request.txid,
+        added: added_txids,
+        removed: removed_txids,
+        num_bids: ch.num_bids(),
+    }))
 }
 
 /// Check if request start height has been reached in order to initiate
@@ -261,27 +935,98 @@ fn check_request(request: &Request, height: u64) -> bool {
 }
 
 /// Attempt to fetch the winnings bids for a request in the service chain
-fn get_request_bids<T: Service>(request: &Request, service: &T) -> Result<BidSet> {
+fn get_request_bids<T: Service + ?Sized>(request: &Request, service: &T) -> Result<BidSet> {
     match service.get_request_bids(&request.txid)? {
         Some(bids) => return Ok(bids),
         _ => Err(Error::from(CError::MissingBids)),
     }
 }
 
+/// Check that the fee promised by a request has been locked in the service
+/// chain. The result of the check is stored so that unverified requests can be
+/// flagged via the api
+fn check_request_deposit<T: Service, D: Storage>(request: &Request, service: &T, storage: &D) -> Result<bool> {
+    match service.get_request_deposit(request)? {
+        Some(deposit) => {
+            storage.save_request_deposit(&deposit)?;
+            if !deposit.is_verified() {
+                warn! {"Request {} deposit unverified (locked: {}, promised: {})", request.txid, deposit.locked, deposit.promised}
+            }
+            Ok(deposit.is_verified())
+        }
+        None => {
+            warn! {"Request {} deposit not found", request.txid}
+            Ok(false)
+        }
+    }
+}
+
+/// Validate the parameters of a request reported by the service chain against
+/// sanity limits, returning the reason the request is invalid if any
+fn validate_request(request: &Request, genesis: &sha256d::Hash, max_duration: u64) -> Option<String> {
+    if request.end_blockheight <= request.start_blockheight {
+        return Some(format!(
+            "end height {} not after start height {}",
+            request.end_blockheight, request.start_blockheight
+        ));
+    }
+    let duration = (request.end_blockheight - request.start_blockheight) as u64;
+    if duration > max_duration {
+        return Some(format!("duration {} above max duration {}", duration, max_duration));
+    }
+    if request.fee_percentage > 100 {
+        return Some(format!("fee percentage {} above 100", request.fee_percentage));
+    }
+    if request.num_tickets == 0 {
+        return Some("no tickets".to_owned());
+    }
+    if request.genesis_blockhash != *genesis {
+        return Some(format!(
+            "genesis {} does not match {}",
+            request.genesis_blockhash, genesis
+        ));
+    }
+    None
+}
+
 /// Fetch next challenge state given a request and bids in the service chain
-/// A challenge is fetched only when a request exists and the required
-/// starting blockheight has been reached in the service chain
-pub fn fetch_next<T: Service>(service: &T, genesis: &sha256d::Hash) -> Result<Option<ChallengeState>> {
+/// A challenge is fetched only when a valid request exists, the required
+/// starting blockheight has been reached in the service chain and the request
+/// fee deposit has been verified. Requests failing validation against the max
+/// duration in service chain blocks and other sanity limits are rejected and
+/// the rejection is stored so that it can be retrieved via the api
+pub fn fetch_next<T: Service, D: Storage>(
+    service: &T,
+    storage: &D,
+    genesis: &sha256d::Hash,
+    max_duration: u64,
+) -> Result<Option<ChallengeState>> {
     info!("Fetching challenge request!");
     match service.get_request(&genesis)? {
         Some(req) => {
+            if let Some(reason) = validate_request(&req, genesis, max_duration) {
+                warn! {"Rejecting invalid request {}: {}", req.txid, reason}
+                storage.save_request_rejection(&RequestRejection {
+                    txid: req.txid,
+                    genesis_blockhash: req.genesis_blockhash,
+                    reason,
+                })?;
+                return Ok(None);
+            }
             let height = service.get_blockheight()?;
             if check_request(&req, height) {
+                if !check_request_deposit(&req, service, storage)? {
+                    warn! {"Refusing to challenge request with unverified deposit: {}", req.txid}
+                    return Ok(None);
+                }
                 let bids = get_request_bids(&req, service)?;
                 return Ok(Some(ChallengeState {
                     request: req,
-                    bids: bids,
-                    latest_challenge: None,
+                    bids: Arc::new(bids),
+                    spilled_bids: None,
+                    latest_challenge: Arc::new(LatestChallenge::new(None)),
+                    payload: None,
+                    commit_reveal: false,
                 }));
             } else {
                 warn! {"Request (startheight: {}) not ready for current height: {}", req.start_blockheight, height}
@@ -302,8 +1047,12 @@ mod tests {
     use std::iter::FromIterator;
     use std::sync::mpsc::{channel, Receiver, Sender};
 
-    use crate::error::Error;
+    use bitcoin::hashes::hex::FromHex;
+
+    use crate::clock::SystemClock;
+    use crate::error::{Error, ErrorCategory};
     use crate::interfaces::mocks::clientchain::MockClientChain;
+    use crate::interfaces::mocks::clock::{MockClock, MOCK_CLOCK_UNIX_START};
     use crate::interfaces::mocks::service::MockService;
     use crate::interfaces::mocks::storage::MockStorage;
     use crate::interfaces::response::Response;
@@ -314,32 +1063,39 @@ mod tests {
         setup_logger();
         let mut clientchain = MockClientChain::new();
         let dummy_hash = gen_dummy_hash(5);
+        let clock = MockClock::new();
 
         // duration doesn't matter here
-        assert!(verify_challenge(&dummy_hash, &clientchain, time::Duration::from_millis(10)).unwrap() == ());
+        assert!(verify_challenge(&dummy_hash, &clientchain, time::Duration::from_millis(10), &clock).unwrap() == ());
+        assert_eq!(time::Duration::from_secs(0), clock.elapsed());
 
-        // test that for very small duration this fails
-        let res = verify_challenge(&dummy_hash, &clientchain, time::Duration::from_nanos(1));
+        // test that for zero duration this fails
+        let res = verify_challenge(&dummy_hash, &clientchain, time::Duration::from_secs(0), &clock);
         match res {
             Ok(_) => assert!(false, "should not return Ok"),
             Err(Error::Coordinator(e)) => assert_eq!(CError::UnverifiedChallenge.to_string(), e.to_string()),
             Err(_) => assert!(false, "should not return any error"),
         }
 
-        // test with clientchain returning false
+        // test with clientchain returning false, attempted until the verify
+        // duration has passed
         clientchain.return_false = true;
-        let res = verify_challenge(&dummy_hash, &clientchain, time::Duration::from_millis(10));
+        let res = verify_challenge(&dummy_hash, &clientchain, time::Duration::from_millis(250), &clock);
         match res {
             Ok(_) => assert!(false, "should not return Ok"),
             Err(Error::Coordinator(e)) => assert_eq!(CError::UnverifiedChallenge.to_string(), e.to_string()),
             Err(_) => assert!(false, "should not return any error"),
         }
+        assert_eq!(
+            time::Duration::from_millis(3 * CHALLENGER_VERIFY_INTERVAL),
+            clock.elapsed()
+        );
         clientchain.return_false = false;
 
         // test with clientchain failing
         clientchain.return_err = true;
         assert!(
-            verify_challenge(&dummy_hash, &clientchain, time::Duration::from_millis(10)).is_err(),
+            verify_challenge(&dummy_hash, &clientchain, time::Duration::from_millis(10), &clock).is_err(),
             "verify_challenge failed"
         );
     }
@@ -359,10 +1115,23 @@ mod tests {
             .unwrap()
             .clone();
         let (vtx, vrx): (Sender<ChallengeResponse>, Receiver<ChallengeResponse>) = channel();
+        let clock = MockClock::new();
 
-        // first test with empty response
-        let res = get_challenge_response(&dummy_hash, &vrx, time::Duration::from_millis(1));
+        let mut latency = ChallengeLatency::new();
+
+        // first test with empty response, collected for the whole duration
+        let res = get_challenge_response(
+            &dummy_hash,
+            &vrx,
+            time::Duration::from_secs(60),
+            clock.now(),
+            &mut latency,
+            &mut HashMap::new(),
+            &clock,
+        );
         assert_eq!(res.unwrap().len(), 0);
+        assert_eq!(0, latency.proof_ms.len());
+        assert_eq!(time::Duration::from_secs(60), clock.elapsed());
 
         // then test with a few dummy responses and old hashes that are ignored
         let old_dummy_hash = gen_dummy_hash(8);
@@ -373,20 +1142,55 @@ mod tests {
         vtx.send(ChallengeResponse(old_dummy_hash, dummy_bid.clone())).unwrap();
         vtx.send(ChallengeResponse(dummy_hash, dummy_bid.clone())).unwrap();
         vtx.send(ChallengeResponse(old_dummy_hash, dummy_bid.clone())).unwrap();
-        let res = get_challenge_response(&dummy_hash, &vrx, time::Duration::from_millis(1)).unwrap();
+        let sent_time = clock.now();
+        clock.advance(time::Duration::from_millis(20));
+        let mut accepted = HashMap::new();
+        let res = get_challenge_response(
+            &dummy_hash,
+            &vrx,
+            time::Duration::from_millis(1),
+            sent_time,
+            &mut latency,
+            &mut accepted,
+            &clock,
+        )
+        .unwrap();
         assert_eq!(res.len(), 1);
         assert_eq!(res, dummy_response_set);
+        assert_eq!(vec![20], latency.proof_ms); // first response of the bid only
+        assert_eq!(
+            Some(&(MOCK_CLOCK_UNIX_START * 1000 + 60_020)),
+            accepted.get(&dummy_bid.txid)
+        );
+        assert_eq!(&latency.proof_ms, latency.bid_proof_ms.get(&dummy_bid.txid).unwrap());
 
-        // then test with dummy hash but little time to fetch
+        // then test with dummy hash but no time to fetch
         let mut dummy_response_set = ChallengeResponseIds::new();
         let _ = dummy_response_set.insert(dummy_bid.txid);
         vtx.send(ChallengeResponse(dummy_hash, dummy_bid.clone())).unwrap();
-        let res = get_challenge_response(&dummy_hash, &vrx, time::Duration::from_nanos(1)).unwrap();
+        let res = get_challenge_response(
+            &dummy_hash,
+            &vrx,
+            time::Duration::from_secs(0),
+            clock.now(),
+            &mut latency,
+            &mut HashMap::new(),
+            &clock,
+        )
+        .unwrap();
         assert_eq!(res.len(), 0);
 
         // then drop channel sender and test correct error is returned
         std::mem::drop(vtx);
-        let res = get_challenge_response(&dummy_hash, &vrx, time::Duration::from_millis(1));
+        let res = get_challenge_response(
+            &dummy_hash,
+            &vrx,
+            time::Duration::from_millis(1),
+            clock.now(),
+            &mut latency,
+            &mut HashMap::new(),
+            &clock,
+        );
         match res {
             Ok(_) => assert!(false, "should not return Ok"),
             Err(Error::Coordinator(e)) => assert_eq!(CError::ReceiverDisconnected.to_string(), e.to_string()),
@@ -580,20 +1384,21 @@ mod tests {
     #[test]
     fn fetch_next_test() {
         setup_logger();
-        let dummy_hash = gen_dummy_hash(255);
+        let dummy_hash = gen_dummy_hash(0);
 
         let mut service = MockService::new();
+        let storage = Arc::new(MockStorage::new());
         let dummy_request = service.get_request(&dummy_hash).unwrap().unwrap();
         let dummy_set = service.get_request_bids(&dummy_hash).unwrap().unwrap();
 
         // first test what happens when service fails
         service.return_err = true;
-        assert!(fetch_next(&service, &dummy_hash).is_err());
+        assert!(fetch_next(&service, storage.as_ref(), &dummy_hash, 100).is_err());
         service.return_err = false;
 
         // then test when get_request returns none
         service.return_none = true;
-        let res = fetch_next(&service, &dummy_hash).unwrap();
+        let res = fetch_next(&service, storage.as_ref(), &dummy_hash, 100).unwrap();
         match res {
             None => assert!(true),
             Some(_) => assert!(false, "not expecting value"),
@@ -602,18 +1407,267 @@ mod tests {
 
         // then test when get_request returns Request
         let _ = service.height.replace(dummy_request.start_blockheight as u64);
-        let res = fetch_next(&service, &dummy_hash).unwrap().unwrap();
-        assert_eq!(res.latest_challenge, None);
-        assert_eq!(res.bids, dummy_set);
+        let res = fetch_next(&service, storage.as_ref(), &dummy_hash, 100)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.latest_challenge.hash(), None);
+        assert_eq!(*res.bids, dummy_set);
         assert_eq!(res.request, dummy_request);
 
         // then test when get_request returns None as height too low
         let _ = service.height.replace(1);
-        let res = fetch_next(&service, &dummy_hash).unwrap();
+        let res = fetch_next(&service, storage.as_ref(), &dummy_hash, 100).unwrap();
         match res {
             None => assert!(true),
             Some(_) => assert!(false, "not expecting value"),
         }
+
+        // then test when get_request returns None as deposit is unverified
+        service.return_unverified = true;
+        let _ = service.height.replace(dummy_request.start_blockheight as u64);
+        let res = fetch_next(&service, storage.as_ref(), &dummy_hash, 100).unwrap();
+        match res {
+            None => assert!(true),
+            Some(_) => assert!(false, "not expecting value"),
+        }
+        let deposits = storage.get_request_deposits(Some(false), None).unwrap();
+        assert_eq!(1, deposits.len());
+        assert_eq!(dummy_request.txid, deposits[0].txid);
+        service.return_unverified = false;
+
+        // deposit verified again
+        let _ = service.height.replace(dummy_request.start_blockheight as u64);
+        assert!(fetch_next(&service, storage.as_ref(), &dummy_hash, 100)
+            .unwrap()
+            .is_some());
+        assert_eq!(0, storage.get_request_deposits(Some(false), None).unwrap().len());
+        assert_eq!(1, storage.get_request_deposits(Some(true), None).unwrap().len());
+
+        // invalid request is rejected
+        assert!(fetch_next(&service, storage.as_ref(), &dummy_hash, 2)
+            .unwrap()
+            .is_none());
+        let rejections = storage.get_request_rejections(None).unwrap();
+        assert_eq!(1, rejections.len());
+        assert_eq!(dummy_request.txid, rejections[0].txid);
+        assert_eq!("duration 3 above max duration 2", rejections[0].reason);
+        assert!(fetch_next(&service, storage.as_ref(), &gen_dummy_hash(1), 100)
+            .unwrap()
+            .is_none());
+        assert_eq!(1, storage.get_request_rejections(None).unwrap().len());
+    }
+
+    #[test]
+    fn validate_request_test() {
+        setup_logger();
+        let genesis = gen_dummy_hash(0);
+        let request = gen_challenge_state(&gen_dummy_hash(1)).request;
+        assert_eq!(None, validate_request(&request, &genesis, 3));
+
+        let mut invalid = request.clone();
+        invalid.end_blockheight = invalid.start_blockheight;
+        assert_eq!(
+            Some("end height 2 not after start height 2".to_owned()),
+            validate_request(&invalid, &genesis, 3)
+        );
+        assert_eq!(
+            Some("duration 3 above max duration 2".to_owned()),
+            validate_request(&request, &genesis, 2)
+        );
+        let mut invalid = request.clone();
+        invalid.fee_percentage = 101;
+        assert_eq!(
+            Some("fee percentage 101 above 100".to_owned()),
+            validate_request(&invalid, &genesis, 3)
+        );
+        let mut invalid = request.clone();
+        invalid.num_tickets = 0;
+        assert_eq!(Some("no tickets".to_owned()), validate_request(&invalid, &genesis, 3));
+        assert!(validate_request(&request, &gen_dummy_hash(1), 3)
+            .unwrap()
+            .contains("does not match"));
+    }
+
+    #[test]
+    fn send_committed_challenge_test() {
+        setup_logger();
+        let mut clientchain = MockClientChain::new();
+        let latest_challenge = LatestChallenge::new(None);
+        let verify_duration = time::Duration::from_millis(200);
+        let clock = MockClock::new();
+
+        // challenge revealed once the commitment is verified
+        let request = gen_dummy_hash(1);
+        let (sent, commitment) =
+            send_committed_challenge(&clientchain, &request, 3, &latest_challenge, verify_duration, &clock)
+                .unwrap()
+                .unwrap();
+        let commitment = commitment.unwrap();
+        assert!(commitment.opens(&sent.txid));
+        assert_eq!(Some(commitment), latest_challenge.commitment());
+        assert!(latest_challenge.is_pending_reveal());
+        latest_challenge.reveal(sent.txid, clock.now()).unwrap();
+        assert_eq!(Some(sent.txid), latest_challenge.hash());
+
+        // commitment not included, challenge not revealed
+        clientchain.return_false = true;
+        assert!(
+            send_committed_challenge(&clientchain, &request, 4, &latest_challenge, verify_duration, &clock).is_err()
+        );
+        assert_eq!(verify_duration, clock.elapsed());
+        // reserved unspent of the challenge not revealed released
+        assert_eq!(
+            vec![clientchain.send_challenge(&request, 4).unwrap().unwrap().txid],
+            *clientchain.released.borrow()
+        );
+        assert_eq!(None, latest_challenge.hash());
+        assert_eq!(None, latest_challenge.commitment());
+        assert!(!latest_challenge.is_pending_reveal());
+    }
+
+    #[test]
+    fn run_challenge_request_paused_test() {
+        setup_logger();
+        let mut clientchain = MockClientChain::new();
+        let storage = Arc::new(MockStorage::new());
+        let service = MockService::new();
+
+        let dummy_hash = gen_dummy_hash(0);
+        let dummy_request = service.get_request(&dummy_hash).unwrap().unwrap();
+        let _ = service.height.replace(dummy_request.start_blockheight as u64); // set height for fetch_next to succeed
+        let challenge_state = fetch_next(&service, storage.as_ref(), &dummy_hash, 100)
+            .unwrap()
+            .unwrap();
+        let (_vtx, vrx): (Sender<ChallengeResponse>, Receiver<ChallengeResponse>) = channel();
+
+        // no challenges are sent while paused
+        let paused = AtomicBool::new(true);
+        let _ = service.height.replace(dummy_request.start_blockheight as u64); // set height back to starting height
+        run_challenge_request(
+            &service,
+            &clientchain,
+            Arc::new(RwLock::new(Some(challenge_state.clone()))),
+            &vrx,
+            storage.clone(),
+            ChallengerContext {
+                settings: ChallengeRequestSettings {
+                    timing: ChallengeTiming {
+                        challenge_duration: time::Duration::from_millis(10),
+                        verify_duration: time::Duration::from_millis(10),
+                        refresh_delay: time::Duration::from_millis(10),
+                    },
+                    grace_period: time::Duration::from_millis(0),
+                    challenge_frequency: 1,
+                    payment_epoch: None,
+                },
+                stall_detector: StallDetector::disabled(),
+                paused: &paused,
+                stopped: &AtomicBool::new(false),
+                journal: &Journal::disabled(),
+                heartbeat: &Heartbeat::new(),
+                clock: &SystemClock,
+            },
+        )
+        .unwrap();
+        assert_eq!(None, storage.get_response(dummy_request.txid).unwrap());
+        // every scheduled challenge is missed while paused
+        let coverage = storage.get_challenge_coverage(dummy_request.txid).unwrap().unwrap();
+        assert!(coverage.scheduled > 0);
+        assert_eq!(0, coverage.issued);
+        assert_eq!(1, coverage.gaps.len());
+        assert_eq!(dummy_request.start_blockheight as u64, coverage.gaps[0].start_height);
+
+        // no challenges are sent once stopped
+        paused.store(false, Ordering::SeqCst);
+        let _ = service.height.replace(dummy_request.start_blockheight as u64); // set height back to starting height
+        run_challenge_request(
+            &service,
+            &clientchain,
+            Arc::new(RwLock::new(Some(challenge_state.clone()))),
+            &vrx,
+            storage.clone(),
+            ChallengerContext {
+                settings: ChallengeRequestSettings {
+                    timing: ChallengeTiming {
+                        challenge_duration: time::Duration::from_millis(10),
+                        verify_duration: time::Duration::from_millis(10),
+                        refresh_delay: time::Duration::from_millis(10),
+                    },
+                    grace_period: time::Duration::from_millis(0),
+                    challenge_frequency: 1,
+                    payment_epoch: None,
+                },
+                stall_detector: StallDetector::disabled(),
+                paused: &paused,
+                stopped: &AtomicBool::new(true),
+                journal: &Journal::disabled(),
+                heartbeat: &Heartbeat::new(),
+                clock: &SystemClock,
+            },
+        )
+        .unwrap();
+        assert_eq!(None, storage.get_response(dummy_request.txid).unwrap());
+
+        // challenges are sent again once resumed, tagged by amount
+        paused.store(false, Ordering::SeqCst);
+        clientchain.amount_tags = 3;
+        clientchain.wallets = vec!["challenges1".to_owned(), "challenges2".to_owned()];
+        let _ = service.height.replace(dummy_request.start_blockheight as u64); // set height back to starting height
+        run_challenge_request(
+            &service,
+            &clientchain,
+            Arc::new(RwLock::new(Some(challenge_state))),
+            &vrx,
+            storage.clone(),
+            ChallengerContext {
+                settings: ChallengeRequestSettings {
+                    timing: ChallengeTiming {
+                        challenge_duration: time::Duration::from_millis(10),
+                        verify_duration: time::Duration::from_millis(10),
+                        refresh_delay: time::Duration::from_millis(10),
+                    },
+                    grace_period: time::Duration::from_millis(0),
+                    challenge_frequency: 1,
+                    payment_epoch: None,
+                },
+                stall_detector: StallDetector::disabled(),
+                paused: &paused,
+                stopped: &AtomicBool::new(false),
+                journal: &Journal::disabled(),
+                heartbeat: &Heartbeat::new(),
+                clock: &SystemClock,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            4,
+            storage
+                .get_response(dummy_request.txid)
+                .unwrap()
+                .unwrap()
+                .num_challenges
+        );
+        // response stats of each challenge at the height it was issued
+        let stats = storage.get_challenge_stats(dummy_request.txid).unwrap();
+        assert_eq!(4, stats.len());
+        assert!(stats.windows(2).all(|pair| pair[0].height < pair[1].height));
+        assert!(stats.iter().all(|stats| stats.num_bids > 0 && stats.num_responses == 0));
+        assert_eq!(
+            vec![(0, 1), (1, 2), (2, 3), (3, 1)],
+            stats
+                .iter()
+                .map(|stats| stats.amount_tag.map(|tag| (tag.sequence, tag.amount)).unwrap())
+                .collect::<Vec<(u32, u64)>>()
+        );
+        assert_eq!(
+            vec!["challenges1", "challenges2", "challenges1", "challenges2"],
+            stats
+                .iter()
+                .map(|stats| stats.wallet.as_ref().unwrap().as_str())
+                .collect::<Vec<&str>>()
+        );
+        let coverage = storage.get_challenge_coverage(dummy_request.txid).unwrap().unwrap();
+        assert_eq!(4, coverage.issued);
     }
 
     #[test]
@@ -631,7 +1685,9 @@ mod tests {
         // the first challenge
         let _ = service.height.replace(dummy_request.start_blockheight as u64); // set height for fetch_next to succeed
 
-        let challenge_state = fetch_next(&service, &dummy_hash).unwrap().unwrap();
+        let challenge_state = fetch_next(&service, storage.as_ref(), &dummy_hash, 100)
+            .unwrap()
+            .unwrap();
         storage
             .save_challenge_request_state(&challenge_state.request, &challenge_state.bids)
             .unwrap();
@@ -639,7 +1695,11 @@ mod tests {
         let (vtx, vrx): (Sender<ChallengeResponse>, Receiver<ChallengeResponse>) = channel();
 
         let _ = clientchain.height.replace((dummy_request.start_blockheight) + 1); // set height +1 for challenge hash response
-        let dummy_challenge_hash = clientchain.send_challenge().unwrap();
+        let dummy_challenge_hash = clientchain
+            .send_challenge(&challenge_state.request.txid, 0)
+            .unwrap()
+            .unwrap()
+            .txid;
         let dummy_bid = challenge_state.bids.iter().next().unwrap().clone();
         vtx.send(ChallengeResponse(dummy_challenge_hash, dummy_bid.clone()))
             .unwrap();
@@ -653,10 +1713,24 @@ mod tests {
             Arc::new(RwLock::new(Some(challenge_state.clone()))),
             &vrx,
             storage.clone(),
-            time::Duration::from_millis(10),
-            time::Duration::from_millis(10),
-            50,
-            time::Duration::from_millis(10),
+            ChallengerContext {
+                settings: ChallengeRequestSettings {
+                    timing: ChallengeTiming {
+                        challenge_duration: time::Duration::from_millis(10),
+                        verify_duration: time::Duration::from_millis(10),
+                        refresh_delay: time::Duration::from_millis(10),
+                    },
+                    grace_period: time::Duration::from_millis(0),
+                    challenge_frequency: 50,
+                    payment_epoch: None,
+                },
+                stall_detector: StallDetector::disabled(),
+                paused: &AtomicBool::new(false),
+                stopped: &AtomicBool::new(false),
+                journal: &Journal::disabled(),
+                heartbeat: &Heartbeat::new(),
+                clock: &SystemClock,
+            },
         );
 
         match res {
@@ -664,8 +1738,8 @@ mod tests {
                 let resps = storage.get_response(dummy_request.txid).unwrap();
                 assert_eq!(resps, None);
                 let bids = storage.get_bids(dummy_request.txid).unwrap();
-                assert_eq!(challenge_state.bids, HashSet::from_iter(bids.iter().cloned()));
-                let requests = storage.get_requests(None, None, None).unwrap();
+                assert_eq!(*challenge_state.bids, HashSet::from_iter(bids.iter().cloned()));
+                let requests = storage.get_requests(None, None, None, None).unwrap();
                 assert_eq!(1, requests.len());
                 assert_eq!(&challenge_state.request, &requests[0]);
                 assert_eq!(
@@ -687,10 +1761,24 @@ mod tests {
             Arc::new(RwLock::new(Some(challenge_state.clone()))),
             &vrx,
             storage.clone(),
-            time::Duration::from_millis(10),
-            time::Duration::from_millis(10),
-            1,
-            time::Duration::from_millis(10),
+            ChallengerContext {
+                settings: ChallengeRequestSettings {
+                    timing: ChallengeTiming {
+                        challenge_duration: time::Duration::from_millis(10),
+                        verify_duration: time::Duration::from_millis(10),
+                        refresh_delay: time::Duration::from_millis(10),
+                    },
+                    grace_period: time::Duration::from_millis(0),
+                    challenge_frequency: 1,
+                    payment_epoch: None,
+                },
+                stall_detector: StallDetector::disabled(),
+                paused: &AtomicBool::new(false),
+                stopped: &AtomicBool::new(false),
+                journal: &Journal::disabled(),
+                heartbeat: &Heartbeat::new(),
+                clock: &SystemClock,
+            },
         );
 
         match res {
@@ -700,13 +1788,24 @@ mod tests {
                     resps.unwrap(),
                     Response {
                         num_challenges: 4,
-                        bid_responses: [(dummy_bid.txid, 1)].iter().cloned().collect()
+                        bid_responses: [(dummy_bid.txid, 1)].iter().cloned().collect(),
+                        challenges: vec![dummy_challenge_hash; 4]
                     }
                 );
                 assert_eq!(1, storage.challenge_responses.borrow().len());
+                let hashes = storage.get_response_hashes(dummy_request.txid).unwrap();
+                assert_eq!(4, hashes.len());
+                assert!(storage
+                    .get_response(dummy_request.txid)
+                    .unwrap()
+                    .unwrap()
+                    .verify_integrity(&hashes));
+                let latency = storage.get_challenge_latency(dummy_request.txid).unwrap().unwrap();
+                assert_eq!(4, latency.verify_ms.len());
+                assert_eq!(1, latency.proof_ms.len());
                 let bids = storage.get_bids(dummy_request.txid).unwrap();
-                assert_eq!(challenge_state.bids, HashSet::from_iter(bids.iter().cloned()));
-                let requests = storage.get_requests(None, None, None).unwrap();
+                assert_eq!(*challenge_state.bids, HashSet::from_iter(bids.iter().cloned()));
+                let requests = storage.get_requests(None, None, None, None).unwrap();
                 assert_eq!(1, requests.len());
                 assert_eq!(&challenge_state.request, &requests[0]);
                 assert_eq!(
@@ -720,7 +1819,9 @@ mod tests {
 
         // test client chain failure
         let _ = service.height.replace(dummy_request.start_blockheight as u64); // set height for fetch_next to succeed
-        let challenge_state = fetch_next(&service, &dummy_hash).unwrap().unwrap();
+        let challenge_state = fetch_next(&service, storage.as_ref(), &dummy_hash, 100)
+            .unwrap()
+            .unwrap();
 
         clientchain.return_err = true;
         assert!(run_challenge_request(
@@ -729,17 +1830,33 @@ mod tests {
             Arc::new(RwLock::new(Some(challenge_state))),
             &vrx,
             storage.clone(),
-            time::Duration::from_millis(10),
-            time::Duration::from_millis(10),
-            1,
-            time::Duration::from_millis(10),
+            ChallengerContext {
+                settings: ChallengeRequestSettings {
+                    timing: ChallengeTiming {
+                        challenge_duration: time::Duration::from_millis(10),
+                        verify_duration: time::Duration::from_millis(10),
+                        refresh_delay: time::Duration::from_millis(10)
+                    },
+                    grace_period: time::Duration::from_millis(0),
+                    challenge_frequency: 1,
+                    payment_epoch: None
+                },
+                stall_detector: StallDetector::disabled(),
+                paused: &AtomicBool::new(false),
+                stopped: &AtomicBool::new(false),
+                journal: &Journal::disabled(),
+                heartbeat: &Heartbeat::new(),
+                clock: &SystemClock
+            }
         )
         .is_err());
         clientchain.return_err = false;
 
         // test service chain failure
         let _ = service.height.replace(dummy_request.start_blockheight as u64); // set height for fetch_next to succeed
-        let challenge_state = fetch_next(&service, &dummy_hash).unwrap().unwrap();
+        let challenge_state = fetch_next(&service, storage.as_ref(), &dummy_hash, 100)
+            .unwrap()
+            .unwrap();
 
         service.return_err = true;
         assert!(run_challenge_request(
@@ -748,17 +1865,33 @@ mod tests {
             Arc::new(RwLock::new(Some(challenge_state))),
             &vrx,
             storage.clone(),
-            time::Duration::from_millis(10),
-            time::Duration::from_millis(10),
-            1,
-            time::Duration::from_millis(10),
+            ChallengerContext {
+                settings: ChallengeRequestSettings {
+                    timing: ChallengeTiming {
+                        challenge_duration: time::Duration::from_millis(10),
+                        verify_duration: time::Duration::from_millis(10),
+                        refresh_delay: time::Duration::from_millis(10)
+                    },
+                    grace_period: time::Duration::from_millis(0),
+                    challenge_frequency: 1,
+                    payment_epoch: None
+                },
+                stall_detector: StallDetector::disabled(),
+                paused: &AtomicBool::new(false),
+                stopped: &AtomicBool::new(false),
+                journal: &Journal::disabled(),
+                heartbeat: &Heartbeat::new(),
+                clock: &SystemClock
+            }
         )
         .is_err());
         service.return_err = false;
 
         // test storage failure
         let _ = service.height.replace(dummy_request.start_blockheight as u64); // set height for fetch_next to succeed
-        let challenge_state = fetch_next(&service, &dummy_hash).unwrap().unwrap();
+        let challenge_state = fetch_next(&service, storage.as_ref(), &dummy_hash, 100)
+            .unwrap()
+            .unwrap();
 
         let mut storage_err = MockStorage::new();
         storage_err.return_err = true;
@@ -768,17 +1901,33 @@ mod tests {
             Arc::new(RwLock::new(Some(challenge_state))),
             &vrx,
             Arc::new(storage_err),
-            time::Duration::from_millis(10),
-            time::Duration::from_millis(10),
-            1,
-            time::Duration::from_millis(10),
+            ChallengerContext {
+                settings: ChallengeRequestSettings {
+                    timing: ChallengeTiming {
+                        challenge_duration: time::Duration::from_millis(10),
+                        verify_duration: time::Duration::from_millis(10),
+                        refresh_delay: time::Duration::from_millis(10)
+                    },
+                    grace_period: time::Duration::from_millis(0),
+                    challenge_frequency: 1,
+                    payment_epoch: None
+                },
+                stall_detector: StallDetector::disabled(),
+                paused: &AtomicBool::new(false),
+                stopped: &AtomicBool::new(false),
+                journal: &Journal::disabled(),
+                heartbeat: &Heartbeat::new(),
+                clock: &SystemClock
+            }
         )
         .is_err());
 
         // test client chain returning false
         storage = Arc::new(MockStorage::new()); // reset storage;
         let _ = service.height.replace(dummy_request.start_blockheight as u64); // set height for fetch_next to succeed
-        let challenge_state = fetch_next(&service, &dummy_hash).unwrap().unwrap();
+        let challenge_state = fetch_next(&service, storage.as_ref(), &dummy_hash, 100)
+            .unwrap()
+            .unwrap();
 
         clientchain.return_false = true;
         vtx.send(ChallengeResponse(dummy_challenge_hash, dummy_bid.clone()))
@@ -790,10 +1939,24 @@ mod tests {
             Arc::new(RwLock::new(Some(challenge_state))),
             &vrx,
             storage.clone(),
-            time::Duration::from_millis(10),
-            time::Duration::from_millis(10),
-            1,
-            time::Duration::from_millis(10),
+            ChallengerContext {
+                settings: ChallengeRequestSettings {
+                    timing: ChallengeTiming {
+                        challenge_duration: time::Duration::from_millis(10),
+                        verify_duration: time::Duration::from_millis(10),
+                        refresh_delay: time::Duration::from_millis(10),
+                    },
+                    grace_period: time::Duration::from_millis(0),
+                    challenge_frequency: 1,
+                    payment_epoch: None,
+                },
+                stall_detector: StallDetector::disabled(),
+                paused: &AtomicBool::new(false),
+                stopped: &AtomicBool::new(false),
+                journal: &Journal::disabled(),
+                heartbeat: &Heartbeat::new(),
+                clock: &SystemClock,
+            },
         );
         match res {
             Ok(_) => assert!(false, "should not return Ok"),
@@ -808,7 +1971,9 @@ mod tests {
         // test run when height is already passed
         storage = Arc::new(MockStorage::new()); // reset storage;
         let _ = service.height.replace(dummy_request.end_blockheight as u64 + 1); // set height for fetch_next to succeed
-        let challenge_state = fetch_next(&service, &dummy_hash).unwrap().unwrap();
+        let challenge_state = fetch_next(&service, storage.as_ref(), &dummy_hash, 100)
+            .unwrap()
+            .unwrap();
 
         vtx.send(ChallengeResponse(dummy_challenge_hash, dummy_bid.clone()))
             .unwrap();
@@ -818,10 +1983,24 @@ mod tests {
             Arc::new(RwLock::new(Some(challenge_state))),
             &vrx,
             storage.clone(),
-            time::Duration::from_millis(10),
-            time::Duration::from_millis(10),
-            1,
-            time::Duration::from_millis(10),
+            ChallengerContext {
+                settings: ChallengeRequestSettings {
+                    timing: ChallengeTiming {
+                        challenge_duration: time::Duration::from_millis(10),
+                        verify_duration: time::Duration::from_millis(10),
+                        refresh_delay: time::Duration::from_millis(10),
+                    },
+                    grace_period: time::Duration::from_millis(0),
+                    challenge_frequency: 1,
+                    payment_epoch: None,
+                },
+                stall_detector: StallDetector::disabled(),
+                paused: &AtomicBool::new(false),
+                stopped: &AtomicBool::new(false),
+                journal: &Journal::disabled(),
+                heartbeat: &Heartbeat::new(),
+                clock: &SystemClock,
+            },
         );
         match res {
             Ok(_) => {
@@ -830,4 +2009,580 @@ mod tests {
             Err(_) => assert!(false, "should not return error"),
         }
     }
+
+    #[test]
+    fn run_challenge_request_epoch_test() {
+        setup_logger();
+        let clientchain = MockClientChain::new();
+        let storage = Arc::new(MockStorage::new());
+        let service = MockService::new();
+        let dummy_hash = gen_dummy_hash(0);
+        let dummy_request = service.get_request(&dummy_hash).unwrap().unwrap();
+
+        let _ = service.height.replace(dummy_request.start_blockheight as u64);
+        let challenge_state = fetch_next(&service, storage.as_ref(), &dummy_hash, 100)
+            .unwrap()
+            .unwrap();
+        storage
+            .save_challenge_request_state(&challenge_state.request, &challenge_state.bids)
+            .unwrap();
+        let (_vtx, vrx): (Sender<ChallengeResponse>, Receiver<ChallengeResponse>) = channel();
+        let _ = clientchain.height.replace(50);
+
+        // challenges at heights 2 to 5 and epochs ending at heights 3 and 4, with
+        // the final epoch ending with the request not snapshotted
+        let _ = service.height.replace(dummy_request.start_blockheight as u64);
+        run_challenge_request(
+            &service,
+            &clientchain,
+            Arc::new(RwLock::new(Some(challenge_state.clone()))),
+            &vrx,
+            storage.clone(),
+            ChallengerContext {
+                settings: ChallengeRequestSettings {
+                    timing: ChallengeTiming {
+                        challenge_duration: time::Duration::from_millis(10),
+                        verify_duration: time::Duration::from_millis(10),
+                        refresh_delay: time::Duration::from_millis(10),
+                    },
+                    grace_period: time::Duration::from_millis(0),
+                    challenge_frequency: 1,
+                    payment_epoch: Some(1),
+                },
+                stall_detector: StallDetector::disabled(),
+                paused: &AtomicBool::new(false),
+                stopped: &AtomicBool::new(false),
+                journal: &Journal::disabled(),
+                heartbeat: &Heartbeat::new(),
+                clock: &SystemClock,
+            },
+        )
+        .unwrap();
+        let snapshots = storage.get_response_snapshots(dummy_request.txid).unwrap();
+        assert_eq!(2, snapshots.len());
+        assert_eq!(0, snapshots[0].epoch);
+        assert_eq!(3, snapshots[0].service_height);
+        assert_eq!(50, snapshots[0].clientchain_height);
+        assert_eq!(2, snapshots[0].response.num_challenges);
+        assert_eq!(1, snapshots[1].epoch);
+        assert_eq!(4, snapshots[1].service_height);
+        assert_eq!(3, snapshots[1].response.num_challenges);
+        assert_eq!(challenge_state.bids.len(), snapshots[1].bids.len());
+        assert!(!snapshots[1].is_payment_complete);
+
+        // restarting the request continues from the stored epochs
+        let _ = service.height.replace(dummy_request.end_blockheight as u64 - 1);
+        run_challenge_request(
+            &service,
+            &clientchain,
+            Arc::new(RwLock::new(Some(challenge_state))),
+            &vrx,
+            storage.clone(),
+            ChallengerContext {
+                settings: ChallengeRequestSettings {
+                    timing: ChallengeTiming {
+                        challenge_duration: time::Duration::from_millis(10),
+                        verify_duration: time::Duration::from_millis(10),
+                        refresh_delay: time::Duration::from_millis(10),
+                    },
+                    grace_period: time::Duration::from_millis(0),
+                    challenge_frequency: 1,
+                    payment_epoch: Some(1),
+                },
+                stall_detector: StallDetector::disabled(),
+                paused: &AtomicBool::new(false),
+                stopped: &AtomicBool::new(false),
+                journal: &Journal::disabled(),
+                heartbeat: &Heartbeat::new(),
+                clock: &SystemClock,
+            },
+        )
+        .unwrap();
+        assert_eq!(2, storage.get_response_snapshots(dummy_request.txid).unwrap().len());
+    }
+
+    #[test]
+    fn run_challenge_request_clock_test() {
+        setup_logger();
+        let clientchain = MockClientChain::new();
+        let storage = Arc::new(MockStorage::new());
+        let service = MockService::new();
+        let clock = MockClock::new();
+        let dummy_hash = gen_dummy_hash(0);
+        let dummy_request = service.get_request(&dummy_hash).unwrap().unwrap();
+
+        let _ = service.height.replace(dummy_request.start_blockheight as u64);
+        let challenge_state = fetch_next(&service, storage.as_ref(), &dummy_hash, 100)
+            .unwrap()
+            .unwrap();
+        storage
+            .save_challenge_request_state(&challenge_state.request, &challenge_state.bids)
+            .unwrap();
+        let (vtx, vrx): (Sender<ChallengeResponse>, Receiver<ChallengeResponse>) = channel();
+        let dummy_challenge_hash = clientchain
+            .send_challenge(&challenge_state.request.txid, 0)
+            .unwrap()
+            .unwrap()
+            .txid;
+        let dummy_bid = challenge_state.bids.iter().next().unwrap().clone();
+        vtx.send(ChallengeResponse(dummy_challenge_hash, dummy_bid.clone()))
+            .unwrap();
+
+        // whole request fast-forwarded with challenges at heights 2 to 5, each
+        // collecting responses for a minute and the final challenge for the
+        // grace period on top, refreshing as soon as each challenge ends
+        let _ = service.height.replace(dummy_request.start_blockheight as u64);
+        run_challenge_request(
+            &service,
+            &clientchain,
+            Arc::new(RwLock::new(Some(challenge_state.clone()))),
+            &vrx,
+            storage.clone(),
+            ChallengerContext {
+                settings: ChallengeRequestSettings {
+                    timing: ChallengeTiming {
+                        challenge_duration: time::Duration::from_secs(60),
+                        verify_duration: time::Duration::from_secs(300),
+                        refresh_delay: time::Duration::from_secs(30),
+                    },
+                    grace_period: time::Duration::from_secs(120),
+                    challenge_frequency: 1,
+                    payment_epoch: None,
+                },
+                stall_detector: StallDetector::disabled(),
+                paused: &AtomicBool::new(false),
+                stopped: &AtomicBool::new(false),
+                journal: &Journal::disabled(),
+                heartbeat: &Heartbeat::new(),
+                clock: &clock,
+            },
+        )
+        .unwrap();
+        assert_eq!(time::Duration::from_secs(4 * 60 + 120), clock.elapsed());
+        let response = storage.get_response(dummy_request.txid).unwrap().unwrap();
+        assert_eq!(4, response.num_challenges);
+        assert_eq!(Some(&1), response.bid_responses.get(&dummy_bid.txid));
+        let latency = storage.get_challenge_latency(dummy_request.txid).unwrap().unwrap();
+        assert_eq!(vec![0; 4], latency.verify_ms);
+        assert_eq!(vec![0], latency.proof_ms);
+        let coverage = storage.get_challenge_coverage(dummy_request.txid).unwrap().unwrap();
+        assert_eq!(4, coverage.issued);
+        // unspents reserved by the request released once it ends
+        assert_eq!(vec![dummy_request.txid], *clientchain.released.borrow());
+        assert_eq!(
+            vec![BidProof {
+                bid: dummy_bid.txid,
+                challenge: dummy_challenge_hash,
+                clientchain_height: 0,
+                timestamp: MOCK_CLOCK_UNIX_START * 1000,
+            }],
+            storage.get_bid_proofs(dummy_request.txid, dummy_bid.txid).unwrap()
+        );
+    }
+
+    #[test]
+    fn run_challenge_request_stall_test() {
+        setup_logger();
+        let clientchain = MockClientChain::new();
+        let storage = Arc::new(MockStorage::new());
+        let service = MockService::new();
+        let clock = MockClock::new();
+        let dummy_hash = gen_dummy_hash(0);
+        let dummy_request = service.get_request(&dummy_hash).unwrap().unwrap();
+
+        let _ = service.height.replace(dummy_request.start_blockheight as u64);
+        let challenge_state = fetch_next(&service, storage.as_ref(), &dummy_hash, 100)
+            .unwrap()
+            .unwrap();
+        storage
+            .save_challenge_request_state(&challenge_state.request, &challenge_state.bids)
+            .unwrap();
+        let (_vtx, vrx): (Sender<ChallengeResponse>, Receiver<ChallengeResponse>) = channel();
+
+        // the mock client chain never produces a block, so it stalls after the
+        // first challenge at height 2 until the request ends at height 5
+        let _ = service.height.replace(dummy_request.start_blockheight as u64);
+        run_challenge_request(
+            &service,
+            &clientchain,
+            Arc::new(RwLock::new(Some(challenge_state.clone()))),
+            &vrx,
+            storage.clone(),
+            ChallengerContext {
+                settings: ChallengeRequestSettings {
+                    timing: ChallengeTiming {
+                        challenge_duration: time::Duration::from_secs(60),
+                        verify_duration: time::Duration::from_secs(300),
+                        refresh_delay: time::Duration::from_secs(30),
+                    },
+                    grace_period: time::Duration::from_secs(0),
+                    challenge_frequency: 1,
+                    payment_epoch: None,
+                },
+                stall_detector: StallDetector::new(time::Duration::from_secs(60), 10),
+                paused: &AtomicBool::new(false),
+                stopped: &AtomicBool::new(false),
+                journal: &Journal::disabled(),
+                heartbeat: &Heartbeat::new(),
+                clock: &clock,
+            },
+        )
+        .unwrap();
+        let response = storage.get_response(dummy_request.txid).unwrap().unwrap();
+        assert_eq!(1, response.num_challenges);
+        let coverage = storage.get_challenge_coverage(dummy_request.txid).unwrap().unwrap();
+        assert_eq!(1, coverage.issued);
+        assert_eq!(3, coverage.gaps[0].missed);
+
+        // stall period ended with the request
+        assert_eq!(
+            vec![ClientChainStall {
+                clientchain_height: 0,
+                start_height: 3,
+                end_height: 6,
+                start_time: MOCK_CLOCK_UNIX_START,
+                end_time: MOCK_CLOCK_UNIX_START + 150,
+                missed_blocks: 14,
+            }],
+            storage.get_clientchain_stalls(dummy_request.txid).unwrap()
+        );
+    }
+
+    #[test]
+    fn account_clientchain_stall_test() {
+        setup_logger();
+        let storage = MockStorage::new();
+        let mut challenge_state = gen_challenge_state(&gen_dummy_hash(1));
+        let mut stall = ClientChainStall {
+            clientchain_height: 50,
+            start_height: 3,
+            end_height: 9,
+            start_time: 0,
+            end_time: 600,
+            missed_blocks: 9,
+        };
+
+        // end height not set yet
+        let shared_state = RwLock::new(Some(challenge_state.clone()));
+        account_clientchain_stall(&storage, &shared_state, &stall, 51).unwrap();
+        assert_eq!(
+            0,
+            shared_state
+                .read()
+                .unwrap()
+                .as_ref()
+                .unwrap()
+                .request
+                .end_blockheight_clientchain
+        );
+
+        // missed blocks taken off the end height
+        challenge_state.request.end_blockheight_clientchain = 100;
+        storage
+            .save_challenge_request_state(&challenge_state.request, &challenge_state.bids)
+            .unwrap();
+        let shared_state = RwLock::new(Some(challenge_state.clone()));
+        account_clientchain_stall(&storage, &shared_state, &stall, 51).unwrap();
+        assert_eq!(
+            91,
+            shared_state
+                .read()
+                .unwrap()
+                .as_ref()
+                .unwrap()
+                .request
+                .end_blockheight_clientchain
+        );
+        let request = storage.get_request(challenge_state.request.txid).unwrap().unwrap();
+        assert_eq!(91, request.end_blockheight_clientchain);
+
+        // end height kept above the current client chain height
+        stall.missed_blocks = 60;
+        account_clientchain_stall(&storage, &shared_state, &stall, 51).unwrap();
+        let request = storage.get_request(challenge_state.request.txid).unwrap().unwrap();
+        assert_eq!(52, request.end_blockheight_clientchain);
+    }
+
+    #[test]
+    fn response_window_test() {
+        let request = gen_challenge_state(&gen_dummy_hash(1)).request;
+        let duration = time::Duration::from_secs(60);
+        let grace = time::Duration::from_secs(30);
+        // request ends at height 5
+        assert_eq!(duration, response_window(&request, 3, 1, duration, grace));
+        assert_eq!(duration, response_window(&request, 4, 1, duration, grace));
+        assert_eq!(duration + grace, response_window(&request, 5, 1, duration, grace));
+        // no further challenge due before the end of the request
+        assert_eq!(duration, response_window(&request, 3, 2, duration, grace));
+        assert_eq!(duration + grace, response_window(&request, 4, 2, duration, grace));
+        assert_eq!(duration + grace, response_window(&request, 2, 5, duration, grace));
+    }
+
+    #[test]
+    fn challenge_schedule_test() {
+        setup_logger();
+        let mut request = gen_challenge_state(&gen_dummy_hash(1)).request;
+        request.start_blockheight = 10;
+        request.end_blockheight = 20;
+
+        // upcoming request
+        let schedule = challenge_schedule(&request, 5, 4, 60, 1000);
+        assert_eq!(3, schedule.len());
+        assert_eq!(
+            ScheduledChallenge {
+                height: 10,
+                timestamp: 1300
+            },
+            schedule[0]
+        );
+        assert_eq!(14, schedule[1].height);
+        assert_eq!(1540, schedule[1].timestamp);
+        assert_eq!(18, schedule[2].height);
+        assert_eq!(1780, schedule[2].timestamp);
+
+        // active request with challenge at current height
+        let schedule = challenge_schedule(&request, 14, 4, 60, 1000);
+        assert_eq!(2, schedule.len());
+        assert_eq!(14, schedule[0].height);
+        assert_eq!(1000, schedule[0].timestamp);
+        assert_eq!(18, schedule[1].height);
+
+        // active request between challenges
+        let schedule = challenge_schedule(&request, 15, 4, 60, 1000);
+        assert_eq!(1, schedule.len());
+        assert_eq!(18, schedule[0].height);
+        assert_eq!(1180, schedule[0].timestamp);
+
+        // ended request
+        assert_eq!(0, challenge_schedule(&request, 21, 4, 60, 1000).len());
+
+        // zero frequency challenges every block
+        assert_eq!(11, challenge_schedule(&request, 0, 0, 60, 1000).len());
+    }
+
+    #[test]
+    fn recover_response_test() {
+        setup_logger();
+        let storage = MockStorage::new();
+        let state = gen_challenge_state(&gen_dummy_hash(1));
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let bid = state.bids.iter().next().unwrap().txid;
+        let responses: HashSet<sha256d::Hash> = vec![bid].into_iter().collect();
+
+        // nothing stored
+        assert_eq!(Response::new(), recover_response(&storage, state.request.txid).unwrap());
+        assert_eq!(None, storage.get_response(state.request.txid).unwrap());
+
+        // first challenge recorded and applied, second update lost
+        let mut response = Response::new();
+        for i in 10..12 {
+            let record = ChallengeRecord::new(gen_dummy_hash(i), &responses);
+            storage.save_challenge_record(state.request.txid, &record).unwrap();
+            storage.save_challenge_record(state.request.txid, &record).unwrap(); // no duplicates
+            response.challenges.push(gen_dummy_hash(i));
+            response.update(&responses);
+            if i == 10 {
+                storage
+                    .update_response(state.request.txid, gen_dummy_hash(i), &responses, &response)
+                    .unwrap();
+            }
+        }
+        assert_eq!(2, storage.get_challenge_records(state.request.txid).unwrap().len());
+        assert_eq!(
+            1,
+            storage
+                .get_response(state.request.txid)
+                .unwrap()
+                .unwrap()
+                .num_challenges
+        );
+
+        // aggregate rebuilt and stored
+        assert_eq!(response, recover_response(&storage, state.request.txid).unwrap());
+        assert_eq!(response, storage.get_response(state.request.txid).unwrap().unwrap());
+        assert_eq!(response, recover_response(&storage, state.request.txid).unwrap());
+    }
+
+    #[test]
+    fn record_request_failure_test() {
+        setup_logger();
+        let mut service = MockService::new();
+        let storage = MockStorage::new();
+        let state = gen_challenge_state(&gen_dummy_hash(1));
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let mut request = state.request.clone();
+        let mut response = Response::new();
+        response.challenges.push(gen_dummy_hash(10));
+        storage.save_response(request.txid, &response).unwrap();
+
+        // failure recorded with the height and challenges issued
+        let err = Error::from(CError::StorageTimeout("get_request".to_owned()));
+        record_request_failure(&service, &storage, &mut request, &err).unwrap();
+        let failure = RequestFailure {
+            category: ErrorCategory::Transient,
+            message: err.to_string(),
+            height: Some(*service.height.borrow() - 1),
+            challenges: 1,
+        };
+        assert_eq!(Some(failure), request.failure);
+        assert_eq!(request, storage.get_request(request.txid).unwrap().unwrap());
+
+        // failure recorded without the height if the service chain fails
+        service.return_err = true;
+        let err = Error::from(CError::Generic("failed".to_owned()));
+        record_request_failure(&service, &storage, &mut request, &err).unwrap();
+        let failure = storage.get_request(request.txid).unwrap().unwrap().failure.unwrap();
+        assert_eq!(ErrorCategory::Permanent, failure.category);
+        assert_eq!(None, failure.height);
+        assert_eq!(1, failure.challenges);
+    }
+
+    #[test]
+    fn refresh_bids_test() {
+        setup_logger();
+        let mut service = MockService::new();
+        let storage = MockStorage::new();
+        let challenge_state = RwLock::new(None);
+
+        // no active request
+        assert_eq!(None, refresh_bids(&service, &storage, &challenge_state).unwrap());
+
+        // bid missing from the service chain removed and new bid added
+        let mut state = gen_challenge_state(&gen_dummy_hash(1));
+        let kept = state.bids.iter().next().unwrap().clone();
+        let stale = Bid {
+            txid: gen_dummy_hash(7),
+            pubkey: kept.pubkey,
+            payment: None,
+        };
+        let _ = Arc::make_mut(&mut state.bids).insert(stale.clone());
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        *challenge_state.write().unwrap() = Some(state.clone());
+        let added =
+            sha256d::Hash::from_hex("0000000001234567890000000000000000000000000000000000000000000000").unwrap();
+        assert_eq!(
+            Some(BidRefresh {
+                request: state.request.txid,
+                added: vec![added],
+                removed: vec![stale.txid],
+                num_bids: 2,
+            }),
+            refresh_bids(&service, &storage, &challenge_state).unwrap()
+        );
+        let txids = |bids: Vec<Bid>| -> HashSet<sha256d::Hash> { bids.iter().map(|bid| bid.txid).collect() };
+        let expected: HashSet<sha256d::Hash> = vec![kept.txid, added].into_iter().collect();
+        assert_eq!(expected, txids(storage.get_bids(state.request.txid).unwrap()));
+        let refreshed = challenge_state.read().unwrap().as_ref().unwrap().all_bids().unwrap();
+        assert_eq!(expected, txids(refreshed.into_iter().collect()));
+        // original state clones unaffected
+        assert_eq!(2, state.bids.len());
+        assert!(state.bids.contains(&stale));
+
+        // no changes on a second refresh
+        assert_eq!(
+            Some(BidRefresh {
+                request: state.request.txid,
+                added: vec![],
+                removed: vec![],
+                num_bids: 2,
+            }),
+            refresh_bids(&service, &storage, &challenge_state).unwrap()
+        );
+
+        // service chain failure leaves the bids as they are
+        service.return_err = true;
+        assert!(refresh_bids(&service, &storage, &challenge_state).is_err());
+        service.return_err = false;
+        service.return_none = true;
+        assert!(refresh_bids(&service, &storage, &challenge_state).is_err());
+        assert_eq!(2, storage.get_bids(state.request.txid).unwrap().len());
+    }
+
+    #[test]
+    fn latest_challenge_test() {
+        let state = gen_challenge_state(&gen_dummy_hash(1));
+        let challenge_state = Arc::new(RwLock::new(Some(state.clone())));
+        assert_eq!(Some(gen_dummy_hash(0)), state.latest_challenge.hash());
+        assert_eq!(None, state.latest_challenge.sent_time());
+
+        // challenges are set and cleared under the read lock and are seen by
+        // all clones of the state sharing the holder
+        let ch_lock = challenge_state.read().unwrap();
+        let sent_time = time::Instant::now();
+        state.latest_challenge.set(gen_dummy_hash(2), sent_time);
+        let latest_challenge = &ch_lock.as_ref().unwrap().latest_challenge;
+        assert_eq!(Some(gen_dummy_hash(2)), latest_challenge.hash());
+        assert_eq!(Some(sent_time), latest_challenge.sent_time());
+        latest_challenge.clear();
+        assert_eq!(None, state.latest_challenge.hash());
+        assert_eq!(Some(sent_time), state.latest_challenge.sent_time());
+
+        // bids are shared without copying
+        assert!(Arc::ptr_eq(&state.bids, &ch_lock.as_ref().unwrap().bids));
+    }
+
+    #[test]
+    fn spill_bids_test() {
+        setup_logger();
+        let storage = MockStorage::new();
+        let state = gen_challenge_state(&gen_dummy_hash(1));
+        let pubkey = state.bids.iter().next().unwrap().pubkey;
+        let mut bids = BidSet::new();
+        for i in 1..4 {
+            let _ = bids.insert(Bid {
+                txid: gen_dummy_hash(i),
+                pubkey,
+                payment: None,
+            });
+        }
+        storage.save_challenge_request_state(&state.request, &bids).unwrap();
+
+        // bids with the lowest txids are kept in memory
+        let mut hot = bids.clone();
+        assert_eq!(0, trim_bids(&mut hot, 3));
+        assert_eq!(2, trim_bids(&mut hot, 1));
+        assert_eq!(1, hot.len());
+        assert_eq!(gen_dummy_hash(1), hot.iter().next().unwrap().txid);
+
+        // spilled bids are found in storage
+        let spilled = Bid {
+            txid: gen_dummy_hash(3),
+            pubkey,
+            payment: None,
+        };
+        assert!(is_stored_bid(&storage, state.request.txid, &spilled));
+        assert!(!is_stored_bid(&storage, gen_dummy_hash(9), &spilled));
+        let unknown = Bid {
+            txid: gen_dummy_hash(4),
+            pubkey,
+            payment: None,
+        };
+        assert!(!is_stored_bid(&storage, state.request.txid, &unknown));
+
+        // responses are updated incrementally with a valid integrity chain
+        let mut response = Response::new();
+        for i in 5..7 {
+            let challenge = gen_dummy_hash(i);
+            let responses: HashSet<sha256d::Hash> = HashSet::from_iter(vec![gen_dummy_hash(1), gen_dummy_hash(i - 3)]);
+            response.challenges.push(challenge);
+            response.update(&responses);
+            storage
+                .update_response(state.request.txid, challenge, &responses, &response)
+                .unwrap();
+        }
+        assert_eq!(
+            Some(response.clone()),
+            storage.get_response(state.request.txid).unwrap()
+        );
+        assert_eq!(2, response.bid_responses[&gen_dummy_hash(1)]);
+        let hashes = storage.get_response_hashes(state.request.txid).unwrap();
+        assert_eq!(2, hashes.len());
+        assert!(response.verify_integrity(&hashes));
+    }
 }
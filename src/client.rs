@@ -0,0 +1,238 @@
+//! Client
+//!
+//! Typed client of the coordinator json-rpc api, using the api response models
+//! shared with the server
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use base64::encode as b64encode;
+use bitcoin::hashes::sha256d;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::api::{
+    AuditBundleResponse, ExportRequestResponse, GetRequestResponse, GetRequestResponseProofResponse,
+    GetRequestResponseResponse, GetRequestsFullResponse, GetRequestsResponse, HASH_ORDER_PARAM,
+};
+use crate::config::ApiConfig;
+use crate::error::{CError, Error, Result};
+use crate::util::http;
+
+/// Authentication of api client calls
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiClientAuth {
+    /// No authentication
+    None,
+    /// Basic authentication with user and pass
+    Basic(String, String),
+    /// Bearer token authentication
+    Bearer(String),
+}
+
+impl ApiClientAuth {
+    /// Get the authorization header value, if any
+    fn header(&self) -> Option<String> {
+        match self {
+            ApiClientAuth::None => None,
+            ApiClientAuth::Basic(user, pass) => Some(format!("Basic {}", b64encode(&format!("{}:{}", user, pass)))),
+            ApiClientAuth::Bearer(token) => Some(format!("Bearer {}", token)),
+        }
+    }
+}
+
+/// Client of the coordinator api. Calls are made with the internal hash byte
+/// order, regardless of the server default, so that responses deserialize
+/// into the shared api models
+pub struct CoordinatorApiClient {
+    /// Api url of the form http://host[:port]
+    url: String,
+    /// Authentication of api calls
+    auth: ApiClientAuth,
+    /// Id of the next json-rpc call
+    next_id: AtomicUsize,
+}
+
+impl CoordinatorApiClient {
+    /// Return new CoordinatorApiClient for an api url
+    pub fn new(url: &str, auth: ApiClientAuth) -> CoordinatorApiClient {
+        CoordinatorApiClient {
+            url: url.to_owned(),
+            auth,
+            next_id: AtomicUsize::new(1),
+        }
+    }
+
+    /// Return new CoordinatorApiClient for the api host of the api config,
+    /// authenticating with the config user and pass
+    pub fn from_config(config: &ApiConfig) -> CoordinatorApiClient {
+        CoordinatorApiClient::new(
+            &format!("http://{}", config.host),
+            ApiClientAuth::Basic(config.user.clone(), config.pass.clone()),
+        )
+    }
+
+    /// Call an api method with named params, deserializing the result into the
+    /// response type. Results json encoded as a string are decoded first
+    pub fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let mut params = match params {
+            Value::Object(map) => map,
+            Value::Null => serde_json::Map::new(),
+            _ => {
+                return Err(Error::from(CError::Generic(
+                    "api params should be an object".to_owned(),
+                )))
+            }
+        };
+        let _ = params.insert(HASH_ORDER_PARAM.to_owned(), Value::from("internal"));
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+            "id": self.next_id.fetch_add(1, Ordering::SeqCst),
+        });
+        let header = self.auth.header();
+        let res = http::post(
+            &self.url,
+            "application/json",
+            header.as_ref().map(|h| h.as_str()),
+            &body.to_string(),
+        )?;
+        let mut res: Value = serde_json::from_str(&res)
+            .map_err(|e| CError::Generic(format!("api call {} invalid response: {}", method, e)))?;
+        if !res["error"].is_null() {
+            return Err(Error::from(CError::Generic(format!(
+                "api call {} failed: {}",
+                method, res["error"]["message"]
+            ))));
+        }
+        let result = match res["result"].take() {
+            Value::String(result) => serde_json::from_str(&result),
+            result => serde_json::from_value(result),
+        };
+        result.map_err(|e| Error::from(CError::Generic(format!("api call {} invalid result: {}", method, e))))
+    }
+
+    /// Get a request and its bids
+    pub fn get_request(&self, txid: &sha256d::Hash) -> Result<GetRequestResponse> {
+        self.call("getrequest", serde_json::json!({ "txid": txid }))
+    }
+
+    /// Get a page of requests along with their bids
+    pub fn get_requests(&self, page: u64) -> Result<GetRequestsResponse> {
+        self.call("getrequests", serde_json::json!({ "page": page }))
+    }
+
+    /// Get a page of requests along with their bids by cursor, starting with an
+    /// empty cursor and following the next cursor of each page until unset
+    pub fn get_requests_after(&self, cursor: &str) -> Result<GetRequestsResponse> {
+        self.call("getrequests", serde_json::json!({ "cursor": cursor }))
+    }
+
+    /// Get a page by number of the most recently updated requests and their
+    /// bids, most recent first
+    pub fn get_requests_recent(&self, page: u64) -> Result<GetRequestsResponse> {
+        self.call("getrequests", serde_json::json!({ "page": page, "sort": "updated" }))
+    }
+
+    /// Get a page of requests along with their bids and responses
+    pub fn get_requests_full(&self, page: u64) -> Result<GetRequestsFullResponse> {
+        self.call("getrequestsfull", serde_json::json!({ "page": page }))
+    }
+
+    /// Get the challenge response of a request
+    pub fn get_request_response(&self, txid: &sha256d::Hash) -> Result<GetRequestResponseResponse> {
+        self.call("getrequestresponse", serde_json::json!({ "txid": txid }))
+    }
+
+    /// Get the merkle root of the per bid response counts of a request and the
+    /// inclusion proof of the response count of a bid
+    pub fn get_request_response_proof(
+        &self,
+        txid: &sha256d::Hash,
+        bid: &sha256d::Hash,
+    ) -> Result<GetRequestResponseProofResponse> {
+        self.call(
+            "getrequestresponseproof",
+            serde_json::json!({ "txid": txid, "bid": bid }),
+        )
+    }
+
+    /// Get a request and its bids in the shape of the ocean rpc results
+    pub fn export_request(&self, txid: &sha256d::Hash) -> Result<ExportRequestResponse> {
+        self.call("exportrequest", serde_json::json!({ "txid": txid }))
+    }
+
+    /// Start generating the audit bundle of a request on the server
+    pub fn create_audit_bundle(&self, txid: &sha256d::Hash) -> Result<AuditBundleResponse> {
+        self.call("createauditbundle", serde_json::json!({ "txid": txid }))
+    }
+
+    /// Get the progress of the audit bundle of a request, along with the base64
+    /// encoded bundle once complete
+    pub fn get_audit_bundle(&self, txid: &sha256d::Hash) -> Result<AuditBundleResponse> {
+        self.call("getauditbundle", serde_json::json!({ "txid": txid }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use crate::interfaces::response::Response;
+    use crate::util::testing::{gen_dummy_hash, serve_once, setup_logger};
+
+    #[test]
+    fn client_auth_test() {
+        assert_eq!(None, ApiClientAuth::None.header());
+        assert_eq!(
+            Some("Basic dXNlcjpwYXNz".to_owned()),
+            ApiClientAuth::Basic("user".to_owned(), "pass".to_owned()).header()
+        );
+        assert_eq!(
+            Some("Bearer token".to_owned()),
+            ApiClientAuth::Bearer("token".to_owned()).header()
+        );
+    }
+
+    #[test]
+    fn client_call_test() {
+        setup_logger();
+        let txid = gen_dummy_hash(1);
+        let (url, handle) = serve_once(concat!(
+            "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n",
+            r#"{"jsonrpc":"2.0","result":"{\"response\":{\"num_challenges\":2,\"bid_responses\":{}}}","id":1}"#
+        ));
+        let client = CoordinatorApiClient::new(&url, ApiClientAuth::Bearer("token".to_owned()));
+        let res = client.get_request_response(&txid).unwrap();
+        assert_eq!(
+            GetRequestResponseResponse {
+                response: Response {
+                    num_challenges: 2,
+                    bid_responses: HashMap::new(),
+                    challenges: vec![],
+                },
+                summary: None,
+            },
+            res
+        );
+        let request = handle.join().unwrap();
+        assert!(request.contains("Authorization: Bearer token\r\n"));
+        assert!(request.contains("\"method\":\"getrequestresponse\""));
+        assert!(request.contains("\"hash_order\":\"internal\""));
+        assert!(request.contains(&format!("\"txid\":\"{}\"", txid)));
+
+        let (url, _) = serve_once(concat!(
+            "HTTP/1.0 200 OK\r\n\r\n",
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params: `txid` does not exist."},"id":1}"#
+        ));
+        let client = CoordinatorApiClient::new(&url, ApiClientAuth::None);
+        let err = client.get_request(&txid).unwrap_err();
+        assert!(err.to_string().contains("does not exist"));
+
+        let (url, _) = serve_once("HTTP/1.0 401 Unauthorized\r\n\r\n");
+        let client = CoordinatorApiClient::new(&url, ApiClientAuth::None);
+        assert!(client.get_requests(1).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+//! Clock
+//!
+//! Time source of the challenger and payments, which tests replace with a mock
+//! clock to fast-forward through challenge windows and payment schedules
+//! instead of sleeping, along with sanity checks of the coordinator host clock
+//! against the timestamps of the latest service and client chain blocks. Drift
+//! beyond the configured threshold is warned about and added to the challenge
+//! response window, so that a skewed host clock does not silently shorten the
+//! time that guardnodes have to respond to challenges
+
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::config::ChallengeTiming;
+use crate::error::Result;
+use crate::interfaces::clientchain::ClientChain;
+use crate::interfaces::service::Service;
+
+/// Source of the current time and of sleeps, so that time can be simulated in
+/// tests
+pub trait Clock {
+    /// Get the current instant
+    fn now(&self) -> Instant;
+    /// Get the current time since the unix epoch
+    fn unix_time(&self) -> Duration;
+    /// Sleep for a duration
+    fn sleep(&self, duration: Duration);
+    /// Get the real time to block for when waiting on an event, e.g. a
+    /// channel receive, with a timeout of a duration on the clock
+    fn timeout(&self, duration: Duration) -> Duration;
+    /// Account for a wait with a timeout of a duration on the clock that timed
+    /// out without the event
+    fn timed_out(&self, duration: Duration);
+}
+
+/// Clock of the host system
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    /// Get the current instant of the host clock
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    /// Get the current unix time of the host clock
+    fn unix_time(&self) -> Duration {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+    }
+
+    /// Sleep the current thread for a duration
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration)
+    }
+
+    /// Block for the duration itself
+    fn timeout(&self, duration: Duration) -> Duration {
+        duration
+    }
+
+    /// Nothing to account for, as the host clock moved on while blocking
+    fn timed_out(&self, _duration: Duration) {}
+}
+
+/// Get the drift in seconds of the host clock from the latest block timestamp
+/// of a chain with the given block time. The latest block is expected to be
+/// up to a block time old, so there is no drift within that. Positive drift
+/// means that the host clock is ahead of the chain and negative drift that it
+/// is behind
+pub fn chain_drift(now: u64, block_timestamp: u64, block_time: u64) -> i64 {
+    let (now, block_timestamp, block_time) = (now as i64, block_timestamp as i64, block_time as i64);
+    if now < block_timestamp {
+        now - block_timestamp
+    } else if now > block_timestamp + block_time {
+        now - block_timestamp - block_time
+    } else {
+        0
+    }
+}
+
+/// Drift in seconds of the host clock from the service and client chains
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ClockDrift {
+    /// Drift from the latest service chain block timestamp
+    pub service: i64,
+    /// Drift from the latest client chain block timestamp
+    pub clientchain: i64,
+}
+
+impl ClockDrift {
+    /// Get the drift of the host clock that both chains agree on. Drift from
+    /// a single chain more likely means that the chain has stalled or that its
+    /// latest block has a skewed timestamp, so the drift closest to zero is
+    /// taken and drift in opposite directions counts as none
+    pub fn host_drift(&self) -> i64 {
+        if self.service > 0 && self.clientchain > 0 {
+            self.service.min(self.clientchain)
+        } else if self.service < 0 && self.clientchain < 0 {
+            self.service.max(self.clientchain)
+        } else {
+            0
+        }
+    }
+}
+
+/// Measure the drift of the host clock from the latest block timestamps of the
+/// service and client chains, given the block time of each chain in seconds
+pub fn measure_drift<T: Service, K: ClientChain>(
+    service: &T,
+    clientchain: &K,
+    service_block_time: u64,
+    clientchain_block_time: u64,
+) -> Result<ClockDrift> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    Ok(ClockDrift {
+        service: chain_drift(now, service.get_block_time()?, service_block_time),
+        clientchain: chain_drift(now, clientchain.get_block_time()?, clientchain_block_time),
+    })
+}
+
+/// Check the host clock against the service and client chains, warning about
+/// drift from either chain beyond the threshold in seconds. Returns the host
+/// drift if beyond the threshold, or no drift if the threshold is 0 or the
+/// block timestamps cannot be fetched, as the clock check never interrupts
+/// challenging
+pub fn check_clock<T: Service, K: ClientChain>(
+    service: &T,
+    clientchain: &K,
+    service_block_time: u64,
+    clientchain_block_time: u64,
+    threshold: u64,
+) -> i64 {
+    if threshold == 0 {
+        return 0;
+    }
+    let drift = match measure_drift(service, clientchain, service_block_time, clientchain_block_time) {
+        Ok(drift) => drift,
+        Err(e) => {
+            warn!("clock check failed: {}", e);
+            return 0;
+        }
+    };
+    if drift.service.abs() as u64 > threshold {
+        warn!("host clock drifts {}s from the service chain", drift.service);
+    }
+    if drift.clientchain.abs() as u64 > threshold {
+        warn!("host clock drifts {}s from the client chain", drift.clientchain);
+    }
+    let host_drift = drift.host_drift();
+    if host_drift.abs() as u64 > threshold {
+        warn!(
+            "host clock drifts {}s from both chains, extending challenge response windows",
+            host_drift
+        );
+        host_drift
+    } else {
+        0
+    }
+}
+
+/// Extend the challenge response window of a challenge timing by the host
+/// clock drift in either direction, up to doubling the window
+pub fn compensate_timing(mut timing: ChallengeTiming, drift: i64) -> ChallengeTiming {
+    let extension = Duration::from_secs(drift.abs() as u64).min(timing.challenge_duration);
+    timing.challenge_duration += extension;
+    timing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::interfaces::mocks::clientchain::MockClientChain;
+    use crate::interfaces::mocks::service::MockService;
+    use crate::util::testing::setup_logger;
+
+    #[test]
+    fn chain_drift_test() {
+        // latest block within a block time
+        assert_eq!(0, chain_drift(1000, 1000, 60));
+        assert_eq!(0, chain_drift(1060, 1000, 60));
+        // host clock ahead
+        assert_eq!(40, chain_drift(1100, 1000, 60));
+        // host clock behind
+        assert_eq!(-30, chain_drift(970, 1000, 60));
+    }
+
+    #[test]
+    fn host_drift_test() {
+        let drift = |service, clientchain| ClockDrift { service, clientchain };
+        assert_eq!(0, drift(0, 0).host_drift());
+        assert_eq!(40, drift(40, 100).host_drift());
+        assert_eq!(-30, drift(-30, -90).host_drift());
+        // single chain or opposite drift
+        assert_eq!(0, drift(100, 0).host_drift());
+        assert_eq!(0, drift(100, -100).host_drift());
+    }
+
+    #[test]
+    fn check_clock_test() {
+        setup_logger();
+        let mut service = MockService::new();
+        let mut clientchain = MockClientChain::new();
+
+        // no drift
+        assert_eq!(0, check_clock(&service, &clientchain, 60, 60, 30));
+
+        // host clock behind both chains
+        service.block_time_offset = 120;
+        clientchain.block_time_offset = 100;
+        let drift = measure_drift(&service, &clientchain, 60, 60).unwrap();
+        assert!(drift.service <= -119 && drift.service >= -120);
+        assert_eq!(-100, check_clock(&service, &clientchain, 60, 60, 30));
+
+        // below threshold or disabled
+        assert_eq!(0, check_clock(&service, &clientchain, 60, 60, 200));
+        assert_eq!(0, check_clock(&service, &clientchain, 60, 60, 0));
+
+        // stalled service chain
+        service.block_time_offset = -600;
+        clientchain.block_time_offset = 0;
+        assert_eq!(0, check_clock(&service, &clientchain, 60, 60, 30));
+
+        // block timestamps not available
+        clientchain.return_err = true;
+        assert_eq!(0, check_clock(&service, &clientchain, 60, 60, 30));
+    }
+
+    #[test]
+    fn compensate_timing_test() {
+        let timing = ChallengeTiming {
+            challenge_duration: Duration::from_secs(60),
+            verify_duration: Duration::from_secs(300),
+            refresh_delay: Duration::from_secs(30),
+        };
+        assert_eq!(timing, compensate_timing(timing.clone(), 0));
+        assert_eq!(
+            Duration::from_secs(100),
+            compensate_timing(timing.clone(), -40).challenge_duration
+        );
+        // up to doubling the window
+        let compensated = compensate_timing(timing.clone(), 500);
+        assert_eq!(Duration::from_secs(120), compensated.challenge_duration);
+        assert_eq!(timing.verify_duration, compensated.verify_duration);
+    }
+}
@@ -2,8 +2,10 @@
 //!
 //! Config module handling config options from file/env
 
+use std::collections::HashMap;
 use std::env;
 use std::str::FromStr;
+use std::time::Duration;
 
 use config_rs::{Config as ConfigRs, Environment, File};
 use ocean::Address;
@@ -11,7 +13,14 @@ use serde::{Deserialize, Serialize};
 
 use crate::error::InputErrorType::{GenHash, MissingArgument, PrivKey};
 use crate::error::{CError, Error, Result};
+use crate::interfaces::bid::PayoutAddressType;
+use crate::interfaces::clientchain::{ChallengePreflight, ChallengeWalletPolicy};
+use crate::interfaces::request::FeeFilter;
+use crate::payload::PayloadScheme;
+use crate::payments::{PaymentExportFormat, PaymentMode, PayoutOrder};
+use crate::rotation::KeyRotationPolicy;
 use crate::util::checks::{check_hash_string, check_privkey_string};
+use crate::util::ocean::{RpcCredentials, OCEAN_CLIENT_SLOW_CALL_MS};
 
 #[derive(Debug, Serialize, Deserialize)]
 /// Api specific config
@@ -22,6 +31,52 @@ pub struct ApiConfig {
     pub user: String,
     /// Client rpc pass
     pub pass: String,
+    /// Auth provider of admin callers; one of basic, token or oauth2
+    pub auth_provider: String,
+    /// Bearer tokens accepted by the token auth provider
+    pub auth_tokens: Vec<String>,
+    /// Token introspection endpoint url of the oauth2 auth provider
+    pub auth_introspection_url: Option<String>,
+    /// Byte order of the hashes in api params and responses, unless set per
+    /// call; one of rpc or internal
+    pub hash_order: String,
+    /// Number of api server threads, and of worker threads running api calls
+    pub threads: u64,
+    /// Max number of api calls pending a worker thread before rejecting calls
+    pub queue: u64,
+    /// Max time in seconds that api calls wait for a worker thread before
+    /// timing out; 0 disables timeouts
+    pub request_timeout: u64,
+    /// Origins of the browser clients allowed by the api cors policy, e.g.
+    /// explorers; only the null origin is allowed if not set
+    pub cors_origins: Vec<String>,
+    /// Flag to allow any origin, for development only
+    pub cors_allow_all: bool,
+    /// Flag to keep api connections alive between calls
+    pub keep_alive: bool,
+    /// Flag to compress api responses with the gzip or deflate encoding
+    /// accepted by the caller
+    pub compression: bool,
+    /// Min size in bytes of the api responses that are compressed
+    pub compression_min_size: u64,
+    /// Max size in bytes of api responses, with larger responses replaced by
+    /// an error; 0 disables the limit
+    pub max_response_size: u64,
+    /// Max number of calls in a batch request, with larger batches rejected;
+    /// 0 disables the limit
+    pub max_batch_size: u64,
+    /// Max number of api calls per rate limit window of each api key, unless
+    /// overridden for tenants; 0 disables the limit
+    pub rate_limit_key: u64,
+    /// Max number of api calls per rate limit window of each client ip, taken
+    /// from the forwarded headers of a trusted reverse proxy; 0 disables the
+    /// limit
+    pub rate_limit_ip: u64,
+    /// Addresses of the reverse proxies trusted to forward the client ip of
+    /// api calls, in CIDR notation; forwarded headers are ignored if not set
+    pub trusted_proxies: Vec<String>,
+    /// Length in seconds of the api rate limit windows
+    pub rate_limit_window: u64,
 }
 
 impl Default for ApiConfig {
@@ -30,6 +85,24 @@ impl Default for ApiConfig {
             host: String::new(),
             user: String::new(),
             pass: String::new(),
+            auth_provider: String::from("basic"),
+            auth_tokens: vec![],
+            auth_introspection_url: None,
+            hash_order: String::from("rpc"),
+            threads: CONFIG_API_THREADS_DEFAULT,
+            queue: CONFIG_API_QUEUE_DEFAULT,
+            request_timeout: CONFIG_API_REQUEST_TIMEOUT_DEFAULT,
+            cors_origins: vec![],
+            cors_allow_all: false,
+            keep_alive: true,
+            compression: false,
+            compression_min_size: CONFIG_API_COMPRESSION_MIN_SIZE_DEFAULT,
+            max_response_size: 0,
+            max_batch_size: CONFIG_API_MAX_BATCH_SIZE_DEFAULT,
+            rate_limit_key: 0,
+            rate_limit_ip: 0,
+            trusted_proxies: vec![],
+            rate_limit_window: CONFIG_API_RATE_LIMIT_WINDOW_DEFAULT,
         }
     }
 }
@@ -43,6 +116,21 @@ pub struct ServiceConfig {
     pub user: String,
     /// Client rpc pass
     pub pass: String,
+    /// File of the client rpc credentials as user:pass, overriding user and
+    /// pass, that is re-read when credentials are reloaded
+    pub credentials_file: Option<String>,
+}
+
+impl ServiceConfig {
+    /// Get the source of the client rpc credentials
+    pub fn rpc_credentials(&self) -> RpcCredentials {
+        RpcCredentials {
+            user: Some(self.user.clone()),
+            pass: Some(self.pass.clone()),
+            file: self.credentials_file.clone(),
+            env_prefix: Some("CO_SERVICE".to_owned()),
+        }
+    }
 }
 
 impl Default for ServiceConfig {
@@ -51,6 +139,7 @@ impl Default for ServiceConfig {
             host: String::new(),
             user: String::new(),
             pass: String::new(),
+            credentials_file: None,
         }
     }
 }
@@ -64,6 +153,9 @@ pub struct ClientChainConfig {
     pub user: String,
     /// Client rpc pass
     pub pass: String,
+    /// File of the client rpc credentials as user:pass, overriding user and
+    /// pass, that is re-read when credentials are reloaded
+    pub credentials_file: Option<String>,
     /// Client genesis hash
     pub genesis_hash: String,
     /// Block time in seconds
@@ -76,10 +168,83 @@ pub struct ClientChainConfig {
     pub chain: String,
     /// Payment asset label or asset id or ANY asset to be used for payments
     pub payment_asset: String,
+    /// Labels or asset ids of the assets that client chain fees accrue in
+    pub fee_assets: Vec<String>,
     /// Payment key; optional as the coordinator might not be doing payments
     pub payment_key: Option<String>,
     /// Payment address corresponding to payment key
     pub payment_addr: Option<String>,
+    /// Type of the addresses derived from bid pubkeys that bids are paid to;
+    /// one of p2pkh, p2sh-p2wpkh or p2wpkh
+    pub payment_address_type: String,
+    /// Fee percentage override used when calculating bid payments; the
+    /// request fee percentage is used if not set
+    pub fee_percentage: Option<u32>,
+    /// Flag to chain each challenge off the output of the previous challenge
+    /// instead of spending the first challenge asset unspent
+    pub chain_challenges: bool,
+    /// Policy applied before broadcasting a challenge if the previous
+    /// challenge is unconfirmed or the unspent is spent in the mempool; one of
+    /// wait, replace or skip
+    pub challenge_preflight: String,
+    /// Addresses of coinbase outputs that are not counted as fees, e.g. burn
+    /// or treasury outputs
+    pub fee_exclude_addresses: Vec<String>,
+    /// Script types of coinbase outputs that are not counted as fees
+    pub fee_exclude_script_types: Vec<String>,
+    /// Designated fee addresses; only coinbase outputs paying one of them are
+    /// counted as fees if set
+    pub fee_addresses: Vec<String>,
+    /// Mode in which bids are paid; wallet to pay with the payment key or
+    /// watch-only to export payments for an external wallet
+    pub payment_mode: String,
+    /// Dir that bid payment batches are exported to in watch-only mode
+    pub payment_export_dir: String,
+    /// Format of the exported bid payment batches; json or bip21
+    pub payment_export_format: String,
+    /// Flag to pay bids as far as the wallet funds allow when the wallet
+    /// cannot fund all bid payments, deferring the rest until topped up
+    pub partial_payouts: bool,
+    /// Order in which bids are paid with partial payouts; smallest-first,
+    /// largest-first or most-responses
+    pub payout_order: String,
+    /// Scheme of the challenge payload that challenge proofs commit to; none
+    /// or clientchain-blockhash
+    pub challenge_payload: String,
+    /// Number of distinct amounts in satoshis that challenges are tagged with
+    /// by an additional output, cycling by challenge sequence number; 0
+    /// disables tagging
+    pub challenge_amount_tags: u64,
+    /// Wallets of the client node that challenges are issued from; the
+    /// default wallet of the node is used if empty
+    pub challenge_wallets: Vec<ChallengeWalletConfig>,
+    /// Policy of selecting the challenge wallet of each challenge; one of
+    /// round-robin or failover
+    pub challenge_wallet_policy: String,
+    /// Flag to commit to each challenge on the client chain before revealing
+    /// it, so that challenge proofs can not be precomputed
+    pub challenge_commit_reveal: bool,
+}
+
+impl ClientChainConfig {
+    /// Get the source of the client rpc credentials
+    pub fn rpc_credentials(&self) -> RpcCredentials {
+        RpcCredentials {
+            user: Some(self.user.clone()),
+            pass: Some(self.pass.clone()),
+            file: self.credentials_file.clone(),
+            env_prefix: Some("CO_CLIENTCHAIN".to_owned()),
+        }
+    }
+
+    /// Get the filter of the coinbase outputs counted as fees
+    pub fn fee_filter(&self) -> FeeFilter {
+        FeeFilter {
+            exclude_addresses: self.fee_exclude_addresses.clone(),
+            exclude_script_types: self.fee_exclude_script_types.clone(),
+            include_addresses: self.fee_addresses.clone(),
+        }
+    }
 }
 
 impl Default for ClientChainConfig {
@@ -88,14 +253,99 @@ impl Default for ClientChainConfig {
             host: String::new(),
             user: String::new(),
             pass: String::new(),
+            credentials_file: None,
             genesis_hash: String::new(),
             block_time: CONFIG_BLOCK_TIME_DEFAULT,
             asset: String::from("CHALLENGE"),
             asset_key: String::new(),
             chain: String::new(),
             payment_asset: String::new(),
+            fee_assets: vec![String::from("CBT")],
             payment_key: None,
             payment_addr: None,
+            payment_address_type: String::from("p2pkh"),
+            fee_percentage: None,
+            chain_challenges: false,
+            challenge_preflight: String::from("replace"),
+            fee_exclude_addresses: vec![],
+            fee_exclude_script_types: vec![],
+            fee_addresses: vec![],
+            payment_mode: String::from("wallet"),
+            payment_export_dir: String::from("payments"),
+            payment_export_format: String::from("json"),
+            partial_payouts: false,
+            payout_order: String::from("smallest-first"),
+            challenge_payload: String::from("none"),
+            challenge_amount_tags: 0,
+            challenge_wallets: vec![],
+            challenge_wallet_policy: String::from("failover"),
+            challenge_commit_reveal: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+/// Wallet of the client node that challenges are issued from
+pub struct ChallengeWalletConfig {
+    /// Wallet name
+    pub name: String,
+    /// Challenge asset key imported into the wallet if it holds no challenge
+    /// asset; the clientchain asset key is used if not set
+    pub asset_key: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Tenant specific config. Each tenant is a client identified by the genesis
+/// hash of its client chain, with its own api credentials that restrict api
+/// access to the tenant's requests and optional clientchain config overrides
+pub struct TenantConfig {
+    /// Client chain genesis hash identifying the tenant
+    pub genesis_hash: String,
+    /// Tenant api user
+    pub api_user: String,
+    /// Tenant api pass
+    pub api_pass: String,
+    /// Challenge asset label override
+    pub asset: Option<String>,
+    /// Challenge asset key override
+    pub asset_key: Option<String>,
+    /// Payment asset override
+    pub payment_asset: Option<String>,
+    /// Fee assets override
+    pub fee_assets: Option<Vec<String>>,
+    /// Fee percentage override used when calculating bid payments
+    pub fee_percentage: Option<u32>,
+    /// Challenge payload scheme override
+    pub challenge_payload: Option<String>,
+    /// Challenge commit-reveal flag override
+    pub challenge_commit_reveal: Option<bool>,
+    /// Api rate limit override of the tenant api key
+    pub api_rate_limit: Option<u64>,
+}
+
+impl TenantConfig {
+    /// Apply tenant overrides to a clientchain config
+    pub fn apply(&self, clientchain: &mut ClientChainConfig) {
+        if let Some(asset) = &self.asset {
+            clientchain.asset = asset.clone();
+        }
+        if let Some(asset_key) = &self.asset_key {
+            clientchain.asset_key = asset_key.clone();
+        }
+        if let Some(payment_asset) = &self.payment_asset {
+            clientchain.payment_asset = payment_asset.clone();
+        }
+        if let Some(fee_assets) = &self.fee_assets {
+            clientchain.fee_assets = fee_assets.clone();
+        }
+        if let Some(fee_percentage) = self.fee_percentage {
+            clientchain.fee_percentage = Some(fee_percentage);
+        }
+        if let Some(challenge_payload) = &self.challenge_payload {
+            clientchain.challenge_payload = challenge_payload.clone();
+        }
+        if let Some(challenge_commit_reveal) = self.challenge_commit_reveal {
+            clientchain.challenge_commit_reveal = challenge_commit_reveal;
         }
     }
 }
@@ -111,6 +361,26 @@ pub struct StorageConfig {
     pub user: Option<String>,
     /// Storage pass
     pub pass: Option<String>,
+    /// Timeout for establishing storage connections, in seconds
+    pub connect_timeout: u64,
+    /// Timeout for storage socket reads, in seconds
+    pub read_timeout: u64,
+    /// Timeout for storage writes to be acknowledged, in seconds
+    pub write_timeout: u64,
+    /// Deadline for acquiring the storage connection for an operation, in
+    /// seconds, after which the operation fails with a storage timeout error
+    pub operation_timeout: u64,
+    /// Uri of a read replica that api reads are served from instead of the
+    /// primary; api reads use the primary if not set
+    pub read_uri: Option<String>,
+    /// Max lag of the read replica behind the primary, in seconds, above which
+    /// a warning is raised as api reads may be stale
+    pub max_read_lag: u64,
+    /// Hex AES-256 key that sensitive fields, such as bid payment addresses
+    /// and amounts, are encrypted with in storage
+    pub encryption_key: Option<String>,
+    /// File that the hex encryption key is read from if the key is not set
+    pub encryption_keyfile: Option<String>,
 }
 
 impl Default for StorageConfig {
@@ -120,10 +390,155 @@ impl Default for StorageConfig {
             name: String::from("coordinator"),
             user: None,
             pass: None,
+            connect_timeout: CONFIG_STORAGE_CONNECT_TIMEOUT_DEFAULT,
+            read_timeout: CONFIG_STORAGE_READ_TIMEOUT_DEFAULT,
+            write_timeout: CONFIG_STORAGE_WRITE_TIMEOUT_DEFAULT,
+            operation_timeout: CONFIG_STORAGE_OPERATION_TIMEOUT_DEFAULT,
+            read_uri: None,
+            max_read_lag: CONFIG_STORAGE_MAX_READ_LAG_DEFAULT,
+            encryption_key: None,
+            encryption_keyfile: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+/// Challenge timing overrides in seconds for a client chain genesis hash
+pub struct ChallengeTimingConfig {
+    /// Challenge duration override
+    pub challenge_duration: Option<u64>,
+    /// Challenge verify window override
+    pub verify_duration: Option<u64>,
+    /// Challenge refresh delay override
+    pub refresh_delay: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+/// Wallet balance monitor config. Balances and thresholds are in units of the
+/// corresponding asset
+pub struct MonitorConfig {
+    /// Interval between balance checks in seconds; monitor is off if zero
+    pub interval: u64,
+    /// Challenge asset balance required on top of projected consumption
+    pub challenge_asset_threshold: f64,
+    /// Payment asset balance required on top of projected payments
+    pub payment_asset_threshold: f64,
+    /// Challenge asset amount consumed per challenge
+    pub challenge_cost: f64,
+    /// Url that low balance and chain connectivity alerts are posted to
+    pub webhook: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Challenge wallet funding config. Amounts are in units of the challenge asset
+pub struct FundingConfig {
+    /// Amount of each challenge sized output split from the funding utxo;
+    /// funding is off if zero
+    pub challenge_amount: f64,
+    /// Outpoint of the funding utxo as txid:vout; the largest challenge asset
+    /// unspent is used if not set or already spent
+    pub utxo: Option<String>,
+    /// Max number of challenge sized outputs split in one transaction
+    pub max_outputs: u32,
+}
+
+impl Default for FundingConfig {
+    fn default() -> FundingConfig {
+        FundingConfig {
+            challenge_amount: 0.0,
+            utxo: None,
+            max_outputs: CONFIG_FUNDING_MAX_OUTPUTS_DEFAULT,
         }
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+/// Bid lock refunds config
+pub struct RefundsConfig {
+    /// Interval in seconds between checks for expired bid locks of finished
+    /// requests; refunds are off if zero
+    pub interval: u64,
+    /// Flag to sign refund transactions with the service chain wallet and
+    /// broadcast them; refund transactions are exported unsigned if not set
+    pub broadcast: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+/// Outbound proxy config of the http requests originated by the coordinator,
+/// such as alert webhooks and token introspection. Rpc connections to the
+/// service and client chain nodes do not go through the proxy
+pub struct OutboundProxyConfig {
+    /// Proxy url as socks5://[user:pass@]host:port, or socks5h:// to resolve
+    /// hostnames through the proxy as required for tor; no proxy if not set
+    pub url: Option<String>,
+    /// Hosts that are connected to directly instead of through the proxy
+    pub bypass: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+/// Latency bucket of latency weighted bid payments
+pub struct LatencyBucketConfig {
+    /// Upper bound in milliseconds of the proof arrival latency of responses
+    /// in the bucket
+    pub max_ms: u64,
+    /// Weight percentage that responses in the bucket count towards payments
+    pub weight: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+/// Challenge proof acceptance policy of the listener
+pub struct ProofPolicyConfig {
+    /// Policy name, one of allowed-ranges or max-delay
+    pub policy: String,
+    /// Ip ranges in CIDR notation that proofs are accepted from, for the
+    /// allowed-ranges policy
+    #[serde(default)]
+    pub ranges: Vec<String>,
+    /// Max delay in seconds of proofs after the challenge is issued, for the
+    /// max-delay policy
+    #[serde(default)]
+    pub max_delay: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+/// Maintenance window during which non-critical work is deferred
+pub struct MaintenanceWindowConfig {
+    /// Window name recorded in the journal
+    pub name: String,
+    /// Cron expression of the window start times in UTC
+    pub schedule: String,
+    /// Window duration in seconds
+    pub duration: u64,
+    /// Flag to pause issuing challenges during the window, resuming them once
+    /// the window ends
+    #[serde(default)]
+    pub pause_challenges: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+/// Address params of a chain, as numeric prefixes and bech32 hrp
+pub struct AddrParamsConfig {
+    /// Pay to public key hash address prefix
+    pub p2pkh_prefix: u8,
+    /// Pay to script hash address prefix
+    pub p2sh_prefix: u8,
+    /// Blinded address prefix
+    pub blinded_prefix: u8,
+    /// Bech32 human readable part
+    pub bech_hrp: String,
+}
+
+/// Challenge timings used when running a challenge request
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChallengeTiming {
+    /// Duration that challenge responses are accepted for
+    pub challenge_duration: Duration,
+    /// Duration that challenge transaction inclusion is verified for
+    pub verify_duration: Duration,
+    /// Delay between checks for new service chain blocks
+    pub refresh_delay: Duration,
+}
+
 /// Config struct storing all config
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
@@ -133,10 +548,87 @@ pub struct Config {
     pub challenge_duration: u64,
     /// Challenge frequency in number of blocks
     pub challenge_frequency: u64,
+    /// Grace period in seconds after the response window of the final
+    /// challenge of a request that responses are still collected for
+    pub challenge_grace_period: u64,
     /// Block time of service chain in seconds
     pub block_time: u64,
     /// Listener host address
     pub listener_host: String,
+    /// Number of listener threads verifying challenge proof signatures
+    pub listener_verify_threads: u64,
+    /// Max number of challenge proofs queued for verification by the listener
+    pub listener_verify_queue: u64,
+    /// Max number of late challenge proofs, whose challenge ended while queued
+    /// for verification, buffered separately from proofs of current challenges
+    pub listener_late_queue: u64,
+    /// Number of consecutive invalid proof signatures after which a bid is
+    /// blacklisted; bids are never blacklisted if 0
+    pub listener_blacklist_strikes: u32,
+    /// Duration in seconds that bids remain blacklisted
+    pub listener_blacklist_cooldown: u64,
+    /// Flag to store the redacted payloads of listener submissions that could
+    /// not be parsed as dead letters
+    pub listener_dead_letters: bool,
+    /// Max number of payload bytes stored per dead letter
+    pub listener_dead_letter_payload: u64,
+    /// Flag to reject listener submissions with fields that are not challenge
+    /// proof fields instead of ignoring these fields
+    pub listener_strict_proof_fields: bool,
+    /// Flag to repair inconsistencies found by the startup consistency check
+    pub consistency_repair: bool,
+    /// Number of client chain blocks after the end of a paid request that its
+    /// responses are compacted into a summary; compaction is off if not set
+    pub response_compaction_age: Option<u32>,
+    /// Interval in seconds of checking storage for finished requests to pay,
+    /// for requests finished by other coordinator processes; storage is not
+    /// checked if not set
+    pub payments_watch_interval: Option<u64>,
+    /// Length of bid payment epochs in service chain blocks; bids are paid
+    /// once at the end of the request if not set
+    pub payment_epoch: Option<u64>,
+    /// Max request duration in service chain blocks; longer requests are
+    /// rejected
+    pub request_max_duration: u64,
+    /// Max number of bids of the challenged request kept in memory, with the
+    /// rest paged from storage on demand; all bids are kept in memory if 0
+    pub challenge_max_bids: u64,
+    /// Path of the journal file recording coordinator decisions for replay;
+    /// decisions are not journaled if not set
+    pub journal_path: Option<String>,
+    /// Flag to reconcile the responses of each finished request against the
+    /// challenge proofs published by guardnodes on the client chain
+    pub reconciliation: bool,
+    /// Latency buckets, by ascending latency bound, that bid responses are
+    /// weighted by in payments; responses count equally if empty
+    pub latency_weights: Vec<LatencyBucketConfig>,
+    /// Acceptance policies that the listener checks challenge proofs against,
+    /// in order; all proofs passing validation are accepted if empty
+    pub proof_policies: Vec<ProofPolicyConfig>,
+    /// Maintenance windows during which payments and response compaction are
+    /// deferred and challenges optionally paused
+    pub maintenance_windows: Vec<MaintenanceWindowConfig>,
+    /// Duration in ms above which service and client chain rpc calls are
+    /// logged as slow; calls are never logged as slow if 0
+    pub rpc_slow_call_ms: u64,
+    /// Drift in seconds of the host clock from the latest chain block
+    /// timestamps above which the drift is warned about and added to the
+    /// challenge response window; the clock is not checked if 0
+    pub clock_drift_threshold: u64,
+    /// Duration in seconds without a new client chain block after which the
+    /// client chain is considered stalled and challenges are skipped until it
+    /// produces a new block; stalls are never detected if 0
+    pub clientchain_stall_threshold: u64,
+    /// Duration in seconds without a heartbeat of the challenger loop after
+    /// which the loop is considered stalled and restarted; the loop is never
+    /// restarted if 0
+    pub watchdog_timeout: u64,
+    /// Policy of accepting bid key rotation messages within a request, one of
+    /// disabled, old-key, both-keys or admin
+    pub key_rotation: String,
+    /// Hex secp256k1 key that request audit bundles are signed with; bundles
+    /// are not signed if not set
+    pub audit_signing_key: Option<String>,
     /// Api configuration
     pub api: ApiConfig,
     /// Service configuration
@@ -145,12 +637,49 @@ pub struct Config {
     pub clientchain: ClientChainConfig,
     /// Storage configuration
     pub storage: StorageConfig,
+    /// Tenant configurations
+    pub tenants: Vec<TenantConfig>,
+    /// Challenge timing overrides by client chain genesis hash
+    pub challenge_timings: HashMap<String, ChallengeTimingConfig>,
+    /// Address params of additional chains by chain name
+    pub addr_params: HashMap<String, AddrParamsConfig>,
+    /// Wallet balance monitor configuration
+    pub monitor: MonitorConfig,
+    /// Challenge wallet funding configuration
+    pub funding: FundingConfig,
+    /// Bid lock refunds configuration
+    pub refunds: RefundsConfig,
+    /// Outbound http proxy configuration
+    pub outbound_proxy: OutboundProxyConfig,
 }
 
 /// Config default variable definitons
 const CONFIG_CHALLENGE_DURATION_DEFAULT: u64 = 60;
 const CONFIG_CHALLENGE_FREQUENCY_DEFAULT: u64 = 1;
 const CONFIG_BLOCK_TIME_DEFAULT: u64 = 60;
+const CONFIG_LISTENER_VERIFY_THREADS_DEFAULT: u64 = 2;
+const CONFIG_LISTENER_VERIFY_QUEUE_DEFAULT: u64 = 1000;
+const CONFIG_LISTENER_LATE_QUEUE_DEFAULT: u64 = 100;
+const CONFIG_LISTENER_BLACKLIST_STRIKES_DEFAULT: u32 = 5;
+const CONFIG_LISTENER_BLACKLIST_COOLDOWN_DEFAULT: u64 = 3600;
+const CONFIG_LISTENER_DEAD_LETTER_PAYLOAD_DEFAULT: u64 = 1024;
+const CONFIG_REQUEST_MAX_DURATION_DEFAULT: u64 = 43200;
+const CONFIG_CHALLENGE_MAX_BIDS_DEFAULT: u64 = 1000;
+const CONFIG_CLOCK_DRIFT_THRESHOLD_DEFAULT: u64 = 30;
+const CONFIG_CLIENTCHAIN_STALL_THRESHOLD_DEFAULT: u64 = 1800;
+const CONFIG_WATCHDOG_TIMEOUT_DEFAULT: u64 = 900;
+const CONFIG_API_THREADS_DEFAULT: u64 = 2;
+const CONFIG_API_QUEUE_DEFAULT: u64 = 100;
+const CONFIG_API_REQUEST_TIMEOUT_DEFAULT: u64 = 30;
+const CONFIG_API_COMPRESSION_MIN_SIZE_DEFAULT: u64 = 1024;
+const CONFIG_API_MAX_BATCH_SIZE_DEFAULT: u64 = 100;
+const CONFIG_API_RATE_LIMIT_WINDOW_DEFAULT: u64 = 60;
+const CONFIG_FUNDING_MAX_OUTPUTS_DEFAULT: u32 = 100;
+const CONFIG_STORAGE_CONNECT_TIMEOUT_DEFAULT: u64 = 10;
+const CONFIG_STORAGE_READ_TIMEOUT_DEFAULT: u64 = 30;
+const CONFIG_STORAGE_WRITE_TIMEOUT_DEFAULT: u64 = 30;
+const CONFIG_STORAGE_OPERATION_TIMEOUT_DEFAULT: u64 = 60;
+const CONFIG_STORAGE_MAX_READ_LAG_DEFAULT: u64 = 30;
 
 impl Default for Config {
     fn default() -> Config {
@@ -158,12 +687,45 @@ impl Default for Config {
             log_level: String::from("coordinator"),
             challenge_duration: CONFIG_CHALLENGE_DURATION_DEFAULT,
             challenge_frequency: CONFIG_CHALLENGE_FREQUENCY_DEFAULT,
+            challenge_grace_period: 0,
             block_time: CONFIG_BLOCK_TIME_DEFAULT,
             listener_host: String::from("localhost:80"),
+            listener_verify_threads: CONFIG_LISTENER_VERIFY_THREADS_DEFAULT,
+            listener_verify_queue: CONFIG_LISTENER_VERIFY_QUEUE_DEFAULT,
+            listener_late_queue: CONFIG_LISTENER_LATE_QUEUE_DEFAULT,
+            listener_blacklist_strikes: CONFIG_LISTENER_BLACKLIST_STRIKES_DEFAULT,
+            listener_blacklist_cooldown: CONFIG_LISTENER_BLACKLIST_COOLDOWN_DEFAULT,
+            listener_dead_letters: false,
+            listener_dead_letter_payload: CONFIG_LISTENER_DEAD_LETTER_PAYLOAD_DEFAULT,
+            listener_strict_proof_fields: false,
+            consistency_repair: false,
+            response_compaction_age: None,
+            payments_watch_interval: None,
+            payment_epoch: None,
+            request_max_duration: CONFIG_REQUEST_MAX_DURATION_DEFAULT,
+            challenge_max_bids: CONFIG_CHALLENGE_MAX_BIDS_DEFAULT,
+            journal_path: None,
+            reconciliation: false,
+            latency_weights: vec![],
+            proof_policies: vec![],
+            maintenance_windows: vec![],
+            rpc_slow_call_ms: OCEAN_CLIENT_SLOW_CALL_MS,
+            clock_drift_threshold: CONFIG_CLOCK_DRIFT_THRESHOLD_DEFAULT,
+            clientchain_stall_threshold: CONFIG_CLIENTCHAIN_STALL_THRESHOLD_DEFAULT,
+            watchdog_timeout: CONFIG_WATCHDOG_TIMEOUT_DEFAULT,
+            key_rotation: String::from("disabled"),
+            audit_signing_key: None,
             api: ApiConfig::default(),
             service: ServiceConfig::default(),
             clientchain: ClientChainConfig::default(),
             storage: StorageConfig::default(),
+            tenants: vec![],
+            challenge_timings: HashMap::new(),
+            addr_params: HashMap::new(),
+            monitor: MonitorConfig::default(),
+            funding: FundingConfig::default(),
+            refunds: RefundsConfig::default(),
+            outbound_proxy: OutboundProxyConfig::default(),
         }
     }
 }
@@ -204,6 +766,51 @@ impl Config {
         if let Ok(v) = env::var("CO_API_PASS") {
             let _ = conf_rs.set("api.pass", v)?;
         }
+        if let Ok(v) = env::var("CO_API_AUTH_PROVIDER") {
+            let _ = conf_rs.set("api.auth_provider", v)?;
+        }
+        if let Ok(v) = env::var("CO_API_AUTH_INTROSPECTION_URL") {
+            let _ = conf_rs.set("api.auth_introspection_url", v)?;
+        }
+        if let Ok(v) = env::var("CO_API_HASH_ORDER") {
+            let _ = conf_rs.set("api.hash_order", v)?;
+        }
+        if let Ok(v) = env::var("CO_API_THREADS") {
+            let _ = conf_rs.set("api.threads", v)?;
+        }
+        if let Ok(v) = env::var("CO_API_QUEUE") {
+            let _ = conf_rs.set("api.queue", v)?;
+        }
+        if let Ok(v) = env::var("CO_API_REQUEST_TIMEOUT") {
+            let _ = conf_rs.set("api.request_timeout", v)?;
+        }
+        if let Ok(v) = env::var("CO_API_CORS_ALLOW_ALL") {
+            let _ = conf_rs.set("api.cors_allow_all", v)?;
+        }
+        if let Ok(v) = env::var("CO_API_KEEP_ALIVE") {
+            let _ = conf_rs.set("api.keep_alive", v)?;
+        }
+        if let Ok(v) = env::var("CO_API_COMPRESSION") {
+            let _ = conf_rs.set("api.compression", v)?;
+        }
+        if let Ok(v) = env::var("CO_API_COMPRESSION_MIN_SIZE") {
+            let _ = conf_rs.set("api.compression_min_size", v)?;
+        }
+        if let Ok(v) = env::var("CO_API_MAX_RESPONSE_SIZE") {
+            let _ = conf_rs.set("api.max_response_size", v)?;
+        }
+        if let Ok(v) = env::var("CO_API_MAX_BATCH_SIZE") {
+            let _ = conf_rs.set("api.max_batch_size", v)?;
+        }
+        if let Ok(v) = env::var("CO_API_RATE_LIMIT_KEY") {
+            let _ = conf_rs.set("api.rate_limit_key", v)?;
+        }
+        if let Ok(v) = env::var("CO_API_RATE_LIMIT_IP") {
+            let _ = conf_rs.set("api.rate_limit_ip", v)?;
+        }
+        if let Ok(v) = env::var("CO_API_RATE_LIMIT_WINDOW") {
+            let _ = conf_rs.set("api.rate_limit_window", v)?;
+        }
 
         if let Ok(v) = env::var("CO_SERVICE_HOST") {
             let _ = conf_rs.set("service.host", v)?;
@@ -214,6 +821,9 @@ impl Config {
         if let Ok(v) = env::var("CO_SERVICE_PASS") {
             let _ = conf_rs.set("service.pass", v)?;
         }
+        if let Ok(v) = env::var("CO_SERVICE_CREDENTIALS_FILE") {
+            let _ = conf_rs.set("service.credentials_file", v)?;
+        }
 
         if let Ok(v) = env::var("CO_CLIENTCHAIN_HOST") {
             let _ = conf_rs.set("clientchain.host", v)?;
@@ -224,6 +834,9 @@ impl Config {
         if let Ok(v) = env::var("CO_CLIENTCHAIN_PASS") {
             let _ = conf_rs.set("clientchain.pass", v)?;
         }
+        if let Ok(v) = env::var("CO_CLIENTCHAIN_CREDENTIALS_FILE") {
+            let _ = conf_rs.set("clientchain.credentials_file", v)?;
+        }
         if let Ok(v) = env::var("CO_CLIENTCHAIN_ASSET") {
             let _ = conf_rs.set("clientchain.asset", v)?;
         }
@@ -248,6 +861,42 @@ impl Config {
         if let Ok(v) = env::var("CO_CLIENTCHAIN_PAYMENT_ADDR") {
             let _ = conf_rs.set("clientchain.payment_addr", v)?;
         }
+        if let Ok(v) = env::var("CO_CLIENTCHAIN_PAYMENT_ADDRESS_TYPE") {
+            let _ = conf_rs.set("clientchain.payment_address_type", v)?;
+        }
+        if let Ok(v) = env::var("CO_CLIENTCHAIN_CHAIN_CHALLENGES") {
+            let _ = conf_rs.set("clientchain.chain_challenges", v)?;
+        }
+        if let Ok(v) = env::var("CO_CLIENTCHAIN_CHALLENGE_PREFLIGHT") {
+            let _ = conf_rs.set("clientchain.challenge_preflight", v)?;
+        }
+        if let Ok(v) = env::var("CO_CLIENTCHAIN_CHALLENGE_WALLET_POLICY") {
+            let _ = conf_rs.set("clientchain.challenge_wallet_policy", v)?;
+        }
+        if let Ok(v) = env::var("CO_CLIENTCHAIN_PAYMENT_MODE") {
+            let _ = conf_rs.set("clientchain.payment_mode", v)?;
+        }
+        if let Ok(v) = env::var("CO_CLIENTCHAIN_PAYMENT_EXPORT_DIR") {
+            let _ = conf_rs.set("clientchain.payment_export_dir", v)?;
+        }
+        if let Ok(v) = env::var("CO_CLIENTCHAIN_PAYMENT_EXPORT_FORMAT") {
+            let _ = conf_rs.set("clientchain.payment_export_format", v)?;
+        }
+        if let Ok(v) = env::var("CO_CLIENTCHAIN_PARTIAL_PAYOUTS") {
+            let _ = conf_rs.set("clientchain.partial_payouts", v)?;
+        }
+        if let Ok(v) = env::var("CO_CLIENTCHAIN_PAYOUT_ORDER") {
+            let _ = conf_rs.set("clientchain.payout_order", v)?;
+        }
+        if let Ok(v) = env::var("CO_CLIENTCHAIN_CHALLENGE_PAYLOAD") {
+            let _ = conf_rs.set("clientchain.challenge_payload", v)?;
+        }
+        if let Ok(v) = env::var("CO_CLIENTCHAIN_CHALLENGE_AMOUNT_TAGS") {
+            let _ = conf_rs.set("clientchain.challenge_amount_tags", v)?;
+        }
+        if let Ok(v) = env::var("CO_CLIENTCHAIN_CHALLENGE_COMMIT_REVEAL") {
+            let _ = conf_rs.set("clientchain.challenge_commit_reveal", v)?;
+        }
 
         if let Ok(v) = env::var("CO_STORAGE_HOST") {
             let _ = conf_rs.set("storage.host", v)?;
@@ -261,6 +910,30 @@ impl Config {
         if let Ok(v) = env::var("CO_STORAGE_NAME") {
             let _ = conf_rs.set("storage.name", v)?;
         }
+        if let Ok(v) = env::var("CO_STORAGE_CONNECT_TIMEOUT") {
+            let _ = conf_rs.set("storage.connect_timeout", v)?;
+        }
+        if let Ok(v) = env::var("CO_STORAGE_READ_TIMEOUT") {
+            let _ = conf_rs.set("storage.read_timeout", v)?;
+        }
+        if let Ok(v) = env::var("CO_STORAGE_WRITE_TIMEOUT") {
+            let _ = conf_rs.set("storage.write_timeout", v)?;
+        }
+        if let Ok(v) = env::var("CO_STORAGE_OPERATION_TIMEOUT") {
+            let _ = conf_rs.set("storage.operation_timeout", v)?;
+        }
+        if let Ok(v) = env::var("CO_STORAGE_READ_URI") {
+            let _ = conf_rs.set("storage.read_uri", v)?;
+        }
+        if let Ok(v) = env::var("CO_STORAGE_MAX_READ_LAG") {
+            let _ = conf_rs.set("storage.max_read_lag", v)?;
+        }
+        if let Ok(v) = env::var("CO_STORAGE_ENCRYPTION_KEY") {
+            let _ = conf_rs.set("storage.encryption_key", v)?;
+        }
+        if let Ok(v) = env::var("CO_STORAGE_ENCRYPTION_KEYFILE") {
+            let _ = conf_rs.set("storage.encryption_keyfile", v)?;
+        }
 
         // Perform type checks
         let key = conf_rs.get_str("clientchain.asset_key")?;
@@ -290,7 +963,182 @@ impl Config {
                 "clientchain.payment_asset".into(),
             )));
         }
+        if conf_rs.get::<Vec<String>>("clientchain.fee_assets")?.len() == 0 {
+            return Err(Error::from(CError::InputError(
+                MissingArgument,
+                "clientchain.fee_assets".into(),
+            )));
+        }
+
+        let config: Config = conf_rs.try_into()?;
+        let _ = PayoutAddressType::from_str(&config.clientchain.payment_address_type)?;
+        let _ = ChallengePreflight::from_str(&config.clientchain.challenge_preflight)?;
+        let _ = ChallengeWalletPolicy::from_str(&config.clientchain.challenge_wallet_policy)?;
+        let _ = PaymentMode::from_str(&config.clientchain.payment_mode)?;
+        let _ = PaymentExportFormat::from_str(&config.clientchain.payment_export_format)?;
+        let _ = PayoutOrder::from_str(&config.clientchain.payout_order)?;
+        let _ = PayloadScheme::from_str(&config.clientchain.challenge_payload)?;
+        let _ = KeyRotationPolicy::from_str(&config.key_rotation)?;
+        for tenant in config.tenants.iter() {
+            if !check_hash_string(&tenant.genesis_hash) {
+                return Err(Error::from(CError::InputError(GenHash, tenant.genesis_hash.clone())));
+            }
+            if let Some(asset_key) = &tenant.asset_key {
+                if !check_privkey_string(asset_key) {
+                    return Err(Error::from(CError::InputError(PrivKey, asset_key.clone())));
+                }
+            }
+            if let Some(challenge_payload) = &tenant.challenge_payload {
+                let _ = PayloadScheme::from_str(challenge_payload)?;
+            }
+            if tenant.api_user.len() == 0 || tenant.api_pass.len() == 0 {
+                return Err(Error::from(CError::InputError(
+                    MissingArgument,
+                    format!("tenants.api_user/api_pass ({})", tenant.genesis_hash),
+                )));
+            }
+        }
+        for genesis_hash in config.challenge_timings.keys() {
+            if !check_hash_string(genesis_hash) {
+                return Err(Error::from(CError::InputError(GenHash, genesis_hash.clone())));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Get the challenge timings for a client chain genesis hash. Any timing
+    /// not overriden for the genesis hash defaults to the challenge duration
+    /// config, a verify window of 5 service chain blocks and a refresh delay of
+    /// half a service chain block
+    pub fn challenge_timing(&self, genesis_hash: &str) -> ChallengeTiming {
+        let overrides = self.challenge_timings.get(genesis_hash).cloned().unwrap_or_default();
+        ChallengeTiming {
+            challenge_duration: Duration::from_secs(overrides.challenge_duration.unwrap_or(self.challenge_duration)),
+            verify_duration: Duration::from_secs(overrides.verify_duration.unwrap_or(5 * self.block_time)),
+            refresh_delay: Duration::from_secs(overrides.refresh_delay.unwrap_or(self.block_time / 2)),
+        }
+    }
+
+    /// Get the tenant config for a client chain genesis hash, if any
+    pub fn tenant(&self, genesis_hash: &str) -> Option<&TenantConfig> {
+        self.tenants.iter().find(|tenant| tenant.genesis_hash == genesis_hash)
+    }
+
+    /// Get the clientchain config with any overrides of the tenant that
+    /// corresponds to the clientchain genesis hash applied
+    pub fn tenant_clientchain(&self) -> ClientChainConfig {
+        self.tenant_clientchain_for(&self.clientchain.genesis_hash)
+    }
+
+    /// Get the clientchain config of a client chain genesis hash, with the
+    /// overrides of the tenant of that genesis hash applied, if any
+    pub fn tenant_clientchain_for(&self, genesis_hash: &str) -> ClientChainConfig {
+        let mut clientchain = self.clientchain.clone();
+        clientchain.genesis_hash = genesis_hash.to_owned();
+        if let Some(tenant) = self.tenant(genesis_hash) {
+            tenant.apply(&mut clientchain);
+        }
+        clientchain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tenant_clientchain_test() {
+        let mut config = Config::default();
+        config.clientchain.genesis_hash =
+            String::from("1100000000000000000000000000000000000000000000000000000000000022");
+        config.clientchain.payment_asset = String::from("CBT");
+
+        // no tenants
+        let clientchain = config.tenant_clientchain();
+        assert_eq!("CHALLENGE", clientchain.asset);
+        assert_eq!("CBT", clientchain.payment_asset);
+        assert_eq!(vec!["CBT"], clientchain.fee_assets);
+        assert_eq!(None, clientchain.fee_percentage);
+
+        // tenant for another genesis hash
+        let mut tenant = TenantConfig {
+            genesis_hash: String::from("2200000000000000000000000000000000000000000000000000000000000011"),
+            api_user: String::from("user"),
+            api_pass: String::from("pass"),
+            asset: Some(String::from("CHALLENGE2")),
+            asset_key: None,
+            payment_asset: None,
+            fee_assets: Some(vec![String::from("CBT"), String::from("FEE")]),
+            fee_percentage: Some(50),
+            challenge_payload: None,
+            challenge_commit_reveal: Some(true),
+            api_rate_limit: None,
+        };
+        config.tenants.push(tenant.clone());
+        assert!(config.tenant(&config.clientchain.genesis_hash).is_none());
+        let clientchain = config.tenant_clientchain();
+        assert_eq!("CHALLENGE", clientchain.asset);
+        assert_eq!(vec!["CBT"], clientchain.fee_assets);
+        assert_eq!(None, clientchain.fee_percentage);
+        assert!(!clientchain.challenge_commit_reveal);
+
+        // overrides keyed by the tenant genesis hash
+        let clientchain = config.tenant_clientchain_for(&tenant.genesis_hash);
+        assert_eq!(tenant.genesis_hash, clientchain.genesis_hash);
+        assert_eq!("CHALLENGE2", clientchain.asset);
+        assert_eq!(vec!["CBT", "FEE"], clientchain.fee_assets);
+        assert_eq!(Some(50), clientchain.fee_percentage);
+        assert!(clientchain.challenge_commit_reveal);
+
+        // tenant for clientchain genesis hash
+        tenant.genesis_hash = config.clientchain.genesis_hash.clone();
+        config.tenants.push(tenant);
+        assert!(config.tenant(&config.clientchain.genesis_hash).is_some());
+        let clientchain = config.tenant_clientchain();
+        assert_eq!("CHALLENGE2", clientchain.asset);
+        assert_eq!("CBT", clientchain.payment_asset);
+        assert_eq!(vec!["CBT", "FEE"], clientchain.fee_assets);
+        assert_eq!(Some(50), clientchain.fee_percentage);
+        assert!(clientchain.challenge_commit_reveal);
+    }
+
+    #[test]
+    fn challenge_timing_test() {
+        let mut config = Config::default();
+        let genesis_hash = String::from("1100000000000000000000000000000000000000000000000000000000000022");
+
+        // no overrides
+        let timing = config.challenge_timing(&genesis_hash);
+        assert_eq!(Duration::from_secs(60), timing.challenge_duration);
+        assert_eq!(Duration::from_secs(300), timing.verify_duration);
+        assert_eq!(Duration::from_secs(30), timing.refresh_delay);
+
+        // overrides for another genesis hash
+        let _ = config.challenge_timings.insert(
+            String::from("2200000000000000000000000000000000000000000000000000000000000011"),
+            ChallengeTimingConfig {
+                challenge_duration: Some(10),
+                verify_duration: Some(20),
+                refresh_delay: Some(5),
+            },
+        );
+        assert_eq!(
+            Duration::from_secs(60),
+            config.challenge_timing(&genesis_hash).challenge_duration
+        );
 
-        Ok(conf_rs.try_into()?)
+        // partial overrides for genesis hash
+        let _ = config.challenge_timings.insert(
+            genesis_hash.clone(),
+            ChallengeTimingConfig {
+                challenge_duration: Some(10),
+                verify_duration: None,
+                refresh_delay: Some(5),
+            },
+        );
+        let timing = config.challenge_timing(&genesis_hash);
+        assert_eq!(Duration::from_secs(10), timing.challenge_duration);
+        assert_eq!(Duration::from_secs(300), timing.verify_duration);
+        assert_eq!(Duration::from_secs(5), timing.refresh_delay);
     }
 }
@@ -0,0 +1,925 @@
+//! Config check
+//!
+//! Validation of the cross-field constraints of the coordinator config, along
+//! with checks of the service and client chain nodes when reachable, reported
+//! in a human readable form with remediation hints
+
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use bitcoin::hashes::{hex::FromHex, sha256d};
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::PrivateKey;
+use ocean::{Address, AddressParams};
+use ocean_rpc::RpcApi;
+
+use crate::audit::AuditSigner;
+use crate::auth::auth_provider;
+use crate::config::{ClientChainConfig, Config};
+use crate::error::Error;
+use crate::funding::parse_outpoint;
+use crate::interfaces::bid::PayoutAddressType;
+use crate::interfaces::clientchain::{ChallengePreflight, ChallengeWalletPolicy};
+use crate::interfaces::request::FEE_FILTER_SCRIPT_TYPES;
+use crate::maintenance::MaintenanceWindows;
+use crate::payload::PayloadScheme;
+use crate::payments::{PaymentExportFormat, PaymentMode};
+use crate::proof_policy::{IpRange, ProofPolicies};
+use crate::util::addr_params::AddrParamsRegistry;
+use crate::util::checks::{check_hash_string, check_privkey_string};
+use crate::util::encryption::FieldCipher;
+use crate::util::hash_order::HashOrder;
+use crate::util::http::OutboundProxy;
+use crate::util::ocean::OceanClient;
+
+/// Outcome of a config check
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheckStatus {
+    /// Check passed
+    Ok,
+    /// Check passed but the config might not behave as intended
+    Warning,
+    /// Check failed and the coordinator should not be run with the config
+    Failure,
+}
+
+/// Result of a single config check with an optional remediation hint
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckResult {
+    /// Name of the config option or constraint checked
+    pub name: String,
+    /// Check outcome
+    pub status: CheckStatus,
+    /// Description of the outcome
+    pub message: String,
+    /// Remediation hint for warnings and failures
+    pub hint: Option<String>,
+}
+
+/// Report of all the checks performed on a config
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ConfigReport {
+    /// Results of the checks in the order performed
+    pub results: Vec<CheckResult>,
+}
+
+impl ConfigReport {
+    /// Add a passed check
+    fn ok(&mut self, name: &str, message: String) {
+        self.results.push(CheckResult {
+            name: name.to_owned(),
+            status: CheckStatus::Ok,
+            message,
+            hint: None,
+        });
+    }
+
+    /// Add a check that passed with a warning
+    fn warning(&mut self, name: &str, message: String, hint: &str) {
+        self.results.push(CheckResult {
+            name: name.to_owned(),
+            status: CheckStatus::Warning,
+            message,
+            hint: Some(hint.to_owned()),
+        });
+    }
+
+    /// Add a failed check
+    fn failure(&mut self, name: &str, message: String, hint: &str) {
+        self.results.push(CheckResult {
+            name: name.to_owned(),
+            status: CheckStatus::Failure,
+            message,
+            hint: Some(hint.to_owned()),
+        });
+    }
+
+    /// Check whether all checks passed, possibly with warnings
+    pub fn is_ok(&self) -> bool {
+        self.results.iter().all(|result| result.status != CheckStatus::Failure)
+    }
+
+    /// Get the results with a specific status
+    pub fn with_status(&self, status: CheckStatus) -> Vec<&CheckResult> {
+        self.results.iter().filter(|result| result.status == status).collect()
+    }
+}
+
+impl fmt::Display for ConfigReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for result in self.results.iter() {
+            let label = match result.status {
+                CheckStatus::Ok => " OK ",
+                CheckStatus::Warning => "WARN",
+                CheckStatus::Failure => "FAIL",
+            };
+            writeln!(f, "[{}] {}: {}", label, result.name, result.message)?;
+            if let Some(hint) = &result.hint {
+                writeln!(f, "       hint: {}", hint)?;
+            }
+        }
+        write!(
+            f,
+            "{} checks, {} warnings, {} failures",
+            self.results.len(),
+            self.with_status(CheckStatus::Warning).len(),
+            self.with_status(CheckStatus::Failure).len()
+        )
+    }
+}
+
+/// Derive the p2pkh address of a wif encoded private key for address params
+fn privkey_address(key: &str, addr_params: &'static AddressParams) -> Option<Address> {
+    PrivateKey::from_wif(key)
+        .ok()
+        .map(|privkey| Address::p2pkh(&privkey.public_key(&Secp256k1::new()), None, addr_params))
+}
+
+/// Check the clientchain genesis hash, asset key and payment config
+fn check_clientchain(config: &Config, registry: &AddrParamsRegistry, report: &mut ConfigReport) {
+    let clientchain = &config.clientchain;
+    if check_hash_string(&clientchain.genesis_hash) {
+        report.ok("clientchain.genesis_hash", clientchain.genesis_hash.clone());
+    } else {
+        report.failure(
+            "clientchain.genesis_hash",
+            format!("invalid genesis hash {}", clientchain.genesis_hash),
+            "set the 64 character hex block hash of block 0, as returned by getblockhash 0",
+        );
+    }
+
+    if check_privkey_string(&clientchain.asset_key) {
+        report.ok("clientchain.asset_key", "valid private key".to_owned());
+    } else {
+        report.failure(
+            "clientchain.asset_key",
+            "invalid private key".to_owned(),
+            "set the 52 character base58check private key holding the challenge asset, as returned by dumpprivkey",
+        );
+    }
+
+    let addr_params = registry.get(&clientchain.chain);
+    let payment_mode = PaymentMode::from_str(&clientchain.payment_mode);
+    if let Err(e) = &payment_mode {
+        report.failure(
+            "clientchain.payment_mode",
+            e.to_string(),
+            "set payment_mode to wallet or watch-only",
+        );
+    }
+    if let Ok(PaymentMode::WatchOnly) = payment_mode {
+        report.ok(
+            "clientchain.payment_mode",
+            format!("bid payments exported to {}", clientchain.payment_export_dir),
+        );
+        if clientchain.payment_key.is_some() {
+            report.warning(
+                "clientchain.payment_key",
+                "payment key not used in watch-only mode".to_owned(),
+                "remove payment_key so that it is not kept on the coordinator host",
+            );
+        }
+    } else {
+        match &clientchain.payment_addr {
+            None => report.warning(
+                "clientchain.payment_addr",
+                "payment address not set; bid payments are calculated but not paid".to_owned(),
+                "set payment_addr and payment_key to pay bids",
+            ),
+            Some(payment_addr) => match Address::from_str(payment_addr) {
+                Err(e) => report.failure(
+                    "clientchain.payment_addr",
+                    format!("invalid address {}: {}", payment_addr, e),
+                    "set an address of the client chain holding the payment asset",
+                ),
+                Ok(addr) => {
+                    if *addr.params != *addr_params {
+                        report.failure(
+                            "clientchain.payment_addr",
+                            format!(
+                                "address {} does not match the address params of chain {}",
+                                payment_addr, clientchain.chain
+                            ),
+                            "set clientchain.chain to the payment address chain or add its params under addr_params",
+                        );
+                    } else {
+                        report.ok("clientchain.payment_addr", payment_addr.clone());
+                    }
+                    match &clientchain.payment_key {
+                        None => report.warning(
+                            "clientchain.payment_key",
+                            "payment key not set".to_owned(),
+                            "payments only succeed if the payment address key is already in the client chain wallet",
+                        ),
+                        Some(key) if !check_privkey_string(key) => report.failure(
+                            "clientchain.payment_key",
+                            "invalid private key".to_owned(),
+                            "set the 52 character base58check private key of the payment address",
+                        ),
+                        Some(key) => match privkey_address(key, addr_params) {
+                            Some(key_addr) if key_addr.to_string() != *payment_addr => report.warning(
+                                "clientchain.payment_key",
+                                format!("key address {} differs from payment address", key_addr),
+                                "set the key of the payment address, unless the payment address is not p2pkh",
+                            ),
+                            _ => report.ok("clientchain.payment_key", "valid private key".to_owned()),
+                        },
+                    }
+                }
+            },
+        }
+    }
+    if let Err(e) = PaymentExportFormat::from_str(&clientchain.payment_export_format) {
+        report.failure(
+            "clientchain.payment_export_format",
+            e.to_string(),
+            "set payment_export_format to json or bip21",
+        );
+    }
+
+    if clientchain.payment_asset.len() == 0 {
+        report.failure(
+            "clientchain.payment_asset",
+            "payment asset not set".to_owned(),
+            "set the payment asset label or id, or ANY to pay with any asset",
+        );
+    }
+    if let Err(e) = PayoutAddressType::from_str(&clientchain.payment_address_type) {
+        report.failure(
+            "clientchain.payment_address_type",
+            e.to_string(),
+            "set payment_address_type to p2pkh, p2sh-p2wpkh or p2wpkh",
+        );
+    }
+    if let Err(e) = ChallengePreflight::from_str(&clientchain.challenge_preflight) {
+        report.failure(
+            "clientchain.challenge_preflight",
+            e.to_string(),
+            "set challenge_preflight to wait, replace or skip",
+        );
+    }
+    if let Err(e) = ChallengeWalletPolicy::from_str(&clientchain.challenge_wallet_policy) {
+        report.failure(
+            "clientchain.challenge_wallet_policy",
+            e.to_string(),
+            "set challenge_wallet_policy to round-robin or failover",
+        );
+    }
+    for wallet in clientchain.challenge_wallets.iter() {
+        if let Some(asset_key) = &wallet.asset_key {
+            if !check_privkey_string(asset_key) {
+                report.failure(
+                    &format!("clientchain.challenge_wallets.asset_key ({})", wallet.name),
+                    "invalid private key".to_owned(),
+                    "set the wif encoded private key of the challenge asset in the wallet",
+                );
+            }
+        }
+    }
+    if let Err(e) = PayloadScheme::from_str(&clientchain.challenge_payload) {
+        report.failure(
+            "clientchain.challenge_payload",
+            e.to_string(),
+            "set challenge_payload to none or clientchain-blockhash",
+        );
+    }
+    check_fee_filter(clientchain, addr_params, report);
+    if let Some(fee_percentage) = clientchain.fee_percentage {
+        if fee_percentage > 100 {
+            report.failure(
+                "clientchain.fee_percentage",
+                format!("fee percentage {} above 100", fee_percentage),
+                "set a percentage between 0 and 100",
+            );
+        }
+    }
+}
+
+/// Check that the fee filter addresses are client chain addresses and the
+/// excluded script types are known
+fn check_fee_filter(clientchain: &ClientChainConfig, addr_params: &AddressParams, report: &mut ConfigReport) {
+    let filter = clientchain.fee_filter();
+    if filter.is_empty() {
+        return;
+    }
+    for address in filter.exclude_addresses.iter().chain(filter.include_addresses.iter()) {
+        let valid = Address::from_str(address)
+            .map(|addr| *addr.params == *addr_params)
+            .unwrap_or(false);
+        if !valid {
+            report.failure(
+                "clientchain.fee_filter",
+                format!("invalid address {} for chain {}", address, clientchain.chain),
+                "set fee_addresses and fee_exclude_addresses to client chain addresses",
+            );
+            return;
+        }
+    }
+    for script_type in filter.exclude_script_types.iter() {
+        if !FEE_FILTER_SCRIPT_TYPES.contains(&script_type.as_str()) {
+            report.failure(
+                "clientchain.fee_filter",
+                format!("unknown script type {}", script_type),
+                &format!(
+                    "set fee_exclude_script_types to any of {}",
+                    FEE_FILTER_SCRIPT_TYPES.join(", ")
+                ),
+            );
+            return;
+        }
+    }
+    report.ok(
+        "clientchain.fee_filter",
+        format!(
+            "{} excluded addresses, {} excluded script types, {} fee addresses",
+            filter.exclude_addresses.len(),
+            filter.exclude_script_types.len(),
+            filter.include_addresses.len()
+        ),
+    );
+}
+
+/// Check that a cors origin is the null origin or an http(s) scheme and host
+/// with an optional port
+fn check_cors_origin(origin: &str) -> bool {
+    if origin == "null" {
+        return true;
+    }
+    let host = if origin.starts_with("https://") {
+        &origin[8..]
+    } else if origin.starts_with("http://") {
+        &origin[7..]
+    } else {
+        return false;
+    };
+    host.len() > 0 && !host.contains('/')
+}
+
+/// Check tenant and challenge timing overrides, the watchdog timeout against
+/// the challenge timings and the client chain stall threshold against the
+/// client chain block time
+fn check_overrides(config: &Config, report: &mut ConfigReport) {
+    let mut genesis_hashes = HashSet::new();
+    for tenant in config.tenants.iter() {
+        let name = format!("tenants.{}", tenant.genesis_hash);
+        if !check_hash_string(&tenant.genesis_hash) {
+            report.failure(
+                &name,
+                "invalid genesis hash".to_owned(),
+                "identify tenants by the 64 character hex genesis hash of their client chain",
+            );
+        } else if !genesis_hashes.insert(tenant.genesis_hash.clone()) {
+            report.failure(
+                &name,
+                "duplicate tenant".to_owned(),
+                "merge the tenant entries, each client chain can only have one tenant",
+            );
+        } else if tenant.api_user.len() == 0 || tenant.api_pass.len() == 0 {
+            report.failure(
+                &name,
+                "tenant api credentials not set".to_owned(),
+                "set api_user and api_pass for the tenant",
+            );
+        } else if tenant.api_user == config.api.user {
+            report.failure(
+                &name,
+                "tenant api user same as admin api user".to_owned(),
+                "set a tenant api user different to the admin api user",
+            );
+        } else {
+            report.ok(&name, "tenant config valid".to_owned());
+        }
+    }
+
+    let challenge_interval = config.block_time * config.challenge_frequency;
+    for (genesis_hash, timing) in config.challenge_timings.iter() {
+        let name = format!("challenge_timings.{}", genesis_hash);
+        if !check_hash_string(genesis_hash) {
+            report.failure(
+                &name,
+                "invalid genesis hash".to_owned(),
+                "key challenge timings by the 64 character hex genesis hash of the client chain",
+            );
+        } else if timing.challenge_duration.unwrap_or(config.challenge_duration) > challenge_interval {
+            report.warning(
+                &name,
+                "challenge duration longer than the challenge interval".to_owned(),
+                "reduce challenge_duration below block_time * challenge_frequency",
+            );
+        }
+    }
+    if config.challenge_duration > challenge_interval {
+        report.warning(
+            "challenge_duration",
+            format!(
+                "challenge duration {}s longer than the challenge interval {}s",
+                config.challenge_duration, challenge_interval
+            ),
+            "reduce challenge_duration below block_time * challenge_frequency",
+        );
+    }
+    // the response window is up to doubled by clock drift compensation
+    let loop_interval = config.block_time + 2 * config.challenge_duration + config.challenge_grace_period;
+    if config.watchdog_timeout > 0 && config.watchdog_timeout <= loop_interval {
+        report.warning(
+            "watchdog_timeout",
+            format!(
+                "watchdog timeout {}s not above the longest challenger loop interval {}s",
+                config.watchdog_timeout, loop_interval
+            ),
+            "increase watchdog_timeout above block_time + 2 * challenge_duration + challenge_grace_period",
+        );
+    }
+    // blocks are up to a few block times apart without the chain stalling
+    let stall_interval = 2 * config.clientchain.block_time;
+    if config.clientchain_stall_threshold > 0 && config.clientchain_stall_threshold <= stall_interval {
+        report.warning(
+            "clientchain_stall_threshold",
+            format!(
+                "client chain stall threshold {}s not above twice the client chain block time {}s",
+                config.clientchain_stall_threshold, stall_interval
+            ),
+            "increase clientchain_stall_threshold above 2 * clientchain.block_time",
+        );
+    }
+}
+
+/// Check the listener, payment epoch, latency weight, api and storage options
+fn check_options(config: &Config, report: &mut ConfigReport) {
+    if config.listener_verify_threads == 0 {
+        report.failure(
+            "listener_verify_threads",
+            "no verification threads".to_owned(),
+            "set at least 1 thread, the listener falls back to a single thread otherwise",
+        );
+    }
+    if config.listener_blacklist_strikes > 0 && config.listener_blacklist_cooldown == 0 {
+        report.warning(
+            "listener_blacklist_cooldown",
+            "blacklist cool-down of 0 seconds".to_owned(),
+            "set a positive cool-down or set listener_blacklist_strikes to 0 to disable blacklisting",
+        );
+    }
+    if config.payment_epoch == Some(0) {
+        report.failure(
+            "payment_epoch",
+            "payment epoch of 0 blocks".to_owned(),
+            "set a positive epoch length or unset payment_epoch to pay at the end of requests",
+        );
+    }
+    if config
+        .latency_weights
+        .windows(2)
+        .any(|buckets| buckets[0].max_ms >= buckets[1].max_ms)
+        || config.latency_weights.iter().any(|bucket| bucket.weight > 100)
+    {
+        report.failure(
+            "latency_weights",
+            "latency buckets out of order or weighted above 100%".to_owned(),
+            "order buckets by ascending max_ms with weights of at most 100",
+        );
+    }
+    if let Err(e) = ProofPolicies::from_config(&config.proof_policies) {
+        report.failure(
+            "proof_policies",
+            e.to_string(),
+            "set policy to allowed-ranges with ranges in CIDR notation or max-delay with a positive max_delay",
+        );
+    }
+    if let Err(e) = MaintenanceWindows::from_config(&config.maintenance_windows) {
+        report.failure(
+            "maintenance_windows",
+            e.to_string(),
+            "set schedule to a cron expression of five fields and a positive duration in seconds",
+        );
+    }
+    match auth_provider(&config.api) {
+        Ok(_) => report.ok("api.auth_provider", config.api.auth_provider.clone()),
+        Err(e) => report.failure(
+            "api.auth_provider",
+            e.to_string(),
+            "set auth_provider to basic, token with auth_tokens or oauth2 with auth_introspection_url",
+        ),
+    }
+    if let Err(e) = HashOrder::from_str(&config.api.hash_order) {
+        report.failure("api.hash_order", e.to_string(), "set hash_order to rpc or internal");
+    }
+    if config.api.threads == 0 {
+        report.failure(
+            "api.threads",
+            "no api threads".to_owned(),
+            "set at least 1 thread, otherwise no api calls are served",
+        );
+    }
+    if config.api.queue == 0 {
+        report.failure(
+            "api.queue",
+            "api queue of size 0".to_owned(),
+            "set a positive queue size, otherwise all api calls are rejected",
+        );
+    }
+    let rate_limited = config.api.rate_limit_key > 0
+        || config.api.rate_limit_ip > 0
+        || config
+            .tenants
+            .iter()
+            .any(|tenant| tenant.api_rate_limit.map_or(false, |limit| limit > 0));
+    if rate_limited && config.api.rate_limit_window == 0 {
+        report.failure(
+            "api.rate_limit_window",
+            "rate limit window of 0 seconds".to_owned(),
+            "set a positive window, otherwise api calls are never rate limited",
+        );
+    }
+    if let Some(proxy) = config
+        .api
+        .trusted_proxies
+        .iter()
+        .find(|proxy| IpRange::from_str(proxy).is_err())
+    {
+        report.failure(
+            "api.trusted_proxies",
+            format!("invalid proxy address {}", proxy),
+            "set proxy addresses in CIDR notation, e.g. 10.0.0.0/8",
+        );
+    }
+    if config.api.rate_limit_ip > 0 && config.api.trusted_proxies.is_empty() {
+        report.warning(
+            "api.rate_limit_ip",
+            "no trusted proxies to take client ips from".to_owned(),
+            "set trusted_proxies to the reverse proxies in front of the api, otherwise calls are not limited by ip",
+        );
+    }
+    if let Some(origin) = config.api.cors_origins.iter().find(|origin| !check_cors_origin(origin)) {
+        report.failure(
+            "api.cors_origins",
+            format!("invalid origin {}", origin),
+            "set origins as scheme://host[:port] without a path, e.g. https://explorer.example.com",
+        );
+    }
+    if config.api.cors_allow_all {
+        report.warning(
+            "api.cors_allow_all",
+            "api calls allowed from any origin".to_owned(),
+            "set cors_origins to the allowed origins instead, outside of development",
+        );
+    }
+    if config.funding.challenge_amount > 0.0 {
+        if let Some(utxo) = &config.funding.utxo {
+            if let Err(e) = parse_outpoint(utxo) {
+                report.failure(
+                    "funding.utxo",
+                    e.to_string(),
+                    "set utxo to the funding outpoint as txid:vout",
+                );
+            }
+        }
+        if config.funding.max_outputs == 0 {
+            report.warning(
+                "funding.max_outputs",
+                "max 0 outputs per split".to_owned(),
+                "set a positive max, otherwise the challenge wallet is never funded",
+            );
+        }
+        let challenge_sats = (config.funding.challenge_amount * 100_000_000.0).round() as u64;
+        if challenge_sats <= config.clientchain.challenge_amount_tags {
+            report.warning(
+                "clientchain.challenge_amount_tags",
+                format!(
+                    "challenge amount of {} satoshis does not cover the {} amount tags",
+                    challenge_sats, config.clientchain.challenge_amount_tags
+                ),
+                "set a challenge amount above the number of amount tags so that tagged challenges can spend it",
+            );
+        }
+    }
+    for (name, credentials) in vec![
+        ("service.credentials_file", config.service.rpc_credentials()),
+        ("clientchain.credentials_file", config.clientchain.rpc_credentials()),
+    ] {
+        if let Err(e) = credentials.resolve() {
+            report.failure(
+                name,
+                e.to_string(),
+                "set a readable file containing the rpc credentials as user:pass",
+            );
+        }
+    }
+    if let Some(read_uri) = &config.storage.read_uri {
+        if !read_uri.starts_with("mongodb://") {
+            report.failure(
+                "storage.read_uri",
+                format!("invalid read replica uri {}", read_uri),
+                "set read_uri to a mongodb:// uri of the read replica",
+            );
+        }
+    }
+    if config.storage.operation_timeout == 0 {
+        report.warning(
+            "storage.operation_timeout",
+            "operation timeout of 0 seconds".to_owned(),
+            "set a positive timeout, otherwise concurrent storage operations fail immediately",
+        );
+    }
+    if let Err(e) = FieldCipher::from_config(&config.storage) {
+        report.failure(
+            "storage.encryption_key",
+            e.to_string(),
+            "set a 64 character hex key, or a readable keyfile containing one",
+        );
+    }
+    if let Err(e) = OutboundProxy::from_config(&config.outbound_proxy) {
+        report.failure(
+            "outbound_proxy.url",
+            e.to_string(),
+            "set url to socks5://[user:pass@]host:port, or socks5h:// to resolve hostnames through the proxy",
+        );
+    }
+    if let Err(e) = AuditSigner::from_config(config) {
+        report.failure(
+            "audit_signing_key",
+            e.to_string(),
+            "set a 64 character hex secp256k1 key",
+        );
+    }
+}
+
+/// Check the config options that do not require connecting to any node
+pub fn check_config(config: &Config) -> ConfigReport {
+    let mut report = ConfigReport::default();
+    let registry = AddrParamsRegistry::from_config(&config.addr_params);
+    check_clientchain(config, &registry, &mut report);
+    check_overrides(config, &mut report);
+    check_options(config, &mut report);
+    report
+}
+
+/// Check the service and client chain nodes, if reachable. The client chain
+/// genesis hash is checked against the node and the challenge asset holdings
+/// of the wallet against the asset key
+pub fn check_nodes(config: &Config, report: &mut ConfigReport) {
+    let service = OceanClient::with_credentials(config.service.host.clone(), config.service.rpc_credentials());
+    match service.and_then(|client| client.get_block_count().map_err(Error::from)) {
+        Ok(height) => report.ok("service", format!("reachable at height {}", height)),
+        Err(e) => report.warning(
+            "service",
+            format!("unreachable: {}", e),
+            "check service host and rpc credentials",
+        ),
+    }
+
+    let clientchain = &config.clientchain;
+    let client = match OceanClient::with_credentials(clientchain.host.clone(), clientchain.rpc_credentials()) {
+        Ok(client) => client,
+        Err(e) => {
+            report.warning(
+                "clientchain",
+                format!("unreachable: {}", e),
+                "check clientchain host and rpc credentials",
+            );
+            return;
+        }
+    };
+    match client.get_block_hash(0) {
+        Ok(genesis_hash) => {
+            if sha256d::Hash::from_hex(&clientchain.genesis_hash).ok() == Some(genesis_hash) {
+                report.ok("clientchain", format!("reachable with genesis hash {}", genesis_hash));
+            } else {
+                report.failure(
+                    "clientchain",
+                    format!("node genesis hash {} differs from config", genesis_hash),
+                    "set clientchain.genesis_hash to the node genesis hash or connect to the intended chain",
+                );
+            }
+        }
+        Err(e) => {
+            report.warning(
+                "clientchain",
+                format!("unreachable: {}; skipping wallet checks", e),
+                "check clientchain host and rpc credentials",
+            );
+            return;
+        }
+    }
+
+    let asset_addr = privkey_address(
+        &clientchain.asset_key,
+        AddrParamsRegistry::from_config(&config.addr_params).get(&clientchain.chain),
+    );
+    match client.list_unspent(None, None, None, None, Some(&clientchain.asset)) {
+        Ok(unspent) => {
+            if unspent.len() == 0 {
+                report.failure(
+                    "clientchain.asset",
+                    format!("no unspent for challenge asset {} in the wallet", clientchain.asset),
+                    &match &asset_addr {
+                        Some(addr) => format!("fund address {} of asset_key with the challenge asset", addr),
+                        None => "import asset_key into the wallet and fund it with the challenge asset".to_owned(),
+                    },
+                );
+            } else if asset_addr.map_or(false, |addr| {
+                !unspent
+                    .iter()
+                    .any(|entry| entry.address.to_string() == addr.to_string())
+            }) {
+                report.warning(
+                    "clientchain.asset_key",
+                    "challenge asset is not held by the asset key address".to_owned(),
+                    "set asset_key to the key of the address holding the challenge asset",
+                );
+            } else {
+                report.ok(
+                    "clientchain.asset",
+                    format!("{} unspent for challenge asset {}", unspent.len(), clientchain.asset),
+                );
+            }
+        }
+        Err(e) => report.warning(
+            "clientchain.asset",
+            format!("failed listing wallet unspent: {}", e),
+            "check the client chain wallet is loaded",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::{
+        ChallengeTimingConfig, LatencyBucketConfig, MaintenanceWindowConfig, ProofPolicyConfig, TenantConfig,
+    };
+
+    /// Generate a config passing all the offline checks
+    fn gen_config() -> Config {
+        let mut config = Config::default();
+        config.clientchain.genesis_hash = "ff".repeat(32);
+        config.clientchain.asset_key = "cScSHCQp9AEwzZoucRpX9bMRkLCJ4LoQWBNFTZuD6tPX9qwNMWfQ".to_owned();
+        config.clientchain.payment_asset = "CBT".to_owned();
+        config.clientchain.chain = "elements".to_owned();
+        let addr = privkey_address(&config.clientchain.asset_key, &AddressParams::ELEMENTS).unwrap();
+        config.clientchain.payment_addr = Some(addr.to_string());
+        config
+    }
+
+    #[test]
+    fn check_config_test() {
+        let config = gen_config();
+        let report = check_config(&config);
+        assert!(report.is_ok(), "{}", report);
+        // payment key not set
+        assert_eq!(1, report.with_status(CheckStatus::Warning).len());
+
+        // payment key of other address
+        let mut config = gen_config();
+        config.clientchain.payment_key = Some("cN9spWsvaxA8taS7DFMxnk1yJD2gaF2PX1npuTpy3vuZFJdwavaw".to_owned());
+        let report = check_config(&config);
+        assert!(report.is_ok());
+        assert_eq!(
+            "clientchain.payment_key",
+            report.with_status(CheckStatus::Warning)[0].name
+        );
+        config.clientchain.payment_key = Some(config.clientchain.asset_key.clone());
+        assert_eq!(0, check_config(&config).with_status(CheckStatus::Warning).len());
+
+        // watch-only payments with payment key
+        let mut config = gen_config();
+        config.clientchain.payment_mode = "watch-only".to_owned();
+        config.clientchain.payment_key = Some(config.clientchain.asset_key.clone());
+        let report = check_config(&config);
+        assert!(report.is_ok());
+        assert_eq!(
+            "clientchain.payment_key",
+            report.with_status(CheckStatus::Warning)[0].name
+        );
+        config.clientchain.payment_key = None;
+        assert_eq!(0, check_config(&config).with_status(CheckStatus::Warning).len());
+
+        // challenge amount not covering the amount tags
+        let mut config = gen_config();
+        config.funding.challenge_amount = 0.0000001;
+        config.clientchain.challenge_amount_tags = 10;
+        let report = check_config(&config);
+        assert!(report.is_ok());
+        assert!(report
+            .with_status(CheckStatus::Warning)
+            .iter()
+            .any(|result| result.name == "clientchain.challenge_amount_tags"));
+        config.clientchain.challenge_amount_tags = 9;
+        assert!(!check_config(&config)
+            .with_status(CheckStatus::Warning)
+            .iter()
+            .any(|result| result.name == "clientchain.challenge_amount_tags"));
+
+        // invalid keys and genesis
+        let mut config = gen_config();
+        config.clientchain.genesis_hash = "ff".to_owned();
+        config.clientchain.asset_key = "key".to_owned();
+        let report = check_config(&config);
+        assert!(!report.is_ok());
+        let failures = report.with_status(CheckStatus::Failure);
+        assert_eq!(2, failures.len());
+        assert_eq!("clientchain.genesis_hash", failures[0].name);
+        assert_eq!("clientchain.asset_key", failures[1].name);
+        assert!(failures[0].hint.is_some());
+
+        // payment addr of other chain
+        let mut config = gen_config();
+        config.clientchain.chain = "ocean_main".to_owned();
+        let report = check_config(&config);
+        assert_eq!(
+            "clientchain.payment_addr",
+            report.with_status(CheckStatus::Failure)[0].name
+        );
+
+        // duplicate tenants and bad overrides
+        let mut config = gen_config();
+        let tenant = TenantConfig {
+            genesis_hash: "aa".repeat(32),
+            api_user: "tenant".to_owned(),
+            api_pass: "pass".to_owned(),
+            asset: None,
+            asset_key: None,
+            payment_asset: None,
+            fee_assets: None,
+            fee_percentage: None,
+            challenge_payload: None,
+            challenge_commit_reveal: None,
+            api_rate_limit: None,
+        };
+        config.tenants = vec![tenant.clone(), tenant];
+        let _ = config
+            .challenge_timings
+            .insert("bb".to_owned(), ChallengeTimingConfig::default());
+        config.listener_verify_threads = 0;
+        config.clientchain.payment_address_type = "p2tr".to_owned();
+        config.clientchain.challenge_preflight = "abandon".to_owned();
+        config.clientchain.challenge_wallet_policy = "random".to_owned();
+        config.clientchain.fee_exclude_script_types = vec!["burn".to_owned()];
+        config.clientchain.payment_mode = "external".to_owned();
+        config.payment_epoch = Some(0);
+        config.latency_weights = vec![
+            LatencyBucketConfig {
+                max_ms: 5000,
+                weight: 100,
+            },
+            LatencyBucketConfig {
+                max_ms: 1000,
+                weight: 50,
+            },
+        ];
+        config.proof_policies = vec![ProofPolicyConfig {
+            policy: "max-delay".to_owned(),
+            ranges: vec![],
+            max_delay: 0,
+        }];
+        config.maintenance_windows = vec![MaintenanceWindowConfig {
+            name: "weekly".to_owned(),
+            schedule: "0 3 * *".to_owned(),
+            duration: 3600,
+            pause_challenges: false,
+        }];
+        config.api.hash_order = "reversed".to_owned();
+        config.api.queue = 0;
+        config.api.trusted_proxies = vec!["10.0.0.0/33".to_owned()];
+        config.api.cors_origins = vec![
+            "https://explorer.example.com".to_owned(),
+            "explorer.example.com".to_owned(),
+        ];
+        config.storage.encryption_key = Some("abcd".to_owned());
+        config.outbound_proxy.url = Some("http://127.0.0.1:8080".to_owned());
+        config.audit_signing_key = Some("00".repeat(32));
+        let report = check_config(&config);
+        let failures: Vec<String> = report
+            .with_status(CheckStatus::Failure)
+            .iter()
+            .map(|result| result.name.clone())
+            .collect();
+        assert_eq!(
+            vec![
+                "clientchain.payment_mode".to_owned(),
+                "clientchain.payment_address_type".to_owned(),
+                "clientchain.challenge_preflight".to_owned(),
+                "clientchain.challenge_wallet_policy".to_owned(),
+                "clientchain.fee_filter".to_owned(),
+                format!("tenants.{}", "aa".repeat(32)),
+                "challenge_timings.bb".to_owned(),
+                "listener_verify_threads".to_owned(),
+                "payment_epoch".to_owned(),
+                "latency_weights".to_owned(),
+                "proof_policies".to_owned(),
+                "maintenance_windows".to_owned(),
+                "api.hash_order".to_owned(),
+                "api.queue".to_owned(),
+                "api.trusted_proxies".to_owned(),
+                "api.cors_origins".to_owned(),
+                "storage.encryption_key".to_owned(),
+                "outbound_proxy.url".to_owned(),
+                "audit_signing_key".to_owned(),
+            ],
+            failures
+        );
+        assert!(report.to_string().ends_with("19 failures"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Connectivity
+//!
+//! Degraded mode of the coordinator when the service or client chain rpc is
+//! unreachable. Challenging pauses with alerts while the api keeps serving
+//! reads from storage, and resumes once both chains are reachable again
+
+use std::sync::RwLock;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::interfaces::clientchain::ClientChain;
+use crate::interfaces::service::Service;
+use crate::util::http;
+
+/// Status of the coordinator while degraded by unreachable chains
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DegradedStatus {
+    /// Unix timestamp in seconds since which the coordinator is degraded
+    pub since: u64,
+    /// Error of the request that was interrupted by the unreachable chains
+    pub error: String,
+    /// Whether the service chain rpc was reachable on the latest check
+    pub service_reachable: bool,
+    /// Whether the client chain rpc was reachable on the latest check
+    pub clientchain_reachable: bool,
+    /// Number of connectivity checks since degraded
+    pub checks: u32,
+}
+
+/// Connectivity alert posted to the alert webhook when entering and leaving
+/// the degraded mode
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum ConnectivityAlert {
+    /// Chains unreachable and challenging paused
+    Degraded(DegradedStatus),
+    /// Chains reachable again and challenging resumed
+    Restored(DegradedStatus),
+}
+
+/// Check whether the service and client chain rpcs are reachable
+pub fn check_reachable<T: Service, K: ClientChain>(service: &T, clientchain: &K) -> (bool, bool) {
+    (service.get_blockheight().is_ok(), clientchain.get_blockheight().is_ok())
+}
+
+/// Post a connectivity alert to the webhook, if set. Webhook failures are
+/// logged only
+fn post_alert(webhook: Option<&str>, alert: &ConnectivityAlert) {
+    if let Some(webhook) = webhook {
+        if let Err(e) = http::post(
+            webhook,
+            "application/json",
+            None,
+            &serde_json::to_string(alert).unwrap(),
+        ) {
+            warn!("connectivity alert webhook failed: {}", e);
+        }
+    }
+}
+
+/// Run the degraded mode after a request failed with an error, if either chain
+/// is unreachable. The shared degraded status is set while waiting, checking
+/// connectivity every retry interval, and cleared once both chains are
+/// reachable. Returns false without waiting if both chains are reachable, in
+/// which case the error is not a connectivity failure
+pub fn run_degraded<T: Service, K: ClientChain>(
+    service: &T,
+    clientchain: &K,
+    error: &Error,
+    status: &RwLock<Option<DegradedStatus>>,
+    webhook: Option<&str>,
+    retry_interval: Duration,
+) -> bool {
+    let (service_reachable, clientchain_reachable) = check_reachable(service, clientchain);
+    if service_reachable && clientchain_reachable {
+        return false;
+    }
+    let mut degraded = DegradedStatus {
+        since: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        error: error.to_string(),
+        service_reachable,
+        clientchain_reachable,
+        checks: 1,
+    };
+    warn!(
+        "Chains unreachable (service: {}, clientchain: {}), challenges paused: {}",
+        service_reachable, clientchain_reachable, error
+    );
+    post_alert(webhook, &ConnectivityAlert::Degraded(degraded.clone()));
+    *status.write().unwrap() = Some(degraded.clone());
+
+    while !(degraded.service_reachable && degraded.clientchain_reachable) {
+        thread::sleep(retry_interval);
+        let (service_reachable, clientchain_reachable) = check_reachable(service, clientchain);
+        degraded.service_reachable = service_reachable;
+        degraded.clientchain_reachable = clientchain_reachable;
+        degraded.checks += 1;
+        *status.write().unwrap() = Some(degraded.clone());
+    }
+
+    info!("Chains reachable after {} checks, challenges resumed", degraded.checks);
+    post_alert(webhook, &ConnectivityAlert::Restored(degraded));
+    *status.write().unwrap() = None;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::CError;
+    use crate::interfaces::mocks::clientchain::MockClientChain;
+    use crate::interfaces::mocks::service::MockService;
+    use crate::util::testing::setup_logger;
+
+    #[test]
+    fn run_degraded_test() {
+        setup_logger();
+        let error = Error::from(CError::Generic("connection refused".to_owned()));
+        let status = RwLock::new(None);
+        let clientchain = MockClientChain::new();
+
+        // reachable chains are not a connectivity failure
+        let service = MockService::new();
+        assert_eq!((true, true), check_reachable(&service, &clientchain));
+        assert!(!run_degraded(
+            &service,
+            &clientchain,
+            &error,
+            &status,
+            None,
+            Duration::from_millis(1)
+        ));
+        assert_eq!(None, *status.read().unwrap());
+
+        let mut service = MockService::new();
+        service.return_err = true;
+        assert_eq!((false, true), check_reachable(&service, &clientchain));
+    }
+}
@@ -0,0 +1,280 @@
+//! Consistency
+//!
+//! Consistency checks between storage and the service and client chains
+
+use std::fmt;
+
+use bitcoin::hashes::sha256d;
+
+use crate::error::Result;
+use crate::interfaces::clientchain::ClientChain;
+use crate::interfaces::response::Response;
+use crate::interfaces::service::Service;
+use crate::interfaces::storage::Storage;
+
+/// Inconsistency between stored requests/responses and the service or client
+/// chain, identified by the request txid
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inconsistency {
+    /// Stored request heights differ from the active service chain request
+    RequestHeights(sha256d::Hash),
+    /// Stored request genesis differs from the active service chain request
+    RequestGenesis(sha256d::Hash),
+    /// Stored request starts after the current service chain height
+    RequestStartHeight(sha256d::Hash),
+    /// Stored request client chain start height is after the current client
+    /// chain height
+    ClientChainStartHeight(sha256d::Hash),
+    /// Stored response challenge is not found on the client chain
+    MissingChallenge(sha256d::Hash, sha256d::Hash),
+    /// Stored response has more responses for a bid than challenges issued
+    ResponseCount(sha256d::Hash, sha256d::Hash),
+    /// Stored response is missing challenges of the stored challenge records
+    ResponseRecords(sha256d::Hash, u32),
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Inconsistency::RequestHeights(ref txid) => {
+                write!(f, "request {} heights differ from service chain", txid)
+            }
+            Inconsistency::RequestGenesis(ref txid) => {
+                write!(f, "request {} genesis differs from service chain", txid)
+            }
+            Inconsistency::RequestStartHeight(ref txid) => {
+                write!(f, "request {} starts after service chain height", txid)
+            }
+            Inconsistency::ClientChainStartHeight(ref txid) => {
+                write!(f, "request {} starts after client chain height", txid)
+            }
+            Inconsistency::MissingChallenge(ref txid, ref challenge) => {
+                write!(f, "request {} challenge {} not found on client chain", txid, challenge)
+            }
+            Inconsistency::ResponseCount(ref txid, ref bid) => {
+                write!(f, "request {} bid {} responses exceed number of challenges", txid, bid)
+            }
+            Inconsistency::ResponseRecords(ref txid, num) => {
+                write!(f, "request {} response missing {} recorded challenges", txid, num)
+            }
+        }
+    }
+}
+
+/// Check stored requests for the client chain genesis hash against the service
+/// chain and stored responses against client chain challenge transactions.
+/// Stored responses are also checked against their per challenge records.
+/// Request heights, responses missing recorded challenges and response counts
+/// are repaired if the repair flag is set, while all other inconsistencies are
+/// only reported. All inconsistencies found are returned
+pub fn check_consistency<T: Service, K: ClientChain, D: Storage>(
+    service: &T,
+    clientchain: &K,
+    storage: &D,
+    genesis_hash: &sha256d::Hash,
+    repair: bool,
+) -> Result<Vec<Inconsistency>> {
+    info!("Running consistency check");
+    let mut inconsistencies = vec![];
+    let service_height = service.get_blockheight()?;
+    let client_height = clientchain.get_blockheight()?;
+
+    // check the stored version of the active service chain request
+    if let Some(active) = service.get_request(genesis_hash)? {
+        if let Some(mut stored) = storage.get_request(active.txid)? {
+            if stored.genesis_blockhash != active.genesis_blockhash {
+                inconsistencies.push(Inconsistency::RequestGenesis(stored.txid));
+            }
+            if stored.start_blockheight != active.start_blockheight || stored.end_blockheight != active.end_blockheight
+            {
+                inconsistencies.push(Inconsistency::RequestHeights(stored.txid));
+                if repair {
+                    stored.start_blockheight = active.start_blockheight;
+                    stored.end_blockheight = active.end_blockheight;
+                    storage.update_request(&stored)?;
+                    info!("Request {} heights repaired", stored.txid);
+                }
+            }
+        }
+    }
+
+    for request in storage.get_requests(None, Some(*genesis_hash), None, None)? {
+        if request.start_blockheight as u64 > service_height {
+            inconsistencies.push(Inconsistency::RequestStartHeight(request.txid));
+        }
+        if request.start_blockheight_clientchain > client_height {
+            inconsistencies.push(Inconsistency::ClientChainStartHeight(request.txid));
+        }
+
+        let records = storage.get_challenge_records(request.txid)?;
+        if !records.is_empty() {
+            let mut response = storage.get_response(request.txid)?.unwrap_or(Response::new());
+            let applied = response.apply_records(&records);
+            if applied > 0 {
+                inconsistencies.push(Inconsistency::ResponseRecords(request.txid, applied));
+                if repair {
+                    storage.save_response(request.txid, &response)?;
+                    info!("Request {} response rebuilt from challenge records", request.txid);
+                }
+            }
+        }
+
+        if let Some(mut response) = storage.get_response(request.txid)? {
+            for challenge in response.challenges.iter() {
+                if !clientchain.verify_challenge(challenge)? {
+                    inconsistencies.push(Inconsistency::MissingChallenge(request.txid, *challenge));
+                }
+            }
+
+            let mut repaired = false;
+            for (bid, count) in response.bid_responses.iter_mut() {
+                if *count > response.num_challenges {
+                    inconsistencies.push(Inconsistency::ResponseCount(request.txid, *bid));
+                    if repair {
+                        *count = response.num_challenges;
+                        repaired = true;
+                    }
+                }
+            }
+            if repaired {
+                storage.save_response(request.txid, &response)?;
+                info!("Request {} response counts repaired", request.txid);
+            }
+        }
+    }
+
+    for inconsistency in inconsistencies.iter() {
+        warn!("Inconsistency found: {}", inconsistency);
+    }
+    info!("Consistency check found {} inconsistencies", inconsistencies.len());
+    Ok(inconsistencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::interfaces::mocks::clientchain::MockClientChain;
+    use crate::interfaces::mocks::service::MockService;
+    use crate::interfaces::mocks::storage::MockStorage;
+    use crate::interfaces::response::ChallengeRecord;
+    use crate::util::testing::{gen_challenge_state, gen_dummy_hash, setup_logger};
+
+    #[test]
+    fn check_consistency_test() {
+        setup_logger();
+        let clientchain = MockClientChain::new();
+        let service = MockService::new();
+        let storage = MockStorage::new();
+        let genesis_hash = gen_dummy_hash(0);
+        let _ = service.height.replace(10);
+        let _ = clientchain.height.replace(10);
+
+        // nothing stored
+        let res = check_consistency(&service, &clientchain, &storage, &genesis_hash, false).unwrap();
+        assert_eq!(0, res.len());
+
+        // consistent active request stored
+        let active = service.get_request(&genesis_hash).unwrap().unwrap();
+        let mut state = gen_challenge_state(&active.txid);
+        state.request = active.clone();
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let res = check_consistency(&service, &clientchain, &storage, &genesis_hash, false).unwrap();
+        assert_eq!(0, res.len());
+
+        // stored request heights differ and start after chain heights
+        state.request.start_blockheight = 20;
+        state.request.start_blockheight_clientchain = 20;
+        storage.update_request(&state.request).unwrap();
+        let res = check_consistency(&service, &clientchain, &storage, &genesis_hash, false).unwrap();
+        assert_eq!(
+            vec![
+                Inconsistency::RequestHeights(active.txid),
+                Inconsistency::RequestStartHeight(active.txid),
+                Inconsistency::ClientChainStartHeight(active.txid)
+            ],
+            res
+        );
+        assert_eq!(20, storage.get_request(active.txid).unwrap().unwrap().start_blockheight);
+
+        // repair heights
+        let res = check_consistency(&service, &clientchain, &storage, &genesis_hash, true).unwrap();
+        assert_eq!(3, res.len());
+        assert_eq!(
+            active.start_blockheight,
+            storage.get_request(active.txid).unwrap().unwrap().start_blockheight
+        );
+        let res = check_consistency(&service, &clientchain, &storage, &genesis_hash, false).unwrap();
+        assert_eq!(vec![Inconsistency::ClientChainStartHeight(active.txid)], res);
+        let _ = clientchain.height.replace(20);
+
+        // response counts exceeding number of challenges
+        let bid = gen_dummy_hash(5);
+        let mut response = Response::new();
+        response.num_challenges = 1;
+        let _ = response.bid_responses.insert(bid, 3);
+        response.challenges = vec![gen_dummy_hash(6)];
+        storage.save_response(active.txid, &response).unwrap();
+        let res = check_consistency(&service, &clientchain, &storage, &genesis_hash, false).unwrap();
+        assert_eq!(vec![Inconsistency::ResponseCount(active.txid, bid)], res);
+        let res = check_consistency(&service, &clientchain, &storage, &genesis_hash, true).unwrap();
+        assert_eq!(1, res.len());
+        assert_eq!(
+            Some(&1),
+            storage
+                .get_response(active.txid)
+                .unwrap()
+                .unwrap()
+                .bid_responses
+                .get(&bid)
+        );
+        let res = check_consistency(&service, &clientchain, &storage, &genesis_hash, true).unwrap();
+        assert_eq!(0, res.len());
+
+        // response missing recorded challenges
+        let responses = vec![bid].into_iter().collect();
+        storage
+            .save_challenge_record(active.txid, &ChallengeRecord::new(gen_dummy_hash(6), &responses))
+            .unwrap();
+        storage
+            .save_challenge_record(active.txid, &ChallengeRecord::new(gen_dummy_hash(7), &responses))
+            .unwrap();
+        let res = check_consistency(&service, &clientchain, &storage, &genesis_hash, false).unwrap();
+        assert_eq!(vec![Inconsistency::ResponseRecords(active.txid, 1)], res);
+        let res = check_consistency(&service, &clientchain, &storage, &genesis_hash, true).unwrap();
+        assert_eq!(1, res.len());
+        let response = storage.get_response(active.txid).unwrap().unwrap();
+        assert_eq!(2, response.num_challenges);
+        assert_eq!(Some(&2), response.bid_responses.get(&bid));
+        let res = check_consistency(&service, &clientchain, &storage, &genesis_hash, false).unwrap();
+        assert_eq!(0, res.len());
+    }
+
+    #[test]
+    fn check_consistency_missing_challenge_test() {
+        setup_logger();
+        let mut clientchain = MockClientChain::new();
+        let service = MockService::new();
+        let storage = MockStorage::new();
+        let genesis_hash = gen_dummy_hash(0);
+        let _ = service.height.replace(10);
+
+        let state = gen_challenge_state(&gen_dummy_hash(1));
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let mut response = Response::new();
+        response.num_challenges = 1;
+        response.challenges = vec![gen_dummy_hash(6)];
+        storage.save_response(state.request.txid, &response).unwrap();
+
+        clientchain.return_false = true;
+        let res = check_consistency(&service, &clientchain, &storage, &genesis_hash, true).unwrap();
+        assert_eq!(
+            vec![Inconsistency::MissingChallenge(state.request.txid, gen_dummy_hash(6))],
+            res
+        );
+    }
+}
@@ -2,96 +2,696 @@
 //!
 //! Coordinator entry point for spawning all components
 
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::mem;
+use std::result;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
 use std::sync::{Arc, RwLock};
 use std::{thread, time};
 
 use bitcoin::hashes::{hex::FromHex, sha256d};
 
-use crate::challenger::{ChallengeResponse, ChallengeState};
+use crate::api::{ApiContext, CoordinatorInfo};
+use crate::audit::AuditSigner;
+use crate::blacklist::Blacklist;
+use crate::challenger::{ChallengeRequestSettings, ChallengeResponse, ChallengeState, ChallengerContext};
+use crate::clock::Clock;
 use crate::config::Config;
-use crate::error::Result;
+use crate::connectivity::DegradedStatus;
+use crate::dead_letter::DeadLetters;
+use crate::error::{skip_transient, CError, Error, Result};
+use crate::funding::Funding;
 use crate::interfaces::clientchain::{ClientChain, RpcClientChain};
 use crate::interfaces::service::{RpcService, Service};
-use crate::interfaces::storage::{MongoStorage, Storage};
+use crate::interfaces::storage::{MongoStorage, ReplicaStorage, Storage};
+use crate::journal::Journal;
+use crate::maintenance::MaintenanceWindows;
+use crate::payload::PayloadScheme;
+use crate::proof_policy::ProofPolicies;
+use crate::registry::ChallengeRegistry;
+use crate::rotation::KeyRotationPolicy;
+use crate::util::addr_params::AddrParamsRegistry;
+use crate::util::logger::RequestLogContext;
+use crate::util::ocean::rpc_stats;
+use crate::watchdog::{Heartbeat, ResponseRelay, Stall};
+
+/// Interval in milliseconds of checking the stopped flag while sleeping
+const STOP_CHECK_MS: u64 = 100;
+
+/// Events of the coordinator lifecycle reported to the event callbacks of
+/// applications embedding the coordinator
+#[derive(Debug, Clone, PartialEq)]
+pub enum CoordinatorEvent {
+    /// Coordinator components started
+    Started,
+    /// Challenging of a service request started
+    RequestStarted(sha256d::Hash),
+    /// Service request finished and sent for payment
+    RequestFinished(sha256d::Hash),
+    /// Service request failed with an error
+    RequestFailed(String),
+    /// Challenging resumed after the chains were unreachable
+    ChallengesResumed,
+    /// Challenger loop restarted after stalling, with the stall diagnostics
+    ChallengerRestarted(Stall),
+    /// Coordinator stopped, with the error that stopped it if any
+    Stopped(Option<String>),
+}
+
+/// Callback invoked on each coordinator event
+pub type EventCallback = Box<dyn Fn(&CoordinatorEvent) + Send + Sync>;
+
+/// Event callbacks of the coordinator
+pub struct CoordinatorEvents {
+    /// Callbacks invoked in the order they were added
+    callbacks: Vec<EventCallback>,
+}
+
+impl CoordinatorEvents {
+    /// Return new CoordinatorEvents instance without callbacks
+    pub fn new() -> CoordinatorEvents {
+        CoordinatorEvents { callbacks: vec![] }
+    }
+
+    /// Add a callback invoked on each event
+    pub fn add(&mut self, callback: EventCallback) {
+        self.callbacks.push(callback);
+    }
+
+    /// Invoke the callbacks with an event
+    pub fn emit(&self, event: CoordinatorEvent) {
+        for callback in self.callbacks.iter() {
+            callback(&event);
+        }
+    }
+}
+
+/// Builder of a coordinator that can be embedded in other applications with
+/// custom implementations of the service chain, client chain and storage
+/// interfaces. Api reads are served from the api storage, which defaults to
+/// the storage. Payments, the balance monitor, bid refunds and challenge
+/// funding connect to the chains via the rpc config regardless, and are run
+/// unless disabled in the config; payments can also be disabled in the builder
+pub struct CoordinatorBuilder<T, K, D, A = D> {
+    /// Coordinator config
+    config: Config,
+    /// Service chain interface
+    service: Arc<T>,
+    /// Client chain interface
+    clientchain: Arc<K>,
+    /// Storage interface
+    storage: Arc<D>,
+    /// Storage interface that api reads are served from
+    api_storage: Arc<A>,
+    /// Flag to run bid payments
+    payments: bool,
+    /// Event callbacks
+    events: CoordinatorEvents,
+}
+
+impl CoordinatorBuilder<RpcService, RpcClientChain, MongoStorage, ReplicaStorage> {
+    /// Return a builder for the rpc service and client chains and the mongo
+    /// storage of the config, with api reads served from the read replica if
+    /// configured
+    pub fn from_config(config: Config) -> Result<Self> {
+        // clientchain config with any overrides of the corresponding tenant
+        let clientchain_config = config.tenant_clientchain();
+        let service = Arc::new(RpcService::new(&config.service)?);
+        let clientchain = Arc::new(RpcClientChain::new(&clientchain_config)?);
+        let storage = Arc::new(MongoStorage::new(config.storage.clone())?);
+        let api_storage = Arc::new(ReplicaStorage::new(storage.clone(), &config.storage)?);
+        Ok(CoordinatorBuilder {
+            config,
+            service,
+            clientchain,
+            storage,
+            api_storage,
+            payments: true,
+            events: CoordinatorEvents::new(),
+        })
+    }
+}
+
+impl<T, K, D> CoordinatorBuilder<T, K, D, D> {
+    /// Return a builder for custom service chain, client chain and storage
+    /// implementations
+    pub fn new(config: Config, service: Arc<T>, clientchain: Arc<K>, storage: Arc<D>) -> Self {
+        CoordinatorBuilder {
+            config,
+            service,
+            clientchain,
+            api_storage: storage.clone(),
+            storage,
+            payments: true,
+            events: CoordinatorEvents::new(),
+        }
+    }
+}
+
+impl<T, K, D, A> CoordinatorBuilder<T, K, D, A>
+where
+    T: Service + Send + Sync + 'static,
+    K: ClientChain + Send + Sync + 'static,
+    D: Storage + Send + Sync + 'static,
+    A: Storage + Send + Sync + 'static,
+{
+    /// Serve api reads from a separate storage
+    pub fn api_storage<B>(self, api_storage: Arc<B>) -> CoordinatorBuilder<T, K, D, B> {
+        CoordinatorBuilder {
+            config: self.config,
+            service: self.service,
+            clientchain: self.clientchain,
+            storage: self.storage,
+            api_storage,
+            payments: self.payments,
+            events: self.events,
+        }
+    }
+
+    /// Set whether bid payments are run
+    pub fn payments(mut self, payments: bool) -> Self {
+        self.payments = payments;
+        self
+    }
+
+    /// Add a callback invoked on each coordinator event. Callbacks run on the
+    /// coordinator thread and should return promptly
+    pub fn on_event<F: Fn(&CoordinatorEvent) + Send + Sync + 'static>(mut self, callback: F) -> Self {
+        self.events.add(Box::new(callback));
+        self
+    }
+
+    /// Start the coordinator in a separate thread, returning a handle to stop
+    /// it
+    pub fn start(self) -> CoordinatorHandle {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = stopped.clone();
+        let thread = thread::spawn(move || self.run_until(&thread_stopped).map_err(|e| e.to_string()));
+        CoordinatorHandle { stopped, thread }
+    }
+
+    /// Run the coordinator in the current thread until it fails
+    pub fn run(self) -> Result<()> {
+        self.run_until(&AtomicBool::new(false))
+    }
+
+    /// Run the coordinator until it fails or the stopped flag is set, emitting
+    /// the stopped event with the error if any
+    fn run_until(mut self, stopped: &AtomicBool) -> Result<()> {
+        // events are shared with the components and emit the stopped event last
+        let events = Arc::new(mem::replace(&mut self.events, CoordinatorEvents::new()));
+        let res = run_components(self, events.clone(), stopped);
+        events.emit(CoordinatorEvent::Stopped(res.as_ref().err().map(|e| e.to_string())));
+        res
+    }
+}
+
+/// Handle of a coordinator started in a separate thread
+pub struct CoordinatorHandle {
+    /// Flag stopping the coordinator
+    stopped: Arc<AtomicBool>,
+    /// Coordinator thread, returning the error that stopped it if any
+    thread: thread::JoinHandle<result::Result<(), String>>,
+}
+
+impl CoordinatorHandle {
+    /// Stop the coordinator and wait for its components to shut down. The
+    /// current service request is left unfinished at its next challenge
+    /// refresh and resumed on the next start; while the chains are unreachable
+    /// the coordinator stops once they are reachable again
+    pub fn stop(self) -> Result<()> {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.wait()
+    }
+
+    /// Wait for the coordinator to stop, returning the error that stopped it
+    pub fn wait(self) -> Result<()> {
+        match self.thread.join() {
+            Ok(res) => res.map_err(|e| Error::from(CError::Generic(e))),
+            Err(_) => Err(Error::from(CError::Generic("coordinator thread panicked".to_owned()))),
+        }
+    }
+}
+
+/// Sleep for a duration unless the stopped flag is set in the meantime
+fn sleep_until_stopped(duration: time::Duration, stopped: &AtomicBool) {
+    let until = time::Instant::now() + duration;
+    while !stopped.load(Ordering::SeqCst) {
+        let now = time::Instant::now();
+        if now >= until {
+            return;
+        }
+        thread::sleep((until - now).min(time::Duration::from_millis(STOP_CHECK_MS)));
+    }
+}
 
 /// Run coordinator main method
 pub fn run(config: Config) -> Result<()> {
     info!("Running coordinator!");
+    CoordinatorBuilder::from_config(config)?.run()
+}
 
-    let service = RpcService::new(&config.service)?;
-    let clientchain = RpcClientChain::new(&config.clientchain)?;
-    let storage = Arc::new(MongoStorage::new(config.storage.clone())?);
-    let genesis_hash = sha256d::Hash::from_hex(&config.clientchain.genesis_hash)?;
+/// Spawn all coordinator components of the builder and run challenge requests
+/// until a request fails or the stopped flag is set, shutting the components
+/// down. Events are emitted to the given callbacks instead of those of the
+/// builder
+fn run_components<T, K, D, A>(
+    builder: CoordinatorBuilder<T, K, D, A>,
+    events: Arc<CoordinatorEvents>,
+    stopped: &AtomicBool,
+) -> Result<()>
+where
+    T: Service + Send + Sync + 'static,
+    K: ClientChain + Send + Sync + 'static,
+    D: Storage + Send + Sync + 'static,
+    A: Storage + Send + Sync + 'static,
+{
+    let CoordinatorBuilder {
+        config,
+        service,
+        clientchain,
+        storage,
+        api_storage,
+        payments,
+        ..
+    } = builder;
+    let config = Arc::new(config);
+    // clientchain config with any overrides of the corresponding tenant
+    let clientchain_config = config.tenant_clientchain();
+    // slow rpc call threshold of all ocean clients
+    rpc_stats().set_slow_call_ms(config.rpc_slow_call_ms);
+    // proxy of webhook and token introspection requests, if configured
+    ::util::http::init_outbound_proxy(&config.outbound_proxy)?;
 
-    let api_handler = ::api::run_api_server(&config.api, storage.clone());
-    let (req_send, req_recv): (Sender<sha256d::Hash>, Receiver<sha256d::Hash>) = channel();
-    let mut payments_handler = ::payments::run_payments(config.clientchain.clone(), storage.clone(), req_recv)?;
+    // challenge wallet funding at the start of each request, if configured
+    let funding = Funding::from_config(&config.funding, &clientchain_config)?;
+    let genesis_hash = sha256d::Hash::from_hex(&clientchain_config.genesis_hash)?;
+    // journal of coordinator decisions shared by all components
+    let journal = Arc::new(Journal::from_path(&config.journal_path)?);
+    // coordinator version and features, logged on startup and served by the api
+    let info = CoordinatorInfo::from_config(&config);
+    info!("{}", serde_json::to_string_pretty(&info).unwrap());
+
+    // check stored data against the service and client chains before resuming
+    let _ = ::consistency::check_consistency(
+        service.as_ref(),
+        clientchain.as_ref(),
+        storage.as_ref(),
+        &genesis_hash,
+        config.consistency_repair,
+    )?;
 
-    // create a challenge state mutex to share between challenger and listener.
-    // initially None
+    // create a challenge state mutex to share between challenger, listener and
+    // api. initially None
     let shared_challenge = Arc::new(RwLock::new(None));
-    // and a channel for sending responses from listener to challenger
+    // and a registry of the challenge states of the requests being challenged
+    // that the listener routes challenge proofs by
+    let challenge_registry = Arc::new(ChallengeRegistry::new());
+    // and a channel for sending responses from listener and api to challenger
     let (verify_tx, verify_rx): (Sender<ChallengeResponse>, Receiver<ChallengeResponse>) = channel();
+    // wallet balance status shared between balance monitor and api
+    let wallet_status = Arc::new(RwLock::new(None));
+    // flag set via the api or for maintenance windows to pause issuing
+    // challenges
+    let challenges_paused = Arc::new(AtomicBool::new(false));
+    // maintenance windows deferring payments and compaction
+    let maintenance = Arc::new(MaintenanceWindows::from_config(&config.maintenance_windows)?);
+    // degraded status while chains are unreachable, shared with the api
+    let degraded_status = Arc::new(RwLock::new(None));
+    // blacklist of bids sending invalid proofs shared between listener and api
+    let blacklist = Arc::new(Blacklist::with_storage(
+        config.listener_blacklist_strikes,
+        config.listener_blacklist_cooldown,
+        storage.clone(),
+    )?);
+    // acceptance policies that the listener checks challenge proofs against
+    let proof_policies = Arc::new(ProofPolicies::from_config(&config.proof_policies)?);
+    // capture of the listener submissions that could not be parsed
+    let dead_letters = Arc::new(if config.listener_dead_letters {
+        DeadLetters::new(storage.clone(), config.listener_dead_letter_payload as usize)
+    } else {
+        DeadLetters::disabled()
+    });
+
+    let api_handler = ::api::run_api_server(
+        &config.api,
+        &config.tenants,
+        ApiContext {
+            storage: api_storage,
+            clientchain: clientchain.clone(),
+            service: service.clone(),
+            challenge_frequency: config.challenge_frequency,
+            block_time: config.block_time,
+            payment_epoch: config.payment_epoch,
+            fee_percentage: clientchain_config.fee_percentage,
+            challenge: shared_challenge.clone(),
+            challenge_resp: verify_tx.clone(),
+            wallet_status: wallet_status.clone(),
+            challenges_paused: challenges_paused.clone(),
+            degraded_status: degraded_status.clone(),
+            journal: journal.clone(),
+            journal_path: config.journal_path.clone(),
+            blacklist: blacklist.clone(),
+            key_rotation: KeyRotationPolicy::from_str(&config.key_rotation)?,
+            audit_signer: AuditSigner::from_config(&config)?,
+            info,
+        },
+    )?;
+    let (req_send, req_recv): (Sender<sha256d::Hash>, Receiver<sha256d::Hash>) = channel();
+    let mut payments_handler = if payments {
+        Some(::payments::run_payments(
+            clientchain_config.clone(),
+            storage.clone(),
+            req_recv,
+            ::payments::PaymentSettings {
+                compaction_age: config.response_compaction_age,
+                watch_interval: config.payments_watch_interval,
+                latency_weights: config.latency_weights.clone(),
+            },
+            &AddrParamsRegistry::from_config(&config.addr_params),
+            journal.clone(),
+            maintenance.clone(),
+        )?)
+    } else {
+        None
+    };
+
+    let monitor_handle = ::monitor::run_monitor(
+        config.monitor.clone(),
+        &clientchain_config,
+        &config.service,
+        config.challenge_frequency,
+        storage.clone(),
+        shared_challenge.clone(),
+        wallet_status,
+    )?;
+
+    let refunds_handle =
+        ::refunds::run_refunds(config.refunds.clone(), &config.service, storage.clone(), genesis_hash)?;
+
+    let maintenance_handle = ::maintenance::run_maintenance(maintenance, challenges_paused.clone(), journal.clone());
+
     // start listener along with a oneshot channel to send shutdown message
-    let listener_handle = ::listener::run_listener(&config.listener_host, shared_challenge.clone(), verify_tx);
+    let listener_handle = ::listener::run_listener(
+        &config.listener_host,
+        challenge_registry.clone(),
+        verify_tx,
+        config.listener_verify_threads as usize,
+        config.listener_verify_queue as usize,
+        config.listener_late_queue as usize,
+        ::listener::ListenerContext {
+            journal: journal.clone(),
+            blacklist,
+            policies: proof_policies,
+            dead_letters,
+            strict_fields: config.listener_strict_proof_fields,
+        },
+    );
 
-    // This loop runs continuously fetching and running challenge requests,
-    // generating challenge responses and fails on any errors that occur
+    events.emit(CoordinatorEvent::Started);
+
+    // The challenger loop runs in a separate thread with its own stopped flag
+    // and heartbeat. If the watchdog finds the loop stalled the loop is
+    // abandoned, leaving its request unfinished, and replaced by a new loop
+    // that resumes the request, with challenge responses relayed to the loop
+    // currently running
+    let relay = ResponseRelay::new(verify_rx);
+    let funding = Arc::new(funding);
+    let stall_timeout = time::Duration::from_secs(config.watchdog_timeout);
+    let mut payments_failed = false;
+    let res = loop {
+        let loop_stopped = Arc::new(AtomicBool::new(false));
+        let heartbeat = Arc::new(Heartbeat::new());
+        let (done_tx, done_rx) = channel();
+        {
+            let config = config.clone();
+            let service = service.clone();
+            let clientchain = clientchain.clone();
+            let storage = storage.clone();
+            let shared_challenge = shared_challenge.clone();
+            let challenge_registry = challenge_registry.clone();
+            let verify_rx = relay.attach();
+            let funding = funding.clone();
+            let challenges_paused = challenges_paused.clone();
+            let degraded_status = degraded_status.clone();
+            let req_send = if payments_handler.is_some() {
+                Some(req_send.clone())
+            } else {
+                None
+            };
+            let journal = journal.clone();
+            let events = events.clone();
+            let heartbeat = heartbeat.clone();
+            let loop_stopped = loop_stopped.clone();
+            let _ = thread::spawn(move || {
+                let _ = done_tx.send(run_challenger_loop(
+                    service.as_ref(),
+                    clientchain.as_ref(),
+                    storage,
+                    shared_challenge,
+                    &ChallengerLoopContext {
+                        config: &config,
+                        genesis_hash,
+                        req_send,
+                        registry: &challenge_registry,
+                        verify_rx: &verify_rx,
+                        funding: (*funding).as_ref(),
+                        degraded_status: &degraded_status,
+                        events: &events,
+                        paused: &challenges_paused,
+                        stopped: &loop_stopped,
+                        journal: &journal,
+                        heartbeat: &heartbeat,
+                        clock: &::clock::SystemClock,
+                    },
+                ));
+            });
+        }
+
+        // wait for the loop to finish, stopping it once the coordinator is
+        // stopped or payments fail, and restart it if stalled
+        let res = loop {
+            match done_rx.recv_timeout(time::Duration::from_millis(STOP_CHECK_MS)) {
+                Ok(res) => break Some(res),
+                Err(RecvTimeoutError::Disconnected) => {
+                    break Some(Err(Error::from(CError::Generic("challenger loop panicked".to_owned()))))
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+            if stopped.load(Ordering::SeqCst) {
+                loop_stopped.store(true, Ordering::SeqCst);
+            }
+            if let Some(handle) = payments_handler.as_mut() {
+                if !payments_failed && handle.got_err() {
+                    payments_failed = true;
+                    loop_stopped.store(true, Ordering::SeqCst);
+                }
+            }
+            if config.watchdog_timeout > 0 {
+                if let Some(stall) = heartbeat.stall(stall_timeout) {
+                    error!(
+                        "Challenger loop stalled at {} for {} sec, request: {:?}",
+                        stall.stage, stall.elapsed_secs, stall.request
+                    );
+                    warn!(
+                        "Rpc stats at stall: {}",
+                        serde_json::to_string(&rpc_stats().methods()).unwrap()
+                    );
+                    // abandon the loop, which leaves the shared state alone
+                    // once unblocked, and stop accepting proofs until the
+                    // request is resumed
+                    let stopping = loop_stopped.swap(true, Ordering::SeqCst);
+                    *shared_challenge.write().unwrap() = None;
+                    if stopping {
+                        break Some(Ok(()));
+                    }
+                    info!("Restarting challenger loop");
+                    events.emit(CoordinatorEvent::ChallengerRestarted(stall));
+                    break None;
+                }
+            }
+        };
+        if let Some(res) = res {
+            break res;
+        }
+    };
+    api_handler.close(); // try closing the api server
+    if let Some(handle) = payments_handler {
+        if !payments_failed {
+            handle.stop(); // try closing the payments service
+        }
+    }
+    listener_handle.stop(); // try stop listener service
+    if let Some(handle) = monitor_handle {
+        handle.stop(); // try stop balance monitor
+    }
+    if let Some(handle) = refunds_handle {
+        handle.stop(); // try stop bid refunds
+    }
+    if let Some(handle) = maintenance_handle {
+        handle.stop(); // try stop maintenance windows
+    }
+    res
+}
+
+/// Config, registry, channels, funding and shared state that the challenger
+/// loop runs requests with
+pub struct ChallengerLoopContext<'a> {
+    /// Coordinator config
+    pub config: &'a Config,
+    /// Genesis hash of the client chain that requests are fetched for
+    pub genesis_hash: sha256d::Hash,
+    /// Channel sending finished requests for payment, if payments are run
+    pub req_send: Option<Sender<sha256d::Hash>>,
+    /// Registry of the challenge states that the listener routes proofs by
+    pub registry: &'a ChallengeRegistry,
+    /// Channel receiving the verified challenge responses
+    pub verify_rx: &'a Receiver<ChallengeResponse>,
+    /// Funding of the expected challenges of requests, if configured
+    pub funding: Option<&'a Funding>,
+    /// Degraded status set while the chains are unreachable
+    pub degraded_status: &'a RwLock<Option<DegradedStatus>>,
+    /// Event callbacks
+    pub events: &'a CoordinatorEvents,
+    /// Flag pausing challenges
+    pub paused: &'a AtomicBool,
+    /// Flag stopping the loop
+    pub stopped: &'a AtomicBool,
+    /// Journal recording challenges issued and responses saved
+    pub journal: &'a Journal,
+    /// Heartbeat beaten by the loop and its requests
+    pub heartbeat: &'a Heartbeat,
+    /// Clock of the challenger
+    pub clock: &'a dyn Clock,
+}
+
+/// Run challenge requests until a request fails with a permanent error or the
+/// stopped flag is set, beating the heartbeat before each request. If the
+/// chains are unreachable challenging pauses in degraded mode until both chains
+/// are reachable, while the api keeps serving reads, and requests failing with
+/// other transient errors are retried after a block. Finished requests are
+/// sent for payment if payments are run. The challenge state is left alone
+/// once the stopped flag is set, as the loop may have been abandoned by the
+/// watchdog and replaced by a new loop
+fn run_challenger_loop<T: Service, K: ClientChain, D: Storage + Send + Sync + 'static>(
+    service: &T,
+    clientchain: &K,
+    storage: Arc<D>,
+    shared_challenge: Arc<RwLock<Option<ChallengeState>>>,
+    context: &ChallengerLoopContext,
+) -> Result<()> {
+    let (config, events, degraded_status) = (context.config, context.events, context.degraded_status);
+    let (heartbeat, stopped) = (context.heartbeat, context.stopped);
     loop {
-        match run_request(
-            &config,
-            &service,
-            &clientchain,
-            storage.clone(),
-            shared_challenge.clone(),
-            &verify_rx,
-            genesis_hash,
-        ) {
+        heartbeat.beat("next request");
+        heartbeat.set_request(None);
+        if stopped.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        match run_request(service, clientchain, storage.clone(), shared_challenge.clone(), context) {
             Ok(res) => {
                 if let Some(request_id) = res {
                     // if challenge request succeeds print responses
-                    req_send.send(request_id).unwrap();
-                    info! {"***** Response *****"}
-                    let resp = storage.get_response(request_id)?.unwrap();
-                    info! {"{}", serde_json::to_string_pretty(&resp).unwrap()};
+                    if let Some(req_send) = &context.req_send {
+                        req_send.send(request_id).unwrap();
+                    }
+                    events.emit(CoordinatorEvent::RequestFinished(request_id));
+                    skip_transient(
+                        "request report",
+                        report_request(config, clientchain, storage.as_ref(), request_id),
+                    )?;
+                }
+                if stopped.load(Ordering::SeqCst) {
+                    return Ok(());
                 }
                 // Reset challenge state to None.
                 *shared_challenge.write().unwrap() = None;
 
                 info! {"Sleeping for {} sec...", config.block_time}
-                thread::sleep(time::Duration::from_secs(config.block_time))
+                sleep_until_stopped(time::Duration::from_secs(config.block_time), stopped);
             }
             Err(err) => {
-                api_handler.close(); // try closing the api server
-                payments_handler.stop(); // try closing the payments service
-                listener_handle.stop(); // try stop listener service
+                if stopped.load(Ordering::SeqCst) {
+                    return Err(err);
+                }
+                // clear the challenge state so that no proofs are accepted
+                *shared_challenge.write().unwrap() = None;
+                events.emit(CoordinatorEvent::RequestFailed(err.to_string()));
+                heartbeat.idle("degraded");
+                if ::connectivity::run_degraded(
+                    service,
+                    clientchain,
+                    &err,
+                    degraded_status,
+                    config.monitor.webhook.as_ref().map(|webhook| webhook.as_str()),
+                    time::Duration::from_secs(config.block_time),
+                ) {
+                    events.emit(CoordinatorEvent::ChallengesResumed);
+                    continue;
+                }
+                if err.retryable() {
+                    warn! {"Request failed, retrying in {} sec: {}", config.block_time, err};
+                    sleep_until_stopped(time::Duration::from_secs(config.block_time), stopped);
+                    continue;
+                }
                 return Err(err);
             }
         }
-        if payments_handler.got_err() {
-            break;
+    }
+}
+
+/// Log the response of a finished request and reconcile it against the proofs
+/// published on the client chain if configured
+fn report_request<K: ClientChain, D: Storage>(
+    config: &Config,
+    clientchain: &K,
+    storage: &D,
+    request_id: sha256d::Hash,
+) -> Result<()> {
+    info! {"***** Response *****"}
+    let resp = storage.get_response(request_id)?.unwrap();
+    info! {"{}", serde_json::to_string_pretty(&resp).unwrap()};
+    if config.reconciliation {
+        // reconciliation failures are reported without interrupting the
+        // coordinator
+        let request = storage.get_request(request_id)?.unwrap();
+        if let Err(e) = ::reconciliation::reconcile_response(clientchain, storage, &request) {
+            warn!("Reconciliation of request {} failed: {}", request_id, e);
         }
     }
-    api_handler.close(); // try closing the api server
-    listener_handle.stop(); // try stop listener service
     Ok(())
 }
 
 /// Run request method attemps to fetch a challenge request and run it
-/// This involves storing the Request and winning bids, issuing challenges
-/// on the client chain and listening for responses on these challenges
-pub fn run_request<T: Service, K: ClientChain, D: Storage>(
-    config: &Config,
+/// This involves storing the Request and winning bids, funding the expected
+/// challenges if funding is configured, issuing challenges on the client chain
+/// and listening for responses on these challenges. Bids above the configured
+/// max are spilled from memory to storage once stored. The challenge state is
+/// registered in the challenge registry for the listener to route proofs to
+/// while the request is being challenged, unless the stopped flag is set before
+/// the request is challenged. Requests left unfinished once the stopped flag is
+/// set are not returned, while requests that fail to be challenged are stored
+/// with the failure
+pub fn run_request<T: Service, K: ClientChain, D: Storage + Send + Sync + 'static>(
     service: &T,
     clientchain: &K,
     storage: Arc<D>,
     shared_challenge: Arc<RwLock<Option<ChallengeState>>>,
-    verify_rx: &Receiver<ChallengeResponse>,
-    genesis_hash: sha256d::Hash,
+    context: &ChallengerLoopContext,
 ) -> Result<Option<sha256d::Hash>> {
-    match ::challenger::fetch_next(service, &genesis_hash)? {
+    let (config, registry, events, stopped) = (context.config, context.registry, context.events, context.stopped);
+    match ::challenger::fetch_next(
+        service,
+        storage.as_ref(),
+        &context.genesis_hash,
+        config.request_max_duration,
+    )? {
         Some(mut challenge) => {
+            context.heartbeat.set_request(Some(challenge.request.txid));
+            // tag logs with the request txid and store them for retrieval
+            let _log_context = RequestLogContext::new(challenge.request.txid, storage.as_ref());
+
             // First attempt to store the challenge state information
             // on requests and winning bids and exit if it fails.
             // If already set update challenge state with correct version from storage
@@ -104,36 +704,167 @@ pub fn run_request<T: Service, K: ClientChain, D: Storage>(
                 config.clientchain.block_time,
             )?;
 
+            // overrides of the tenant of the request client chain, if any
+            let request_clientchain = config.tenant_clientchain_for(&challenge.request.genesis_blockhash.to_string());
+
+            // fix the payload that proofs of the request commit to, taken at
+            // the request client chain start height
+            let scheme = PayloadScheme::from_str(&request_clientchain.challenge_payload)?;
+            challenge.payload = scheme.payload(&challenge.request, clientchain)?;
+            if let Some(payload) = &challenge.payload {
+                info!(
+                    "Request {} proofs commit to {} payload {} at height {}",
+                    challenge.request.txid,
+                    scheme.as_str(),
+                    payload.commitment,
+                    payload.height
+                );
+            }
+
+            // commit to the request challenges before revealing them
+            challenge.commit_reveal = request_clientchain.challenge_commit_reveal;
+
+            // keep only hot bids in memory for requests with many bids
+            if config.challenge_max_bids > 0 {
+                let spilled = challenge.spill_bids(config.challenge_max_bids as usize, storage.clone());
+                if spilled > 0 {
+                    info!(
+                        "Spilled {} bids of request {} to storage",
+                        spilled, challenge.request.txid
+                    );
+                }
+            }
+
+            // split challenge sized outputs for the expected challenges;
+            // funding failures are reported without interrupting the request
+            if let Some(funding) = context.funding {
+                if let Err(e) = funding.fund_request(
+                    &challenge.request,
+                    service.get_blockheight()?,
+                    config.challenge_frequency,
+                ) {
+                    warn!("Funding request {} failed: {}", challenge.request.txid, e);
+                }
+            }
+
+            // challenge timings with any overrides for the request genesis
+            // hash, with the response window extended by any host clock drift
+            let drift = ::clock::check_clock(
+                service,
+                clientchain,
+                config.block_time,
+                config.clientchain.block_time,
+                config.clock_drift_threshold,
+            );
+            let timing = ::clock::compensate_timing(
+                config.challenge_timing(&challenge.request.genesis_blockhash.to_string()),
+                drift,
+            );
+
+            // the loop may have been abandoned while waiting for the chains
+            if stopped.load(Ordering::SeqCst) {
+                return Ok(None);
+            }
+
             // modify challenge state for the new challenge request
+            let request_txid = challenge.request.txid;
+            events.emit(CoordinatorEvent::RequestStarted(request_txid));
             *shared_challenge.write().unwrap() = Some(challenge);
+            // route proofs of the request to the challenge state until the
+            // request is done
+            let _registration = registry.register(request_txid, shared_challenge.clone());
 
             // run challenge request storing expected responses
             match ::challenger::run_challenge_request(
                 service,
                 clientchain,
                 shared_challenge.clone(),
-                &verify_rx,
+                context.verify_rx,
                 storage.clone(),
-                time::Duration::from_secs(5 * config.block_time),
-                time::Duration::from_secs(config.challenge_duration),
-                config.challenge_frequency,
-                time::Duration::from_secs(config.block_time / 2),
+                ChallengerContext {
+                    settings: ChallengeRequestSettings {
+                        timing,
+                        grace_period: time::Duration::from_secs(config.challenge_grace_period),
+                        challenge_frequency: config.challenge_frequency,
+                        payment_epoch: config.payment_epoch,
+                    },
+                    stall_detector: ::stall::StallDetector::new(
+                        time::Duration::from_secs(config.clientchain_stall_threshold),
+                        config.clientchain.block_time,
+                    ),
+                    paused: context.paused,
+                    stopped,
+                    journal: context.journal,
+                    heartbeat: context.heartbeat,
+                    clock: context.clock,
+                },
             ) {
+                Ok(()) if stopped.load(Ordering::SeqCst) => Ok(None),
                 Ok(()) => {
-                    // update end clientchain height with final height
-                    let mut shared_ch_lock = shared_challenge.write().unwrap();
-                    let ch_final = shared_ch_lock.as_mut().unwrap();
-                    ch_final.request.end_blockheight_clientchain = clientchain.get_blockheight()?;
-                    info!(
-                        "Request client chain end height updated to {}",
-                        ch_final.request.end_blockheight_clientchain
-                    );
-                    storage.update_request(&ch_final.request)?;
-                    return Ok(Some(ch_final.request.txid));
+                    // update end clientchain height with final height,
+                    // holding the write lock only for the update itself
+                    let end_height = clientchain.get_blockheight()?;
+                    let request = {
+                        let mut shared_ch_lock = shared_challenge.write().unwrap();
+                        let ch_final = shared_ch_lock.as_mut().unwrap();
+                        ch_final.request.end_blockheight_clientchain = end_height;
+                        ch_final.request.failure = None;
+                        ch_final.request.clone()
+                    };
+                    info!("Request client chain end height updated to {}", end_height);
+                    storage.update_request(&request)?;
+                    return Ok(Some(request.txid));
+                }
+                Err(err) => {
+                    // record why the request was not serviced for clients
+                    let mut request = shared_challenge.read().unwrap().as_ref().unwrap().request.clone();
+                    if let Err(e) = ::challenger::record_request_failure(service, storage.as_ref(), &mut request, &err)
+                    {
+                        warn!("Recording failure of request {} failed: {}", request.txid, e);
+                    }
+                    Err(err)
                 }
-                Err(err) => Err(err),
             }
         }
         None => Ok(None),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    #[test]
+    fn coordinator_events_test() {
+        let received = Arc::new(Mutex::new(vec![]));
+        let mut events = CoordinatorEvents::new();
+        events.emit(CoordinatorEvent::Started);
+        for _ in 0..2 {
+            let received = received.clone();
+            events.add(Box::new(move |event: &CoordinatorEvent| {
+                received.lock().unwrap().push(event.clone())
+            }));
+        }
+        events.emit(CoordinatorEvent::Stopped(None));
+        assert_eq!(
+            vec![CoordinatorEvent::Stopped(None), CoordinatorEvent::Stopped(None)],
+            *received.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn sleep_until_stopped_test() {
+        let stopped = AtomicBool::new(false);
+        let now = time::Instant::now();
+        sleep_until_stopped(time::Duration::from_millis(50), &stopped);
+        assert!(now.elapsed() >= time::Duration::from_millis(50));
+
+        // no sleep once stopped
+        stopped.store(true, Ordering::SeqCst);
+        let now = time::Instant::now();
+        sleep_until_stopped(time::Duration::from_secs(60), &stopped);
+        assert!(now.elapsed() < time::Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,139 @@
+//! Coverage
+//!
+//! Challenge coverage of requests, as the fraction of the challenges scheduled
+//! over the request service period that the coordinator issued on time. The
+//! service period is split into slots of challenge frequency blocks starting at
+//! the request start height, as challenges are issued every challenge frequency
+//! blocks, and slots without a challenge are reported as gaps, e.g. due to
+//! coordinator downtime, paused challenges or unreachable or stalled chains.
+//! This lets clients disputing results tell coordinator failures from guardnode
+//! failures
+
+use serde::{Deserialize, Serialize};
+
+use crate::interfaces::request::Request;
+
+/// Range of service chain heights in which no challenge was issued
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageGap {
+    /// First height of the gap
+    pub start_height: u64,
+    /// Last height of the gap
+    pub end_height: u64,
+    /// Number of scheduled challenges missed
+    pub missed: u32,
+}
+
+/// Challenge coverage of a request up to a service chain height
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChallengeCoverage {
+    /// Challenge frequency in blocks that challenges were scheduled at
+    pub frequency: u64,
+    /// Service chain height that coverage was computed up to
+    pub height: u64,
+    /// Number of challenges scheduled up to the height
+    pub scheduled: u32,
+    /// Number of scheduled challenges issued on time, i.e. within their slot
+    pub issued: u32,
+    /// Fraction of scheduled challenges issued on time, 1 if none scheduled
+    pub coverage: f64,
+    /// Gaps of consecutive slots without a challenge
+    pub gaps: Vec<CoverageGap>,
+}
+
+impl ChallengeCoverage {
+    /// Compute the coverage of a request up to a service chain height from the
+    /// heights that its challenges were issued at
+    pub fn new(request: &Request, challenge_heights: &[u64], frequency: u64, height: u64) -> ChallengeCoverage {
+        let frequency = frequency.max(1);
+        let start = request.start_blockheight as u64;
+        let end = (request.end_blockheight as u64).min(height);
+        let mut coverage = ChallengeCoverage {
+            frequency,
+            height,
+            scheduled: 0,
+            issued: 0,
+            coverage: 1.0,
+            gaps: vec![],
+        };
+        let mut slot_start = start;
+        while slot_start <= end {
+            let slot_end = (slot_start + frequency - 1).min(request.end_blockheight as u64);
+            coverage.scheduled += 1;
+            if challenge_heights
+                .iter()
+                .any(|height| *height >= slot_start && *height <= slot_end)
+            {
+                coverage.issued += 1;
+            } else if coverage
+                .gaps
+                .last()
+                .map_or(false, |gap| gap.end_height + 1 == slot_start)
+            {
+                // extend the gap of the previous slot
+                let gap = coverage.gaps.last_mut().unwrap();
+                gap.end_height = slot_end;
+                gap.missed += 1;
+            } else {
+                coverage.gaps.push(CoverageGap {
+                    start_height: slot_start,
+                    end_height: slot_end,
+                    missed: 1,
+                });
+            }
+            slot_start += frequency;
+        }
+        if coverage.scheduled > 0 {
+            coverage.coverage = coverage.issued as f64 / coverage.scheduled as f64;
+        }
+        coverage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::util::testing::{gen_challenge_state, gen_dummy_hash};
+
+    #[test]
+    fn challenge_coverage_test() {
+        let mut request = gen_challenge_state(&gen_dummy_hash(1)).request;
+        request.start_blockheight = 10;
+        request.end_blockheight = 29;
+
+        // nothing scheduled before the start height
+        let coverage = ChallengeCoverage::new(&request, &[], 2, 9);
+        assert_eq!(0, coverage.scheduled);
+        assert!(coverage.gaps.is_empty());
+
+        // challenges within slots of 2 blocks, missing those at 14-19 and 24
+        let heights = vec![10, 13, 20, 23, 26, 29];
+        let coverage = ChallengeCoverage::new(&request, &heights, 2, 100);
+        assert_eq!(10, coverage.scheduled);
+        assert_eq!(6, coverage.issued);
+        assert!(coverage.coverage > 0.59 && coverage.coverage < 0.61);
+        assert_eq!(
+            vec![
+                CoverageGap {
+                    start_height: 14,
+                    end_height: 19,
+                    missed: 3,
+                },
+                CoverageGap {
+                    start_height: 24,
+                    end_height: 25,
+                    missed: 1,
+                },
+            ],
+            coverage.gaps
+        );
+
+        // only slots started by the height are scheduled
+        let coverage = ChallengeCoverage::new(&request, &heights, 2, 15);
+        assert_eq!(3, coverage.scheduled);
+        assert_eq!(2, coverage.issued);
+        assert_eq!(1, coverage.gaps.len());
+        assert_eq!(15, coverage.gaps[0].end_height);
+    }
+}
@@ -0,0 +1,156 @@
+//! Dead letter
+//!
+//! Capture of the challenge proof submissions that the listener could not
+//! parse, which the journal records without the proof, so that guardnode
+//! integration problems can be debugged from the rejected payloads. Payloads
+//! are truncated and the values of unknown fields redacted before they are
+//! stored, and storage keeps only the most recent dead letters
+
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bitcoin::hashes::sha256d;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::interfaces::storage::Storage;
+
+/// Fields of challenge proof submissions that are kept unredacted
+pub const DEAD_LETTER_PROOF_FIELDS: [&str; 7] = ["txid", "pubkey", "hash", "sig", "version", "request", "sigtype"];
+
+/// Replacement of the values of redacted fields
+pub const DEAD_LETTER_REDACTED: &str = "<redacted>";
+
+/// Rejected challenge proof submission that could not be parsed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DeadLetter {
+    /// Unix timestamp in seconds that the submission was received at
+    pub time: u64,
+    /// Rejection reason
+    pub reason: String,
+    /// Remote address of the submitter, if known
+    pub remote_addr: Option<String>,
+    /// Request txid of the listener path that the submission was routed by,
+    /// if any
+    pub route: Option<sha256d::Hash>,
+    /// Size of the submitted payload in bytes
+    pub size: u64,
+    /// Redacted payload, truncated to the max payload size
+    pub payload: String,
+    /// Flag set if the payload was truncated
+    pub truncated: bool,
+}
+
+/// Redact the values of the fields of a json object payload that are not
+/// challenge proof fields, leaving payloads that are not json objects as is
+fn redact(body: &[u8]) -> String {
+    match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Object(mut obj)) => {
+            for (key, value) in obj.iter_mut() {
+                if !DEAD_LETTER_PROOF_FIELDS.contains(&key.as_str()) {
+                    *value = Value::from(DEAD_LETTER_REDACTED);
+                }
+            }
+            Value::Object(obj).to_string()
+        }
+        _ => String::from_utf8_lossy(body).into_owned(),
+    }
+}
+
+impl DeadLetter {
+    /// Create a dead letter of a rejected payload, redacted and truncated to
+    /// at most max payload bytes
+    pub fn new(
+        body: &[u8],
+        reason: &str,
+        remote_addr: Option<String>,
+        route: Option<sha256d::Hash>,
+        max_payload: usize,
+    ) -> DeadLetter {
+        let mut payload = redact(body);
+        let truncated = payload.len() > max_payload;
+        if truncated {
+            // truncate on a char boundary
+            let mut end = max_payload;
+            while !payload.is_char_boundary(end) {
+                end -= 1;
+            }
+            payload.truncate(end);
+        }
+        DeadLetter {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            reason: reason.to_owned(),
+            remote_addr,
+            route,
+            size: body.len() as u64,
+            payload,
+            truncated,
+        }
+    }
+}
+
+/// Dead letter capture shared by the listener threads, storing the dead
+/// letters of rejected submissions if enabled
+pub struct DeadLetters {
+    /// Storage of dead letters; None if capture is disabled
+    storage: Option<Arc<dyn Storage + Send + Sync>>,
+    /// Max payload bytes kept per dead letter
+    max_payload: usize,
+}
+
+impl DeadLetters {
+    /// Create a dead letter capture storing dead letters with at most max
+    /// payload bytes
+    pub fn new(storage: Arc<dyn Storage + Send + Sync>, max_payload: usize) -> DeadLetters {
+        DeadLetters {
+            storage: Some(storage),
+            max_payload,
+        }
+    }
+
+    /// Dead letter capture that does not capture anything
+    pub fn disabled() -> DeadLetters {
+        DeadLetters {
+            storage: None,
+            max_payload: 0,
+        }
+    }
+
+    /// Capture the dead letter of a rejected payload. Storage failures are
+    /// logged and do not affect the rejection
+    pub fn capture(&self, body: &[u8], reason: &str, remote_addr: Option<String>, route: Option<sha256d::Hash>) {
+        if let Some(storage) = &self.storage {
+            let letter = DeadLetter::new(body, reason, remote_addr, route, self.max_payload);
+            if let Err(e) = storage.save_dead_letter(&letter) {
+                warn!("failed storing dead letter: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dead_letter_new_test() {
+        // unknown fields are redacted
+        let body = br#"{"txid":"00","sig":"3044","rpcpassword":"secret"}"#;
+        let letter = DeadLetter::new(body, "bad-proof-data", Some("127.0.0.1".to_owned()), None, 1024);
+        assert!(!letter.payload.contains("secret"));
+        assert!(letter.payload.contains(DEAD_LETTER_REDACTED));
+        assert!(letter.payload.contains(r#""sig":"3044""#));
+        assert_eq!(body.len() as u64, letter.size);
+        assert!(!letter.truncated);
+
+        // payloads that are not json objects are truncated
+        let body = "[\"é\"]".repeat(4);
+        let letter = DeadLetter::new(body.as_bytes(), "bad-json-data", None, None, 3);
+        assert_eq!("[\"", letter.payload);
+        assert!(letter.truncated);
+        assert_eq!(body.len() as u64, letter.size);
+    }
+}
@@ -4,6 +4,7 @@
 
 use std::error;
 use std::fmt;
+use std::io;
 use std::result;
 
 use bitcoin::hashes::hex::Error as HashesHexError;
@@ -13,10 +14,46 @@ use config_rs::ConfigError;
 use mongodb::Error as MongoDbError;
 use ocean::AddressError;
 use ocean_rpc::Error as OceanRpcError;
+use serde::{Deserialize, Serialize};
 
 /// Crate specific Result for crate specific Errors
 pub type Result<T> = result::Result<T, Error>;
 
+/// Category of an error, deciding whether the failed operation is retried or
+/// the coordinator stops
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorCategory {
+    /// Temporary failure, e.g. of a chain or storage connection, that the
+    /// failed operation is retried after
+    Transient,
+    /// Failure that retrying does not fix
+    Permanent,
+    /// Invalid configuration or input
+    Configuration,
+}
+
+impl ErrorCategory {
+    /// Get the error category name as stored
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCategory::Transient => "transient",
+            ErrorCategory::Permanent => "permanent",
+            ErrorCategory::Configuration => "configuration",
+        }
+    }
+
+    /// Get the error category from its stored name
+    pub fn from_name(name: &str) -> Option<ErrorCategory> {
+        match name {
+            "transient" => Some(ErrorCategory::Transient),
+            "permanent" => Some(ErrorCategory::Permanent),
+            "configuration" => Some(ErrorCategory::Configuration),
+            _ => None,
+        }
+    }
+}
+
 /// Coordinator library specific errors
 #[derive(Debug)]
 pub enum CError {
@@ -31,10 +68,28 @@ pub enum CError {
     MissingUnspent(String, String),
     /// Config input error. Takes parameter input error type
     InputError(InputErrorType, String),
+    /// Storage operation did not complete within the configured timeout. Takes
+    /// parameter storage operation
+    StorageTimeout(String),
     /// Generic error from string error message
     Generic(String),
 }
 
+impl CError {
+    /// Get the category of the error. Challenges not yet verified, bids or
+    /// unspents not yet available and storage timeouts are transient
+    pub fn category(&self) -> ErrorCategory {
+        match *self {
+            CError::MissingBids
+            | CError::UnverifiedChallenge
+            | CError::MissingUnspent(_, _)
+            | CError::StorageTimeout(_) => ErrorCategory::Transient,
+            CError::InputError(_, _) => ErrorCategory::Configuration,
+            CError::ReceiverDisconnected | CError::Generic(_) => ErrorCategory::Permanent,
+        }
+    }
+}
+
 impl From<String> for CError {
     fn from(e: String) -> CError {
         CError::Generic(e)
@@ -70,6 +125,7 @@ impl fmt::Display for CError {
             CError::MissingUnspent(ref asset, ref chain) => {
                 write!(f, "No unspent found for {} asset on {} chain", asset, chain)
             }
+            CError::StorageTimeout(ref operation) => write!(f, "Storage operation timed out: {}", operation),
             _ => f.write_str(error::Error::description(self)),
         }
     }
@@ -84,6 +140,7 @@ impl error::Error for CError {
             CError::ReceiverDisconnected => "Challenge response receiver disconnected",
             CError::MissingUnspent(_, _) => "No unspent found for asset",
             CError::InputError(_, _) => "Input parameter error",
+            CError::StorageTimeout(_) => "Storage operation timed out",
         }
     }
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
@@ -114,6 +171,39 @@ pub enum Error {
     Coordinator(CError),
 }
 
+impl Error {
+    /// Get the category of the error. Rpc errors retried by the rpc client and
+    /// mongodb io errors are transient, as are the transient coordinator errors
+    pub fn category(&self) -> ErrorCategory {
+        match *self {
+            Error::OceanRpc(OceanRpcError::JsonRpc(_)) => ErrorCategory::Transient,
+            Error::MongoDb(MongoDbError::IoError(_)) => ErrorCategory::Transient,
+            Error::Config(_) => ErrorCategory::Configuration,
+            Error::Coordinator(ref e) => e.category(),
+            _ => ErrorCategory::Permanent,
+        }
+    }
+
+    /// Check whether the failed operation can be retried
+    pub fn retryable(&self) -> bool {
+        self.category() == ErrorCategory::Transient
+    }
+}
+
+/// Swallow a transient error of an operation after logging it, so that the
+/// operation is retried on its next run, returning any other error. Returns
+/// None if the error was swallowed
+pub fn skip_transient<T>(operation: &str, res: Result<T>) -> Result<Option<T>> {
+    match res {
+        Ok(value) => Ok(Some(value)),
+        Err(ref e) if e.retryable() => {
+            warn!("{} failed, retrying later: {}", operation, e);
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
 impl From<OceanRpcError> for Error {
     fn from(e: OceanRpcError) -> Error {
         Error::OceanRpc(e)
@@ -152,6 +242,15 @@ impl From<AddressError> for Error {
 
 impl From<MongoDbError> for Error {
     fn from(e: MongoDbError) -> Error {
+        // surface socket timeouts as typed storage timeouts
+        if let MongoDbError::IoError(ref io_err) = e {
+            match io_err.kind() {
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+                    return Error::Coordinator(CError::StorageTimeout(io_err.to_string()))
+                }
+                _ => (),
+            }
+        }
         Error::MongoDb(e)
     }
 }
@@ -191,3 +290,37 @@ impl error::Error for Error {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_category_test() {
+        let transient = Error::from(CError::StorageTimeout("get_request".to_owned()));
+        assert_eq!(ErrorCategory::Transient, transient.category());
+        assert!(transient.retryable());
+        let io_err = io::Error::new(io::ErrorKind::ConnectionRefused, "connection refused");
+        assert!(Error::from(MongoDbError::IoError(io_err)).retryable());
+
+        let permanent = Error::from(CError::Generic("failed".to_owned()));
+        assert_eq!(ErrorCategory::Permanent, permanent.category());
+        assert!(!permanent.retryable());
+        let input = Error::from(CError::InputError(InputErrorType::GenHash, "00".to_owned()));
+        assert_eq!(ErrorCategory::Configuration, input.category());
+        assert!(!input.retryable());
+
+        assert_eq!(None, skip_transient::<u32>("op", Err(transient)).unwrap());
+        assert_eq!(Some(1), skip_transient("op", Ok(1)).unwrap());
+        assert!(skip_transient::<u32>("op", Err(permanent)).is_err());
+
+        for category in &[
+            ErrorCategory::Transient,
+            ErrorCategory::Permanent,
+            ErrorCategory::Configuration,
+        ] {
+            assert_eq!(Some(*category), ErrorCategory::from_name(category.as_str()));
+        }
+        assert_eq!(None, ErrorCategory::from_name("unknown"));
+    }
+}
@@ -0,0 +1,232 @@
+//! Funding
+//!
+//! Challenge wallet funding that splits a funding utxo into challenge sized
+//! outputs at the start of each request, one per expected challenge, so that
+//! long requests never stall on a single challenge asset unspent tied up in
+//! an unconfirmed transaction
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use bitcoin::hashes::{hex::FromHex, sha256d};
+use bitcoin::Amount;
+use ocean_rpc::{json, RpcApi};
+use serde_json::Value;
+
+use crate::config::{ClientChainConfig, FundingConfig};
+use crate::error::{CError, Error, Result};
+use crate::interfaces::request::Request;
+use crate::util::ocean::OceanClient;
+
+/// Parse a funding utxo outpoint of the form txid:vout
+pub fn parse_outpoint(outpoint: &str) -> Result<(sha256d::Hash, u32)> {
+    let mut parts = outpoint.splitn(2, ':');
+    match (parts.next(), parts.next()) {
+        (Some(txid), Some(vout)) => match (sha256d::Hash::from_hex(txid), u32::from_str(vout)) {
+            (Ok(txid), Ok(vout)) => Ok((txid, vout)),
+            _ => Err(Error::from(CError::Generic(format!("invalid outpoint: {}", outpoint)))),
+        },
+        _ => Err(Error::from(CError::Generic(format!("invalid outpoint: {}", outpoint)))),
+    }
+}
+
+/// Number of challenges expected for the remainder of a request, given the
+/// service chain height and the challenge frequency
+pub fn expected_challenges(request: &Request, service_height: u64, challenge_frequency: u64) -> u32 {
+    let start_height = service_height.max(request.start_blockheight as u64);
+    if (request.end_blockheight as u64) < start_height {
+        return 0;
+    }
+    ((request.end_blockheight as u64 - start_height) / challenge_frequency.max(1) + 1) as u32
+}
+
+/// Number of challenge sized outputs to split from the funding amount, so that
+/// together with the existing challenge sized outputs there is one per expected
+/// challenge. Bounded by the funding amount and the max outputs per split
+pub fn split_outputs(
+    expected: u32,
+    existing: u32,
+    funding_amount: Amount,
+    challenge_amount: Amount,
+    max_outputs: u32,
+) -> u32 {
+    if challenge_amount == Amount::ZERO || existing >= expected {
+        return 0;
+    }
+    let affordable = funding_amount.as_sat() / challenge_amount.as_sat();
+    (expected - existing)
+        .min(max_outputs)
+        .min(affordable.min(u32::max_value() as u64) as u32)
+}
+
+/// Funding struct holding data and logic required to split the funding utxo
+/// into challenge sized outputs
+pub struct Funding {
+    /// Ocean rpc connectivity to client chain
+    client: OceanClient,
+    /// Funding config
+    config: FundingConfig,
+    /// Challenge asset label
+    asset: String,
+}
+
+impl Funding {
+    /// Return new Funding instance for the clientchain wallet, or None if
+    /// funding is off
+    pub fn from_config(config: &FundingConfig, clientchain_config: &ClientChainConfig) -> Result<Option<Funding>> {
+        if config.challenge_amount <= 0.0 {
+            return Ok(None);
+        }
+        if let Some(utxo) = &config.utxo {
+            let _ = parse_outpoint(utxo)?;
+        }
+        let client =
+            OceanClient::with_credentials(clientchain_config.host.clone(), clientchain_config.rpc_credentials())?;
+        Ok(Some(Funding {
+            client,
+            config: config.clone(),
+            asset: clientchain_config.asset.clone(),
+        }))
+    }
+
+    /// Select the funding unspent; the configured funding utxo if unspent,
+    /// otherwise the largest challenge asset unspent above the challenge amount
+    fn funding_unspent(
+        &self,
+        unspents: &[json::ListUnspentResultEntry],
+        challenge_amount: Amount,
+    ) -> Result<Option<json::ListUnspentResultEntry>> {
+        if let Some(utxo) = &self.config.utxo {
+            let (txid, vout) = parse_outpoint(utxo)?;
+            if let Some(unspent) = unspents.iter().find(|entry| entry.txid == txid && entry.vout == vout) {
+                return Ok(Some(unspent.clone()));
+            }
+        }
+        Ok(unspents
+            .iter()
+            .filter(|entry| entry.amount > challenge_amount)
+            .max_by_key(|entry| entry.amount)
+            .cloned())
+    }
+
+    /// Get a new wallet address for a challenge sized output, unconfidential
+    /// as challenge transactions are not blinded
+    fn new_address(&self) -> Result<String> {
+        let address: String = self.client.call("getnewaddress", &[])?;
+        let info: Value = self.client.call("validateaddress", &[Value::from(address.clone())])?;
+        Ok(info["unconfidential"].as_str().map_or(address, |addr| addr.to_owned()))
+    }
+
+    /// Fund the expected challenges of a request by splitting the funding
+    /// unspent into challenge sized outputs, with any change paid back to the
+    /// funding address. Returns the txid of the split transaction or None if
+    /// existing challenge sized outputs already cover the request
+    pub fn fund_request(
+        &self,
+        request: &Request,
+        service_height: u64,
+        challenge_frequency: u64,
+    ) -> Result<Option<sha256d::Hash>> {
+        let challenge_amount = Amount::from_btc(self.config.challenge_amount)
+            .map_err(|e| Error::from(CError::Generic(format!("invalid challenge amount: {}", e))))?;
+        // include unconfirmed outputs of previous splits
+        let unspents = self.client.list_unspent(Some(0), None, None, None, Some(&self.asset))?;
+        let existing = unspents.iter().filter(|entry| entry.amount == challenge_amount).count() as u32;
+        let expected = expected_challenges(request, service_height, challenge_frequency);
+        let funding = match self.funding_unspent(&unspents, challenge_amount)? {
+            Some(funding) => funding,
+            None => {
+                return Err(Error::from(CError::MissingUnspent(
+                    self.asset.clone(),
+                    String::from("Client"),
+                )))
+            }
+        };
+        let num_outputs = split_outputs(
+            expected,
+            existing,
+            funding.amount,
+            challenge_amount,
+            self.config.max_outputs,
+        );
+        info!(
+            "funding {} expected challenges with {} existing and {} new outputs",
+            expected, existing, num_outputs
+        );
+        if num_outputs == 0 {
+            if existing < expected {
+                warn!("funding unspent {}:{} too small to split", funding.txid, funding.vout);
+            }
+            return Ok(None);
+        }
+
+        let utxos = vec![json::CreateRawTransactionInput {
+            txid: funding.txid,
+            vout: funding.vout,
+            sequence: None,
+        }];
+        let mut outs = HashMap::new();
+        let mut outs_assets = HashMap::new();
+        for _ in 0..num_outputs {
+            let address = self.new_address()?;
+            let _ = outs.insert(address.clone(), challenge_amount);
+            let _ = outs_assets.insert(address, funding.asset.clone());
+        }
+        let change = funding.amount - challenge_amount * num_outputs as u64;
+        if change > Amount::ZERO {
+            let _ = outs.insert(funding.address.to_string(), change);
+            let _ = outs_assets.insert(funding.address.to_string(), funding.asset.clone());
+        }
+
+        // no fees as with challenge transactions of the policy asset
+        let tx_hex = self
+            .client
+            .create_raw_transaction_hex(&utxos, &outs, Some(&outs_assets), None)?;
+        let tx_signed = self
+            .client
+            .sign_raw_transaction(&Vec::<u8>::from_hex(&tx_hex)? as &[u8], None, None, None)?;
+        let txid = self.client.send_raw_transaction(&tx_signed.hex)?;
+        info!("funding split {} sent", txid);
+        Ok(Some(txid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::util::testing::{gen_challenge_state, gen_dummy_hash};
+
+    #[test]
+    fn parse_outpoint_test() {
+        let txid = gen_dummy_hash(1);
+        assert_eq!((txid, 3), parse_outpoint(&format!("{}:3", txid)).unwrap());
+        assert!(parse_outpoint(&txid.to_string()).is_err());
+        assert!(parse_outpoint(&format!("{}:x", txid)).is_err());
+        assert!(parse_outpoint("zz:0").is_err());
+    }
+
+    #[test]
+    fn expected_challenges_test() {
+        let mut request = gen_challenge_state(&gen_dummy_hash(1)).request;
+        request.start_blockheight = 10;
+        request.end_blockheight = 20;
+        assert_eq!(11, expected_challenges(&request, 0, 1));
+        assert_eq!(11, expected_challenges(&request, 10, 1));
+        assert_eq!(6, expected_challenges(&request, 10, 2));
+        assert_eq!(1, expected_challenges(&request, 20, 2));
+        assert_eq!(0, expected_challenges(&request, 21, 2));
+    }
+
+    #[test]
+    fn split_outputs_test() {
+        let btc = |value: f64| Amount::from_btc(value).unwrap();
+        assert_eq!(10, split_outputs(10, 0, btc(1.0), btc(0.01), 100));
+        assert_eq!(4, split_outputs(10, 6, btc(1.0), btc(0.01), 100));
+        assert_eq!(0, split_outputs(10, 10, btc(1.0), btc(0.01), 100));
+        assert_eq!(0, split_outputs(10, 12, btc(1.0), btc(0.01), 100));
+        assert_eq!(5, split_outputs(10, 0, btc(1.0), btc(0.01), 5));
+        assert_eq!(3, split_outputs(10, 0, btc(0.035), btc(0.01), 100));
+        assert_eq!(0, split_outputs(10, 0, btc(1.0), Amount::ZERO, 100));
+    }
+}
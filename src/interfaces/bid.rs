@@ -3,19 +3,23 @@
 //! Service request models for bids and bid payments
 
 use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
 
 use bitcoin::{hashes::sha256d, secp256k1::PublicKey, Amount};
-use ocean::Address;
+use ocean::{Address, AddressParams};
 use ocean_rpc::json::GetRequestBidsResultBid;
-use serde::{Serialize, Serializer};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::{CError, Error};
 
 /// Bid struct storing successful bids and modelling data that need to be stored
-#[derive(Clone, Debug, PartialEq, Hash, Eq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Hash, Eq, Serialize, Deserialize)]
 pub struct Bid {
     /// Ocean transaction ID of the bid transaction
     pub txid: sha256d::Hash,
     /// Bid owner verification public key
-    #[serde(serialize_with = "serialize_pubkey")]
+    #[serde(serialize_with = "serialize_pubkey", deserialize_with = "deserialize_pubkey")]
     pub pubkey: PublicKey,
     /// Bid payment optional
     pub payment: Option<BidPayment>,
@@ -32,24 +36,405 @@ impl Bid {
     }
 }
 
+/// Type of the address that bids are paid to, derived from the bid pubkey
+#[derive(Clone, Copy, Debug, PartialEq, Hash, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PayoutAddressType {
+    /// Pay to pubkey hash
+    P2pkh,
+    /// Pay to witness pubkey hash nested in pay to script hash
+    P2shP2wpkh,
+    /// Pay to witness pubkey hash
+    P2wpkh,
+}
+
+impl PayoutAddressType {
+    /// Get the address type name as used in config
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayoutAddressType::P2pkh => "p2pkh",
+            PayoutAddressType::P2shP2wpkh => "p2sh-p2wpkh",
+            PayoutAddressType::P2wpkh => "p2wpkh",
+        }
+    }
+
+    /// Derive the address of this type for a bid pubkey and address params
+    pub fn address(&self, pubkey: &PublicKey, addr_params: &'static AddressParams) -> Address {
+        let pubkey = bitcoin::PublicKey {
+            key: *pubkey,
+            compressed: true,
+        };
+        match self {
+            PayoutAddressType::P2pkh => Address::p2pkh(&pubkey, None, addr_params),
+            PayoutAddressType::P2shP2wpkh => Address::p2shwpkh(&pubkey, None, addr_params),
+            PayoutAddressType::P2wpkh => Address::p2wpkh(&pubkey, None, addr_params),
+        }
+    }
+}
+
+impl Default for PayoutAddressType {
+    fn default() -> PayoutAddressType {
+        PayoutAddressType::P2pkh
+    }
+}
+
+impl fmt::Display for PayoutAddressType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for PayoutAddressType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<PayoutAddressType, Error> {
+        match s {
+            "p2pkh" => Ok(PayoutAddressType::P2pkh),
+            "p2sh-p2wpkh" => Ok(PayoutAddressType::P2shP2wpkh),
+            "p2wpkh" => Ok(PayoutAddressType::P2wpkh),
+            _ => Err(Error::from(CError::Generic(format!(
+                "unknown payout address type: {}",
+                s
+            )))),
+        }
+    }
+}
+
 /// Bid payment struct holding information for fee payments received by bid
 /// owners
-#[derive(Clone, Debug, PartialEq, Hash, Eq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Hash, Eq, Serialize, Deserialize)]
 pub struct BidPayment {
-    /// Bid payment transaction id; optional as might not be set yet
-    pub txid: Option<sha256d::Hash>,
-    /// Additional bid payment transaction ids, for when tx is split
-    pub extra_txids: Option<Vec<sha256d::Hash>>,
+    /// Bid payment transactions; empty if not paid yet and multiple if the
+    /// payment was split across transactions
+    pub txs: Vec<BidPaymentTx>,
     /// Bid pay to address
     pub address: Address,
+    /// Type of the bid pay to address
+    pub address_type: PayoutAddressType,
     /// Bid amount expected
     #[serde(with = "bitcoin::util::amount::serde::as_btc")]
     pub amount: Amount,
+    /// Payment intent id (bid txid, amount and attempt id) recorded before
+    /// broadcasting a payment and cleared once the payment outcome is known
+    pub intent: Option<String>,
+    /// Inputs from which the amount was calculated; optional as not recorded
+    /// for payments calculated by earlier versions
+    pub basis: Option<BidPaymentBasis>,
+}
+
+impl BidPayment {
+    /// Generate a new payment intent id for a bid payment attempt
+    pub fn new_intent(bid_txid: &sha256d::Hash, amount: &Amount, attempt_id: u64) -> String {
+        format!("{}:{}:{}", bid_txid, amount.as_sat(), attempt_id)
+    }
+
+    /// Check whether the payment has been broadcast
+    pub fn is_paid(&self) -> bool {
+        self.txs.len() > 0
+    }
+
+    /// Check whether all payment transactions have at least the given number
+    /// of confirmations
+    pub fn is_confirmed(&self, confirmations: u32) -> bool {
+        self.is_paid() && self.txs.iter().all(|tx| tx.confirmations >= confirmations)
+    }
+}
+
+/// Bid payment transaction struct holding a transaction of a bid payment, with
+/// the amount paid by the transaction and its confirmations when last checked
+#[derive(Clone, Debug, PartialEq, Hash, Eq, Serialize, Deserialize)]
+pub struct BidPaymentTx {
+    /// Payment transaction id
+    pub txid: sha256d::Hash,
+    /// Amount paid to the bid address by the transaction
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub amount: Amount,
+    /// Number of confirmations of the transaction
+    pub confirmations: u32,
+}
+
+/// Version of the formula used to calculate bid payment amounts from a bid
+/// payment basis
+pub const BID_PAYMENT_FORMULA_VERSION: u32 = 1;
+
+/// Version of the formula used to calculate bid payment amounts from a bid
+/// payment basis with latency weighting
+pub const BID_PAYMENT_LATENCY_FORMULA_VERSION: u32 = 2;
+
+/// Latency weighting of the responses of a bid, with the number of responses
+/// in each latency bucket by proof arrival latency and the weight of each
+/// bucket, recorded so that weighted payments can be audited
+#[derive(Clone, Debug, PartialEq, Hash, Eq, Serialize, Deserialize)]
+pub struct LatencyWeighting {
+    /// Upper latency bound of each bucket in milliseconds, ascending
+    pub bucket_max_ms: Vec<u64>,
+    /// Weight percentage of the responses in each bucket
+    pub bucket_weights: Vec<u32>,
+    /// Number of bid responses in each bucket
+    pub bucket_responses: Vec<u32>,
+    /// Number of bid responses without a recorded latency, counted in full
+    pub unmeasured_responses: u32,
+}
+
+impl LatencyWeighting {
+    /// Bucket the proof latencies of the responses of a bid. Responses slower
+    /// than the last bucket bound count in the last bucket
+    pub fn new(
+        bucket_max_ms: Vec<u64>,
+        bucket_weights: Vec<u32>,
+        proof_ms: &[u64],
+        num_responses: u32,
+    ) -> LatencyWeighting {
+        let mut bucket_responses = vec![0; bucket_max_ms.len()];
+        let measured = proof_ms.len().min(num_responses as usize);
+        if bucket_max_ms.len() > 0 {
+            for ms in proof_ms[..measured].iter() {
+                let bucket = bucket_max_ms
+                    .iter()
+                    .position(|max_ms| ms <= max_ms)
+                    .unwrap_or(bucket_max_ms.len() - 1);
+                bucket_responses[bucket] += 1;
+            }
+        }
+        LatencyWeighting {
+            bucket_max_ms,
+            bucket_weights,
+            unmeasured_responses: num_responses - bucket_responses.iter().sum::<u32>(),
+            bucket_responses,
+        }
+    }
+
+    /// Number of responses weighted by bucket, in percent of a response
+    pub fn weighted_responses(&self) -> u64 {
+        self.bucket_responses
+            .iter()
+            .zip(self.bucket_weights.iter())
+            .map(|(responses, weight)| *responses as u64 * *weight as u64)
+            .sum::<u64>()
+            + self.unmeasured_responses as u64 * 100
+    }
+}
+
+/// Bid payment basis struct holding the inputs from which a bid payment amount
+/// was calculated, so that the payment can be traced back to the responses and
+/// fees it was derived from
+#[derive(Clone, Debug, PartialEq, Hash, Eq, Serialize, Deserialize)]
+pub struct BidPaymentBasis {
+    /// Version of the formula used to calculate the amount
+    pub formula_version: u32,
+    /// Total service fees of the client chain blocks paid for
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub fees_amount: Amount,
+    /// Percentage of the fees paid to bids
+    pub fee_percentage: u32,
+    /// Number of bids that the fees are split between
+    pub num_bids: u32,
+    /// Number of challenges issued
+    pub num_challenges: u32,
+    /// Number of challenges responded to by the bid
+    pub num_responses: u32,
+    /// Latency weighting of the bid responses; not set if responses count
+    /// equally
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_weighting: Option<LatencyWeighting>,
+}
+
+impl BidPaymentBasis {
+    /// Calculate the bid payment amount. In formula version 1 the fee
+    /// percentage of the fees is split equally between bids and each bid share
+    /// is scaled by the bid responses over the challenges issued. In formula
+    /// version 2 each response is weighted by its latency bucket instead
+    pub fn amount(&self) -> Amount {
+        if self.num_bids == 0 || self.num_challenges == 0 {
+            return Amount::ZERO;
+        }
+        let bid_amount = self.fees_amount * self.fee_percentage as u64 / 100 / self.num_bids as u64;
+        match &self.latency_weighting {
+            Some(weighting) => bid_amount * weighting.weighted_responses() / (self.num_challenges as u64 * 100),
+            None => bid_amount * self.num_responses as u64 / self.num_challenges as u64,
+        }
+    }
 }
 
 /// Type defining a set of Bids
 pub type BidSet = HashSet<Bid>;
 
+/// Blacklisting of a bid that repeatedly submitted challenge proofs with
+/// invalid signatures. Proofs of the bid are rejected without verification
+/// until the blacklisting expires
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BidBlacklisting {
+    /// Bid txid
+    pub txid: sha256d::Hash,
+    /// Request txid of the bid
+    pub request: sha256d::Hash,
+    /// Genesis blockhash of the request client chain
+    pub genesis_blockhash: sha256d::Hash,
+    /// Number of invalid proofs that led to the blacklisting
+    pub strikes: u32,
+    /// Unix timestamp in seconds of the blacklisting
+    pub since: u64,
+    /// Unix timestamp in seconds that the blacklisting expires
+    pub until: u64,
+}
+
+impl BidBlacklisting {
+    /// Check whether the blacklisting is still in effect at a unix timestamp
+    pub fn is_active(&self, now: u64) -> bool {
+        now < self.until
+    }
+}
+
+/// Rotation of the key of a bid within a request, replacing the bid pubkey
+/// that challenge proofs are verified against and that the bid is paid to.
+/// The signatures of the rotation message are kept so that the rotation can
+/// be audited
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BidKeyRotation {
+    /// Bid txid
+    pub txid: sha256d::Hash,
+    /// Request txid of the bid
+    pub request: sha256d::Hash,
+    /// Genesis blockhash of the request client chain
+    pub genesis_blockhash: sha256d::Hash,
+    /// Bid pubkey replaced by the rotation
+    #[serde(serialize_with = "serialize_pubkey", deserialize_with = "deserialize_pubkey")]
+    pub old_pubkey: PublicKey,
+    /// Bid pubkey after the rotation
+    #[serde(serialize_with = "serialize_pubkey", deserialize_with = "deserialize_pubkey")]
+    pub new_pubkey: PublicKey,
+    /// DER hex signature of the rotation message by the old pubkey, if signed
+    pub sig: Option<String>,
+    /// DER hex signature of the rotation message by the new pubkey, if signed
+    pub new_sig: Option<String>,
+    /// Key rotation policy that the rotation was accepted under
+    pub policy: String,
+    /// Unix timestamp in seconds of the rotation
+    pub time: u64,
+}
+
+/// Lock output of a bid transaction on the service chain, spendable by the
+/// bid lock key once the lock expiry height is reached
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BidLock {
+    /// Bid txid
+    pub txid: sha256d::Hash,
+    /// Output index of the lock in the bid transaction
+    pub vout: u32,
+    /// Locked amount
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub value: Amount,
+    /// Asset id of the locked amount
+    pub asset: String,
+    /// Service chain height at which the lock expires
+    pub lock_height: u32,
+    /// Key that the locked amount can be spent with after the lock expires
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub pubkey: PublicKey,
+}
+
+impl BidLock {
+    /// Check whether the lock has expired at a service chain height
+    pub fn is_expired(&self, height: u64) -> bool {
+        height >= self.lock_height as u64
+    }
+}
+
+/// State of a bid lock refund
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BidRefundState {
+    /// Refund transaction exported unsigned for the bid owner to sign and
+    /// broadcast
+    Exported,
+    /// Refund transaction signed by the service wallet and broadcast
+    Broadcast,
+}
+
+impl BidRefundState {
+    /// Get the refund state name as stored
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BidRefundState::Exported => "exported",
+            BidRefundState::Broadcast => "broadcast",
+        }
+    }
+}
+
+impl Default for BidRefundState {
+    fn default() -> BidRefundState {
+        BidRefundState::Exported
+    }
+}
+
+impl fmt::Display for BidRefundState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for BidRefundState {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<BidRefundState, Error> {
+        match s {
+            "exported" => Ok(BidRefundState::Exported),
+            "broadcast" => Ok(BidRefundState::Broadcast),
+            _ => Err(Error::from(CError::Generic(format!("unknown bid refund state: {}", s)))),
+        }
+    }
+}
+
+/// Refund of the expired service chain lock of a request bid. Refunds are
+/// recorded separately from the fee payments of bids
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BidRefund {
+    /// Bid txid
+    pub txid: sha256d::Hash,
+    /// Request txid of the bid
+    pub request: sha256d::Hash,
+    /// Genesis blockhash of the request client chain
+    pub genesis_blockhash: sha256d::Hash,
+    /// Service chain height at which the bid lock expired
+    pub lock_height: u32,
+    /// Refunded amount
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub amount: Amount,
+    /// Service chain address that the amount is refunded to
+    pub address: String,
+    /// Refund state
+    pub state: BidRefundState,
+    /// Txid of the refund transaction, if broadcast
+    pub refund_txid: Option<sha256d::Hash>,
+    /// Hex of the refund transaction; signed if broadcast and unsigned if
+    /// exported
+    pub tx_hex: String,
+}
+
+/// Liability of the bid payments of a request, or request epoch, deferred as
+/// the wallet could not fund them. Liabilities are cleared once the deferred
+/// payments are made
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PaymentLiability {
+    /// Request txid
+    pub request: sha256d::Hash,
+    /// Genesis blockhash of the request client chain
+    pub genesis_blockhash: sha256d::Hash,
+    /// Payment epoch, if paid per epoch
+    pub epoch: Option<u32>,
+    /// Txids of the bids whose payments are deferred, in payout order
+    pub bids: Vec<sha256d::Hash>,
+    /// Total amount of the deferred payments
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub amount: Amount,
+    /// Wallet balance of the payment asset left when the payments were
+    /// deferred; payments are resumed once the balance exceeds it
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub balance: Amount,
+    /// Unix timestamp in milliseconds of the deferral
+    pub time: u64,
+}
+
 /// Custom serializer for type PublicKey in order to serialize
 /// the key into a string and not the default u8 vector
 fn serialize_pubkey<S>(x: &PublicKey, s: S) -> Result<S::Ok, S::Error>
@@ -59,12 +444,20 @@ where
     s.serialize_str(&x.to_string())
 }
 
+/// Custom deserializer for type PublicKey parsing the key from the string
+/// produced by serialize_pubkey
+fn deserialize_pubkey<'de, D>(d: D) -> Result<PublicKey, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(d)?;
+    PublicKey::from_str(&s).map_err(de::Error::custom)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use std::str::FromStr;
-
     use bitcoin::hashes::hex::FromHex;
 
     use util::testing::setup_logger;
@@ -80,10 +473,160 @@ mod tests {
             payment: None,
         };
 
-        let serialized = serde_json::to_string(&bid);
+        let serialized = serde_json::to_string(&bid).unwrap();
         assert_eq!(
             format!(r#"{{"txid":"{}","pubkey":"{}","payment":null}}"#, txid_hex, pubkey_hex),
-            serialized.unwrap()
+            serialized
         );
+        assert_eq!(bid, serde_json::from_str::<Bid>(&serialized).unwrap());
+        assert!(serde_json::from_str::<Bid>(&serialized.replace(pubkey_hex, "02")).is_err());
+    }
+
+    #[test]
+    fn bid_payment_confirmed_test() {
+        setup_logger();
+        let mut payment = BidPayment {
+            txs: vec![],
+            address: Address::from_str("CMAMyHorv18WKKTvg5Kifi8ap8CuSJUxXT").unwrap(),
+            address_type: PayoutAddressType::P2pkh,
+            amount: Amount::from_sat(100),
+            intent: None,
+            basis: None,
+        };
+        assert!(!payment.is_paid());
+        assert!(!payment.is_confirmed(0));
+
+        payment.txs = vec![
+            BidPaymentTx {
+                txid: sha256d::Hash::from_hex("1234567890000000000000000000000000000000000000000000000000000000")
+                    .unwrap(),
+                amount: Amount::from_sat(60),
+                confirmations: 3,
+            },
+            BidPaymentTx {
+                txid: sha256d::Hash::from_hex("1234567890000000000000000000000000000000000000000000000000000001")
+                    .unwrap(),
+                amount: Amount::from_sat(40),
+                confirmations: 1,
+            },
+        ];
+        assert!(payment.is_paid());
+        assert!(payment.is_confirmed(1));
+        assert!(!payment.is_confirmed(2));
+    }
+
+    #[test]
+    fn bid_payment_basis_test() {
+        setup_logger();
+        let mut basis = BidPaymentBasis {
+            formula_version: BID_PAYMENT_FORMULA_VERSION,
+            fees_amount: Amount::from_btc(6.0).unwrap(),
+            fee_percentage: 75,
+            num_bids: 4,
+            num_challenges: 3,
+            num_responses: 2,
+            latency_weighting: None,
+        };
+        assert_eq!(Amount::from_btc(0.75).unwrap(), basis.amount());
+
+        basis.num_responses = 3;
+        assert_eq!(Amount::from_btc(1.125).unwrap(), basis.amount());
+
+        // rounded down to the satoshi at each step
+        basis.fees_amount = Amount::from_sat(1001);
+        basis.fee_percentage = 50;
+        basis.num_bids = 3;
+        basis.num_responses = 1;
+        assert_eq!(Amount::from_sat(55), basis.amount());
+
+        basis.num_challenges = 0;
+        assert_eq!(Amount::ZERO, basis.amount());
+    }
+
+    #[test]
+    fn latency_weighting_test() {
+        setup_logger();
+        let weighting = LatencyWeighting::new(vec![1000, 5000], vec![100, 50], &[200, 1000, 3000, 9000], 5);
+        assert_eq!(vec![2, 2], weighting.bucket_responses);
+        assert_eq!(1, weighting.unmeasured_responses);
+        assert_eq!(400, weighting.weighted_responses());
+
+        // samples beyond the bid responses are ignored
+        let weighting = LatencyWeighting::new(vec![1000], vec![80], &[200, 300], 1);
+        assert_eq!(vec![1], weighting.bucket_responses);
+        assert_eq!(0, weighting.unmeasured_responses);
+        assert_eq!(80, weighting.weighted_responses());
+
+        let mut basis = BidPaymentBasis {
+            formula_version: BID_PAYMENT_LATENCY_FORMULA_VERSION,
+            fees_amount: Amount::from_btc(6.0).unwrap(),
+            fee_percentage: 75,
+            num_bids: 4,
+            num_challenges: 3,
+            num_responses: 2,
+            latency_weighting: Some(LatencyWeighting::new(vec![1000, 5000], vec![100, 50], &[200, 3000], 2)),
+        };
+        assert_eq!(Amount::from_btc(0.5625).unwrap(), basis.amount());
+
+        // full weight matches the unweighted formula
+        basis.latency_weighting = Some(LatencyWeighting::new(vec![1000], vec![100], &[200, 3000], 2));
+        assert_eq!(Amount::from_btc(0.75).unwrap(), basis.amount());
+    }
+
+    #[test]
+    fn payout_address_type_test() {
+        setup_logger();
+        let pubkey = PublicKey::from_str("026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3").unwrap();
+        let btc_pubkey = bitcoin::PublicKey {
+            key: pubkey,
+            compressed: true,
+        };
+        for (name, address_type, address) in vec![
+            (
+                "p2pkh",
+                PayoutAddressType::P2pkh,
+                Address::p2pkh(&btc_pubkey, None, &AddressParams::ELEMENTS),
+            ),
+            (
+                "p2sh-p2wpkh",
+                PayoutAddressType::P2shP2wpkh,
+                Address::p2shwpkh(&btc_pubkey, None, &AddressParams::ELEMENTS),
+            ),
+            (
+                "p2wpkh",
+                PayoutAddressType::P2wpkh,
+                Address::p2wpkh(&btc_pubkey, None, &AddressParams::ELEMENTS),
+            ),
+        ] {
+            assert_eq!(address_type, PayoutAddressType::from_str(name).unwrap());
+            assert_eq!(name, address_type.to_string());
+            assert_eq!(format!(r#""{}""#, name), serde_json::to_string(&address_type).unwrap());
+            assert_eq!(address, address_type.address(&pubkey, &AddressParams::ELEMENTS));
+        }
+        assert!(PayoutAddressType::from_str("p2tr").is_err());
+        assert_eq!(PayoutAddressType::P2pkh, PayoutAddressType::default());
+    }
+
+    #[test]
+    fn bid_refund_test() {
+        setup_logger();
+        let lock = BidLock {
+            txid: sha256d::Hash::from_hex("1234567890000000000000000000000000000000000000000000000000000000").unwrap(),
+            vout: 0,
+            value: Amount::from_sat(1000),
+            asset: "CBT".to_owned(),
+            lock_height: 10,
+            pubkey: PublicKey::from_str("026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3").unwrap(),
+        };
+        assert!(!lock.is_expired(9));
+        assert!(lock.is_expired(10));
+        assert!(lock.is_expired(11));
+
+        for state in vec![BidRefundState::Exported, BidRefundState::Broadcast] {
+            assert_eq!(state, BidRefundState::from_str(state.as_str()).unwrap());
+            assert_eq!(format!("\"{}\"", state), serde_json::to_string(&state).unwrap());
+        }
+        assert!(BidRefundState::from_str("paid").is_err());
+        assert_eq!(BidRefundState::Exported, BidRefundState::default());
     }
 }
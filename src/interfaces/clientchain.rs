@@ -3,12 +3,20 @@
 //! Client chain interface and implementations
 
 use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use bitcoin::hashes::{hex::FromHex, sha256d};
+use bitcoin::hashes::{hex::FromHex, hex::ToHex, sha256d, Hash};
+use bitcoin::Amount;
 use ocean_rpc::{json, RpcApi};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::config::ClientChainConfig;
 use crate::error::{CError, Error, Result};
+use crate::interfaces::service::block_time;
 use crate::util::ocean::OceanClient;
 
 /// Method that returns the first unspent output for given asset
@@ -26,58 +34,474 @@ pub fn get_first_unspent(client: &OceanClient, asset: &str) -> Result<json::List
     Ok(unspent[0].clone())
 }
 
+/// Sign a raw transaction with the keys of a wallet, returning the signed
+/// transaction hex or an error if the wallet could not sign all inputs
+fn sign_raw_tx(client: &OceanClient, tx_hex: &str) -> Result<String> {
+    let signed: Value = client.call("signrawtransaction", &[Value::from(tx_hex)])?;
+    match (signed["complete"].as_bool(), signed["hex"].as_str()) {
+        (Some(true), Some(signed_hex)) => Ok(signed_hex.to_owned()),
+        _ => Err(Error::from(CError::Generic(
+            "transaction not signed by wallet".to_owned(),
+        ))),
+    }
+}
+
+/// Interval of polling the mempool while waiting for the previous challenge
+/// to confirm
+pub const CHALLENGE_PREFLIGHT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Policy of the pre-flight check run before broadcasting a challenge when the
+/// previous challenge is still unconfirmed or the unspent to be spent is
+/// already spent in the mempool, so that the challenge budget is not burnt on
+/// conflicting transactions
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChallengePreflight {
+    /// Wait up to a client chain block for the previous challenge to confirm,
+    /// skipping the challenge if it does not or the unspent is already spent
+    Wait,
+    /// Do not chain off an unconfirmed previous challenge and spend the first
+    /// challenge asset unspent that is not already spent in the mempool
+    Replace,
+    /// Skip the challenge
+    Skip,
+}
+
+impl ChallengePreflight {
+    /// Get the policy name as used in config
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChallengePreflight::Wait => "wait",
+            ChallengePreflight::Replace => "replace",
+            ChallengePreflight::Skip => "skip",
+        }
+    }
+}
+
+impl FromStr for ChallengePreflight {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ChallengePreflight> {
+        match s {
+            "wait" => Ok(ChallengePreflight::Wait),
+            "replace" => Ok(ChallengePreflight::Replace),
+            "skip" => Ok(ChallengePreflight::Skip),
+            _ => Err(Error::from(CError::Generic(format!(
+                "unknown challenge preflight policy: {}",
+                s
+            )))),
+        }
+    }
+}
+
+/// Policy of selecting the wallet that challenges are issued from when several
+/// challenge wallets are configured. Wallets whose challenge is skipped by the
+/// pre-flight check or fails are passed over for the next wallet in order, so
+/// that a stuck wallet or unconfirmed chain of challenges does not halt
+/// challenging
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChallengeWalletPolicy {
+    /// Issue each challenge from the wallet after the one that issued the
+    /// previous challenge
+    RoundRobin,
+    /// Issue challenges from the wallet that issued the previous challenge,
+    /// starting with the first wallet
+    Failover,
+}
+
+impl ChallengeWalletPolicy {
+    /// Get the policy name as used in config
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChallengeWalletPolicy::RoundRobin => "round-robin",
+            ChallengeWalletPolicy::Failover => "failover",
+        }
+    }
+
+    /// Get the order in which a number of wallets are tried for the next
+    /// challenge, given the index of the wallet that issued the previous one
+    pub fn order(&self, wallets: usize, last: usize) -> Vec<usize> {
+        let start = match self {
+            ChallengeWalletPolicy::RoundRobin => last + 1,
+            ChallengeWalletPolicy::Failover => last,
+        };
+        (0..wallets).map(|i| (start + i) % wallets).collect()
+    }
+}
+
+impl FromStr for ChallengeWalletPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ChallengeWalletPolicy> {
+        match s {
+            "round-robin" => Ok(ChallengeWalletPolicy::RoundRobin),
+            "failover" => Ok(ChallengeWalletPolicy::Failover),
+            _ => Err(Error::from(CError::Generic(format!(
+                "unknown challenge wallet policy: {}",
+                s
+            )))),
+        }
+    }
+}
+
+/// Amount tag of a challenge, paid by a second output of the challenge
+/// transaction so that challenges broadcast without OP_RETURN support can be
+/// matched to their sequence number in the request. Tags cycle through a
+/// configured number of distinct amounts of one or more satoshis
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ChallengeAmountTag {
+    /// Sequence number of the challenge in the request, starting at 0
+    pub sequence: u32,
+    /// Amount of the tag output in satoshis
+    pub amount: u64,
+}
+
+impl ChallengeAmountTag {
+    /// Get the tag of the challenge with a sequence number given the number of
+    /// distinct tag amounts, or None if challenges are not tagged
+    pub fn new(sequence: u32, amount_tags: u64) -> Option<ChallengeAmountTag> {
+        if amount_tags == 0 {
+            return None;
+        }
+        Some(ChallengeAmountTag {
+            sequence,
+            amount: 1 + sequence as u64 % amount_tags,
+        })
+    }
+}
+
+/// Challenge transaction broadcast to the client chain
+#[derive(Debug, Clone, PartialEq)]
+pub struct SentChallenge {
+    /// Challenge txid
+    pub txid: sha256d::Hash,
+    /// Amount tag of the challenge, if challenges are tagged
+    pub amount_tag: Option<ChallengeAmountTag>,
+    /// Name of the wallet the challenge was issued from, if challenge wallets
+    /// are configured
+    pub wallet: Option<String>,
+}
+
+/// Commitment to a challenge published to the client chain before the
+/// challenge transaction is broadcast, as an OP_RETURN output pushing the
+/// sha256d hash of the challenge txid in internal byte order. The commitment
+/// does not give away the challenge that proofs sign, while the revealed
+/// challenge can be checked against it by anyone
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ChallengeCommitment {
+    /// Commitment hash of the challenge txid
+    pub hash: sha256d::Hash,
+    /// Txid of the transaction publishing the commitment
+    pub txid: sha256d::Hash,
+}
+
+impl ChallengeCommitment {
+    /// Get the commitment hash of a challenge txid
+    pub fn commit(challenge: &sha256d::Hash) -> sha256d::Hash {
+        sha256d::Hash::hash(&challenge[..])
+    }
+
+    /// Check whether a challenge txid opens the commitment
+    pub fn opens(&self, challenge: &sha256d::Hash) -> bool {
+        ChallengeCommitment::commit(challenge) == self.hash
+    }
+}
+
+/// Challenge transaction signed but held back from the client chain until it
+/// is revealed, along with the commitment published to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommittedChallenge {
+    /// Challenge to be revealed
+    pub challenge: SentChallenge,
+    /// Commitment published to the challenge
+    pub commitment: ChallengeCommitment,
+    /// Signed challenge transaction hex, broadcast on reveal
+    pub tx_hex: String,
+}
+
+/// Challenge transaction in raw hex and decoded form as fetched from the client
+/// chain, allowing independent verification of broadcast challenges
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ChallengeTx {
+    /// Raw transaction hex
+    pub hex: String,
+    /// Decoded transaction
+    pub decoded: Value,
+}
+
+/// Challenge proof published by a guardnode on the client chain as an OP_RETURN
+/// output pushing the challenge txid followed by the bid txid, both in internal
+/// byte order
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PublishedProof {
+    /// Txid of the transaction publishing the proof
+    pub txid: sha256d::Hash,
+    /// Client chain height of the block including the transaction
+    pub height: u32,
+    /// Challenge txid
+    pub challenge: sha256d::Hash,
+    /// Bid txid
+    pub bid: sha256d::Hash,
+}
+
+impl PublishedProof {
+    /// Parse a published proof from the hex script of a transaction output,
+    /// returning None if the output is not a proof OP_RETURN output
+    pub fn from_script_hex(txid: sha256d::Hash, height: u32, script_hex: &str) -> Option<PublishedProof> {
+        let script = Vec::<u8>::from_hex(script_hex).ok()?;
+        // OP_RETURN followed by a single 64 byte push
+        if script.len() != 66 || script[0] != 0x6a || script[1] != 0x40 {
+            return None;
+        }
+        Some(PublishedProof {
+            txid,
+            height,
+            challenge: sha256d::Hash::from_slice(&script[2..34]).ok()?,
+            bid: sha256d::Hash::from_slice(&script[34..66]).ok()?,
+        })
+    }
+}
+
+/// Reservation of a challenge asset unspent by the challenge of a request
+/// round spending it
+#[derive(Debug, Clone, PartialEq)]
+pub struct UtxoReservation {
+    /// Request txid of the challenge
+    pub request: sha256d::Hash,
+    /// Sequence number of the challenge in the request
+    pub sequence: u32,
+    /// Txid of the challenge spending the unspent
+    pub challenge: sha256d::Hash,
+}
+
+/// Reservations of challenge asset unspents by outpoint. Unspents are reserved
+/// from when a challenge spending them is signed until the challenge confirms
+/// or fails, so that unspents of challenges not in the mempool yet, such as
+/// committed challenges held back until revealed, are not double spent by the
+/// challenges of other requests
+#[derive(Debug, Default)]
+pub struct UtxoReservations {
+    /// Reservations by unspent txid and vout
+    reservations: Mutex<HashMap<(sha256d::Hash, u32), UtxoReservation>>,
+}
+
+impl UtxoReservations {
+    /// Create an empty reservation table
+    pub fn new() -> UtxoReservations {
+        UtxoReservations::default()
+    }
+
+    /// Reserve an unspent for a challenge, returning false if the unspent is
+    /// already reserved by another challenge
+    pub fn reserve(&self, txid: sha256d::Hash, vout: u32, reservation: UtxoReservation) -> bool {
+        let mut reservations = self.reservations.lock().unwrap();
+        if let Some(reserved) = reservations.get(&(txid, vout)) {
+            if reserved.challenge != reservation.challenge {
+                return false;
+            }
+        }
+        let _ = reservations.insert((txid, vout), reservation);
+        true
+    }
+
+    /// Get the reservation of an unspent, if reserved
+    pub fn get(&self, txid: &sha256d::Hash, vout: u32) -> Option<UtxoReservation> {
+        self.reservations.lock().unwrap().get(&(*txid, vout)).cloned()
+    }
+
+    /// Check whether an unspent is reserved
+    pub fn is_reserved(&self, txid: &sha256d::Hash, vout: u32) -> bool {
+        self.get(txid, vout).is_some()
+    }
+
+    /// Release the unspents reserved by a challenge, returning the number of
+    /// unspents released
+    pub fn release_challenge(&self, challenge: &sha256d::Hash) -> usize {
+        let mut reservations = self.reservations.lock().unwrap();
+        let before = reservations.len();
+        reservations.retain(|_, reservation| reservation.challenge != *challenge);
+        before - reservations.len()
+    }
+
+    /// Release the unspents reserved by all the challenges of a request,
+    /// returning the number of unspents released
+    pub fn release_request(&self, request: &sha256d::Hash) -> usize {
+        let mut reservations = self.reservations.lock().unwrap();
+        let before = reservations.len();
+        reservations.retain(|_, reservation| reservation.request != *request);
+        before - reservations.len()
+    }
+
+    /// Get the number of reserved unspents
+    pub fn len(&self) -> usize {
+        self.reservations.lock().unwrap().len()
+    }
+
+    /// Check whether no unspents are reserved
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 /// ClientChain trait defining desired functionality for interfacing
 /// with the client chain when coordinating the guardnode service
 pub trait ClientChain {
-    /// Send challenge transaction to client chain
-    fn send_challenge(&self) -> Result<sha256d::Hash>;
-    /// Verify challenge transaction has been included in the chain
+    /// Send the challenge transaction with a sequence number in a request to
+    /// client chain, returning None if the challenge was not sent due to the
+    /// pre-flight check. The unspent spent by the challenge is reserved for
+    /// the request until the challenge is verified or released
+    fn send_challenge(&self, request: &sha256d::Hash, sequence: u32) -> Result<Option<SentChallenge>>;
+    /// Sign the challenge transaction with a sequence number in a request
+    /// without broadcasting it and publish a commitment to its txid to client
+    /// chain, returning None if the challenge was not signed due to the
+    /// pre-flight check. The unspent spent by the challenge is reserved for
+    /// the request until the challenge is verified or released
+    fn commit_challenge(&self, request: &sha256d::Hash, sequence: u32) -> Result<Option<CommittedChallenge>>;
+    /// Reveal a committed challenge by broadcasting its transaction to client
+    /// chain
+    fn reveal_challenge(&self, committed: &CommittedChallenge) -> Result<()>;
+    /// Verify challenge transaction has been included in the chain, releasing
+    /// the unspent reserved by the challenge once included
     fn verify_challenge(&self, txid: &sha256d::Hash) -> Result<bool>;
+    /// Release the unspent reserved by a challenge that failed, so that later
+    /// challenges can spend it unless it is spent in the mempool
+    fn release_challenge(&self, txid: &sha256d::Hash);
+    /// Release the unspents reserved by all the challenges of a request once
+    /// the request ends
+    fn release_request(&self, request: &sha256d::Hash);
     /// Get height of client chain
     fn get_blockheight(&self) -> Result<u32>;
+    /// Get the timestamp of the latest client chain block in unix seconds
+    fn get_block_time(&self) -> Result<u64>;
+    /// Get the hash of the client chain block at a height
+    fn get_block_hash(&self, height: u32) -> Result<sha256d::Hash>;
+    /// Get raw and decoded challenge transaction
+    fn get_challenge_tx(&self, txid: &sha256d::Hash) -> Result<ChallengeTx>;
+    /// Get challenge proofs published by guardnodes in a range of blocks
+    fn get_published_proofs(&self, start_height: u32, end_height: u32) -> Result<Vec<PublishedProof>>;
 }
 
-/// Rpc implementation of Service using an underlying ocean rpc connection
-pub struct RpcClientChain<'a> {
-    /// Rpc client instance
+/// Wallet that challenges are issued from
+struct ChallengeWallet {
+    /// Wallet name, None for the default wallet of the node
+    name: Option<String>,
+    /// Rpc client instance of the wallet
     client: OceanClient,
-    /// Challenge asset id
-    asset: &'a str,
+    /// Output of the last challenge sent from the wallet, spent by the next
+    /// challenge if challenges are chained
+    prev_challenge: Mutex<Option<json::ListUnspentResultEntry>>,
 }
 
-impl<'a> RpcClientChain<'a> {
-    /// Create an RpcClientChain with underlying rpc client connectivity
-    pub fn new(clientchain_config: &'a ClientChainConfig) -> Result<Self> {
-        let client = OceanClient::new(
-            clientchain_config.host.clone(),
-            Some(clientchain_config.user.clone()),
-            Some(clientchain_config.pass.clone()),
-        )?;
+impl ChallengeWallet {
+    /// Connect to a challenge wallet, importing the challenge asset key if the
+    /// wallet holds no challenge asset
+    fn new(clientchain_config: &ClientChainConfig, name: Option<String>, asset_key: &str) -> Result<ChallengeWallet> {
+        let host = match &name {
+            Some(name) => format!("{}/wallet/{}", clientchain_config.host, name),
+            None => clientchain_config.host.clone(),
+        };
+        let client = OceanClient::with_credentials(host, clientchain_config.rpc_credentials())?;
         // check we have funds for challenge asset
         match get_first_unspent(&client, &clientchain_config.asset) {
             // If this fails attempt to import the private key and then fetch the unspent again
             Err(_) => {
-                client.import_priv_key(&clientchain_config.asset_key, None, None)?;
+                client.import_priv_key(asset_key, None, None)?;
                 if let Err(e) = get_first_unspent(&client, &clientchain_config.asset) {
                     return Err(e);
                 }
             }
             _ => (),
         }
-
-        Ok(RpcClientChain {
+        Ok(ChallengeWallet {
+            name,
             client,
-            asset: &clientchain_config.asset,
+            prev_challenge: Mutex::new(None),
         })
     }
 }
 
-impl<'a> ClientChain for RpcClientChain<'a> {
-    /// Send challenge transaction to client chain
-    fn send_challenge(&self) -> Result<sha256d::Hash> {
-        // get any unspent for the challenge asset
-        let unspent = get_first_unspent(&self.client, self.asset)?;
+/// Rpc implementation of Service using an underlying ocean rpc connection
+pub struct RpcClientChain {
+    /// Rpc client instance
+    client: OceanClient,
+    /// Wallets that challenges are issued from
+    wallets: Vec<ChallengeWallet>,
+    /// Policy of selecting the wallet of each challenge
+    wallet_policy: ChallengeWalletPolicy,
+    /// Index of the wallet that issued the last challenge
+    last_wallet: Mutex<usize>,
+    /// Challenge asset id
+    asset: String,
+    /// Flag to chain challenges off the previous challenge output
+    chain_challenges: bool,
+    /// Pre-flight policy applied before broadcasting challenges
+    preflight: ChallengePreflight,
+    /// Max duration of waiting for the previous challenge to confirm
+    preflight_wait: Duration,
+    /// Number of distinct challenge amount tags, 0 if challenges are not
+    /// tagged
+    amount_tags: u64,
+    /// Challenge asset unspents reserved by the challenges of requests
+    reservations: UtxoReservations,
+}
 
+impl RpcClientChain {
+    /// Create an RpcClientChain with underlying rpc client connectivity.
+    /// Challenges are issued from the default wallet of the node unless
+    /// challenge wallets are configured, in which case wallets without funds
+    /// are warned about and only an error is returned if no wallet has funds
+    pub fn new(clientchain_config: &ClientChainConfig) -> Result<Self> {
+        let client =
+            OceanClient::with_credentials(clientchain_config.host.clone(), clientchain_config.rpc_credentials())?;
+        let mut wallets = vec![];
+        if clientchain_config.challenge_wallets.is_empty() {
+            wallets.push(ChallengeWallet::new(
+                clientchain_config,
+                None,
+                &clientchain_config.asset_key,
+            )?);
+        } else {
+            let mut last_err = None;
+            for wallet in clientchain_config.challenge_wallets.iter() {
+                let asset_key = wallet.asset_key.as_ref().unwrap_or(&clientchain_config.asset_key);
+                match ChallengeWallet::new(clientchain_config, Some(wallet.name.clone()), asset_key) {
+                    Ok(challenge_wallet) => wallets.push(challenge_wallet),
+                    Err(e) => {
+                        warn!("challenge wallet {} unavailable: {}", wallet.name, e);
+                        last_err = Some(e);
+                    }
+                }
+            }
+            if let (true, Some(e)) = (wallets.is_empty(), last_err) {
+                return Err(e);
+            }
+        }
+
+        Ok(RpcClientChain {
+            client,
+            wallets,
+            wallet_policy: ChallengeWalletPolicy::from_str(&clientchain_config.challenge_wallet_policy)?,
+            last_wallet: Mutex::new(0),
+            asset: clientchain_config.asset.clone(),
+            chain_challenges: clientchain_config.chain_challenges,
+            preflight: ChallengePreflight::from_str(&clientchain_config.challenge_preflight)?,
+            preflight_wait: Duration::from_secs(clientchain_config.block_time),
+            amount_tags: clientchain_config.challenge_amount_tags,
+            reservations: UtxoReservations::new(),
+        })
+    }
+
+    /// Send a challenge transaction spending the given unspent to the same
+    /// address, amount and asset. If the challenge is tagged, the tag amount
+    /// is paid to a new wallet address instead. The transaction is only
+    /// signed and not broadcast if the broadcast flag is not set. Returns the
+    /// txid, the signed transaction hex and the challenge output
+    fn send_challenge_from(
+        &self,
+        wallet: &ChallengeWallet,
+        unspent: &json::ListUnspentResultEntry,
+        amount_tag: Option<&ChallengeAmountTag>,
+        broadcast: bool,
+    ) -> Result<(sha256d::Hash, String, json::ListUnspentResultEntry)> {
         // construct the challenge transaction excluding fees
         // which are not required for policy transactions
         let utxos = vec![json::CreateRawTransactionInput {
@@ -86,31 +510,344 @@ impl<'a> ClientChain for RpcClientChain<'a> {
             sequence: None,
         }];
 
+        let mut amount = unspent.amount;
         let mut outs = HashMap::new();
-        let _ = outs.insert(unspent.address.to_string(), unspent.amount);
-
         let mut outs_assets = HashMap::new();
-        let _ = outs_assets.insert(unspent.address.to_string(), unspent.asset);
+        if let Some(tag) = amount_tag {
+            let tag_amount = Amount::from_sat(tag.amount);
+            if amount <= tag_amount {
+                return Err(Error::from(CError::Generic(format!(
+                    "unspent {}:{} does not cover challenge amount tag {}",
+                    unspent.txid, unspent.vout, tag.amount
+                ))));
+            }
+            amount = amount - tag_amount;
+            let tag_address: String = wallet.client.call("getnewaddress", &[])?;
+            let _ = outs.insert(tag_address.clone(), tag_amount);
+            let _ = outs_assets.insert(tag_address, unspent.asset.clone());
+        }
+        let _ = outs.insert(unspent.address.to_string(), amount);
+        let _ = outs_assets.insert(unspent.address.to_string(), unspent.asset.clone());
 
-        let tx_hex = self
+        let tx_hex = wallet
             .client
             .create_raw_transaction_hex(&utxos, &outs, Some(&outs_assets), None)?;
 
-        // sign the transaction and send via the client rpc
-        let tx_signed = self
-            .client
-            .sign_raw_transaction(&Vec::<u8>::from_hex(&tx_hex)? as &[u8], None, None, None)?;
+        // sign the transaction and send via the client rpc, or else only
+        // decode the txid of the signed transaction held back
+        let tx_signed = sign_raw_tx(&wallet.client, &tx_hex)?;
+        let txid = if broadcast {
+            wallet.client.send_raw_transaction(tx_signed.as_str())?
+        } else {
+            self.raw_txid(&tx_signed)?
+        };
+
+        // the challenge output pays the same address and asset as the unspent
+        // and is the only output unless the challenge is tagged
+        let mut output = unspent.clone();
+        output.txid = txid;
+        output.vout = 0;
+        output.amount = amount;
+        if amount_tag.is_some() {
+            output.vout = self.output_vout(&tx_signed, &unspent.address.to_string())?;
+        }
+        Ok((txid, tx_signed, output))
+    }
+
+    /// Get the txid of a raw transaction
+    fn raw_txid(&self, tx_hex: &str) -> Result<sha256d::Hash> {
+        let decoded: Value = self.client.call("decoderawtransaction", &[Value::from(tx_hex)])?;
+        Ok(sha256d::Hash::from_hex(decoded["txid"].as_str().unwrap_or(""))?)
+    }
+
+    /// Publish a commitment hash to client chain in the OP_RETURN output of a
+    /// transaction funded by the default wallet of the node, returning the
+    /// txid of the transaction
+    fn publish_commitment(&self, hash: &sha256d::Hash) -> Result<sha256d::Hash> {
+        let tx_hex: String = self.client.call(
+            "createrawtransaction",
+            &[serde_json::json!([]), serde_json::json!({ "data": hash[..].to_hex() })],
+        )?;
+        let funded: Value = self.client.call("fundrawtransaction", &[Value::from(tx_hex)])?;
+        let funded_hex = match funded["hex"].as_str() {
+            Some(funded_hex) => funded_hex,
+            None => {
+                return Err(Error::from(CError::Generic(
+                    "commitment transaction not funded by wallet".to_owned(),
+                )))
+            }
+        };
+        let tx_signed = sign_raw_tx(&self.client, funded_hex)?;
+        Ok(self.client.send_raw_transaction(tx_signed.as_str())?)
+    }
+
+    /// Get the index of the output of a raw transaction paying an address
+    fn output_vout(&self, tx_hex: &str, address: &str) -> Result<u32> {
+        let decoded: Value = self.client.call("decoderawtransaction", &[Value::from(tx_hex)])?;
+        for vout in decoded["vout"].as_array().into_iter().flatten() {
+            let pays_address = vout["scriptPubKey"]["addresses"]
+                .as_array()
+                .map_or(false, |addresses| addresses.iter().any(|a| a.as_str() == Some(address)));
+            if let (true, Some(n)) = (pays_address, vout["n"].as_u64()) {
+                return Ok(n as u32);
+            }
+        }
+        Err(Error::from(CError::Generic(format!(
+            "transaction has no output paying {}",
+            address
+        ))))
+    }
 
-        Ok(self.client.send_raw_transaction(&tx_signed.hex)?)
+    /// Check whether a transaction is still unconfirmed in the mempool
+    fn is_unconfirmed(&self, txid: &sha256d::Hash) -> bool {
+        // getmempoolentry fails for transactions that are not in the mempool
+        self.client
+            .call::<Value>("getmempoolentry", &[Value::from(txid.to_string())])
+            .is_ok()
     }
 
-    /// Verify challenge transaction has been included in the chain
+    /// Check whether an unspent is already spent by a transaction in the
+    /// mempool, possibly one not known to the wallet
+    fn is_spent_in_mempool(&self, unspent: &json::ListUnspentResultEntry) -> Result<bool> {
+        // gettxout including the mempool returns null for spent outputs
+        let txout: Value = self.client.call(
+            "gettxout",
+            &[
+                Value::from(unspent.txid.to_string()),
+                Value::from(unspent.vout),
+                Value::from(true),
+            ],
+        )?;
+        Ok(txout.is_null())
+    }
+
+    /// Pre-flight check of the previous challenge, returning whether the next
+    /// challenge can be sent and whether it can be chained off the previous one
+    fn preflight_prev_challenge(&self, prev: &json::ListUnspentResultEntry) -> (bool, bool) {
+        if !self.is_unconfirmed(&prev.txid) {
+            return (true, true);
+        }
+        match self.preflight {
+            ChallengePreflight::Wait => {
+                let start = Instant::now();
+                while start.elapsed() < self.preflight_wait {
+                    thread::sleep(CHALLENGE_PREFLIGHT_POLL_INTERVAL);
+                    if !self.is_unconfirmed(&prev.txid) {
+                        return (true, true);
+                    }
+                }
+                warn!("previous challenge {} still unconfirmed", prev.txid);
+                (false, false)
+            }
+            ChallengePreflight::Replace => {
+                warn!("previous challenge {} unconfirmed, not chaining off it", prev.txid);
+                (true, false)
+            }
+            ChallengePreflight::Skip => {
+                warn!("previous challenge {} unconfirmed", prev.txid);
+                (false, false)
+            }
+        }
+    }
+
+    /// Pre-flight check of the challenge asset unspents, returning the first
+    /// unspent not spent in the mempool if the policy allows replacing a spent
+    /// unspent or None if the first unspent is spent and it does not. Unspents
+    /// not covering the amount tag, such as the tag outputs of previous
+    /// challenges, and unspents reserved by other challenges are not spent
+    fn preflight_unspent(
+        &self,
+        wallet: &ChallengeWallet,
+        amount_tag: Option<&ChallengeAmountTag>,
+    ) -> Result<Option<json::ListUnspentResultEntry>> {
+        let min_amount = Amount::from_sat(amount_tag.map_or(0, |tag| tag.amount));
+        let unspents = wallet.client.list_unspent(None, None, None, None, Some(&self.asset))?;
+        for unspent in unspents.into_iter().filter(|unspent| {
+            unspent.amount > min_amount && !self.reservations.is_reserved(&unspent.txid, unspent.vout)
+        }) {
+            if !self.is_spent_in_mempool(&unspent)? {
+                return Ok(Some(unspent));
+            }
+            warn!("unspent {}:{} already spent in mempool", unspent.txid, unspent.vout);
+            if self.preflight != ChallengePreflight::Replace {
+                return Ok(None);
+            }
+        }
+        Err(Error::from(CError::MissingUnspent(
+            self.asset.clone(),
+            String::from("Client"),
+        )))
+    }
+
+    /// Send a challenge transaction from a wallet. If challenges are chained
+    /// the output of the previous challenge of the wallet is spent so that
+    /// each challenge is unique, falling back to the first unspent of the
+    /// challenge asset if there is no previous challenge or spending its
+    /// output fails. Before broadcasting, the previous challenge and the
+    /// unspent are checked for conflicts and the pre-flight policy is applied,
+    /// returning None if the challenge is skipped. The unspent spent is
+    /// reserved for the challenge of the request round. The challenge is only
+    /// signed and not broadcast if the broadcast flag is not set, returning the
+    /// txid along with the signed transaction hex
+    fn send_challenge_with(
+        &self,
+        wallet: &ChallengeWallet,
+        request: &sha256d::Hash,
+        sequence: u32,
+        amount_tag: Option<&ChallengeAmountTag>,
+        broadcast: bool,
+    ) -> Result<Option<(sha256d::Hash, String)>> {
+        let mut prev_challenge = wallet.prev_challenge.lock().unwrap();
+        if let Some(prev) = prev_challenge.take() {
+            let (send, chain) = self.preflight_prev_challenge(&prev);
+            if !send {
+                *prev_challenge = Some(prev);
+                return Ok(None);
+            }
+            if self.chain_challenges
+                && chain
+                && !self.reservations.is_reserved(&prev.txid, prev.vout)
+                && !self.is_spent_in_mempool(&prev)?
+            {
+                match self.send_challenge_from(wallet, &prev, amount_tag, broadcast) {
+                    Ok((txid, tx_hex, output)) => {
+                        self.reserve(&prev, request, sequence, txid);
+                        *prev_challenge = Some(output);
+                        return Ok(Some((txid, tx_hex)));
+                    }
+                    Err(e) => warn!("failed chaining challenge off {}:{}: {}", prev.txid, prev.vout, e),
+                }
+            }
+        }
+
+        // get any unspent for the challenge asset
+        let unspent = match self.preflight_unspent(wallet, amount_tag)? {
+            Some(unspent) => unspent,
+            None => return Ok(None),
+        };
+        let (txid, tx_hex, output) = self.send_challenge_from(wallet, &unspent, amount_tag, broadcast)?;
+        self.reserve(&unspent, request, sequence, txid);
+        *prev_challenge = Some(output);
+        Ok(Some((txid, tx_hex)))
+    }
+
+    /// Reserve an unspent spent by the challenge of a request round
+    fn reserve(
+        &self,
+        unspent: &json::ListUnspentResultEntry,
+        request: &sha256d::Hash,
+        sequence: u32,
+        challenge: sha256d::Hash,
+    ) {
+        let reservation = UtxoReservation {
+            request: *request,
+            sequence,
+            challenge,
+        };
+        if !self.reservations.reserve(unspent.txid, unspent.vout, reservation) {
+            warn!(
+                "unspent {}:{} already reserved by another challenge",
+                unspent.txid, unspent.vout
+            );
+        }
+    }
+
+    /// Send challenge transaction to client chain from the challenge wallets
+    /// in the order of the wallet policy, passing over wallets whose challenge
+    /// is skipped by the pre-flight check or fails. Returns None if the
+    /// challenge is skipped by any wallet and not sent by another, or else the
+    /// error of the last wallet. Challenges are tagged with the amount of
+    /// their sequence number if amount tags are configured. The challenge is
+    /// only signed and not broadcast if the broadcast flag is not set
+    fn issue_challenge(
+        &self,
+        request: &sha256d::Hash,
+        sequence: u32,
+        broadcast: bool,
+    ) -> Result<Option<(SentChallenge, String)>> {
+        let amount_tag = ChallengeAmountTag::new(sequence, self.amount_tags);
+        let mut last_wallet = self.last_wallet.lock().unwrap();
+        let mut skipped = false;
+        let mut last_err = None;
+        for index in self.wallet_policy.order(self.wallets.len(), *last_wallet) {
+            let wallet = &self.wallets[index];
+            let wallet_name = wallet.name.as_ref().map_or("default", |name| name.as_str());
+            match self.send_challenge_with(wallet, request, sequence, amount_tag.as_ref(), broadcast) {
+                Ok(Some((txid, tx_hex))) => {
+                    *last_wallet = index;
+                    return Ok(Some((
+                        SentChallenge {
+                            txid,
+                            amount_tag,
+                            wallet: wallet.name.clone(),
+                        },
+                        tx_hex,
+                    )));
+                }
+                Ok(None) => {
+                    warn!("challenge skipped by wallet {}", wallet_name);
+                    skipped = true;
+                }
+                Err(e) => {
+                    warn!("failed sending challenge from wallet {}: {}", wallet_name, e);
+                    last_err = Some(e);
+                }
+            }
+        }
+        if let (false, Some(e)) = (skipped, last_err) {
+            return Err(e);
+        }
+        Ok(None)
+    }
+}
+
+impl ClientChain for RpcClientChain {
+    /// Send challenge transaction to client chain from the challenge wallets
+    /// in the order of the wallet policy
+    fn send_challenge(&self, request: &sha256d::Hash, sequence: u32) -> Result<Option<SentChallenge>> {
+        Ok(self
+            .issue_challenge(request, sequence, true)?
+            .map(|(challenge, _)| challenge))
+    }
+
+    /// Sign challenge transaction from the challenge wallets in the order of
+    /// the wallet policy and publish the commitment to its txid, funded by the
+    /// default wallet of the node
+    fn commit_challenge(&self, request: &sha256d::Hash, sequence: u32) -> Result<Option<CommittedChallenge>> {
+        let (challenge, tx_hex) = match self.issue_challenge(request, sequence, false)? {
+            Some(issued) => issued,
+            None => return Ok(None),
+        };
+        let hash = ChallengeCommitment::commit(&challenge.txid);
+        let txid = self.publish_commitment(&hash)?;
+        Ok(Some(CommittedChallenge {
+            challenge,
+            commitment: ChallengeCommitment { hash, txid },
+            tx_hex,
+        }))
+    }
+
+    /// Broadcast the signed transaction of a committed challenge
+    fn reveal_challenge(&self, committed: &CommittedChallenge) -> Result<()> {
+        let txid = self.client.send_raw_transaction(committed.tx_hex.as_str())?;
+        if txid != committed.challenge.txid {
+            return Err(Error::from(CError::Generic(format!(
+                "revealed challenge {} does not match committed challenge {}",
+                txid, committed.challenge.txid
+            ))));
+        }
+        Ok(())
+    }
+
+    /// Verify challenge transaction has been included in the chain, releasing
+    /// the unspent reserved by the challenge as it is now spent on chain
     fn verify_challenge(&self, txid: &sha256d::Hash) -> Result<bool> {
         match self.client.get_raw_transaction_verbose(txid, None) {
             Ok(tx) => {
                 // check for blockhash and number of confirmations
                 if let (Some(_hash), Some(n_conf)) = (tx.blockhash, tx.confirmations) {
                     if n_conf > 0 {
+                        let _ = self.reservations.release_challenge(txid);
                         return Ok(true);
                     }
                 }
@@ -122,8 +859,179 @@ impl<'a> ClientChain for RpcClientChain<'a> {
         Ok(false)
     }
 
+    /// Release the unspent reserved by a failed challenge
+    fn release_challenge(&self, txid: &sha256d::Hash) {
+        if self.reservations.release_challenge(txid) > 0 {
+            info!("released unspent reserved by challenge {}", txid);
+        }
+    }
+
+    /// Release the unspents reserved by the challenges of a request
+    fn release_request(&self, request: &sha256d::Hash) {
+        let released = self.reservations.release_request(request);
+        if released > 0 {
+            info!("released {} unspents reserved by request {}", released, request);
+        }
+    }
+
     /// Return block count of chain
     fn get_blockheight(&self) -> Result<u32> {
         Ok(self.client.get_block_count()? as u32)
     }
+
+    /// Get the timestamp of the latest block of chain from its header
+    fn get_block_time(&self) -> Result<u64> {
+        block_time(&self.client)
+    }
+
+    /// Get the hash of the block at a height of chain
+    fn get_block_hash(&self, height: u32) -> Result<sha256d::Hash> {
+        Ok(self.client.get_block_hash(height.into())?)
+    }
+
+    /// Get raw challenge transaction and decode it via the client rpc
+    fn get_challenge_tx(&self, txid: &sha256d::Hash) -> Result<ChallengeTx> {
+        let hex: String = self
+            .client
+            .call("getrawtransaction", &[Value::from(txid.to_string())])?;
+        let decoded: Value = self.client.call("decoderawtransaction", &[Value::from(hex.clone())])?;
+        Ok(ChallengeTx { hex, decoded })
+    }
+
+    /// Scan the outputs of all block transactions in a range of heights for
+    /// proofs published by guardnodes
+    fn get_published_proofs(&self, start_height: u32, end_height: u32) -> Result<Vec<PublishedProof>> {
+        let mut proofs = vec![];
+        for height in start_height..=end_height {
+            let hash = self.client.get_block_hash(height.into())?;
+            // verbosity 2 includes the decoded block transactions
+            let block: Value = self
+                .client
+                .call("getblock", &[Value::from(hash.to_string()), Value::from(2)])?;
+            for tx in block["tx"].as_array().into_iter().flatten() {
+                let txid = match tx["txid"].as_str().map(sha256d::Hash::from_hex) {
+                    Some(Ok(txid)) => txid,
+                    _ => continue,
+                };
+                for vout in tx["vout"].as_array().into_iter().flatten() {
+                    if let Some(proof) = vout["scriptPubKey"]["hex"]
+                        .as_str()
+                        .and_then(|script_hex| PublishedProof::from_script_hex(txid, height, script_hex))
+                    {
+                        proofs.push(proof);
+                    }
+                }
+            }
+        }
+        Ok(proofs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::util::testing::gen_dummy_hash;
+
+    #[test]
+    fn challenge_wallet_policy_test() {
+        assert_eq!(
+            ChallengeWalletPolicy::RoundRobin,
+            ChallengeWalletPolicy::from_str("round-robin").unwrap()
+        );
+        assert_eq!("failover", ChallengeWalletPolicy::Failover.as_str());
+        assert!(ChallengeWalletPolicy::from_str("random").is_err());
+
+        assert_eq!(vec![2, 0, 1], ChallengeWalletPolicy::RoundRobin.order(3, 1));
+        assert_eq!(vec![0, 1, 2], ChallengeWalletPolicy::RoundRobin.order(3, 2));
+        assert_eq!(vec![1, 2, 0], ChallengeWalletPolicy::Failover.order(3, 1));
+        assert_eq!(vec![0], ChallengeWalletPolicy::Failover.order(1, 0));
+        assert_eq!(vec![0], ChallengeWalletPolicy::RoundRobin.order(1, 0));
+    }
+
+    #[test]
+    fn challenge_amount_tag_test() {
+        assert_eq!(None, ChallengeAmountTag::new(5, 0));
+        let amounts: Vec<u64> = (0..5)
+            .map(|sequence| ChallengeAmountTag::new(sequence, 3).unwrap().amount)
+            .collect();
+        assert_eq!(vec![1, 2, 3, 1, 2], amounts);
+        assert_eq!(4, ChallengeAmountTag::new(4, 3).unwrap().sequence);
+    }
+
+    #[test]
+    fn published_proof_from_script_hex_test() {
+        let txid = gen_dummy_hash(1);
+        let script_hex = format!("6a40{}{}", "02".repeat(32), "03".repeat(32));
+        let proof = PublishedProof::from_script_hex(txid, 5, &script_hex).unwrap();
+        assert_eq!(
+            PublishedProof {
+                txid,
+                height: 5,
+                challenge: gen_dummy_hash(2),
+                bid: gen_dummy_hash(3),
+            },
+            proof
+        );
+
+        // other outputs
+        assert!(PublishedProof::from_script_hex(txid, 5, &script_hex[..130]).is_none());
+        assert!(PublishedProof::from_script_hex(txid, 5, &format!("6a3f{}", &script_hex[4..])).is_none());
+        assert!(PublishedProof::from_script_hex(txid, 5, "76a914").is_none());
+        assert!(PublishedProof::from_script_hex(txid, 5, "zz").is_none());
+    }
+
+    #[test]
+    fn challenge_preflight_from_str_test() {
+        for policy in vec![
+            ChallengePreflight::Wait,
+            ChallengePreflight::Replace,
+            ChallengePreflight::Skip,
+        ] {
+            assert_eq!(policy, ChallengePreflight::from_str(policy.as_str()).unwrap());
+        }
+        assert!(ChallengePreflight::from_str("abandon").is_err());
+    }
+
+    #[test]
+    fn challenge_commitment_test() {
+        let challenge = gen_dummy_hash(2);
+        let commitment = ChallengeCommitment {
+            hash: ChallengeCommitment::commit(&challenge),
+            txid: gen_dummy_hash(3),
+        };
+        assert_eq!(sha256d::Hash::hash(&[2; 32]), commitment.hash);
+        assert!(commitment.opens(&challenge));
+        assert!(!commitment.opens(&gen_dummy_hash(4)));
+        // the commitment does not give away the challenge
+        assert_ne!(challenge, commitment.hash);
+    }
+
+    #[test]
+    fn utxo_reservations_test() {
+        let reservations = UtxoReservations::new();
+        let reservation = |request: u8, sequence: u32, challenge: u8| UtxoReservation {
+            request: gen_dummy_hash(request),
+            sequence,
+            challenge: gen_dummy_hash(challenge),
+        };
+        assert!(reservations.is_empty());
+
+        // unspents reserved by one challenge at a time
+        assert!(reservations.reserve(gen_dummy_hash(10), 0, reservation(1, 0, 20)));
+        assert!(reservations.reserve(gen_dummy_hash(10), 0, reservation(1, 0, 20)));
+        assert!(!reservations.reserve(gen_dummy_hash(10), 0, reservation(2, 0, 21)));
+        assert_eq!(Some(reservation(1, 0, 20)), reservations.get(&gen_dummy_hash(10), 0));
+        assert!(!reservations.is_reserved(&gen_dummy_hash(10), 1));
+        assert!(reservations.reserve(gen_dummy_hash(10), 1, reservation(2, 0, 21)));
+        assert!(reservations.reserve(gen_dummy_hash(11), 0, reservation(1, 1, 22)));
+        assert_eq!(3, reservations.len());
+
+        // released by challenge and by request
+        assert_eq!(1, reservations.release_challenge(&gen_dummy_hash(21)));
+        assert_eq!(0, reservations.release_challenge(&gen_dummy_hash(21)));
+        assert!(!reservations.is_reserved(&gen_dummy_hash(10), 1));
+        assert_eq!(2, reservations.release_request(&gen_dummy_hash(1)));
+        assert!(reservations.is_empty());
+    }
 }
@@ -0,0 +1,693 @@
+//! # Faults
+//!
+//! Fault injection wrappers of the Service, ClientChain and Storage interfaces
+//! for exercising the resilience of the challenger and payments loops. Only
+//! available in tests or with the fault-injection feature enabled
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use bitcoin::hashes::sha256d;
+
+use crate::coverage::ChallengeCoverage;
+use crate::dead_letter::DeadLetter;
+use crate::error::{CError, Result};
+use crate::interfaces::clientchain::{ChallengeTx, ClientChain, CommittedChallenge, PublishedProof, SentChallenge};
+use crate::interfaces::response::{
+    BidProof, ChallengeLatency, ChallengeRecord, ChallengeStats, Response, ResponseReconciliation, ResponseSnapshot,
+    ResponseSummary,
+};
+use crate::interfaces::service::Service;
+use crate::interfaces::storage::Storage;
+use crate::interfaces::{
+    bid::{Bid, BidBlacklisting, BidKeyRotation, BidLock, BidRefund, BidSet, PaymentLiability},
+    request::{FeePool, Request, RequestAnnotation, RequestCursor, RequestDeposit, RequestFull, RequestRejection},
+};
+use crate::stall::ClientChainStall;
+
+/// Fault injection config
+#[derive(Debug, Clone)]
+pub struct FaultConfig {
+    /// Percentage of calls that fail with an injected error
+    pub fail_percent: u32,
+    /// Latency added to every call
+    pub latency: Duration,
+    /// Percentage of blockheight calls that return a stale height
+    pub stale_percent: u32,
+    /// Number of blocks that stale heights lag behind the actual height
+    pub stale_blocks: u64,
+    /// Seed of the pseudo random faults so that runs can be reproduced
+    pub seed: u64,
+}
+
+impl Default for FaultConfig {
+    fn default() -> FaultConfig {
+        FaultConfig {
+            fail_percent: 0,
+            latency: Duration::from_millis(0),
+            stale_percent: 0,
+            stale_blocks: 1,
+            seed: 1,
+        }
+    }
+}
+
+/// Fault injector deciding which calls fail or return stale heights from a
+/// seeded pseudo random sequence
+pub struct FaultInjector {
+    /// Fault injection config
+    config: FaultConfig,
+    /// Xorshift pseudo random generator state
+    state: Mutex<u64>,
+}
+
+impl FaultInjector {
+    /// Return new FaultInjector from the fault injection config
+    pub fn new(config: FaultConfig) -> FaultInjector {
+        FaultInjector {
+            state: Mutex::new(config.seed.max(1)),
+            config,
+        }
+    }
+
+    /// Roll the pseudo random generator returning true for the given
+    /// percentage of rolls
+    fn roll(&self, percent: u32) -> bool {
+        if percent == 0 {
+            return false;
+        }
+        let mut state = self.state.lock().unwrap();
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state % 100 < percent as u64
+    }
+
+    /// Inject latency to a call and fail it with an injected error for the
+    /// configured percentage of calls
+    pub fn inject(&self, call: &str) -> Result<()> {
+        if self.config.latency > Duration::from_millis(0) {
+            thread::sleep(self.config.latency);
+        }
+        if self.roll(self.config.fail_percent) {
+            warn!("injected fault: {}", call);
+            return Err(CError::Generic(format!("injected fault: {}", call)).into());
+        }
+        Ok(())
+    }
+
+    /// Return a stale height lagging behind the actual height for the
+    /// configured percentage of calls
+    pub fn height(&self, height: u64) -> u64 {
+        if self.roll(self.config.stale_percent) {
+            return height.saturating_sub(self.config.stale_blocks);
+        }
+        height
+    }
+}
+
+/// Service wrapper injecting faults into the calls of the wrapped service
+pub struct FaultyService<T: Service> {
+    /// Wrapped service
+    pub inner: T,
+    /// Fault injector
+    pub faults: FaultInjector,
+}
+
+impl<T: Service> FaultyService<T> {
+    /// Return new FaultyService wrapping a service
+    pub fn new(inner: T, config: FaultConfig) -> FaultyService<T> {
+        FaultyService {
+            inner,
+            faults: FaultInjector::new(config),
+        }
+    }
+}
+
+impl<T: Service> Service for FaultyService<T> {
+    fn get_requests(&self) -> Result<Option<Vec<Request>>> {
+        self.faults.inject("service get_requests")?;
+        self.inner.get_requests()
+    }
+
+    fn get_request(&self, hash: &sha256d::Hash) -> Result<Option<Request>> {
+        self.faults.inject("service get_request")?;
+        self.inner.get_request(hash)
+    }
+
+    fn get_request_bids(&self, hash: &sha256d::Hash) -> Result<Option<BidSet>> {
+        self.faults.inject("service get_request_bids")?;
+        self.inner.get_request_bids(hash)
+    }
+
+    fn get_blockheight(&self) -> Result<u64> {
+        self.faults.inject("service get_blockheight")?;
+        Ok(self.faults.height(self.inner.get_blockheight()?))
+    }
+
+    fn get_block_time(&self) -> Result<u64> {
+        self.faults.inject("service get_block_time")?;
+        self.inner.get_block_time()
+    }
+
+    fn get_request_deposit(&self, request: &Request) -> Result<Option<RequestDeposit>> {
+        self.faults.inject("service get_request_deposit")?;
+        self.inner.get_request_deposit(request)
+    }
+
+    fn get_bid_lock(&self, txid: &sha256d::Hash) -> Result<Option<BidLock>> {
+        self.faults.inject("service get_bid_lock")?;
+        self.inner.get_bid_lock(txid)
+    }
+}
+
+/// ClientChain wrapper injecting faults into the calls of the wrapped client
+/// chain
+pub struct FaultyClientChain<K: ClientChain> {
+    /// Wrapped client chain
+    pub inner: K,
+    /// Fault injector
+    pub faults: FaultInjector,
+}
+
+impl<K: ClientChain> FaultyClientChain<K> {
+    /// Return new FaultyClientChain wrapping a client chain
+    pub fn new(inner: K, config: FaultConfig) -> FaultyClientChain<K> {
+        FaultyClientChain {
+            inner,
+            faults: FaultInjector::new(config),
+        }
+    }
+}
+
+impl<K: ClientChain> ClientChain for FaultyClientChain<K> {
+    fn send_challenge(&self, request: &sha256d::Hash, sequence: u32) -> Result<Option<SentChallenge>> {
+        self.faults.inject("clientchain send_challenge")?;
+        self.inner.send_challenge(request, sequence)
+    }
+
+    fn commit_challenge(&self, request: &sha256d::Hash, sequence: u32) -> Result<Option<CommittedChallenge>> {
+        self.faults.inject("clientchain commit_challenge")?;
+        self.inner.commit_challenge(request, sequence)
+    }
+
+    fn reveal_challenge(&self, committed: &CommittedChallenge) -> Result<()> {
+        self.faults.inject("clientchain reveal_challenge")?;
+        self.inner.reveal_challenge(committed)
+    }
+
+    fn verify_challenge(&self, txid: &sha256d::Hash) -> Result<bool> {
+        self.faults.inject("clientchain verify_challenge")?;
+        self.inner.verify_challenge(txid)
+    }
+
+    fn release_challenge(&self, txid: &sha256d::Hash) {
+        self.inner.release_challenge(txid)
+    }
+
+    fn release_request(&self, request: &sha256d::Hash) {
+        self.inner.release_request(request)
+    }
+
+    fn get_blockheight(&self) -> Result<u32> {
+        self.faults.inject("clientchain get_blockheight")?;
+        Ok(self.faults.height(self.inner.get_blockheight()? as u64) as u32)
+    }
+
+    fn get_block_time(&self) -> Result<u64> {
+        self.faults.inject("clientchain get_block_time")?;
+        self.inner.get_block_time()
+    }
+
+    fn get_block_hash(&self, height: u32) -> Result<sha256d::Hash> {
+        self.faults.inject("clientchain get_block_hash")?;
+        self.inner.get_block_hash(height)
+    }
+
+    fn get_challenge_tx(&self, txid: &sha256d::Hash) -> Result<ChallengeTx> {
+        self.faults.inject("clientchain get_challenge_tx")?;
+        self.inner.get_challenge_tx(txid)
+    }
+
+    fn get_published_proofs(&self, start_height: u32, end_height: u32) -> Result<Vec<PublishedProof>> {
+        self.faults.inject("clientchain get_published_proofs")?;
+        self.inner.get_published_proofs(start_height, end_height)
+    }
+}
+
+/// Storage wrapper injecting faults into the calls of the wrapped storage
+pub struct FaultyStorage<D: Storage> {
+    /// Wrapped storage
+    pub inner: D,
+    /// Fault injector
+    pub faults: FaultInjector,
+}
+
+impl<D: Storage> FaultyStorage<D> {
+    /// Return new FaultyStorage wrapping a storage
+    pub fn new(inner: D, config: FaultConfig) -> FaultyStorage<D> {
+        FaultyStorage {
+            inner,
+            faults: FaultInjector::new(config),
+        }
+    }
+}
+
+impl<D: Storage> Storage for FaultyStorage<D> {
+    fn save_challenge_request_state(&self, request: &Request, bids: &BidSet) -> Result<()> {
+        self.faults.inject("storage save_challenge_request_state")?;
+        self.inner.save_challenge_request_state(request, bids)
+    }
+
+    fn update_request(&self, request: &Request) -> Result<()> {
+        self.faults.inject("storage update_request")?;
+        self.inner.update_request(request)
+    }
+
+    fn update_bid(&self, request_hash: sha256d::Hash, bid: &Bid) -> Result<()> {
+        self.faults.inject("storage update_bid")?;
+        self.inner.update_bid(request_hash, bid)
+    }
+
+    fn update_bids(&self, request_hash: sha256d::Hash, bids: &[Bid]) -> Result<()> {
+        self.faults.inject("storage update_bids")?;
+        self.inner.update_bids(request_hash, bids)
+    }
+
+    fn remove_bids(&self, request_hash: sha256d::Hash, bids: &[sha256d::Hash]) -> Result<()> {
+        self.faults.inject("storage remove_bids")?;
+        self.inner.remove_bids(request_hash, bids)
+    }
+
+    fn save_response(&self, request_hash: sha256d::Hash, response: &Response) -> Result<()> {
+        self.faults.inject("storage save_response")?;
+        self.inner.save_response(request_hash, response)
+    }
+
+    fn update_response(
+        &self,
+        request_hash: sha256d::Hash,
+        challenge: sha256d::Hash,
+        responses: &HashSet<sha256d::Hash>,
+        response: &Response,
+    ) -> Result<()> {
+        self.faults.inject("storage update_response")?;
+        self.inner.update_response(request_hash, challenge, responses, response)
+    }
+
+    fn save_challenge_record(&self, request_hash: sha256d::Hash, record: &ChallengeRecord) -> Result<()> {
+        self.faults.inject("storage save_challenge_record")?;
+        self.inner.save_challenge_record(request_hash, record)
+    }
+
+    fn get_challenge_records(&self, request_hash: sha256d::Hash) -> Result<Vec<ChallengeRecord>> {
+        self.faults.inject("storage get_challenge_records")?;
+        self.inner.get_challenge_records(request_hash)
+    }
+
+    fn save_challenge_stats(&self, request_hash: sha256d::Hash, stats: &ChallengeStats) -> Result<()> {
+        self.faults.inject("storage save_challenge_stats")?;
+        self.inner.save_challenge_stats(request_hash, stats)
+    }
+
+    fn get_challenge_stats(&self, request_hash: sha256d::Hash) -> Result<Vec<ChallengeStats>> {
+        self.faults.inject("storage get_challenge_stats")?;
+        self.inner.get_challenge_stats(request_hash)
+    }
+
+    fn save_bid_proofs(&self, request_hash: sha256d::Hash, proofs: &[BidProof]) -> Result<()> {
+        self.faults.inject("storage save_bid_proofs")?;
+        self.inner.save_bid_proofs(request_hash, proofs)
+    }
+
+    fn get_bid_proofs(&self, request_hash: sha256d::Hash, bid_hash: sha256d::Hash) -> Result<Vec<BidProof>> {
+        self.faults.inject("storage get_bid_proofs")?;
+        self.inner.get_bid_proofs(request_hash, bid_hash)
+    }
+
+    fn get_response(&self, request_hash: sha256d::Hash) -> Result<Option<Response>> {
+        self.faults.inject("storage get_response")?;
+        self.inner.get_response(request_hash)
+    }
+
+    fn get_response_hashes(&self, request_hash: sha256d::Hash) -> Result<Vec<sha256d::Hash>> {
+        self.faults.inject("storage get_response_hashes")?;
+        self.inner.get_response_hashes(request_hash)
+    }
+
+    fn compact_response(&self, request_hash: sha256d::Hash, summary: &ResponseSummary) -> Result<()> {
+        self.faults.inject("storage compact_response")?;
+        self.inner.compact_response(request_hash, summary)
+    }
+
+    fn save_response_snapshot(&self, request_hash: sha256d::Hash, snapshot: &ResponseSnapshot) -> Result<()> {
+        self.faults.inject("storage save_response_snapshot")?;
+        self.inner.save_response_snapshot(request_hash, snapshot)
+    }
+
+    fn get_response_snapshots(&self, request_hash: sha256d::Hash) -> Result<Vec<ResponseSnapshot>> {
+        self.faults.inject("storage get_response_snapshots")?;
+        self.inner.get_response_snapshots(request_hash)
+    }
+
+    fn save_challenge_latency(&self, request_hash: sha256d::Hash, latency: &ChallengeLatency) -> Result<()> {
+        self.faults.inject("storage save_challenge_latency")?;
+        self.inner.save_challenge_latency(request_hash, latency)
+    }
+
+    fn get_challenge_latency(&self, request_hash: sha256d::Hash) -> Result<Option<ChallengeLatency>> {
+        self.faults.inject("storage get_challenge_latency")?;
+        self.inner.get_challenge_latency(request_hash)
+    }
+
+    fn save_challenge_coverage(&self, request_hash: sha256d::Hash, coverage: &ChallengeCoverage) -> Result<()> {
+        self.faults.inject("storage save_challenge_coverage")?;
+        self.inner.save_challenge_coverage(request_hash, coverage)
+    }
+
+    fn get_challenge_coverage(&self, request_hash: sha256d::Hash) -> Result<Option<ChallengeCoverage>> {
+        self.faults.inject("storage get_challenge_coverage")?;
+        self.inner.get_challenge_coverage(request_hash)
+    }
+
+    fn save_clientchain_stall(&self, request_hash: sha256d::Hash, stall: &ClientChainStall) -> Result<()> {
+        self.faults.inject("storage save_clientchain_stall")?;
+        self.inner.save_clientchain_stall(request_hash, stall)
+    }
+
+    fn get_clientchain_stalls(&self, request_hash: sha256d::Hash) -> Result<Vec<ClientChainStall>> {
+        self.faults.inject("storage get_clientchain_stalls")?;
+        self.inner.get_clientchain_stalls(request_hash)
+    }
+
+    fn save_response_reconciliation(
+        &self,
+        request_hash: sha256d::Hash,
+        reconciliation: &ResponseReconciliation,
+    ) -> Result<()> {
+        self.faults.inject("storage save_response_reconciliation")?;
+        self.inner.save_response_reconciliation(request_hash, reconciliation)
+    }
+
+    fn get_response_reconciliation(&self, request_hash: sha256d::Hash) -> Result<Option<ResponseReconciliation>> {
+        self.faults.inject("storage get_response_reconciliation")?;
+        self.inner.get_response_reconciliation(request_hash)
+    }
+
+    fn save_fee_pool(&self, request_hash: sha256d::Hash, pool: &FeePool) -> Result<()> {
+        self.faults.inject("storage save_fee_pool")?;
+        self.inner.save_fee_pool(request_hash, pool)
+    }
+
+    fn get_fee_pool(&self, request_hash: sha256d::Hash) -> Result<Option<FeePool>> {
+        self.faults.inject("storage get_fee_pool")?;
+        self.inner.get_fee_pool(request_hash)
+    }
+
+    fn get_bids(&self, request_hash: sha256d::Hash) -> Result<Vec<Bid>> {
+        self.faults.inject("storage get_bids")?;
+        self.inner.get_bids(request_hash)
+    }
+
+    fn get_bid(&self, request_hash: sha256d::Hash, bid_hash: sha256d::Hash) -> Result<Option<Bid>> {
+        self.faults.inject("storage get_bid")?;
+        self.inner.get_bid(request_hash, bid_hash)
+    }
+
+    fn get_requests(
+        &self,
+        complete: Option<bool>,
+        genesis: Option<sha256d::Hash>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<Request>> {
+        self.faults.inject("storage get_requests")?;
+        self.inner.get_requests(complete, genesis, limit, skip)
+    }
+
+    fn get_requests_full(
+        &self,
+        genesis: Option<sha256d::Hash>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<RequestFull>> {
+        self.faults.inject("storage get_requests_full")?;
+        self.inner.get_requests_full(genesis, limit, skip)
+    }
+
+    fn get_requests_page(
+        &self,
+        genesis: Option<sha256d::Hash>,
+        after: Option<&RequestCursor>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<Request>> {
+        self.faults.inject("storage get_requests_page")?;
+        self.inner.get_requests_page(genesis, after, limit, skip)
+    }
+
+    fn get_requests_count(&self, genesis: Option<sha256d::Hash>) -> Result<i64> {
+        self.faults.inject("storage get_requests_count")?;
+        self.inner.get_requests_count(genesis)
+    }
+
+    fn get_requests_recent(
+        &self,
+        genesis: Option<sha256d::Hash>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<Request>> {
+        self.faults.inject("storage get_requests_recent")?;
+        self.inner.get_requests_recent(genesis, limit, skip)
+    }
+
+    fn get_request(&self, request_hash: sha256d::Hash) -> Result<Option<Request>> {
+        self.faults.inject("storage get_request")?;
+        self.inner.get_request(request_hash)
+    }
+
+    fn save_request_logs(&self, request_hash: sha256d::Hash, logs: &[String]) -> Result<()> {
+        self.faults.inject("storage save_request_logs")?;
+        self.inner.save_request_logs(request_hash, logs)
+    }
+
+    fn get_request_logs(&self, request_hash: sha256d::Hash) -> Result<Vec<String>> {
+        self.faults.inject("storage get_request_logs")?;
+        self.inner.get_request_logs(request_hash)
+    }
+
+    fn save_request_deposit(&self, deposit: &RequestDeposit) -> Result<()> {
+        self.faults.inject("storage save_request_deposit")?;
+        self.inner.save_request_deposit(deposit)
+    }
+
+    fn get_request_deposits(
+        &self,
+        verified: Option<bool>,
+        genesis: Option<sha256d::Hash>,
+    ) -> Result<Vec<RequestDeposit>> {
+        self.faults.inject("storage get_request_deposits")?;
+        self.inner.get_request_deposits(verified, genesis)
+    }
+
+    fn save_request_rejection(&self, rejection: &RequestRejection) -> Result<()> {
+        self.faults.inject("storage save_request_rejection")?;
+        self.inner.save_request_rejection(rejection)
+    }
+
+    fn get_request_rejections(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<RequestRejection>> {
+        self.faults.inject("storage get_request_rejections")?;
+        self.inner.get_request_rejections(genesis)
+    }
+
+    fn save_request_annotation(&self, request_hash: sha256d::Hash, annotation: &RequestAnnotation) -> Result<()> {
+        self.faults.inject("storage save_request_annotation")?;
+        self.inner.save_request_annotation(request_hash, annotation)
+    }
+
+    fn get_request_annotation(&self, request_hash: sha256d::Hash) -> Result<Option<RequestAnnotation>> {
+        self.faults.inject("storage get_request_annotation")?;
+        self.inner.get_request_annotation(request_hash)
+    }
+
+    fn get_labelled_requests(&self, label: &str) -> Result<Vec<sha256d::Hash>> {
+        self.faults.inject("storage get_labelled_requests")?;
+        self.inner.get_labelled_requests(label)
+    }
+
+    fn save_dead_letter(&self, letter: &DeadLetter) -> Result<()> {
+        self.faults.inject("storage save_dead_letter")?;
+        self.inner.save_dead_letter(letter)
+    }
+
+    fn get_dead_letters(&self, limit: u64) -> Result<Vec<DeadLetter>> {
+        self.faults.inject("storage get_dead_letters")?;
+        self.inner.get_dead_letters(limit)
+    }
+
+    fn save_bid_blacklisting(&self, blacklisting: &BidBlacklisting) -> Result<()> {
+        self.faults.inject("storage save_bid_blacklisting")?;
+        self.inner.save_bid_blacklisting(blacklisting)
+    }
+
+    fn get_bid_blacklistings(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<BidBlacklisting>> {
+        self.faults.inject("storage get_bid_blacklistings")?;
+        self.inner.get_bid_blacklistings(genesis)
+    }
+
+    fn save_bid_key_rotation(&self, rotation: &BidKeyRotation) -> Result<()> {
+        self.faults.inject("storage save_bid_key_rotation")?;
+        self.inner.save_bid_key_rotation(rotation)
+    }
+
+    fn get_bid_key_rotations(&self, request_hash: sha256d::Hash) -> Result<Vec<BidKeyRotation>> {
+        self.faults.inject("storage get_bid_key_rotations")?;
+        self.inner.get_bid_key_rotations(request_hash)
+    }
+
+    fn save_bid_refund(&self, refund: &BidRefund) -> Result<()> {
+        self.faults.inject("storage save_bid_refund")?;
+        self.inner.save_bid_refund(refund)
+    }
+
+    fn get_bid_refunds(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<BidRefund>> {
+        self.faults.inject("storage get_bid_refunds")?;
+        self.inner.get_bid_refunds(genesis)
+    }
+
+    fn save_payment_liability(&self, liability: &PaymentLiability) -> Result<()> {
+        self.faults.inject("storage save_payment_liability")?;
+        self.inner.save_payment_liability(liability)
+    }
+
+    fn remove_payment_liability(&self, request_hash: sha256d::Hash, epoch: Option<u32>) -> Result<()> {
+        self.faults.inject("storage remove_payment_liability")?;
+        self.inner.remove_payment_liability(request_hash, epoch)
+    }
+
+    fn get_payment_liabilities(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<PaymentLiability>> {
+        self.faults.inject("storage get_payment_liabilities")?;
+        self.inner.get_payment_liabilities(genesis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::AtomicBool;
+    use std::sync::{mpsc::channel, Arc, RwLock};
+    use std::time::Instant;
+
+    use crate::challenger::{run_challenge_request, ChallengeRequestSettings, ChallengerContext};
+    use crate::clock::SystemClock;
+    use crate::config::ChallengeTiming;
+    use crate::interfaces::mocks::clientchain::MockClientChain;
+    use crate::interfaces::mocks::service::MockService;
+    use crate::interfaces::mocks::storage::MockStorage;
+    use crate::journal::Journal;
+    use crate::stall::StallDetector;
+    use crate::util::testing::{gen_challenge_state, gen_dummy_hash, setup_logger};
+    use crate::watchdog::Heartbeat;
+
+    #[test]
+    fn fault_injector_test() {
+        setup_logger();
+        // no faults
+        let faults = FaultInjector::new(FaultConfig::default());
+        for _ in 0..100 {
+            assert!(faults.inject("call").is_ok());
+            assert_eq!(10, faults.height(10));
+        }
+
+        // all calls faulty
+        let mut config = FaultConfig::default();
+        config.fail_percent = 100;
+        config.stale_percent = 100;
+        config.stale_blocks = 3;
+        let faults = FaultInjector::new(config.clone());
+        assert!(faults
+            .inject("call")
+            .unwrap_err()
+            .to_string()
+            .contains("injected fault: call"));
+        assert_eq!(7, faults.height(10));
+        assert_eq!(0, faults.height(2));
+
+        // some calls faulty and reproducible with the same seed
+        config.fail_percent = 50;
+        let run = |config: &FaultConfig| {
+            let faults = FaultInjector::new(config.clone());
+            (0..100).map(|_| faults.inject("call").is_err()).collect::<Vec<bool>>()
+        };
+        let fails = run(&config);
+        let num_fails = fails.iter().filter(|fail| **fail).count();
+        assert!(num_fails > 20 && num_fails < 80);
+        assert_eq!(fails, run(&config));
+        config.seed = 2;
+        assert!(fails != run(&config));
+
+        // latency
+        config.fail_percent = 0;
+        config.latency = Duration::from_millis(10);
+        let faults = FaultInjector::new(config);
+        let now = Instant::now();
+        assert!(faults.inject("call").is_ok());
+        assert!(now.elapsed() >= Duration::from_millis(10));
+    }
+
+    #[test]
+    fn faulty_interfaces_test() {
+        setup_logger();
+        let mut config = FaultConfig::default();
+        config.stale_percent = 100;
+        let service = FaultyService::new(MockService::new(), config.clone());
+        let height = *service.inner.height.borrow();
+        assert_eq!(height - 1, service.get_blockheight().unwrap());
+        assert!(service.get_requests().is_ok());
+
+        config.fail_percent = 100;
+        let service = FaultyService::new(MockService::new(), config.clone());
+        assert!(service.get_requests().is_err());
+        assert!(service.get_blockheight().is_err());
+        let clientchain = FaultyClientChain::new(MockClientChain::new(), config.clone());
+        assert!(clientchain.send_challenge(&gen_dummy_hash(1), 0).is_err());
+        let storage = FaultyStorage::new(MockStorage::new(), config.clone());
+        assert!(storage.get_request(gen_dummy_hash(1)).is_err());
+
+        // challenge request fails on storage faults
+        let challenge_state = Arc::new(RwLock::new(Some(gen_challenge_state(&gen_dummy_hash(1)))));
+        let (_, verify_rx) = channel();
+        let res = run_challenge_request(
+            &MockService::new(),
+            &MockClientChain::new(),
+            challenge_state,
+            &verify_rx,
+            Arc::new(storage),
+            ChallengerContext {
+                settings: ChallengeRequestSettings {
+                    timing: ChallengeTiming {
+                        challenge_duration: Duration::from_millis(10),
+                        verify_duration: Duration::from_millis(10),
+                        refresh_delay: Duration::from_millis(10),
+                    },
+                    grace_period: Duration::from_millis(0),
+                    challenge_frequency: 1,
+                    payment_epoch: None,
+                },
+                stall_detector: StallDetector::disabled(),
+                paused: &AtomicBool::new(false),
+                stopped: &AtomicBool::new(false),
+                journal: &Journal::disabled(),
+                heartbeat: &Heartbeat::new(),
+                clock: &SystemClock,
+            },
+        );
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("injected fault: storage get_response"));
+    }
+}
@@ -3,11 +3,15 @@
 //! Mock clientchain implementation for testing
 
 use std::cell::RefCell;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use bitcoin::hashes::{sha256d, Hash};
 
 use crate::error::*;
-use crate::interfaces::clientchain::ClientChain;
+use crate::interfaces::clientchain::{
+    ChallengeAmountTag, ChallengeCommitment, ChallengeTx, ClientChain, CommittedChallenge, PublishedProof,
+    SentChallenge,
+};
 
 /// Mock implementation of ClientChain using some mock logic for testing
 pub struct MockClientChain {
@@ -19,6 +23,19 @@ pub struct MockClientChain {
     pub return_false: bool,
     /// Mock client chain blockheight
     pub height: RefCell<u32>,
+    /// Mock proofs published on the client chain
+    pub published_proofs: RefCell<Vec<PublishedProof>>,
+    /// Offset in seconds from the current time of the mock latest block
+    /// timestamp
+    pub block_time_offset: i64,
+    /// Number of distinct challenge amount tags, 0 if challenges are not
+    /// tagged
+    pub amount_tags: u64,
+    /// Names of the wallets challenges are issued from in turn, by challenge
+    /// sequence number
+    pub wallets: Vec<String>,
+    /// Challenges and requests whose reserved unspents were released
+    pub released: RefCell<Vec<sha256d::Hash>>,
 }
 
 impl MockClientChain {
@@ -28,18 +45,56 @@ impl MockClientChain {
             return_err: false,
             return_false: false,
             height: RefCell::new(0),
+            published_proofs: RefCell::new(vec![]),
+            block_time_offset: 0,
+            amount_tags: 0,
+            wallets: vec![],
+            released: RefCell::new(vec![]),
         }
     }
 }
 
 impl ClientChain for MockClientChain {
     /// Send challenge transaction to client chain
-    fn send_challenge(&self) -> Result<sha256d::Hash> {
+    fn send_challenge(&self, _request: &sha256d::Hash, sequence: u32) -> Result<Option<SentChallenge>> {
         if self.return_err {
             return Err(Error::from(CError::Generic("send_challenge failed".to_owned())));
         }
         // Use height to generate mock challenge hash
-        Ok(sha256d::Hash::from_slice(&[(*self.height.borrow() % 16) as u8; 32])?)
+        Ok(Some(SentChallenge {
+            txid: sha256d::Hash::from_slice(&[(*self.height.borrow() % 16) as u8; 32])?,
+            amount_tag: ChallengeAmountTag::new(sequence, self.amount_tags),
+            wallet: match self.wallets.len() {
+                0 => None,
+                len => Some(self.wallets[sequence as usize % len].clone()),
+            },
+        }))
+    }
+
+    /// Commit to the mock challenge, with the commitment txid derived from
+    /// the commitment hash
+    fn commit_challenge(&self, request: &sha256d::Hash, sequence: u32) -> Result<Option<CommittedChallenge>> {
+        let challenge = match self.send_challenge(request, sequence)? {
+            Some(challenge) => challenge,
+            None => return Ok(None),
+        };
+        let hash = ChallengeCommitment::commit(&challenge.txid);
+        Ok(Some(CommittedChallenge {
+            challenge,
+            commitment: ChallengeCommitment {
+                hash,
+                txid: sha256d::Hash::hash(&hash[..]),
+            },
+            tx_hex: "00".to_owned(),
+        }))
+    }
+
+    /// Reveal mock committed challenge
+    fn reveal_challenge(&self, _committed: &CommittedChallenge) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("reveal_challenge failed".to_owned())));
+        }
+        Ok(())
     }
 
     /// Verify challenge transaction has been included in the chain
@@ -53,8 +108,63 @@ impl ClientChain for MockClientChain {
         Ok(true)
     }
 
+    /// Record the release of the unspent reserved by a challenge
+    fn release_challenge(&self, txid: &sha256d::Hash) {
+        self.released.borrow_mut().push(*txid);
+    }
+
+    /// Record the release of the unspents reserved by a request
+    fn release_request(&self, request: &sha256d::Hash) {
+        self.released.borrow_mut().push(*request);
+    }
+
     /// Get block count dummy
     fn get_blockheight(&self) -> Result<u32> {
         Ok(self.height.clone().into_inner())
     }
+
+    /// Get the mock latest block timestamp, offset from the current time
+    fn get_block_time(&self) -> Result<u64> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("get_block_time failed".to_owned())));
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Ok((now as i64 + self.block_time_offset) as u64)
+    }
+
+    /// Get mock block hash derived from the height
+    fn get_block_hash(&self, height: u32) -> Result<sha256d::Hash> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("get_block_hash failed".to_owned())));
+        }
+        Ok(sha256d::Hash::hash(&height.to_le_bytes()))
+    }
+
+    /// Get dummy challenge transaction
+    fn get_challenge_tx(&self, txid: &sha256d::Hash) -> Result<ChallengeTx> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("get_challenge_tx failed".to_owned())));
+        }
+        Ok(ChallengeTx {
+            hex: "00".to_owned(),
+            decoded: serde_json::json!({ "txid": txid.to_string() }),
+        })
+    }
+
+    /// Get mock published proofs within the block range
+    fn get_published_proofs(&self, start_height: u32, end_height: u32) -> Result<Vec<PublishedProof>> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("get_published_proofs failed".to_owned())));
+        }
+        Ok(self
+            .published_proofs
+            .borrow()
+            .iter()
+            .filter(|proof| proof.height >= start_height && proof.height <= end_height)
+            .cloned()
+            .collect())
+    }
 }
@@ -0,0 +1,68 @@
+//! Mock clock
+//!
+//! Mock clock implementation for testing, with sleeps fast-forwarding the
+//! clock instead of blocking
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::clock::Clock;
+
+/// Unix time in seconds that the mock clock starts at
+pub const MOCK_CLOCK_UNIX_START: u64 = 1_600_000_000;
+
+/// Mock implementation of Clock, starting at a fixed unix time and advancing
+/// only when slept on, when waits time out or when advanced explicitly
+pub struct MockClock {
+    /// Instant that the clock started at
+    start: Instant,
+    /// Time elapsed on the clock since it started
+    elapsed: Mutex<Duration>,
+}
+
+impl MockClock {
+    /// Create a MockClock starting at the mock unix start time
+    pub fn new() -> Self {
+        MockClock {
+            start: Instant::now(),
+            elapsed: Mutex::new(Duration::from_secs(0)),
+        }
+    }
+
+    /// Advance the clock by a duration
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+
+    /// Get the time elapsed on the clock since it started
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+impl Clock for MockClock {
+    /// Get the start instant advanced by the elapsed time
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    /// Get the mock unix start time advanced by the elapsed time
+    fn unix_time(&self) -> Duration {
+        Duration::from_secs(MOCK_CLOCK_UNIX_START) + self.elapsed()
+    }
+
+    /// Advance the clock by the duration without blocking
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration)
+    }
+
+    /// Do not block, so that waits only pick up events that already happened
+    fn timeout(&self, _duration: Duration) -> Duration {
+        Duration::from_secs(0)
+    }
+
+    /// Advance the clock by the duration of the wait that timed out
+    fn timed_out(&self, duration: Duration) {
+        self.advance(duration)
+    }
+}
@@ -3,5 +3,6 @@
 //! Mock Interfaces used for testing of coordinator library functionality
 
 pub mod clientchain;
+pub mod clock;
 pub mod service;
 pub mod storage;
@@ -4,15 +4,17 @@
 
 use std::cell::RefCell;
 use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use bitcoin::hashes::{hex::FromHex, sha256d, Hash};
 use bitcoin::secp256k1::PublicKey;
+use bitcoin::Amount;
 
 use crate::error::{CError, Error, Result};
 use crate::interfaces::service::Service;
 use crate::interfaces::{
-    bid::{Bid, BidSet},
-    request::Request as ServiceRequest,
+    bid::{Bid, BidLock, BidSet},
+    request::{PaymentState, Request as ServiceRequest, RequestDeposit},
 };
 
 /// Mock implementation of Service using some mock logic for testing
@@ -23,11 +25,17 @@ pub struct MockService {
     /// Flag that when set returns None on all inherited methods that return
     /// Option
     pub return_none: bool,
+    /// Flag that when set returns a request deposit with a locked amount lower
+    /// than the promised fee
+    pub return_unverified: bool,
     /// Current active request
     pub request: RefCell<ServiceRequest>,
     /// Mock service chain blockheight - incremented by default on
     /// get_blockheight
     pub height: RefCell<u64>,
+    /// Offset in seconds from the current time of the mock latest block
+    /// timestamp
+    pub block_time_offset: i64,
 }
 
 impl MockService {
@@ -46,13 +54,20 @@ impl MockService {
             start_blockheight_clientchain: 0,
             end_blockheight_clientchain: 0,
             is_payment_complete: false,
+            payment_state: PaymentState::Pending,
+            response_summary: None,
+            fees: vec![],
+            fee_filter: None,
+            failure: None,
         };
 
         MockService {
             return_err: false,
             return_none: false,
+            return_unverified: false,
             request: RefCell::new(request),
             height: RefCell::new(0),
+            block_time_offset: 0,
         }
     }
 }
@@ -60,7 +75,13 @@ impl MockService {
 impl Service for MockService {
     /// Get all active requests, if any, from service chain
     fn get_requests(&self) -> Result<Option<Vec<ServiceRequest>>> {
-        Ok(None)
+        if self.return_none {
+            return Ok(None);
+        }
+        if self.return_err {
+            return Err(Error::from(CError::Generic("get_requests failed".to_owned())));
+        }
+        Ok(Some(vec![self.request.borrow().clone()]))
     }
 
     /// Try get active request, by genesis hash, from service chain
@@ -117,4 +138,51 @@ impl Service for MockService {
         *height += 1; // increment height for integration testing
         Ok(*height - 1) // return previous height
     }
+
+    /// Get the mock latest block timestamp, offset from the current time
+    fn get_block_time(&self) -> Result<u64> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("get_block_time failed".to_owned())));
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Ok((now as i64 + self.block_time_offset) as u64)
+    }
+
+    /// Try get the fee deposit of an active request from service chain
+    fn get_request_deposit(&self, request: &ServiceRequest) -> Result<Option<RequestDeposit>> {
+        if self.return_none {
+            return Ok(None);
+        }
+        if self.return_err {
+            return Err(Error::from(CError::Generic("get_request_deposit failed".to_owned())));
+        }
+        Ok(Some(RequestDeposit {
+            txid: request.txid,
+            genesis_blockhash: request.genesis_blockhash,
+            promised: Amount::from_sat(1000),
+            locked: Amount::from_sat(if self.return_unverified { 999 } else { 1000 }),
+        }))
+    }
+
+    /// Try get the lock output of a bid from service chain, expiring at the
+    /// end height of the mock request
+    fn get_bid_lock(&self, txid: &sha256d::Hash) -> Result<Option<BidLock>> {
+        if self.return_none {
+            return Ok(None);
+        }
+        if self.return_err {
+            return Err(Error::from(CError::Generic("get_bid_lock failed".to_owned())));
+        }
+        Ok(Some(BidLock {
+            txid: *txid,
+            vout: 0,
+            value: Amount::from_sat(1000),
+            asset: "CBT".to_owned(),
+            lock_height: self.request.borrow().end_blockheight,
+            pubkey: PublicKey::from_str("026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3").unwrap(),
+        }))
+    }
 }
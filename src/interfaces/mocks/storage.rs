@@ -3,18 +3,29 @@
 //! Mock storage implementation for testing
 
 use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 
-use bitcoin::hashes::sha256d;
+use bitcoin::hashes::{hex::FromHex, sha256d};
 use mongodb::ordered::OrderedDocument;
 use mongodb::Bson;
 
+use crate::coverage::ChallengeCoverage;
+use crate::dead_letter::DeadLetter;
 use crate::error::{CError, Error, Result};
 use crate::interfaces::storage::*;
 use crate::interfaces::{
-    bid::{Bid, BidSet},
-    request::Request as ServiceRequest,
-    response::Response,
+    bid::{Bid, BidBlacklisting, BidKeyRotation, BidRefund, BidSet, PaymentLiability},
+    request::{
+        FeePool, Request as ServiceRequest, RequestAnnotation, RequestCursor, RequestDeposit, RequestFull,
+        RequestRejection,
+    },
+    response::{
+        BidProof, ChallengeLatency, ChallengeRecord, ChallengeStats, Response, ResponseReconciliation,
+        ResponseSnapshot, ResponseSummary,
+    },
 };
+use crate::stall::ClientChainStall;
 use crate::util::doc_format::*;
 
 /// Mock implementation of Storage storing data in memory for testing
@@ -29,6 +40,42 @@ pub struct MockStorage {
     pub bids: RefCell<Vec<OrderedDocument>>,
     /// Store challenge responses in memory
     pub challenge_responses: RefCell<Vec<OrderedDocument>>,
+    /// Store challenge records in memory
+    pub challenge_records: RefCell<Vec<OrderedDocument>>,
+    /// Store challenge stats in memory
+    pub challenge_stats: RefCell<Vec<OrderedDocument>>,
+    /// Store request logs in memory
+    pub request_logs: RefCell<HashMap<sha256d::Hash, Vec<String>>>,
+    /// Store request deposits in memory
+    pub request_deposits: RefCell<Vec<OrderedDocument>>,
+    /// Store response snapshots in memory
+    pub response_snapshots: RefCell<Vec<OrderedDocument>>,
+    /// Store challenge latencies in memory
+    pub challenge_latencies: RefCell<Vec<OrderedDocument>>,
+    /// Store challenge coverages in memory
+    pub challenge_coverages: RefCell<Vec<OrderedDocument>>,
+    /// Store bid proofs in memory
+    pub bid_proofs: RefCell<Vec<OrderedDocument>>,
+    /// Store client chain stalls in memory
+    pub clientchain_stalls: RefCell<Vec<OrderedDocument>>,
+    /// Store response reconciliations in memory
+    pub response_reconciliations: RefCell<Vec<OrderedDocument>>,
+    /// Store fee pools in memory
+    pub fee_pools: RefCell<Vec<OrderedDocument>>,
+    /// Store request rejections in memory
+    pub request_rejections: RefCell<Vec<OrderedDocument>>,
+    /// Store request annotations in memory
+    pub request_annotations: RefCell<Vec<OrderedDocument>>,
+    /// Store dead letters in memory
+    pub dead_letters: RefCell<Vec<OrderedDocument>>,
+    /// Store bid blacklistings in memory
+    pub bid_blacklistings: RefCell<Vec<OrderedDocument>>,
+    /// Store bid key rotations in memory
+    pub bid_key_rotations: RefCell<Vec<OrderedDocument>>,
+    /// Store bid refunds in memory
+    pub bid_refunds: RefCell<Vec<OrderedDocument>>,
+    /// Store payment liabilities in memory
+    pub payment_liabilities: RefCell<Vec<OrderedDocument>>,
 }
 
 impl MockStorage {
@@ -39,6 +86,24 @@ impl MockStorage {
             requests: RefCell::new(vec![]),
             bids: RefCell::new(vec![]),
             challenge_responses: RefCell::new(vec![]),
+            challenge_records: RefCell::new(vec![]),
+            challenge_stats: RefCell::new(vec![]),
+            request_logs: RefCell::new(HashMap::new()),
+            request_deposits: RefCell::new(vec![]),
+            response_snapshots: RefCell::new(vec![]),
+            challenge_latencies: RefCell::new(vec![]),
+            challenge_coverages: RefCell::new(vec![]),
+            bid_proofs: RefCell::new(vec![]),
+            clientchain_stalls: RefCell::new(vec![]),
+            response_reconciliations: RefCell::new(vec![]),
+            fee_pools: RefCell::new(vec![]),
+            request_rejections: RefCell::new(vec![]),
+            request_annotations: RefCell::new(vec![]),
+            dead_letters: RefCell::new(vec![]),
+            bid_blacklistings: RefCell::new(vec![]),
+            bid_key_rotations: RefCell::new(vec![]),
+            bid_refunds: RefCell::new(vec![]),
+            payment_liabilities: RefCell::new(vec![]),
         }
     }
 }
@@ -58,7 +123,9 @@ impl Storage for MockStorage {
             .iter()
             .any(|req_store| req_store.get("txid").unwrap().as_str().unwrap() == &request.txid.to_string())
         {
-            self.requests.borrow_mut().push(request_to_doc(&request));
+            let mut request_doc = request_to_doc(&request);
+            let _ = request_doc.insert("updated_at", updated_at_ms() as i64);
+            self.requests.borrow_mut().push(request_doc);
         }
         for bid in bids.iter() {
             self.bids
@@ -73,6 +140,7 @@ impl Storage for MockStorage {
         for request in self.requests.borrow_mut().iter_mut() {
             if request.get("txid").unwrap().as_str().unwrap() == &request_update.txid.to_string() {
                 *request = request_to_doc(&request_update);
+                let _ = request.insert("updated_at", updated_at_ms() as i64);
             }
         }
         Ok(())
@@ -83,25 +151,181 @@ impl Storage for MockStorage {
         Ok(())
     }
 
+    /// Update multiple bids of a specific request
+    fn update_bids(&self, _request_hash: sha256d::Hash, _bids: &[Bid]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Remove bids of a specific request from mock storage
+    fn remove_bids(&self, request_hash: sha256d::Hash, bids: &[sha256d::Hash]) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("remove_bids failed".to_owned())));
+        }
+        let txids: Vec<String> = bids.iter().map(|bid| bid.to_string()).collect();
+        self.bids.borrow_mut().retain(|doc| {
+            doc.get("request_id").unwrap().as_str().unwrap() != request_hash.to_string()
+                || !txids.contains(&doc.get("txid").unwrap().as_str().unwrap().to_owned())
+        });
+        Ok(())
+    }
+
     /// Store response for a specific challenge request
     fn save_response(&self, request_hash: sha256d::Hash, response: &Response) -> Result<()> {
         if self.return_err {
             return Err(Error::from(CError::Generic("save_response failed".to_owned())));
         }
 
-        for resp_doc in self.challenge_responses.borrow_mut().iter_mut() {
-            if resp_doc.get("request_id").unwrap().as_str().unwrap() == &request_hash.to_string() {
-                *resp_doc = response_to_doc(&Bson::String(request_hash.to_string()), &response);
+        let mut resp_doc = response_to_doc(&Bson::String(request_hash.to_string()), &response);
+        let mut hashes = self.get_response_hashes(request_hash)?;
+        hashes.push(response.integrity_hash(hashes.last().cloned()));
+        let _ = resp_doc.insert(
+            "hashes",
+            hashes
+                .iter()
+                .map(|hash| Bson::String(hash.to_string()))
+                .collect::<Vec<Bson>>(),
+        );
+
+        for stored_doc in self.challenge_responses.borrow_mut().iter_mut() {
+            if stored_doc.get("request_id").unwrap().as_str().unwrap() == &request_hash.to_string() {
+                *stored_doc = resp_doc;
                 return Ok(());
             }
         }
 
-        self.challenge_responses
-            .borrow_mut()
-            .push(response_to_doc(&Bson::String(request_hash.to_string()), &response));
+        self.challenge_responses.borrow_mut().push(resp_doc);
+        Ok(())
+    }
+
+    /// Apply the bid responses of a single challenge to the response stored in
+    /// memory, extending the integrity hash chain
+    fn update_response(
+        &self,
+        request_hash: sha256d::Hash,
+        challenge: sha256d::Hash,
+        responses: &HashSet<sha256d::Hash>,
+        response: &Response,
+    ) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("update_response failed".to_owned())));
+        }
+
+        let mut hashes = self.get_response_hashes(request_hash)?;
+        hashes.push(response.integrity_hash(hashes.last().cloned()));
+        let mut stored = self.get_response(request_hash)?.unwrap_or_else(Response::new);
+        stored.challenges.push(challenge);
+        stored.update(responses);
+        let mut resp_doc = response_to_doc(&Bson::String(request_hash.to_string()), &stored);
+        let _ = resp_doc.insert(
+            "hashes",
+            hashes
+                .iter()
+                .map(|hash| Bson::String(hash.to_string()))
+                .collect::<Vec<Bson>>(),
+        );
+
+        for stored_doc in self.challenge_responses.borrow_mut().iter_mut() {
+            if stored_doc.get("request_id").unwrap().as_str().unwrap() == &request_hash.to_string() {
+                *stored_doc = resp_doc;
+                return Ok(());
+            }
+        }
+
+        self.challenge_responses.borrow_mut().push(resp_doc);
+        Ok(())
+    }
+
+    /// Append challenge record in memory, unless already stored
+    fn save_challenge_record(&self, request_hash: sha256d::Hash, record: &ChallengeRecord) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("save_challenge_record failed".to_owned())));
+        }
+        let mut records = self.challenge_records.borrow_mut();
+        if !records.iter().any(|doc| {
+            doc.get("txid").unwrap().as_str().unwrap() == request_hash.to_string()
+                && doc.get("challenge").unwrap().as_str().unwrap() == record.challenge.to_string()
+        }) {
+            records.push(challenge_record_to_doc(&request_hash, record));
+        }
+        Ok(())
+    }
+
+    /// Get challenge records stored in memory for a specific request
+    fn get_challenge_records(&self, request_hash: sha256d::Hash) -> Result<Vec<ChallengeRecord>> {
+        Ok(self
+            .challenge_records
+            .borrow()
+            .iter()
+            .filter(|doc| doc.get("txid").unwrap().as_str().unwrap() == request_hash.to_string())
+            .map(|doc| doc_to_challenge_record(doc))
+            .collect())
+    }
+
+    /// Store challenge stats in memory, unless already stored
+    fn save_challenge_stats(&self, request_hash: sha256d::Hash, stats: &ChallengeStats) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("save_challenge_stats failed".to_owned())));
+        }
+        let mut challenge_stats = self.challenge_stats.borrow_mut();
+        if !challenge_stats.iter().any(|doc| {
+            doc.get("txid").unwrap().as_str().unwrap() == request_hash.to_string()
+                && doc.get("challenge").unwrap().as_str().unwrap() == stats.challenge.to_string()
+        }) {
+            challenge_stats.push(challenge_stats_to_doc(&request_hash, stats));
+        }
         Ok(())
     }
 
+    /// Get challenge stats stored in memory for a specific request ordered by
+    /// height
+    fn get_challenge_stats(&self, request_hash: sha256d::Hash) -> Result<Vec<ChallengeStats>> {
+        let mut stats: Vec<ChallengeStats> = self
+            .challenge_stats
+            .borrow()
+            .iter()
+            .filter(|doc| doc.get("txid").unwrap().as_str().unwrap() == request_hash.to_string())
+            .map(|doc| doc_to_challenge_stats(doc))
+            .collect();
+        stats.sort_by_key(|stats| stats.height);
+        Ok(stats)
+    }
+
+    /// Store bid proofs in memory, unless the proof of the bid to the
+    /// challenge is already stored
+    fn save_bid_proofs(&self, request_hash: sha256d::Hash, proofs: &[BidProof]) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("save_bid_proofs failed".to_owned())));
+        }
+        let mut bid_proofs = self.bid_proofs.borrow_mut();
+        for proof in proofs {
+            if !bid_proofs.iter().any(|doc| {
+                doc.get("txid").unwrap().as_str().unwrap() == request_hash.to_string()
+                    && doc.get("bid").unwrap().as_str().unwrap() == proof.bid.to_string()
+                    && doc.get("challenge").unwrap().as_str().unwrap() == proof.challenge.to_string()
+            }) {
+                bid_proofs.push(bid_proof_to_doc(&request_hash, proof));
+            }
+        }
+        Ok(())
+    }
+
+    /// Get bid proofs stored in memory for a bid of a specific request ordered
+    /// by the time they were accepted at
+    fn get_bid_proofs(&self, request_hash: sha256d::Hash, bid_hash: sha256d::Hash) -> Result<Vec<BidProof>> {
+        let mut proofs: Vec<BidProof> = self
+            .bid_proofs
+            .borrow()
+            .iter()
+            .filter(|doc| {
+                doc.get("txid").unwrap().as_str().unwrap() == request_hash.to_string()
+                    && doc.get("bid").unwrap().as_str().unwrap() == bid_hash.to_string()
+            })
+            .map(|doc| doc_to_bid_proof(doc))
+            .collect();
+        proofs.sort_by_key(|proof| proof.timestamp);
+        Ok(proofs)
+    }
+
     /// Get challenge response for a specific request
     fn get_response(&self, request_hash: sha256d::Hash) -> Result<Option<Response>> {
         for doc in self.challenge_responses.borrow().to_vec().iter() {
@@ -112,39 +336,318 @@ impl Storage for MockStorage {
         Ok(None)
     }
 
+    /// Get the integrity hash chain of the response of a specific request
+    fn get_response_hashes(&self, request_hash: sha256d::Hash) -> Result<Vec<sha256d::Hash>> {
+        for doc in self.challenge_responses.borrow().iter() {
+            if doc.get("request_id").unwrap().as_str().unwrap() == request_hash.to_string() {
+                return Ok(doc_to_response_hashes(doc));
+            }
+        }
+        Ok(vec![])
+    }
+
+    /// Compact challenge response for a specific request
+    fn compact_response(&self, request_hash: sha256d::Hash, summary: &ResponseSummary) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("compact_response failed".to_owned())));
+        }
+        for request in self.requests.borrow_mut().iter_mut() {
+            if request.get("txid").unwrap().as_str().unwrap() == &request_hash.to_string() {
+                let _ = request.insert("response_summary", response_summary_to_doc(summary));
+                let _ = request.insert("updated_at", updated_at_ms() as i64);
+            }
+        }
+        self.challenge_responses
+            .borrow_mut()
+            .retain(|doc| doc.get("request_id").unwrap().as_str().unwrap() != request_hash.to_string());
+        self.challenge_records
+            .borrow_mut()
+            .retain(|doc| doc.get("txid").unwrap().as_str().unwrap() != request_hash.to_string());
+        self.bid_proofs
+            .borrow_mut()
+            .retain(|doc| doc.get("txid").unwrap().as_str().unwrap() != request_hash.to_string());
+        Ok(())
+    }
+
+    /// Store response snapshot in memory, replacing any previous snapshot of
+    /// the epoch
+    fn save_response_snapshot(&self, request_hash: sha256d::Hash, snapshot: &ResponseSnapshot) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("save_response_snapshot failed".to_owned())));
+        }
+        let mut snapshots = self.response_snapshots.borrow_mut();
+        snapshots.retain(|doc| {
+            doc.get("txid").unwrap().as_str().unwrap() != request_hash.to_string()
+                || doc.get("epoch").unwrap().as_i32().unwrap() as u32 != snapshot.epoch
+        });
+        snapshots.push(response_snapshot_to_doc(&request_hash, snapshot));
+        Ok(())
+    }
+
+    /// Get response snapshots stored in memory for a specific request ordered
+    /// by epoch
+    fn get_response_snapshots(&self, request_hash: sha256d::Hash) -> Result<Vec<ResponseSnapshot>> {
+        let mut snapshots: Vec<ResponseSnapshot> = self
+            .response_snapshots
+            .borrow()
+            .iter()
+            .filter(|doc| doc.get("txid").unwrap().as_str().unwrap() == request_hash.to_string())
+            .map(|doc| doc_to_response_snapshot(doc))
+            .collect::<Result<_>>()?;
+        snapshots.sort_by_key(|snapshot| snapshot.epoch);
+        Ok(snapshots)
+    }
+
+    /// Store challenge latency in memory, replacing any previous latency of
+    /// the request
+    fn save_challenge_latency(&self, request_hash: sha256d::Hash, latency: &ChallengeLatency) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("save_challenge_latency failed".to_owned())));
+        }
+        let mut latencies = self.challenge_latencies.borrow_mut();
+        latencies.retain(|doc| doc.get("txid").unwrap().as_str().unwrap() != request_hash.to_string());
+        latencies.push(challenge_latency_to_doc(&request_hash, latency));
+        Ok(())
+    }
+
+    /// Get challenge latency stored in memory for a specific request
+    fn get_challenge_latency(&self, request_hash: sha256d::Hash) -> Result<Option<ChallengeLatency>> {
+        Ok(self
+            .challenge_latencies
+            .borrow()
+            .iter()
+            .find(|doc| doc.get("txid").unwrap().as_str().unwrap() == request_hash.to_string())
+            .map(|doc| doc_to_challenge_latency(doc)))
+    }
+
+    /// Store challenge coverage in memory, replacing any previous coverage of
+    /// the request
+    fn save_challenge_coverage(&self, request_hash: sha256d::Hash, coverage: &ChallengeCoverage) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic(
+                "save_challenge_coverage failed".to_owned(),
+            )));
+        }
+        let mut coverages = self.challenge_coverages.borrow_mut();
+        coverages.retain(|doc| doc.get("txid").unwrap().as_str().unwrap() != request_hash.to_string());
+        coverages.push(challenge_coverage_to_doc(&request_hash, coverage));
+        Ok(())
+    }
+
+    /// Get challenge coverage stored in memory for a specific request
+    fn get_challenge_coverage(&self, request_hash: sha256d::Hash) -> Result<Option<ChallengeCoverage>> {
+        Ok(self
+            .challenge_coverages
+            .borrow()
+            .iter()
+            .find(|doc| doc.get("txid").unwrap().as_str().unwrap() == request_hash.to_string())
+            .map(|doc| doc_to_challenge_coverage(doc)))
+    }
+
+    /// Store client chain stall in memory, unless a stall with the same start
+    /// time is already stored
+    fn save_clientchain_stall(&self, request_hash: sha256d::Hash, stall: &ClientChainStall) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("save_clientchain_stall failed".to_owned())));
+        }
+        let mut stalls = self.clientchain_stalls.borrow_mut();
+        if !stalls.iter().any(|doc| {
+            doc.get("txid").unwrap().as_str().unwrap() == request_hash.to_string()
+                && doc.get_i64("start_time").unwrap() == stall.start_time as i64
+        }) {
+            stalls.push(clientchain_stall_to_doc(&request_hash, stall));
+        }
+        Ok(())
+    }
+
+    /// Get client chain stalls stored in memory for a specific request ordered
+    /// by start time
+    fn get_clientchain_stalls(&self, request_hash: sha256d::Hash) -> Result<Vec<ClientChainStall>> {
+        let mut stalls: Vec<ClientChainStall> = self
+            .clientchain_stalls
+            .borrow()
+            .iter()
+            .filter(|doc| doc.get("txid").unwrap().as_str().unwrap() == request_hash.to_string())
+            .map(|doc| doc_to_clientchain_stall(doc))
+            .collect();
+        stalls.sort_by_key(|stall| stall.start_time);
+        Ok(stalls)
+    }
+
+    /// Store response reconciliation in memory, replacing any previous
+    /// reconciliation of the request
+    fn save_response_reconciliation(
+        &self,
+        request_hash: sha256d::Hash,
+        reconciliation: &ResponseReconciliation,
+    ) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic(
+                "save_response_reconciliation failed".to_owned(),
+            )));
+        }
+        let mut reconciliations = self.response_reconciliations.borrow_mut();
+        reconciliations.retain(|doc| doc.get("txid").unwrap().as_str().unwrap() != request_hash.to_string());
+        reconciliations.push(response_reconciliation_to_doc(&request_hash, reconciliation));
+        Ok(())
+    }
+
+    /// Get response reconciliation stored in memory for a specific request
+    fn get_response_reconciliation(&self, request_hash: sha256d::Hash) -> Result<Option<ResponseReconciliation>> {
+        Ok(self
+            .response_reconciliations
+            .borrow()
+            .iter()
+            .find(|doc| doc.get("txid").unwrap().as_str().unwrap() == request_hash.to_string())
+            .map(|doc| doc_to_response_reconciliation(doc)))
+    }
+
+    /// Store fee pool in memory, replacing any previous fee pool of the request
+    fn save_fee_pool(&self, request_hash: sha256d::Hash, pool: &FeePool) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("save_fee_pool failed".to_owned())));
+        }
+        let mut pools = self.fee_pools.borrow_mut();
+        pools.retain(|doc| doc.get("txid").unwrap().as_str().unwrap() != request_hash.to_string());
+        pools.push(fee_pool_to_doc(&request_hash, pool));
+        Ok(())
+    }
+
+    /// Get fee pool stored in memory for a specific request
+    fn get_fee_pool(&self, request_hash: sha256d::Hash) -> Result<Option<FeePool>> {
+        Ok(self
+            .fee_pools
+            .borrow()
+            .iter()
+            .find(|doc| doc.get("txid").unwrap().as_str().unwrap() == request_hash.to_string())
+            .map(|doc| doc_to_fee_pool(doc)))
+    }
+
     /// Get all bids for a specific request
     fn get_bids(&self, request_hash: sha256d::Hash) -> Result<Vec<Bid>> {
         let mut bids = Vec::new();
         for doc in self.bids.borrow().to_vec().iter() {
             if doc.get("request_id").unwrap().as_str().unwrap() == request_hash.to_string() {
-                let _ = bids.push(doc_to_bid(doc));
+                let _ = bids.push(doc_to_bid(doc)?);
             }
         }
         Ok(bids)
     }
 
+    /// Get a single bid of a specific request by bid txid
+    fn get_bid(&self, request_hash: sha256d::Hash, bid_hash: sha256d::Hash) -> Result<Option<Bid>> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("get_bid failed".to_owned())));
+        }
+        for doc in self.bids.borrow().iter() {
+            if doc.get("request_id").unwrap().as_str().unwrap() == request_hash.to_string()
+                && doc.get("txid").unwrap().as_str().unwrap() == bid_hash.to_string()
+            {
+                return doc_to_bid(doc).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
     /// Get all the requests, with an optional flag to return payment complete
-    /// only
+    /// only and an optional genesis hash to return a single tenant's requests
     fn get_requests(
         &self,
-        _complete: Option<bool>,
+        complete: Option<bool>,
+        genesis: Option<sha256d::Hash>,
         limit: Option<i64>,
         skip: Option<i64>,
     ) -> Result<Vec<ServiceRequest>> {
         let skip_val = skip.unwrap_or(0);
         let limit_val = limit.unwrap_or(10000000);
         let mut requests = vec![];
-        for (i, doc) in self.requests.borrow().to_vec().iter().enumerate() {
+        for (i, request) in self
+            .requests
+            .borrow()
+            .iter()
+            .map(|doc| doc_to_request(doc))
+            .filter(|request| complete.map_or(true, |flag| request.is_payment_complete == flag))
+            .filter(|request| genesis.map_or(true, |hash| request.genesis_blockhash == hash))
+            .enumerate()
+        {
             if i as i64 >= skip_val && (requests.len() as i64) < limit_val {
-                requests.push(doc_to_request(doc))
+                requests.push(request)
             }
         }
         Ok(requests)
     }
 
-    /// Get the number of requests stored in memory
-    fn get_requests_count(&self) -> Result<i64> {
-        Ok(self.requests.borrow().len() as i64)
+    /// Get requests stored in memory joined with their bids and responses,
+    /// optionally for a genesis hash
+    fn get_requests_full(
+        &self,
+        genesis: Option<sha256d::Hash>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<RequestFull>> {
+        let mut requests = vec![];
+        for request in self.get_requests(None, genesis, limit, skip)? {
+            requests.push(RequestFull {
+                bids: self.get_bids(request.txid)?,
+                response: self.get_response(request.txid)?,
+                request,
+            });
+        }
+        Ok(requests)
+    }
+
+    /// Get a page of requests stored in memory sorted by start height and
+    /// txid, starting after the cursor if set, optionally for a genesis hash
+    fn get_requests_page(
+        &self,
+        genesis: Option<sha256d::Hash>,
+        after: Option<&RequestCursor>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<ServiceRequest>> {
+        let mut requests = self.get_requests(None, genesis, None, None)?;
+        requests.sort_by_key(|request| (request.start_blockheight, request.txid.to_string()));
+        Ok(requests
+            .into_iter()
+            .filter(|request| after.map_or(true, |cursor| cursor.precedes(request)))
+            .skip(skip.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(10000000) as usize)
+            .collect())
+    }
+
+    /// Get the number of requests stored in memory, optionally for a genesis
+    /// hash
+    fn get_requests_count(&self, genesis: Option<sha256d::Hash>) -> Result<i64> {
+        Ok(self
+            .requests
+            .borrow()
+            .iter()
+            .filter(|doc| genesis.map_or(true, |hash| doc_to_request(doc).genesis_blockhash == hash))
+            .count() as i64)
+    }
+
+    /// Get requests stored in memory sorted by last updated time, most recent
+    /// first, optionally for a genesis hash
+    fn get_requests_recent(
+        &self,
+        genesis: Option<sha256d::Hash>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<ServiceRequest>> {
+        let mut docs: Vec<OrderedDocument> = self
+            .requests
+            .borrow()
+            .iter()
+            .filter(|doc| genesis.map_or(true, |hash| doc_to_request(doc).genesis_blockhash == hash))
+            .cloned()
+            .collect();
+        docs.sort_by_key(|doc| Reverse(doc.get_i64("updated_at").ok()));
+        Ok(docs
+            .iter()
+            .map(|doc| doc_to_request(doc))
+            .skip(skip.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(10000000) as usize)
+            .collect())
     }
 
     /// Get request for a specific request txid
@@ -156,4 +659,261 @@ impl Storage for MockStorage {
         }
         Ok(None)
     }
+
+    /// Store request log lines in memory
+    fn save_request_logs(&self, request_hash: sha256d::Hash, logs: &[String]) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("save_request_logs failed".to_owned())));
+        }
+        let mut request_logs = self.request_logs.borrow_mut();
+        let stored = request_logs.entry(request_hash).or_insert(vec![]);
+        stored.extend_from_slice(logs);
+        if stored.len() > STORAGE_REQUEST_LOGS_LIMIT {
+            let excess = stored.len() - STORAGE_REQUEST_LOGS_LIMIT;
+            let _ = stored.drain(..excess);
+        }
+        Ok(())
+    }
+
+    /// Get request log lines stored in memory
+    fn get_request_logs(&self, request_hash: sha256d::Hash) -> Result<Vec<String>> {
+        Ok(self.request_logs.borrow().get(&request_hash).cloned().unwrap_or(vec![]))
+    }
+
+    /// Store request deposit in memory, replacing any previous deposit
+    fn save_request_deposit(&self, deposit: &RequestDeposit) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("save_request_deposit failed".to_owned())));
+        }
+        let mut deposits = self.request_deposits.borrow_mut();
+        deposits.retain(|doc| doc.get("txid").unwrap().as_str().unwrap() != deposit.txid.to_string());
+        deposits.push(request_deposit_to_doc(deposit));
+        Ok(())
+    }
+
+    /// Get request deposits stored in memory, optionally filtered by verified
+    /// flag and genesis hash
+    fn get_request_deposits(
+        &self,
+        verified: Option<bool>,
+        genesis: Option<sha256d::Hash>,
+    ) -> Result<Vec<RequestDeposit>> {
+        Ok(self
+            .request_deposits
+            .borrow()
+            .iter()
+            .map(|doc| doc_to_request_deposit(doc))
+            .filter(|deposit| verified.map_or(true, |flag| deposit.is_verified() == flag))
+            .filter(|deposit| genesis.map_or(true, |hash| deposit.genesis_blockhash == hash))
+            .collect())
+    }
+
+    /// Store request rejection in memory, replacing any previous rejection
+    fn save_request_rejection(&self, rejection: &RequestRejection) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("save_request_rejection failed".to_owned())));
+        }
+        let mut rejections = self.request_rejections.borrow_mut();
+        rejections.retain(|doc| doc.get("txid").unwrap().as_str().unwrap() != rejection.txid.to_string());
+        rejections.push(request_rejection_to_doc(rejection));
+        Ok(())
+    }
+
+    /// Get request rejections stored in memory, optionally filtered by genesis
+    /// hash
+    fn get_request_rejections(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<RequestRejection>> {
+        Ok(self
+            .request_rejections
+            .borrow()
+            .iter()
+            .map(|doc| doc_to_request_rejection(doc))
+            .filter(|rejection| genesis.map_or(true, |hash| rejection.genesis_blockhash == hash))
+            .collect())
+    }
+
+    /// Store request annotation in memory, replacing any previous annotation
+    /// of the request
+    fn save_request_annotation(&self, request_hash: sha256d::Hash, annotation: &RequestAnnotation) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic(
+                "save_request_annotation failed".to_owned(),
+            )));
+        }
+        let mut annotations = self.request_annotations.borrow_mut();
+        annotations.retain(|doc| doc.get("txid").unwrap().as_str().unwrap() != request_hash.to_string());
+        annotations.push(request_annotation_to_doc(&request_hash, annotation));
+        Ok(())
+    }
+
+    /// Get request annotation stored in memory for a specific request
+    fn get_request_annotation(&self, request_hash: sha256d::Hash) -> Result<Option<RequestAnnotation>> {
+        Ok(self
+            .request_annotations
+            .borrow()
+            .iter()
+            .find(|doc| doc.get("txid").unwrap().as_str().unwrap() == request_hash.to_string())
+            .map(|doc| doc_to_request_annotation(doc)))
+    }
+
+    /// Get the hashes of the requests annotated with a label in memory
+    fn get_labelled_requests(&self, label: &str) -> Result<Vec<sha256d::Hash>> {
+        Ok(self
+            .request_annotations
+            .borrow()
+            .iter()
+            .filter(|doc| doc_to_request_annotation(doc).has_label(label))
+            .map(|doc| sha256d::Hash::from_hex(doc.get("txid").unwrap().as_str().unwrap()).unwrap())
+            .collect())
+    }
+
+    /// Store dead letter in memory, removing the oldest dead letters beyond
+    /// the limit
+    fn save_dead_letter(&self, letter: &DeadLetter) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("save_dead_letter failed".to_owned())));
+        }
+        let mut letters = self.dead_letters.borrow_mut();
+        letters.push(dead_letter_to_doc(letter));
+        if letters.len() > STORAGE_DEAD_LETTERS_LIMIT {
+            let excess = letters.len() - STORAGE_DEAD_LETTERS_LIMIT;
+            let _ = letters.drain(..excess);
+        }
+        Ok(())
+    }
+
+    /// Get up to a limit of the most recent dead letters stored in memory
+    fn get_dead_letters(&self, limit: u64) -> Result<Vec<DeadLetter>> {
+        Ok(self
+            .dead_letters
+            .borrow()
+            .iter()
+            .rev()
+            .take(limit as usize)
+            .map(|doc| doc_to_dead_letter(doc))
+            .collect())
+    }
+
+    /// Store bid blacklisting in memory, replacing any previous blacklisting
+    /// of the bid at the same time
+    fn save_bid_blacklisting(&self, blacklisting: &BidBlacklisting) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("save_bid_blacklisting failed".to_owned())));
+        }
+        let mut blacklistings = self.bid_blacklistings.borrow_mut();
+        blacklistings.retain(|doc| {
+            let stored = doc_to_bid_blacklisting(doc);
+            stored.txid != blacklisting.txid || stored.since != blacklisting.since
+        });
+        blacklistings.push(bid_blacklisting_to_doc(blacklisting));
+        Ok(())
+    }
+
+    /// Get bid blacklistings stored in memory ordered by time, optionally
+    /// filtered by genesis hash
+    fn get_bid_blacklistings(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<BidBlacklisting>> {
+        let mut blacklistings: Vec<BidBlacklisting> = self
+            .bid_blacklistings
+            .borrow()
+            .iter()
+            .map(|doc| doc_to_bid_blacklisting(doc))
+            .filter(|blacklisting| genesis.map_or(true, |hash| blacklisting.genesis_blockhash == hash))
+            .collect();
+        blacklistings.sort_by_key(|blacklisting| blacklisting.since);
+        Ok(blacklistings)
+    }
+
+    /// Store bid key rotation in memory and update the pubkey of the bid
+    fn save_bid_key_rotation(&self, rotation: &BidKeyRotation) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("save_bid_key_rotation failed".to_owned())));
+        }
+        for doc in self.bids.borrow_mut().iter_mut() {
+            if doc.get("request_id").unwrap().as_str().unwrap() == rotation.request.to_string()
+                && doc.get("txid").unwrap().as_str().unwrap() == rotation.txid.to_string()
+            {
+                let _ = doc.insert("pubkey", rotation.new_pubkey.to_string());
+            }
+        }
+        self.bid_key_rotations
+            .borrow_mut()
+            .push(bid_key_rotation_to_doc(rotation));
+        Ok(())
+    }
+
+    /// Get bid key rotations of a request stored in memory ordered by time
+    fn get_bid_key_rotations(&self, request_hash: sha256d::Hash) -> Result<Vec<BidKeyRotation>> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("get_bid_key_rotations failed".to_owned())));
+        }
+        let mut rotations: Vec<BidKeyRotation> = self
+            .bid_key_rotations
+            .borrow()
+            .iter()
+            .map(|doc| doc_to_bid_key_rotation(doc))
+            .filter(|rotation| rotation.request == request_hash)
+            .collect();
+        rotations.sort_by_key(|rotation| rotation.time);
+        Ok(rotations)
+    }
+
+    /// Store bid refund in memory, replacing any previous refund of the bid
+    fn save_bid_refund(&self, refund: &BidRefund) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("save_bid_refund failed".to_owned())));
+        }
+        let mut refunds = self.bid_refunds.borrow_mut();
+        refunds.retain(|doc| doc_to_bid_refund(doc).txid != refund.txid);
+        refunds.push(bid_refund_to_doc(refund));
+        Ok(())
+    }
+
+    /// Get bid refunds stored in memory ordered by lock height, optionally
+    /// filtered by genesis hash
+    fn get_bid_refunds(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<BidRefund>> {
+        let mut refunds: Vec<BidRefund> = self
+            .bid_refunds
+            .borrow()
+            .iter()
+            .map(|doc| doc_to_bid_refund(doc))
+            .filter(|refund| genesis.map_or(true, |hash| refund.genesis_blockhash == hash))
+            .collect();
+        refunds.sort_by_key(|refund| refund.lock_height);
+        Ok(refunds)
+    }
+
+    /// Store payment liability in memory, replacing any previous liability of
+    /// the request epoch
+    fn save_payment_liability(&self, liability: &PaymentLiability) -> Result<()> {
+        if self.return_err {
+            return Err(Error::from(CError::Generic("save_payment_liability failed".to_owned())));
+        }
+        self.remove_payment_liability(liability.request, liability.epoch)?;
+        self.payment_liabilities
+            .borrow_mut()
+            .push(payment_liability_to_doc(liability));
+        Ok(())
+    }
+
+    /// Remove payment liability of the request epoch from memory
+    fn remove_payment_liability(&self, request_hash: sha256d::Hash, epoch: Option<u32>) -> Result<()> {
+        self.payment_liabilities.borrow_mut().retain(|doc| {
+            let liability = doc_to_payment_liability(doc);
+            liability.request != request_hash || liability.epoch != epoch
+        });
+        Ok(())
+    }
+
+    /// Get payment liabilities stored in memory ordered by deferral time,
+    /// optionally filtered by genesis hash
+    fn get_payment_liabilities(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<PaymentLiability>> {
+        let mut liabilities: Vec<PaymentLiability> = self
+            .payment_liabilities
+            .borrow()
+            .iter()
+            .map(|doc| doc_to_payment_liability(doc))
+            .filter(|liability| genesis.map_or(true, |hash| liability.genesis_blockhash == hash))
+            .collect();
+        liabilities.sort_by_key(|liability| liability.time);
+        Ok(liabilities)
+    }
 }
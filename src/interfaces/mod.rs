@@ -4,6 +4,8 @@
 
 pub mod bid;
 pub mod clientchain;
+#[cfg(any(test, feature = "fault-injection"))]
+pub mod faults;
 pub mod request;
 pub mod response;
 pub mod service;
@@ -2,13 +2,22 @@
 //!
 //! Service request models for client requests
 
-use bitcoin::hashes::sha256d;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use bitcoin::hashes::{sha256d, Hash};
+use bitcoin::Amount;
 use ocean_rpc::json::GetRequestsResult;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{CError, Error, ErrorCategory};
+use crate::interfaces::bid::Bid;
+use crate::interfaces::response::{Response, ResponseSummary};
 
 /// Request struct storing info on client request and modelling data that need
 /// to be stored
-#[derive(Debug, PartialEq, Clone, Serialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Request {
     /// Ocean transaction ID of the request transaction
     pub txid: sha256d::Hash,
@@ -28,6 +37,25 @@ pub struct Request {
     pub end_blockheight_clientchain: u32,
     /// Payment complete flag for request
     pub is_payment_complete: bool,
+    /// Payment state of the request, distinguishing requests that the
+    /// coordinator does not pay from requests paid successfully
+    #[serde(default)]
+    pub payment_state: PaymentState,
+    /// Summary of the request responses, set once responses are compacted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_summary: Option<ResponseSummary>,
+    /// Fees accrued in each fee asset over the request client chain blocks,
+    /// set once the request fees are calculated
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fees: Vec<AssetFees>,
+    /// Filter that the coinbase outputs counted in the request fees were
+    /// selected by; not set if all coinbase outputs were counted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_filter: Option<FeeFilter>,
+    /// Reason that challenging the request failed, if the request could not
+    /// be serviced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<RequestFailure>,
 }
 
 impl Request {
@@ -43,6 +71,589 @@ impl Request {
             start_blockheight_clientchain: 0,
             end_blockheight_clientchain: 0,
             is_payment_complete: false,
+            payment_state: PaymentState::Pending,
+            response_summary: None,
+            fees: vec![],
+            fee_filter: None,
+            failure: None,
+        }
+    }
+}
+
+/// Failure of challenging a request, recorded on the request so that clients
+/// can tell why the request was not serviced
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct RequestFailure {
+    /// Category of the error that challenging failed with
+    pub category: ErrorCategory,
+    /// Error message
+    pub message: String,
+    /// Service chain height at the failure, if it could be fetched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u64>,
+    /// Number of challenges issued for the request before the failure
+    pub challenges: u32,
+}
+
+impl RequestFailure {
+    /// Create a request failure from the error that challenging failed with
+    pub fn new(err: &Error, height: Option<u64>, challenges: u32) -> RequestFailure {
+        RequestFailure {
+            category: err.category(),
+            message: err.to_string(),
+            height,
+            challenges,
+        }
+    }
+}
+
+/// Payment state of a request
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PaymentState {
+    /// Bids are not paid by the coordinator, with payments only calculated,
+    /// or there are no bid payments
+    NotRequired,
+    /// Request not finished or not processed for payment yet
+    Pending,
+    /// All bid payments broadcast
+    Paid,
+    /// Bid payments exported for payment by an external wallet and awaiting
+    /// matching client chain transactions
+    Exported,
+    /// Some bid payments failed or are unresolved; retried on the next
+    /// payments run
+    Failed,
+    /// Some bid payments deferred as the wallet could not fund them; paid
+    /// once the wallet is topped up
+    Partial,
+}
+
+impl PaymentState {
+    /// Get the payment state name as stored
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentState::NotRequired => "not-required",
+            PaymentState::Pending => "pending",
+            PaymentState::Paid => "paid",
+            PaymentState::Exported => "exported",
+            PaymentState::Failed => "failed",
+            PaymentState::Partial => "partial",
+        }
+    }
+
+    /// Check whether payment of the request is complete, with no further
+    /// payments to make or wait for
+    pub fn is_complete(&self) -> bool {
+        match self {
+            PaymentState::NotRequired | PaymentState::Paid => true,
+            PaymentState::Pending | PaymentState::Exported | PaymentState::Failed | PaymentState::Partial => false,
+        }
+    }
+
+    /// Get the payment state of requests stored before payment states were
+    /// recorded, from the payment complete flag
+    pub fn from_legacy(is_payment_complete: bool) -> PaymentState {
+        if is_payment_complete {
+            PaymentState::Paid
+        } else {
+            PaymentState::Pending
+        }
+    }
+}
+
+impl Default for PaymentState {
+    fn default() -> PaymentState {
+        PaymentState::Pending
+    }
+}
+
+impl fmt::Display for PaymentState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for PaymentState {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<PaymentState, Error> {
+        match s {
+            "not-required" => Ok(PaymentState::NotRequired),
+            "pending" => Ok(PaymentState::Pending),
+            "paid" => Ok(PaymentState::Paid),
+            "exported" => Ok(PaymentState::Exported),
+            "failed" => Ok(PaymentState::Failed),
+            "partial" => Ok(PaymentState::Partial),
+            _ => Err(Error::from(CError::Generic(format!("unknown payment state: {}", s)))),
+        }
+    }
+}
+
+/// Fees accrued in a fee asset
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssetFees {
+    /// Asset label, or asset id of unlabelled assets
+    pub asset: String,
+    /// Fee amount
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub amount: Amount,
+}
+
+/// Get the total amount of the fees of all fee assets
+pub fn total_fees(fees: &[AssetFees]) -> Amount {
+    fees.iter().fold(Amount::ZERO, |total, fee| total + fee.amount)
+}
+
+/// Fees accrued in a client chain block
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct BlockFees {
+    /// Client chain block height
+    pub height: u32,
+    /// Fees accrued in the block per fee asset
+    pub fees: Vec<AssetFees>,
+}
+
+/// Get the fee totals per fee asset of a range of client chain blocks
+pub fn block_fees_totals(blocks: &[BlockFees]) -> Vec<AssetFees> {
+    let mut totals: BTreeMap<String, Amount> = BTreeMap::new();
+    for fee in blocks.iter().flat_map(|block| block.fees.iter()) {
+        *totals.entry(fee.asset.clone()).or_insert(Amount::ZERO) += fee.amount;
+    }
+    totals
+        .into_iter()
+        .map(|(asset, amount)| AssetFees { asset, amount })
+        .collect()
+}
+
+/// Fee pool of a finished request, holding the client chain fees accrued over
+/// the request per block and per fee asset along with the share of the fees
+/// allocated to guardnodes, so that the payment of the request can be verified
+/// without access to the client chain
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct FeePool {
+    /// Client chain height of the first block of the request
+    pub start_height: u32,
+    /// Client chain height of the last block of the request
+    pub end_height: u32,
+    /// Fees accrued per client chain block, for the blocks with any fees
+    pub blocks: Vec<BlockFees>,
+    /// Fee totals per fee asset
+    pub fees: Vec<AssetFees>,
+    /// Total amount of the fees of all fee assets
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub total: Amount,
+    /// Percentage of the fees allocated to guardnodes
+    pub fee_percentage: u32,
+    /// Amount of the fees allocated to guardnodes
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub guardnode_amount: Amount,
+    /// Filter of the coinbase outputs counted as fees, if any
+    pub fee_filter: Option<FeeFilter>,
+}
+
+impl FeePool {
+    /// Create a new fee pool from the fees of the blocks of a request,
+    /// totalling the fees and the amount allocated to guardnodes
+    pub fn new(
+        start_height: u32,
+        end_height: u32,
+        blocks: Vec<BlockFees>,
+        fee_percentage: u32,
+        fee_filter: Option<FeeFilter>,
+    ) -> FeePool {
+        let fees = block_fees_totals(&blocks);
+        let total = total_fees(&fees);
+        FeePool {
+            start_height,
+            end_height,
+            blocks,
+            fees,
+            total,
+            fee_percentage,
+            guardnode_amount: total * fee_percentage as u64 / 100,
+            fee_filter,
+        }
+    }
+}
+
+/// Script types of client chain outputs that fee filters can exclude
+pub const FEE_FILTER_SCRIPT_TYPES: &[&str] = &[
+    "pubkey",
+    "pubkeyhash",
+    "scripthash",
+    "multisig",
+    "nulldata",
+    "witness_v0_keyhash",
+    "witness_v0_scripthash",
+    "fee",
+    "nonstandard",
+];
+
+/// Filter of the client chain coinbase outputs counted as fees, so that burn
+/// or treasury outputs designated by the chain operator are left out of the
+/// fee totals
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct FeeFilter {
+    /// Addresses of outputs that are not fees
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_addresses: Vec<String>,
+    /// Script types of outputs that are not fees
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_script_types: Vec<String>,
+    /// Designated fee addresses; only outputs paying one of them are fees if
+    /// any are set
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_addresses: Vec<String>,
+}
+
+impl FeeFilter {
+    /// Check if the filter counts every coinbase output as fees
+    pub fn is_empty(&self) -> bool {
+        self.exclude_addresses.is_empty() && self.exclude_script_types.is_empty() && self.include_addresses.is_empty()
+    }
+
+    /// Check if an output with the given script type and addresses is counted
+    /// as fees. Exclusions take precedence over designated fee addresses
+    pub fn is_fee(&self, addresses: &[String], script_type: &str) -> bool {
+        if self.exclude_script_types.iter().any(|excluded| excluded == script_type) {
+            return false;
+        }
+        if addresses.iter().any(|address| self.exclude_addresses.contains(address)) {
+            return false;
+        }
+        self.include_addresses.is_empty() || addresses.iter().any(|address| self.include_addresses.contains(address))
+    }
+}
+
+/// Request in the shape of the request entries returned by the ocean
+/// getrequests rpc, for comparing stored requests against the service chain.
+/// Request parameters that are not stored by the coordinator are null
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OceanRequest {
+    /// Genesis blockhash of client issuing request
+    pub genesis_block: sha256d::Hash,
+    /// Request start block height
+    pub start_block_height: u32,
+    /// Num of Guardnode tickets set by client
+    pub num_tickets: u32,
+    /// Auction price decay constant; not stored
+    pub decay_const: Option<u32>,
+    /// Auction start price; not stored
+    pub start_price: Option<f64>,
+    /// Current auction price; not stored
+    pub auction_price: Option<f64>,
+    /// Fee percentage for Guardnodes set by client
+    pub fee_percentage: u32,
+    /// Request end block height
+    pub end_block_height: u32,
+    /// Ocean transaction ID of the request transaction
+    pub txid: sha256d::Hash,
+}
+
+impl OceanRequest {
+    /// Return the ocean getrequests entry of a stored request
+    pub fn from_request(request: &Request) -> Self {
+        OceanRequest {
+            genesis_block: request.genesis_blockhash,
+            start_block_height: request.start_blockheight,
+            num_tickets: request.num_tickets,
+            decay_const: None,
+            start_price: None,
+            auction_price: None,
+            fee_percentage: request.fee_percentage,
+            end_block_height: request.end_blockheight,
+            txid: request.txid,
         }
     }
 }
+
+/// Bid in the shape of the bids returned by the ocean getrequestbids rpc
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OceanRequestBid {
+    /// Ocean transaction ID of the bid transaction
+    pub txid: sha256d::Hash,
+    /// Bid owner verification public key
+    pub fee_pub_key: String,
+}
+
+/// Request and bids in the shape returned by the ocean getrequestbids rpc
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct OceanRequestBids {
+    /// Request fields as returned by getrequests
+    #[serde(flatten)]
+    pub request: OceanRequest,
+    /// Request winning bids
+    pub bids: Vec<OceanRequestBid>,
+}
+
+impl OceanRequestBids {
+    /// Return the ocean getrequestbids result of a stored request and its bids
+    pub fn from_request(request: &Request, bids: &[Bid]) -> Self {
+        OceanRequestBids {
+            request: OceanRequest::from_request(request),
+            bids: bids
+                .iter()
+                .map(|bid| OceanRequestBid {
+                    txid: bid.txid,
+                    fee_pub_key: bid.pubkey.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Request fee deposit modelling the fee promised by the request parameters
+/// and the amount actually locked by the request transaction on the service
+/// chain
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct RequestDeposit {
+    /// Ocean transaction ID of the request transaction
+    pub txid: sha256d::Hash,
+    /// Genesis blockhash of client issuing request
+    pub genesis_blockhash: sha256d::Hash,
+    /// Fee amount promised by the request
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub promised: Amount,
+    /// Fee amount locked on the service chain
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub locked: Amount,
+}
+
+impl RequestDeposit {
+    /// Check whether the locked amount covers the promised fee
+    pub fn is_verified(&self) -> bool {
+        self.locked >= self.promised
+    }
+}
+
+/// Request joined with its bids and response, if any
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct RequestFull {
+    /// Service request
+    pub request: Request,
+    /// Request winning bids
+    pub bids: Vec<Bid>,
+    /// Request challenge response
+    pub response: Option<Response>,
+}
+
+/// Position of a request in the deterministic order of requests by service
+/// chain start height and txid, from which the next page of requests starts.
+/// Requests stored later never move the requests after a cursor, unlike
+/// offsets into the stored order of requests
+#[derive(Debug, PartialEq, Clone)]
+pub struct RequestCursor {
+    /// Request start block height
+    pub start_blockheight: u32,
+    /// Ocean transaction ID of the request transaction
+    pub txid: sha256d::Hash,
+}
+
+impl RequestCursor {
+    /// Return the cursor positioned at a request
+    pub fn at(request: &Request) -> RequestCursor {
+        RequestCursor {
+            start_blockheight: request.start_blockheight,
+            txid: request.txid,
+        }
+    }
+
+    /// Check whether the cursor precedes a request in the order of requests
+    pub fn precedes(&self, request: &Request) -> bool {
+        (request.start_blockheight, request.txid.to_string()) > (self.start_blockheight, self.txid.to_string())
+    }
+}
+
+/// Opaque token of the cursor, encoded as url safe base64
+impl fmt::Display for RequestCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut bytes = self.start_blockheight.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.txid[..]);
+        write!(f, "{}", base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD))
+    }
+}
+
+impl FromStr for RequestCursor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<RequestCursor, Error> {
+        let invalid = || Error::from(CError::Generic(format!("invalid request cursor: {}", s)));
+        let bytes = base64::decode_config(s, base64::URL_SAFE_NO_PAD).map_err(|_| invalid())?;
+        if bytes.len() != 36 {
+            return Err(invalid());
+        }
+        let mut height = [0u8; 4];
+        height.copy_from_slice(&bytes[..4]);
+        Ok(RequestCursor {
+            start_blockheight: u32::from_be_bytes(height),
+            txid: sha256d::Hash::from_slice(&bytes[4..]).map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Request rejected by the coordinator as its parameters failed validation,
+/// along with the reason for the rejection
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct RequestRejection {
+    /// Ocean transaction ID of the request transaction
+    pub txid: sha256d::Hash,
+    /// Genesis blockhash of client issuing request
+    pub genesis_blockhash: sha256d::Hash,
+    /// Reason the request was rejected
+    pub reason: String,
+}
+
+/// Operator annotation of a request with labels and free-text notes, stored
+/// alongside the request and not used in challenging or payments
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct RequestAnnotation {
+    /// Labels of the request, e.g. `trial` or `disputed`
+    pub labels: Vec<String>,
+    /// Free-text notes on the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+impl RequestAnnotation {
+    /// Check whether the request has a label
+    pub fn has_label(&self, label: &str) -> bool {
+        self.labels.iter().any(|request_label| request_label == label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::util::testing::gen_dummy_hash;
+
+    #[test]
+    fn request_cursor_test() {
+        let cursor = RequestCursor {
+            start_blockheight: 300,
+            txid: gen_dummy_hash(4),
+        };
+        let token = cursor.to_string();
+        assert_eq!(48, token.len());
+        assert_eq!(cursor, RequestCursor::from_str(&token).unwrap());
+        assert!(RequestCursor::from_str("").is_err());
+        assert!(RequestCursor::from_str(&token[..40]).is_err());
+        assert!(RequestCursor::from_str("not a cursor").is_err());
+    }
+
+    #[test]
+    fn fee_pool_test() {
+        let fee = |asset: &str, sat: u64| AssetFees {
+            asset: asset.to_owned(),
+            amount: Amount::from_sat(sat),
+        };
+        let blocks = vec![
+            BlockFees {
+                height: 10,
+                fees: vec![fee("CBT", 1000), fee("DGLD", 50)],
+            },
+            BlockFees {
+                height: 12,
+                fees: vec![fee("CBT", 500)],
+            },
+        ];
+        let pool = FeePool::new(10, 15, blocks.clone(), 30, None);
+        assert_eq!(vec![fee("CBT", 1500), fee("DGLD", 50)], pool.fees);
+        assert_eq!(Amount::from_sat(1550), pool.total);
+        assert_eq!(Amount::from_sat(465), pool.guardnode_amount);
+        assert_eq!(blocks, pool.blocks);
+
+        let pool = FeePool::new(10, 15, vec![], 30, None);
+        assert_eq!(Vec::<AssetFees>::new(), pool.fees);
+        assert_eq!(Amount::ZERO, pool.guardnode_amount);
+    }
+
+    #[test]
+    fn payment_state_test() {
+        for state in vec![
+            PaymentState::NotRequired,
+            PaymentState::Pending,
+            PaymentState::Paid,
+            PaymentState::Exported,
+            PaymentState::Failed,
+            PaymentState::Partial,
+        ] {
+            assert_eq!(state, PaymentState::from_str(state.as_str()).unwrap());
+            assert_eq!(format!("\"{}\"", state), serde_json::to_string(&state).unwrap());
+        }
+        assert!(PaymentState::from_str("complete").is_err());
+        assert_eq!(PaymentState::Paid, PaymentState::from_legacy(true));
+        assert_eq!(PaymentState::Pending, PaymentState::from_legacy(false));
+        assert!(PaymentState::Paid.is_complete());
+        assert!(PaymentState::NotRequired.is_complete());
+        assert!(!PaymentState::Exported.is_complete());
+        assert!(!PaymentState::Failed.is_complete());
+        assert!(!PaymentState::Partial.is_complete());
+    }
+
+    #[test]
+    fn request_failure_test() {
+        let err = Error::from(CError::MissingUnspent("CHALLENGE".to_owned(), "client".to_owned()));
+        let failure = RequestFailure::new(&err, Some(12), 3);
+        assert_eq!(ErrorCategory::Transient, failure.category);
+        assert_eq!(err.to_string(), failure.message);
+        assert_eq!(
+            format!(
+                r#"{{"category":"transient","message":{},"height":12,"challenges":3}}"#,
+                serde_json::to_string(&failure.message).unwrap()
+            ),
+            serde_json::to_string(&failure).unwrap()
+        );
+
+        // height left out if unknown
+        let failure = RequestFailure::new(&Error::from(CError::Generic("failed".to_owned())), None, 0);
+        assert_eq!(
+            r#"{"category":"permanent","message":"coordinator error: generic Error: failed","challenges":0}"#,
+            serde_json::to_string(&failure).unwrap()
+        );
+        assert_eq!(
+            failure,
+            serde_json::from_str(&serde_json::to_string(&failure).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn fee_filter_test() {
+        let fee_addr = String::from("2dt74vJqpNTM12pbiNpHGvry7tyvUu9tJR8");
+        let burn_addr = String::from("2dZRkPX3hrPtuBrmMkbGtxTxsuYYgAaFrXZ");
+
+        // empty filter counts all outputs
+        let mut filter = FeeFilter::default();
+        assert!(filter.is_empty());
+        assert!(filter.is_fee(&[burn_addr.clone()], "pubkeyhash"));
+        assert!(filter.is_fee(&[], "fee"));
+
+        // excluded addresses and script types
+        filter.exclude_addresses = vec![burn_addr.clone()];
+        filter.exclude_script_types = vec![String::from("nulldata")];
+        assert!(!filter.is_empty());
+        assert!(!filter.is_fee(&[burn_addr.clone()], "pubkeyhash"));
+        assert!(!filter.is_fee(&[], "nulldata"));
+        assert!(filter.is_fee(&[fee_addr.clone()], "pubkeyhash"));
+        assert!(filter.is_fee(&[], "fee"));
+
+        // only designated fee addresses, unless excluded
+        filter.include_addresses = vec![fee_addr.clone(), burn_addr.clone()];
+        assert!(filter.is_fee(&[fee_addr.clone()], "pubkeyhash"));
+        assert!(!filter.is_fee(&[burn_addr.clone()], "pubkeyhash"));
+        assert!(!filter.is_fee(&[], "fee"));
+        filter.exclude_script_types.push(String::from("pubkeyhash"));
+        assert!(!filter.is_fee(&[fee_addr], "pubkeyhash"));
+
+        // empty filter lists are not serialized
+        filter.include_addresses = vec![];
+        filter.exclude_script_types = vec![];
+        assert_eq!(
+            format!("{{\"exclude_addresses\":[\"{}\"]}}", burn_addr),
+            serde_json::to_string(&filter).unwrap()
+        );
+    }
+}
@@ -2,20 +2,26 @@
 //!
 //! Response model for service challenge responses
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-use bitcoin::hashes::sha256d;
-use serde::Serialize;
+use bitcoin::hashes::{sha256d, Hash, HashEngine};
+use serde::{Deserialize, Serialize};
+
+use crate::interfaces::bid::Bid;
+use crate::interfaces::clientchain::ChallengeAmountTag;
 
 /// Response struct that models responses to service challenges
 /// by keeping track of the total number of challengers and the
 /// number of challenges that each bid owner responded to
-#[derive(Debug, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Response {
     /// Total number of challenges
     pub num_challenges: u32,
     /// Number of responses per bid txid
     pub bid_responses: HashMap<sha256d::Hash, u32>,
+    /// Challenge transaction hashes in the order they were issued
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub challenges: Vec<sha256d::Hash>,
 }
 
 impl Response {
@@ -24,6 +30,64 @@ impl Response {
         Response {
             num_challenges: 0,
             bid_responses: HashMap::new(),
+            challenges: vec![],
+        }
+    }
+
+    /// Summarise the response into the total number of challenges, bids
+    /// and responses and the overall response rate of bids to challenges
+    pub fn summary(&self) -> ResponseSummary {
+        let num_bids = self.bid_responses.len() as u32;
+        let num_responses: u32 = self.bid_responses.values().sum();
+        let response_rate = if self.num_challenges == 0 || num_bids == 0 {
+            0.0
+        } else {
+            num_responses as f64 / (self.num_challenges as f64 * num_bids as f64)
+        };
+        ResponseSummary {
+            num_challenges: self.num_challenges,
+            num_bids,
+            num_responses,
+            response_rate,
+        }
+    }
+
+    /// Rolling integrity hash of the response chained to the hash of the
+    /// previous response update, if any. The response is serialized with sorted
+    /// keys so that the hash does not depend on the order of bid responses
+    pub fn integrity_hash(&self, prev_hash: Option<sha256d::Hash>) -> sha256d::Hash {
+        let serialized = serde_json::to_string(&serde_json::to_value(self).unwrap()).unwrap();
+        let mut engine = sha256d::Hash::engine();
+        if let Some(hash) = prev_hash {
+            engine.input(&hash[..]);
+        }
+        engine.input(serialized.as_bytes());
+        sha256d::Hash::from_engine(engine)
+    }
+
+    /// Verify that a chain of integrity hashes ends with the hash of this
+    /// response chained to the previous hash in the chain
+    pub fn verify_integrity(&self, hashes: &[sha256d::Hash]) -> bool {
+        match hashes.split_last() {
+            Some((last, prev)) => *last == self.integrity_hash(prev.last().cloned()),
+            None => false,
+        }
+    }
+
+    /// Get the challenges and bid responses of this response that came after
+    /// a previous response of the same request
+    pub fn since(&self, prev: &Response) -> Response {
+        let mut bid_responses = HashMap::new();
+        for (txid, num) in self.bid_responses.iter() {
+            let prev_num = prev.bid_responses.get(txid).cloned().unwrap_or(0);
+            if *num > prev_num {
+                let _ = bid_responses.insert(*txid, num - prev_num);
+            }
+        }
+        Response {
+            num_challenges: self.num_challenges.saturating_sub(prev.num_challenges),
+            bid_responses,
+            challenges: self.challenges.iter().skip(prev.challenges.len()).cloned().collect(),
         }
     }
 
@@ -35,6 +99,320 @@ impl Response {
             *bid_entry += 1;
         }
     }
+
+    /// Apply the per challenge records of the request whose challenges are
+    /// missing from this response, in the order they were recorded. Returns
+    /// the number of records applied
+    pub fn apply_records(&mut self, records: &[ChallengeRecord]) -> u32 {
+        let mut applied = 0;
+        for record in records.iter() {
+            if self.challenges.contains(&record.challenge) {
+                continue;
+            }
+            self.challenges.push(record.challenge);
+            self.update(&record.responders.iter().cloned().collect());
+            applied += 1;
+        }
+        applied
+    }
+}
+
+/// Append-only record of the bids that responded to a single challenge of a
+/// request, stored before the aggregate Response is updated so that the
+/// aggregate can be rebuilt if its update is lost
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChallengeRecord {
+    /// Challenge transaction hash
+    pub challenge: sha256d::Hash,
+    /// Txids of the bids that responded to the challenge, sorted
+    pub responders: Vec<sha256d::Hash>,
+}
+
+impl ChallengeRecord {
+    /// Create new ChallengeRecord from the challenge response ids
+    pub fn new(challenge: sha256d::Hash, responses: &HashSet<sha256d::Hash>) -> ChallengeRecord {
+        let mut responders: Vec<sha256d::Hash> = responses.iter().cloned().collect();
+        responders.sort();
+        ChallengeRecord { challenge, responders }
+    }
+}
+
+/// Number of responses to a single challenge of a request along with the
+/// service chain height that the challenge was issued at. Kept after the per
+/// bid responses are compacted so that the response coverage over the request
+/// period remains available
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChallengeStats {
+    /// Challenge transaction hash
+    pub challenge: sha256d::Hash,
+    /// Service chain height that the challenge was issued at
+    pub height: u64,
+    /// Number of request bids expected to respond to the challenge
+    pub num_bids: u32,
+    /// Number of bids that responded to the challenge
+    pub num_responses: u32,
+    /// Amount tag of the challenge transaction, if challenges are tagged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount_tag: Option<ChallengeAmountTag>,
+    /// Name of the wallet the challenge was issued from, if challenge wallets
+    /// are configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallet: Option<String>,
+}
+
+/// Challenge proof of a bid accepted in response to a single challenge of a
+/// request, kept per bid so that bid owners can tell which challenges they
+/// responded to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BidProof {
+    /// Bid txid
+    pub bid: sha256d::Hash,
+    /// Challenge transaction hash
+    pub challenge: sha256d::Hash,
+    /// Client chain height once the challenge was verified to be included,
+    /// which the proof was accepted at or shortly after
+    pub clientchain_height: u32,
+    /// Unix timestamp in ms that the proof was accepted at
+    pub timestamp: u64,
+}
+
+/// Responses to the challenges of a request issued at a service chain height
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HeightStats {
+    /// Service chain height
+    pub height: u64,
+    /// Number of challenges issued at the height
+    pub num_challenges: u32,
+    /// Max number of bids expected to respond to a challenge at the height
+    pub num_bids: u32,
+    /// Total number of responses to the challenges at the height
+    pub num_responses: u32,
+    /// Ratio of responses to the maximum possible number of responses
+    pub response_rate: f64,
+}
+
+impl HeightStats {
+    /// Aggregate the stats of the challenges of a request by the service chain
+    /// height that they were issued at, in ascending height order
+    pub fn from_challenges(stats: &[ChallengeStats]) -> Vec<HeightStats> {
+        let mut heights: BTreeMap<u64, (HeightStats, u64)> = BTreeMap::new();
+        for challenge in stats.iter() {
+            let (height, expected) = heights.entry(challenge.height).or_insert((
+                HeightStats {
+                    height: challenge.height,
+                    num_challenges: 0,
+                    num_bids: 0,
+                    num_responses: 0,
+                    response_rate: 0.0,
+                },
+                0,
+            ));
+            height.num_challenges += 1;
+            height.num_bids = height.num_bids.max(challenge.num_bids);
+            height.num_responses += challenge.num_responses;
+            *expected += challenge.num_bids as u64;
+        }
+        heights
+            .into_iter()
+            .map(|(_, (mut height, expected))| {
+                if expected > 0 {
+                    height.response_rate = height.num_responses as f64 / expected as f64;
+                }
+                height
+            })
+            .collect()
+    }
+}
+
+/// Summary statistics of a Response that are kept on the request once the
+/// per bid responses have been compacted
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResponseSummary {
+    /// Total number of challenges
+    pub num_challenges: u32,
+    /// Number of bids that responded to at least one challenge
+    pub num_bids: u32,
+    /// Total number of responses of all bids
+    pub num_responses: u32,
+    /// Ratio of responses to the maximum possible number of responses
+    pub response_rate: f64,
+}
+
+/// Snapshot of the response of a request at the end of a payment epoch, along
+/// with the bids of the request carrying the payments for the epoch
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ResponseSnapshot {
+    /// Epoch number starting from zero
+    pub epoch: u32,
+    /// Service chain height at the end of the epoch
+    pub service_height: u32,
+    /// Client chain height at the end of the epoch
+    pub clientchain_height: u32,
+    /// Response of the request at the end of the epoch
+    pub response: Response,
+    /// Request bids with the payment for the epoch, if any
+    pub bids: Vec<Bid>,
+    /// Flag set when payments for the epoch are complete
+    pub is_payment_complete: bool,
+}
+
+/// Percentiles of a set of latency samples in milliseconds
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct LatencyPercentiles {
+    /// Number of samples
+    pub count: u32,
+    /// Median latency
+    pub p50: u64,
+    /// 90th percentile latency
+    pub p90: u64,
+    /// 99th percentile latency
+    pub p99: u64,
+    /// Max latency
+    pub max: u64,
+}
+
+impl LatencyPercentiles {
+    /// Calculate nearest rank percentiles of latency samples. All percentiles
+    /// are zero if there are no samples
+    pub fn from_samples(samples: &[u64]) -> LatencyPercentiles {
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let percentile = |p: usize| {
+            if sorted.len() == 0 {
+                return 0;
+            }
+            sorted[((p * sorted.len() + 99) / 100).max(1) - 1]
+        };
+        LatencyPercentiles {
+            count: sorted.len() as u32,
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: sorted.last().cloned().unwrap_or(0),
+        }
+    }
+}
+
+/// Challenge round-trip latency samples of a request in milliseconds, measured
+/// from the broadcast of each challenge
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChallengeLatency {
+    /// Latency to the confirmed verification of each challenge
+    pub verify_ms: Vec<u64>,
+    /// Latency to the arrival of each challenge proof
+    pub proof_ms: Vec<u64>,
+    /// Latency to the arrival of each credited proof per bid txid, in the
+    /// order that the bid responses were counted
+    pub bid_proof_ms: HashMap<sha256d::Hash, Vec<u64>>,
+}
+
+impl ChallengeLatency {
+    /// Create new ChallengeLatency instance
+    pub fn new() -> ChallengeLatency {
+        ChallengeLatency {
+            verify_ms: vec![],
+            proof_ms: vec![],
+            bid_proof_ms: HashMap::new(),
+        }
+    }
+
+    /// Record the arrival latency of a credited proof of a bid
+    pub fn add_proof(&mut self, bid_txid: sha256d::Hash, proof_ms: u64) {
+        self.proof_ms.push(proof_ms);
+        self.bid_proof_ms
+            .entry(bid_txid)
+            .or_insert_with(Vec::new)
+            .push(proof_ms);
+    }
+
+    /// Get the proof latency percentiles of each bid, ordered by bid txid
+    pub fn bid_percentiles(&self) -> Vec<BidLatency> {
+        let mut bids: Vec<BidLatency> = self
+            .bid_proof_ms
+            .iter()
+            .map(|(txid, samples)| BidLatency {
+                txid: *txid,
+                proof: LatencyPercentiles::from_samples(samples),
+            })
+            .collect();
+        bids.sort_by_key(|bid| bid.txid);
+        bids
+    }
+
+    /// Get the proof latency samples per bid of the responses counted between
+    /// a previous response and a later response of the request, as used for
+    /// the payment of an epoch
+    pub fn bid_proof_ms_since(&self, prev: &Response, response: &Response) -> HashMap<sha256d::Hash, Vec<u64>> {
+        let mut bid_proof_ms = HashMap::new();
+        for (txid, samples) in self.bid_proof_ms.iter() {
+            let start = (prev.bid_responses.get(txid).cloned().unwrap_or(0) as usize).min(samples.len());
+            let end = (response.bid_responses.get(txid).cloned().unwrap_or(0) as usize)
+                .min(samples.len())
+                .max(start);
+            let _ = bid_proof_ms.insert(*txid, samples[start..end].to_vec());
+        }
+        bid_proof_ms
+    }
+
+    /// Get the verification and proof latency percentiles
+    pub fn percentiles(&self) -> (LatencyPercentiles, LatencyPercentiles) {
+        (
+            LatencyPercentiles::from_samples(&self.verify_ms),
+            LatencyPercentiles::from_samples(&self.proof_ms),
+        )
+    }
+}
+
+/// Proof latency percentiles of a bid
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BidLatency {
+    /// Bid txid
+    pub txid: sha256d::Hash,
+    /// Proof arrival latency percentiles
+    pub proof: LatencyPercentiles,
+}
+
+/// Reconciliation of the responses of a bid received by the listener against
+/// the proofs published on the client chain by the bid guardnode
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BidReconciliation {
+    /// Bid txid
+    pub txid: sha256d::Hash,
+    /// Number of responses received by the listener
+    pub listener_responses: u32,
+    /// Number of request challenges with a proof published on the client chain
+    pub onchain_responses: u32,
+    /// Flag set when listener and on-chain responses diverge
+    pub is_divergent: bool,
+}
+
+impl BidReconciliation {
+    /// Create new BidReconciliation flagging divergence of the response counts
+    pub fn new(txid: sha256d::Hash, listener_responses: u32, onchain_responses: u32) -> BidReconciliation {
+        BidReconciliation {
+            txid,
+            listener_responses,
+            onchain_responses,
+            is_divergent: listener_responses != onchain_responses,
+        }
+    }
+}
+
+/// Reconciliation of the response of a request against guardnode proofs
+/// published on the client chain up to a client chain height
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ResponseReconciliation {
+    /// Client chain height that published proofs were scanned up to
+    pub clientchain_height: u32,
+    /// Reconciliation of each request bid
+    pub bids: Vec<BidReconciliation>,
+}
+
+impl ResponseReconciliation {
+    /// Get the bids with divergent listener and on-chain responses
+    pub fn divergent(&self) -> Vec<&BidReconciliation> {
+        self.bids.iter().filter(|bid| bid.is_divergent).collect()
+    }
 }
 
 #[cfg(test)]
@@ -91,4 +469,256 @@ mod tests {
         assert_eq!(2, *resp.bid_responses.get(&hash_b).unwrap());
         assert_eq!(3, *resp.bid_responses.get(&hash_c).unwrap());
     }
+
+    #[test]
+    fn response_integrity() {
+        let mut resp = Response::new();
+        let hash_genesis = resp.integrity_hash(None);
+        assert!(resp.verify_integrity(&[hash_genesis]));
+        assert!(!resp.verify_integrity(&[]));
+
+        let mut txids = HashSet::new();
+        let _ = txids.insert(gen_dummy_hash(1));
+        let _ = txids.insert(gen_dummy_hash(2));
+        let _ = txids.insert(gen_dummy_hash(3));
+        resp.update(&txids);
+        let hash_update = resp.integrity_hash(Some(hash_genesis));
+        assert!(resp.verify_integrity(&[hash_genesis, hash_update]));
+        assert!(!resp.verify_integrity(&[hash_update]));
+        assert!(!resp.verify_integrity(&[hash_genesis]));
+
+        // hash does not depend on bid response order
+        let mut resp_reordered = Response::new();
+        resp_reordered.num_challenges = 1;
+        for i in (1..4).rev() {
+            let _ = resp_reordered.bid_responses.insert(gen_dummy_hash(i), 1);
+        }
+        assert_eq!(hash_update, resp_reordered.integrity_hash(Some(hash_genesis)));
+
+        // retroactive edit detected
+        let _ = resp.bid_responses.insert(gen_dummy_hash(1), 2);
+        assert!(!resp.verify_integrity(&[hash_genesis, hash_update]));
+    }
+
+    #[test]
+    fn response_summary() {
+        let mut resp = Response::new();
+        assert_eq!(
+            ResponseSummary {
+                num_challenges: 0,
+                num_bids: 0,
+                num_responses: 0,
+                response_rate: 0.0,
+            },
+            resp.summary()
+        );
+
+        resp.num_challenges = 4;
+        let _ = resp.bid_responses.insert(gen_dummy_hash(1), 4);
+        let _ = resp.bid_responses.insert(gen_dummy_hash(2), 2);
+        assert_eq!(
+            ResponseSummary {
+                num_challenges: 4,
+                num_bids: 2,
+                num_responses: 6,
+                response_rate: 0.75,
+            },
+            resp.summary()
+        );
+    }
+
+    #[test]
+    fn response_since() {
+        let mut prev = Response::new();
+        prev.num_challenges = 2;
+        prev.challenges = vec![gen_dummy_hash(10), gen_dummy_hash(11)];
+        let _ = prev.bid_responses.insert(gen_dummy_hash(1), 2);
+        let _ = prev.bid_responses.insert(gen_dummy_hash(2), 1);
+
+        let mut resp = Response::new();
+        resp.num_challenges = 5;
+        resp.challenges = vec![
+            gen_dummy_hash(10),
+            gen_dummy_hash(11),
+            gen_dummy_hash(12),
+            gen_dummy_hash(13),
+            gen_dummy_hash(14),
+        ];
+        let _ = resp.bid_responses.insert(gen_dummy_hash(1), 2);
+        let _ = resp.bid_responses.insert(gen_dummy_hash(2), 4);
+        let _ = resp.bid_responses.insert(gen_dummy_hash(3), 1);
+
+        let since = resp.since(&prev);
+        assert_eq!(3, since.num_challenges);
+        assert_eq!(
+            vec![gen_dummy_hash(12), gen_dummy_hash(13), gen_dummy_hash(14)],
+            since.challenges
+        );
+        assert_eq!(2, since.bid_responses.len());
+        assert_eq!(Some(&3), since.bid_responses.get(&gen_dummy_hash(2)));
+        assert_eq!(Some(&1), since.bid_responses.get(&gen_dummy_hash(3)));
+
+        // since empty response is the same response
+        assert_eq!(resp, resp.since(&Response::new()));
+    }
+
+    #[test]
+    fn height_stats_from_challenges() {
+        assert_eq!(0, HeightStats::from_challenges(&[]).len());
+
+        let stats = vec![
+            ChallengeStats {
+                challenge: gen_dummy_hash(1),
+                height: 12,
+                num_bids: 4,
+                num_responses: 3,
+                amount_tag: None,
+                wallet: None,
+            },
+            ChallengeStats {
+                challenge: gen_dummy_hash(2),
+                height: 10,
+                num_bids: 4,
+                num_responses: 0,
+                amount_tag: None,
+                wallet: None,
+            },
+            ChallengeStats {
+                challenge: gen_dummy_hash(3),
+                height: 12,
+                num_bids: 2,
+                num_responses: 2,
+                amount_tag: None,
+                wallet: None,
+            },
+            ChallengeStats {
+                challenge: gen_dummy_hash(4),
+                height: 13,
+                num_bids: 0,
+                num_responses: 0,
+                amount_tag: None,
+                wallet: None,
+            },
+        ];
+        assert_eq!(
+            vec![
+                HeightStats {
+                    height: 10,
+                    num_challenges: 1,
+                    num_bids: 4,
+                    num_responses: 0,
+                    response_rate: 0.0,
+                },
+                HeightStats {
+                    height: 12,
+                    num_challenges: 2,
+                    num_bids: 4,
+                    num_responses: 5,
+                    response_rate: 5.0 / 6.0,
+                },
+                HeightStats {
+                    height: 13,
+                    num_challenges: 1,
+                    num_bids: 0,
+                    num_responses: 0,
+                    response_rate: 0.0,
+                },
+            ],
+            HeightStats::from_challenges(&stats)
+        );
+    }
+
+    #[test]
+    fn response_apply_records() {
+        let mut txids = HashSet::new();
+        let _ = txids.insert(gen_dummy_hash(2));
+        let _ = txids.insert(gen_dummy_hash(1));
+        let records = vec![
+            ChallengeRecord::new(gen_dummy_hash(10), &txids),
+            ChallengeRecord::new(gen_dummy_hash(11), &HashSet::new()),
+            ChallengeRecord::new(gen_dummy_hash(12), &txids),
+        ];
+        assert_eq!(vec![gen_dummy_hash(1), gen_dummy_hash(2)], records[0].responders);
+
+        // rebuild from records
+        let mut rebuilt = Response::new();
+        assert_eq!(3, rebuilt.apply_records(&records));
+        let mut resp = Response::new();
+        for record in records.iter() {
+            resp.challenges.push(record.challenge);
+            resp.update(&record.responders.iter().cloned().collect());
+        }
+        assert_eq!(resp, rebuilt);
+
+        // only records missing from the response are applied
+        let mut behind = Response::new();
+        behind.challenges.push(gen_dummy_hash(10));
+        behind.update(&txids);
+        assert_eq!(2, behind.apply_records(&records));
+        assert_eq!(resp, behind);
+        assert_eq!(0, behind.apply_records(&records));
+        assert_eq!(resp, behind);
+    }
+
+    #[test]
+    fn latency_percentiles() {
+        let empty = LatencyPercentiles::from_samples(&[]);
+        assert_eq!(0, empty.count);
+        assert_eq!(0, empty.p50);
+        assert_eq!(0, empty.max);
+
+        let single = LatencyPercentiles::from_samples(&[7]);
+        assert_eq!(1, single.count);
+        assert_eq!(7, single.p50);
+        assert_eq!(7, single.p99);
+
+        let samples: Vec<u64> = (1..=100).rev().collect();
+        let percentiles = LatencyPercentiles::from_samples(&samples);
+        assert_eq!(100, percentiles.count);
+        assert_eq!(50, percentiles.p50);
+        assert_eq!(90, percentiles.p90);
+        assert_eq!(99, percentiles.p99);
+        assert_eq!(100, percentiles.max);
+
+        let mut latency = ChallengeLatency::new();
+        latency.verify_ms = vec![300, 100, 200];
+        latency.proof_ms = vec![10, 20];
+        let (verify, proof) = latency.percentiles();
+        assert_eq!(200, verify.p50);
+        assert_eq!(300, verify.p90);
+        assert_eq!(10, proof.p50);
+        assert_eq!(20, proof.max);
+    }
+
+    #[test]
+    fn challenge_latency_bid_proofs_test() {
+        let hash_a = gen_dummy_hash(1);
+        let hash_b = gen_dummy_hash(2);
+        let mut latency = ChallengeLatency::new();
+        latency.add_proof(hash_b, 30);
+        latency.add_proof(hash_a, 10);
+        latency.add_proof(hash_a, 20);
+        latency.add_proof(hash_a, 40);
+        assert_eq!(vec![30, 10, 20, 40], latency.proof_ms);
+        assert_eq!(&vec![10, 20, 40], latency.bid_proof_ms.get(&hash_a).unwrap());
+
+        let bids = latency.bid_percentiles();
+        assert_eq!(2, bids.len());
+        assert_eq!(hash_a, bids[0].txid);
+        assert_eq!(3, bids[0].proof.count);
+        assert_eq!(20, bids[0].proof.p50);
+        assert_eq!(hash_b, bids[1].txid);
+        assert_eq!(30, bids[1].proof.max);
+
+        let mut prev = Response::new();
+        let _ = prev.bid_responses.insert(hash_a, 1);
+        let mut response = Response::new();
+        let _ = response.bid_responses.insert(hash_a, 3);
+        let _ = response.bid_responses.insert(hash_b, 1);
+        let since = latency.bid_proof_ms_since(&prev, &response);
+        assert_eq!(&vec![20, 40], since.get(&hash_a).unwrap());
+        assert_eq!(&vec![30], since.get(&hash_b).unwrap());
+        let since = latency.bid_proof_ms_since(&response, &response);
+        assert!(since.get(&hash_a).unwrap().is_empty());
+    }
 }
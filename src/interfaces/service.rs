@@ -2,14 +2,17 @@
 //!
 //! Service chain interface and implementations
 
-use bitcoin::hashes::sha256d;
+use std::str::FromStr;
+
+use bitcoin::{hashes::sha256d, secp256k1::PublicKey, Amount};
 use ocean_rpc::RpcApi;
+use serde_json::Value;
 
 use crate::config::ServiceConfig;
-use crate::error::Result;
+use crate::error::{CError, Error, Result};
 use crate::interfaces::{
-    bid::{Bid, BidSet},
-    request::Request,
+    bid::{Bid, BidLock, BidSet},
+    request::{Request, RequestDeposit},
 };
 use crate::util::ocean::OceanClient;
 
@@ -23,6 +26,42 @@ pub trait Service {
     fn get_request_bids(&self, hash: &sha256d::Hash) -> Result<Option<BidSet>>;
     /// Get service chain blockheight
     fn get_blockheight(&self) -> Result<u64>;
+    /// Get the timestamp of the latest service chain block in unix seconds
+    fn get_block_time(&self) -> Result<u64>;
+    /// Try get the fee deposit of an active request from service chain, if the
+    /// request and its locked output are found
+    fn get_request_deposit(&self, request: &Request) -> Result<Option<RequestDeposit>>;
+    /// Try get the lock output of a bid, by bid transaction hash, from service
+    /// chain along with the height at which the lock expires
+    fn get_bid_lock(&self, txid: &sha256d::Hash) -> Result<Option<BidLock>>;
+}
+
+/// Get the timestamp of the latest block of a chain in unix seconds from the
+/// header of the best block
+pub fn block_time(client: &OceanClient) -> Result<u64> {
+    let hash: String = client.call("getbestblockhash", &[])?;
+    let header: Value = client.call("getblockheader", &[Value::from(hash.as_str())])?;
+    match header["time"].as_u64() {
+        Some(time) => Ok(time),
+        None => Err(Error::from(CError::Generic(format!(
+            "no timestamp in block header {}",
+            hash
+        )))),
+    }
+}
+
+/// Parse the lock expiry height and lock key of a bid lock output script of
+/// the form <height> OP_CHECKLOCKTIMEVERIFY OP_DROP <pubkey> OP_CHECKSIG from
+/// its asm
+pub fn parse_bid_lock_asm(asm: &str) -> Option<(u32, PublicKey)> {
+    let ops: Vec<&str> = asm.split_whitespace().collect();
+    if ops.len() != 5 || ops[1] != "OP_CHECKLOCKTIMEVERIFY" || ops[2] != "OP_DROP" || ops[4] != "OP_CHECKSIG" {
+        return None;
+    }
+    match (u32::from_str(ops[0]), PublicKey::from_str(ops[3])) {
+        (Ok(height), Ok(pubkey)) => Some((height, pubkey)),
+        _ => None,
+    }
 }
 
 /// Rpc implementation of Service using an underlying ocean rpc connection
@@ -34,11 +73,7 @@ pub struct RpcService {
 impl RpcService {
     /// Create an RpcService with underlying rpc client connectivity
     pub fn new(service_config: &ServiceConfig) -> Result<Self> {
-        let client = OceanClient::new(
-            service_config.host.clone(),
-            Some(service_config.user.clone()),
-            Some(service_config.pass.clone()),
-        )?;
+        let client = OceanClient::with_credentials(service_config.host.clone(), service_config.rpc_credentials())?;
 
         let _ = client.get_block_count()?; // check connectivity
 
@@ -85,4 +120,92 @@ impl Service for RpcService {
     fn get_blockheight(&self) -> Result<u64> {
         Ok(self.client.get_block_count()?)
     }
+
+    /// Get the timestamp of the latest service chain block from its header
+    fn get_block_time(&self) -> Result<u64> {
+        block_time(&self.client)
+    }
+
+    /// Try get the fee deposit of an active request from service chain. The
+    /// promised fee is the request start price and the locked amount is the
+    /// value of the unspent request transaction output
+    fn get_request_deposit(&self, request: &Request) -> Result<Option<RequestDeposit>> {
+        let requests = self
+            .client
+            .call::<Vec<Value>>("getrequests", &[Value::from(request.genesis_blockhash.to_string())])?;
+        let txid = request.txid.to_string();
+        let start_price = match requests
+            .iter()
+            .find(|req| req["txid"].as_str() == Some(&txid))
+            .and_then(|req| req["startPrice"].as_f64())
+        {
+            Some(price) => price,
+            None => return Ok(None),
+        };
+        let txout = self
+            .client
+            .call::<Value>("gettxout", &[Value::from(txid), Value::from(0)])?;
+        let locked = match txout["value"].as_f64() {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        Ok(Some(RequestDeposit {
+            txid: request.txid,
+            genesis_blockhash: request.genesis_blockhash,
+            promised: Amount::from_btc(start_price).map_err(|e| CError::Generic(e.to_string()))?,
+            locked: Amount::from_btc(locked).map_err(|e| CError::Generic(e.to_string()))?,
+        }))
+    }
+
+    /// Try get the lock output of a bid from service chain, by finding the
+    /// bid transaction output with a lock script
+    fn get_bid_lock(&self, txid: &sha256d::Hash) -> Result<Option<BidLock>> {
+        let tx = self
+            .client
+            .call::<Value>("getrawtransaction", &[Value::from(txid.to_string()), Value::from(1)])?;
+        let outputs = match tx["vout"].as_array() {
+            Some(outputs) => outputs,
+            None => return Ok(None),
+        };
+        for (vout, output) in outputs.iter().enumerate() {
+            if let Some((lock_height, pubkey)) = output["scriptPubKey"]["asm"].as_str().and_then(parse_bid_lock_asm) {
+                return Ok(Some(BidLock {
+                    txid: *txid,
+                    vout: output["n"].as_u64().unwrap_or(vout as u64) as u32,
+                    value: Amount::from_btc(output["value"].as_f64().unwrap_or(0.0))
+                        .map_err(|e| CError::Generic(e.to_string()))?,
+                    asset: output["asset"].as_str().unwrap_or("").to_owned(),
+                    lock_height,
+                    pubkey,
+                }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bid_lock_asm_test() {
+        let pubkey = "026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3";
+        assert_eq!(
+            Some((1440, PublicKey::from_str(pubkey).unwrap())),
+            parse_bid_lock_asm(&format!("1440 OP_CHECKLOCKTIMEVERIFY OP_DROP {} OP_CHECKSIG", pubkey))
+        );
+        assert_eq!(
+            None,
+            parse_bid_lock_asm(&format!("OP_DUP OP_HASH160 {} OP_EQUALVERIFY OP_CHECKSIG", pubkey))
+        );
+        assert_eq!(
+            None,
+            parse_bid_lock_asm(&format!("1440 OP_CHECKSEQUENCEVERIFY OP_DROP {} OP_CHECKSIG", pubkey))
+        );
+        assert_eq!(
+            None,
+            parse_bid_lock_asm("1440 OP_CHECKLOCKTIMEVERIFY OP_DROP 00 OP_CHECKSIG")
+        );
+    }
 }
@@ -2,24 +2,37 @@
 //!
 //! Storage interface and implementations
 
+use std::collections::HashSet;
 use std::mem::drop;
-use std::sync::{Mutex, MutexGuard};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use bitcoin::hashes::sha256d;
+use bitcoin::hashes::{hex::FromHex, sha256d};
+use mongodb::common::{ReadMode, ReadPreference, WriteConcern};
 use mongodb::db::{Database, ThreadedDatabase};
+use mongodb::ordered::OrderedDocument;
 use mongodb::{
-    coll::options::{FindOptions, UpdateOptions},
-    Client, ThreadedClient,
+    coll::options::{FindOptions, UpdateOptions, WriteModel},
+    Bson, Client, ClientOptions, CommandType, Error as MongoDbError, ThreadedClient,
 };
 
 use crate::config::StorageConfig;
-use crate::error::{Error::MongoDb, Result};
-use crate::interfaces::response::Response;
+use crate::coverage::ChallengeCoverage;
+use crate::dead_letter::DeadLetter;
+use crate::error::{CError, Error::MongoDb, Result};
+use crate::interfaces::response::{
+    BidProof, ChallengeLatency, ChallengeRecord, ChallengeStats, Response, ResponseReconciliation, ResponseSnapshot,
+    ResponseSummary,
+};
 use crate::interfaces::{
-    bid::{Bid, BidSet},
-    request::Request,
+    bid::{Bid, BidBlacklisting, BidKeyRotation, BidRefund, BidSet, PaymentLiability},
+    request::{FeePool, Request, RequestAnnotation, RequestCursor, RequestDeposit, RequestFull, RequestRejection},
 };
+use crate::stall::ClientChainStall;
 use crate::util::doc_format::*;
+use crate::util::encryption::init_field_cipher;
 
 /// Storage trait defining required functionality for objects that store request
 /// and challenge information
@@ -30,19 +43,221 @@ pub trait Storage {
     fn update_request(&self, request: &Request) -> Result<()>;
     /// Update bid in storage
     fn update_bid(&self, request_hash: sha256d::Hash, bid: &Bid) -> Result<()>;
+    /// Update multiple bids of a specific request in a single storage write
+    fn update_bids(&self, request_hash: sha256d::Hash, bids: &[Bid]) -> Result<()>;
+    /// Remove bids of a specific request by bid txid
+    fn remove_bids(&self, request_hash: sha256d::Hash, bids: &[sha256d::Hash]) -> Result<()>;
     /// Store response for a specific challenge request
     fn save_response(&self, request_hash: sha256d::Hash, response: &Response) -> Result<()>;
+    /// Apply the bid responses of a single challenge to the stored response of
+    /// a specific request without rewriting the full response, extending the
+    /// integrity hash chain with the hash of the updated response
+    fn update_response(
+        &self,
+        request_hash: sha256d::Hash,
+        challenge: sha256d::Hash,
+        responses: &HashSet<sha256d::Hash>,
+        response: &Response,
+    ) -> Result<()>;
+    /// Append the record of the bid responses to a single challenge of a
+    /// specific request. Records are never overwritten
+    fn save_challenge_record(&self, request_hash: sha256d::Hash, record: &ChallengeRecord) -> Result<()>;
+    /// Get the challenge records of a specific request in the order they were
+    /// saved
+    fn get_challenge_records(&self, request_hash: sha256d::Hash) -> Result<Vec<ChallengeRecord>>;
+    /// Store the response stats of a single challenge of a specific request.
+    /// Stats are never overwritten and are kept once the response is compacted
+    fn save_challenge_stats(&self, request_hash: sha256d::Hash, stats: &ChallengeStats) -> Result<()>;
+    /// Get the challenge response stats of a specific request ordered by the
+    /// service chain height that the challenges were issued at
+    fn get_challenge_stats(&self, request_hash: sha256d::Hash) -> Result<Vec<ChallengeStats>>;
+    /// Store the accepted challenge proofs of the bids of a specific request.
+    /// Saving the proof of a bid to a challenge again leaves the first proof
+    /// unchanged
+    fn save_bid_proofs(&self, request_hash: sha256d::Hash, proofs: &[BidProof]) -> Result<()>;
+    /// Get the accepted challenge proofs of a bid of a specific request ordered
+    /// by the time they were accepted at
+    fn get_bid_proofs(&self, request_hash: sha256d::Hash, bid_hash: sha256d::Hash) -> Result<Vec<BidProof>>;
     /// Get challenge response for a specific request
     fn get_response(&self, request_hash: sha256d::Hash) -> Result<Option<Response>>;
+    /// Get the integrity hash chain of the response updates of a specific
+    /// request, the last hash corresponding to the latest response
+    fn get_response_hashes(&self, request_hash: sha256d::Hash) -> Result<Vec<sha256d::Hash>>;
+    /// Compact the response of a specific request by storing the response
+    /// summary on the request and removing the per bid responses, challenge
+    /// records and bid proofs
+    fn compact_response(&self, request_hash: sha256d::Hash, summary: &ResponseSummary) -> Result<()>;
+    /// Store the response snapshot of a payment epoch for a specific request,
+    /// replacing any previous snapshot of the epoch
+    fn save_response_snapshot(&self, request_hash: sha256d::Hash, snapshot: &ResponseSnapshot) -> Result<()>;
+    /// Get the payment epoch response snapshots of a specific request ordered
+    /// by epoch
+    fn get_response_snapshots(&self, request_hash: sha256d::Hash) -> Result<Vec<ResponseSnapshot>>;
+    /// Store the challenge latency samples of a specific request along with
+    /// their percentiles
+    fn save_challenge_latency(&self, request_hash: sha256d::Hash, latency: &ChallengeLatency) -> Result<()>;
+    /// Get the challenge latency samples of a specific request
+    fn get_challenge_latency(&self, request_hash: sha256d::Hash) -> Result<Option<ChallengeLatency>>;
+    /// Store the challenge coverage of a specific request
+    fn save_challenge_coverage(&self, request_hash: sha256d::Hash, coverage: &ChallengeCoverage) -> Result<()>;
+    /// Get the challenge coverage of a specific request
+    fn get_challenge_coverage(&self, request_hash: sha256d::Hash) -> Result<Option<ChallengeCoverage>>;
+    /// Store a client chain stall period of a specific request
+    fn save_clientchain_stall(&self, request_hash: sha256d::Hash, stall: &ClientChainStall) -> Result<()>;
+    /// Get the client chain stall periods of a specific request ordered by
+    /// start time
+    fn get_clientchain_stalls(&self, request_hash: sha256d::Hash) -> Result<Vec<ClientChainStall>>;
+    /// Store the reconciliation of a request response against on-chain proofs
+    fn save_response_reconciliation(
+        &self,
+        request_hash: sha256d::Hash,
+        reconciliation: &ResponseReconciliation,
+    ) -> Result<()>;
+    /// Get the reconciliation of a request response against on-chain proofs
+    fn get_response_reconciliation(&self, request_hash: sha256d::Hash) -> Result<Option<ResponseReconciliation>>;
+    /// Store the fee pool of a finished request
+    fn save_fee_pool(&self, request_hash: sha256d::Hash, pool: &FeePool) -> Result<()>;
+    /// Get the fee pool of a finished request
+    fn get_fee_pool(&self, request_hash: sha256d::Hash) -> Result<Option<FeePool>>;
     /// Get all bids for a specific request
     fn get_bids(&self, request_hash: sha256d::Hash) -> Result<Vec<Bid>>;
+    /// Get a single bid of a specific request by bid txid
+    fn get_bid(&self, request_hash: sha256d::Hash, bid_hash: sha256d::Hash) -> Result<Option<Bid>>;
     /// Get all the requests, with an optional flag to return payment complete
-    /// only
-    fn get_requests(&self, complete: Option<bool>, limit: Option<i64>, skip: Option<i64>) -> Result<Vec<Request>>;
-    /// Get the number of requests in storage
-    fn get_requests_count(&self) -> Result<i64>;
+    /// only and an optional genesis hash to return a single tenant's requests
+    fn get_requests(
+        &self,
+        complete: Option<bool>,
+        genesis: Option<sha256d::Hash>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<Request>>;
+    /// Get requests joined with their bids and responses, with an optional
+    /// genesis hash to return a single tenant's requests
+    fn get_requests_full(
+        &self,
+        genesis: Option<sha256d::Hash>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<RequestFull>>;
+    /// Get a page of requests in the deterministic order of requests by start
+    /// height and txid, starting after the cursor if set, with an optional
+    /// genesis hash to return a single tenant's requests
+    fn get_requests_page(
+        &self,
+        genesis: Option<sha256d::Hash>,
+        after: Option<&RequestCursor>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<Request>>;
+    /// Get the number of requests in storage, optionally for a genesis hash
+    fn get_requests_count(&self, genesis: Option<sha256d::Hash>) -> Result<i64>;
+    /// Get requests ordered by the time they were last updated in storage,
+    /// most recently updated first, with an optional genesis hash to return a
+    /// single tenant's requests
+    fn get_requests_recent(
+        &self,
+        genesis: Option<sha256d::Hash>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<Request>>;
     /// Get request for a specific request txid
     fn get_request(&self, request_hash: sha256d::Hash) -> Result<Option<Request>>;
+    /// Store log lines for a specific request, keeping only the most recent
+    /// STORAGE_REQUEST_LOGS_LIMIT lines
+    fn save_request_logs(&self, request_hash: sha256d::Hash, logs: &[String]) -> Result<()>;
+    /// Get stored log lines for a specific request
+    fn get_request_logs(&self, request_hash: sha256d::Hash) -> Result<Vec<String>>;
+    /// Store the result of the fee deposit check of a request
+    fn save_request_deposit(&self, deposit: &RequestDeposit) -> Result<()>;
+    /// Get stored request fee deposits, with an optional flag to return
+    /// verified or unverified deposits only and an optional genesis hash
+    fn get_request_deposits(
+        &self,
+        verified: Option<bool>,
+        genesis: Option<sha256d::Hash>,
+    ) -> Result<Vec<RequestDeposit>>;
+    /// Store the rejection of a request that failed validation
+    fn save_request_rejection(&self, rejection: &RequestRejection) -> Result<()>;
+    /// Get stored request rejections, with an optional genesis hash
+    fn get_request_rejections(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<RequestRejection>>;
+    /// Store the operator annotation of a specific request, replacing any
+    /// previous annotation of the request
+    fn save_request_annotation(&self, request_hash: sha256d::Hash, annotation: &RequestAnnotation) -> Result<()>;
+    /// Get the operator annotation of a specific request
+    fn get_request_annotation(&self, request_hash: sha256d::Hash) -> Result<Option<RequestAnnotation>>;
+    /// Get the hashes of the requests annotated with a label
+    fn get_labelled_requests(&self, label: &str) -> Result<Vec<sha256d::Hash>>;
+    /// Store the dead letter of a listener submission that could not be
+    /// parsed, keeping only the most recent STORAGE_DEAD_LETTERS_LIMIT dead
+    /// letters
+    fn save_dead_letter(&self, letter: &DeadLetter) -> Result<()>;
+    /// Get up to a limit of the most recent stored dead letters, most recent
+    /// first
+    fn get_dead_letters(&self, limit: u64) -> Result<Vec<DeadLetter>>;
+    /// Store the blacklisting of a bid after repeated invalid proofs
+    fn save_bid_blacklisting(&self, blacklisting: &BidBlacklisting) -> Result<()>;
+    /// Get stored bid blacklistings, with an optional genesis hash
+    fn get_bid_blacklistings(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<BidBlacklisting>>;
+    /// Store the rotation of the key of a bid, updating the pubkey of the
+    /// stored bid
+    fn save_bid_key_rotation(&self, rotation: &BidKeyRotation) -> Result<()>;
+    /// Get the stored bid key rotations of a specific request ordered by time
+    fn get_bid_key_rotations(&self, request_hash: sha256d::Hash) -> Result<Vec<BidKeyRotation>>;
+    /// Store the refund of the lock of a bid, replacing any previous refund of
+    /// the bid
+    fn save_bid_refund(&self, refund: &BidRefund) -> Result<()>;
+    /// Get stored bid lock refunds, with an optional genesis hash
+    fn get_bid_refunds(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<BidRefund>>;
+    /// Store the liability of the deferred bid payments of a request, or
+    /// request epoch, replacing any previous liability of the request epoch
+    fn save_payment_liability(&self, liability: &PaymentLiability) -> Result<()>;
+    /// Remove the liability of the deferred bid payments of a request, or
+    /// request epoch, once the payments are made
+    fn remove_payment_liability(&self, request_hash: sha256d::Hash, epoch: Option<u32>) -> Result<()>;
+    /// Get stored payment liabilities, with an optional genesis hash
+    fn get_payment_liabilities(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<PaymentLiability>>;
+}
+
+/// Max number of log lines stored per request
+pub const STORAGE_REQUEST_LOGS_LIMIT: usize = 500;
+
+/// Max number of dead letters stored
+pub const STORAGE_DEAD_LETTERS_LIMIT: usize = 1000;
+
+/// Build Request collection filter from optional payment complete flag and
+/// genesis hash
+fn requests_filter(complete: Option<bool>, genesis: Option<sha256d::Hash>) -> OrderedDocument {
+    let mut filter = doc! {};
+    if let Some(is_complete) = complete {
+        let _ = filter.insert("is_payment_complete", is_complete);
+    }
+    if let Some(genesis_hash) = genesis {
+        let _ = filter.insert("genesis_blockhash", genesis_hash.to_string());
+    }
+    filter
+}
+
+/// Interval between attempts to acquire the storage connection lock
+const STORAGE_LOCK_RETRY_MILLIS: u64 = 10;
+
+/// Last updated time recorded for a request write
+static STORAGE_LAST_UPDATED_AT: AtomicU64 = AtomicU64::new(0);
+
+/// Get the time in ms since the unix epoch to record as the last updated time
+/// of a request write. Times are strictly increasing so that requests written
+/// within the same ms keep the order they were written in
+pub fn updated_at_ms() -> u64 {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let now = since_epoch.as_secs() * 1000 + since_epoch.subsec_millis() as u64;
+    let mut last = STORAGE_LAST_UPDATED_AT.load(Ordering::SeqCst);
+    loop {
+        let next = now.max(last + 1);
+        match STORAGE_LAST_UPDATED_AT.compare_exchange(last, next, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => return next,
+            Err(actual) => last = actual,
+        }
+    }
 }
 
 /// Database implementation of Storage trait
@@ -54,10 +269,10 @@ pub struct MongoStorage {
 }
 
 impl MongoStorage {
-    /// Create DbStorage instance
-    pub fn new(storage_config: StorageConfig) -> Result<Self> {
-        let uri = &format!("mongodb://{}/{}", storage_config.host, storage_config.name);
-        let client = Client::with_uri(&uri)?;
+    /// Connect to the coordinator db at a uri, authenticating with the
+    /// user/pass from config
+    fn connect(uri: &str, storage_config: &StorageConfig, options: ClientOptions) -> Result<Database> {
+        let client = Client::with_uri_and_options(uri, options)?;
 
         let db = client.db("coordinator");
         if let Some(ref user) = storage_config.user {
@@ -65,17 +280,149 @@ impl MongoStorage {
                 db.auth(user, pass)?;
             }
         }
+        Ok(db)
+    }
+
+    /// Create DbStorage instance
+    pub fn new(storage_config: StorageConfig) -> Result<Self> {
+        let uri = &format!(
+            "mongodb://{}/{}?connectTimeoutMS={}&socketTimeoutMS={}",
+            storage_config.host,
+            storage_config.name,
+            storage_config.connect_timeout * 1000,
+            storage_config.read_timeout * 1000
+        );
+        let mut options = ClientOptions::new();
+        options.server_selection_timeout_ms = (storage_config.connect_timeout * 1000) as i64;
+        options.write_concern = WriteConcern {
+            w_timeout: (storage_config.write_timeout * 1000) as i32,
+            ..WriteConcern::new()
+        };
+        let db = MongoStorage::connect(uri, &storage_config, options)?;
+        init_field_cipher(&storage_config)?;
 
         // Specify collections Indexes
         if let Err(e) = db.collection("Request").create_index(doc! ("txid":1), None) {
             return Err(MongoDb(e));
         }
+        if let Err(e) = db
+            .collection("Request")
+            .create_index(doc! ("start_blockheight":1, "txid":1), None)
+        {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db.collection("Request").create_index(doc! ("updated_at":1), None) {
+            return Err(MongoDb(e));
+        }
         if let Err(e) = db.collection("Bid").create_index(doc! ("request_id":1), None) {
             return Err(MongoDb(e));
         }
         if let Err(e) = db.collection("Response").create_index(doc! ("request_id":1), None) {
             return Err(MongoDb(e));
         }
+        if let Err(e) = db
+            .collection("ChallengeRecord")
+            .create_index(doc! ("txid":1, "challenge":1), None)
+        {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db
+            .collection("ChallengeStats")
+            .create_index(doc! ("txid":1, "height":1), None)
+        {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db
+            .collection("BidProof")
+            .create_index(doc! ("txid":1, "bid":1, "timestamp":1), None)
+        {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db.collection("RequestLogs").create_index(doc! ("txid":1), None) {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db.collection("RequestDeposit").create_index(doc! ("txid":1), None) {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db
+            .collection("ResponseSnapshot")
+            .create_index(doc! ("txid":1, "epoch":1), None)
+        {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db.collection("ChallengeLatency").create_index(doc! ("txid":1), None) {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db.collection("ChallengeCoverage").create_index(doc! ("txid":1), None) {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db
+            .collection("ClientChainStall")
+            .create_index(doc! ("txid":1, "start_time":1), None)
+        {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db.collection("RequestRejection").create_index(doc! ("txid":1), None) {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db.collection("RequestAnnotation").create_index(doc! ("txid":1), None) {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db.collection("RequestAnnotation").create_index(doc! ("labels":1), None) {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db
+            .collection("BidBlacklisting")
+            .create_index(doc! ("txid":1, "since":1), None)
+        {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db
+            .collection("BidKeyRotation")
+            .create_index(doc! ("request":1, "time":1), None)
+        {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db.collection("BidRefund").create_index(doc! ("txid":1), None) {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db
+            .collection("ResponseReconciliation")
+            .create_index(doc! ("txid":1), None)
+        {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db.collection("FeePool").create_index(doc! ("txid":1), None) {
+            return Err(MongoDb(e));
+        }
+        if let Err(e) = db
+            .collection("PaymentLiability")
+            .create_index(doc! ("request":1, "epoch":1), None)
+        {
+            return Err(MongoDb(e));
+        }
+
+        MongoStorage::migrate_amounts(&db)?;
+
+        Ok(MongoStorage {
+            db: Mutex::new(db),
+            config: storage_config,
+        })
+    }
+
+    /// Create DbStorage instance of the read replica at the read uri of the
+    /// config, preferring secondaries. Indexes and migrations are left to the
+    /// primary as the replica is only read from
+    pub fn new_read_replica(storage_config: StorageConfig) -> Result<Self> {
+        let uri = match storage_config.read_uri {
+            Some(ref uri) => uri.clone(),
+            None => return Err(CError::Generic("storage read_uri not set".to_owned()).into()),
+        };
+        let mut options = ClientOptions::new();
+        options.server_selection_timeout_ms = (storage_config.connect_timeout * 1000) as i64;
+        options.read_preference = Some(ReadPreference::new(ReadMode::SecondaryPreferred, None));
+        let db = MongoStorage::connect(&uri, &storage_config, options)?;
+        init_field_cipher(&storage_config)?;
 
         Ok(MongoStorage {
             db: Mutex::new(db),
@@ -83,6 +430,84 @@ impl MongoStorage {
         })
     }
 
+    /// Get the time in seconds of the last write applied by the connected
+    /// member, as reported by isMaster for replica set members, or None if
+    /// not reported
+    pub fn last_write_time(&self) -> Result<Option<i64>> {
+        let db_locked = self.lock_db("last_write_time")?;
+        let res = db_locked.command(doc! {"isMaster": 1}, CommandType::IsMaster, None)?;
+        drop(db_locked);
+        // optime timestamps hold the seconds in their high 32 bits
+        Ok(
+            match res.get_document("lastWrite").ok().and_then(|last_write| {
+                last_write
+                    .get_document("opTime")
+                    .ok()
+                    .and_then(|op_time| op_time.get("ts").cloned())
+            }) {
+                Some(Bson::TimeStamp(ts)) => Some(ts >> 32),
+                _ => None,
+            },
+        )
+    }
+
+    /// Migrate documents with payment amounts stored as floating point btc
+    /// values by earlier versions to integer satoshi amounts
+    fn migrate_amounts(db: &Database) -> Result<()> {
+        let bids = db.collection("Bid");
+        for doc in bids.find(Some(doc! {"payment.amount": {"$type": "double"}}), None)? {
+            let doc = doc?;
+            let bid = bid_to_doc(doc.get("request_id").unwrap(), &doc_to_bid(&doc)?);
+            let filter = doc! {"_id": doc.get("_id").unwrap().clone()};
+            let _ = bids.update_one(filter, doc! {"$set" => bid}, None)?;
+        }
+
+        let deposits = db.collection("RequestDeposit");
+        let deposits_filter = doc! {"$or": [{"promised": {"$type": "double"}}, {"locked": {"$type": "double"}}]};
+        for doc in deposits.find(Some(deposits_filter), None)? {
+            let doc = doc?;
+            let deposit = request_deposit_to_doc(&doc_to_request_deposit(&doc));
+            let filter = doc! {"_id": doc.get("_id").unwrap().clone()};
+            let _ = deposits.update_one(filter, doc! {"$set" => deposit}, None)?;
+        }
+
+        let snapshots = db.collection("ResponseSnapshot");
+        for doc in snapshots.find(Some(doc! {"bids.payment.amount": {"$type": "double"}}), None)? {
+            let doc = doc?;
+            let request_id = doc.get("txid").unwrap();
+            let bids: Vec<Bson> = doc_to_response_snapshot(&doc)?
+                .bids
+                .iter()
+                .map(|bid| Bson::Document(bid_to_doc(request_id, bid)))
+                .collect();
+            let filter = doc! {"_id": doc.get("_id").unwrap().clone()};
+            let _ = snapshots.update_one(filter, doc! {"$set" => {"bids": bids}}, None)?;
+        }
+        Ok(())
+    }
+
+    /// Acquire the db connection for a storage operation, failing with a
+    /// storage timeout error if it is not acquired within the operation timeout
+    /// as the connection is held by an operation on a hung connection
+    fn lock_db(&self, operation: &str) -> Result<MutexGuard<Database>> {
+        let deadline = Instant::now() + Duration::from_secs(self.config.operation_timeout);
+        let db_locked = loop {
+            match self.db.try_lock() {
+                Ok(db_locked) => break db_locked,
+                Err(TryLockError::Poisoned(e)) => break e.into_inner(),
+                Err(TryLockError::WouldBlock) => {
+                    if Instant::now() >= deadline {
+                        warn!("storage operation {} timed out waiting for connection", operation);
+                        return Err(CError::StorageTimeout(operation.to_owned()).into());
+                    }
+                    thread::sleep(Duration::from_millis(STORAGE_LOCK_RETRY_MILLIS));
+                }
+            }
+        };
+        self.auth(&db_locked)?;
+        Ok(db_locked)
+    }
+
     /// Do db authentication using user/pass from config
     fn auth(&self, db_locked: &MutexGuard<Database>) -> Result<()> {
         match db_locked.list_collections(None) {
@@ -103,8 +528,7 @@ impl MongoStorage {
 impl Storage for MongoStorage {
     /// Store the state of a challenge request
     fn save_challenge_request_state(&self, request: &Request, bids: &BidSet) -> Result<()> {
-        let db_locked = self.db.lock().unwrap();
-        self.auth(&db_locked)?;
+        let db_locked = self.lock_db("save_challenge_request_state")?;
 
         let request_id;
         let coll = db_locked.collection("Request");
@@ -114,7 +538,9 @@ impl Storage for MongoStorage {
                 request_id = res.get("_id").unwrap().clone();
             }
             None => {
-                request_id = coll.insert_one(request_to_doc(&request), None)?.inserted_id.unwrap();
+                let mut request_doc = request_to_doc(&request);
+                let _ = request_doc.insert("updated_at", updated_at_ms() as i64);
+                request_id = coll.insert_one(request_doc, None)?.inserted_id.unwrap();
             }
         }
 
@@ -131,21 +557,25 @@ impl Storage for MongoStorage {
         Ok(())
     }
 
-    /// Update entry in Request collection with given Request object
+    /// Update entry in Request collection with given Request object, removing
+    /// any failure recorded if the request no longer has one
     fn update_request(&self, request: &Request) -> Result<()> {
-        let db_locked = self.db.lock().unwrap();
-        self.auth(&db_locked)?;
+        let db_locked = self.lock_db("update_request")?;
         let coll = db_locked.collection("Request");
         let filter = doc! {"txid"=>&request.txid.clone().to_string()};
-        let update = doc! {"$set" => request_to_doc(&request)};
+        let mut request_doc = request_to_doc(&request);
+        let _ = request_doc.insert("updated_at", updated_at_ms() as i64);
+        let mut update = doc! {"$set" => request_doc};
+        if request.failure.is_none() {
+            let _ = update.insert("$unset", doc! {"failure": ""});
+        }
         let _ = coll.update_one(filter, update, None)?;
         Ok(())
     }
 
     /// Update entry in Bid collection with given Bid object
     fn update_bid(&self, request_hash: sha256d::Hash, bid: &Bid) -> Result<()> {
-        let db_locked = self.db.lock().unwrap();
-        self.auth(&db_locked)?;
+        let db_locked = self.lock_db("update_bid")?;
 
         let request_id = db_locked
             .collection("Request")
@@ -167,10 +597,117 @@ impl Storage for MongoStorage {
         Ok(())
     }
 
-    /// Store response for a specific challenge request
+    /// Update multiple bids of a specific request with a single bulk write
+    /// instead of a storage round trip per bid
+    fn update_bids(&self, request_hash: sha256d::Hash, bids: &[Bid]) -> Result<()> {
+        if bids.len() == 0 {
+            return Ok(());
+        }
+        let db_locked = self.lock_db("update_bids")?;
+
+        let request_id = db_locked
+            .collection("Request")
+            .find_one(
+                Some(doc! {
+                    "txid": request_hash.to_string(),
+                }),
+                None,
+            )?
+            .unwrap()
+            .get("_id")
+            .unwrap()
+            .clone();
+
+        let updates = bids
+            .iter()
+            .map(|bid| WriteModel::UpdateOne {
+                filter: doc! {"request_id": request_id.clone(), "txid": bid.txid.to_string()},
+                update: doc! {"$set" => bid_to_doc(&request_id, bid)},
+                upsert: None,
+            })
+            .collect();
+        let result = db_locked.collection("Bid").bulk_write(updates, true);
+        if let Some(e) = result.bulk_write_exception {
+            return Err(MongoDb(MongoDbError::BulkWriteError(e)));
+        }
+        Ok(())
+    }
+
+    /// Remove bids of a specific request by bid txid
+    fn remove_bids(&self, request_hash: sha256d::Hash, bids: &[sha256d::Hash]) -> Result<()> {
+        if bids.len() == 0 {
+            return Ok(());
+        }
+        let db_locked = self.lock_db("remove_bids")?;
+
+        let request_id = db_locked
+            .collection("Request")
+            .find_one(
+                Some(doc! {
+                    "txid": request_hash.to_string(),
+                }),
+                None,
+            )?
+            .unwrap()
+            .get("_id")
+            .unwrap()
+            .clone();
+
+        let txids: Vec<Bson> = bids.iter().map(|bid| Bson::String(bid.to_string())).collect();
+        let _ = db_locked
+            .collection("Bid")
+            .delete_many(doc! {"request_id": request_id, "txid": {"$in": txids}}, None)?;
+        Ok(())
+    }
+
+    /// Store response for a specific challenge request, extending the response
+    /// integrity hash chain with the hash of the response
     fn save_response(&self, request_hash: sha256d::Hash, response: &Response) -> Result<()> {
-        let db_locked = self.db.lock().unwrap();
-        self.auth(&db_locked)?;
+        let db_locked = self.lock_db("save_response")?;
+
+        let request_id = db_locked
+            .collection("Request")
+            .find_one(
+                Some(doc! {
+                    "txid": request_hash.to_string(),
+                }),
+                None,
+            )?
+            .unwrap()
+            .get("_id")
+            .unwrap()
+            .clone();
+
+        let coll = db_locked.collection("Response");
+        let filter = doc! {"request_id": request_id.clone()};
+        let prev_hash = match coll.find_one(Some(filter.clone()), None)? {
+            Some(doc) => doc_to_response_hashes(&doc).last().cloned(),
+            None => None,
+        };
+        let update = doc! {
+            "$set" => response_to_doc(&request_id, &response),
+            "$push" => doc! {"hashes" => response.integrity_hash(prev_hash).to_string()}
+        };
+        let options = UpdateOptions {
+            upsert: Some(true),
+            ..Default::default()
+        };
+        let _ = coll.update_one(filter, update, Some(options))?;
+        Ok(())
+    }
+
+    /// Apply the bid responses of a single challenge to the stored response by
+    /// incrementing the response counts of the responding bids and pushing the
+    /// challenge hash, extending the response integrity hash chain with the
+    /// hash of the updated response
+    fn update_response(
+        &self,
+        request_hash: sha256d::Hash,
+        challenge: sha256d::Hash,
+        responses: &HashSet<sha256d::Hash>,
+        response: &Response,
+    ) -> Result<()> {
+        let db_locked = self.lock_db("update_response")?;
 
         let request_id = db_locked
             .collection("Request")
@@ -187,7 +724,82 @@ impl Storage for MongoStorage {
 
         let coll = db_locked.collection("Response");
         let filter = doc! {"request_id": request_id.clone()};
-        let update = doc! {"$set" => response_to_doc(&request_id, &response)};
+        // only fetch the last hash of the chain
+        let mut options = FindOptions::new();
+        options.projection = Some(doc! {"hashes": {"$slice": -1}, "bid_responses": 0});
+        let prev_hash = match coll.find_one(Some(filter.clone()), Some(options))? {
+            Some(doc) => doc_to_response_hashes(&doc).last().cloned(),
+            None => None,
+        };
+        let mut inc = doc! {"num_challenges": 1i32};
+        for txid in responses.iter() {
+            let _ = inc.insert(format!("bid_responses.{}", txid), 1i32);
+        }
+        let mut update = doc! {
+            "$inc" => inc,
+            "$push" => doc! {
+                "challenges" => challenge.to_string(),
+                "hashes" => response.integrity_hash(prev_hash).to_string()
+            }
+        };
+        if responses.is_empty() {
+            // bid responses are required on the first update of the response
+            let _ = update.insert("$setOnInsert", doc! {"bid_responses": doc! {}});
+        }
+        let options = UpdateOptions {
+            upsert: Some(true),
+            ..Default::default()
+        };
+        let _ = coll.update_one(filter, update, Some(options))?;
+        Ok(())
+    }
+
+    /// Append the record of a single challenge of a specific request. Saving
+    /// the record of a challenge again leaves the first record unchanged
+    fn save_challenge_record(&self, request_hash: sha256d::Hash, record: &ChallengeRecord) -> Result<()> {
+        let db_locked = self.lock_db("save_challenge_record")?;
+
+        let coll = db_locked.collection("ChallengeRecord");
+        let filter = doc! {"txid": request_hash.to_string(), "challenge": record.challenge.to_string()};
+        let update = doc! {"$setOnInsert" => challenge_record_to_doc(&request_hash, record)};
+        let options = UpdateOptions {
+            upsert: Some(true),
+            ..Default::default()
+        };
+        let _ = coll.update_one(filter, update, Some(options))?;
+        Ok(())
+    }
+
+    /// Get the challenge records of a specific request in the order they were
+    /// saved
+    fn get_challenge_records(&self, request_hash: sha256d::Hash) -> Result<Vec<ChallengeRecord>> {
+        let db_locked = self.lock_db("get_challenge_records")?;
+
+        let mut options = FindOptions::new();
+        options.sort = Some(doc! { "_id" : 1 });
+        let resps = db_locked.collection("ChallengeRecord").find(
+            Some(doc! {
+                "txid": request_hash.to_string(),
+            }),
+            Some(options),
+        )?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut records = vec![];
+        for resp in resps {
+            records.push(doc_to_challenge_record(&resp?));
+        }
+        Ok(records)
+    }
+
+    /// Store the response stats of a single challenge of a specific request.
+    /// Saving the stats of a challenge again leaves the first stats unchanged
+    fn save_challenge_stats(&self, request_hash: sha256d::Hash, stats: &ChallengeStats) -> Result<()> {
+        let db_locked = self.lock_db("save_challenge_stats")?;
+
+        let coll = db_locked.collection("ChallengeStats");
+        let filter = doc! {"txid": request_hash.to_string(), "challenge": stats.challenge.to_string()};
+        let update = doc! {"$setOnInsert" => challenge_stats_to_doc(&request_hash, stats)};
         let options = UpdateOptions {
             upsert: Some(true),
             ..Default::default()
@@ -196,10 +808,82 @@ impl Storage for MongoStorage {
         Ok(())
     }
 
+    /// Get the challenge response stats of a specific request ordered by
+    /// service chain height
+    fn get_challenge_stats(&self, request_hash: sha256d::Hash) -> Result<Vec<ChallengeStats>> {
+        let db_locked = self.lock_db("get_challenge_stats")?;
+
+        let mut options = FindOptions::new();
+        options.sort = Some(doc! { "height" : 1, "_id": 1 });
+        let resps = db_locked.collection("ChallengeStats").find(
+            Some(doc! {
+                "txid": request_hash.to_string(),
+            }),
+            Some(options),
+        )?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut stats = vec![];
+        for resp in resps {
+            stats.push(doc_to_challenge_stats(&resp?));
+        }
+        Ok(stats)
+    }
+
+    /// Store the accepted challenge proofs of the bids of a specific request.
+    /// Saving the proof of a bid to a challenge again leaves the first proof
+    /// unchanged
+    fn save_bid_proofs(&self, request_hash: sha256d::Hash, proofs: &[BidProof]) -> Result<()> {
+        if proofs.is_empty() {
+            return Ok(());
+        }
+        let db_locked = self.lock_db("save_bid_proofs")?;
+
+        let updates = proofs
+            .iter()
+            .map(|proof| WriteModel::UpdateOne {
+                filter: doc! {
+                    "txid": request_hash.to_string(),
+                    "bid": proof.bid.to_string(),
+                    "challenge": proof.challenge.to_string(),
+                },
+                update: doc! {"$setOnInsert" => bid_proof_to_doc(&request_hash, proof)},
+                upsert: Some(true),
+            })
+            .collect();
+        let result = db_locked.collection("BidProof").bulk_write(updates, true);
+        if let Some(e) = result.bulk_write_exception {
+            return Err(MongoDb(MongoDbError::BulkWriteError(e)));
+        }
+        Ok(())
+    }
+
+    /// Get the accepted challenge proofs of a bid of a specific request ordered
+    /// by the time they were accepted at
+    fn get_bid_proofs(&self, request_hash: sha256d::Hash, bid_hash: sha256d::Hash) -> Result<Vec<BidProof>> {
+        let db_locked = self.lock_db("get_bid_proofs")?;
+
+        let mut options = FindOptions::new();
+        options.sort = Some(doc! { "timestamp" : 1 });
+        let resps = db_locked.collection("BidProof").find(
+            Some(doc! {
+                "txid": request_hash.to_string(),
+                "bid": bid_hash.to_string(),
+            }),
+            Some(options),
+        )?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut proofs = vec![];
+        for resp in resps {
+            proofs.push(doc_to_bid_proof(&resp?));
+        }
+        Ok(proofs)
+    }
+
     /// Get challenge response for a specific request
     fn get_response(&self, request_hash: sha256d::Hash) -> Result<Option<Response>> {
-        let db_locked = self.db.lock().unwrap();
-        self.auth(&db_locked)?;
+        let db_locked = self.lock_db("get_response")?;
 
         let mut resp_aggr = db_locked.collection("Request").aggregate(
             [
@@ -233,57 +917,348 @@ impl Storage for MongoStorage {
         Ok(None)
     }
 
-    /// Get all bids for a specific request
-    fn get_bids(&self, request_hash: sha256d::Hash) -> Result<Vec<Bid>> {
-        let db_locked = self.db.lock().unwrap();
-        self.auth(&db_locked)?;
+    /// Compact challenge response for a specific request. The summary is set
+    /// on the request before the response is removed so that an interrupted
+    /// compaction can be run again
+    fn compact_response(&self, request_hash: sha256d::Hash, summary: &ResponseSummary) -> Result<()> {
+        let db_locked = self.lock_db("compact_response")?;
 
-        let mut resp_aggr = db_locked.collection("Request").aggregate(
-            [
-                doc! {
-                    "$lookup": {
-                        "from": "Bid",
-                        "localField": "_id",
-                        "foreignField": "request_id",
-                        "as": "bids"
-                    }
-                },
-                doc! {
-                    "$match": {
-                        "txid": request_hash.to_string()
-                    },
-                },
-            ]
-            .to_vec(),
-            None,
-        )?;
-        drop(db_locked); // drop immediately on get requests
+        let coll = db_locked.collection("Request");
+        let filter = doc! {"txid": request_hash.to_string()};
+        let request_id = match coll.find_one(Some(filter.clone()), None)? {
+            Some(request_doc) => request_doc.get("_id").unwrap().clone(),
+            None => return Ok(()),
+        };
+        let update = doc! {"$set" => doc! {
+            "response_summary" => response_summary_to_doc(summary),
+            "updated_at" => updated_at_ms() as i64,
+        }};
+        let _ = coll.update_one(filter, update, None)?;
 
-        let mut all_bids = Vec::new();
-        if let Some(resp) = resp_aggr.next() {
-            for bid in resp?.get_array("bids").unwrap().iter() {
-                let _ = all_bids.push(doc_to_bid(bid.as_document().unwrap()));
-            }
-        }
+        let _ = db_locked
+            .collection("Response")
+            .delete_one(doc! {"request_id": request_id}, None)?;
+        let _ = db_locked
+            .collection("ChallengeRecord")
+            .delete_many(doc! {"txid": request_hash.to_string()}, None)?;
+        let _ = db_locked
+            .collection("BidProof")
+            .delete_many(doc! {"txid": request_hash.to_string()}, None)?;
+        Ok(())
+    }
+
+    /// Get the integrity hash chain of the response of a specific request
+    fn get_response_hashes(&self, request_hash: sha256d::Hash) -> Result<Vec<sha256d::Hash>> {
+        let db_locked = self.lock_db("get_response_hashes")?;
+
+        let request = db_locked.collection("Request").find_one(
+            Some(doc! {
+                "txid": request_hash.to_string(),
+            }),
+            None,
+        )?;
+        let resp = match request {
+            Some(doc) => db_locked.collection("Response").find_one(
+                Some(doc! {
+                    "request_id": doc.get("_id").unwrap().clone(),
+                }),
+                None,
+            )?,
+            None => None,
+        };
+        drop(db_locked); // drop immediately on get requests
+
+        match resp {
+            Some(doc) => Ok(doc_to_response_hashes(&doc)),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Store the response snapshot of a payment epoch for a specific request,
+    /// replacing any previous snapshot of the epoch
+    fn save_response_snapshot(&self, request_hash: sha256d::Hash, snapshot: &ResponseSnapshot) -> Result<()> {
+        let db_locked = self.lock_db("save_response_snapshot")?;
+
+        let coll = db_locked.collection("ResponseSnapshot");
+        let filter = doc! {"txid": request_hash.to_string(), "epoch": snapshot.epoch};
+        let update = doc! {"$set" => response_snapshot_to_doc(&request_hash, snapshot)};
+        let options = UpdateOptions {
+            upsert: Some(true),
+            ..Default::default()
+        };
+        let _ = coll.update_one(filter, update, Some(options))?;
+        Ok(())
+    }
+
+    /// Get the payment epoch response snapshots of a specific request ordered
+    /// by epoch
+    fn get_response_snapshots(&self, request_hash: sha256d::Hash) -> Result<Vec<ResponseSnapshot>> {
+        let db_locked = self.lock_db("get_response_snapshots")?;
+
+        let mut options = FindOptions::new();
+        options.sort = Some(doc! { "epoch" : 1 });
+        let resps = db_locked.collection("ResponseSnapshot").find(
+            Some(doc! {
+                "txid": request_hash.to_string(),
+            }),
+            Some(options),
+        )?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut snapshots = vec![];
+        for resp in resps {
+            if let Ok(snapshot) = resp {
+                snapshots.push(doc_to_response_snapshot(&snapshot)?)
+            }
+        }
+        Ok(snapshots)
+    }
+
+    /// Store the challenge latency samples of a specific request along with
+    /// their percentiles
+    fn save_challenge_latency(&self, request_hash: sha256d::Hash, latency: &ChallengeLatency) -> Result<()> {
+        let db_locked = self.lock_db("save_challenge_latency")?;
+
+        let coll = db_locked.collection("ChallengeLatency");
+        let filter = doc! {"txid": request_hash.to_string()};
+        let update = doc! {"$set" => challenge_latency_to_doc(&request_hash, latency)};
+        let options = UpdateOptions {
+            upsert: Some(true),
+            ..Default::default()
+        };
+        let _ = coll.update_one(filter, update, Some(options))?;
+        Ok(())
+    }
+
+    /// Get the challenge latency samples of a specific request
+    fn get_challenge_latency(&self, request_hash: sha256d::Hash) -> Result<Option<ChallengeLatency>> {
+        let db_locked = self.lock_db("get_challenge_latency")?;
+
+        let resp = db_locked.collection("ChallengeLatency").find_one(
+            Some(doc! {
+                "txid": request_hash.to_string(),
+            }),
+            None,
+        )?;
+        drop(db_locked); // drop immediately on get requests
+
+        Ok(resp.map(|doc| doc_to_challenge_latency(&doc)))
+    }
+
+    /// Store the challenge coverage of a specific request, replacing any
+    /// previous coverage of the request
+    fn save_challenge_coverage(&self, request_hash: sha256d::Hash, coverage: &ChallengeCoverage) -> Result<()> {
+        let db_locked = self.lock_db("save_challenge_coverage")?;
+
+        let coll = db_locked.collection("ChallengeCoverage");
+        let filter = doc! {"txid": request_hash.to_string()};
+        let update = doc! {"$set" => challenge_coverage_to_doc(&request_hash, coverage)};
+        let options = UpdateOptions {
+            upsert: Some(true),
+            ..Default::default()
+        };
+        let _ = coll.update_one(filter, update, Some(options))?;
+        Ok(())
+    }
+
+    /// Get the challenge coverage of a specific request
+    fn get_challenge_coverage(&self, request_hash: sha256d::Hash) -> Result<Option<ChallengeCoverage>> {
+        let db_locked = self.lock_db("get_challenge_coverage")?;
+
+        let resp = db_locked.collection("ChallengeCoverage").find_one(
+            Some(doc! {
+                "txid": request_hash.to_string(),
+            }),
+            None,
+        )?;
+        drop(db_locked); // drop immediately on get requests
+
+        Ok(resp.map(|doc| doc_to_challenge_coverage(&doc)))
+    }
+
+    /// Store a client chain stall period of a specific request. Saving a
+    /// stall with the same start time again leaves the first stall unchanged
+    fn save_clientchain_stall(&self, request_hash: sha256d::Hash, stall: &ClientChainStall) -> Result<()> {
+        let db_locked = self.lock_db("save_clientchain_stall")?;
+
+        let coll = db_locked.collection("ClientChainStall");
+        let filter = doc! {"txid": request_hash.to_string(), "start_time": stall.start_time as i64};
+        let update = doc! {"$setOnInsert" => clientchain_stall_to_doc(&request_hash, stall)};
+        let options = UpdateOptions {
+            upsert: Some(true),
+            ..Default::default()
+        };
+        let _ = coll.update_one(filter, update, Some(options))?;
+        Ok(())
+    }
+
+    /// Get the client chain stall periods of a specific request ordered by
+    /// start time
+    fn get_clientchain_stalls(&self, request_hash: sha256d::Hash) -> Result<Vec<ClientChainStall>> {
+        let db_locked = self.lock_db("get_clientchain_stalls")?;
+
+        let mut options = FindOptions::new();
+        options.sort = Some(doc! { "start_time" : 1 });
+        let resps = db_locked.collection("ClientChainStall").find(
+            Some(doc! {
+                "txid": request_hash.to_string(),
+            }),
+            Some(options),
+        )?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut stalls = vec![];
+        for resp in resps {
+            stalls.push(doc_to_clientchain_stall(&resp?));
+        }
+        Ok(stalls)
+    }
+
+    /// Store the reconciliation of a request response against on-chain proofs,
+    /// replacing any previous reconciliation of the request
+    fn save_response_reconciliation(
+        &self,
+        request_hash: sha256d::Hash,
+        reconciliation: &ResponseReconciliation,
+    ) -> Result<()> {
+        let db_locked = self.lock_db("save_response_reconciliation")?;
+
+        let coll = db_locked.collection("ResponseReconciliation");
+        let filter = doc! {"txid": request_hash.to_string()};
+        let update = doc! {"$set" => response_reconciliation_to_doc(&request_hash, reconciliation)};
+        let options = UpdateOptions {
+            upsert: Some(true),
+            ..Default::default()
+        };
+        let _ = coll.update_one(filter, update, Some(options))?;
+        Ok(())
+    }
+
+    /// Get the reconciliation of a request response against on-chain proofs
+    fn get_response_reconciliation(&self, request_hash: sha256d::Hash) -> Result<Option<ResponseReconciliation>> {
+        let db_locked = self.lock_db("get_response_reconciliation")?;
+
+        let resp = db_locked.collection("ResponseReconciliation").find_one(
+            Some(doc! {
+                "txid": request_hash.to_string(),
+            }),
+            None,
+        )?;
+        drop(db_locked); // drop immediately on get requests
+
+        Ok(resp.map(|doc| doc_to_response_reconciliation(&doc)))
+    }
+
+    /// Store the fee pool of a finished request, replacing any previous fee
+    /// pool of the request
+    fn save_fee_pool(&self, request_hash: sha256d::Hash, pool: &FeePool) -> Result<()> {
+        let db_locked = self.lock_db("save_fee_pool")?;
+
+        let coll = db_locked.collection("FeePool");
+        let filter = doc! {"txid": request_hash.to_string()};
+        let update = doc! {"$set" => fee_pool_to_doc(&request_hash, pool)};
+        let options = UpdateOptions {
+            upsert: Some(true),
+            ..Default::default()
+        };
+        let _ = coll.update_one(filter, update, Some(options))?;
+        Ok(())
+    }
+
+    /// Get the fee pool of a finished request
+    fn get_fee_pool(&self, request_hash: sha256d::Hash) -> Result<Option<FeePool>> {
+        let db_locked = self.lock_db("get_fee_pool")?;
+
+        let resp = db_locked.collection("FeePool").find_one(
+            Some(doc! {
+                "txid": request_hash.to_string(),
+            }),
+            None,
+        )?;
+        drop(db_locked); // drop immediately on get requests
+
+        Ok(resp.map(|doc| doc_to_fee_pool(&doc)))
+    }
+
+    /// Get all bids for a specific request
+    fn get_bids(&self, request_hash: sha256d::Hash) -> Result<Vec<Bid>> {
+        let db_locked = self.lock_db("get_bids")?;
+
+        let mut resp_aggr = db_locked.collection("Request").aggregate(
+            [
+                doc! {
+                    "$lookup": {
+                        "from": "Bid",
+                        "localField": "_id",
+                        "foreignField": "request_id",
+                        "as": "bids"
+                    }
+                },
+                doc! {
+                    "$match": {
+                        "txid": request_hash.to_string()
+                    },
+                },
+            ]
+            .to_vec(),
+            None,
+        )?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut all_bids = Vec::new();
+        if let Some(resp) = resp_aggr.next() {
+            for bid in resp?.get_array("bids").unwrap().iter() {
+                let _ = all_bids.push(doc_to_bid(bid.as_document().unwrap())?);
+            }
+        }
         Ok(all_bids)
     }
 
+    /// Get a single bid of a specific request by bid txid
+    fn get_bid(&self, request_hash: sha256d::Hash, bid_hash: sha256d::Hash) -> Result<Option<Bid>> {
+        let db_locked = self.lock_db("get_bid")?;
+
+        let request_id = match db_locked.collection("Request").find_one(
+            Some(doc! {
+                "txid": request_hash.to_string(),
+            }),
+            None,
+        )? {
+            Some(doc) => doc.get("_id").unwrap().clone(),
+            None => return Ok(None),
+        };
+        let resp = db_locked.collection("Bid").find_one(
+            Some(doc! {
+                "request_id": request_id,
+                "txid": bid_hash.to_string(),
+            }),
+            None,
+        )?;
+        drop(db_locked); // drop immediately on get requests
+
+        match resp {
+            Some(doc) => Ok(Some(doc_to_bid(&doc)?)),
+            None => Ok(None),
+        }
+    }
+
     /// Get all the requests, with an optional flag to return payment complete
-    /// only
-    fn get_requests(&self, complete: Option<bool>, limit: Option<i64>, skip: Option<i64>) -> Result<Vec<Request>> {
-        let db_locked = self.db.lock().unwrap();
-        self.auth(&db_locked)?;
+    /// only and an optional genesis hash to return a single tenant's requests
+    fn get_requests(
+        &self,
+        complete: Option<bool>,
+        genesis: Option<sha256d::Hash>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<Request>> {
+        let db_locked = self.lock_db("get_requests")?;
 
         let mut options = FindOptions::new();
         options.sort = Some(doc! { "_id" : 1 }); // sort ascending, latest request is last
         options.limit = limit; // limit the number of returned requests
         options.skip = skip; // number of requests to skip
-        let filter = if let Some(is_complete) = complete {
-            Some(doc! { "is_payment_complete": is_complete })
-        } else {
-            None
-        };
-        let resps = db_locked.collection("Request").find(filter, Some(options))?;
+        let filter = requests_filter(complete, genesis);
+        let resps = db_locked.collection("Request").find(Some(filter), Some(options))?;
         drop(db_locked); // drop immediately on get requests
 
         let mut requests = vec![];
@@ -295,17 +1270,151 @@ impl Storage for MongoStorage {
         Ok(requests)
     }
 
-    /// Get the number of requests in the Request collection
-    fn get_requests_count(&self) -> Result<i64> {
-        let db_locked = self.db.lock().unwrap();
-        self.auth(&db_locked)?;
-        Ok(db_locked.collection("Request").count(None, None)?)
+    /// Get requests joined with their bids and responses in a single
+    /// aggregation, with an optional genesis hash to return a single tenant's
+    /// requests
+    fn get_requests_full(
+        &self,
+        genesis: Option<sha256d::Hash>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<RequestFull>> {
+        let db_locked = self.lock_db("get_requests_full")?;
+
+        let mut pipeline = vec![
+            doc! {"$match": requests_filter(None, genesis)},
+            doc! {"$sort": {"_id": 1}}, // sort ascending, latest request is last
+        ];
+        if let Some(skip_val) = skip {
+            pipeline.push(doc! {"$skip": skip_val});
+        }
+        if let Some(limit_val) = limit {
+            pipeline.push(doc! {"$limit": limit_val});
+        }
+        pipeline.push(doc! {
+            "$lookup": {
+                "from": "Bid",
+                "localField": "_id",
+                "foreignField": "request_id",
+                "as": "bids"
+            }
+        });
+        pipeline.push(doc! {
+            "$lookup": {
+                "from": "Response",
+                "localField": "_id",
+                "foreignField": "request_id",
+                "as": "response"
+            }
+        });
+        let resps = db_locked.collection("Request").aggregate(pipeline, None)?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut requests = vec![];
+        for resp in resps {
+            if let Ok(doc) = resp {
+                let bids = doc
+                    .get_array("bids")
+                    .unwrap()
+                    .iter()
+                    .map(|bid| doc_to_bid(bid.as_document().unwrap()))
+                    .collect::<Result<Vec<_>>>()?;
+                let response = doc
+                    .get_array("response")
+                    .unwrap()
+                    .first()
+                    .map(|response| doc_to_response(response.as_document().unwrap()));
+                requests.push(RequestFull {
+                    request: doc_to_request(&doc),
+                    bids,
+                    response,
+                })
+            }
+        }
+        Ok(requests)
+    }
+
+    /// Get a page of requests sorted by start height and txid, starting after
+    /// the cursor if set, optionally for a genesis hash
+    fn get_requests_page(
+        &self,
+        genesis: Option<sha256d::Hash>,
+        after: Option<&RequestCursor>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<Request>> {
+        let db_locked = self.lock_db("get_requests_page")?;
+
+        let mut options = FindOptions::new();
+        options.sort = Some(doc! { "start_blockheight": 1, "txid": 1 });
+        options.limit = limit;
+        options.skip = skip;
+        let mut filter = requests_filter(None, genesis);
+        if let Some(cursor) = after {
+            let _ = filter.insert(
+                "$or",
+                vec![
+                    Bson::from(doc! { "start_blockheight": { "$gt": cursor.start_blockheight } }),
+                    Bson::from(doc! {
+                        "start_blockheight": cursor.start_blockheight,
+                        "txid": { "$gt": cursor.txid.to_string() }
+                    }),
+                ],
+            );
+        }
+        let resps = db_locked.collection("Request").find(Some(filter), Some(options))?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut requests = vec![];
+        for resp in resps {
+            if let Ok(req) = resp {
+                requests.push(doc_to_request(&req))
+            }
+        }
+        Ok(requests)
+    }
+
+    /// Get the number of requests in the Request collection, optionally for a
+    /// genesis hash
+    fn get_requests_count(&self, genesis: Option<sha256d::Hash>) -> Result<i64> {
+        let db_locked = self.lock_db("get_requests_count")?;
+        Ok(db_locked
+            .collection("Request")
+            .count(Some(requests_filter(None, genesis)), None)?)
+    }
+
+    /// Get requests sorted by last updated time, most recent first, optionally
+    /// for a genesis hash. Requests stored before updated times were recorded
+    /// come last
+    fn get_requests_recent(
+        &self,
+        genesis: Option<sha256d::Hash>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<Request>> {
+        let db_locked = self.lock_db("get_requests_recent")?;
+
+        let mut options = FindOptions::new();
+        options.sort = Some(doc! { "updated_at": -1, "_id": -1 });
+        options.limit = limit;
+        options.skip = skip;
+        let resps = db_locked
+            .collection("Request")
+            .find(Some(requests_filter(None, genesis)), Some(options))?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut requests = vec![];
+        for resp in resps {
+            if let Ok(req) = resp {
+                requests.push(doc_to_request(&req))
+            }
+        }
+        Ok(requests)
     }
 
     /// Get request for a specific request txid
     fn get_request(&self, request_hash: sha256d::Hash) -> Result<Option<Request>> {
-        let db_locked = self.db.lock().unwrap();
-        self.auth(&db_locked)?;
+        let db_locked = self.lock_db("get_request")?;
 
         let request = db_locked.collection("Request").find_one(
             Some(doc! {
@@ -320,4 +1429,772 @@ impl Storage for MongoStorage {
             None => Ok(None),
         }
     }
+
+    /// Store log lines for a specific request. Logs are keyed by request txid
+    /// as they might be emitted before the request itself is stored
+    fn save_request_logs(&self, request_hash: sha256d::Hash, logs: &[String]) -> Result<()> {
+        let db_locked = self.lock_db("save_request_logs")?;
+
+        let logs_bson: Vec<Bson> = logs.iter().map(|log| Bson::String(log.clone())).collect();
+        let coll = db_locked.collection("RequestLogs");
+        let filter = doc! {"txid": request_hash.to_string()};
+        let update = doc! {
+            "$push": {
+                "logs": {
+                    "$each": logs_bson,
+                    "$slice": -(STORAGE_REQUEST_LOGS_LIMIT as i32)
+                }
+            }
+        };
+        let options = UpdateOptions {
+            upsert: Some(true),
+            ..Default::default()
+        };
+        let _ = coll.update_one(filter, update, Some(options))?;
+        Ok(())
+    }
+
+    /// Get stored log lines for a specific request
+    fn get_request_logs(&self, request_hash: sha256d::Hash) -> Result<Vec<String>> {
+        let db_locked = self.lock_db("get_request_logs")?;
+
+        let resp = db_locked.collection("RequestLogs").find_one(
+            Some(doc! {
+                "txid": request_hash.to_string(),
+            }),
+            None,
+        )?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut logs = vec![];
+        if let Some(doc) = resp {
+            for log in doc.get_array("logs").unwrap().iter() {
+                logs.push(log.as_str().unwrap().to_owned());
+            }
+        }
+        Ok(logs)
+    }
+
+    /// Store the result of the fee deposit check of a request, replacing any
+    /// previous result
+    fn save_request_deposit(&self, deposit: &RequestDeposit) -> Result<()> {
+        let db_locked = self.lock_db("save_request_deposit")?;
+
+        let coll = db_locked.collection("RequestDeposit");
+        let filter = doc! {"txid": deposit.txid.to_string()};
+        let update = doc! {"$set" => request_deposit_to_doc(&deposit)};
+        let options = UpdateOptions {
+            upsert: Some(true),
+            ..Default::default()
+        };
+        let _ = coll.update_one(filter, update, Some(options))?;
+        Ok(())
+    }
+
+    /// Get stored request fee deposits, with an optional flag to return
+    /// verified or unverified deposits only and an optional genesis hash
+    fn get_request_deposits(
+        &self,
+        verified: Option<bool>,
+        genesis: Option<sha256d::Hash>,
+    ) -> Result<Vec<RequestDeposit>> {
+        let db_locked = self.lock_db("get_request_deposits")?;
+
+        let mut filter = doc! {};
+        if let Some(is_verified) = verified {
+            let _ = filter.insert("is_verified", is_verified);
+        }
+        if let Some(genesis_hash) = genesis {
+            let _ = filter.insert("genesis_blockhash", genesis_hash.to_string());
+        }
+        let resps = db_locked.collection("RequestDeposit").find(Some(filter), None)?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut deposits = vec![];
+        for resp in resps {
+            if let Ok(deposit) = resp {
+                deposits.push(doc_to_request_deposit(&deposit))
+            }
+        }
+        Ok(deposits)
+    }
+
+    /// Store the rejection of a request that failed validation, replacing any
+    /// previous rejection
+    fn save_request_rejection(&self, rejection: &RequestRejection) -> Result<()> {
+        let db_locked = self.lock_db("save_request_rejection")?;
+
+        let coll = db_locked.collection("RequestRejection");
+        let filter = doc! {"txid": rejection.txid.to_string()};
+        let update = doc! {"$set" => request_rejection_to_doc(&rejection)};
+        let options = UpdateOptions {
+            upsert: Some(true),
+            ..Default::default()
+        };
+        let _ = coll.update_one(filter, update, Some(options))?;
+        Ok(())
+    }
+
+    /// Get stored request rejections, with an optional genesis hash
+    fn get_request_rejections(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<RequestRejection>> {
+        let db_locked = self.lock_db("get_request_rejections")?;
+
+        let mut filter = doc! {};
+        if let Some(genesis_hash) = genesis {
+            let _ = filter.insert("genesis_blockhash", genesis_hash.to_string());
+        }
+        let resps = db_locked.collection("RequestRejection").find(Some(filter), None)?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut rejections = vec![];
+        for resp in resps {
+            if let Ok(rejection) = resp {
+                rejections.push(doc_to_request_rejection(&rejection))
+            }
+        }
+        Ok(rejections)
+    }
+
+    /// Store the operator annotation of a specific request, replacing any
+    /// previous annotation of the request
+    fn save_request_annotation(&self, request_hash: sha256d::Hash, annotation: &RequestAnnotation) -> Result<()> {
+        let db_locked = self.lock_db("save_request_annotation")?;
+
+        let coll = db_locked.collection("RequestAnnotation");
+        let filter = doc! {"txid": request_hash.to_string()};
+        let update = doc! {"$set" => request_annotation_to_doc(&request_hash, annotation)};
+        let options = UpdateOptions {
+            upsert: Some(true),
+            ..Default::default()
+        };
+        let _ = coll.update_one(filter, update, Some(options))?;
+        Ok(())
+    }
+
+    /// Get the operator annotation of a specific request
+    fn get_request_annotation(&self, request_hash: sha256d::Hash) -> Result<Option<RequestAnnotation>> {
+        let db_locked = self.lock_db("get_request_annotation")?;
+
+        let resp = db_locked.collection("RequestAnnotation").find_one(
+            Some(doc! {
+                "txid": request_hash.to_string(),
+            }),
+            None,
+        )?;
+        drop(db_locked); // drop immediately on get requests
+
+        Ok(resp.map(|doc| doc_to_request_annotation(&doc)))
+    }
+
+    /// Get the hashes of the requests annotated with a label
+    fn get_labelled_requests(&self, label: &str) -> Result<Vec<sha256d::Hash>> {
+        let db_locked = self.lock_db("get_labelled_requests")?;
+
+        let resps = db_locked
+            .collection("RequestAnnotation")
+            .find(Some(doc! {"labels": label}), None)?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut hashes = vec![];
+        for resp in resps {
+            if let Ok(annotation) = resp {
+                hashes.push(sha256d::Hash::from_hex(annotation.get_str("txid").unwrap())?)
+            }
+        }
+        Ok(hashes)
+    }
+
+    /// Store the dead letter of a listener submission, removing the oldest
+    /// dead letters beyond the limit
+    fn save_dead_letter(&self, letter: &DeadLetter) -> Result<()> {
+        let db_locked = self.lock_db("save_dead_letter")?;
+
+        let coll = db_locked.collection("DeadLetter");
+        let _ = coll.insert_one(dead_letter_to_doc(letter), None)?;
+        let mut options = FindOptions::new();
+        options.sort = Some(doc! { "_id": -1 });
+        options.skip = Some(STORAGE_DEAD_LETTERS_LIMIT as i64);
+        options.limit = Some(1);
+        if let Some(excess) = coll.find_one(None, Some(options))? {
+            if let Some(id) = excess.get("_id") {
+                let _ = coll.delete_many(doc! { "_id": { "$lte": id.clone() } }, None)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get up to a limit of the most recent stored dead letters
+    fn get_dead_letters(&self, limit: u64) -> Result<Vec<DeadLetter>> {
+        let db_locked = self.lock_db("get_dead_letters")?;
+
+        let mut options = FindOptions::new();
+        options.sort = Some(doc! { "_id": -1 });
+        options.limit = Some(limit as i64);
+        let resps = db_locked.collection("DeadLetter").find(None, Some(options))?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut letters = vec![];
+        for resp in resps {
+            if let Ok(letter) = resp {
+                letters.push(doc_to_dead_letter(&letter))
+            }
+        }
+        Ok(letters)
+    }
+
+    /// Store the blacklisting of a bid, replacing any previous blacklisting of
+    /// the bid at the same time
+    fn save_bid_blacklisting(&self, blacklisting: &BidBlacklisting) -> Result<()> {
+        let db_locked = self.lock_db("save_bid_blacklisting")?;
+
+        let coll = db_locked.collection("BidBlacklisting");
+        let filter = doc! {"txid": blacklisting.txid.to_string(), "since": blacklisting.since as i64};
+        let update = doc! {"$set" => bid_blacklisting_to_doc(&blacklisting)};
+        let options = UpdateOptions {
+            upsert: Some(true),
+            ..Default::default()
+        };
+        let _ = coll.update_one(filter, update, Some(options))?;
+        Ok(())
+    }
+
+    /// Get stored bid blacklistings ordered by time, with an optional genesis
+    /// hash
+    fn get_bid_blacklistings(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<BidBlacklisting>> {
+        let db_locked = self.lock_db("get_bid_blacklistings")?;
+
+        let mut filter = doc! {};
+        if let Some(genesis_hash) = genesis {
+            let _ = filter.insert("genesis_blockhash", genesis_hash.to_string());
+        }
+        let mut options = FindOptions::new();
+        options.sort = Some(doc! { "since" : 1 });
+        let resps = db_locked
+            .collection("BidBlacklisting")
+            .find(Some(filter), Some(options))?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut blacklistings = vec![];
+        for resp in resps {
+            if let Ok(blacklisting) = resp {
+                blacklistings.push(doc_to_bid_blacklisting(&blacklisting))
+            }
+        }
+        Ok(blacklistings)
+    }
+
+    /// Store the rotation of the key of a bid and set the pubkey of the bid in
+    /// the Bid collection to the rotated pubkey
+    fn save_bid_key_rotation(&self, rotation: &BidKeyRotation) -> Result<()> {
+        let db_locked = self.lock_db("save_bid_key_rotation")?;
+
+        let request_id = db_locked
+            .collection("Request")
+            .find_one(
+                Some(doc! {
+                    "txid": rotation.request.to_string(),
+                }),
+                None,
+            )?
+            .ok_or_else(|| CError::Generic(format!("request {} not found", rotation.request)))?
+            .get("_id")
+            .unwrap()
+            .clone();
+
+        let filter = doc! {"request_id": request_id, "txid": rotation.txid.to_string()};
+        let update = doc! {"$set": {"pubkey": rotation.new_pubkey.to_string()}};
+        let _ = db_locked.collection("Bid").update_one(filter, update, None)?;
+
+        let coll = db_locked.collection("BidKeyRotation");
+        let filter = doc! {"txid": rotation.txid.to_string(), "time": rotation.time as i64};
+        let update = doc! {"$set" => bid_key_rotation_to_doc(&rotation)};
+        let options = UpdateOptions {
+            upsert: Some(true),
+            ..Default::default()
+        };
+        let _ = coll.update_one(filter, update, Some(options))?;
+        Ok(())
+    }
+
+    /// Get the stored bid key rotations of a specific request ordered by time
+    fn get_bid_key_rotations(&self, request_hash: sha256d::Hash) -> Result<Vec<BidKeyRotation>> {
+        let db_locked = self.lock_db("get_bid_key_rotations")?;
+
+        let mut options = FindOptions::new();
+        options.sort = Some(doc! { "time" : 1 });
+        let resps = db_locked.collection("BidKeyRotation").find(
+            Some(doc! {
+                "request": request_hash.to_string(),
+            }),
+            Some(options),
+        )?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut rotations = vec![];
+        for resp in resps {
+            if let Ok(rotation) = resp {
+                rotations.push(doc_to_bid_key_rotation(&rotation))
+            }
+        }
+        Ok(rotations)
+    }
+
+    /// Store the refund of the lock of a bid, replacing any previous refund of
+    /// the bid
+    fn save_bid_refund(&self, refund: &BidRefund) -> Result<()> {
+        let db_locked = self.lock_db("save_bid_refund")?;
+
+        let coll = db_locked.collection("BidRefund");
+        let filter = doc! {"txid": refund.txid.to_string()};
+        let update = doc! {"$set" => bid_refund_to_doc(&refund)};
+        let options = UpdateOptions {
+            upsert: Some(true),
+            ..Default::default()
+        };
+        let _ = coll.update_one(filter, update, Some(options))?;
+        Ok(())
+    }
+
+    /// Get stored bid lock refunds ordered by lock height, with an optional
+    /// genesis hash
+    fn get_bid_refunds(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<BidRefund>> {
+        let db_locked = self.lock_db("get_bid_refunds")?;
+
+        let mut filter = doc! {};
+        if let Some(genesis_hash) = genesis {
+            let _ = filter.insert("genesis_blockhash", genesis_hash.to_string());
+        }
+        let mut options = FindOptions::new();
+        options.sort = Some(doc! { "lock_height" : 1 });
+        let resps = db_locked.collection("BidRefund").find(Some(filter), Some(options))?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut refunds = vec![];
+        for resp in resps {
+            if let Ok(refund) = resp {
+                refunds.push(doc_to_bid_refund(&refund))
+            }
+        }
+        Ok(refunds)
+    }
+
+    /// Store the liability of the deferred bid payments of a request, or
+    /// request epoch, replacing any previous liability of the request epoch
+    fn save_payment_liability(&self, liability: &PaymentLiability) -> Result<()> {
+        let db_locked = self.lock_db("save_payment_liability")?;
+
+        let coll = db_locked.collection("PaymentLiability");
+        let filter = payment_liability_filter(liability.request, liability.epoch);
+        let update = doc! {"$set" => payment_liability_to_doc(&liability)};
+        let options = UpdateOptions {
+            upsert: Some(true),
+            ..Default::default()
+        };
+        let _ = coll.update_one(filter, update, Some(options))?;
+        Ok(())
+    }
+
+    /// Remove the liability of the deferred bid payments of a request, or
+    /// request epoch
+    fn remove_payment_liability(&self, request_hash: sha256d::Hash, epoch: Option<u32>) -> Result<()> {
+        let db_locked = self.lock_db("remove_payment_liability")?;
+
+        let _ = db_locked
+            .collection("PaymentLiability")
+            .delete_one(payment_liability_filter(request_hash, epoch), None)?;
+        Ok(())
+    }
+
+    /// Get stored payment liabilities ordered by deferral time, with an
+    /// optional genesis hash
+    fn get_payment_liabilities(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<PaymentLiability>> {
+        let db_locked = self.lock_db("get_payment_liabilities")?;
+
+        let mut filter = doc! {};
+        if let Some(genesis_hash) = genesis {
+            let _ = filter.insert("genesis_blockhash", genesis_hash.to_string());
+        }
+        let mut options = FindOptions::new();
+        options.sort = Some(doc! { "time" : 1 });
+        let resps = db_locked
+            .collection("PaymentLiability")
+            .find(Some(filter), Some(options))?;
+        drop(db_locked); // drop immediately on get requests
+
+        let mut liabilities = vec![];
+        for resp in resps {
+            if let Ok(liability) = resp {
+                liabilities.push(doc_to_payment_liability(&liability))
+            }
+        }
+        Ok(liabilities)
+    }
+}
+
+/// Build PaymentLiability collection filter of the liability of a request, or
+/// request epoch. Request liabilities have no epoch field, which the null
+/// epoch filter matches
+fn payment_liability_filter(request_hash: sha256d::Hash, epoch: Option<u32>) -> OrderedDocument {
+    doc! {
+        "request": request_hash.to_string(),
+        "epoch": epoch.map_or(Bson::Null, |epoch| Bson::from(epoch)),
+    }
+}
+
+/// Interval between checks of the lag of the read replica behind the primary
+pub const STORAGE_READ_LAG_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Storage reading from a read replica, if set, while writing to the primary,
+/// isolating heavy read traffic such as api calls from the write path of the
+/// challenger and payments. The lag of the replica behind the primary is
+/// checked periodically on reads, warning when reads may be stale. Reads
+/// failing on the replica with a transient error fall back to the primary
+pub struct ReplicaStorage {
+    /// Primary storage that writes go to
+    primary: Arc<MongoStorage>,
+    /// Read replica storage that reads go to; reads go to the primary if None
+    replica: Option<MongoStorage>,
+    /// Max lag in seconds of the replica before warning of stale reads
+    max_lag: u64,
+    /// Time of the last lag check and the lag measured, if reported
+    lag: Mutex<Option<(Instant, Option<u64>)>>,
+}
+
+impl ReplicaStorage {
+    /// Return new ReplicaStorage for a primary storage, connecting to the read
+    /// replica if a read uri is set in the storage config
+    pub fn new(primary: Arc<MongoStorage>, storage_config: &StorageConfig) -> Result<ReplicaStorage> {
+        let replica = match storage_config.read_uri {
+            Some(_) => Some(MongoStorage::new_read_replica(storage_config.clone())?),
+            None => None,
+        };
+        Ok(ReplicaStorage {
+            primary,
+            replica,
+            max_lag: storage_config.max_read_lag,
+            lag: Mutex::new(None),
+        })
+    }
+
+    /// Get the lag in seconds of the read replica behind the primary as of the
+    /// last lag check, if known
+    pub fn lag(&self) -> Option<u64> {
+        self.lag.lock().unwrap().and_then(|(_, lag)| lag)
+    }
+
+    /// Measure the lag of the read replica behind the primary as the difference
+    /// between the times of the last writes they applied
+    fn measure_lag(&self, replica: &MongoStorage) -> Result<Option<u64>> {
+        match (self.primary.last_write_time()?, replica.last_write_time()?) {
+            (Some(primary_time), Some(replica_time)) => Ok(Some((primary_time - replica_time).max(0) as u64)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Get the storage to read from, checking the lag of the read replica if
+    /// not checked within the lag check interval. Lag check failures are only
+    /// logged, as the replica may still serve reads
+    fn read(&self) -> &MongoStorage {
+        let replica = match self.replica {
+            Some(ref replica) => replica,
+            None => return self.primary.as_ref(),
+        };
+        let mut lag = self.lag.lock().unwrap();
+        if lag.map_or(true, |(checked, _)| {
+            checked.elapsed() >= STORAGE_READ_LAG_CHECK_INTERVAL
+        }) {
+            let measured = match self.measure_lag(replica) {
+                Ok(measured) => measured,
+                Err(e) => {
+                    warn!("storage read replica lag check failed: {}", e);
+                    None
+                }
+            };
+            if let Some(measured) = measured {
+                if measured > self.max_lag {
+                    warn!("storage read replica {}s behind primary, reads may be stale", measured);
+                }
+            }
+            *lag = Some((Instant::now(), measured));
+        }
+        replica
+    }
+
+    /// Run a read on the storage to read from, reading from the primary if
+    /// the read replica fails with a transient error
+    fn read_with<T, F>(&self, read: F) -> Result<T>
+    where
+        F: Fn(&MongoStorage) -> Result<T>,
+    {
+        match read(self.read()) {
+            Err(ref e) if e.retryable() && self.replica.is_some() => {
+                warn!("storage read replica read failed, reading from primary: {}", e);
+                read(self.primary.as_ref())
+            }
+            res => res,
+        }
+    }
+}
+
+impl Storage for ReplicaStorage {
+    fn save_challenge_request_state(&self, request: &Request, bids: &BidSet) -> Result<()> {
+        self.primary.save_challenge_request_state(request, bids)
+    }
+
+    fn update_request(&self, request: &Request) -> Result<()> {
+        self.primary.update_request(request)
+    }
+
+    fn update_bid(&self, request_hash: sha256d::Hash, bid: &Bid) -> Result<()> {
+        self.primary.update_bid(request_hash, bid)
+    }
+
+    fn update_bids(&self, request_hash: sha256d::Hash, bids: &[Bid]) -> Result<()> {
+        self.primary.update_bids(request_hash, bids)
+    }
+
+    fn remove_bids(&self, request_hash: sha256d::Hash, bids: &[sha256d::Hash]) -> Result<()> {
+        self.primary.remove_bids(request_hash, bids)
+    }
+
+    fn save_response(&self, request_hash: sha256d::Hash, response: &Response) -> Result<()> {
+        self.primary.save_response(request_hash, response)
+    }
+
+    fn update_response(
+        &self,
+        request_hash: sha256d::Hash,
+        challenge: sha256d::Hash,
+        responses: &HashSet<sha256d::Hash>,
+        response: &Response,
+    ) -> Result<()> {
+        self.primary
+            .update_response(request_hash, challenge, responses, response)
+    }
+
+    fn save_challenge_record(&self, request_hash: sha256d::Hash, record: &ChallengeRecord) -> Result<()> {
+        self.primary.save_challenge_record(request_hash, record)
+    }
+
+    fn get_challenge_records(&self, request_hash: sha256d::Hash) -> Result<Vec<ChallengeRecord>> {
+        self.read_with(|storage| storage.get_challenge_records(request_hash))
+    }
+
+    fn save_challenge_stats(&self, request_hash: sha256d::Hash, stats: &ChallengeStats) -> Result<()> {
+        self.primary.save_challenge_stats(request_hash, stats)
+    }
+
+    fn get_challenge_stats(&self, request_hash: sha256d::Hash) -> Result<Vec<ChallengeStats>> {
+        self.read_with(|storage| storage.get_challenge_stats(request_hash))
+    }
+
+    fn save_bid_proofs(&self, request_hash: sha256d::Hash, proofs: &[BidProof]) -> Result<()> {
+        self.primary.save_bid_proofs(request_hash, proofs)
+    }
+
+    fn get_bid_proofs(&self, request_hash: sha256d::Hash, bid_hash: sha256d::Hash) -> Result<Vec<BidProof>> {
+        self.read_with(|storage| storage.get_bid_proofs(request_hash, bid_hash))
+    }
+
+    fn get_response(&self, request_hash: sha256d::Hash) -> Result<Option<Response>> {
+        self.read_with(|storage| storage.get_response(request_hash))
+    }
+
+    fn get_response_hashes(&self, request_hash: sha256d::Hash) -> Result<Vec<sha256d::Hash>> {
+        self.read_with(|storage| storage.get_response_hashes(request_hash))
+    }
+
+    fn compact_response(&self, request_hash: sha256d::Hash, summary: &ResponseSummary) -> Result<()> {
+        self.primary.compact_response(request_hash, summary)
+    }
+
+    fn save_response_snapshot(&self, request_hash: sha256d::Hash, snapshot: &ResponseSnapshot) -> Result<()> {
+        self.primary.save_response_snapshot(request_hash, snapshot)
+    }
+
+    fn get_response_snapshots(&self, request_hash: sha256d::Hash) -> Result<Vec<ResponseSnapshot>> {
+        self.read_with(|storage| storage.get_response_snapshots(request_hash))
+    }
+
+    fn save_challenge_latency(&self, request_hash: sha256d::Hash, latency: &ChallengeLatency) -> Result<()> {
+        self.primary.save_challenge_latency(request_hash, latency)
+    }
+
+    fn get_challenge_latency(&self, request_hash: sha256d::Hash) -> Result<Option<ChallengeLatency>> {
+        self.read_with(|storage| storage.get_challenge_latency(request_hash))
+    }
+
+    fn save_challenge_coverage(&self, request_hash: sha256d::Hash, coverage: &ChallengeCoverage) -> Result<()> {
+        self.primary.save_challenge_coverage(request_hash, coverage)
+    }
+
+    fn get_challenge_coverage(&self, request_hash: sha256d::Hash) -> Result<Option<ChallengeCoverage>> {
+        self.read_with(|storage| storage.get_challenge_coverage(request_hash))
+    }
+
+    fn save_clientchain_stall(&self, request_hash: sha256d::Hash, stall: &ClientChainStall) -> Result<()> {
+        self.primary.save_clientchain_stall(request_hash, stall)
+    }
+
+    fn get_clientchain_stalls(&self, request_hash: sha256d::Hash) -> Result<Vec<ClientChainStall>> {
+        self.read_with(|storage| storage.get_clientchain_stalls(request_hash))
+    }
+
+    fn save_response_reconciliation(
+        &self,
+        request_hash: sha256d::Hash,
+        reconciliation: &ResponseReconciliation,
+    ) -> Result<()> {
+        self.primary.save_response_reconciliation(request_hash, reconciliation)
+    }
+
+    fn get_response_reconciliation(&self, request_hash: sha256d::Hash) -> Result<Option<ResponseReconciliation>> {
+        self.read_with(|storage| storage.get_response_reconciliation(request_hash))
+    }
+
+    fn save_fee_pool(&self, request_hash: sha256d::Hash, pool: &FeePool) -> Result<()> {
+        self.primary.save_fee_pool(request_hash, pool)
+    }
+
+    fn get_fee_pool(&self, request_hash: sha256d::Hash) -> Result<Option<FeePool>> {
+        self.read_with(|storage| storage.get_fee_pool(request_hash))
+    }
+
+    fn get_bids(&self, request_hash: sha256d::Hash) -> Result<Vec<Bid>> {
+        self.read_with(|storage| storage.get_bids(request_hash))
+    }
+
+    fn get_bid(&self, request_hash: sha256d::Hash, bid_hash: sha256d::Hash) -> Result<Option<Bid>> {
+        self.read_with(|storage| storage.get_bid(request_hash, bid_hash))
+    }
+
+    fn get_requests(
+        &self,
+        complete: Option<bool>,
+        genesis: Option<sha256d::Hash>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<Request>> {
+        self.read_with(|storage| storage.get_requests(complete, genesis, limit, skip))
+    }
+
+    fn get_requests_full(
+        &self,
+        genesis: Option<sha256d::Hash>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<RequestFull>> {
+        self.read_with(|storage| storage.get_requests_full(genesis, limit, skip))
+    }
+
+    fn get_requests_page(
+        &self,
+        genesis: Option<sha256d::Hash>,
+        after: Option<&RequestCursor>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<Request>> {
+        self.read_with(|storage| storage.get_requests_page(genesis, after, limit, skip))
+    }
+
+    fn get_requests_count(&self, genesis: Option<sha256d::Hash>) -> Result<i64> {
+        self.read_with(|storage| storage.get_requests_count(genesis))
+    }
+
+    fn get_requests_recent(
+        &self,
+        genesis: Option<sha256d::Hash>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Vec<Request>> {
+        self.read_with(|storage| storage.get_requests_recent(genesis, limit, skip))
+    }
+
+    fn get_request(&self, request_hash: sha256d::Hash) -> Result<Option<Request>> {
+        self.read_with(|storage| storage.get_request(request_hash))
+    }
+
+    fn save_request_logs(&self, request_hash: sha256d::Hash, logs: &[String]) -> Result<()> {
+        self.primary.save_request_logs(request_hash, logs)
+    }
+
+    fn get_request_logs(&self, request_hash: sha256d::Hash) -> Result<Vec<String>> {
+        self.read_with(|storage| storage.get_request_logs(request_hash))
+    }
+
+    fn save_request_deposit(&self, deposit: &RequestDeposit) -> Result<()> {
+        self.primary.save_request_deposit(deposit)
+    }
+
+    fn get_request_deposits(
+        &self,
+        verified: Option<bool>,
+        genesis: Option<sha256d::Hash>,
+    ) -> Result<Vec<RequestDeposit>> {
+        self.read_with(|storage| storage.get_request_deposits(verified, genesis))
+    }
+
+    fn save_request_rejection(&self, rejection: &RequestRejection) -> Result<()> {
+        self.primary.save_request_rejection(rejection)
+    }
+
+    fn get_request_rejections(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<RequestRejection>> {
+        self.read_with(|storage| storage.get_request_rejections(genesis))
+    }
+
+    fn save_request_annotation(&self, request_hash: sha256d::Hash, annotation: &RequestAnnotation) -> Result<()> {
+        self.primary.save_request_annotation(request_hash, annotation)
+    }
+
+    fn get_request_annotation(&self, request_hash: sha256d::Hash) -> Result<Option<RequestAnnotation>> {
+        self.read_with(|storage| storage.get_request_annotation(request_hash))
+    }
+
+    fn get_labelled_requests(&self, label: &str) -> Result<Vec<sha256d::Hash>> {
+        self.read_with(|storage| storage.get_labelled_requests(label))
+    }
+
+    fn save_dead_letter(&self, letter: &DeadLetter) -> Result<()> {
+        self.primary.save_dead_letter(letter)
+    }
+
+    fn get_dead_letters(&self, limit: u64) -> Result<Vec<DeadLetter>> {
+        self.read_with(|storage| storage.get_dead_letters(limit))
+    }
+
+    fn save_bid_blacklisting(&self, blacklisting: &BidBlacklisting) -> Result<()> {
+        self.primary.save_bid_blacklisting(blacklisting)
+    }
+
+    fn get_bid_blacklistings(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<BidBlacklisting>> {
+        self.read_with(|storage| storage.get_bid_blacklistings(genesis))
+    }
+
+    fn save_bid_key_rotation(&self, rotation: &BidKeyRotation) -> Result<()> {
+        self.primary.save_bid_key_rotation(rotation)
+    }
+
+    fn get_bid_key_rotations(&self, request_hash: sha256d::Hash) -> Result<Vec<BidKeyRotation>> {
+        self.read_with(|storage| storage.get_bid_key_rotations(request_hash))
+    }
+
+    fn save_bid_refund(&self, refund: &BidRefund) -> Result<()> {
+        self.primary.save_bid_refund(refund)
+    }
+
+    fn get_bid_refunds(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<BidRefund>> {
+        self.read_with(|storage| storage.get_bid_refunds(genesis))
+    }
+
+    fn save_payment_liability(&self, liability: &PaymentLiability) -> Result<()> {
+        self.primary.save_payment_liability(liability)
+    }
+
+    fn remove_payment_liability(&self, request_hash: sha256d::Hash, epoch: Option<u32>) -> Result<()> {
+        self.primary.remove_payment_liability(request_hash, epoch)
+    }
+
+    fn get_payment_liabilities(&self, genesis: Option<sha256d::Hash>) -> Result<Vec<PaymentLiability>> {
+        self.read_with(|storage| storage.get_payment_liabilities(genesis))
+    }
 }
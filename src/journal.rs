@@ -0,0 +1,648 @@
+//! Journal
+//!
+//! Append-only journal of the externally visible decisions of the coordinator,
+//! i.e. challenges issued, challenge proofs accepted or rejected, responses
+//! saved and bid payments computed. Each decision is recorded along with the
+//! inputs it was made from so that the journal can be replayed to reproduce
+//! and audit coordinator behaviour after incidents
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bitcoin::hashes::{
+    hex::{FromHex, ToHex},
+    sha256d,
+};
+use bitcoin::secp256k1::{PublicKey, Signature};
+use bitcoin::Amount;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{CError, Result};
+use crate::interfaces::bid::{Bid, BidKeyRotation, BidPaymentBasis, LatencyWeighting};
+use crate::interfaces::clientchain::{ChallengeAmountTag, ChallengeCommitment};
+use crate::proof::ChallengeProof;
+use crate::proof_policy::PolicyResult;
+use crate::rotation::verify_bid_key_rotation;
+
+/// Challenge proof as recorded in the journal, holding all the inputs required
+/// to verify the proof signature again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalProof {
+    /// Request txid the proof is bound to, set for v2 proofs
+    pub request: Option<sha256d::Hash>,
+    /// Challenge hash
+    pub challenge: sha256d::Hash,
+    /// Bid txid
+    pub bid: sha256d::Hash,
+    /// Bid pubkey hex
+    pub pubkey: String,
+    /// DER encoded signature hex
+    pub sig: String,
+    /// Challenge payload commitment that the proof commits to, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<sha256d::Hash>,
+}
+
+impl JournalProof {
+    /// Record a challenge proof
+    pub fn from_proof(proof: &ChallengeProof) -> JournalProof {
+        JournalProof {
+            request: proof.request,
+            challenge: proof.hash,
+            bid: proof.bid.txid,
+            pubkey: proof.bid.pubkey.to_string(),
+            sig: proof.sig.serialize_der().to_hex(),
+            payload: proof.payload,
+        }
+    }
+
+    /// Rebuild the challenge proof from the recorded inputs
+    pub fn to_proof(&self) -> Result<ChallengeProof> {
+        Ok(ChallengeProof {
+            hash: self.challenge,
+            sig: Signature::from_der(&Vec::<u8>::from_hex(&self.sig)?)?,
+            bid: Bid {
+                txid: self.bid,
+                pubkey: PublicKey::from_str(&self.pubkey)?,
+                payment: None,
+            },
+            request: self.request,
+            payload: self.payload,
+        })
+    }
+}
+
+/// Coordinator decisions recorded in the journal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JournalEvent {
+    /// Challenge issued for a request at a service chain height
+    ChallengeIssued {
+        /// Request txid
+        request: sha256d::Hash,
+        /// Challenge hash
+        challenge: sha256d::Hash,
+        /// Service chain height
+        height: u64,
+        /// Amount tag of the challenge transaction, if challenges are tagged
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        amount_tag: Option<ChallengeAmountTag>,
+        /// Name of the wallet the challenge was issued from, if challenge
+        /// wallets are configured
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        wallet: Option<String>,
+        /// Commitment published to the challenge before it was revealed, if
+        /// the challenge was committed to
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        commitment: Option<ChallengeCommitment>,
+    },
+    /// Challenge proof accepted after verifying its signature
+    ProofAccepted {
+        /// Accepted proof
+        proof: JournalProof,
+    },
+    /// Challenge proof rejected, with the proof if it could be parsed
+    ProofRejected {
+        /// Rejected proof
+        proof: Option<JournalProof>,
+        /// Rejection reason as returned to the guardnode
+        reason: String,
+        /// Results of the proof acceptance policies, if rejected by a policy
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        policies: Vec<PolicyResult>,
+    },
+    /// Responses to a challenge saved for a request
+    ResponseSaved {
+        /// Request txid
+        request: sha256d::Hash,
+        /// Challenge hash
+        challenge: sha256d::Hash,
+        /// Txids of the bids that responded to the challenge
+        bids: Vec<sha256d::Hash>,
+        /// Number of challenges of the request response so far
+        num_challenges: u32,
+    },
+    /// Bid payment amount computed from its payment basis
+    PaymentComputed {
+        /// Request txid
+        request: sha256d::Hash,
+        /// Payment epoch, if paid per epoch
+        epoch: Option<u32>,
+        /// Bid txid
+        bid: sha256d::Hash,
+        /// Version of the formula used to calculate the amount
+        formula_version: u32,
+        /// Total service fees in satoshis
+        fees_amount: u64,
+        /// Percentage of the fees paid to bids
+        fee_percentage: u32,
+        /// Number of bids that the fees are split between
+        num_bids: u32,
+        /// Number of challenges issued
+        num_challenges: u32,
+        /// Number of challenges responded to by the bid
+        num_responses: u32,
+        /// Latency weighting of the bid responses, if any
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        latency_weighting: Option<LatencyWeighting>,
+        /// Computed payment amount in satoshis
+        amount: u64,
+    },
+    /// Bid key rotated within a request after verifying the rotation
+    /// signatures required by the key rotation policy
+    BidKeyRotated {
+        /// Rotation along with its signatures
+        rotation: BidKeyRotation,
+    },
+    /// Maintenance window started, deferring non-critical work until its end
+    MaintenanceStarted {
+        /// Window name
+        window: String,
+        /// Unix timestamp in seconds that the window started at
+        start: u64,
+        /// Unix timestamp in seconds that the window ends at
+        end: u64,
+        /// Flag set if challenges are paused for the window
+        pause_challenges: bool,
+    },
+    /// Maintenance window ended
+    MaintenanceEnded {
+        /// Window name
+        window: String,
+        /// Unix timestamp in seconds that the window started at
+        start: u64,
+    },
+}
+
+impl JournalEvent {
+    /// Record a computed bid payment along with its payment basis
+    pub fn payment_computed(
+        request: sha256d::Hash,
+        epoch: Option<u32>,
+        bid: sha256d::Hash,
+        basis: &BidPaymentBasis,
+        amount: &Amount,
+    ) -> JournalEvent {
+        JournalEvent::PaymentComputed {
+            request,
+            epoch,
+            bid,
+            formula_version: basis.formula_version,
+            fees_amount: basis.fees_amount.as_sat(),
+            fee_percentage: basis.fee_percentage,
+            num_bids: basis.num_bids,
+            num_challenges: basis.num_challenges,
+            num_responses: basis.num_responses,
+            latency_weighting: basis.latency_weighting.clone(),
+            amount: amount.as_sat(),
+        }
+    }
+}
+
+/// Journal entry with the sequence number and unix timestamp of the event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Sequence number of the entry in the journal
+    pub seq: u64,
+    /// Unix timestamp in milliseconds
+    pub time: u64,
+    /// Recorded event
+    pub event: JournalEvent,
+}
+
+/// Journal file along with the sequence number of the next entry
+struct JournalWriter {
+    /// Journal file opened for appending
+    file: File,
+    /// Sequence number of the next entry
+    seq: u64,
+}
+
+/// Journal recording coordinator decisions as json lines appended to a file.
+/// A disabled journal discards all events
+pub struct Journal {
+    /// Journal writer; None if the journal is disabled
+    writer: Option<Mutex<JournalWriter>>,
+}
+
+impl Journal {
+    /// Open the journal file at path for appending, resuming the entry
+    /// sequence from any entries already in the file
+    pub fn open(path: &str) -> Result<Journal> {
+        let seq = match File::open(path) {
+            Ok(file) => BufReader::new(file).lines().count() as u64,
+            Err(_) => 0,
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| CError::Generic(format!("failed opening journal {}: {}", path, e)))?;
+        info!("Journal {} opened at entry {}", path, seq);
+        Ok(Journal {
+            writer: Some(Mutex::new(JournalWriter { file, seq })),
+        })
+    }
+
+    /// Journal discarding all events
+    pub fn disabled() -> Journal {
+        Journal { writer: None }
+    }
+
+    /// Open the journal at path if set, otherwise return a disabled journal
+    pub fn from_path(path: &Option<String>) -> Result<Journal> {
+        match path {
+            Some(path) => Journal::open(path),
+            None => Ok(Journal::disabled()),
+        }
+    }
+
+    /// Append an event to the journal. Journal failures are logged and do not
+    /// interrupt the coordinator
+    pub fn record(&self, event: JournalEvent) {
+        if let Some(writer) = &self.writer {
+            let mut writer = writer.lock().unwrap();
+            let entry = JournalEntry {
+                seq: writer.seq,
+                time: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or(0),
+                event,
+            };
+            let line = serde_json::to_string(&entry).unwrap();
+            match writeln!(writer.file, "{}", line) {
+                Ok(()) => writer.seq += 1,
+                Err(e) => warn!("failed writing journal entry {}: {}", entry.seq, e),
+            }
+        }
+    }
+
+    /// Read all the entries of the journal file at path
+    pub fn read(path: &str) -> Result<Vec<JournalEntry>> {
+        let file = File::open(path).map_err(|e| CError::Generic(format!("failed opening journal {}: {}", path, e)))?;
+        let mut entries = vec![];
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| CError::Generic(format!("failed reading journal line {}: {}", i + 1, e)))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            entries.push(
+                serde_json::from_str::<JournalEntry>(&line)
+                    .map_err(|e| CError::Generic(format!("invalid journal line {}: {}", i + 1, e)))?,
+            );
+        }
+        Ok(entries)
+    }
+}
+
+/// Journal entry whose recorded decision is not reproduced on replay
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayDiscrepancy {
+    /// Sequence number of the entry
+    pub seq: u64,
+    /// Description of the discrepancy
+    pub reason: String,
+}
+
+/// Replay the journal entries, checking that each recorded decision is
+/// reproduced from the recorded inputs. Proof signatures are verified again,
+/// saved responses are checked against the proofs accepted for the challenge,
+/// challenges are checked against any commitment to them, payment amounts are
+/// calculated again from their payment basis and bid key rotations are verified
+/// again against their recorded signatures. Returns the entries whose decision
+/// could not be reproduced
+pub fn replay(entries: &[JournalEntry]) -> Vec<ReplayDiscrepancy> {
+    let mut discrepancies = vec![];
+    let mut discrepancy = |seq: u64, reason: String| discrepancies.push(ReplayDiscrepancy { seq, reason });
+
+    // challenges issued and bids with accepted proofs per challenge
+    let mut challenges: HashMap<sha256d::Hash, sha256d::Hash> = HashMap::new();
+    let mut accepted: HashMap<sha256d::Hash, HashSet<sha256d::Hash>> = HashMap::new();
+    let mut prev_seq: Option<u64> = None;
+
+    for entry in entries {
+        if let Some(prev) = prev_seq {
+            if entry.seq != prev + 1 {
+                discrepancy(entry.seq, format!("sequence gap after entry {}", prev));
+            }
+        }
+        prev_seq = Some(entry.seq);
+
+        match &entry.event {
+            JournalEvent::ChallengeIssued {
+                request,
+                challenge,
+                commitment,
+                ..
+            } => {
+                if let Some(commitment) = commitment {
+                    if !commitment.opens(challenge) {
+                        discrepancy(
+                            entry.seq,
+                            format!("challenge does not open commitment {}", commitment.hash),
+                        );
+                    }
+                }
+                let _ = challenges.insert(*challenge, *request);
+            }
+            JournalEvent::ProofAccepted { proof } => {
+                match challenges.get(&proof.challenge) {
+                    None => discrepancy(
+                        entry.seq,
+                        format!("proof accepted for unknown challenge {}", proof.challenge),
+                    ),
+                    Some(request) => {
+                        if proof.request.map_or(false, |proof_request| proof_request != *request) {
+                            discrepancy(entry.seq, format!("proof accepted for other request {}", request));
+                        }
+                    }
+                }
+                match proof.to_proof().and_then(|proof| ChallengeProof::verify(&proof)) {
+                    Ok(()) => {
+                        let _ = accepted
+                            .entry(proof.challenge)
+                            .or_insert(HashSet::new())
+                            .insert(proof.bid);
+                    }
+                    Err(e) => discrepancy(entry.seq, format!("accepted proof does not verify: {}", e)),
+                }
+            }
+            JournalEvent::ProofRejected { proof, reason, .. } => {
+                // only signature rejections can be reproduced without the
+                // challenge state at the time of the rejection
+                if let (Some(proof), true) = (proof, reason.starts_with("bad-sig")) {
+                    if let Ok(()) = proof.to_proof().and_then(|proof| ChallengeProof::verify(&proof)) {
+                        discrepancy(entry.seq, "rejected proof signature verifies".to_owned());
+                    }
+                }
+            }
+            JournalEvent::ResponseSaved {
+                request,
+                challenge,
+                bids,
+                ..
+            } => {
+                if challenges.get(challenge) != Some(request) {
+                    discrepancy(entry.seq, format!("response saved for unknown challenge {}", challenge));
+                }
+                let challenge_accepted = accepted.get(challenge);
+                for bid in bids {
+                    if !challenge_accepted.map_or(false, |bids| bids.contains(bid)) {
+                        discrepancy(
+                            entry.seq,
+                            format!("response saved without accepted proof for bid {}", bid),
+                        );
+                    }
+                }
+            }
+            JournalEvent::PaymentComputed {
+                formula_version,
+                fees_amount,
+                fee_percentage,
+                num_bids,
+                num_challenges,
+                num_responses,
+                latency_weighting,
+                amount,
+                ..
+            } => {
+                let basis = BidPaymentBasis {
+                    formula_version: *formula_version,
+                    fees_amount: Amount::from_sat(*fees_amount),
+                    fee_percentage: *fee_percentage,
+                    num_bids: *num_bids,
+                    num_challenges: *num_challenges,
+                    num_responses: *num_responses,
+                    latency_weighting: latency_weighting.clone(),
+                };
+                if basis.amount().as_sat() != *amount {
+                    discrepancy(
+                        entry.seq,
+                        format!(
+                            "payment amount {} does not match basis amount {}",
+                            amount,
+                            basis.amount().as_sat()
+                        ),
+                    );
+                }
+            }
+            JournalEvent::BidKeyRotated { rotation } => {
+                if let Err(e) = verify_bid_key_rotation(rotation) {
+                    discrepancy(entry.seq, format!("bid key rotation does not verify: {}", e));
+                }
+            }
+            // maintenance windows are recorded for audit only
+            JournalEvent::MaintenanceStarted { .. } | JournalEvent::MaintenanceEnded { .. } => {}
+        }
+    }
+    discrepancies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::fs;
+
+    use bitcoin::consensus::serialize;
+    use bitcoin::secp256k1::{Message, Secp256k1, SecretKey};
+
+    use crate::rotation::KeyRotation;
+    use crate::util::testing::gen_dummy_hash;
+
+    /// Generate a journal proof of a challenge signed by a dummy key
+    fn gen_journal_proof(challenge: sha256d::Hash, bid: sha256d::Hash) -> JournalProof {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).unwrap();
+        let sig = secp.sign(&Message::from_slice(&serialize(&challenge)).unwrap(), &secret_key);
+        JournalProof {
+            request: None,
+            challenge,
+            bid,
+            pubkey: PublicKey::from_secret_key(&secp, &secret_key).to_string(),
+            sig: sig.serialize_der().to_hex(),
+            payload: None,
+        }
+    }
+
+    #[test]
+    fn journal_record_read_test() {
+        let path = env::temp_dir().join("coordinator_journal_record_read_test.jsonl");
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+
+        let event = JournalEvent::ChallengeIssued {
+            request: gen_dummy_hash(1),
+            challenge: gen_dummy_hash(2),
+            height: 5,
+            amount_tag: Some(ChallengeAmountTag { sequence: 0, amount: 1 }),
+            wallet: Some("challenges1".to_owned()),
+            commitment: Some(ChallengeCommitment {
+                hash: ChallengeCommitment::commit(&gen_dummy_hash(2)),
+                txid: gen_dummy_hash(3),
+            }),
+        };
+        let journal = Journal::open(path).unwrap();
+        journal.record(event.clone());
+        journal.record(event.clone());
+        drop(journal);
+
+        // sequence is resumed when reopening
+        let journal = Journal::open(path).unwrap();
+        journal.record(event.clone());
+
+        let entries = Journal::read(path).unwrap();
+        assert_eq!(3, entries.len());
+        assert_eq!(
+            vec![0, 1, 2],
+            entries.iter().map(|entry| entry.seq).collect::<Vec<u64>>()
+        );
+        assert_eq!(event, entries[2].event);
+        let _ = fs::remove_file(path);
+
+        // disabled journal discards events
+        Journal::disabled().record(event);
+    }
+
+    #[test]
+    fn replay_test() {
+        let request = gen_dummy_hash(1);
+        let challenge = gen_dummy_hash(2);
+        let proof = gen_journal_proof(challenge, gen_dummy_hash(3));
+        let mut bad_proof = gen_journal_proof(challenge, gen_dummy_hash(4));
+        bad_proof.sig = gen_journal_proof(gen_dummy_hash(9), gen_dummy_hash(4)).sig;
+        let basis = BidPaymentBasis {
+            formula_version: 1,
+            fees_amount: Amount::from_sat(1000),
+            fee_percentage: 50,
+            num_bids: 2,
+            num_challenges: 1,
+            num_responses: 1,
+            latency_weighting: None,
+        };
+        let events = vec![
+            JournalEvent::ChallengeIssued {
+                request,
+                challenge,
+                height: 5,
+                amount_tag: None,
+                wallet: None,
+                commitment: Some(ChallengeCommitment {
+                    hash: ChallengeCommitment::commit(&challenge),
+                    txid: gen_dummy_hash(9),
+                }),
+            },
+            JournalEvent::ProofAccepted { proof: proof.clone() },
+            JournalEvent::ProofRejected {
+                proof: Some(bad_proof.clone()),
+                reason: "bad-sig: secp256k1 error".to_owned(),
+                policies: vec![],
+            },
+            JournalEvent::ResponseSaved {
+                request,
+                challenge,
+                bids: vec![proof.bid],
+                num_challenges: 1,
+            },
+            JournalEvent::payment_computed(request, None, proof.bid, &basis, &basis.amount()),
+        ];
+        let mut entries: Vec<JournalEntry> = events
+            .into_iter()
+            .enumerate()
+            .map(|(seq, event)| JournalEntry {
+                seq: seq as u64,
+                time: 0,
+                event,
+            })
+            .collect();
+        assert_eq!(Vec::<ReplayDiscrepancy>::new(), replay(&entries));
+
+        // latency weighted payments are replayed with their recorded weighting
+        let mut weighted_basis = basis.clone();
+        weighted_basis.formula_version = 2;
+        weighted_basis.latency_weighting = Some(LatencyWeighting::new(vec![1000], vec![50], &[3000], 1));
+        assert_eq!(Amount::from_sat(125), weighted_basis.amount());
+        let mut weighted_entries = vec![JournalEntry {
+            seq: 0,
+            time: 0,
+            event: JournalEvent::payment_computed(request, None, proof.bid, &weighted_basis, &weighted_basis.amount()),
+        }];
+        let serialized = serde_json::to_string(&weighted_entries[0]).unwrap();
+        assert_eq!(weighted_entries[0], serde_json::from_str(&serialized).unwrap());
+        assert!(replay(&weighted_entries).is_empty());
+        weighted_entries[0].event =
+            JournalEvent::payment_computed(request, None, proof.bid, &weighted_basis, &basis.amount());
+        assert_eq!(1, replay(&weighted_entries).len());
+
+        // challenges not opening their commitment are reported
+        let mut uncommitted = vec![entries[0].clone()];
+        if let JournalEvent::ChallengeIssued { commitment, .. } = &mut uncommitted[0].event {
+            *commitment = Some(ChallengeCommitment {
+                hash: ChallengeCommitment::commit(&request),
+                txid: gen_dummy_hash(9),
+            });
+        }
+        assert!(replay(&uncommitted)[0].reason.contains("does not open commitment"));
+
+        // tamper with recorded decisions
+        entries[1].event = JournalEvent::ProofAccepted { proof: bad_proof };
+        entries[2].event = JournalEvent::ProofRejected {
+            proof: Some(proof.clone()),
+            reason: "bad-sig: secp256k1 error".to_owned(),
+            policies: vec![],
+        };
+        entries[4].event = JournalEvent::payment_computed(request, None, proof.bid, &basis, &Amount::from_sat(1));
+        let discrepancies = replay(&entries);
+        assert_eq!(
+            vec![1, 2, 3, 4],
+            discrepancies.iter().map(|d| d.seq).collect::<Vec<u64>>()
+        );
+        assert!(discrepancies[2].reason.contains("without accepted proof"));
+
+        // sequence gaps are reported
+        let _ = entries.remove(1);
+        assert!(replay(&entries)[0].reason.contains("sequence gap"));
+    }
+
+    #[test]
+    fn replay_bid_key_rotation_test() {
+        let secp = Secp256k1::new();
+        let old_key = SecretKey::from_slice(&[0xcd; 32]).unwrap();
+        let new_pubkey = PublicKey::from_secret_key(&secp, &SecretKey::from_slice(&[0xce; 32]).unwrap());
+        let message = KeyRotation::signed_message(&gen_dummy_hash(1), &gen_dummy_hash(3), &new_pubkey).unwrap();
+        let rotation = BidKeyRotation {
+            txid: gen_dummy_hash(3),
+            request: gen_dummy_hash(1),
+            genesis_blockhash: gen_dummy_hash(0),
+            old_pubkey: PublicKey::from_secret_key(&secp, &old_key),
+            new_pubkey,
+            sig: Some(secp.sign(&message, &old_key).serialize_der().to_hex()),
+            new_sig: None,
+            policy: "old-key".to_owned(),
+            time: 0,
+        };
+        let mut entries = vec![JournalEntry {
+            seq: 0,
+            time: 0,
+            event: JournalEvent::BidKeyRotated { rotation },
+        }];
+        let serialized = serde_json::to_string(&entries[0]).unwrap();
+        assert!(serialized.contains("\"event\":\"bid_key_rotated\""));
+        assert_eq!(entries[0], serde_json::from_str(&serialized).unwrap());
+        assert!(replay(&entries).is_empty());
+
+        // rotations not signed as required by their policy
+        if let JournalEvent::BidKeyRotated { rotation } = &mut entries[0].event {
+            rotation.policy = "both-keys".to_owned();
+        }
+        let discrepancies = replay(&entries);
+        assert_eq!(1, discrepancies.len());
+        assert!(discrepancies[0].reason.contains("bid key rotation does not verify"));
+    }
+}
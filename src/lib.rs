@@ -18,26 +18,60 @@
 
 #[macro_use]
 extern crate log;
+extern crate aes_gcm;
 extern crate base64;
 extern crate bitcoin;
 extern crate config as config_rs;
+extern crate env_logger;
+extern crate flate2;
 extern crate futures;
 extern crate hyper;
 extern crate ocean_rpc;
+extern crate rand;
 extern crate rust_ocean as ocean;
 extern crate serde as serde;
 extern crate serde_json;
+extern crate signal_hook;
 #[macro_use]
 extern crate mongodb;
 extern crate jsonrpc_http_server;
 
 pub mod api;
+pub mod archive;
+pub mod audit;
+pub mod auth;
+pub mod blacklist;
 pub mod challenger;
+pub mod client;
+pub mod clock;
 pub mod config;
+pub mod config_check;
+pub mod connectivity;
+pub mod consistency;
 pub mod coordinator;
+pub mod coverage;
+pub mod dead_letter;
 pub mod error;
+pub mod funding;
+pub mod journal;
 pub mod listener;
+pub mod maintenance;
+pub mod merkle;
+pub mod monitor;
+pub mod payload;
 pub mod payments;
+pub mod proof;
+pub mod proof_policy;
+pub mod proof_vectors;
+pub mod reconciliation;
+pub mod refunds;
+pub mod registry;
+pub mod rerun;
+pub mod rotation;
+pub mod stall;
+pub mod watchdog;
 
 pub mod interfaces;
 pub mod util;
+
+pub use crate::coordinator::{CoordinatorBuilder, CoordinatorEvent, CoordinatorHandle};
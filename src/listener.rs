@@ -2,129 +2,412 @@
 //!
 //! Listener interface and implementations
 
-use std::net::ToSocketAddrs;
-use std::str::FromStr;
-use std::sync::mpsc::Sender;
-use std::sync::{Arc, RwLock};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, Sender, SyncSender, TryRecvError, TrySendError};
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Duration;
 
-use bitcoin::consensus::serialize;
-use bitcoin::hashes::{hex::FromHex, sha256d};
-use bitcoin::secp256k1::{Message, PublicKey, Secp256k1, Signature};
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::hashes::sha256d;
 use futures::future;
 use futures::sync::oneshot;
 use hyper::rt::{self, Future, Stream};
-use hyper::service::service_fn;
+use hyper::server::conn::AddrStream;
+use hyper::service::{make_service_fn, service_fn};
 use hyper::{Body, Method, Request, Response, Server, StatusCode};
-use serde_json::{self, Value};
 
-use crate::challenger::{ChallengeResponse, ChallengeState};
-use crate::error::Result;
-use crate::interfaces::bid::Bid;
+use crate::blacklist::Blacklist;
+use crate::challenger::{ChallengeResponse, LatestChallenge};
+use crate::dead_letter::DeadLetters;
+use crate::interfaces::request::Request as ServiceRequest;
+use crate::journal::{Journal, JournalEvent, JournalProof};
+use crate::proof::{check_proof_challenge, ChallengeProof};
+use crate::proof_policy::{policy_rejection_reason, ProofContext, ProofPolicies};
+use crate::registry::ChallengeRegistry;
 use crate::util::handler::Handle;
 
-/// Messsage type for challenge proofs sent by guardnodes
-#[derive(Debug)]
-struct ChallengeProof {
-    /// Challenge (transaction id) hash
-    hash: sha256d::Hash,
-    /// Challenge signature for hash and pubkey
-    sig: Signature,
-    /// Pubkey used to generate challenge signature
-    bid: Bid,
+/// Job queued for the proof verification pool, carrying the parsed proof and
+/// the channel on which the verification outcome is returned to the handler
+struct VerifyJob {
+    /// Challenge proof pending signature verification
+    proof: ChallengeProof,
+    /// Request of the challenge that the proof responds to
+    request: ServiceRequest,
+    /// Latest challenge of the request
+    latest: Arc<LatestChallenge>,
+    /// Channel to return the verification result, or the response status and
+    /// reason of the rejection, to the http handler
+    result: oneshot::Sender<std::result::Result<(), (StatusCode, String)>>,
 }
 
-impl ChallengeProof {
-    /// Parse serde json value into ChallengeProof struct result
-    fn from_json(val: Value) -> Result<ChallengeProof> {
-        let hash = sha256d::Hash::from_hex(val["hash"].as_str().unwrap_or(""))?;
-        let txid = sha256d::Hash::from_hex(val["txid"].as_str().unwrap_or(""))?;
-        let pubkey = PublicKey::from_str(val["pubkey"].as_str().unwrap_or(""))?;
-        let sig = Signature::from_der(&Vec::<u8>::from_hex(val["sig"].as_str().unwrap_or(""))?)?;
-        Ok(ChallengeProof {
-            hash,
-            sig,
-            bid: Bid {
-                txid,
-                pubkey,
-                payment: None,
-            },
-        })
+impl VerifyJob {
+    /// Check whether the proof still responds to the latest challenge of its
+    /// request
+    fn is_current(&self) -> bool {
+        self.latest.hash() == Some(self.proof.hash)
     }
+}
 
-    /// Verify the challenge proof signature using the pubkey and challenge hash
-    fn verify(challenge_proof: &ChallengeProof) -> Result<()> {
-        let secp = Secp256k1::new();
-        secp.verify(
-            &Message::from_slice(&serialize(&challenge_proof.hash))?,
-            &challenge_proof.sig,
-            &challenge_proof.bid.pubkey,
-        )?;
-        Ok(())
+/// Interval in ms that idle pool threads wait for proofs of current challenges
+/// before checking for late proofs again
+const VERIFY_POLL_MS: u64 = 50;
+
+/// Queues of the proof verification pool. Proofs responding to the latest
+/// challenge of their request are verified first, while proofs whose challenge
+/// ended while queued are moved to a separate bounded queue of late proofs,
+/// verified only when no current proofs are waiting, so that bursts of
+/// stragglers do not hold up the proofs of challenges in flight
+struct VerifyQueues {
+    /// Queue of proofs of current challenges
+    current: Mutex<Receiver<VerifyJob>>,
+    /// Sender of proofs to the late proofs queue
+    late_tx: SyncSender<VerifyJob>,
+    /// Queue of late proofs
+    late: Mutex<Receiver<VerifyJob>>,
+}
+
+impl VerifyQueues {
+    /// Get the next job to verify, waiting for current proofs if none are
+    /// queued. Late proofs that do not fit in the late proofs queue are
+    /// rejected. Returns Err once all current proof senders have been dropped
+    fn next(&self, journal: &Journal) -> std::result::Result<Option<VerifyJob>, ()> {
+        // lock only for the duration of each receive
+        let current = self.current.lock().unwrap().try_recv();
+        let job = match current {
+            Ok(job) => job,
+            Err(TryRecvError::Disconnected) => return Err(()),
+            Err(TryRecvError::Empty) => {
+                if let Ok(job) = self.late.lock().unwrap().try_recv() {
+                    return Ok(Some(job));
+                }
+                let current = self
+                    .current
+                    .lock()
+                    .unwrap()
+                    .recv_timeout(Duration::from_millis(VERIFY_POLL_MS));
+                match current {
+                    Ok(job) => job,
+                    Err(RecvTimeoutError::Timeout) => return Ok(None),
+                    Err(RecvTimeoutError::Disconnected) => return Err(()),
+                }
+            }
+        };
+        if job.is_current() {
+            return Ok(Some(job));
+        }
+        match self.late_tx.try_send(job) {
+            Ok(()) => Ok(None),
+            Err(TrySendError::Full(job)) | Err(TrySendError::Disconnected(job)) => {
+                journal.record(JournalEvent::ProofRejected {
+                    proof: Some(JournalProof::from_proof(&job.proof)),
+                    reason: "late-queue-full".to_owned(),
+                    policies: vec![],
+                });
+                let _ = job
+                    .result
+                    .send(Err((StatusCode::SERVICE_UNAVAILABLE, "late-queue-full".to_owned())));
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Journal, blacklist, acceptance policies and dead letter capture that the
+/// listener checks and records challenge proofs with
+#[derive(Clone)]
+pub struct ListenerContext {
+    /// Journal recording accepted and rejected proofs
+    pub journal: Arc<Journal>,
+    /// Blacklist of bids repeatedly sending invalid proofs
+    pub blacklist: Arc<Blacklist>,
+    /// Acceptance policies of proofs
+    pub policies: Arc<ProofPolicies>,
+    /// Dead letter capture of submissions that could not be parsed
+    pub dead_letters: Arc<DeadLetters>,
+    /// Flag to reject submissions with fields that are not challenge proof
+    /// fields
+    pub strict_fields: bool,
+}
+
+impl ListenerContext {
+    /// Return a listener context that records nothing and checks proofs
+    /// against no blacklist or acceptance policies
+    pub fn disabled() -> ListenerContext {
+        ListenerContext {
+            journal: Arc::new(Journal::disabled()),
+            blacklist: Arc::new(Blacklist::disabled()),
+            policies: Arc::new(ProofPolicies::disabled()),
+            dead_letters: Arc::new(DeadLetters::disabled()),
+            strict_fields: false,
+        }
+    }
+}
+
+/// Pool of threads verifying challenge proof signatures away from the hyper
+/// worker threads. Proofs are passed through a bounded queue so that under
+/// proof floods excess requests are rejected instead of starving connection
+/// handling. Verified proofs are forwarded to the challenger by the pool and
+/// verification outcomes are recorded in the journal. Invalid signatures count
+/// as strikes towards blacklisting the proof bid. The pool also carries the
+/// acceptance policies that proofs are checked against before being queued and
+/// the dead letter capture of submissions that could not be parsed. Proofs of
+/// current challenges are verified ahead of late proofs
+#[derive(Clone)]
+struct VerifyPool {
+    /// Bounded queue of verification jobs shared by all pool threads
+    queue: SyncSender<VerifyJob>,
+    /// Journal recording accepted and rejected proofs
+    journal: Arc<Journal>,
+    /// Blacklist of bids repeatedly sending invalid proofs
+    blacklist: Arc<Blacklist>,
+    /// Acceptance policies of proofs
+    policies: Arc<ProofPolicies>,
+    /// Dead letter capture of submissions that could not be parsed
+    dead_letters: Arc<DeadLetters>,
+    /// Flag to reject submissions with fields that are not challenge proof
+    /// fields
+    strict_fields: bool,
+}
+
+impl VerifyPool {
+    /// Spawn a verification pool with the given number of threads, at least
+    /// one, queue size and late proofs queue size. Pool threads exit once all
+    /// queue senders have been dropped
+    fn new(
+        num_threads: usize,
+        queue_size: usize,
+        late_queue_size: usize,
+        challenge_resp: Sender<ChallengeResponse>,
+        context: ListenerContext,
+    ) -> VerifyPool {
+        let ListenerContext {
+            journal,
+            blacklist,
+            policies,
+            dead_letters,
+            strict_fields,
+        } = context;
+        let (queue_tx, queue_rx) = sync_channel::<VerifyJob>(queue_size);
+        let (late_tx, late_rx) = sync_channel::<VerifyJob>(late_queue_size);
+        let queues = Arc::new(VerifyQueues {
+            current: Mutex::new(queue_rx),
+            late_tx,
+            late: Mutex::new(late_rx),
+        });
+        // queued proofs would never be verified without any threads
+        for i in 0..num_threads.max(1) {
+            let queues = queues.clone();
+            let challenge_resp = challenge_resp.clone();
+            let journal = journal.clone();
+            let blacklist = blacklist.clone();
+            let _ = thread::Builder::new()
+                .name(format!("verifier-{}", i))
+                .spawn(move || loop {
+                    match queues.next(&journal) {
+                        Ok(None) => continue,
+                        Ok(Some(job)) => {
+                            let result = match ChallengeProof::verify(&job.proof) {
+                                Ok(()) => {
+                                    blacklist.clear_strikes(&job.proof.bid.txid);
+                                    journal.record(JournalEvent::ProofAccepted {
+                                        proof: JournalProof::from_proof(&job.proof),
+                                    });
+                                    // send successful response to challenger
+                                    challenge_resp
+                                        .send(ChallengeResponse(job.proof.hash, job.proof.bid.clone()))
+                                        .unwrap();
+                                    Ok(())
+                                }
+                                Err(e) => {
+                                    let _ = blacklist.strike(&job.proof.bid.txid, &job.request);
+                                    journal.record(JournalEvent::ProofRejected {
+                                        proof: Some(JournalProof::from_proof(&job.proof)),
+                                        reason: format!("bad-sig: {}", e),
+                                        policies: vec![],
+                                    });
+                                    Err((StatusCode::BAD_REQUEST, format!("bad-sig: {}", e)))
+                                }
+                            };
+                            // handler might have gone away - ignore
+                            let _ = job.result.send(result);
+                        }
+                        Err(_) => break, // all senders dropped
+                    }
+                })
+                .expect("failed spawning verifier thread");
+        }
+        VerifyPool {
+            queue: queue_tx,
+            journal,
+            blacklist,
+            policies,
+            dead_letters,
+            strict_fields,
+        }
+    }
+
+    /// Queue a challenge proof for verification and return a future resolving
+    /// to the http response once the proof has been verified. If the queue is
+    /// full the proof is rejected immediately
+    fn verify(
+        &self,
+        proof: ChallengeProof,
+        request: ServiceRequest,
+        latest: Arc<LatestChallenge>,
+    ) -> impl Future<Item = Response<Body>, Error = hyper::Error> + Send {
+        let (result_tx, result_rx) = oneshot::channel();
+        if let Err(e) = self.queue.try_send(VerifyJob {
+            proof,
+            request,
+            latest,
+            result: result_tx,
+        }) {
+            let (msg, job) = match e {
+                TrySendError::Full(job) => ("verify-queue-full", job),
+                TrySendError::Disconnected(job) => ("verify-pool-down", job),
+            };
+            self.journal.record(JournalEvent::ProofRejected {
+                proof: Some(JournalProof::from_proof(&job.proof)),
+                reason: msg.to_owned(),
+                policies: vec![],
+            });
+            return future::Either::A(future::ok(response(StatusCode::SERVICE_UNAVAILABLE, msg.to_owned())));
+        }
+        future::Either::B(result_rx.then(|res| {
+            Ok::<_, hyper::Error>(match res {
+                Ok(Ok(())) => response(StatusCode::OK, String::new()),
+                Ok(Err((status, reason))) => response(status, reason),
+                Err(_) => response(StatusCode::SERVICE_UNAVAILABLE, "verify-pool-down".to_owned()),
+            })
+        }))
+    }
+}
+
+/// Parse the challenge proof request body, route the proof to the challenge
+/// state of its request and check that there is an active challenge, that the
+/// proof bid exists and is not blacklisted, that the proof hash is correct and
+/// that the proof passes the acceptance policies. Proofs are routed by the
+/// request txid of the listener path, or else of the v2 proof. Returns the
+/// proof ready for signature verification along with the challenge request and
+/// its latest challenge or the error response. Rejected proofs are recorded in
+/// the journal, along with the results of all the policies if rejected by a
+/// policy, and submissions that could not be parsed are captured as dead
+/// letters
+fn check_challengeproof(
+    body: &[u8],
+    remote_addr: Option<SocketAddr>,
+    route: Option<sha256d::Hash>,
+    registry: &ChallengeRegistry,
+    verify_pool: &VerifyPool,
+) -> std::result::Result<(ChallengeProof, ServiceRequest, Arc<LatestChallenge>), Response<Body>> {
+    let journal = &verify_pool.journal;
+    let blacklist = &verify_pool.blacklist;
+    // parse the challenge proof from the request body, route and check it
+    let res = match ChallengeProof::from_slice(body, verify_pool.strict_fields) {
+        Ok(proof) => {
+            // record the proof along with any rejection
+            let journal_proof = JournalProof::from_proof(&proof);
+            registry
+                .route(route.or(proof.request), &proof.hash)
+                .and_then(|challenge| {
+                    let proof = check_proof_challenge(proof, &challenge)?;
+                    let request = blacklist.check_proof(&proof, &challenge)?;
+                    Ok((proof, request, challenge))
+                })
+                .map_err(|e| (Some(journal_proof), e))
+        }
+        Err(ref e) if e.is_data() => Err((None, format!("bad-proof-data: {}", e))),
+        Err(e) => Err((None, format!("bad-json-data: {}", e))),
+    };
+    let (proof, request, challenge) = res.map_err(|(proof, reason)| {
+        if proof.is_none() {
+            verify_pool
+                .dead_letters
+                .capture(body, &reason, remote_addr.map(|addr| addr.ip().to_string()), route);
+        }
+        let resp = response(StatusCode::BAD_REQUEST, reason.clone());
+        journal.record(JournalEvent::ProofRejected {
+            proof,
+            reason,
+            policies: vec![],
+        });
+        resp
+    })?;
+
+    let context = ProofContext {
+        remote_addr: remote_addr.map(|addr| addr.ip()),
+        challenge_age: challenge
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|ch| ch.latest_challenge.sent_time())
+            .map(|time| time.elapsed()),
+    };
+    if let Err(results) = verify_pool.policies.check(&proof, &context) {
+        let reason = policy_rejection_reason(&results);
+        let resp = response(StatusCode::BAD_REQUEST, reason.clone());
+        journal.record(JournalEvent::ProofRejected {
+            proof: Some(JournalProof::from_proof(&proof)),
+            reason,
+            policies: results,
+        });
+        return Err(resp);
     }
+    let latest = challenge
+        .read()
+        .unwrap()
+        .as_ref()
+        .map(|ch| ch.latest_challenge.clone())
+        .unwrap_or_else(|| Arc::new(LatestChallenge::new(None)));
+    Ok((proof, request, latest))
 }
 
-/// Handle the POST request /challengeproof. Validate body is in json format,
-/// parse this into a ChallengeProof struct and then verify that there is an
-/// active challenge, that the proof bid exists and that the sig is correct.
-/// Successful responses are pushed to the challenge response channel for the
+/// Handle the POST request /challengeproof or /challengeproof/<request_txid>.
+/// Validate body is in json format, parse this into a v1 or v2 ChallengeProof
+/// struct, route it to the challenge state of its request and then verify that
+/// there is an active challenge for the proof request, that the proof bid
+/// exists, that the proof passes the acceptance policies and that the sig is
+/// correct. The remote address of the guardnode is read from the request
+/// extensions. Signature verification is handed to the verification pool,
+/// which pushes successful responses to the challenge response channel for the
 /// challenger to receive
 fn handle_challengeproof(
     req: Request<Body>,
-    challenge: Arc<RwLock<Option<ChallengeState>>>,
-    challenge_resp: Sender<ChallengeResponse>,
+    route: Option<sha256d::Hash>,
+    registry: Arc<ChallengeRegistry>,
+    verify_pool: VerifyPool,
 ) -> impl Future<Item = Response<Body>, Error = hyper::Error> + Send {
-    let resp = req.into_body().concat2().map(move |body| {
-        // parse request body
-        match serde_json::from_slice::<Value>(body.as_ref()) {
-            // parse json from body
-            Ok(obj) => match ChallengeProof::from_json(obj) {
-                // parse challenge proof from json
-                Ok(proof) => {
-                    // check for an active challenge
-                    let ch_lock = challenge.read().unwrap();
-                    if let Some(ch) = ch_lock.as_ref() {
-                        if let Some(h) = ch.latest_challenge {
-                            // check challenge proof bid exists
-                            if !ch.bids.contains(&proof.bid) {
-                                return response(StatusCode::BAD_REQUEST, "bad-bid".to_owned());
-                            }
-                            // drop lock immediately
-                            std::mem::drop(ch_lock);
-                            // check challenge proof hash is correct
-                            if proof.hash != h {
-                                return response(StatusCode::BAD_REQUEST, "bad-hash".to_owned());
-                            }
-                            // check challenge proof sig is correct
-                            if let Err(e) = ChallengeProof::verify(&proof) {
-                                return response(StatusCode::BAD_REQUEST, format!("bad-sig: {}", e));
-                            }
-                            // send successful response to challenger
-                            challenge_resp
-                                .send(ChallengeResponse(proof.hash, proof.bid.clone()))
-                                .unwrap();
-                            return response(StatusCode::OK, String::new());
-                        }
-                    } else {
-                        // drop lock immediately
-                        std::mem::drop(ch_lock);
-                    }
-                    response(StatusCode::BAD_REQUEST, format!("no-active-challenge"))
-                }
-                Err(e) => response(StatusCode::BAD_REQUEST, format!("bad-proof-data: {}", e)),
-            },
-            Err(e) => response(StatusCode::BAD_REQUEST, format!("bad-json-data: {}", e)),
+    let remote_addr = req.extensions().get::<SocketAddr>().cloned();
+    let resp = req.into_body().concat2().and_then(move |body| {
+        match check_challengeproof(body.as_ref(), remote_addr, route, &registry, &verify_pool) {
+            Ok((proof, request, latest)) => future::Either::A(verify_pool.verify(proof, request, latest)),
+            Err(resp) => future::Either::B(future::ok(resp)),
         }
     });
     resp
 }
 
-/// Handler for the listener server. Only allows requests to /
-/// and to the /challengeproof POST uri for receiving challenges from guardnodes
+/// Get the request txid that a challenge proof is routed to from a
+/// /challengeproof/<request_txid> path
+fn challengeproof_route(path: &str) -> Option<sha256d::Hash> {
+    if path.starts_with(CHALLENGEPROOF_PATH_PREFIX) {
+        sha256d::Hash::from_hex(&path[CHALLENGEPROOF_PATH_PREFIX.len()..]).ok()
+    } else {
+        None
+    }
+}
+
+/// Prefix of the listener paths that route challenge proofs by request txid
+const CHALLENGEPROOF_PATH_PREFIX: &str = "/challengeproof/";
+
+/// Handler for the listener server. Only allows requests to / and to the
+/// /challengeproof and /challengeproof/<request_txid> POST uris for receiving
+/// challenges from guardnodes
 fn handle(
     req: Request<Body>,
-    challenge: Arc<RwLock<Option<ChallengeState>>>,
-    challenge_resp: Sender<ChallengeResponse>,
+    registry: Arc<ChallengeRegistry>,
+    verify_pool: VerifyPool,
 ) -> impl Future<Item = Response<Body>, Error = hyper::Error> + Send {
     let resp = match (req.method(), req.uri().path()) {
         (&Method::GET, "/") => response(
@@ -133,7 +416,12 @@ fn handle(
         ),
 
         (&Method::POST, "/challengeproof") => {
-            return future::Either::A(handle_challengeproof(req, challenge, challenge_resp));
+            return future::Either::A(handle_challengeproof(req, None, registry, verify_pool));
+        }
+
+        (&Method::POST, path) if challengeproof_route(path).is_some() => {
+            let route = challengeproof_route(path);
+            return future::Either::A(handle_challengeproof(req, route, registry, verify_pool));
         }
 
         _ => response(StatusCode::NOT_FOUND, format!("Invalid request {}", req.uri().path())),
@@ -151,24 +439,36 @@ fn response(status: StatusCode, message: String) -> Response<Body> {
 }
 
 /// Run the listener server that listens to a specified address for incoming
-/// requests and passes these to handle(). The server runs in a new thread and
-/// can be shutdown via a future oneshot channel receiver from the main method
-/// of the coordinator
+/// challenge proofs, routes them to the challenge states of the requests in the
+/// registry and checks them before handing them to a pool of verify_threads
+/// threads. Proofs of current challenges are queued ahead of late proofs, which
+/// are buffered in a separate bounded queue. The server runs in a new thread
+/// and is shutdown via the returned handle
 pub fn run_listener(
     listener_host: &String,
-    challenge: Arc<RwLock<Option<ChallengeState>>>,
+    registry: Arc<ChallengeRegistry>,
     ch_resp: Sender<ChallengeResponse>,
+    verify_threads: usize,
+    verify_queue_size: usize,
+    late_queue_size: usize,
+    context: ListenerContext,
 ) -> Handle {
     let addr: Vec<_> = listener_host
         .to_socket_addrs()
         .expect("Unable to resolve domain")
         .collect();
 
-    let listener_service = move || {
-        let challenge = Arc::clone(&challenge);
-        let challenge_resp = ch_resp.clone();
-        service_fn(move |req: Request<Body>| handle(req, challenge.clone(), challenge_resp.clone()))
-    };
+    let verify_pool = VerifyPool::new(verify_threads, verify_queue_size, late_queue_size, ch_resp, context);
+    let listener_service = make_service_fn(move |socket: &AddrStream| {
+        // pass the remote address of each connection to the proof handler
+        let remote_addr = socket.remote_addr();
+        let registry = Arc::clone(&registry);
+        let verify_pool = verify_pool.clone();
+        Ok::<_, hyper::Error>(service_fn(move |mut req: Request<Body>| {
+            let _ = req.extensions_mut().insert(remote_addr);
+            handle(req, registry.clone(), verify_pool.clone())
+        }))
+    });
 
     let (tx, rx) = oneshot::channel();
     let server = Server::bind(&addr[0])
@@ -191,125 +491,35 @@ mod tests {
     use super::*;
 
     use std::sync::mpsc::{channel, Receiver, TryRecvError};
+    use std::sync::RwLock;
+    use std::time::Instant;
 
-    use bitcoin::hashes::hex::ToHex;
-    use bitcoin::secp256k1::SecretKey;
+    use bitcoin::consensus::serialize;
+    use bitcoin::hashes::hex::{FromHex, ToHex};
+    use bitcoin::hashes::sha256d;
+    use bitcoin::secp256k1::{Message, Secp256k1, SecretKey};
 
+    use crate::config::ProofPolicyConfig;
+    use crate::interfaces::bid::Bid;
+    use crate::proof_vectors::{
+        proof_vectors, vector_bid_pubkey, VECTOR_BID_TXID, VECTOR_CHALLENGE_HASH, VECTOR_REQUEST_TXID,
+    };
     use crate::util::testing::{gen_challenge_state_with_challenge, gen_dummy_hash, setup_logger};
 
-    #[test]
-    fn challengeproof_from_json_test() {
-        setup_logger();
-        // good data
-        let data = r#"
-        {
-            "txid": "0000000000000000000000000000000000000000000000000000000000000000",
-            "pubkey": "03356190524d52d7e94e1bd43e8f23778e585a4fe1f275e65a06fa5ceedb67d111",
-            "hash": "0404040404040404040404040404040404040404040404040404040404040404",
-            "sig": "304402201742daea5ec3b7306b9164be862fc1659cc830032180b8b17beffe02645860d602201039eba402d22e630308e6af05da8dd4f05b51b7d672ca5fc9e3b0a57776365c"
-        }"#;
-        let proof = ChallengeProof::from_json(serde_json::from_str::<Value>(data).unwrap());
-        assert!(proof.is_ok());
-
-        // bad txid
-        let data = r#"
-        {
-            "txid": "",
-            "pubkey": "03356190524d52d7e94e1bd43e8f23778e585a4fe1f275e65a06fa5ceedb67d111",
-            "hash": "0404040404040404040404040404040404040404040404040404040404040404",
-            "sig": "304402201742daea5ec3b7306b9164be862fc1659cc830032180b8b17beffe02645860d602201039eba402d22e630308e6af05da8dd4f05b51b7d672ca5fc9e3b0a57776365c"
-        }"#;
-        let proof = ChallengeProof::from_json(serde_json::from_str::<Value>(data).unwrap());
-        assert!(proof.err().unwrap().to_string().contains("bitcoin hashes hex error"));
-
-        // bad pubkey
-        let data = r#"
-        {
-            "txid": "0000000000000000000000000000000000000000000000000000000000000000",
-            "pubkey": "0356190524d52d7e94e1bd43e8f23778e585a4fe1f275e65a06fa5ceedb67d111",
-            "hash": "0404040404040404040404040404040404040404040404040404040404040404",
-            "sig": "304402201742daea5ec3b7306b9164be862fc1659cc830032180b8b17beffe02645860d602201039eba402d22e630308e6af05da8dd4f05b51b7d672ca5fc9e3b0a57776365c"
-        }"#;
-        let proof = ChallengeProof::from_json(serde_json::from_str::<Value>(data).unwrap());
-        assert!(proof.err().unwrap().to_string().contains("secp256k1 error"));
-
-        // bad hash
-        let data = r#"
-        {
-            "txid": "0000000000000000000000000000000000000000000000000000000000000000",
-            "pubkey": "03356190524d52d7e94e1bd43e8f23778e585a4fe1f275e65a06fa5ceedb67d111",
-            "hash": "04040404040404040404040404040404040404040404040404040404040404",
-            "sig": "304402201742daea5ec3b7306b9164be862fc1659cc830032180b8b17beffe02645860d602201039eba402d22e630308e6af05da8dd4f05b51b7d672ca5fc9e3b0a57776365c"
-        }"#;
-        let proof = ChallengeProof::from_json(serde_json::from_str::<Value>(data).unwrap());
-        assert!(proof.err().unwrap().to_string().contains("bitcoin hashes hex error"));
-
-        // bad sig
-        let data = r#"
-        {
-            "txid": "0000000000000000000000000000000000000000000000000000000000000000",
-            "pubkey": "03356190524d52d7e94e1bd43e8f23778e585a4fe1f275e65a06fa5ceedb67d111",
-            "hash": "0404040404040404040404040404040404040404040404040404040404040404",
-            "sig": "4402201742daea5ec3b7306b9164be862fc1659cc830032180b8b17beffe02645860d602201039eba402d22e630308e6af05da8dd4f05b51b7d672ca5fc9e3b0a57776365c"
-        }"#;
-        let proof = ChallengeProof::from_json(serde_json::from_str::<Value>(data).unwrap());
-        assert!(proof.err().unwrap().to_string().contains("secp256k1 error"));
-    }
-
-    #[test]
-    fn challengeproof_verify_test() {
-        setup_logger();
-        let chl_hash = gen_dummy_hash(11);
-        let _challenge_state = gen_challenge_state_with_challenge(&gen_dummy_hash(3), &chl_hash);
-        let bid_txid = _challenge_state.bids.iter().next().unwrap().txid;
-        let bid_pubkey = _challenge_state.bids.iter().next().unwrap().pubkey;
-
-        // verify good sig
-        let secp = Secp256k1::new();
-        let secret_key = SecretKey::from_slice(&[0xaa; 32]).unwrap();
-        let sig = secp.sign(&Message::from_slice(&serialize(&chl_hash)).unwrap(), &secret_key);
-
-        let proof = ChallengeProof {
-            hash: chl_hash,
-            sig: sig,
-            bid: Bid {
-                txid: bid_txid,
-                pubkey: bid_pubkey,
-                payment: None,
-            },
-        };
-
-        let verify = ChallengeProof::verify(&proof);
-        assert!(verify.is_ok());
-
-        // verify bad sig
-        let secret_key = SecretKey::from_slice(&[0xbb; 32]).unwrap();
-        let sig = secp.sign(&Message::from_slice(&serialize(&chl_hash)).unwrap(), &secret_key);
-
-        let proof = ChallengeProof {
-            hash: chl_hash,
-            sig: sig,
-            bid: Bid {
-                txid: bid_txid,
-                pubkey: bid_pubkey,
-                payment: None,
-            },
-        };
-
-        let verify = ChallengeProof::verify(&proof);
-        assert!(verify.err().unwrap().to_string().contains("secp256k1 error"));
-    }
-
     #[test]
     fn handle_test() {
         setup_logger();
         let (resp_tx, resp_rx): (Sender<ChallengeResponse>, Receiver<ChallengeResponse>) = channel();
+        let verify_pool = VerifyPool::new(2, 16, 16, resp_tx, ListenerContext::disabled());
 
         let chl_hash = gen_dummy_hash(11);
         let _challenge_state = gen_challenge_state_with_challenge(&gen_dummy_hash(3), &chl_hash);
         let bid_txid = _challenge_state.bids.iter().next().unwrap().txid;
         let bid_pubkey = _challenge_state.bids.iter().next().unwrap().pubkey;
+        let request_txid = _challenge_state.request.txid;
         let challenge_state = Arc::new(RwLock::new(Some(_challenge_state)));
+        let registry = Arc::new(ChallengeRegistry::new());
+        let _registration = registry.register(request_txid, challenge_state.clone());
 
         // Request get /
         let data = "";
@@ -318,7 +528,7 @@ mod tests {
             .uri("/")
             .body(Body::from(data))
             .unwrap();
-        let _ = handle(request, challenge_state.clone(), resp_tx.clone())
+        let _ = handle(request, registry.clone(), verify_pool.clone())
             .map(|res| {
                 assert_eq!(res.status(), StatusCode::OK);
                 res.into_body()
@@ -341,7 +551,7 @@ mod tests {
             .uri("/dummy")
             .body(Body::from(data))
             .unwrap();
-        let _ = handle(request, challenge_state.clone(), resp_tx.clone())
+        let _ = handle(request, registry.clone(), verify_pool.clone())
             .map(|res| {
                 assert_eq!(res.status(), StatusCode::NOT_FOUND);
                 res.into_body()
@@ -361,7 +571,7 @@ mod tests {
             .uri("/dummy")
             .body(Body::from(data))
             .unwrap();
-        let _ = handle(request, challenge_state.clone(), resp_tx.clone())
+        let _ = handle(request, registry.clone(), verify_pool.clone())
             .map(|res| {
                 assert_eq!(res.status(), StatusCode::NOT_FOUND);
                 res.into_body()
@@ -381,7 +591,7 @@ mod tests {
             .uri("/challengeproof")
             .body(Body::from(data))
             .unwrap();
-        let _ = handle(request, challenge_state.clone(), resp_tx.clone())
+        let _ = handle(request, registry.clone(), verify_pool.clone())
             .map(|res| {
                 assert_eq!(res.status(), StatusCode::BAD_REQUEST);
                 res.into_body()
@@ -414,9 +624,9 @@ mod tests {
         let request = Request::builder()
             .method("POST")
             .uri("/challengeproof")
-            .body(Body::from(data))
+            .body(Body::from(data.clone()))
             .unwrap();
-        let _ = handle(request, challenge_state.clone(), resp_tx.clone())
+        let _ = handle(request, registry.clone(), verify_pool.clone())
             .map(|res| {
                 assert_eq!(res.status(), StatusCode::OK);
                 res.into_body()
@@ -438,23 +648,263 @@ mod tests {
                     },
                 ))
         ); // check receiver not empty
+
+        // Request good post /challengeproof/<request_txid>
+        let request = Request::builder()
+            .method("POST")
+            .uri(format!("/challengeproof/{}", request_txid).as_str())
+            .body(Body::from(data.clone()))
+            .unwrap();
+        let _ = handle(request, registry.clone(), verify_pool.clone())
+            .map(|res| {
+                assert_eq!(res.status(), StatusCode::OK);
+                res.into_body()
+                    .concat2()
+                    .map(|chunk| {
+                        assert_eq!("", String::from_utf8_lossy(&chunk));
+                    })
+                    .wait()
+            })
+            .wait();
+        assert!(resp_rx.try_recv().is_ok()); // check receiver not empty
+
+        // Request post /challengeproof/<request_txid> for a request that is
+        // not being challenged
+        let request = Request::builder()
+            .method("POST")
+            .uri(format!("/challengeproof/{}", gen_dummy_hash(9)).as_str())
+            .body(Body::from(data.clone()))
+            .unwrap();
+        let _ = handle(request, registry.clone(), verify_pool.clone())
+            .map(|res| {
+                assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+                res.into_body()
+                    .concat2()
+                    .map(|chunk| {
+                        assert!(String::from_utf8_lossy(&chunk).contains("bad-request"));
+                    })
+                    .wait()
+            })
+            .wait();
+        assert!(resp_rx.try_recv() == Err(TryRecvError::Empty)); // check receiver empty
+
+        // Request post /challengeproof/<invalid txid>
+        let request = Request::builder()
+            .method("POST")
+            .uri("/challengeproof/dummy")
+            .body(Body::from(data))
+            .unwrap();
+        let _ = handle(request, registry.clone(), verify_pool.clone())
+            .map(|res| {
+                assert_eq!(res.status(), StatusCode::NOT_FOUND);
+                res.into_body()
+                    .concat2()
+                    .map(|chunk| {
+                        assert_eq!("Invalid request /challengeproof/dummy", String::from_utf8_lossy(&chunk));
+                    })
+                    .wait()
+            })
+            .wait();
+        assert!(resp_rx.try_recv() == Err(TryRecvError::Empty)); // check receiver empty
+    }
+
+    #[test]
+    fn proof_vectors_test() {
+        setup_logger();
+        let (resp_tx, resp_rx): (Sender<ChallengeResponse>, Receiver<ChallengeResponse>) = channel();
+        let verify_pool = VerifyPool::new(1, 16, 16, resp_tx, ListenerContext::disabled());
+
+        // challenge of the vectors request for the vectors bid
+        let chl_hash = sha256d::Hash::from_hex(VECTOR_CHALLENGE_HASH).unwrap();
+        let bid = Bid {
+            txid: sha256d::Hash::from_hex(VECTOR_BID_TXID).unwrap(),
+            pubkey: vector_bid_pubkey().unwrap(),
+            payment: None,
+        };
+        let mut state = gen_challenge_state_with_challenge(&gen_dummy_hash(1), &chl_hash);
+        state.request.txid = sha256d::Hash::from_hex(VECTOR_REQUEST_TXID).unwrap();
+        state.bids = Arc::new(vec![bid.clone()].into_iter().collect());
+        let challenge_state = Arc::new(RwLock::new(Some(state)));
+        let registry = Arc::new(ChallengeRegistry::new());
+        let _registration = registry.register(
+            sha256d::Hash::from_hex(VECTOR_REQUEST_TXID).unwrap(),
+            challenge_state.clone(),
+        );
+
+        // vector proofs posted to the listener are accepted only if valid
+        for vector in proof_vectors().unwrap() {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/challengeproof")
+                .body(Body::from(vector.proof.to_string()))
+                .unwrap();
+            let _ = handle(request, registry.clone(), verify_pool.clone())
+                .map(|res| {
+                    if vector.valid {
+                        assert_eq!(res.status(), StatusCode::OK, "{}", vector.description);
+                    } else {
+                        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", vector.description);
+                        res.into_body()
+                            .concat2()
+                            .map(|chunk| {
+                                assert!(String::from_utf8_lossy(&chunk).contains("bad-sig"));
+                            })
+                            .wait()
+                            .unwrap();
+                    }
+                })
+                .wait();
+            if vector.valid {
+                assert!(resp_rx.try_recv() == Ok(ChallengeResponse(chl_hash, bid.clone())));
+            } else {
+                assert!(resp_rx.try_recv() == Err(TryRecvError::Empty));
+            }
+        }
+    }
+
+    #[test]
+    fn verify_pool_test() {
+        setup_logger();
+        let (resp_tx, resp_rx): (Sender<ChallengeResponse>, Receiver<ChallengeResponse>) = channel();
+
+        let chl_hash = gen_dummy_hash(5);
+        let _challenge_state = gen_challenge_state_with_challenge(&gen_dummy_hash(2), &chl_hash);
+        let bid = _challenge_state.bids.iter().next().unwrap().clone();
+        let secret_key = SecretKey::from_slice(&[0xaa; 32]).unwrap();
+        let secp = Secp256k1::new();
+        let sig = secp.sign(&Message::from_slice(&serialize(&chl_hash)).unwrap(), &secret_key);
+
+        // pool configured without any threads still runs a thread verifying
+        // proofs
+        let verify_pool = VerifyPool::new(0, 1, 16, resp_tx.clone(), ListenerContext::disabled());
+        let proof = ChallengeProof {
+            hash: chl_hash,
+            sig: sig,
+            bid: bid.clone(),
+            request: None,
+            payload: None,
+        };
+        let _ = verify_pool
+            .verify(
+                proof,
+                _challenge_state.request.clone(),
+                _challenge_state.latest_challenge.clone(),
+            )
+            .map(|res| {
+                assert_eq!(res.status(), StatusCode::OK);
+            })
+            .wait();
+        assert!(resp_rx.try_recv() == Ok(ChallengeResponse(chl_hash, bid.clone())));
+
+        // pool with threads verifies proofs and forwards them to the challenger
+        let blacklist = Arc::new(Blacklist::new(1, 600));
+        let verify_pool = VerifyPool::new(
+            1,
+            1,
+            16,
+            resp_tx.clone(),
+            ListenerContext {
+                blacklist: blacklist.clone(),
+                ..ListenerContext::disabled()
+            },
+        );
+        let proof = ChallengeProof {
+            hash: chl_hash,
+            sig: sig,
+            bid: bid.clone(),
+            request: None,
+            payload: None,
+        };
+        let _ = verify_pool
+            .verify(
+                proof,
+                _challenge_state.request.clone(),
+                _challenge_state.latest_challenge.clone(),
+            )
+            .map(|res| {
+                assert_eq!(res.status(), StatusCode::OK);
+            })
+            .wait();
+        assert!(resp_rx.try_recv() == Ok(ChallengeResponse(chl_hash, bid.clone())));
+
+        // invalid sig is rejected by the pool
+        let sig = secp.sign(
+            &Message::from_slice(&serialize(&chl_hash)).unwrap(),
+            &SecretKey::from_slice(&[0xbb; 32]).unwrap(),
+        );
+        let proof = ChallengeProof {
+            hash: chl_hash,
+            sig: sig,
+            bid: bid.clone(),
+            request: None,
+            payload: None,
+        };
+        let _ = verify_pool
+            .verify(
+                proof,
+                _challenge_state.request.clone(),
+                _challenge_state.latest_challenge.clone(),
+            )
+            .map(|res| {
+                assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+                res.into_body()
+                    .concat2()
+                    .map(|chunk| {
+                        assert!(String::from_utf8_lossy(&chunk).contains("bad-sig"));
+                    })
+                    .wait()
+            })
+            .wait();
+        assert!(resp_rx.try_recv() == Err(TryRecvError::Empty)); // check receiver empty
+
+        // invalid sig strikes the bid, which is then blacklisted
+        let blacklisting = blacklist.check(&bid.txid).unwrap();
+        assert_eq!(_challenge_state.request.txid, blacklisting.request);
+        assert_eq!(1, blacklisting.strikes);
+
+        // late proofs are verified once no current proofs are queued or
+        // rejected if the late proofs queue is full
+        let sig = secp.sign(&Message::from_slice(&serialize(&chl_hash)).unwrap(), &secret_key);
+        let latest = Arc::new(LatestChallenge::new(Some(gen_dummy_hash(6))));
+        for (late_queue_size, status) in vec![(1, StatusCode::OK), (0, StatusCode::SERVICE_UNAVAILABLE)] {
+            let verify_pool = VerifyPool::new(1, 1, late_queue_size, resp_tx.clone(), ListenerContext::disabled());
+            let proof = ChallengeProof {
+                hash: chl_hash,
+                sig: sig,
+                bid: bid.clone(),
+                request: None,
+                payload: None,
+            };
+            let _ = verify_pool
+                .verify(proof, _challenge_state.request.clone(), latest.clone())
+                .map(|res| {
+                    assert_eq!(res.status(), status);
+                })
+                .wait();
+        }
+        assert!(resp_rx.try_recv() == Ok(ChallengeResponse(chl_hash, bid.clone())));
+        assert!(resp_rx.try_recv() == Err(TryRecvError::Empty)); // check receiver empty
     }
 
     #[test]
     fn handle_challengeproof_test() {
         setup_logger();
         let (resp_tx, resp_rx): (Sender<ChallengeResponse>, Receiver<ChallengeResponse>) = channel();
+        let verify_pool = VerifyPool::new(2, 16, 16, resp_tx, ListenerContext::disabled());
 
         let chl_hash = gen_dummy_hash(8);
         let _challenge_state = gen_challenge_state_with_challenge(&gen_dummy_hash(1), &chl_hash);
         let bid_txid = _challenge_state.bids.iter().next().unwrap().txid;
         let bid_pubkey = _challenge_state.bids.iter().next().unwrap().pubkey;
+        let request_txid = _challenge_state.request.txid;
         let challenge_state = Arc::new(RwLock::new(Some(_challenge_state)));
+        let registry = Arc::new(ChallengeRegistry::new());
+        let _registration = registry.register(request_txid, challenge_state.clone());
 
         // Request body data empty
         let data = "";
         let request = Request::new(Body::from(data));
-        let _ = handle_challengeproof(request, challenge_state.clone(), resp_tx.clone())
+        let _ = handle_challengeproof(request, None, registry.clone(), verify_pool.clone())
             .map(|res| {
                 assert_eq!(res.status(), StatusCode::BAD_REQUEST);
                 res.into_body()
@@ -473,7 +923,7 @@ mod tests {
             "txid": "1234567890000000000000000000000000000000000000000000000000000000",
         }"#;
         let request = Request::new(Body::from(data));
-        let _ = handle_challengeproof(request, challenge_state.clone(), resp_tx.clone())
+        let _ = handle_challengeproof(request, None, registry.clone(), verify_pool.clone())
             .map(|res| {
                 assert_eq!(res.status(), StatusCode::BAD_REQUEST);
                 res.into_body()
@@ -492,7 +942,7 @@ mod tests {
             "txid": "1234567890000000000000000000000000000000000000000000000000000000"
         }"#;
         let request = Request::new(Body::from(data));
-        let _ = handle_challengeproof(request, challenge_state.clone(), resp_tx.clone())
+        let _ = handle_challengeproof(request, None, registry.clone(), verify_pool.clone())
             .map(|res| {
                 assert_eq!(res.status(), StatusCode::BAD_REQUEST);
                 res.into_body()
@@ -514,7 +964,7 @@ mod tests {
             "sig": "304402201742daea5ec3b7306b9164be862fc1659cc830032180b8b17beffe02645860d602201039eba402d22e630308e6af05da8dd4f05b51b7d672ca5fc9e3b0a57776365c"
         }"#;
         let request = Request::new(Body::from(data));
-        let _ = handle_challengeproof(request, challenge_state.clone(), resp_tx.clone())
+        let _ = handle_challengeproof(request, None, registry.clone(), verify_pool.clone())
             .map(|res| {
                 assert_eq!(res.status(), StatusCode::BAD_REQUEST);
                 res.into_body()
@@ -528,7 +978,14 @@ mod tests {
         assert!(resp_rx.try_recv() == Err(TryRecvError::Empty)); // check receiver empty
 
         // No active challenge (hash is None) so request rejected
-        challenge_state.write().unwrap().as_mut().unwrap().latest_challenge = None;
+        let latest_challenge = challenge_state
+            .read()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .latest_challenge
+            .clone();
+        latest_challenge.clear();
         let data = r#"
         {
             "txid": "0000000000000000000000000000000000000000000000000000000000000000",
@@ -537,7 +994,7 @@ mod tests {
             "sig": "304402201742daea5ec3b7306b9164be862fc1659cc830032180b8b17beffe02645860d602201039eba402d22e630308e6af05da8dd4f05b51b7d672ca5fc9e3b0a57776365c"
         }"#;
         let request = Request::new(Body::from(data));
-        let _ = handle_challengeproof(request, challenge_state.clone(), resp_tx.clone())
+        let _ = handle_challengeproof(request, None, registry.clone(), verify_pool.clone())
             .map(|res| {
                 assert_eq!(res.status(), StatusCode::BAD_REQUEST);
                 res.into_body()
@@ -548,7 +1005,7 @@ mod tests {
                     .wait()
             })
             .wait();
-        challenge_state.write().unwrap().as_mut().unwrap().latest_challenge = Some(chl_hash);
+        latest_challenge.set(chl_hash, Instant::now());
         assert!(resp_rx.try_recv() == Err(TryRecvError::Empty)); // check receiver empty
 
         // Invalid bid on request body (txid does not exist)
@@ -560,7 +1017,7 @@ mod tests {
             "sig": "304402201742daea5ec3b7306b9164be862fc1659cc830032180b8b17beffe02645860d602201039eba402d22e630308e6af05da8dd4f05b51b7d672ca5fc9e3b0a57776365c"
         }"#;
         let request = Request::new(Body::from(data));
-        let _ = handle_challengeproof(request, challenge_state.clone(), resp_tx.clone())
+        let _ = handle_challengeproof(request, None, registry.clone(), verify_pool.clone())
             .map(|res| {
                 assert_eq!(res.status(), StatusCode::BAD_REQUEST);
                 res.into_body()
@@ -585,7 +1042,7 @@ mod tests {
             bid_txid
         );
         let request = Request::new(Body::from(data));
-        let _ = handle_challengeproof(request, challenge_state.clone(), resp_tx.clone())
+        let _ = handle_challengeproof(request, None, registry.clone(), verify_pool.clone())
             .map(|res| {
                 assert_eq!(res.status(), StatusCode::BAD_REQUEST);
                 res.into_body()
@@ -610,7 +1067,7 @@ mod tests {
             bid_txid, bid_pubkey
         );
         let request = Request::new(Body::from(data));
-        let _ = handle_challengeproof(request, challenge_state.clone(), resp_tx.clone())
+        let _ = handle_challengeproof(request, None, registry.clone(), verify_pool.clone())
             .map(|res| {
                 assert_eq!(res.status(), StatusCode::BAD_REQUEST);
                 res.into_body()
@@ -635,7 +1092,7 @@ mod tests {
             bid_txid, bid_pubkey, chl_hash
         );
         let request = Request::new(Body::from(data));
-        let _ = handle_challengeproof(request, challenge_state.clone(), resp_tx.clone())
+        let _ = handle_challengeproof(request, None, registry.clone(), verify_pool.clone())
             .map(|res| {
                 assert_eq!(res.status(), StatusCode::BAD_REQUEST);
                 res.into_body()
@@ -665,8 +1122,8 @@ mod tests {
             chl_hash,
             sig.serialize_der().to_hex()
         );
-        let request = Request::new(Body::from(data));
-        let _ = handle_challengeproof(request, challenge_state.clone(), resp_tx.clone())
+        let request = Request::new(Body::from(data.clone()));
+        let _ = handle_challengeproof(request, None, registry.clone(), verify_pool.clone())
             .map(|res| {
                 assert_eq!(res.status(), StatusCode::OK);
                 res.into_body()
@@ -688,5 +1145,79 @@ mod tests {
                     },
                 ))
         ); // check receiver not empty
+
+        // Proof of a blacklisted bid is rejected without verification
+        let blacklist = Arc::new(Blacklist::new(1, 600));
+        let _ = blacklist.strike(&bid_txid, &challenge_state.read().unwrap().as_ref().unwrap().request);
+        let (resp_tx, resp_rx): (Sender<ChallengeResponse>, Receiver<ChallengeResponse>) = channel();
+        let verify_pool = VerifyPool::new(
+            1,
+            1,
+            16,
+            resp_tx,
+            ListenerContext {
+                blacklist: blacklist,
+                ..ListenerContext::disabled()
+            },
+        );
+        let request = Request::new(Body::from(data));
+        let _ = handle_challengeproof(request, None, registry.clone(), verify_pool.clone())
+            .map(|res| {
+                assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+                res.into_body()
+                    .concat2()
+                    .map(|chunk| {
+                        assert!(String::from_utf8_lossy(&chunk).contains("bid-blacklisted"));
+                    })
+                    .wait()
+            })
+            .wait();
+        assert!(resp_rx.try_recv() == Err(TryRecvError::Empty)); // check receiver empty
+
+        // Proof rejected by an acceptance policy without verification unless
+        // sent from an allowed address
+        let policies = ProofPolicies::from_config(&[ProofPolicyConfig {
+            policy: "allowed-ranges".to_owned(),
+            ranges: vec!["10.0.0.0/8".to_owned()],
+            max_delay: 0,
+        }])
+        .unwrap();
+        let (resp_tx, resp_rx): (Sender<ChallengeResponse>, Receiver<ChallengeResponse>) = channel();
+        let verify_pool = VerifyPool::new(
+            1,
+            1,
+            16,
+            resp_tx,
+            ListenerContext {
+                policies: Arc::new(policies),
+                ..ListenerContext::disabled()
+            },
+        );
+        for (remote_addr, status) in vec![
+            (None, StatusCode::BAD_REQUEST),
+            (Some("192.168.0.1:9000"), StatusCode::BAD_REQUEST),
+            (Some("10.0.0.1:9000"), StatusCode::OK),
+        ] {
+            let mut request = Request::new(Body::from(data));
+            if let Some(addr) = remote_addr {
+                let _ = request.extensions_mut().insert(addr.parse::<SocketAddr>().unwrap());
+            }
+            let _ = handle_challengeproof(request, None, registry.clone(), verify_pool.clone())
+                .map(|res| {
+                    assert_eq!(res.status(), status);
+                    res.into_body()
+                        .concat2()
+                        .map(|chunk| {
+                            assert_eq!(
+                                status == StatusCode::BAD_REQUEST,
+                                String::from_utf8_lossy(&chunk).contains("policy-rejected: allowed-ranges")
+                            );
+                        })
+                        .wait()
+                })
+                .wait();
+        }
+        assert!(resp_rx.try_recv().is_ok()); // check receiver not empty
+        assert!(resp_rx.try_recv() == Err(TryRecvError::Empty)); // only the allowed proof
     }
 }
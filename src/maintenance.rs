@@ -0,0 +1,325 @@
+//! Maintenance
+//!
+//! Maintenance windows during which the coordinator defers non-critical work,
+//! i.e. bid payments and response compaction, and optionally pauses issuing
+//! challenges, resuming them once the window ends. Windows start at the times
+//! matching a cron schedule and last for a fixed duration. The start and end
+//! of each window are recorded in the journal
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use futures::sync::oneshot;
+
+use crate::clock::{Clock, SystemClock};
+use crate::config::MaintenanceWindowConfig;
+use crate::error::{CError, Error, Result};
+use crate::journal::{Journal, JournalEvent};
+use crate::util::handler::Handle;
+use crate::util::scheduler::{CronSchedule, JobStatus, Schedule, Scheduler};
+
+/// Interval in seconds of checking for maintenance window starts and ends
+pub const MAINTENANCE_CHECK_INTERVAL: u64 = 10;
+
+/// Maintenance window starting at the times matching a cron schedule
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaintenanceWindow {
+    /// Window name
+    pub name: String,
+    /// Schedule of the window start times
+    pub schedule: CronSchedule,
+    /// Window duration
+    pub duration: Duration,
+    /// Flag to pause issuing challenges during the window
+    pub pause_challenges: bool,
+}
+
+impl MaintenanceWindow {
+    /// Create a maintenance window from its config, parsing the cron schedule
+    pub fn from_config(config: &MaintenanceWindowConfig) -> Result<MaintenanceWindow> {
+        if config.duration == 0 {
+            return Err(Error::from(CError::Generic(format!(
+                "maintenance window {} has no duration",
+                config.name
+            ))));
+        }
+        Ok(MaintenanceWindow {
+            name: config.name.clone(),
+            schedule: CronSchedule::parse(&config.schedule)?,
+            duration: Duration::from_secs(config.duration),
+            pause_challenges: config.pause_challenges,
+        })
+    }
+
+    /// Get the unix timestamp in seconds that the window started at if the
+    /// window is in progress at a unix timestamp
+    pub fn started_at(&self, timestamp: u64) -> Option<u64> {
+        let start = self
+            .schedule
+            .next_after(timestamp.saturating_sub(self.duration.as_secs()))?;
+        if start <= timestamp {
+            Some(start)
+        } else {
+            None
+        }
+    }
+}
+
+/// Maintenance windows shared by the coordinator components deferring work
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MaintenanceWindows {
+    /// Configured windows
+    windows: Vec<MaintenanceWindow>,
+}
+
+impl MaintenanceWindows {
+    /// Create the maintenance windows from their configs
+    pub fn from_config(configs: &[MaintenanceWindowConfig]) -> Result<MaintenanceWindows> {
+        Ok(MaintenanceWindows {
+            windows: configs
+                .iter()
+                .map(MaintenanceWindow::from_config)
+                .collect::<Result<Vec<MaintenanceWindow>>>()?,
+        })
+    }
+
+    /// Check whether no maintenance windows are configured
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// Get the windows in progress at a unix timestamp in seconds along with
+    /// the timestamp that each started at
+    pub fn active(&self, timestamp: u64) -> Vec<(&MaintenanceWindow, u64)> {
+        self.windows
+            .iter()
+            .filter_map(|window| window.started_at(timestamp).map(|start| (window, start)))
+            .collect()
+    }
+
+    /// Check whether non-critical work is deferred at the current time of a
+    /// clock as a maintenance window is in progress
+    pub fn defers_work(&self, clock: &dyn Clock) -> bool {
+        !self.active(clock.unix_time().as_secs()).is_empty()
+    }
+}
+
+/// Tracker of the maintenance windows in progress, recording the start and end
+/// of each window and pausing challenges while any window pausing challenges
+/// is in progress
+#[derive(Debug, Default)]
+pub struct MaintenanceTracker {
+    /// Names and start timestamps of the windows in progress
+    current: Vec<(String, u64)>,
+    /// Flag set if challenges were paused for a window, so that challenges
+    /// paused via the api are not resumed once the window ends
+    paused: bool,
+}
+
+impl MaintenanceTracker {
+    /// Create a tracker with no windows in progress
+    pub fn new() -> MaintenanceTracker {
+        MaintenanceTracker::default()
+    }
+
+    /// Update the windows in progress at a unix timestamp in seconds,
+    /// recording windows ending and starting since the previous update and
+    /// pausing or resuming challenges
+    pub fn update(
+        &mut self,
+        windows: &MaintenanceWindows,
+        timestamp: u64,
+        challenges_paused: &AtomicBool,
+        journal: &Journal,
+    ) {
+        let active = windows.active(timestamp);
+        for (name, start) in self.current.iter() {
+            if !active
+                .iter()
+                .any(|(window, window_start)| window.name == *name && window_start == start)
+            {
+                info!("maintenance window {} ended", name);
+                journal.record(JournalEvent::MaintenanceEnded {
+                    window: name.clone(),
+                    start: *start,
+                });
+            }
+        }
+        for (window, start) in active.iter() {
+            if !self
+                .current
+                .iter()
+                .any(|(name, current_start)| *name == window.name && current_start == start)
+            {
+                info!(
+                    "maintenance window {} started, deferring payments and compaction",
+                    window.name
+                );
+                journal.record(JournalEvent::MaintenanceStarted {
+                    window: window.name.clone(),
+                    start: *start,
+                    end: *start + window.duration.as_secs(),
+                    pause_challenges: window.pause_challenges,
+                });
+            }
+        }
+        self.current = active
+            .iter()
+            .map(|(window, start)| (window.name.clone(), *start))
+            .collect();
+
+        let pause = active.iter().any(|(window, _)| window.pause_challenges);
+        if pause && !self.paused {
+            if !challenges_paused.swap(true, Ordering::SeqCst) {
+                info!("challenges paused for maintenance");
+                self.paused = true;
+            }
+        } else if !pause && self.paused {
+            challenges_paused.store(false, Ordering::SeqCst);
+            info!("challenges resumed after maintenance");
+            self.paused = false;
+        }
+    }
+}
+
+/// Run the maintenance window tracker in a separate thread, if any maintenance
+/// windows are configured, pausing and resuming challenges via the shared
+/// challenges paused flag
+pub fn run_maintenance<'a>(
+    windows: Arc<MaintenanceWindows>,
+    challenges_paused: Arc<AtomicBool>,
+    journal: Arc<Journal>,
+) -> Option<Handle<'a>> {
+    if windows.is_empty() {
+        return None;
+    }
+    let (tx, mut rx) = oneshot::channel();
+    Some(Handle::new(
+        tx,
+        None,
+        thread::spawn(move || {
+            let clock = SystemClock;
+            let mut tracker = MaintenanceTracker::new();
+            let mut scheduler = Scheduler::new();
+            let _ = scheduler.add(
+                "maintenance windows",
+                Schedule::Interval(Duration::from_secs(MAINTENANCE_CHECK_INTERVAL)),
+                || {
+                    tracker.update(&windows, clock.unix_time().as_secs(), &challenges_paused, &journal);
+                    Ok(JobStatus::Continue)
+                },
+            );
+            if let Err(err) = scheduler.run(Some(&mut rx)) {
+                error! {"maintenance error: {}", err};
+            }
+        }),
+        "MAINTENANCE",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::fs;
+
+    /// Window starting daily at 03:00 UTC for an hour
+    fn gen_window_config(pause_challenges: bool) -> MaintenanceWindowConfig {
+        MaintenanceWindowConfig {
+            name: "daily".to_owned(),
+            schedule: "0 3 * * *".to_owned(),
+            duration: 3600,
+            pause_challenges,
+        }
+    }
+
+    #[test]
+    fn maintenance_windows_test() {
+        let windows = MaintenanceWindows::from_config(&[gen_window_config(false)]).unwrap();
+        assert!(!windows.is_empty());
+        assert!(MaintenanceWindows::from_config(&[]).unwrap().is_empty());
+
+        // second day at 03:00
+        let start = 86400 + 3 * 3600;
+        assert_eq!(0, windows.active(start - 1).len());
+        for timestamp in &[start, start + 1800, start + 3599] {
+            let active = windows.active(*timestamp);
+            assert_eq!(1, active.len());
+            assert_eq!("daily", active[0].0.name);
+            assert_eq!(start, active[0].1);
+        }
+        assert_eq!(0, windows.active(start + 3600).len());
+        assert_eq!(start + 86400, windows.active(start + 86400).pop().unwrap().1);
+
+        // invalid windows
+        let mut config = gen_window_config(false);
+        config.schedule = "0 25 * * *".to_owned();
+        assert!(MaintenanceWindows::from_config(&[config]).is_err());
+        let mut config = gen_window_config(false);
+        config.duration = 0;
+        assert!(MaintenanceWindows::from_config(&[config]).is_err());
+    }
+
+    #[test]
+    fn maintenance_tracker_test() {
+        let path = env::temp_dir().join("coordinator_maintenance_tracker_test.jsonl");
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+        let journal = Journal::open(path).unwrap();
+        let windows = MaintenanceWindows::from_config(&[gen_window_config(true)]).unwrap();
+        let paused = AtomicBool::new(false);
+        let mut tracker = MaintenanceTracker::new();
+        let start = 86400 + 3 * 3600;
+
+        // challenges paused for the window and resumed at its end
+        tracker.update(&windows, start - 10, &paused, &journal);
+        assert!(!paused.load(Ordering::SeqCst));
+        tracker.update(&windows, start, &paused, &journal);
+        assert!(paused.load(Ordering::SeqCst));
+        tracker.update(&windows, start + 10, &paused, &journal);
+        assert!(paused.load(Ordering::SeqCst));
+        tracker.update(&windows, start + 3600, &paused, &journal);
+        assert!(!paused.load(Ordering::SeqCst));
+
+        // challenges paused via the api stay paused after the window
+        paused.store(true, Ordering::SeqCst);
+        tracker.update(&windows, start + 86400, &paused, &journal);
+        tracker.update(&windows, start + 86400 + 3600, &paused, &journal);
+        assert!(paused.load(Ordering::SeqCst));
+
+        let events: Vec<JournalEvent> = Journal::read(path)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.event)
+            .collect();
+        assert_eq!(
+            vec![
+                JournalEvent::MaintenanceStarted {
+                    window: "daily".to_owned(),
+                    start,
+                    end: start + 3600,
+                    pause_challenges: true,
+                },
+                JournalEvent::MaintenanceEnded {
+                    window: "daily".to_owned(),
+                    start,
+                },
+                JournalEvent::MaintenanceStarted {
+                    window: "daily".to_owned(),
+                    start: start + 86400,
+                    end: start + 86400 + 3600,
+                    pause_challenges: true,
+                },
+                JournalEvent::MaintenanceEnded {
+                    window: "daily".to_owned(),
+                    start: start + 86400,
+                },
+            ],
+            events
+        );
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,211 @@
+//! Merkle
+//!
+//! Merkle tree of the per bid response counts of a request, letting each
+//! guardnode verify that its response count is included in the root published
+//! by the coordinator. Leaves commit to a bid txid and its response count and
+//! are ordered by bid txid, and inner nodes are domain separated from leaves. A
+//! node without a sibling is carried up to the next level unchanged, so that no
+//! leaf can be duplicated to produce a different tree with the same root
+
+use bitcoin::hashes::{sha256d, Hash, HashEngine};
+use serde::{Deserialize, Serialize};
+
+use crate::interfaces::response::Response;
+
+/// Prefix of the preimage of leaf hashes
+const LEAF_PREFIX: u8 = 0;
+
+/// Prefix of the preimage of inner node hashes
+const NODE_PREFIX: u8 = 1;
+
+/// Hash of the leaf of a bid txid and its response count
+pub fn leaf_hash(bid: &sha256d::Hash, count: u32) -> sha256d::Hash {
+    let mut engine = sha256d::Hash::engine();
+    engine.input(&[LEAF_PREFIX]);
+    engine.input(&bid[..]);
+    engine.input(&count.to_le_bytes());
+    sha256d::Hash::from_engine(engine)
+}
+
+/// Hash of an inner node from the hashes of its children
+fn node_hash(left: &sha256d::Hash, right: &sha256d::Hash) -> sha256d::Hash {
+    let mut engine = sha256d::Hash::engine();
+    engine.input(&[NODE_PREFIX]);
+    engine.input(&left[..]);
+    engine.input(&right[..]);
+    sha256d::Hash::from_engine(engine)
+}
+
+/// Sibling hash on the path from a leaf to the root
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MerkleStep {
+    /// Sibling hash
+    pub hash: sha256d::Hash,
+    /// Whether the sibling is the left child of the parent
+    pub left: bool,
+}
+
+/// Proof of inclusion of the response count of a bid in a response merkle root
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InclusionProof {
+    /// Bid txid
+    pub bid: sha256d::Hash,
+    /// Number of challenges the bid responded to
+    pub count: u32,
+    /// Index of the leaf of the bid
+    pub index: u32,
+    /// Sibling hashes from the leaf up to the root
+    pub path: Vec<MerkleStep>,
+}
+
+impl InclusionProof {
+    /// Compute the root committed to by the proof
+    pub fn root(&self) -> sha256d::Hash {
+        self.path.iter().fold(leaf_hash(&self.bid, self.count), |hash, step| {
+            if step.left {
+                node_hash(&step.hash, &hash)
+            } else {
+                node_hash(&hash, &step.hash)
+            }
+        })
+    }
+
+    /// Verify the proof against a response merkle root
+    pub fn verify(&self, root: &sha256d::Hash) -> bool {
+        self.root() == *root
+    }
+}
+
+/// Merkle tree of the per bid response counts of a response
+#[derive(Debug, Clone)]
+pub struct ResponseMerkleTree {
+    /// Bid txids and response counts of the leaves, ordered by bid txid
+    leaves: Vec<(sha256d::Hash, u32)>,
+    /// Node hashes of each level, from the leaves up to the root
+    levels: Vec<Vec<sha256d::Hash>>,
+}
+
+impl ResponseMerkleTree {
+    /// Build the merkle tree of the bid response counts of a response
+    pub fn new(response: &Response) -> ResponseMerkleTree {
+        let mut leaves: Vec<(sha256d::Hash, u32)> = response
+            .bid_responses
+            .iter()
+            .map(|(bid, count)| (*bid, *count))
+            .collect();
+        leaves.sort_by(|a, b| a.0[..].cmp(&b.0[..]));
+        let mut levels = vec![leaves
+            .iter()
+            .map(|(bid, count)| leaf_hash(bid, *count))
+            .collect::<Vec<sha256d::Hash>>()];
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| {
+                    if pair.len() == 2 {
+                        node_hash(&pair[0], &pair[1])
+                    } else {
+                        pair[0]
+                    }
+                })
+                .collect();
+            levels.push(next);
+        }
+        ResponseMerkleTree { leaves, levels }
+    }
+
+    /// Get the merkle root, or None if the response has no bids
+    pub fn root(&self) -> Option<sha256d::Hash> {
+        self.levels.last().and_then(|level| level.first().cloned())
+    }
+
+    /// Get the number of leaves, i.e. bids, of the tree
+    pub fn num_leaves(&self) -> u32 {
+        self.leaves.len() as u32
+    }
+
+    /// Get the inclusion proof of the response count of a bid, or None if the
+    /// bid is not in the tree
+    pub fn proof(&self, bid: &sha256d::Hash) -> Option<InclusionProof> {
+        let index = self.leaves.iter().position(|(leaf_bid, _)| leaf_bid == bid)?;
+        let mut path = vec![];
+        let mut node = index;
+        for level in self.levels[..self.levels.len() - 1].iter() {
+            let sibling = node ^ 1;
+            if sibling < level.len() {
+                path.push(MerkleStep {
+                    hash: level[sibling],
+                    left: sibling < node,
+                });
+            }
+            node /= 2;
+        }
+        Some(InclusionProof {
+            bid: *bid,
+            count: self.leaves[index].1,
+            index: index as u32,
+            path,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::util::testing::gen_dummy_hash;
+
+    #[test]
+    fn response_merkle_tree_test() {
+        let mut response = Response::new();
+        assert_eq!(None, ResponseMerkleTree::new(&response).root());
+
+        // single leaf is the root
+        let _ = response.bid_responses.insert(gen_dummy_hash(1), 3);
+        let tree = ResponseMerkleTree::new(&response);
+        assert_eq!(Some(leaf_hash(&gen_dummy_hash(1), 3)), tree.root());
+        let proof = tree.proof(&gen_dummy_hash(1)).unwrap();
+        assert!(proof.path.is_empty());
+        assert!(proof.verify(&tree.root().unwrap()));
+
+        // odd number of leaves, with the last leaf carried up
+        let _ = response.bid_responses.insert(gen_dummy_hash(2), 5);
+        let _ = response.bid_responses.insert(gen_dummy_hash(3), 0);
+        let tree = ResponseMerkleTree::new(&response);
+        assert_eq!(3, tree.num_leaves());
+        let root = tree.root().unwrap();
+        assert_eq!(
+            node_hash(
+                &node_hash(&leaf_hash(&gen_dummy_hash(1), 3), &leaf_hash(&gen_dummy_hash(2), 5)),
+                &leaf_hash(&gen_dummy_hash(3), 0)
+            ),
+            root
+        );
+        for (bid, count) in response.bid_responses.iter() {
+            let proof = tree.proof(bid).unwrap();
+            assert_eq!(*count, proof.count);
+            assert!(proof.verify(&root));
+        }
+        let proof = tree.proof(&gen_dummy_hash(3)).unwrap();
+        assert_eq!(2, proof.index);
+        assert_eq!(1, proof.path.len());
+        assert!(proof.path[0].left);
+
+        // tampered counts are not included
+        let mut proof = tree.proof(&gen_dummy_hash(2)).unwrap();
+        proof.count = 6;
+        assert!(!proof.verify(&root));
+        assert_eq!(None, tree.proof(&gen_dummy_hash(4)));
+
+        // root does not depend on the order of insertion
+        let mut reordered = Response::new();
+        for i in (1..4).rev() {
+            let _ = reordered
+                .bid_responses
+                .insert(gen_dummy_hash(i), response.bid_responses[&gen_dummy_hash(i)]);
+        }
+        assert_eq!(Some(root), ResponseMerkleTree::new(&reordered).root());
+    }
+}
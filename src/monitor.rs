@@ -0,0 +1,420 @@
+//! Monitor
+//!
+//! Wallet balance monitor that periodically checks the client chain wallet
+//! balances of the challenge and payment assets against the projected
+//! consumption of active requests and raises alerts when running low
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use bitcoin::hashes::{hex::FromHex, sha256d};
+use bitcoin::Amount;
+use futures::sync::oneshot;
+use ocean_rpc::RpcApi;
+use serde::Serialize;
+
+use crate::challenger::ChallengeState;
+use crate::config::{ClientChainConfig, MonitorConfig, ServiceConfig};
+use crate::error::Result;
+use crate::interfaces::service::{RpcService, Service};
+use crate::interfaces::storage::Storage;
+use crate::util::scheduler::{JobStatus, Schedule, Scheduler};
+use crate::util::{handler::Handle, http, ocean::OceanClient};
+
+/// Low balance alert raised by the monitor
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum BalanceAlert {
+    /// Challenge asset balance below threshold after projected consumption
+    LowChallengeAsset,
+    /// Payment asset balance below threshold after projected payments
+    LowPaymentAsset,
+}
+
+impl fmt::Display for BalanceAlert {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BalanceAlert::LowChallengeAsset => write!(f, "low challenge asset balance"),
+            BalanceAlert::LowPaymentAsset => write!(f, "low payment asset balance"),
+        }
+    }
+}
+
+/// Wallet balance status as of the latest monitor check
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BalanceStatus {
+    /// Challenge asset wallet balance
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub challenge_balance: Amount,
+    /// Projected challenge asset consumption of the active request
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub challenge_projected: Amount,
+    /// Payment asset wallet balance
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub payment_balance: Amount,
+    /// Projected payments of requests not yet paid
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub payment_projected: Amount,
+    /// Alerts raised for balances below threshold
+    pub alerts: Vec<BalanceAlert>,
+}
+
+impl BalanceStatus {
+    /// Create a balance status raising an alert for each balance that does not
+    /// cover the projected consumption plus the configured threshold
+    pub fn new(
+        challenge_balance: Amount,
+        challenge_projected: Amount,
+        payment_balance: Amount,
+        payment_projected: Amount,
+        config: &MonitorConfig,
+    ) -> BalanceStatus {
+        let mut alerts = vec![];
+        if challenge_balance < challenge_projected + btc_amount(config.challenge_asset_threshold) {
+            alerts.push(BalanceAlert::LowChallengeAsset);
+        }
+        if payment_balance < payment_projected + btc_amount(config.payment_asset_threshold) {
+            alerts.push(BalanceAlert::LowPaymentAsset);
+        }
+        BalanceStatus {
+            challenge_balance,
+            challenge_projected,
+            payment_balance,
+            payment_projected,
+            alerts,
+        }
+    }
+}
+
+/// Convert a config btc value to an Amount, treating invalid values as zero
+fn btc_amount(btc: f64) -> Amount {
+    Amount::from_btc(btc).unwrap_or(Amount::ZERO)
+}
+
+/// Projected challenge asset consumption of the remaining challenges of the
+/// active request, given the service chain height and the challenge frequency
+pub fn projected_challenge_consumption(
+    challenge: &Option<ChallengeState>,
+    service_height: u64,
+    challenge_frequency: u64,
+    challenge_cost: Amount,
+) -> Amount {
+    match challenge {
+        Some(challenge) if challenge.request.end_blockheight as u64 >= service_height => {
+            let remaining =
+                (challenge.request.end_blockheight as u64 - service_height) / challenge_frequency.max(1) + 1;
+            challenge_cost * remaining
+        }
+        _ => Amount::ZERO,
+    }
+}
+
+/// Projected payment asset consumption of the bid payments of requests for the
+/// genesis hash that have not been paid yet. Only payments already calculated
+/// for finished requests are included
+pub fn projected_payment_consumption(storage: &dyn Storage, genesis_hash: sha256d::Hash) -> Result<Amount> {
+    let mut projected = Amount::ZERO;
+    for request in storage.get_requests(Some(false), Some(genesis_hash), None, None)? {
+        for bid in storage.get_bids(request.txid)? {
+            if let Some(payment) = bid.payment {
+                if !payment.is_paid() {
+                    projected += payment.amount;
+                }
+            }
+        }
+    }
+    Ok(projected)
+}
+
+/// Monitor struct holding data and logic required to check wallet balances
+/// against projected consumption and raise low balance alerts
+pub struct Monitor {
+    /// Ocean rpc connectivity to client chain
+    client: OceanClient,
+    /// Service chain connectivity for the current service chain height
+    service: RpcService,
+    /// Thread safe storage instance
+    storage: Arc<dyn Storage + Send + Sync>,
+    /// Shared challenge state of the active request
+    challenge: Arc<RwLock<Option<ChallengeState>>>,
+    /// Shared latest balance status
+    status: Arc<RwLock<Option<BalanceStatus>>>,
+    /// Monitor config
+    config: MonitorConfig,
+    /// Challenge asset label
+    asset: String,
+    /// Payment asset label or asset id or ANY asset
+    payment_asset: String,
+    /// Genesis hash of the clientchain
+    genesis_hash: sha256d::Hash,
+    /// Challenge frequency in number of service chain blocks
+    challenge_frequency: u64,
+}
+
+impl Monitor {
+    /// Return new Monitor instance for the clientchain wallet
+    pub fn new(
+        config: MonitorConfig,
+        clientchain_config: &ClientChainConfig,
+        service_config: &ServiceConfig,
+        challenge_frequency: u64,
+        storage: Arc<dyn Storage + Send + Sync>,
+        challenge: Arc<RwLock<Option<ChallengeState>>>,
+        status: Arc<RwLock<Option<BalanceStatus>>>,
+    ) -> Result<Monitor> {
+        let client = OceanClient::new(
+            clientchain_config.host.clone(),
+            Some(clientchain_config.user.clone()),
+            Some(clientchain_config.pass.clone()),
+        )?;
+        Ok(Monitor {
+            client,
+            service: RpcService::new(service_config)?,
+            storage,
+            challenge,
+            status,
+            config,
+            asset: clientchain_config.asset.clone(),
+            payment_asset: clientchain_config.payment_asset.clone(),
+            genesis_hash: sha256d::Hash::from_hex(&clientchain_config.genesis_hash)?,
+            challenge_frequency,
+        })
+    }
+
+    /// Get the wallet balances of the challenge and payment assets. For the
+    /// ANY payment asset the balances of all assets are summed
+    fn get_wallet_balances(&self) -> Result<(Amount, Amount)> {
+        let balances = self.client.call::<HashMap<String, f64>>("getbalance", &[])?;
+        let challenge_balance = btc_amount(*balances.get(&self.asset).unwrap_or(&0.0));
+        let payment_balance = if self.payment_asset == "ANY" {
+            btc_amount(balances.values().sum())
+        } else {
+            btc_amount(*balances.get(&self.payment_asset).unwrap_or(&0.0))
+        };
+        Ok((challenge_balance, payment_balance))
+    }
+
+    /// Check wallet balances against projected consumption, update the shared
+    /// balance status and raise any alerts. Alerts are logged on every check
+    /// while the webhook is only called when the alerts raised change
+    fn check_balances(&self) -> Result<()> {
+        let (challenge_balance, payment_balance) = self.get_wallet_balances()?;
+        let height = self.service.get_blockheight()?;
+        let challenge_projected = projected_challenge_consumption(
+            &*self.challenge.read().unwrap(),
+            height,
+            self.challenge_frequency,
+            btc_amount(self.config.challenge_cost),
+        );
+        let payment_projected = projected_payment_consumption(self.storage.as_ref(), self.genesis_hash)?;
+        let status = BalanceStatus::new(
+            challenge_balance,
+            challenge_projected,
+            payment_balance,
+            payment_projected,
+            &self.config,
+        );
+
+        for alert in status.alerts.iter() {
+            warn!("Balance alert: {}", alert);
+        }
+        let alerts_changed = match &*self.status.read().unwrap() {
+            Some(prev_status) => prev_status.alerts != status.alerts,
+            None => true,
+        };
+        if let Some(webhook) = &self.config.webhook {
+            if alerts_changed && status.alerts.len() > 0 {
+                if let Err(e) = http::post(
+                    webhook,
+                    "application/json",
+                    None,
+                    &serde_json::to_string(&status).unwrap(),
+                ) {
+                    warn!("balance alert webhook failed: {}", e);
+                }
+            }
+        }
+        *self.status.write().unwrap() = Some(status);
+        Ok(())
+    }
+
+    /// Main monitor method checking balances every monitor interval until a
+    /// shutdown signal is received. Check failures are logged and retried on
+    /// the next interval
+    fn do_monitor(&self, mut kill_recv: oneshot::Receiver<()>) -> Result<()> {
+        let mut scheduler = Scheduler::new();
+        let _ = scheduler.add(
+            "balance check",
+            Schedule::Interval(Duration::from_secs(self.config.interval)),
+            || {
+                if let Err(e) = self.check_balances() {
+                    warn!("balance check failed: {}", e);
+                }
+                Ok(JobStatus::Continue)
+            },
+        );
+        scheduler.run(Some(&mut kill_recv))
+    }
+}
+
+/// Run balance monitor in a separate thread, if the monitor interval has been
+/// set, updating the shared balance status on every check
+pub fn run_monitor<'a>(
+    config: MonitorConfig,
+    clientchain_config: &ClientChainConfig,
+    service_config: &ServiceConfig,
+    challenge_frequency: u64,
+    storage: Arc<dyn Storage + Send + Sync>,
+    challenge: Arc<RwLock<Option<ChallengeState>>>,
+    status: Arc<RwLock<Option<BalanceStatus>>>,
+) -> Result<Option<Handle<'a>>> {
+    if config.interval == 0 {
+        return Ok(None);
+    }
+    let monitor = Monitor::new(
+        config,
+        clientchain_config,
+        service_config,
+        challenge_frequency,
+        storage,
+        challenge,
+        status,
+    )?;
+    let (tx, rx) = oneshot::channel();
+    Ok(Some(Handle::new(
+        tx,
+        None,
+        thread::spawn(move || {
+            if let Err(err) = monitor.do_monitor(rx) {
+                error! {"monitor error: {}", err};
+            }
+        }),
+        "MONITOR",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use bitcoin::PublicKey;
+    use ocean::{Address, AddressParams};
+
+    use crate::interfaces::bid::{Bid, BidPayment, BidPaymentTx, BidSet, PayoutAddressType};
+    use crate::interfaces::mocks::storage::MockStorage;
+    use crate::util::testing::{gen_challenge_state, gen_dummy_hash, setup_logger};
+
+    #[test]
+    fn balance_status_test() {
+        setup_logger();
+        let mut config = MonitorConfig::default();
+        config.challenge_asset_threshold = 1.0;
+        config.payment_asset_threshold = 2.0;
+        let btc = |value: f64| Amount::from_btc(value).unwrap();
+
+        let status = BalanceStatus::new(btc(2.0), btc(1.0), btc(5.0), btc(3.0), &config);
+        assert_eq!(0, status.alerts.len());
+
+        let status = BalanceStatus::new(btc(1.5), btc(1.0), btc(5.0), btc(3.0), &config);
+        assert_eq!(vec![BalanceAlert::LowChallengeAsset], status.alerts);
+
+        let status = BalanceStatus::new(btc(1.5), btc(1.0), btc(4.0), btc(3.0), &config);
+        assert_eq!(
+            vec![BalanceAlert::LowChallengeAsset, BalanceAlert::LowPaymentAsset],
+            status.alerts
+        );
+    }
+
+    #[test]
+    fn projected_challenge_consumption_test() {
+        setup_logger();
+        let cost = Amount::from_sat(10);
+
+        // no active request
+        assert_eq!(Amount::ZERO, projected_challenge_consumption(&None, 2, 1, cost));
+
+        // active request from height 2 to 5
+        let challenge = Some(gen_challenge_state(&gen_dummy_hash(1)));
+        assert_eq!(
+            Amount::from_sat(40),
+            projected_challenge_consumption(&challenge, 2, 1, cost)
+        );
+        assert_eq!(
+            Amount::from_sat(20),
+            projected_challenge_consumption(&challenge, 2, 2, cost)
+        );
+        assert_eq!(
+            Amount::from_sat(10),
+            projected_challenge_consumption(&challenge, 5, 1, cost)
+        );
+        assert_eq!(Amount::ZERO, projected_challenge_consumption(&challenge, 6, 1, cost));
+    }
+
+    #[test]
+    fn projected_payment_consumption_test() {
+        setup_logger();
+        let storage = MockStorage::new();
+        let genesis_hash = gen_dummy_hash(0);
+        assert_eq!(
+            Amount::ZERO,
+            projected_payment_consumption(&storage, genesis_hash).unwrap()
+        );
+
+        let pubkey = PublicKey::from_str("026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3").unwrap();
+        let payment = BidPayment {
+            txs: vec![],
+            address: Address::p2pkh(&pubkey, None, &AddressParams::ELEMENTS),
+            address_type: PayoutAddressType::P2pkh,
+            amount: Amount::from_sat(100),
+            intent: None,
+            basis: None,
+        };
+        let mut bids = BidSet::new();
+        let _ = bids.insert(Bid {
+            txid: gen_dummy_hash(5),
+            pubkey: pubkey.key,
+            payment: Some(payment.clone()),
+        });
+        let mut paid_payment = payment.clone();
+        paid_payment.txs = vec![BidPaymentTx {
+            txid: gen_dummy_hash(7),
+            amount: Amount::from_sat(100),
+            confirmations: 0,
+        }];
+        let _ = bids.insert(Bid {
+            txid: gen_dummy_hash(6),
+            pubkey: pubkey.key,
+            payment: Some(paid_payment),
+        });
+        let _ = bids.insert(Bid {
+            txid: gen_dummy_hash(8),
+            pubkey: pubkey.key,
+            payment: None,
+        });
+
+        // unpaid request with paid, unpaid and uncalculated bid payments
+        let state = gen_challenge_state(&gen_dummy_hash(1));
+        storage.save_challenge_request_state(&state.request, &bids).unwrap();
+        assert_eq!(
+            Amount::from_sat(100),
+            projected_payment_consumption(&storage, genesis_hash).unwrap()
+        );
+        assert_eq!(
+            Amount::ZERO,
+            projected_payment_consumption(&storage, gen_dummy_hash(9)).unwrap()
+        );
+
+        // paid request
+        let mut state = gen_challenge_state(&gen_dummy_hash(2));
+        state.request.is_payment_complete = true;
+        storage.save_challenge_request_state(&state.request, &bids).unwrap();
+        assert_eq!(
+            Amount::from_sat(100),
+            projected_payment_consumption(&storage, genesis_hash).unwrap()
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Payload
+//!
+//! Challenge payload schemes defining data that guardnodes commit to in their
+//! challenge proofs along with the challenge hash, such as a client chain block
+//! hash proving that the guardnode holds the client chain data. The scheme is
+//! set in the clientchain config, with tenant overrides, and the payload of a
+//! request is fixed once the request is challenged
+
+use std::str::FromStr;
+
+use bitcoin::hashes::sha256d;
+use serde::Serialize;
+
+use crate::error::{CError, Error, Result};
+use crate::interfaces::clientchain::ClientChain;
+use crate::interfaces::request::Request;
+
+/// Scheme of the payload that challenge proofs commit to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PayloadScheme {
+    /// Proofs commit to the challenge only
+    None,
+    /// Proofs also commit to the hash of the client chain block at the client
+    /// chain start height of the request
+    ClientchainBlockhash,
+}
+
+impl PayloadScheme {
+    /// Get the scheme name as used in config
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayloadScheme::None => "none",
+            PayloadScheme::ClientchainBlockhash => "clientchain-blockhash",
+        }
+    }
+
+    /// Get the payload of a request under the scheme from the client chain,
+    /// or None if proofs commit to the challenge only. The request client
+    /// chain start height is expected to be set
+    pub fn payload<K: ClientChain>(&self, request: &Request, clientchain: &K) -> Result<Option<ChallengePayload>> {
+        match self {
+            PayloadScheme::None => Ok(None),
+            PayloadScheme::ClientchainBlockhash => {
+                let height = request.start_blockheight_clientchain;
+                Ok(Some(ChallengePayload {
+                    scheme: *self,
+                    height,
+                    commitment: clientchain.get_block_hash(height)?,
+                }))
+            }
+        }
+    }
+}
+
+impl FromStr for PayloadScheme {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<PayloadScheme> {
+        match s {
+            "none" => Ok(PayloadScheme::None),
+            "clientchain-blockhash" => Ok(PayloadScheme::ClientchainBlockhash),
+            _ => Err(Error::from(CError::Generic(format!(
+                "unknown challenge payload scheme: {}",
+                s
+            )))),
+        }
+    }
+}
+
+/// Payload of the challenges of a request that challenge proofs commit to
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChallengePayload {
+    /// Payload scheme
+    pub scheme: PayloadScheme,
+    /// Client chain height that the payload was taken at
+    pub height: u32,
+    /// Commitment appended to the preimage of the message signed by proofs
+    pub commitment: sha256d::Hash,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::interfaces::mocks::clientchain::MockClientChain;
+    use crate::util::testing::{gen_challenge_state, gen_dummy_hash};
+
+    #[test]
+    fn payload_test() {
+        let mut clientchain = MockClientChain::new();
+        let mut request = gen_challenge_state(&gen_dummy_hash(1)).request;
+        request.start_blockheight_clientchain = 7;
+
+        assert_eq!(None, PayloadScheme::None.payload(&request, &clientchain).unwrap());
+        let payload = PayloadScheme::ClientchainBlockhash
+            .payload(&request, &clientchain)
+            .unwrap()
+            .unwrap();
+        assert_eq!(PayloadScheme::ClientchainBlockhash, payload.scheme);
+        assert_eq!(7, payload.height);
+        assert_eq!(clientchain.get_block_hash(7).unwrap(), payload.commitment);
+        assert!(clientchain.get_block_hash(8).unwrap() != payload.commitment);
+
+        clientchain.return_err = true;
+        assert!(PayloadScheme::ClientchainBlockhash
+            .payload(&request, &clientchain)
+            .is_err());
+        assert_eq!(None, PayloadScheme::None.payload(&request, &clientchain).unwrap());
+    }
+
+    #[test]
+    fn payload_scheme_from_str_test() {
+        for scheme in vec![PayloadScheme::None, PayloadScheme::ClientchainBlockhash] {
+            assert_eq!(scheme, PayloadScheme::from_str(scheme.as_str()).unwrap());
+        }
+        assert!(PayloadScheme::from_str("blockhash").is_err());
+    }
+}
@@ -2,57 +2,623 @@
 //!
 //! TODO: Add description
 
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{Receiver, RecvTimeoutError};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
-use bitcoin::{hashes::sha256d, Amount, PublicKey};
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::{hashes::sha256d, Amount};
 use futures::sync::oneshot;
 use ocean::{Address, AddressParams};
 use ocean_rpc::{json::SendAnyToAddressResult, RpcApi};
+use serde_json::Value;
 
-use crate::config::ClientChainConfig;
-use crate::error::{CError, Error, Result};
+use crate::clock::{Clock, SystemClock};
+use crate::config::{ClientChainConfig, LatencyBucketConfig};
+use crate::error::{skip_transient, CError, Error, Result};
 use crate::interfaces::{
-    bid::{Bid, BidPayment},
-    request::Request,
-    response::Response,
+    bid::{
+        Bid, BidPayment, BidPaymentBasis, BidPaymentTx, LatencyWeighting, PaymentLiability, PayoutAddressType,
+        BID_PAYMENT_FORMULA_VERSION, BID_PAYMENT_LATENCY_FORMULA_VERSION,
+    },
+    request::{block_fees_totals, total_fees, AssetFees, BlockFees, FeeFilter, FeePool, PaymentState, Request},
+    response::{ChallengeLatency, Response, ResponseSnapshot},
     storage::Storage,
 };
-use crate::util::{handler::Handle, ocean::OceanClient};
+use crate::journal::{Journal, JournalEvent};
+use crate::maintenance::MaintenanceWindows;
+use crate::util::scheduler::{JobStatus, Schedule, Scheduler};
+use crate::util::{addr_params::AddrParamsRegistry, handler::Handle, logger::RequestLogContext, ocean::OceanClient};
 
-/// Get addr params from chain name
-pub fn get_chain_addr_params(chain: &String) -> &'static AddressParams {
-    match chain.to_lowercase().as_ref() {
-        "ocean_main" => return &AddressParams::OCEAN,
-        "gold_main" => return &AddressParams::GOLD,
-        _ => &AddressParams::ELEMENTS,
+/// Get the fee asset that a coinbase output with an optional asset label pays
+/// fees in, if any. Labelled outputs are fees if their label or asset id is one
+/// of the fee assets, while unlabelled outputs are always fees, keyed by their
+/// asset id
+fn fee_asset(label: Option<&str>, asset: &str, fee_assets: &[String]) -> Option<String> {
+    match label {
+        Some(label) => {
+            // any other label is a policy asset
+            if fee_assets
+                .iter()
+                .any(|fee_asset| fee_asset == label || fee_asset == asset)
+            {
+                Some(label.to_owned())
+            } else {
+                None
+            }
+        }
+        None => Some(asset.to_owned()),
+    }
+}
+
+/// Get the addresses and script type of an output of a verbose transaction
+fn output_script(txout: &Value) -> (Vec<String>, String) {
+    let script = &txout["scriptPubKey"];
+    let mut addresses: Vec<String> = script["addresses"].as_array().map_or(vec![], |addresses| {
+        addresses
+            .iter()
+            .filter_map(|address| address.as_str().map(String::from))
+            .collect()
+    });
+    // single address field of newer node versions
+    if let Some(address) = script["address"].as_str() {
+        addresses.push(address.to_owned());
+    }
+    (addresses, script["type"].as_str().unwrap_or("").to_owned())
+}
+
+/// Add the fee outputs of a verbose coinbase transaction to the fee totals per
+/// fee asset. Outputs that the fee filter does not count as fees are skipped
+fn add_coinbase_fees(
+    tx: &Value,
+    fee_assets: &[String],
+    fee_filter: &FeeFilter,
+    fees: &mut BTreeMap<String, Amount>,
+) -> Result<()> {
+    for txout in tx["vout"].as_array().map_or(&[][..], |vout| vout.as_slice()) {
+        let (addresses, script_type) = output_script(txout);
+        if !fee_filter.is_fee(&addresses, &script_type) {
+            continue;
+        }
+        let asset = txout["asset"].as_str().unwrap_or("");
+        if let Some(fee_asset) = fee_asset(txout["assetlabel"].as_str(), asset, fee_assets) {
+            let value =
+                Amount::from_btc(txout["value"].as_f64().unwrap_or(0.0)).map_err(|e| CError::Generic(e.to_string()))?;
+            *fees.entry(fee_asset).or_insert(Amount::ZERO) += value;
+        }
     }
+    Ok(())
 }
 
-/// Function that calculates all the fees accumulated in the duration of a
-/// service request in the clientchain
-fn calculate_fees(request: &Request, client: &OceanClient) -> Result<Amount> {
-    let mut fee_sum = Amount::ZERO;
-    for i in request.start_blockheight_clientchain..=request.end_blockheight_clientchain {
+/// Function that calculates the fees accumulated in each block of a range of
+/// clientchain blocks per fee asset, skipping blocks without any fees. Only the
+/// coinbase outputs counted by the fee filter are included
+fn calculate_block_fees(
+    start_height: u32,
+    end_height: u32,
+    client: &OceanClient,
+    fee_assets: &[String],
+    fee_filter: &FeeFilter,
+) -> Result<Vec<BlockFees>> {
+    let mut blocks = vec![];
+    for i in start_height..=end_height {
         let block = client.get_block_info(&client.get_block_hash(i.into())?)?;
-        let tx = client.get_raw_transaction_verbose(&block.tx[0], None)?; // coinbase tx
-        assert!(tx.is_coinbase() == true);
-        for txout in tx.vout {
-            match txout.assetlabel {
-                Some(label) => {
-                    // any other label is a policy asset
-                    if label == "CBT" {
-                        fee_sum += txout.value;
-                    }
+        // coinbase tx, fetched untyped for the output script types
+        let tx: Value = client.call(
+            "getrawtransaction",
+            &[Value::from(block.tx[0].to_string()), Value::from(1)],
+        )?;
+        assert!(tx["vin"][0]["coinbase"].is_string());
+        let mut fees: BTreeMap<String, Amount> = BTreeMap::new();
+        add_coinbase_fees(&tx, fee_assets, fee_filter, &mut fees)?;
+        if fees.len() > 0 {
+            blocks.push(BlockFees {
+                height: i,
+                fees: fees
+                    .into_iter()
+                    .map(|(asset, amount)| AssetFees { asset, amount })
+                    .collect(),
+            });
+        }
+    }
+    Ok(blocks)
+}
+
+/// Function that calculates all the fees accumulated in a range of clientchain
+/// blocks, e.g. the duration of a service request or of a payment epoch, per
+/// fee asset. Only the coinbase outputs counted by the fee filter are included
+fn calculate_fees(
+    start_height: u32,
+    end_height: u32,
+    client: &OceanClient,
+    fee_assets: &[String],
+    fee_filter: &FeeFilter,
+) -> Result<Vec<AssetFees>> {
+    let blocks = calculate_block_fees(start_height, end_height, client, fee_assets, fee_filter)?;
+    Ok(block_fees_totals(&blocks))
+}
+
+/// Number of recent wallet transactions scanned when reconciling payment
+/// intents
+pub const PAYMENTS_RECONCILE_TX_COUNT: u64 = 1000;
+
+/// Number of confirmations after which the confirmations of bid payment
+/// transactions are no longer tracked
+pub const PAYMENTS_CONFIRMATIONS_FINAL: u32 = 6;
+
+/// Label of the bid payout addresses imported as watch-only addresses in
+/// watch-only payment mode
+pub const PAYMENTS_WATCH_LABEL: &str = "coordinator-payouts";
+
+/// Mode in which bids are paid
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PaymentMode {
+    /// Bids are paid by the client chain wallet holding the payment key
+    Wallet,
+    /// Bid payments are exported in batch files for payment by an external
+    /// wallet and confirmed by watching the client chain for transactions
+    /// paying the bid addresses, without the payment key on the coordinator
+    WatchOnly,
+}
+
+impl PaymentMode {
+    /// Get the payment mode name as used in config
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentMode::Wallet => "wallet",
+            PaymentMode::WatchOnly => "watch-only",
+        }
+    }
+}
+
+impl FromStr for PaymentMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<PaymentMode> {
+        match s {
+            "wallet" => Ok(PaymentMode::Wallet),
+            "watch-only" => Ok(PaymentMode::WatchOnly),
+            _ => Err(Error::from(CError::Generic(format!("unknown payment mode: {}", s)))),
+        }
+    }
+}
+
+/// Format of the batch files that bid payments are exported in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PaymentExportFormat {
+    /// Json with the amounts per address as taken by the sendmany rpc of the
+    /// client chain node, along with the intent of each bid payment
+    Json,
+    /// BIP21 style payment uris, one per line
+    Bip21,
+}
+
+impl PaymentExportFormat {
+    /// Get the export format name as used in config
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentExportFormat::Json => "json",
+            PaymentExportFormat::Bip21 => "bip21",
+        }
+    }
+
+    /// Get the extension of batch files in the export format
+    fn extension(&self) -> &'static str {
+        match self {
+            PaymentExportFormat::Json => "json",
+            PaymentExportFormat::Bip21 => "txt",
+        }
+    }
+}
+
+impl FromStr for PaymentExportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<PaymentExportFormat> {
+        match s {
+            "json" => Ok(PaymentExportFormat::Json),
+            "bip21" => Ok(PaymentExportFormat::Bip21),
+            _ => Err(Error::from(CError::Generic(format!(
+                "unknown payment export format: {}",
+                s
+            )))),
+        }
+    }
+}
+
+/// Order in which bid payments are paid when the wallet cannot fund all of
+/// them, with the payments that the funds do not reach deferred
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PayoutOrder {
+    /// Smallest payments first, paying as many bids as possible
+    SmallestFirst,
+    /// Largest payments first
+    LargestFirst,
+    /// Payments of the bids with the most challenge responses first
+    MostResponses,
+}
+
+impl PayoutOrder {
+    /// Get the payout order name as used in config
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayoutOrder::SmallestFirst => "smallest-first",
+            PayoutOrder::LargestFirst => "largest-first",
+            PayoutOrder::MostResponses => "most-responses",
+        }
+    }
+}
+
+impl FromStr for PayoutOrder {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<PayoutOrder> {
+        match s {
+            "smallest-first" => Ok(PayoutOrder::SmallestFirst),
+            "largest-first" => Ok(PayoutOrder::LargestFirst),
+            "most-responses" => Ok(PayoutOrder::MostResponses),
+            _ => Err(Error::from(CError::Generic(format!("unknown payout order: {}", s)))),
+        }
+    }
+}
+
+/// Outcome of paying the bid payments of a request or request epoch
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PayoutResult {
+    /// All bid payments made
+    Complete,
+    /// Bid payments made as far as the wallet funds allow, with the rest
+    /// deferred until the wallet is topped up
+    Partial,
+    /// Some bid payments failed or are unresolved
+    Failed,
+}
+
+impl PayoutResult {
+    /// Get the outcome of payments that either succeeded or failed
+    fn from_success(success: bool) -> PayoutResult {
+        if success {
+            PayoutResult::Complete
+        } else {
+            PayoutResult::Failed
+        }
+    }
+
+    /// Combine the outcomes of paying several batches of bid payments, such
+    /// as the epochs of a request. Failures take precedence over deferrals
+    pub fn and(self, other: PayoutResult) -> PayoutResult {
+        match (self, other) {
+            (PayoutResult::Failed, _) | (_, PayoutResult::Failed) => PayoutResult::Failed,
+            (PayoutResult::Partial, _) | (_, PayoutResult::Partial) => PayoutResult::Partial,
+            _ => PayoutResult::Complete,
+        }
+    }
+}
+
+/// Select the unpaid bid payments that a wallet balance can fund in full, in
+/// payout order. Payments are funded in order until the next payment exceeds
+/// the remaining balance, with that and all later payments deferred so that
+/// the payout order is kept. Returns the txids of the deferred bids in payout
+/// order along with the balance left after the funded payments
+pub fn select_payouts(bids: &[Bid], balance: Amount, order: PayoutOrder) -> (Vec<sha256d::Hash>, Amount) {
+    let mut unpaid: Vec<(&Bid, &BidPayment)> = bids
+        .iter()
+        .filter_map(|bid| bid.payment.as_ref().map(|payment| (bid, payment)))
+        .filter(|(_, payment)| !payment.is_paid() && payment.intent.is_none() && payment.amount != Amount::ZERO)
+        .collect();
+    let responses = |payment: &BidPayment| payment.basis.as_ref().map_or(0, |basis| basis.num_responses);
+    match order {
+        PayoutOrder::SmallestFirst => unpaid.sort_by_key(|(_, payment)| payment.amount),
+        PayoutOrder::LargestFirst => unpaid.sort_by(|a, b| b.1.amount.cmp(&a.1.amount)),
+        PayoutOrder::MostResponses => unpaid.sort_by(|a, b| responses(b.1).cmp(&responses(a.1))),
+    }
+
+    let mut remaining = balance;
+    let mut deferred = vec![];
+    for (bid, payment) in unpaid {
+        if deferred.len() == 0 && payment.amount <= remaining {
+            remaining = remaining - payment.amount;
+        } else {
+            deferred.push(bid.txid);
+        }
+    }
+    (deferred, remaining)
+}
+
+/// Bid payment exported for payment by an external wallet
+#[derive(Serialize, Debug, Clone, PartialEq)]
+struct ExportedPayment {
+    /// Bid txid
+    bid: sha256d::Hash,
+    /// Bid pay to address
+    address: String,
+    /// Bid payment amount
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    amount: Amount,
+    /// Payment intent id resolved once a matching transaction is found
+    intent: String,
+}
+
+/// Batch of the bid payments of a request, or of a request epoch, exported for
+/// payment by an external wallet
+#[derive(Serialize, Debug, Clone, PartialEq)]
+struct PaymentBatch {
+    /// Request txid
+    request: sha256d::Hash,
+    /// Payment epoch; not set for requests paid at the end of the request
+    epoch: Option<u32>,
+    /// Payment asset label or asset id
+    asset: String,
+    /// Amounts per address as taken by sendmany. Payments to the same address
+    /// are summed and expected to be paid by a single output
+    amounts: BTreeMap<String, f64>,
+    /// Exported bid payments
+    payments: Vec<ExportedPayment>,
+}
+
+impl PaymentBatch {
+    /// Build the batch of the unpaid bid payments with a payment intent
+    fn new(request: sha256d::Hash, epoch: Option<u32>, asset: &str, bids: &[Bid]) -> PaymentBatch {
+        let mut amounts = BTreeMap::new();
+        let mut payments = vec![];
+        for bid in bids.iter() {
+            if let Some(payment) = &bid.payment {
+                if let Some(intent) = &payment.intent {
+                    let address = payment.address.to_string();
+                    *amounts.entry(address.clone()).or_insert(Amount::ZERO) += payment.amount;
+                    payments.push(ExportedPayment {
+                        bid: bid.txid,
+                        address,
+                        amount: payment.amount,
+                        intent: intent.clone(),
+                    });
+                }
+            }
+        }
+        PaymentBatch {
+            request,
+            epoch,
+            asset: asset.to_owned(),
+            amounts: amounts
+                .into_iter()
+                .map(|(address, amount)| (address, amount.as_btc()))
+                .collect(),
+            payments,
+        }
+    }
+
+    /// Get the name of the batch file, from the request txid and epoch
+    fn file_name(&self, format: PaymentExportFormat) -> String {
+        match self.epoch {
+            Some(epoch) => format!("{}-{}.{}", self.request, epoch, format.extension()),
+            None => format!("{}.{}", self.request, format.extension()),
+        }
+    }
+
+    /// Encode the batch in the export format. Payment uris carry the summed
+    /// amount of each address, labelled with the request txid
+    fn encode(&self, format: PaymentExportFormat) -> String {
+        match format {
+            PaymentExportFormat::Json => serde_json::to_string_pretty(self).unwrap(),
+            PaymentExportFormat::Bip21 => {
+                let asset = if self.asset == "ANY" {
+                    String::new()
+                } else {
+                    format!("&asset={}", self.asset)
+                };
+                self.amounts
+                    .iter()
+                    .map(|(address, amount)| {
+                        format!(
+                            "ocean:{}?amount={:.8}&label={}{}\n",
+                            address, amount, self.request, asset
+                        )
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Write a payment batch file in the export format to the export dir,
+/// replacing any previous export of the batch. Returns the batch file path
+fn write_payment_batch(dir: &str, format: PaymentExportFormat, batch: &PaymentBatch) -> Result<PathBuf> {
+    fs::create_dir_all(dir).map_err(|e| CError::Generic(format!("failed creating export dir {}: {}", dir, e)))?;
+    let path = Path::new(dir).join(batch.file_name(format));
+    fs::write(&path, batch.encode(format))
+        .map_err(|e| CError::Generic(format!("failed writing payment batch {}: {}", path.display(), e)))?;
+    Ok(path)
+}
+
+/// Client chain transaction output paying an address watched by the wallet
+#[derive(Debug, Clone, PartialEq)]
+struct WatchedTx {
+    /// Transaction id
+    txid: sha256d::Hash,
+    /// Address paid
+    address: String,
+    /// Amount paid to the address
+    amount: Amount,
+    /// Number of confirmations of the transaction
+    confirmations: u32,
+}
+
+/// Match the pending intents of exported bid payment batches to the watched
+/// transactions paying the bid addresses. The payments of each batch to the
+/// same address are matched to a single output paying their summed amount, as
+/// exported. Outputs are matched once and outputs already recorded as bid
+/// payments are never matched, so that repeated payments to an address across
+/// epochs are told apart. Returns the payment transactions of each intent
+fn match_exported_payments(batches: &[&[Bid]], watched: &[WatchedTx]) -> HashMap<String, Vec<BidPaymentTx>> {
+    let mut claimed: HashSet<(sha256d::Hash, String)> = HashSet::new();
+    for bid in batches.iter().flat_map(|bids| bids.iter()) {
+        if let Some(payment) = &bid.payment {
+            for tx in payment.txs.iter() {
+                let _ = claimed.insert((tx.txid, payment.address.to_string()));
+            }
+        }
+    }
+    let mut intents = HashMap::new();
+    for bids in batches.iter() {
+        let mut expected: BTreeMap<String, (Amount, Vec<(&String, Amount)>)> = BTreeMap::new();
+        for payment in bids.iter().filter_map(|bid| bid.payment.as_ref()) {
+            if let Some(intent) = &payment.intent {
+                let address_expected = expected
+                    .entry(payment.address.to_string())
+                    .or_insert((Amount::ZERO, vec![]));
+                address_expected.0 += payment.amount;
+                address_expected.1.push((intent, payment.amount));
+            }
+        }
+        for (address, (total, address_intents)) in expected {
+            let found = watched.iter().find(|tx| {
+                tx.address == address && tx.amount == total && !claimed.contains(&(tx.txid, address.clone()))
+            });
+            if let Some(tx) = found {
+                let _ = claimed.insert((tx.txid, address.clone()));
+                for (intent, amount) in address_intents {
+                    let _ = intents.insert(
+                        intent.clone(),
+                        vec![BidPaymentTx {
+                            txid: tx.txid,
+                            amount,
+                            confirmations: tx.confirmations,
+                        }],
+                    );
                 }
-                None => fee_sum += txout.value,
             }
         }
     }
-    Ok(fee_sum)
+    intents
+}
+
+/// Current unix time in ms of the clock, used as the attempt id of payment
+/// intents
+fn unix_time_ms(clock: &dyn Clock) -> u64 {
+    let since_epoch = clock.unix_time();
+    since_epoch.as_secs() * 1000 + since_epoch.subsec_millis() as u64
+}
+
+/// Check if a bid has a payment intent that has not been resolved
+fn has_pending_intent(bid: &Bid) -> bool {
+    match &bid.payment {
+        Some(payment) => !payment.is_paid() && payment.intent.is_some(),
+        None => false,
+    }
+}
+
+/// Check if a bid has been paid with transactions that have not reached the
+/// final number of confirmations
+fn has_unconfirmed_payment(bid: &Bid) -> bool {
+    match &bid.payment {
+        Some(payment) => payment.is_paid() && !payment.is_confirmed(PAYMENTS_CONFIRMATIONS_FINAL),
+        None => false,
+    }
+}
+
+/// Resolve pending bid payment intents against the intents found in the
+/// wallet, setting the payment transactions for each intent that was broadcast.
+/// Returns the number of intents that remain unresolved
+fn resolve_payment_intents(bids: &mut Vec<Bid>, wallet_intents: &HashMap<String, Vec<BidPaymentTx>>) -> usize {
+    let mut unresolved = 0;
+    for bid in bids.iter_mut() {
+        if !has_pending_intent(bid) {
+            continue;
+        }
+        let payment = bid.payment.as_mut().unwrap();
+        match wallet_intents.get(payment.intent.as_ref().unwrap()) {
+            Some(txs) => {
+                info!(
+                    "payment intent {} resolved to txids {:?}",
+                    payment.intent.as_ref().unwrap(),
+                    txs.iter().map(|tx| tx.txid).collect::<Vec<_>>()
+                );
+                payment.txs = txs.clone();
+                payment.intent = None;
+            }
+            None => unresolved += 1,
+        }
+    }
+    unresolved
+}
+
+/// Compact the responses of paid requests for the genesis hash that ended at
+/// least `age` client chain blocks before the given client chain height. The
+/// per bid responses are no longer needed after payment and are replaced by a
+/// response summary stored on the request. Returns the number of compacted
+/// responses
+pub fn compact_responses(
+    storage: &dyn Storage,
+    genesis_hash: sha256d::Hash,
+    clientchain_height: u32,
+    age: u32,
+) -> Result<u32> {
+    let mut compacted = 0;
+    for request in storage.get_requests(Some(true), Some(genesis_hash), None, None)? {
+        if request.response_summary.is_some() || request.end_blockheight_clientchain + age > clientchain_height {
+            continue;
+        }
+        if let Some(response) = storage.get_response(request.txid)? {
+            storage.compact_response(request.txid, &response.summary())?;
+            info! {"Compacted responses for request: {}", request.txid};
+            compacted += 1;
+        }
+    }
+    Ok(compacted)
+}
+
+/// Number of seconds between checks for unpaid epochs of active requests
+pub const PAYMENTS_EPOCH_CHECK_INTERVAL: u64 = 60;
+
+/// Get the service chain heights at which the bids of a request are paid. With
+/// a payment epoch length set, bids are paid at the end of each epoch and at
+/// the end of the request, otherwise only at the end of the request
+pub fn payment_schedule(request: &Request, payment_epoch: Option<u64>) -> Vec<u32> {
+    let mut schedule = vec![];
+    if let Some(epoch_length) = payment_epoch {
+        let mut height = request.start_blockheight as u64 + epoch_length.max(1);
+        while height < request.end_blockheight as u64 {
+            schedule.push(height as u32);
+            height += epoch_length.max(1);
+        }
+    }
+    schedule.push(request.end_blockheight);
+    schedule
+}
+
+/// Get the snapshot of the final payment epoch of a finished request, covering
+/// the rest of the request after the last stored epoch snapshot. Returns None
+/// if the last stored snapshot already covers the end of the request
+fn final_epoch_snapshot(
+    request: &Request,
+    snapshots: &[ResponseSnapshot],
+    response: Response,
+    bids: Vec<Bid>,
+) -> Option<ResponseSnapshot> {
+    let last = snapshots.last()?;
+    if last.clientchain_height >= request.end_blockheight_clientchain {
+        return None;
+    }
+    Some(ResponseSnapshot {
+        epoch: last.epoch + 1,
+        service_height: request.end_blockheight,
+        clientchain_height: request.end_blockheight_clientchain,
+        response,
+        bids: bids
+            .into_iter()
+            .map(|mut bid| {
+                bid.payment = None;
+                bid
+            })
+            .collect(),
+        is_payment_complete: false,
+    })
 }
 
 /// Function that calculates the fee amount to be received per bid given total
@@ -62,6 +628,338 @@ fn calculate_bid_payment(fees_amount: &Amount, fee_percentage: u64, num_bids: u6
     Ok(total_amount / num_bids) // amount per bid
 }
 
+/// Number of the latest requests whose fee pools the fee accrual rate of
+/// earnings estimates is taken from
+pub const EARNINGS_SAMPLE_REQUESTS: i64 = 10;
+
+/// Estimated earnings of a hypothetical bid on an active or upcoming request
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct EarningsEstimate {
+    /// Number of finished requests that the fee accrual rate is taken from
+    pub sample_requests: u32,
+    /// Fees accrued per service chain block by the sampled requests
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub fee_rate: Amount,
+    /// Number of service chain blocks of the request
+    pub blocks: u32,
+    /// Fees expected to accrue over the request
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub fees_amount: Amount,
+    /// Percentage of the fees paid to bids
+    pub fee_percentage: u32,
+    /// Number of bids that the fees are split between, including the bid
+    pub num_bids: u32,
+    /// Share of the challenges that the bid is assumed to respond to
+    pub response_rate: f64,
+    /// Expected payout of the bid
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub amount: Amount,
+}
+
+/// Estimate the payout of a hypothetical bid on a request from the fees that
+/// accrued per service chain block over recently finished requests, given as
+/// pairs of each request and its fee pool, and the number of bids already on
+/// the request. The bid shares the fees with the other bids up to the tickets
+/// of the request and its share is scaled by the response rate, as in the
+/// bid payment formula when response latencies are not weighted
+pub fn estimate_earnings(
+    request: &Request,
+    num_bids: usize,
+    samples: &[(Request, FeePool)],
+    fee_percentage: u32,
+    response_rate: f64,
+) -> EarningsEstimate {
+    let request_blocks = |request: &Request| request.end_blockheight.saturating_sub(request.start_blockheight);
+    let sample_blocks: u64 = samples.iter().map(|(sample, _)| request_blocks(sample) as u64).sum();
+    let sample_fees: u64 = samples.iter().map(|(_, pool)| pool.total.as_sat()).sum();
+    let blocks = request_blocks(request);
+    let fees_amount = if sample_blocks > 0 {
+        Amount::from_sat((sample_fees as u128 * blocks as u128 / sample_blocks as u128) as u64)
+    } else {
+        Amount::ZERO
+    };
+    let num_bids = (num_bids as u32 + 1).min(request.num_tickets.max(1));
+    let response_rate = response_rate.max(0.0).min(1.0);
+    let share = fees_amount * fee_percentage as u64 / 100 / num_bids as u64;
+    EarningsEstimate {
+        sample_requests: samples.len() as u32,
+        fee_rate: Amount::from_sat(sample_fees / sample_blocks.max(1)),
+        blocks,
+        fees_amount,
+        fee_percentage,
+        num_bids,
+        response_rate,
+        amount: Amount::from_sat((share.as_sat() as f64 * response_rate) as u64),
+    }
+}
+
+/// Get the payment state of a request from the payments of its bids. A request
+/// is only paid once every bid payment calculated has been broadcast, as proof
+/// that the funds were sent, and payment is not required if the coordinator
+/// does not do payments or no bid payments were calculated. Requests whose
+/// unpaid bid payments were all exported with a payment intent are exported,
+/// while requests with payments deferred for lack of wallet funds are partially
+/// paid
+pub fn request_payment_state(do_payment: bool, bids: &[Bid], payout: PayoutResult) -> PaymentState {
+    let payments: Vec<&BidPayment> = bids.iter().filter_map(|bid| bid.payment.as_ref()).collect();
+    if !do_payment || payments.len() == 0 {
+        return PaymentState::NotRequired;
+    }
+    match payout {
+        PayoutResult::Failed => return PaymentState::Failed,
+        PayoutResult::Partial => return PaymentState::Partial,
+        PayoutResult::Complete => (),
+    }
+    let unpaid: Vec<&&BidPayment> = payments
+        .iter()
+        .filter(|payment| !payment.is_paid() && payment.amount != Amount::ZERO)
+        .collect();
+    if unpaid.len() == 0 {
+        PaymentState::Paid
+    } else if unpaid.iter().all(|payment| payment.intent.is_some()) {
+        PaymentState::Exported
+    } else {
+        PaymentState::Failed
+    }
+}
+
+/// Get the latency weighting of the responses of a bid from the proof latency
+/// samples of the responses, if latency weights are configured
+fn latency_weighting(
+    latency_weights: &[LatencyBucketConfig],
+    proof_ms: Option<&Vec<u64>>,
+    num_responses: u32,
+) -> Option<LatencyWeighting> {
+    if latency_weights.len() == 0 {
+        return None;
+    }
+    Some(LatencyWeighting::new(
+        latency_weights.iter().map(|bucket| bucket.max_ms).collect(),
+        latency_weights.iter().map(|bucket| bucket.weight).collect(),
+        proof_ms.map_or(&[][..], |samples| samples.as_slice()),
+        num_responses,
+    ))
+}
+
+/// Make the actual payments to bid owners for the service request with the
+/// wallet of the rpc client. Uses sendtoaddress if the asset label has been
+/// specified or sendanytoaddress if not. Errors don't kill the process but
+/// signal that payments have failed. Already paid bids are skipped.
+/// Before broadcasting, a payment intent is recorded in storage and passed
+/// as the wallet transaction comment so that payments interrupted by a
+/// crash or a failed send can be reconciled instead of paid twice. The intent
+/// is only resolved once the send succeeds, and bids with an unresolved intent
+/// are never paid again. Deferred bids are skipped without counting
+/// as failed. Bid payment updates are stored via the persist callback.
+fn complete_bid_payments<C: RpcApi>(
+    client: &C,
+    payment_asset: &str,
+    bids: &mut Vec<Bid>,
+    deferred: &[sha256d::Hash],
+    persist: &mut dyn FnMut(&Bid) -> Result<()>,
+    clock: &dyn Clock,
+) -> Result<bool> {
+    let use_sendany = payment_asset == "ANY";
+    let mut success = true;
+    for bid in bids {
+        let bid_txid = bid.txid;
+        let mut bid_payment = match bid.payment.clone() {
+            Some(bid_payment) => bid_payment,
+            None => continue,
+        };
+        if bid_payment.is_paid() {
+            warn!(
+                "addr {} paid already (txids: {:?})",
+                &bid_payment.address,
+                bid_payment.txs.iter().map(|tx| tx.txid).collect::<Vec<_>>()
+            );
+            continue;
+        }
+        if let Some(intent) = &bid_payment.intent {
+            warn!(
+                "addr {} has unresolved payment intent {} - refusing to pay again",
+                &bid_payment.address, intent
+            );
+            success = false;
+            continue;
+        }
+        if deferred.contains(&bid_txid) {
+            info!(
+                "payment to {} for {} deferred",
+                &bid_payment.address, bid_payment.amount
+            );
+            continue;
+        }
+
+        // record payment intent before broadcasting
+        let intent = BidPayment::new_intent(&bid_txid, &bid_payment.amount, unix_time_ms(clock));
+        bid_payment.intent = Some(intent.clone());
+        bid.payment = Some(bid_payment.clone());
+        persist(&bid)?;
+
+        info!("payment to {} for {}", &bid_payment.address, bid_payment.amount);
+        if use_sendany {
+            match client.send_any_to_address(
+                &bid_payment.address,
+                bid_payment.amount,
+                Some(&intent),
+                None,
+                None,
+                Some(true),
+                None,
+            ) {
+                Ok(res) => {
+                    let txids = match res {
+                        SendAnyToAddressResult::Txid(txid) => vec![txid],
+                        SendAnyToAddressResult::Txids(txids) => txids,
+                    };
+                    info!("payment (ANY) txids {:?}", txids);
+                    bid_payment.txs = get_payment_txs(client, &intent, &txids, bid_payment.amount);
+                }
+                Err(err) => {
+                    warn!(
+                        "bid payment (send_any_to_address) failed: {} - intent {} left for reconciliation",
+                        err, intent
+                    );
+                    success = false; // mark that payments failed but
+                                     // keep going
+                    continue;
+                }
+            }
+        } else {
+            match client.send_to_address(
+                &bid_payment.address,
+                bid_payment.amount,
+                Some(&intent),
+                None,
+                Some(false),
+                Some(payment_asset),
+            ) {
+                Ok(txid) => {
+                    bid_payment.txs = vec![BidPaymentTx {
+                        txid,
+                        amount: bid_payment.amount,
+                        confirmations: 0,
+                    }];
+                    info!("payment ({}) txid {}", payment_asset, txid);
+                }
+                Err(err) => {
+                    warn!(
+                        "bid payment (send_to_address) failed: {} - intent {} left for reconciliation",
+                        err, intent
+                    );
+                    success = false; // mark that payments failed but
+                                     // keep going
+                    continue;
+                }
+            }
+        }
+
+        // payment broadcast so intent is resolved; failed sends, e.g. rpc
+        // timeouts after the wallet already broadcast, keep the intent pending
+        // until it is reconciled against the wallet transactions
+        bid_payment.intent = None;
+        bid.payment = Some(bid_payment);
+        persist(&bid)?;
+    }
+    Ok(success)
+}
+
+/// Get the payment transactions of a payment split across the given txids.
+/// The amount sent by each transaction is looked up in the wallet send
+/// transactions of the payment intent, while single transaction payments
+/// send the whole payment amount
+fn get_payment_txs<C: RpcApi>(client: &C, intent: &str, txids: &[sha256d::Hash], amount: Amount) -> Vec<BidPaymentTx> {
+    if txids.len() == 1 {
+        return vec![BidPaymentTx {
+            txid: txids[0],
+            amount,
+            confirmations: 0,
+        }];
+    }
+    let wallet_txs = match get_wallet_payment_intents(client) {
+        Ok(mut intents) => intents.remove(intent).unwrap_or(vec![]),
+        Err(err) => {
+            warn!("failed getting wallet payment transactions: {}", err);
+            vec![]
+        }
+    };
+    txids
+        .iter()
+        .map(
+            |txid| match wallet_txs.iter().find(|wallet_tx| wallet_tx.txid == *txid) {
+                Some(wallet_tx) => wallet_tx.clone(),
+                None => {
+                    warn!("payment txid {} not found in wallet", txid);
+                    BidPaymentTx {
+                        txid: *txid,
+                        amount: Amount::ZERO,
+                        confirmations: 0,
+                    }
+                }
+            },
+        )
+        .collect()
+}
+
+/// Get the payment intents and corresponding payment transactions of recent
+/// wallet send transactions, by reading the comment attached to each
+/// transaction. Payments split across transactions have a payment
+/// transaction per txid with the amount sent by the transaction
+fn get_wallet_payment_intents<C: RpcApi>(client: &C) -> Result<HashMap<String, Vec<BidPaymentTx>>> {
+    let txs = client.call::<Vec<Value>>(
+        "listtransactions",
+        &[Value::from("*"), Value::from(PAYMENTS_RECONCILE_TX_COUNT)],
+    )?;
+    let mut intents = HashMap::new();
+    for tx in txs {
+        if tx["category"].as_str() != Some("send") {
+            continue;
+        }
+        if let (Some(comment), Some(txid)) = (tx["comment"].as_str(), tx["txid"].as_str()) {
+            let txid = sha256d::Hash::from_hex(txid)?;
+            // send amounts are listed negative, as btc values parsed exactly
+            let amount = Amount::from_btc(tx["amount"].as_f64().unwrap_or(0.0).abs())
+                .map_err(|e| CError::Generic(e.to_string()))?;
+            let confirmations = tx["confirmations"].as_i64().unwrap_or(0).max(0) as u32;
+            let payment_txs = intents.entry(comment.to_owned()).or_insert(vec![]);
+            match payment_txs.iter_mut().find(|payment_tx| payment_tx.txid == txid) {
+                Some(payment_tx) => payment_tx.amount += amount,
+                None => payment_txs.push(BidPaymentTx {
+                    txid,
+                    amount,
+                    confirmations,
+                }),
+            }
+        }
+    }
+    Ok(intents)
+}
+
+/// Settings of payments taken from the coordinator config rather than the
+/// client chain config
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentSettings {
+    /// Number of client chain blocks after the end of a paid request that its
+    /// responses are compacted; responses are never compacted if not set
+    pub compaction_age: Option<u32>,
+    /// Interval in seconds of checking storage for finished requests; storage
+    /// is not checked if not set
+    pub watch_interval: Option<u64>,
+    /// Latency buckets that bid responses are weighted by; responses count
+    /// equally if empty
+    pub latency_weights: Vec<LatencyBucketConfig>,
+}
+
+/// Fees of a request, or of an epoch of a request, shared between its bids
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FeeShare {
+    /// Total fees accrued
+    amount: Amount,
+    /// Percentage of the fees paid to the bids
+    percentage: u32,
+}
+
 /// Payment Struct holding data and logic required to pay bids at the end of the
 /// service request
 pub struct Payments {
@@ -72,178 +970,881 @@ pub struct Payments {
     pub client: OceanClient,
     /// Clientchain address params required for fee payments
     pub addr_params: &'static AddressParams,
+    /// Type of the addresses derived from bid pubkeys that bids are paid to
+    pub address_type: PayoutAddressType,
     /// Payment asset with which fees rewards will be paid
     pub payment_asset: String,
+    /// Labels or asset ids of the assets that client chain fees accrue in
+    pub fee_assets: Vec<String>,
+    /// Filter of the coinbase outputs counted as fees
+    pub fee_filter: FeeFilter,
     /// Flag that determines whether we do actual payments or just collect and
     /// store payment data
     pub do_payment: bool,
+    /// Genesis hash of the clientchain for which requests are paid
+    pub genesis_hash: sha256d::Hash,
+    /// Fee percentage override; request fee percentage is used if not set
+    pub fee_percentage: Option<u32>,
+    /// Number of client chain blocks after the end of a paid request that its
+    /// responses are compacted; responses are never compacted if not set
+    pub compaction_age: Option<u32>,
+    /// Requests with bid payment transactions whose confirmations are tracked
+    pub unconfirmed: Mutex<HashSet<sha256d::Hash>>,
+    /// Interval in seconds of checking storage for finished requests that have
+    /// not been received via the request channel; storage is not checked if
+    /// not set
+    pub watch_interval: Option<u64>,
+    /// Finished requests already picked up for payment
+    pub watched: Mutex<HashSet<sha256d::Hash>>,
+    /// Finished requests whose payment is retried after a transient failure
+    pub retried: Mutex<HashSet<sha256d::Hash>>,
+    /// Journal recording computed bid payments
+    pub journal: Arc<Journal>,
+    /// Latency buckets that bid responses are weighted by; responses count
+    /// equally if empty
+    pub latency_weights: Vec<LatencyBucketConfig>,
+    /// Mode in which bids are paid
+    pub payment_mode: PaymentMode,
+    /// Dir that bid payment batches are exported to in watch-only mode
+    pub export_dir: String,
+    /// Format of the exported bid payment batches
+    pub export_format: PaymentExportFormat,
+    /// Flag to pay bids as far as the wallet funds allow, deferring the rest
+    /// until the wallet is topped up, instead of attempting every payment
+    pub partial_payouts: bool,
+    /// Order in which bids are paid when payments are deferred
+    pub payout_order: PayoutOrder,
+    /// Clock timing the periodic payment checks and payment intents
+    pub clock: Arc<dyn Clock + Send + Sync>,
+    /// Maintenance windows during which payments and compaction are deferred
+    pub maintenance: Arc<MaintenanceWindows>,
+    /// Flag set if response compaction was deferred by a maintenance window
+    pub compaction_deferred: AtomicBool,
 }
 
-impl Payments {
-    /// Method that does the actual payments to bid owners for the service
-    /// request. Uses sendtoaddress if the asset label has been specified or
-    /// sendanytoaddress if not. Errors don't kill the process but
-    /// signal that payments have failed. Already paid bids are skipped.
-    fn complete_bid_payments(&self, bids: &mut Vec<Bid>) -> Result<bool> {
-        let use_sendany = self.payment_asset == "ANY";
-        let mut success = true;
-        for bid in bids {
-            if let Some(bid_payment) = bid.payment.as_mut() {
-                if !bid_payment.txid.is_none() {
-                    warn!(
-                        "addr {} paid already (txid: {})",
-                        &bid_payment.address,
-                        bid_payment.txid.unwrap()
-                    );
+impl Payments {
+    /// Pay the bid payments of a request, or of a request epoch, with the
+    /// wallet or by exporting them for an external wallet in watch-only mode.
+    /// With partial payouts, wallet payments are made in payout order as far
+    /// as the wallet balance allows and the rest are deferred, recording the
+    /// deferred payments as a payment liability. Returns the payout outcome
+    fn pay_bids(
+        &self,
+        request_hash: sha256d::Hash,
+        epoch: Option<u32>,
+        bids: &mut Vec<Bid>,
+        persist: &mut dyn FnMut(&Bid) -> Result<()>,
+    ) -> Result<PayoutResult> {
+        match self.payment_mode {
+            PaymentMode::Wallet if self.partial_payouts => {
+                let (deferred, remaining) = select_payouts(bids, self.get_payment_balance()?, self.payout_order);
+                let success = complete_bid_payments(
+                    &self.client,
+                    &self.payment_asset,
+                    bids,
+                    &deferred,
+                    persist,
+                    self.clock.as_ref(),
+                )?;
+                self.record_payment_liability(request_hash, epoch, bids, &deferred, remaining)?;
+                if success && deferred.len() > 0 {
+                    Ok(PayoutResult::Partial)
+                } else {
+                    Ok(PayoutResult::from_success(success))
+                }
+            }
+            PaymentMode::Wallet => Ok(PayoutResult::from_success(complete_bid_payments(
+                &self.client,
+                &self.payment_asset,
+                bids,
+                &[],
+                persist,
+                self.clock.as_ref(),
+            )?)),
+            PaymentMode::WatchOnly => Ok(PayoutResult::from_success(self.export_bid_payments(
+                request_hash,
+                epoch,
+                bids,
+                persist,
+            )?)),
+        }
+    }
+
+    /// Get the wallet balance of the payment asset. For the ANY payment asset
+    /// the balances of all assets are summed
+    fn get_payment_balance(&self) -> Result<Amount> {
+        let balances = self.client.call::<HashMap<String, f64>>("getbalance", &[])?;
+        let balance = if self.payment_asset == "ANY" {
+            balances.values().sum()
+        } else {
+            *balances.get(&self.payment_asset).unwrap_or(&0.0)
+        };
+        Ok(Amount::from_btc(balance).unwrap_or(Amount::ZERO))
+    }
+
+    /// Record the bid payments of a request, or request epoch, deferred for
+    /// lack of wallet funds as a payment liability along with the balance left
+    /// after the payments made, or clear the liability if none are deferred
+    fn record_payment_liability(
+        &self,
+        request_hash: sha256d::Hash,
+        epoch: Option<u32>,
+        bids: &[Bid],
+        deferred: &[sha256d::Hash],
+        balance: Amount,
+    ) -> Result<()> {
+        if deferred.len() == 0 {
+            return self.storage.remove_payment_liability(request_hash, epoch);
+        }
+        let amount = bids
+            .iter()
+            .filter(|bid| deferred.contains(&bid.txid))
+            .filter_map(|bid| bid.payment.as_ref())
+            .fold(Amount::ZERO, |total, payment| total + payment.amount);
+        warn!(
+            "{} bid payments for {} deferred until the wallet is topped up",
+            deferred.len(),
+            amount
+        );
+        self.storage.save_payment_liability(&PaymentLiability {
+            request: request_hash,
+            genesis_blockhash: self.genesis_hash,
+            epoch,
+            bids: deferred.to_vec(),
+            amount,
+            balance,
+            time: unix_time_ms(self.clock.as_ref()),
+        })
+    }
+
+    /// Export the unpaid bid payments of a request, or of a request epoch, in
+    /// a batch file for payment by an external wallet. A payment intent is
+    /// recorded for each payment, as with wallet payments, and the bid
+    /// addresses are imported as watch-only addresses so that the transactions
+    /// paying them are found in the wallet and matched to the intents. Bids
+    /// exported before keep their intent, so exporting a batch again writes
+    /// the same batch file
+    fn export_bid_payments(
+        &self,
+        request_hash: sha256d::Hash,
+        epoch: Option<u32>,
+        bids: &mut Vec<Bid>,
+        persist: &mut dyn FnMut(&Bid) -> Result<()>,
+    ) -> Result<bool> {
+        for bid in bids.iter_mut() {
+            let bid_txid = bid.txid;
+            if let Some(bid_payment) = bid.payment.as_mut() {
+                if bid_payment.is_paid() || bid_payment.intent.is_some() || bid_payment.amount == Amount::ZERO {
+                    continue;
+                }
+                bid_payment.intent = Some(BidPayment::new_intent(
+                    &bid_txid,
+                    &bid_payment.amount,
+                    unix_time_ms(self.clock.as_ref()),
+                ));
+            } else {
+                continue;
+            }
+            persist(bid)?;
+        }
+
+        let batch = PaymentBatch::new(request_hash, epoch, &self.payment_asset, bids);
+        if batch.payments.len() == 0 {
+            return Ok(true);
+        }
+        for address in batch.amounts.keys() {
+            let _ = self.client.call::<Value>(
+                "importaddress",
+                &[
+                    Value::from(address.as_str()),
+                    Value::from(PAYMENTS_WATCH_LABEL),
+                    Value::Bool(false),
+                ],
+            )?;
+        }
+        let path = write_payment_batch(&self.export_dir, self.export_format, &batch)?;
+        info!("exported {} bid payments to {}", batch.payments.len(), path.display());
+        Ok(true)
+    }
+
+    /// Get the watched wallet transaction outputs paying the bid addresses
+    /// imported in watch-only mode, among recent wallet transactions
+    fn get_watched_txs(&self) -> Result<Vec<WatchedTx>> {
+        let txs = self.client.call::<Vec<Value>>(
+            "listtransactions",
+            &[
+                Value::from("*"),
+                Value::from(PAYMENTS_RECONCILE_TX_COUNT),
+                Value::from(0),
+                Value::Bool(true),
+            ],
+        )?;
+        let mut watched = vec![];
+        for tx in txs {
+            if tx["category"].as_str() != Some("receive") {
+                continue;
+            }
+            if let (Some(address), Some(txid)) = (tx["address"].as_str(), tx["txid"].as_str()) {
+                watched.push(WatchedTx {
+                    txid: sha256d::Hash::from_hex(txid)?,
+                    address: address.to_owned(),
+                    amount: Amount::from_btc(tx["amount"].as_f64().unwrap_or(0.0).abs())
+                        .map_err(|e| CError::Generic(e.to_string()))?,
+                    confirmations: tx["confirmations"].as_i64().unwrap_or(0).max(0) as u32,
+                });
+            }
+        }
+        Ok(watched)
+    }
+
+    /// Reconcile any pending payment intents of a request with the wallet
+    /// transactions. Intents matching a wallet transaction are resolved to
+    /// that transaction, while unmatched intents remain pending and block
+    /// the corresponding bids from being paid again. In watch-only mode the
+    /// intents of exported payments are matched to the watched transactions
+    /// paying the bid addresses instead. Returns the number of intents that
+    /// remain unresolved
+    fn reconcile_payment_intents(&self, request: &Request) -> Result<usize> {
+        let mut bids = self.storage.get_bids(request.txid)?;
+        let (mut snapshots, paid_snapshots): (Vec<ResponseSnapshot>, Vec<ResponseSnapshot>) = self
+            .storage
+            .get_response_snapshots(request.txid)?
+            .into_iter()
+            .partition(|snapshot| snapshot.bids.iter().any(|bid| has_pending_intent(bid)));
+        if !bids.iter().any(|bid| has_pending_intent(bid)) && snapshots.len() == 0 {
+            return Ok(0);
+        }
+        let wallet_intents = match self.payment_mode {
+            PaymentMode::Wallet => get_wallet_payment_intents(&self.client)?,
+            PaymentMode::WatchOnly => {
+                let mut batches: Vec<&[Bid]> = vec![&bids[..]];
+                batches.extend(
+                    snapshots
+                        .iter()
+                        .chain(paid_snapshots.iter())
+                        .map(|snapshot| &snapshot.bids[..]),
+                );
+                match_exported_payments(&batches, &self.get_watched_txs()?)
+            }
+        };
+        let mut unresolved = resolve_payment_intents(&mut bids, &wallet_intents);
+        self.storage.update_bids(request.txid, &bids)?;
+        for snapshot in snapshots.iter_mut() {
+            unresolved += resolve_payment_intents(&mut snapshot.bids, &wallet_intents);
+            self.storage.save_response_snapshot(request.txid, snapshot)?;
+        }
+        if unresolved > 0 {
+            warn! {"{} unresolved payment intents for request: {}", unresolved, request.txid};
+        }
+        Ok(unresolved)
+    }
+
+    /// Process bid payments method handles calculating the payment to be
+    /// received per bid and on which address, and updates the corresponding
+    /// payment info in Storage. The fees, bid count and responses that each
+    /// payment is calculated from are stored as the payment basis and recorded
+    /// in the journal along with the payment amount. With latency weights set,
+    /// the responses are weighted by the proof latency samples of each bid and
+    /// the weighting is stored with the basis
+    fn process_bid_payments(
+        &self,
+        request_hash: sha256d::Hash,
+        epoch: Option<u32>,
+        bids: &mut Vec<Bid>,
+        fees: FeeShare,
+        response: &Response,
+        bid_proof_ms: &HashMap<sha256d::Hash, Vec<u64>>,
+    ) -> Result<()> {
+        let num_bids = bids.len() as u32;
+        for bid in bids {
+            if let Some(bid_resp) = response.bid_responses.get(&bid.txid) {
+                // correct bid payment by calculating the performance
+                // base on successful responses / total responses
+                let latency_weighting =
+                    latency_weighting(&self.latency_weights, bid_proof_ms.get(&bid.txid), *bid_resp);
+                let basis = BidPaymentBasis {
+                    formula_version: if latency_weighting.is_some() {
+                        BID_PAYMENT_LATENCY_FORMULA_VERSION
+                    } else {
+                        BID_PAYMENT_FORMULA_VERSION
+                    },
+                    fees_amount: fees.amount,
+                    fee_percentage: fees.percentage,
+                    num_bids,
+                    num_challenges: response.num_challenges,
+                    num_responses: *bid_resp,
+                    latency_weighting,
+                };
+                // keep any payment outcome or intent from previous runs, along
+                // with the address of any payment already broadcast
+                let (txs, intent, paid_to) = match bid.payment.take() {
+                    Some(payment) => {
+                        let paid_to = if payment.is_paid() {
+                            Some((payment.address, payment.address_type))
+                        } else {
+                            None
+                        };
+                        (payment.txs, payment.intent, paid_to)
+                    }
+                    None => (vec![], None, None),
+                };
+                let (address, address_type) = paid_to.unwrap_or_else(|| {
+                    (
+                        self.address_type.address(&bid.pubkey, self.addr_params),
+                        self.address_type,
+                    )
+                });
+                let amount = basis.amount();
+                self.journal.record(JournalEvent::payment_computed(
+                    request_hash,
+                    epoch,
+                    bid.txid,
+                    &basis,
+                    &amount,
+                ));
+                bid.payment = Some(BidPayment {
+                    amount,
+                    address,
+                    address_type,
+                    txs,
+                    intent,
+                    basis: Some(basis),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Calculate the fees accrued over the client chain blocks of a request,
+    /// recording the fee filter applied with the fee totals. The fee pool of
+    /// the request is calculated once and stored, so that payment retries reuse
+    /// it and clients can verify the payment of the request via the api
+    fn calculate_request_fees(&self, request: &mut Request, fee_percentage: u32) -> Result<()> {
+        let (start_height, end_height) = (
+            request.start_blockheight_clientchain,
+            request.end_blockheight_clientchain,
+        );
+        let pool = match self.storage.get_fee_pool(request.txid)? {
+            Some(ref pool) if pool.start_height == start_height && pool.end_height == end_height => pool.clone(),
+            _ => {
+                let blocks = calculate_block_fees(
+                    start_height,
+                    end_height,
+                    &self.client,
+                    &self.fee_assets,
+                    &self.fee_filter,
+                )?;
+                let fee_filter = if self.fee_filter.is_empty() {
+                    None
+                } else {
+                    Some(self.fee_filter.clone())
+                };
+                let pool = FeePool::new(start_height, end_height, blocks, fee_percentage, fee_filter);
+                self.storage.save_fee_pool(request.txid, &pool)?;
+                pool
+            }
+        };
+        request.fees = pool.fees;
+        request.fee_filter = pool.fee_filter;
+        Ok(())
+    }
+
+    /// Pay the bid payments of a finished request that is not paid per epoch,
+    /// storing the bid payment updates. Returns the payment state of the
+    /// request
+    fn pay_request_bids(&self, request_hash: sha256d::Hash, bids: &mut Vec<Bid>) -> Result<PaymentState> {
+        let mut payout = PayoutResult::Complete;
+        if self.do_payment {
+            payout = self.pay_bids(request_hash, None, bids, &mut |bid: &Bid| {
+                self.storage.update_bid(request_hash, bid)
+            })?;
+            let _ = self.unconfirmed.lock().unwrap().insert(request_hash);
+        }
+
+        // update bids with payment information
+        self.storage.update_bids(request_hash, bids)?;
+        Ok(request_payment_state(self.do_payment, bids, payout))
+    }
+
+    /// Resume the bid payments deferred for lack of wallet funds once the
+    /// wallet balance of the payment asset exceeds the balance left when the
+    /// payments were deferred, i.e. once the wallet has been topped up.
+    /// Deferred epoch payments are resumed along with the other unpaid epochs
+    fn do_deferred_payments(&self) -> Result<()> {
+        if !self.do_payment || !self.partial_payouts || self.payment_mode != PaymentMode::Wallet {
+            return Ok(());
+        }
+        let liabilities: Vec<PaymentLiability> = self
+            .storage
+            .get_payment_liabilities(Some(self.genesis_hash))?
+            .into_iter()
+            .filter(|liability| liability.epoch.is_none())
+            .collect();
+        if liabilities.len() == 0 {
+            return Ok(());
+        }
+        let balance = self.get_payment_balance()?;
+        for liability in liabilities {
+            if balance <= liability.balance {
+                continue;
+            }
+            let mut req = match self.storage.get_request(liability.request)? {
+                Some(req) => req,
+                None => continue,
+            };
+            let _log_context = RequestLogContext::new(req.txid, self.storage.as_ref());
+            info! {"Resuming {} deferred bid payments of request {}", liability.bids.len(), req.txid};
+            let mut bids = self.storage.get_bids(req.txid)?;
+            req.payment_state = self.pay_request_bids(req.txid, &mut bids)?;
+            info! {"Request payment state: {}", req.payment_state};
+            req.is_payment_complete = req.payment_state.is_complete();
+            self.storage.update_request(&req)?;
+        }
+        Ok(())
+    }
+
+    /// Method that handles payments for a single request, fetching bid
+    /// information, calculating fees, updating payment information and doing
+    /// payments. Requests are marked as payment complete unless payments
+    /// failed, with the payment state recording whether bids were paid or
+    /// payment was not required. Requests with response snapshots are paid
+    /// per epoch
+    fn do_request_payment(&self, request: &mut Request) -> Result<()> {
+        let _log_context = RequestLogContext::new(request.txid, self.storage.as_ref());
+
+        let finished = request.end_blockheight_clientchain != 0
+            && (self.client.get_block_count()? as u32) >= request.end_blockheight_clientchain;
+        let snapshots = self.storage.get_response_snapshots(request.txid)?;
+        if snapshots.len() > 0 {
+            return self.do_epoch_payments(request, snapshots, finished);
+        }
+
+        // skip requests that have not finished
+        if !finished {
+            warn! {"Skipping unfinished request: {}", request.txid};
+            return Ok(());
+        }
+
+        // fetch bids, responses, update payment info and do payments
+        let mut bids = self.storage.get_bids(request.txid)?;
+        let mut payment_state = PaymentState::NotRequired;
+        if bids.len() > 0 {
+            if let Some(resp) = self.storage.get_response(request.txid)? {
+                let fee_percentage = self.fee_percentage.unwrap_or(request.fee_percentage);
+                self.calculate_request_fees(request, fee_percentage)?;
+                for fee in request.fees.iter() {
+                    info! {"service fees ({}): {}", fee.asset, fee.amount};
+                }
+                let fees_amount = total_fees(&request.fees);
+                info! {"total service fees: {}", fees_amount};
+                let bid_payment_amount = calculate_bid_payment(&fees_amount, fee_percentage.into(), bids.len() as u64)?;
+                info! {"num bids: {}", bids.len()};
+                info! {"fees per bid: {} ({}%)", bid_payment_amount, fee_percentage};
+                let latency = self
+                    .storage
+                    .get_challenge_latency(request.txid)?
+                    .unwrap_or(ChallengeLatency::new());
+                self.process_bid_payments(
+                    request.txid,
+                    None,
+                    &mut bids,
+                    FeeShare {
+                        amount: fees_amount,
+                        percentage: fee_percentage,
+                    },
+                    &resp,
+                    &latency.bid_proof_ms,
+                )?;
+                payment_state = self.pay_request_bids(request.txid, &mut bids)?;
+            }
+        }
+
+        // update request with payment complete
+        info! {"Request payment state: {}", payment_state};
+        request.payment_state = payment_state;
+        request.is_payment_complete = payment_state.is_complete();
+        self.storage.update_request(request)?;
+        Ok(())
+    }
+
+    /// Method that handles payments for a request paid per epoch. Once the
+    /// request has finished, the snapshot of the final epoch covering the rest
+    /// of the request is stored and the request is marked as payment complete
+    /// unless payments of any epoch failed, with the payment state derived
+    /// from the bid payments of all epochs
+    fn do_epoch_payments(
+        &self,
+        request: &mut Request,
+        mut snapshots: Vec<ResponseSnapshot>,
+        finished: bool,
+    ) -> Result<()> {
+        if finished {
+            if let Some(response) = self.storage.get_response(request.txid)? {
+                let bids = self.storage.get_bids(request.txid)?;
+                if let Some(snapshot) = final_epoch_snapshot(request, &snapshots, response, bids) {
+                    self.storage.save_response_snapshot(request.txid, &snapshot)?;
+                    snapshots.push(snapshot);
+                }
+            }
+        }
+
+        let fee_percentage = self.fee_percentage.unwrap_or(request.fee_percentage);
+        let latency = self
+            .storage
+            .get_challenge_latency(request.txid)?
+            .unwrap_or(ChallengeLatency::new());
+        let mut payout = PayoutResult::Complete;
+        let mut prev_response = Response::new();
+        let mut fees_start_height = request.start_blockheight_clientchain;
+        for snapshot in snapshots.iter_mut() {
+            if !snapshot.is_payment_complete {
+                payout = payout.and(self.do_epoch_payment(
+                    request.txid,
+                    snapshot,
+                    &prev_response,
+                    fees_start_height,
+                    fee_percentage,
+                    &latency,
+                )?);
+            }
+            prev_response = snapshot.response.clone();
+            fees_start_height = snapshot.clientchain_height + 1;
+        }
+
+        if finished {
+            // fee totals of the whole request, as epoch fees are not stored
+            if request.fees.len() == 0 {
+                self.calculate_request_fees(request, fee_percentage)?;
+            }
+            let bids: Vec<Bid> = snapshots
+                .iter()
+                .flat_map(|snapshot| snapshot.bids.iter().cloned())
+                .collect();
+            request.payment_state = request_payment_state(self.do_payment, &bids, payout);
+            info! {"Request payment state: {}", request.payment_state};
+            request.is_payment_complete = request.payment_state.is_complete();
+            self.storage.update_request(request)?;
+        }
+        Ok(())
+    }
+
+    /// Method that handles payments for a single epoch. The fees of the client
+    /// chain blocks within the epoch are split between bids based on their
+    /// responses to the challenges within the epoch, weighted by the proof
+    /// latencies of the epoch responses if latency weights are set. Returns the
+    /// payout outcome of the epoch
+    fn do_epoch_payment(
+        &self,
+        request_hash: sha256d::Hash,
+        snapshot: &mut ResponseSnapshot,
+        prev_response: &Response,
+        fees_start_height: u32,
+        fee_percentage: u32,
+        latency: &ChallengeLatency,
+    ) -> Result<PayoutResult> {
+        let epoch_response = snapshot.response.since(prev_response);
+        if epoch_response.num_challenges > 0
+            && snapshot.bids.len() > 0
+            && fees_start_height <= snapshot.clientchain_height
+        {
+            let fees = calculate_fees(
+                fees_start_height,
+                snapshot.clientchain_height,
+                &self.client,
+                &self.fee_assets,
+                &self.fee_filter,
+            )?;
+            for fee in fees.iter() {
+                info! {"epoch {} service fees ({}): {}", snapshot.epoch, fee.asset, fee.amount};
+            }
+            let fees_amount = total_fees(&fees);
+            info! {"epoch {} total service fees: {}", snapshot.epoch, fees_amount};
+            let bid_payment_amount =
+                calculate_bid_payment(&fees_amount, fee_percentage.into(), snapshot.bids.len() as u64)?;
+            info! {"epoch {} fees per bid: {} ({}%)", snapshot.epoch, bid_payment_amount, fee_percentage};
+            self.process_bid_payments(
+                request_hash,
+                Some(snapshot.epoch),
+                &mut snapshot.bids,
+                FeeShare {
+                    amount: fees_amount,
+                    percentage: fee_percentage,
+                },
+                &epoch_response,
+                &latency.bid_proof_ms_since(prev_response, &snapshot.response),
+            )?;
+        }
+
+        let mut payout = PayoutResult::Complete;
+        if self.do_payment {
+            let mut stored = snapshot.clone();
+            stored.is_payment_complete = false;
+            let storage = &self.storage;
+            let epoch = Some(snapshot.epoch);
+            payout = self.pay_bids(request_hash, epoch, &mut snapshot.bids, &mut |bid: &Bid| {
+                if let Some(stored_bid) = stored.bids.iter_mut().find(|stored_bid| stored_bid.txid == bid.txid) {
+                    *stored_bid = bid.clone();
+                }
+                storage.save_response_snapshot(request_hash, &stored)
+            })?;
+            let _ = self.unconfirmed.lock().unwrap().insert(request_hash);
+        }
+        snapshot.is_payment_complete = payout == PayoutResult::Complete;
+        self.storage.save_response_snapshot(request_hash, snapshot)?;
+        Ok(payout)
+    }
+
+    /// Pay any unpaid epochs of requests that are still running
+    fn do_active_epoch_payments(&self) -> Result<()> {
+        for mut req in self
+            .storage
+            .get_requests(Some(false), Some(self.genesis_hash), None, None)?
+        {
+            let snapshots = self.storage.get_response_snapshots(req.txid)?;
+            if snapshots.iter().any(|snapshot| !snapshot.is_payment_complete) {
+                let _log_context = RequestLogContext::new(req.txid, self.storage.as_ref());
+                self.do_epoch_payments(&mut req, snapshots, false)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Update the confirmations of the payment transactions of a bid that have
+    /// not reached the final number of confirmations. Returns whether any
+    /// confirmations were updated
+    fn update_bid_confirmations(&self, bid: &mut Bid) -> Result<bool> {
+        let mut updated = false;
+        if let Some(payment) = bid.payment.as_mut() {
+            for tx in payment.txs.iter_mut() {
+                if tx.confirmations >= PAYMENTS_CONFIRMATIONS_FINAL {
                     continue;
                 }
-                info!("payment to {} for {}", &bid_payment.address, bid_payment.amount);
-                if use_sendany {
-                    match self.client.send_any_to_address(
-                        &bid_payment.address,
-                        bid_payment.amount,
-                        None,
-                        None,
-                        None,
-                        Some(true),
-                        None,
-                    ) {
-                        Ok(res) => match res {
-                            SendAnyToAddressResult::Txid(txid) => {
-                                bid_payment.txid = Some(txid);
-                                info!("payment (ANY) txid {}", txid);
-                            }
-                            SendAnyToAddressResult::Txids(txids) => {
-                                bid_payment.txid = Some(txids[0]);
-                                bid_payment.extra_txids = Some(txids[1..].to_vec());
-                                info!("payment (ANY) txids {:?}", txids);
-                            }
-                        },
-                        Err(err) => {
-                            warn!("bid payment (send_any_to_address) failed: {}", err);
-                            success = false; // mark that payments failed but
-                                             // keep going
-                        }
-                    }
-                } else {
-                    match self.client.send_to_address(
-                        &bid_payment.address,
-                        bid_payment.amount,
-                        None,
-                        None,
-                        Some(false),
-                        Some(&self.payment_asset),
-                    ) {
-                        Ok(txid) => {
-                            bid_payment.txid = Some(txid);
-                            info!("payment ({}) txid {}", &self.payment_asset, txid);
-                        }
-                        Err(err) => {
-                            warn!("bid payment (send_to_address) failed: {}", err);
-                            success = false; // mark that payments failed but
-                                             // keep going
-                        }
-                    }
+                let wallet_tx = self
+                    .client
+                    .call::<Value>("gettransaction", &[Value::from(tx.txid.to_string())])?;
+                let confirmations = wallet_tx["confirmations"].as_i64().unwrap_or(0).max(0) as u32;
+                if confirmations != tx.confirmations {
+                    tx.confirmations = confirmations;
+                    updated = true;
                 }
             }
         }
-        Ok(success)
+        Ok(updated)
     }
 
-    /// Process bid payments method handles calculating the payment to be
-    /// received per bid and on which address, and updates the corresponding
-    /// payment info in Storage
-    fn process_bid_payments(&self, bids: &mut Vec<Bid>, bid_payment: &Amount, response: &Response) -> Result<()> {
-        for bid in bids {
-            if let Some(bid_resp) = response.bid_responses.get(&bid.txid) {
-                // correct bid payment by calculating the performance
-                // base on successful responses / total responses
-                let bid_payment_corrected = *bid_payment * (*bid_resp).into() / response.num_challenges.into();
-                let bid_pay_to_addr = Address::p2pkh(
-                    &PublicKey {
-                        key: bid.pubkey,
-                        compressed: true,
-                    },
-                    None,
-                    self.addr_params,
-                );
+    /// Update the confirmations of the bid payments of a request, including
+    /// payments of the request epoch snapshots. Returns whether any payment
+    /// transactions remain below the final number of confirmations
+    fn update_payment_confirmations(&self, request_hash: sha256d::Hash) -> Result<bool> {
+        let mut unconfirmed = false;
+        let mut updated_bids = vec![];
+        for mut bid in self.storage.get_bids(request_hash)? {
+            if self.update_bid_confirmations(&mut bid)? {
+                updated_bids.push(bid.clone());
+            }
+            unconfirmed = unconfirmed || has_unconfirmed_payment(&bid);
+        }
+        self.storage.update_bids(request_hash, &updated_bids)?;
+        for mut snapshot in self.storage.get_response_snapshots(request_hash)? {
+            let mut updated = false;
+            for bid in snapshot.bids.iter_mut() {
+                updated = self.update_bid_confirmations(bid)? || updated;
+                unconfirmed = unconfirmed || has_unconfirmed_payment(bid);
+            }
+            if updated {
+                self.storage.save_response_snapshot(request_hash, &snapshot)?;
+            }
+        }
+        Ok(unconfirmed)
+    }
 
-                bid.payment = Some(BidPayment {
-                    amount: bid_payment_corrected,
-                    address: bid_pay_to_addr,
-                    txid: None,
-                    extra_txids: None,
-                });
+    /// Track the confirmations of the payments of requests with unconfirmed
+    /// payment transactions, until all reach the final number of confirmations
+    fn do_payment_confirmations(&self) -> Result<()> {
+        let request_hashes: Vec<sha256d::Hash> = self.unconfirmed.lock().unwrap().iter().cloned().collect();
+        for request_hash in request_hashes {
+            if !self.update_payment_confirmations(request_hash)? {
+                let _ = self.unconfirmed.lock().unwrap().remove(&request_hash);
             }
         }
         Ok(())
     }
 
-    /// Method that handles payments for a single request, fetching bid
-    /// information, calculating fees, updating payment information and doing
-    /// payments. Requests are marked as payment complete if payments are done
-    /// successfully or if the coordinator does not handle payments
-    fn do_request_payment(&self, request: &mut Request) -> Result<()> {
-        // skip requests that have not finished
-        if request.end_blockheight_clientchain == 0
-            || (self.client.get_block_count()? as u32) < request.end_blockheight_clientchain
-        {
-            warn! {"Skipping unfinished request: {}", request.txid};
+    /// Match the exported bid payments of unpaid requests to the watched
+    /// transactions paying the bid addresses in watch-only mode. Exported
+    /// requests are marked as paid once the intents of all their bid payments
+    /// have been resolved, with the confirmations of the matched transactions
+    /// tracked from then on as with wallet payments
+    fn do_exported_payments(&self) -> Result<()> {
+        if self.payment_mode != PaymentMode::WatchOnly {
             return Ok(());
         }
+        for mut req in self
+            .storage
+            .get_requests(Some(false), Some(self.genesis_hash), None, None)?
+        {
+            let _log_context = RequestLogContext::new(req.txid, self.storage.as_ref());
+            if self.reconcile_payment_intents(&req)? > 0 || req.payment_state != PaymentState::Exported {
+                continue;
+            }
+            info! {"Exported payments of request {} paid", req.txid};
+            req.payment_state = PaymentState::Paid;
+            req.is_payment_complete = true;
+            self.storage.update_request(&req)?;
+            let _ = self.unconfirmed.lock().unwrap().insert(req.txid);
+        }
+        Ok(())
+    }
 
-        // fetch bids, responses, update payment info and do payments
-        let mut bids = self.storage.get_bids(request.txid)?;
-        let mut payment_complete = true;
-        if bids.len() > 0 {
-            if let Some(resp) = self.storage.get_response(request.txid)? {
-                let fees_amount = calculate_fees(request, &self.client)?;
-                info! {"total service fees: {}", fees_amount};
-                let bid_payment_amount =
-                    calculate_bid_payment(&fees_amount, request.fee_percentage.into(), bids.len() as u64)?;
-                info! {"num bids: {}", bids.len()};
-                info! {"fees per bid: {} ({}%)", bid_payment_amount, request.fee_percentage};
-                self.process_bid_payments(&mut bids, &bid_payment_amount, &resp)?;
-                if self.do_payment {
-                    payment_complete = self.complete_bid_payments(&mut bids)?
-                }
+    /// Check whether payments and compaction are deferred as a maintenance
+    /// window is in progress
+    fn in_maintenance(&self) -> bool {
+        self.maintenance.defers_work(self.clock.as_ref())
+    }
 
-                // update bids with payment information
-                for bid in bids {
-                    self.storage.update_bid(request.txid, &bid)?;
-                }
+    /// Run the response compaction job if a compaction age has been set,
+    /// deferring it until the end of any maintenance window in progress
+    fn do_response_compaction(&self) -> Result<()> {
+        if self.compaction_age.is_some() && self.in_maintenance() {
+            self.compaction_deferred.store(true, Ordering::SeqCst);
+            return Ok(());
+        }
+        if let Some(age) = self.compaction_age {
+            let height = self.client.get_block_count()? as u32;
+            let compacted = compact_responses(self.storage.as_ref(), self.genesis_hash, height, age)?;
+            if compacted > 0 {
+                info! {"Compacted {} request responses", compacted};
             }
         }
+        Ok(())
+    }
 
-        // update request with payment complete
-        request.is_payment_complete = payment_complete;
-        self.storage.update_request(request)?;
+    /// Pay the finished requests found in storage that have not been picked up
+    /// for payment yet, regardless of the coordinator process that finished
+    /// them, so that deployments running payments apart from the challenger
+    /// pay requests without waiting for a restart. The storage driver has no
+    /// support for change streams, so storage is polled instead. Each request
+    /// is picked up once, with failed payments retried on restart as with
+    /// requests received via the request channel
+    fn do_watched_request_payments(&self) -> Result<()> {
+        let height = self.client.get_block_count()? as u32;
+        for mut req in self
+            .storage
+            .get_requests(Some(false), Some(self.genesis_hash), None, None)?
+        {
+            if req.end_blockheight_clientchain == 0
+                || req.end_blockheight_clientchain > height
+                || !self.watched.lock().unwrap().insert(req.txid)
+            {
+                continue;
+            }
+            info! {"Found finished request: {}", req.txid};
+            self.do_request_payment_or_retry(&mut req)?;
+            self.do_response_compaction()?;
+        }
+        Ok(())
+    }
+
+    /// Pay a finished request, leaving the request to be retried with the
+    /// periodic payment checks if payment fails with a transient error or is
+    /// deferred by a maintenance window
+    fn do_request_payment_or_retry(&self, request: &mut Request) -> Result<()> {
+        if self.in_maintenance() {
+            info! {"Payment of request {} deferred for maintenance", request.txid};
+            let _ = self.retried.lock().unwrap().insert(request.txid);
+            return Ok(());
+        }
+        if skip_transient("request payment", self.do_request_payment(request))?.is_none() {
+            let _ = self.retried.lock().unwrap().insert(request.txid);
+        }
+        Ok(())
+    }
+
+    /// Pay the requests left to be retried, which include newly finished
+    /// requests. Requests are only removed once fetched from storage
+    fn do_retried_payments(&self) -> Result<()> {
+        let retried: Vec<sha256d::Hash> = self.retried.lock().unwrap().iter().cloned().collect();
+        for request_hash in retried {
+            let request = self.storage.get_request(request_hash)?;
+            let _ = self.retried.lock().unwrap().remove(&request_hash);
+            if let Some(mut req) = request {
+                self.do_request_payment_or_retry(&mut req)?;
+            }
+        }
         Ok(())
     }
 
     /// Main Request payments method; first checks for any incomplete requests
-    /// and then listens for new requests on the receiver channel
+    /// and then listens for new requests on the receiver channel, running
+    /// periodic payment checks in between. Operations failing with transient
+    /// errors are retried, with only permanent errors stopping payments
     fn do_request_payments(
         &self,
         req_recv: Receiver<sha256d::Hash>,
         mut kill_recv: oneshot::Receiver<()>,
     ) -> Result<()> {
         // Look for incomplete requests
-        let incomplete_requests = self.storage.get_requests(Some(false), None, None)?;
+        let incomplete_requests = self
+            .storage
+            .get_requests(Some(false), Some(self.genesis_hash), None, None)?;
         for mut req in incomplete_requests {
             info! {"Found incomplete request: {} ", req.txid};
-            let _ = self.do_request_payment(&mut req)?;
+            if req.end_blockheight_clientchain != 0 {
+                let _ = self.watched.lock().unwrap().insert(req.txid);
+            }
+            let _ = self.reconcile_payment_intents(&req)?;
+            self.do_request_payment_or_retry(&mut req)?;
+        }
+        self.do_response_compaction()?;
+
+        // Track confirmations of any payments of previous runs
+        for req in self.storage.get_requests(None, Some(self.genesis_hash), None, None)? {
+            let _ = self.unconfirmed.lock().unwrap().insert(req.txid);
+        }
+        self.do_payment_confirmations()?;
+
+        // Periodically check unpaid epochs and payment confirmations
+        let mut scheduler = Scheduler::with_clock(self.clock.as_ref());
+        let _ = scheduler.add(
+            "epoch payments",
+            Schedule::Delayed(Duration::from_secs(PAYMENTS_EPOCH_CHECK_INTERVAL)),
+            || {
+                if self.in_maintenance() {
+                    return Ok(JobStatus::Continue);
+                }
+                if self.compaction_deferred.swap(false, Ordering::SeqCst) {
+                    let _ = skip_transient("response compaction", self.do_response_compaction())?;
+                }
+                let _ = skip_transient("request payment retries", self.do_retried_payments())?;
+                let _ = skip_transient("epoch payments", self.do_active_epoch_payments())?;
+                let _ = skip_transient("deferred payments", self.do_deferred_payments())?;
+                let _ = skip_transient("exported payments", self.do_exported_payments())?;
+                let _ = skip_transient("payment confirmations", self.do_payment_confirmations())?;
+                Ok(JobStatus::Continue)
+            },
+        );
+        if let Some(interval) = self.watch_interval {
+            let _ = scheduler.add(
+                "finished requests watch",
+                Schedule::Delayed(Duration::from_secs(interval)),
+                || {
+                    let _ = skip_transient("finished requests watch", self.do_watched_request_payments())?;
+                    Ok(JobStatus::Continue)
+                },
+            );
         }
 
         // Wait for new requests
         loop {
             match req_recv.recv_timeout(Duration::from_millis(100)) {
                 Ok(resp) => {
-                    let mut req = self.storage.get_request(resp)?.unwrap();
-                    info! {"New request: {}", req.txid};
-                    let _ = self.do_request_payment(&mut req)?;
+                    info! {"New request: {}", resp};
+                    let _ = self.watched.lock().unwrap().insert(resp);
+                    let _ = self.retried.lock().unwrap().insert(resp);
+                    let _ = skip_transient("request payment", self.do_retried_payments())?;
+                    let _ = skip_transient("response compaction", self.do_response_compaction())?;
                 }
-                Err(RecvTimeoutError::Timeout) => {} // ignore timeout - it's allowed
+                Err(RecvTimeoutError::Timeout) => scheduler.run_pending()?,
                 Err(RecvTimeoutError::Disconnected) => {
                     return Err(Error::from(CError::ReceiverDisconnected));
                 }
@@ -263,18 +1864,46 @@ impl Payments {
     /// Return new Payments instance that requires clientchain config for
     /// various payment info and rpc calls to calculate payment fees and do the
     /// payments as well as a thread-safe reference to a Storage instance for
-    /// getting request information and updating payment details
-    pub fn new(config: ClientChainConfig, storage: Arc<dyn Storage + Send + Sync>) -> Result<Payments> {
-        let client = OceanClient::new(
-            config.host.clone(),
-            Some(config.user.clone()),
-            Some(config.pass.clone()),
-        )?;
+    /// getting request information and updating payment details. Only requests
+    /// for the clientchain genesis hash are paid and optionally compacted. The
+    /// clientchain address params are looked up in the address params registry.
+    /// Storage is checked for finished requests every watch interval of the
+    /// settings, if set, and bid responses are weighted by the latency weights
+    /// of the settings, if any. In
+    /// watch-only payment mode no payment key is imported and bid payments are
+    /// exported instead, while in wallet mode bids may be paid partially as far
+    /// as the wallet funds allow. Payments and compaction are deferred during
+    /// maintenance windows
+    pub fn new(
+        config: ClientChainConfig,
+        storage: Arc<dyn Storage + Send + Sync>,
+        settings: PaymentSettings,
+        addr_params_registry: &AddrParamsRegistry,
+        journal: Arc<Journal>,
+        maintenance: Arc<MaintenanceWindows>,
+    ) -> Result<Payments> {
+        let PaymentSettings {
+            compaction_age,
+            watch_interval,
+            latency_weights,
+        } = settings;
+        let client = OceanClient::with_credentials(config.host.clone(), config.rpc_credentials())?;
+
+        let genesis_hash = sha256d::Hash::from_hex(&config.genesis_hash)?;
+        let address_type = PayoutAddressType::from_str(&config.payment_address_type)?;
+        let payment_mode = PaymentMode::from_str(&config.payment_mode)?;
+        let export_format = PaymentExportFormat::from_str(&config.payment_export_format)?;
+        let payout_order = PayoutOrder::from_str(&config.payout_order)?;
+        let fee_filter = config.fee_filter();
 
         // Check if payment addr/key are set and import the key for payment funds
-        let addr_params = get_chain_addr_params(&config.chain);
+        let addr_params = addr_params_registry.get(&config.chain);
         let mut do_payment = false;
-        if let Some(addr) = &config.payment_addr {
+        if payment_mode == PaymentMode::WatchOnly {
+            // payments exported for an external wallet, without any key
+            info!("watch-only payments exported to {}", config.payment_export_dir);
+            do_payment = true;
+        } else if let Some(addr) = &config.payment_addr {
             let ocean_addr = Address::from_str(&addr)?;
             if *ocean_addr.params != *addr_params {
                 warn!("payment addr and chain config addr param mismatch");
@@ -297,8 +1926,28 @@ impl Payments {
             storage,
             client,
             addr_params,
+            address_type,
             payment_asset: config.payment_asset,
+            fee_filter,
+            fee_assets: config.fee_assets,
             do_payment,
+            genesis_hash,
+            fee_percentage: config.fee_percentage,
+            compaction_age,
+            unconfirmed: Mutex::new(HashSet::new()),
+            watch_interval,
+            watched: Mutex::new(HashSet::new()),
+            retried: Mutex::new(HashSet::new()),
+            journal,
+            latency_weights,
+            payment_mode,
+            export_dir: config.payment_export_dir,
+            export_format,
+            partial_payouts: config.partial_payouts,
+            payout_order,
+            clock: Arc::new(SystemClock),
+            maintenance,
+            compaction_deferred: AtomicBool::new(false),
         })
     }
 }
@@ -309,8 +1958,19 @@ pub fn run_payments<'a>(
     clientchain_config: ClientChainConfig,
     storage: Arc<dyn Storage + Send + Sync>,
     req_recv: Receiver<sha256d::Hash>,
+    settings: PaymentSettings,
+    addr_params_registry: &AddrParamsRegistry,
+    journal: Arc<Journal>,
+    maintenance: Arc<MaintenanceWindows>,
 ) -> Result<Handle<'a>> {
-    let payments = Payments::new(clientchain_config, storage)?;
+    let payments = Payments::new(
+        clientchain_config,
+        storage,
+        settings,
+        addr_params_registry,
+        journal,
+        maintenance,
+    )?;
     let (tx, rx) = oneshot::channel();
     let (err_tx, err_rx) = oneshot::channel();
     Ok(Handle::new(
@@ -330,7 +1990,826 @@ pub fn run_payments<'a>(
 mod tests {
     use super::*;
 
-    use crate::util::testing::setup_logger;
+    use std::cell::RefCell;
+    use std::iter;
+
+    use bitcoin::hashes::Hash;
+
+    use crate::interfaces::mocks::clock::{MockClock, MOCK_CLOCK_UNIX_START};
+    use crate::interfaces::mocks::storage::MockStorage;
+    use crate::util::testing::{gen_challenge_state, gen_dummy_hash, setup_logger};
+
+    #[test]
+    fn compact_responses_test() {
+        setup_logger();
+        let storage = MockStorage::new();
+        let genesis_hash = gen_dummy_hash(0);
+
+        // paid request ending at height 10 with responses
+        let mut state = gen_challenge_state(&gen_dummy_hash(1));
+        state.request.end_blockheight_clientchain = 10;
+        state.request.is_payment_complete = true;
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let mut response = Response::new();
+        response.num_challenges = 4;
+        let _ = response.bid_responses.insert(gen_dummy_hash(5), 3);
+        let _ = response.bid_responses.insert(gen_dummy_hash(6), 1);
+        storage.save_response(state.request.txid, &response).unwrap();
+
+        // unpaid request with responses is never compacted
+        let mut state_unpaid = gen_challenge_state(&gen_dummy_hash(2));
+        state_unpaid.request.end_blockheight_clientchain = 5;
+        storage
+            .save_challenge_request_state(&state_unpaid.request, &state_unpaid.bids)
+            .unwrap();
+        storage.save_response(state_unpaid.request.txid, &response).unwrap();
+
+        // request not old enough
+        assert_eq!(0, compact_responses(&storage, genesis_hash, 14, 5).unwrap());
+        assert_eq!(
+            None,
+            storage
+                .get_request(state.request.txid)
+                .unwrap()
+                .unwrap()
+                .response_summary
+        );
+        assert_eq!(2, storage.challenge_responses.borrow().len());
+
+        // other genesis hash
+        assert_eq!(0, compact_responses(&storage, gen_dummy_hash(9), 15, 5).unwrap());
+
+        // request compacted
+        assert_eq!(1, compact_responses(&storage, genesis_hash, 15, 5).unwrap());
+        assert_eq!(
+            Some(response.summary()),
+            storage
+                .get_request(state.request.txid)
+                .unwrap()
+                .unwrap()
+                .response_summary
+        );
+        assert_eq!(None, storage.get_response(state.request.txid).unwrap());
+        assert!(storage.get_response(state_unpaid.request.txid).unwrap().is_some());
+
+        // already compacted
+        assert_eq!(0, compact_responses(&storage, genesis_hash, 100, 5).unwrap());
+    }
+
+    #[test]
+    fn payment_schedule_test() {
+        setup_logger();
+        let mut request = gen_challenge_state(&gen_dummy_hash(1)).request;
+        request.start_blockheight = 10;
+        request.end_blockheight = 20;
+
+        assert_eq!(vec![20], payment_schedule(&request, None));
+        assert_eq!(vec![14, 18, 20], payment_schedule(&request, Some(4)));
+        assert_eq!(vec![15, 20], payment_schedule(&request, Some(5)));
+        assert_eq!(vec![20], payment_schedule(&request, Some(10)));
+        assert_eq!(10, payment_schedule(&request, Some(0)).len());
+    }
+
+    #[test]
+    fn final_epoch_snapshot_test() {
+        setup_logger();
+        let state = gen_challenge_state(&gen_dummy_hash(1));
+        let mut request = state.request.clone();
+        request.end_blockheight_clientchain = 100;
+        let mut bids: Vec<Bid> = state.bids.iter().cloned().collect();
+        bids[0].payment = Some(BidPayment {
+            txs: vec![BidPaymentTx {
+                txid: gen_dummy_hash(9),
+                amount: Amount::from_sat(100),
+                confirmations: 1,
+            }],
+            address: Address::from_str("CMAMyHorv18WKKTvg5Kifi8ap8CuSJUxXT").unwrap(),
+            address_type: PayoutAddressType::P2pkh,
+            amount: Amount::from_sat(100),
+            intent: None,
+            basis: None,
+        });
+        let mut response = Response::new();
+        response.num_challenges = 3;
+
+        // no epoch snapshots
+        assert_eq!(
+            None,
+            final_epoch_snapshot(&request, &[], response.clone(), bids.clone())
+        );
+
+        // last epoch snapshot before the end of the request
+        let snapshot = ResponseSnapshot {
+            epoch: 0,
+            service_height: 3,
+            clientchain_height: 60,
+            response: Response::new(),
+            bids: vec![],
+            is_payment_complete: true,
+        };
+        let final_snapshot =
+            final_epoch_snapshot(&request, &[snapshot.clone()], response.clone(), bids.clone()).unwrap();
+        assert_eq!(1, final_snapshot.epoch);
+        assert_eq!(request.end_blockheight, final_snapshot.service_height);
+        assert_eq!(100, final_snapshot.clientchain_height);
+        assert_eq!(response, final_snapshot.response);
+        assert_eq!(None, final_snapshot.bids[0].payment);
+        assert!(!final_snapshot.is_payment_complete);
+
+        // final epoch snapshot already stored
+        assert_eq!(
+            None,
+            final_epoch_snapshot(&request, &[snapshot, final_snapshot], response, bids)
+        );
+    }
+
+    #[test]
+    fn resolve_payment_intents_test() {
+        setup_logger();
+        let bid_template = Bid {
+            txid: gen_dummy_hash(1),
+            pubkey: bitcoin::secp256k1::PublicKey::from_str(
+                "026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3",
+            )
+            .unwrap(),
+            payment: Some(BidPayment {
+                txs: vec![],
+                address: Address::from_str("CMAMyHorv18WKKTvg5Kifi8ap8CuSJUxXT").unwrap(),
+                address_type: PayoutAddressType::P2pkh,
+                amount: Amount::from_sat(100),
+                intent: None,
+                basis: None,
+            }),
+        };
+
+        // bid without intent, bid with resolved intent and bid with unresolved intent
+        let mut bid_no_intent = bid_template.clone();
+        bid_no_intent.txid = gen_dummy_hash(1);
+        let mut bid_resolved = bid_template.clone();
+        bid_resolved.txid = gen_dummy_hash(2);
+        let intent_resolved = BidPayment::new_intent(&bid_resolved.txid, &Amount::from_sat(100), 5);
+        bid_resolved.payment.as_mut().unwrap().intent = Some(intent_resolved.clone());
+        let mut bid_unresolved = bid_template.clone();
+        bid_unresolved.txid = gen_dummy_hash(3);
+        let intent_unresolved = BidPayment::new_intent(&bid_unresolved.txid, &Amount::from_sat(100), 6);
+        bid_unresolved.payment.as_mut().unwrap().intent = Some(intent_unresolved.clone());
+        assert!(!has_pending_intent(&bid_no_intent));
+        assert!(has_pending_intent(&bid_resolved));
+        assert!(has_pending_intent(&bid_unresolved));
+
+        let wallet_txs = vec![
+            BidPaymentTx {
+                txid: gen_dummy_hash(22),
+                amount: Amount::from_sat(60),
+                confirmations: 1,
+            },
+            BidPaymentTx {
+                txid: gen_dummy_hash(23),
+                amount: Amount::from_sat(40),
+                confirmations: 0,
+            },
+        ];
+        let mut wallet_intents = HashMap::new();
+        let _ = wallet_intents.insert(intent_resolved, wallet_txs.clone());
+        let mut bids = vec![bid_no_intent.clone(), bid_resolved, bid_unresolved.clone()];
+        assert_eq!(1, resolve_payment_intents(&mut bids, &wallet_intents));
+        assert_eq!(bid_no_intent, bids[0]);
+        assert_eq!(wallet_txs, bids[1].payment.as_ref().unwrap().txs);
+        assert_eq!(None, bids[1].payment.as_ref().unwrap().intent);
+        assert!(!has_pending_intent(&bids[1]));
+        assert_eq!(bid_unresolved, bids[2]);
+        assert!(has_pending_intent(&bids[2]));
+    }
+
+    /// Generate a bid with a payment of the given amount to the given address
+    /// and an optional payment intent
+    fn gen_paid_bid(txid: sha256d::Hash, address: &str, amount: u64, intent: Option<&str>) -> Bid {
+        let state = gen_challenge_state(&gen_dummy_hash(1));
+        let mut bid = state.bids.iter().next().unwrap().clone();
+        bid.txid = txid;
+        bid.payment = Some(BidPayment {
+            txs: vec![],
+            address: Address::from_str(address).unwrap(),
+            address_type: PayoutAddressType::P2pkh,
+            amount: Amount::from_sat(amount),
+            intent: intent.map(|intent| intent.to_owned()),
+            basis: None,
+        });
+        bid
+    }
+
+    #[test]
+    fn payment_batch_test() {
+        setup_logger();
+        let addr = "CMAMyHorv18WKKTvg5Kifi8ap8CuSJUxXT";
+        let other_addr = "2dt74vJqpNTM12pbiNpHGvry7tyvUu9tJR8";
+        let bids = vec![
+            gen_paid_bid(gen_dummy_hash(2), addr, 100, Some("a")),
+            gen_paid_bid(gen_dummy_hash(3), other_addr, 50, Some("b")),
+            gen_paid_bid(gen_dummy_hash(4), addr, 25, Some("c")),
+            // not exported
+            gen_paid_bid(gen_dummy_hash(5), other_addr, 10, None),
+        ];
+        let batch = PaymentBatch::new(gen_dummy_hash(1), Some(3), "CBT", &bids);
+        assert_eq!(3, batch.payments.len());
+        assert_eq!(
+            vec![(other_addr, 0.0000005), (addr, 0.00000125)],
+            batch
+                .amounts
+                .iter()
+                .map(|(address, amount)| (address.as_str(), *amount))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            format!("{}-3.json", gen_dummy_hash(1)),
+            batch.file_name(PaymentExportFormat::Json)
+        );
+        assert_eq!(
+            format!(
+                "ocean:{}?amount=0.00000050&label={}&asset=CBT\nocean:{}?amount=0.00000125&label={}&asset=CBT\n",
+                other_addr,
+                gen_dummy_hash(1),
+                addr,
+                gen_dummy_hash(1)
+            ),
+            batch.encode(PaymentExportFormat::Bip21)
+        );
+        let json: Value = serde_json::from_str(&batch.encode(PaymentExportFormat::Json)).unwrap();
+        assert_eq!(0.0000005, json["amounts"][other_addr].as_f64().unwrap());
+        assert_eq!("c", json["payments"][2]["intent"].as_str().unwrap());
+
+        // batch files replaced on export
+        let dir = std::env::temp_dir().join("coordinator_payment_batch_test");
+        let dir = dir.to_str().unwrap();
+        let _ = fs::remove_dir_all(dir);
+        let batch = PaymentBatch::new(gen_dummy_hash(1), None, "ANY", &bids);
+        let _ = write_payment_batch(dir, PaymentExportFormat::Bip21, &batch).unwrap();
+        let path = write_payment_batch(dir, PaymentExportFormat::Bip21, &batch).unwrap();
+        assert_eq!(
+            format!("{}.txt", gen_dummy_hash(1)),
+            path.file_name().unwrap().to_str().unwrap()
+        );
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(2, contents.lines().count());
+        assert!(!contents.contains("asset="));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn match_exported_payments_test() {
+        setup_logger();
+        let addr = "CMAMyHorv18WKKTvg5Kifi8ap8CuSJUxXT";
+        let other_addr = "2dt74vJqpNTM12pbiNpHGvry7tyvUu9tJR8";
+        let watched_tx = |txid: u32, address: &str, amount: u64| WatchedTx {
+            txid: gen_dummy_hash(txid),
+            address: address.to_owned(),
+            amount: Amount::from_sat(amount),
+            confirmations: 1,
+        };
+
+        // payments to the same address matched by their summed amount, while
+        // outputs of the wrong amount are not matched
+        let bids = vec![
+            gen_paid_bid(gen_dummy_hash(2), addr, 100, Some("a")),
+            gen_paid_bid(gen_dummy_hash(3), other_addr, 50, Some("b")),
+            gen_paid_bid(gen_dummy_hash(4), addr, 25, Some("c")),
+        ];
+        let watched = vec![
+            watched_tx(10, addr, 100),
+            watched_tx(11, addr, 125),
+            watched_tx(12, other_addr, 40),
+        ];
+        let intents = match_exported_payments(&[&bids[..]], &watched);
+        assert_eq!(2, intents.len());
+        assert_eq!(
+            vec![BidPaymentTx {
+                txid: gen_dummy_hash(11),
+                amount: Amount::from_sat(100),
+                confirmations: 1,
+            }],
+            intents["a"]
+        );
+        assert_eq!(Amount::from_sat(25), intents["c"][0].amount);
+        assert!(!intents.contains_key("b"));
+
+        // repeated payments to an address across epochs matched to distinct
+        // outputs, never to outputs already recorded as payments
+        let mut paid_epoch = vec![gen_paid_bid(gen_dummy_hash(2), addr, 100, None)];
+        paid_epoch[0].payment.as_mut().unwrap().txs = vec![BidPaymentTx {
+            txid: gen_dummy_hash(10),
+            amount: Amount::from_sat(100),
+            confirmations: 1,
+        }];
+        let epoch = vec![gen_paid_bid(gen_dummy_hash(2), addr, 100, Some("d"))];
+        let other_epoch = vec![gen_paid_bid(gen_dummy_hash(2), addr, 100, Some("e"))];
+        let watched = vec![watched_tx(10, addr, 100), watched_tx(13, addr, 100)];
+        let intents = match_exported_payments(&[&paid_epoch[..], &epoch[..], &other_epoch[..]], &watched);
+        assert_eq!(1, intents.len());
+        assert_eq!(gen_dummy_hash(13), intents["d"][0].txid);
+    }
+
+    /// Transaction sent by the mock wallet
+    struct MockWalletTx {
+        txid: sha256d::Hash,
+        address: String,
+        amount: Amount,
+        comment: Option<String>,
+    }
+
+    /// Mock wallet serving the wallet rpc calls of bid payments, recording the
+    /// payments sent and listing them back as wallet send transactions with
+    /// the comment they were sent with. Payments can be set to fail after being
+    /// broadcast, as with rpc timeouts of sends the wallet completed
+    #[derive(Default)]
+    struct MockWallet {
+        sent: RefCell<Vec<MockWalletTx>>,
+        fail_after_send: bool,
+    }
+
+    impl RpcApi for MockWallet {
+        fn call<T: for<'b> serde::de::Deserialize<'b>>(&self, cmd: &str, args: &[Value]) -> ocean_rpc::Result<T> {
+            let result = match cmd {
+                "sendtoaddress" => {
+                    let txid = sha256d::Hash::hash(&[self.sent.borrow().len() as u8]);
+                    self.sent.borrow_mut().push(MockWalletTx {
+                        txid,
+                        address: args[0].as_str().unwrap().to_owned(),
+                        amount: Amount::from_btc(args[1].as_f64().unwrap()).unwrap(),
+                        comment: args
+                            .get(2)
+                            .and_then(|comment| comment.as_str())
+                            .map(|comment| comment.to_owned()),
+                    });
+                    if self.fail_after_send {
+                        return Err(ocean_rpc::Error::from(serde_json::from_str::<Value>("").unwrap_err()));
+                    }
+                    Value::from(txid.to_string())
+                }
+                "listtransactions" => Value::Array(
+                    self.sent
+                        .borrow()
+                        .iter()
+                        .map(|tx| {
+                            serde_json::json!({
+                                "category": "send",
+                                "txid": tx.txid.to_string(),
+                                "amount": -tx.amount.as_btc(),
+                                "comment": tx.comment,
+                                "confirmations": 0,
+                            })
+                        })
+                        .collect(),
+                ),
+                _ => panic!("unexpected wallet rpc call {}", cmd),
+            };
+            Ok(serde_json::from_value(result)?)
+        }
+    }
+
+    /// Pay bids with the mock wallet, simulating a crash of the coordinator on
+    /// the persist call with the given index, if any. Returns the payment
+    /// outcome along with the bids as persisted when the payments stopped
+    fn pay_until_crash(wallet: &MockWallet, bids: &[Bid], crash_at: Option<usize>) -> (Result<bool>, Vec<Bid>) {
+        let mut persisted = bids.to_vec();
+        let mut bids = bids.to_vec();
+        let mut persists = 0;
+        let res = complete_bid_payments(
+            wallet,
+            "CBT",
+            &mut bids,
+            &[],
+            &mut |bid: &Bid| {
+                if crash_at == Some(persists) {
+                    return Err(Error::from(CError::Generic("crash".to_owned())));
+                }
+                persists += 1;
+                let persisted_bid = persisted
+                    .iter_mut()
+                    .find(|persisted_bid| persisted_bid.txid == bid.txid)
+                    .unwrap();
+                *persisted_bid = bid.clone();
+                Ok(())
+            },
+            &MockClock::new(),
+        );
+        (res, persisted)
+    }
+
+    #[test]
+    fn crash_safe_payments_test() {
+        setup_logger();
+        let bids = vec![
+            gen_paid_bid(gen_dummy_hash(2), "CMAMyHorv18WKKTvg5Kifi8ap8CuSJUxXT", 100, None),
+            gen_paid_bid(gen_dummy_hash(3), "2dt74vJqpNTM12pbiNpHGvry7tyvUu9tJR8", 50, None),
+            gen_paid_bid(gen_dummy_hash(4), "2dZRkPX3hrPtuBrmMkbGtxTxsuYYgAaFrXZ", 25, None),
+        ];
+
+        // every bid payment persists its intent before being sent and its
+        // outcome after, so crash right after the fee calculation, after each
+        // payment is sent but before its outcome is stored, after each payment
+        // outcome is stored and before the request payment state is updated
+        for crash_at in (0..bids.len() * 2).map(Some).chain(iter::once(None)) {
+            let wallet = MockWallet::default();
+            let (res, mut persisted) = pay_until_crash(&wallet, &bids, crash_at);
+            assert_eq!(crash_at.is_none(), res.is_ok());
+            if crash_at == Some(1) {
+                // intent attempt id taken from the clock
+                assert_eq!(
+                    Some(BidPayment::new_intent(
+                        &bids[0].txid,
+                        &Amount::from_sat(100),
+                        MOCK_CLOCK_UNIX_START * 1000
+                    )),
+                    persisted[0].payment.as_ref().unwrap().intent
+                );
+            }
+
+            // restart reconciling the persisted intents against the wallet
+            let wallet_intents = get_wallet_payment_intents(&wallet).unwrap();
+            assert_eq!(0, resolve_payment_intents(&mut persisted, &wallet_intents));
+            let (res, persisted) = pay_until_crash(&wallet, &persisted, None);
+            assert!(res.unwrap());
+
+            // each bid paid exactly once, with no bid skipped
+            let sent = wallet.sent.borrow();
+            assert_eq!(bids.len(), sent.len());
+            for bid in persisted.iter() {
+                let payment = bid.payment.as_ref().unwrap();
+                let bid_sent: Vec<&MockWalletTx> = sent
+                    .iter()
+                    .filter(|tx| tx.address == payment.address.to_string())
+                    .collect();
+                assert_eq!(1, bid_sent.len());
+                assert_eq!(payment.amount, bid_sent[0].amount);
+                assert_eq!(
+                    vec![bid_sent[0].txid],
+                    payment.txs.iter().map(|tx| tx.txid).collect::<Vec<_>>()
+                );
+                assert!(!has_pending_intent(bid));
+            }
+            assert_eq!(
+                PaymentState::Paid,
+                request_payment_state(true, &persisted, PayoutResult::Complete)
+            );
+        }
+
+        // crash after an intent is persisted but before the payment is sent
+        // leaves the intent unresolved, so the bid is held back instead of
+        // being paid again and the request payment is reported as failed
+        let wallet = MockWallet::default();
+        let mut persisted = bids.clone();
+        persisted[0].payment.as_mut().unwrap().intent =
+            Some(BidPayment::new_intent(&bids[0].txid, &Amount::from_sat(100), 1));
+        let wallet_intents = get_wallet_payment_intents(&wallet).unwrap();
+        assert_eq!(1, resolve_payment_intents(&mut persisted, &wallet_intents));
+        let (res, persisted) = pay_until_crash(&wallet, &persisted, None);
+        assert!(!res.unwrap());
+        assert_eq!(2, wallet.sent.borrow().len());
+        assert!(has_pending_intent(&persisted[0]));
+        assert!(persisted[1..].iter().all(|bid| bid.payment.as_ref().unwrap().is_paid()));
+        assert_eq!(
+            PaymentState::Failed,
+            request_payment_state(true, &persisted, PayoutResult::Failed)
+        );
+    }
+
+    #[test]
+    fn failed_send_payments_test() {
+        setup_logger();
+        let bids = vec![
+            gen_paid_bid(gen_dummy_hash(2), "CMAMyHorv18WKKTvg5Kifi8ap8CuSJUxXT", 100, None),
+            gen_paid_bid(gen_dummy_hash(3), "2dt74vJqpNTM12pbiNpHGvry7tyvUu9tJR8", 50, None),
+        ];
+
+        // payments broadcast by the wallet but reported as failed keep their
+        // intents pending and are not paid again before being reconciled
+        let wallet = MockWallet {
+            fail_after_send: true,
+            ..MockWallet::default()
+        };
+        let (res, persisted) = pay_until_crash(&wallet, &bids, None);
+        assert!(!res.unwrap());
+        assert_eq!(bids.len(), wallet.sent.borrow().len());
+        assert!(persisted.iter().all(|bid| has_pending_intent(bid)));
+        let (res, mut persisted) = pay_until_crash(&wallet, &persisted, None);
+        assert!(!res.unwrap());
+        assert_eq!(bids.len(), wallet.sent.borrow().len());
+
+        // reconciling against the wallet resolves the intents to the payments
+        // broadcast, so each bid is paid exactly once
+        let wallet_intents = get_wallet_payment_intents(&wallet).unwrap();
+        assert_eq!(0, resolve_payment_intents(&mut persisted, &wallet_intents));
+        let (res, persisted) = pay_until_crash(&wallet, &persisted, None);
+        assert!(res.unwrap());
+        let sent = wallet.sent.borrow();
+        assert_eq!(bids.len(), sent.len());
+        for bid in persisted.iter() {
+            let payment = bid.payment.as_ref().unwrap();
+            let bid_sent: Vec<&MockWalletTx> = sent
+                .iter()
+                .filter(|tx| tx.address == payment.address.to_string())
+                .collect();
+            assert_eq!(1, bid_sent.len());
+            assert_eq!(payment.amount, bid_sent[0].amount);
+            assert_eq!(
+                vec![bid_sent[0].txid],
+                payment.txs.iter().map(|tx| tx.txid).collect::<Vec<_>>()
+            );
+            assert!(!has_pending_intent(bid));
+        }
+        assert_eq!(
+            PaymentState::Paid,
+            request_payment_state(true, &persisted, PayoutResult::Complete)
+        );
+    }
+
+    #[test]
+    fn request_payment_state_test() {
+        setup_logger();
+        let state = gen_challenge_state(&gen_dummy_hash(1));
+        let mut bid = state.bids.iter().next().unwrap().clone();
+        assert_eq!(
+            PaymentState::NotRequired,
+            request_payment_state(true, &[bid.clone()], PayoutResult::Complete)
+        );
+
+        bid.payment = Some(BidPayment {
+            txs: vec![],
+            address: Address::from_str("CMAMyHorv18WKKTvg5Kifi8ap8CuSJUxXT").unwrap(),
+            address_type: PayoutAddressType::P2pkh,
+            amount: Amount::from_sat(100),
+            intent: None,
+            basis: None,
+        });
+        assert_eq!(
+            PaymentState::NotRequired,
+            request_payment_state(false, &[bid.clone()], PayoutResult::Complete)
+        );
+        // payments reported successful without broadcast transactions
+        assert_eq!(
+            PaymentState::Failed,
+            request_payment_state(true, &[bid.clone()], PayoutResult::Complete)
+        );
+
+        // payments exported with an intent
+        bid.payment.as_mut().unwrap().intent = Some("intent".to_owned());
+        assert_eq!(
+            PaymentState::Exported,
+            request_payment_state(true, &[bid.clone()], PayoutResult::Complete)
+        );
+        assert_eq!(
+            PaymentState::Failed,
+            request_payment_state(true, &[bid.clone()], PayoutResult::Failed)
+        );
+        bid.payment.as_mut().unwrap().intent = None;
+
+        bid.payment.as_mut().unwrap().txs = vec![BidPaymentTx {
+            txid: gen_dummy_hash(2),
+            amount: Amount::from_sat(100),
+            confirmations: 0,
+        }];
+        assert_eq!(
+            PaymentState::Paid,
+            request_payment_state(true, &[bid.clone()], PayoutResult::Complete)
+        );
+        assert_eq!(
+            PaymentState::Partial,
+            request_payment_state(true, &[bid.clone()], PayoutResult::Partial)
+        );
+        assert_eq!(
+            PaymentState::Failed,
+            request_payment_state(true, &[bid], PayoutResult::Failed)
+        );
+    }
+
+    #[test]
+    fn estimate_earnings_test() {
+        let state = gen_challenge_state(&gen_dummy_hash(1));
+        let mut request = state.request.clone();
+        request.start_blockheight = 100;
+        request.end_blockheight = 150;
+        let gen_sample = |blocks: u32, fees: u64| {
+            let mut sample = state.request.clone();
+            sample.end_blockheight = sample.start_blockheight + blocks;
+            let pool = FeePool::new(
+                0,
+                blocks,
+                vec![BlockFees {
+                    height: 1,
+                    fees: vec![AssetFees {
+                        asset: "CBT".to_owned(),
+                        amount: Amount::from_sat(fees),
+                    }],
+                }],
+                100,
+                None,
+            );
+            (sample, pool)
+        };
+
+        // no finished requests to sample fees from
+        let estimate = estimate_earnings(&request, 3, &[], 50, 1.0);
+        assert_eq!(0, estimate.sample_requests);
+        assert_eq!(Amount::ZERO, estimate.amount);
+        assert_eq!(4, estimate.num_bids);
+
+        // fees accrue at 1000 sat per block over the samples
+        let samples = vec![gen_sample(10, 4000), gen_sample(30, 36000)];
+        let estimate = estimate_earnings(&request, 3, &samples, 50, 1.0);
+        assert_eq!(
+            EarningsEstimate {
+                sample_requests: 2,
+                fee_rate: Amount::from_sat(1000),
+                blocks: 50,
+                fees_amount: Amount::from_sat(50000),
+                fee_percentage: 50,
+                num_bids: 4,
+                response_rate: 1.0,
+                amount: Amount::from_sat(6250),
+            },
+            estimate
+        );
+
+        // scaled by the response rate
+        let estimate = estimate_earnings(&request, 3, &samples, 50, 0.5);
+        assert_eq!(Amount::from_sat(3125), estimate.amount);
+        let estimate = estimate_earnings(&request, 3, &samples, 50, 2.0);
+        assert_eq!(Amount::from_sat(6250), estimate.amount);
+
+        // bids share fees up to the request tickets
+        let estimate = estimate_earnings(&request, 10, &samples, 50, 1.0);
+        assert_eq!(10, estimate.num_bids);
+        assert_eq!(Amount::from_sat(2500), estimate.amount);
+    }
+
+    #[test]
+    fn select_payouts_test() {
+        let state = gen_challenge_state(&gen_dummy_hash(1));
+        let bid = state.bids.iter().next().unwrap().clone();
+        let gen_bid = |txid: u8, amount: u64, num_responses: u32| {
+            let mut bid = bid.clone();
+            bid.txid = gen_dummy_hash(txid);
+            bid.payment = Some(BidPayment {
+                txs: vec![],
+                address: Address::from_str("CMAMyHorv18WKKTvg5Kifi8ap8CuSJUxXT").unwrap(),
+                address_type: PayoutAddressType::P2pkh,
+                amount: Amount::from_sat(amount),
+                intent: None,
+                basis: Some(BidPaymentBasis {
+                    formula_version: 1,
+                    fees_amount: Amount::from_sat(1000),
+                    fee_percentage: 100,
+                    num_bids: 3,
+                    num_challenges: 10,
+                    num_responses,
+                    latency_weighting: None,
+                }),
+            });
+            bid
+        };
+        let bids = vec![gen_bid(2, 300, 10), gen_bid(3, 100, 2), gen_bid(4, 200, 5)];
+
+        // all payments funded
+        let (deferred, remaining) = select_payouts(&bids, Amount::from_sat(700), PayoutOrder::SmallestFirst);
+        assert_eq!(0, deferred.len());
+        assert_eq!(Amount::from_sat(100), remaining);
+
+        let (deferred, remaining) = select_payouts(&bids, Amount::from_sat(350), PayoutOrder::SmallestFirst);
+        assert_eq!(vec![gen_dummy_hash(2)], deferred);
+        assert_eq!(Amount::from_sat(50), remaining);
+
+        // payments after the first unfunded one are deferred even if they fit
+        let (deferred, remaining) = select_payouts(&bids, Amount::from_sat(350), PayoutOrder::LargestFirst);
+        assert_eq!(vec![gen_dummy_hash(4), gen_dummy_hash(3)], deferred);
+        assert_eq!(Amount::from_sat(50), remaining);
+
+        let (deferred, remaining) = select_payouts(&bids, Amount::from_sat(550), PayoutOrder::MostResponses);
+        assert_eq!(vec![gen_dummy_hash(3)], deferred);
+        assert_eq!(Amount::from_sat(50), remaining);
+
+        // paid and exported payments are not selected
+        let mut bids = bids;
+        bids[0].payment.as_mut().unwrap().txs = vec![BidPaymentTx {
+            txid: gen_dummy_hash(5),
+            amount: Amount::from_sat(300),
+            confirmations: 0,
+        }];
+        bids[1].payment.as_mut().unwrap().intent = Some("intent".to_owned());
+        let (deferred, remaining) = select_payouts(&bids, Amount::from_sat(100), PayoutOrder::LargestFirst);
+        assert_eq!(vec![gen_dummy_hash(4)], deferred);
+        assert_eq!(Amount::from_sat(100), remaining);
+    }
+
+    #[test]
+    fn fee_asset_test() {
+        setup_logger();
+        let asset_id = "b2e15d0d7a0c94e4e2ce0fe6e8691b9e451377f6e46e8045a86f7c4b5d4f0f23";
+        let fee_assets = vec![String::from("CBT"), String::from(asset_id)];
+
+        // labelled fee assets by label or asset id
+        assert_eq!(Some(String::from("CBT")), fee_asset(Some("CBT"), "00", &fee_assets));
+        assert_eq!(Some(String::from("FEE")), fee_asset(Some("FEE"), asset_id, &fee_assets));
+        // policy assets and other labelled assets are not fees
+        assert_eq!(None, fee_asset(Some("CHALLENGE"), "00", &fee_assets));
+        assert_eq!(None, fee_asset(Some("CBT"), "00", &[String::from("FEE")]));
+        // unlabelled assets by asset id
+        assert_eq!(Some(String::from("00")), fee_asset(None, "00", &fee_assets));
+
+        let fees = vec![
+            AssetFees {
+                asset: String::from("CBT"),
+                amount: Amount::from_sat(1500),
+            },
+            AssetFees {
+                asset: String::from("FEE"),
+                amount: Amount::from_sat(500),
+            },
+        ];
+        assert_eq!(Amount::from_sat(2000), total_fees(&fees));
+        assert_eq!(Amount::ZERO, total_fees(&[]));
+    }
+
+    #[test]
+    fn add_coinbase_fees_test() {
+        setup_logger();
+        let fee_addr = "2dt74vJqpNTM12pbiNpHGvry7tyvUu9tJR8";
+        let burn_addr = "2dZRkPX3hrPtuBrmMkbGtxTxsuYYgAaFrXZ";
+        let tx = serde_json::json!({
+            "vin": [{ "coinbase": "0101" }],
+            "vout": [
+                {
+                    "value": 1.5, "asset": "aa", "assetlabel": "CBT",
+                    "scriptPubKey": { "type": "pubkeyhash", "addresses": [fee_addr] }
+                },
+                {
+                    "value": 0.5, "asset": "aa", "assetlabel": "CBT",
+                    "scriptPubKey": { "type": "pubkeyhash", "address": burn_addr }
+                },
+                {
+                    "value": 0.25, "asset": "aa", "assetlabel": "CBT",
+                    "scriptPubKey": { "type": "nulldata" }
+                },
+                {
+                    "value": 10.0, "asset": "bb", "assetlabel": "CHALLENGE",
+                    "scriptPubKey": { "type": "pubkeyhash", "addresses": [fee_addr] }
+                },
+            ]
+        });
+        let fee_assets = vec![String::from("CBT")];
+
+        // all outputs of fee assets
+        let mut filter = FeeFilter::default();
+        let mut fees = BTreeMap::new();
+        add_coinbase_fees(&tx, &fee_assets, &filter, &mut fees).unwrap();
+        assert_eq!(Some(&Amount::from_sat(225000000)), fees.get("CBT"));
+        assert_eq!(1, fees.len());
+
+        // excluded burn address and script type, totals accumulate
+        filter.exclude_addresses = vec![burn_addr.to_owned()];
+        filter.exclude_script_types = vec![String::from("nulldata")];
+        add_coinbase_fees(&tx, &fee_assets, &filter, &mut fees).unwrap();
+        assert_eq!(Some(&Amount::from_sat(375000000)), fees.get("CBT"));
+
+        // designated fee addresses only
+        let filter = FeeFilter {
+            exclude_addresses: vec![],
+            exclude_script_types: vec![],
+            include_addresses: vec![burn_addr.to_owned()],
+        };
+        let mut fees = BTreeMap::new();
+        add_coinbase_fees(&tx, &fee_assets, &filter, &mut fees).unwrap();
+        assert_eq!(Some(&Amount::from_sat(50000000)), fees.get("CBT"));
+    }
+
+    #[test]
+    fn latency_weighting_test() {
+        setup_logger();
+        assert_eq!(None, latency_weighting(&[], Some(&vec![100]), 1));
+
+        let latency_weights = vec![
+            LatencyBucketConfig {
+                max_ms: 1000,
+                weight: 100,
+            },
+            LatencyBucketConfig {
+                max_ms: 5000,
+                weight: 50,
+            },
+        ];
+        let weighting = latency_weighting(&latency_weights, Some(&vec![100, 2000, 9000]), 3).unwrap();
+        assert_eq!(vec![1000, 5000], weighting.bucket_max_ms);
+        assert_eq!(vec![100, 50], weighting.bucket_weights);
+        assert_eq!(vec![1, 2], weighting.bucket_responses);
+        assert_eq!(0, weighting.unmeasured_responses);
+
+        // responses without recorded latencies count in full
+        let weighting = latency_weighting(&latency_weights, None, 2).unwrap();
+        assert_eq!(vec![0, 0], weighting.bucket_responses);
+        assert_eq!(2, weighting.unmeasured_responses);
+        assert_eq!(200, weighting.weighted_responses());
+    }
 
     #[test]
     fn calculate_bid_payment_test() {
@@ -366,18 +2845,4 @@ mod tests {
                 .as_btc()
         );
     }
-
-    #[test]
-    fn get_chain_addr_params_test() {
-        setup_logger();
-        assert_eq!(
-            &AddressParams::OCEAN,
-            get_chain_addr_params(&String::from("ocean_main"))
-        );
-        assert_eq!(&AddressParams::GOLD, get_chain_addr_params(&String::from("gold_main")));
-        assert_eq!(
-            &AddressParams::ELEMENTS,
-            get_chain_addr_params(&String::from("supersilverhazechain"))
-        );
-    }
 }
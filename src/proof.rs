@@ -0,0 +1,685 @@
+//! Proof
+//!
+//! Challenge proof model and validation shared by the listener and the api
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use bitcoin::consensus::serialize;
+use bitcoin::hashes::{hex::FromHex, sha256d, Hash};
+use bitcoin::secp256k1::{Message, PublicKey, Secp256k1, Signature};
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::challenger::ChallengeState;
+use crate::error::{CError, Result};
+use crate::interfaces::bid::Bid;
+
+/// Signature type of v2 challenge proofs. Only DER encoded ecdsa signatures
+/// are currently supported
+pub const PROOF_V2_SIGTYPE: &str = "ecdsa";
+
+/// Challenge proof versions accepted by the listener and the api
+pub const PROOF_VERSIONS: [u64; 2] = [1, 2];
+
+/// Fields of challenge proof submissions
+pub const PROOF_FIELDS: &[&str] = &["txid", "pubkey", "hash", "sig", "version", "request", "sigtype"];
+
+/// Messsage type for challenge proofs sent by guardnodes. Version 1 proofs
+/// sign the challenge hash only, while version 2 proofs are bound to a request
+/// and sign the request txid, challenge hash, bid txid, bid pubkey and sigtype.
+/// For requests with a challenge payload proofs of either version also commit
+/// to the payload commitment
+#[derive(Debug)]
+pub struct ChallengeProof {
+    /// Challenge (transaction id) hash
+    pub hash: sha256d::Hash,
+    /// Challenge signature for hash and pubkey
+    pub sig: Signature,
+    /// Pubkey used to generate challenge signature
+    pub bid: Bid,
+    /// Request (transaction id) hash the proof is bound to, set for v2 proofs
+    pub request: Option<sha256d::Hash>,
+    /// Commitment of the challenge payload of the request, set from the
+    /// challenge state of the request when the proof is checked
+    pub payload: Option<sha256d::Hash>,
+}
+
+impl ChallengeProof {
+    /// Parse serde json value into ChallengeProof struct result. Proofs with
+    /// version 2 are required to also carry the request txid and sigtype,
+    /// while proofs with no version are parsed as version 1. Unknown fields
+    /// are ignored
+    pub fn from_json(val: Value) -> Result<ChallengeProof> {
+        ChallengeProofSeed { strict: false }
+            .deserialize(val)
+            .map_err(|e| CError::Generic(e.to_string()).into())
+    }
+
+    /// Parse a json request body into ChallengeProof struct directly, without
+    /// building an intermediate json value. Unknown fields are rejected if
+    /// strict and ignored otherwise. Errors can be classified into json syntax
+    /// errors and proof data errors via serde_json::Error::is_data
+    pub fn from_slice(body: &[u8], strict: bool) -> std::result::Result<ChallengeProof, serde_json::Error> {
+        let mut deserializer = serde_json::Deserializer::from_slice(body);
+        let proof = ChallengeProofSeed { strict }.deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(proof)
+    }
+
+    /// Get the preimage of the message signed by a challenge proof. For v1
+    /// proofs this is the challenge hash and for v2 proofs the concatenation of
+    /// the request txid, challenge hash, bid txid, compressed bid pubkey and
+    /// sigtype. The payload commitment, if any, is appended to the preimage of
+    /// either version. Hashes are serialized in internal byte order, i.e.
+    /// reversed with respect to their hex representation
+    pub fn signed_preimage(
+        request: Option<&sha256d::Hash>,
+        hash: &sha256d::Hash,
+        bid: &Bid,
+        payload: Option<&sha256d::Hash>,
+    ) -> Vec<u8> {
+        let mut preimage = match request {
+            None => serialize(hash),
+            Some(request) => {
+                let mut preimage = serialize(request);
+                preimage.extend(serialize(hash));
+                preimage.extend(serialize(&bid.txid));
+                preimage.extend(&bid.pubkey.serialize()[..]);
+                preimage.extend(PROOF_V2_SIGTYPE.as_bytes());
+                preimage
+            }
+        };
+        if let Some(payload) = payload {
+            preimage.extend(serialize(payload));
+        }
+        preimage
+    }
+
+    /// Get the message signed by a challenge proof. For v1 proofs without a
+    /// payload this is the challenge hash and otherwise the sha256d hash of
+    /// the preimage of all the proof fields
+    pub fn signed_message(
+        request: Option<&sha256d::Hash>,
+        hash: &sha256d::Hash,
+        bid: &Bid,
+        payload: Option<&sha256d::Hash>,
+    ) -> Result<Message> {
+        let preimage = ChallengeProof::signed_preimage(request, hash, bid, payload);
+        match (request, payload) {
+            (None, None) => Ok(Message::from_slice(&preimage)?),
+            _ => Ok(Message::from_slice(&sha256d::Hash::hash(&preimage)[..])?),
+        }
+    }
+
+    /// Get the message signed by the challenge proof
+    pub fn message(&self) -> Result<Message> {
+        ChallengeProof::signed_message(self.request.as_ref(), &self.hash, &self.bid, self.payload.as_ref())
+    }
+
+    /// Verify the challenge proof signature using the pubkey and proof message
+    pub fn verify(challenge_proof: &ChallengeProof) -> Result<()> {
+        let secp = Secp256k1::new();
+        secp.verify(
+            &challenge_proof.message()?,
+            &challenge_proof.sig,
+            &challenge_proof.bid.pubkey,
+        )?;
+        Ok(())
+    }
+}
+
+/// Deserializer of challenge proofs from json objects, parsing each field into
+/// its typed value as it is read. Unknown fields are rejected if strict and
+/// skipped otherwise
+pub struct ChallengeProofSeed {
+    /// Flag to reject unknown fields
+    pub strict: bool,
+}
+
+/// Challenge proof field names
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "lowercase")]
+enum ProofField {
+    Txid,
+    Pubkey,
+    Hash,
+    Sig,
+    Version,
+    Request,
+    Sigtype,
+    Unknown(String),
+}
+
+/// Deserializer of a string field value into a typed value using a parser,
+/// borrowing the string from the input instead of allocating it where possible
+struct ParseSeed<T> {
+    /// Field name, reported on parse errors
+    field: &'static str,
+    /// Parser of the field string
+    parse: fn(&str) -> Result<T>,
+}
+
+impl<'de, T> DeserializeSeed<'de> for ParseSeed<T> {
+    type Value = T;
+
+    fn deserialize<D: Deserializer<'de>>(self, d: D) -> std::result::Result<T, D::Error> {
+        d.deserialize_str(self)
+    }
+}
+
+impl<'de, T> Visitor<'de> for ParseSeed<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a {} string", self.field)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<T, E> {
+        (self.parse)(v).map_err(|e| E::custom(format!("bad {}: {}", self.field, e)))
+    }
+}
+
+/// Set a challenge proof field read from a map, rejecting duplicate fields
+fn set_field<'de, A: MapAccess<'de>, T>(
+    map: &mut A,
+    slot: &mut Option<T>,
+    field: &'static str,
+    parse: fn(&str) -> Result<T>,
+) -> std::result::Result<(), A::Error> {
+    if slot.is_some() {
+        return Err(de::Error::duplicate_field(field));
+    }
+    *slot = Some(map.next_value_seed(ParseSeed { field, parse })?);
+    Ok(())
+}
+
+/// Parse a hex hash field
+fn parse_hash(s: &str) -> Result<sha256d::Hash> {
+    Ok(sha256d::Hash::from_hex(s)?)
+}
+
+/// Parse a hex compressed or uncompressed pubkey field
+fn parse_pubkey(s: &str) -> Result<PublicKey> {
+    Ok(PublicKey::from_str(s)?)
+}
+
+/// Parse a hex DER encoded signature field
+fn parse_sig(s: &str) -> Result<Signature> {
+    Ok(Signature::from_der(&Vec::<u8>::from_hex(s)?)?)
+}
+
+/// Parse a sigtype field, which only v2 proofs are required to carry
+fn parse_sigtype(s: &str) -> Result<String> {
+    Ok(s.to_owned())
+}
+
+impl<'de> DeserializeSeed<'de> for ChallengeProofSeed {
+    type Value = ChallengeProof;
+
+    fn deserialize<D: Deserializer<'de>>(self, d: D) -> std::result::Result<ChallengeProof, D::Error> {
+        d.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ChallengeProofSeed {
+    type Value = ChallengeProof;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a challenge proof object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<ChallengeProof, A::Error> {
+        let mut txid = None;
+        let mut pubkey = None;
+        let mut hash = None;
+        let mut sig = None;
+        let mut version: Option<Option<u64>> = None;
+        let mut request = None;
+        let mut sigtype = None;
+        while let Some(field) = map.next_key::<ProofField>()? {
+            match field {
+                ProofField::Txid => set_field(&mut map, &mut txid, "txid", parse_hash)?,
+                ProofField::Pubkey => set_field(&mut map, &mut pubkey, "pubkey", parse_pubkey)?,
+                ProofField::Hash => set_field(&mut map, &mut hash, "hash", parse_hash)?,
+                ProofField::Sig => set_field(&mut map, &mut sig, "sig", parse_sig)?,
+                ProofField::Request => set_field(&mut map, &mut request, "request", parse_hash)?,
+                ProofField::Sigtype => set_field(&mut map, &mut sigtype, "sigtype", parse_sigtype)?,
+                ProofField::Version => {
+                    if version.is_some() {
+                        return Err(de::Error::duplicate_field("version"));
+                    }
+                    version = Some(map.next_value()?);
+                }
+                ProofField::Unknown(name) => {
+                    if self.strict {
+                        return Err(de::Error::unknown_field(&name, PROOF_FIELDS));
+                    }
+                    let _ = map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        let request = match version.and_then(|version| version).unwrap_or(1) {
+            1 => None,
+            2 => {
+                let sigtype = sigtype.unwrap_or_default();
+                if sigtype != PROOF_V2_SIGTYPE {
+                    return Err(de::Error::custom(format!("unsupported sigtype: {}", sigtype)));
+                }
+                Some(request.ok_or_else(|| de::Error::missing_field("request"))?)
+            }
+            version => return Err(de::Error::custom(format!("unsupported version: {}", version))),
+        };
+        Ok(ChallengeProof {
+            hash: hash.ok_or_else(|| de::Error::missing_field("hash"))?,
+            sig: sig.ok_or_else(|| de::Error::missing_field("sig"))?,
+            bid: Bid {
+                txid: txid.ok_or_else(|| de::Error::missing_field("txid"))?,
+                pubkey: pubkey.ok_or_else(|| de::Error::missing_field("pubkey"))?,
+                payment: None,
+            },
+            request,
+            payload: None,
+        })
+    }
+}
+
+/// Parse challenge proof json and check that there is an active challenge,
+/// that the proof bid exists and that the proof hash is correct. V2 proofs
+/// are rejected if their request is not the one being challenged. Returns the
+/// proof ready for signature verification or the rejection reason
+pub fn check_challenge_proof(
+    obj: Value,
+    challenge: &Arc<RwLock<Option<ChallengeState>>>,
+) -> std::result::Result<ChallengeProof, String> {
+    match ChallengeProof::from_json(obj) {
+        // parse challenge proof from json
+        Ok(proof) => check_proof_challenge(proof, challenge),
+        Err(e) => Err(format!("bad-proof-data: {}", e)),
+    }
+}
+
+/// Check that there is an active challenge for a parsed challenge proof, that
+/// the proof bid exists and that the proof hash is correct. V2 proofs are
+/// rejected if their request is not the one being challenged, and proofs of
+/// requests with commit-reveal are rejected while the committed challenge is
+/// not revealed yet. Returns the proof, committing to the challenge payload of
+/// the request if any, ready for signature verification or the rejection
+/// reason
+pub fn check_proof_challenge(
+    mut proof: ChallengeProof,
+    challenge: &Arc<RwLock<Option<ChallengeState>>>,
+) -> std::result::Result<ChallengeProof, String> {
+    // check for an active challenge, taking references to the immutable bids
+    // and dropping the lock immediately
+    let active = match challenge.read().unwrap().as_ref() {
+        // proofs sent before the committed challenge is revealed could only
+        // have been precomputed
+        Some(ch) if ch.latest_challenge.is_pending_reveal() => return Err("challenge-not-revealed".to_owned()),
+        Some(ch) => ch.latest_challenge.hash().map(|h| {
+            (
+                h,
+                ch.request.txid,
+                ch.bids.clone(),
+                ch.spilled_bids.clone(),
+                ch.payload.as_ref().map(|payload| payload.commitment),
+            )
+        }),
+        None => None,
+    };
+    if let Some((h, request_hash, bids, spilled, payload)) = active {
+        // check challenge proof request is being challenged
+        if proof.request.map_or(false, |request| request != request_hash) {
+            return Err("bad-request".to_owned());
+        }
+        // check challenge proof bid exists, in memory or else among the bids
+        // spilled to storage
+        if !bids.contains(&proof.bid) {
+            match spilled {
+                Some(ref spilled) if spilled.contains(request_hash, &proof.bid) => (),
+                _ => return Err("bad-bid".to_owned()),
+            }
+        }
+        // check challenge proof hash is correct
+        if proof.hash != h {
+            return Err("bad-hash".to_owned());
+        }
+        proof.payload = payload;
+        return Ok(proof);
+    }
+    Err(format!("no-active-challenge"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Instant;
+
+    use bitcoin::hashes::hex::ToHex;
+    use bitcoin::secp256k1::SecretKey;
+
+    use crate::interfaces::clientchain::ChallengeCommitment;
+
+    use crate::payload::{ChallengePayload, PayloadScheme};
+    use crate::util::testing::{gen_challenge_state_with_challenge, gen_dummy_hash, setup_logger};
+
+    #[test]
+    fn challengeproof_from_json_test() {
+        setup_logger();
+        // good data
+        let data = r#"
+        {
+            "txid": "0000000000000000000000000000000000000000000000000000000000000000",
+            "pubkey": "03356190524d52d7e94e1bd43e8f23778e585a4fe1f275e65a06fa5ceedb67d111",
+            "hash": "0404040404040404040404040404040404040404040404040404040404040404",
+            "sig": "304402201742daea5ec3b7306b9164be862fc1659cc830032180b8b17beffe02645860d602201039eba402d22e630308e6af05da8dd4f05b51b7d672ca5fc9e3b0a57776365c"
+        }"#;
+        let proof = ChallengeProof::from_json(serde_json::from_str::<Value>(data).unwrap());
+        assert!(proof.is_ok());
+
+        // bad txid
+        let data = r#"
+        {
+            "txid": "",
+            "pubkey": "03356190524d52d7e94e1bd43e8f23778e585a4fe1f275e65a06fa5ceedb67d111",
+            "hash": "0404040404040404040404040404040404040404040404040404040404040404",
+            "sig": "304402201742daea5ec3b7306b9164be862fc1659cc830032180b8b17beffe02645860d602201039eba402d22e630308e6af05da8dd4f05b51b7d672ca5fc9e3b0a57776365c"
+        }"#;
+        let proof = ChallengeProof::from_json(serde_json::from_str::<Value>(data).unwrap());
+        assert!(proof.err().unwrap().to_string().contains("bitcoin hashes hex error"));
+
+        // bad pubkey
+        let data = r#"
+        {
+            "txid": "0000000000000000000000000000000000000000000000000000000000000000",
+            "pubkey": "0356190524d52d7e94e1bd43e8f23778e585a4fe1f275e65a06fa5ceedb67d111",
+            "hash": "0404040404040404040404040404040404040404040404040404040404040404",
+            "sig": "304402201742daea5ec3b7306b9164be862fc1659cc830032180b8b17beffe02645860d602201039eba402d22e630308e6af05da8dd4f05b51b7d672ca5fc9e3b0a57776365c"
+        }"#;
+        let proof = ChallengeProof::from_json(serde_json::from_str::<Value>(data).unwrap());
+        assert!(proof.err().unwrap().to_string().contains("secp256k1 error"));
+
+        // bad hash
+        let data = r#"
+        {
+            "txid": "0000000000000000000000000000000000000000000000000000000000000000",
+            "pubkey": "03356190524d52d7e94e1bd43e8f23778e585a4fe1f275e65a06fa5ceedb67d111",
+            "hash": "04040404040404040404040404040404040404040404040404040404040404",
+            "sig": "304402201742daea5ec3b7306b9164be862fc1659cc830032180b8b17beffe02645860d602201039eba402d22e630308e6af05da8dd4f05b51b7d672ca5fc9e3b0a57776365c"
+        }"#;
+        let proof = ChallengeProof::from_json(serde_json::from_str::<Value>(data).unwrap());
+        assert!(proof.err().unwrap().to_string().contains("bitcoin hashes hex error"));
+
+        // bad sig
+        let data = r#"
+        {
+            "txid": "0000000000000000000000000000000000000000000000000000000000000000",
+            "pubkey": "03356190524d52d7e94e1bd43e8f23778e585a4fe1f275e65a06fa5ceedb67d111",
+            "hash": "0404040404040404040404040404040404040404040404040404040404040404",
+            "sig": "4402201742daea5ec3b7306b9164be862fc1659cc830032180b8b17beffe02645860d602201039eba402d22e630308e6af05da8dd4f05b51b7d672ca5fc9e3b0a57776365c"
+        }"#;
+        let proof = ChallengeProof::from_json(serde_json::from_str::<Value>(data).unwrap());
+        assert!(proof.err().unwrap().to_string().contains("secp256k1 error"));
+    }
+
+    #[test]
+    fn challengeproof_from_slice_test() {
+        setup_logger();
+        let data = r#"
+        {
+            "txid": "0000000000000000000000000000000000000000000000000000000000000000",
+            "pubkey": "03356190524d52d7e94e1bd43e8f23778e585a4fe1f275e65a06fa5ceedb67d111",
+            "hash": "0404040404040404040404040404040404040404040404040404040404040404",
+            "sig": "304402201742daea5ec3b7306b9164be862fc1659cc830032180b8b17beffe02645860d602201039eba402d22e630308e6af05da8dd4f05b51b7d672ca5fc9e3b0a57776365c",
+            "client": "guardnode"
+        }"#;
+
+        // unknown fields ignored unless strict
+        let proof = ChallengeProof::from_slice(data.as_bytes(), false).unwrap();
+        assert_eq!(None, proof.request);
+        assert_eq!(sha256d::Hash::from_hex(&"04".repeat(32)).unwrap(), proof.hash);
+        let err = ChallengeProof::from_slice(data.as_bytes(), true).err().unwrap();
+        assert!(err.is_data());
+        assert!(err.to_string().contains("unknown field `client`"));
+
+        // bad fields are reported by name
+        let bad = data.replace(r#""hash": "04"#, r#""hash": "4"#);
+        let err = ChallengeProof::from_slice(bad.as_bytes(), false).err().unwrap();
+        assert!(err.is_data());
+        assert!(err.to_string().contains("bad hash: bitcoin hashes hex error"));
+        let bad = data.replace(r#""sig": "30"#, r#""sig": 30, "old": "30"#);
+        let err = ChallengeProof::from_slice(bad.as_bytes(), false).err().unwrap();
+        assert!(err.to_string().contains("expected a sig string"));
+
+        // missing and duplicate fields
+        let err = ChallengeProof::from_slice(br#"{"txid": "00"}"#, false).err().unwrap();
+        assert!(err.to_string().contains("bad txid"));
+        let bad = data.replace(r#""client": "guardnode""#, r#""version": 2, "sigtype": "ecdsa""#);
+        let err = ChallengeProof::from_slice(bad.as_bytes(), false).err().unwrap();
+        assert!(err.to_string().contains("missing field `request`"));
+        let bad = data.replace(r#""client": "guardnode""#, r#""version": 1, "version": 1"#);
+        let err = ChallengeProof::from_slice(bad.as_bytes(), false).err().unwrap();
+        assert!(err.to_string().contains("duplicate field `version`"));
+
+        // json syntax errors are told from bad proof data
+        let err = ChallengeProof::from_slice(b"", false).err().unwrap();
+        assert!(!err.is_data());
+        let err = ChallengeProof::from_slice(format!("{},", data).as_bytes(), false)
+            .err()
+            .unwrap();
+        assert!(!err.is_data());
+        let err = ChallengeProof::from_slice(b"[]", false).err().unwrap();
+        assert!(err.is_data());
+    }
+
+    #[test]
+    fn challengeproof_verify_test() {
+        setup_logger();
+        let chl_hash = gen_dummy_hash(11);
+        let _challenge_state = gen_challenge_state_with_challenge(&gen_dummy_hash(3), &chl_hash);
+        let bid_txid = _challenge_state.bids.iter().next().unwrap().txid;
+        let bid_pubkey = _challenge_state.bids.iter().next().unwrap().pubkey;
+
+        // verify good sig
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xaa; 32]).unwrap();
+        let sig = secp.sign(&Message::from_slice(&serialize(&chl_hash)).unwrap(), &secret_key);
+
+        let proof = ChallengeProof {
+            hash: chl_hash,
+            sig: sig,
+            bid: Bid {
+                txid: bid_txid,
+                pubkey: bid_pubkey,
+                payment: None,
+            },
+            request: None,
+            payload: None,
+        };
+
+        let verify = ChallengeProof::verify(&proof);
+        assert!(verify.is_ok());
+
+        // verify bad sig
+        let secret_key = SecretKey::from_slice(&[0xbb; 32]).unwrap();
+        let sig = secp.sign(&Message::from_slice(&serialize(&chl_hash)).unwrap(), &secret_key);
+
+        let proof = ChallengeProof {
+            hash: chl_hash,
+            sig: sig,
+            bid: Bid {
+                txid: bid_txid,
+                pubkey: bid_pubkey,
+                payment: None,
+            },
+            request: None,
+            payload: None,
+        };
+
+        let verify = ChallengeProof::verify(&proof);
+        assert!(verify.err().unwrap().to_string().contains("secp256k1 error"));
+    }
+
+    #[test]
+    fn check_challenge_proof_test() {
+        setup_logger();
+        let chl_hash = gen_dummy_hash(8);
+        let _challenge_state = gen_challenge_state_with_challenge(&gen_dummy_hash(1), &chl_hash);
+        let bid = _challenge_state.bids.iter().next().unwrap().clone();
+        let challenge_state = Arc::new(RwLock::new(Some(_challenge_state)));
+        let secp = Secp256k1::new();
+        let sig = secp.sign(
+            &Message::from_slice(&serialize(&chl_hash)).unwrap(),
+            &SecretKey::from_slice(&[0xaa; 32]).unwrap(),
+        );
+        let proof_json = |hash: &sha256d::Hash| {
+            serde_json::json!({
+                "txid": bid.txid.to_string(),
+                "pubkey": bid.pubkey.to_string(),
+                "hash": hash.to_string(),
+                "sig": sig.serialize_der().to_hex(),
+            })
+        };
+
+        // missing proof data
+        let res = check_challenge_proof(serde_json::json!({}), &challenge_state);
+        assert!(res.err().unwrap().contains("bad-proof-data"));
+
+        // bad hash
+        let res = check_challenge_proof(proof_json(&gen_dummy_hash(9)), &challenge_state);
+        assert_eq!("bad-hash", res.err().unwrap());
+
+        // valid proof
+        let res = check_challenge_proof(proof_json(&chl_hash), &challenge_state);
+        assert_eq!(bid, res.unwrap().bid);
+
+        // no active challenge
+        let latest_challenge = challenge_state
+            .read()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .latest_challenge
+            .clone();
+        latest_challenge.clear();
+        let res = check_challenge_proof(proof_json(&chl_hash), &challenge_state);
+        assert_eq!("no-active-challenge", res.err().unwrap());
+
+        // committed challenge not revealed yet
+        let commitment = ChallengeCommitment {
+            hash: ChallengeCommitment::commit(&chl_hash),
+            txid: gen_dummy_hash(10),
+        };
+        latest_challenge.commit(commitment);
+        let res = check_challenge_proof(proof_json(&chl_hash), &challenge_state);
+        assert_eq!("challenge-not-revealed", res.err().unwrap());
+        assert!(latest_challenge.reveal(gen_dummy_hash(9), Instant::now()).is_err());
+        assert!(latest_challenge.is_pending_reveal());
+        latest_challenge.reveal(chl_hash, Instant::now()).unwrap();
+        assert_eq!(Some(commitment), latest_challenge.commitment());
+        let res = check_challenge_proof(proof_json(&chl_hash), &challenge_state);
+        assert_eq!(bid, res.unwrap().bid);
+        latest_challenge.clear();
+        assert_eq!(None, latest_challenge.commitment());
+        *challenge_state.write().unwrap() = None;
+        let res = check_challenge_proof(proof_json(&chl_hash), &challenge_state);
+        assert_eq!("no-active-challenge", res.err().unwrap());
+    }
+
+    #[test]
+    fn challengeproof_v2_test() {
+        setup_logger();
+        let chl_hash = gen_dummy_hash(8);
+        let _challenge_state = gen_challenge_state_with_challenge(&gen_dummy_hash(1), &chl_hash);
+        let request_txid = _challenge_state.request.txid;
+        let bid = _challenge_state.bids.iter().next().unwrap().clone();
+        let challenge_state = Arc::new(RwLock::new(Some(_challenge_state)));
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xaa; 32]).unwrap();
+        let proof_json = |request: &sha256d::Hash, sigtype: &str, sig: &Signature| {
+            serde_json::json!({
+                "version": 2,
+                "request": request.to_string(),
+                "txid": bid.txid.to_string(),
+                "pubkey": bid.pubkey.to_string(),
+                "hash": chl_hash.to_string(),
+                "sigtype": sigtype,
+                "sig": sig.serialize_der().to_hex(),
+            })
+        };
+        let v1_sig = secp.sign(&Message::from_slice(&serialize(&chl_hash)).unwrap(), &secret_key);
+
+        // unsupported version and sigtype
+        let mut data = proof_json(&request_txid, PROOF_V2_SIGTYPE, &v1_sig);
+        data["version"] = 3.into();
+        let proof = ChallengeProof::from_json(data);
+        assert!(proof.err().unwrap().to_string().contains("unsupported version"));
+        let proof = ChallengeProof::from_json(proof_json(&request_txid, "schnorr", &v1_sig));
+        assert!(proof.err().unwrap().to_string().contains("unsupported sigtype"));
+
+        // v2 proof message covers all fields
+        let proof = ChallengeProof::from_json(proof_json(&request_txid, PROOF_V2_SIGTYPE, &v1_sig)).unwrap();
+        assert_eq!(Some(request_txid), proof.request);
+        let message = proof.message().unwrap();
+        assert!(message != Message::from_slice(&serialize(&chl_hash)).unwrap());
+        let mut other_proof = ChallengeProof::from_json(proof_json(&request_txid, PROOF_V2_SIGTYPE, &v1_sig)).unwrap();
+        other_proof.request = Some(gen_dummy_hash(2));
+        assert!(message != other_proof.message().unwrap());
+
+        // v1 signature is not valid for v2 proof
+        assert!(ChallengeProof::verify(&proof).is_err());
+
+        // v2 signature over all fields
+        let sig = secp.sign(&message, &secret_key);
+        let res = check_challenge_proof(proof_json(&request_txid, PROOF_V2_SIGTYPE, &sig), &challenge_state);
+        let proof = res.unwrap();
+        assert_eq!(bid, proof.bid);
+        assert!(ChallengeProof::verify(&proof).is_ok());
+
+        // v2 proof for a request not being challenged
+        let res = check_challenge_proof(proof_json(&gen_dummy_hash(2), PROOF_V2_SIGTYPE, &sig), &challenge_state);
+        assert_eq!("bad-request", res.err().unwrap());
+    }
+
+    #[test]
+    fn challengeproof_payload_test() {
+        setup_logger();
+        let chl_hash = gen_dummy_hash(8);
+        let mut _challenge_state = gen_challenge_state_with_challenge(&gen_dummy_hash(1), &chl_hash);
+        let commitment = gen_dummy_hash(5);
+        _challenge_state.payload = Some(ChallengePayload {
+            scheme: PayloadScheme::ClientchainBlockhash,
+            height: 2,
+            commitment,
+        });
+        let bid = _challenge_state.bids.iter().next().unwrap().clone();
+        let challenge_state = Arc::new(RwLock::new(Some(_challenge_state)));
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xaa; 32]).unwrap();
+        let proof_json = |sig: &Signature| {
+            serde_json::json!({
+                "txid": bid.txid.to_string(),
+                "pubkey": bid.pubkey.to_string(),
+                "hash": chl_hash.to_string(),
+                "sig": sig.serialize_der().to_hex(),
+            })
+        };
+
+        // proofs of requests with a payload commit to the payload
+        let message = ChallengeProof::signed_message(None, &chl_hash, &bid, Some(&commitment)).unwrap();
+        assert!(message != Message::from_slice(&serialize(&chl_hash)).unwrap());
+        assert!(message != ChallengeProof::signed_message(None, &chl_hash, &bid, Some(&gen_dummy_hash(6))).unwrap());
+        let sig = secp.sign(&message, &secret_key);
+        let proof = check_challenge_proof(proof_json(&sig), &challenge_state).unwrap();
+        assert_eq!(Some(commitment), proof.payload);
+        assert!(ChallengeProof::verify(&proof).is_ok());
+
+        // signature over the challenge hash only does not verify
+        let sig = secp.sign(&Message::from_slice(&serialize(&chl_hash)).unwrap(), &secret_key);
+        let proof = check_challenge_proof(proof_json(&sig), &challenge_state).unwrap();
+        assert!(ChallengeProof::verify(&proof).is_err());
+    }
+}
@@ -0,0 +1,377 @@
+//! Proof policy
+//!
+//! Acceptance policies that challenge proofs are checked against by the
+//! listener on top of the proof validation, such as restricting the addresses
+//! that guardnodes send proofs from or how long after the challenge proofs can
+//! arrive. Deployments can chain the built-in policies via the config or add
+//! their own implementations of the ProofPolicy trait
+
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ProofPolicyConfig;
+use crate::error::{CError, Error, Result};
+use crate::proof::ChallengeProof;
+
+/// Context of a challenge proof that acceptance policies are evaluated on
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProofContext {
+    /// Address of the guardnode that sent the proof, if known
+    pub remote_addr: Option<IpAddr>,
+    /// Time elapsed since the challenge was issued, if known
+    pub challenge_age: Option<Duration>,
+}
+
+/// Acceptance policy of challenge proofs
+pub trait ProofPolicy: Send + Sync {
+    /// Name of the policy recorded along with its results
+    fn name(&self) -> String;
+    /// Check a challenge proof in its context, returning the rejection reason
+    /// if the proof is not accepted by the policy
+    fn check(&self, proof: &ChallengeProof, context: &ProofContext) -> std::result::Result<(), String>;
+}
+
+/// Result of a policy check of a challenge proof
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyResult {
+    /// Policy name
+    pub policy: String,
+    /// Rejection reason if the proof was rejected by the policy
+    pub rejection: Option<String>,
+}
+
+/// Range of ip addresses in CIDR notation, e.g. 10.0.0.0/8. Addresses without
+/// a prefix length are single address ranges
+#[derive(Debug, Clone, PartialEq)]
+pub struct IpRange {
+    /// Network address
+    addr: IpAddr,
+    /// Number of leading bits of the network address that addresses in the
+    /// range share
+    prefix_len: u8,
+}
+
+impl IpRange {
+    /// Check whether an address is in the range. Ipv4 addresses mapped to ipv6
+    /// are matched against ipv4 ranges
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        let addr = match *addr {
+            IpAddr::V6(v6) if v6.segments()[..6] == [0, 0, 0, 0, 0, 0xffff] => IpAddr::V4(v6.to_ipv4().unwrap()),
+            _ => *addr,
+        };
+        match (self.addr, addr) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = if self.prefix_len == 0 {
+                    0
+                } else {
+                    !0u32 << (32 - self.prefix_len as u32)
+                };
+                u32::from(net) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = if self.prefix_len == 0 {
+                    0
+                } else {
+                    !0u128 << (128 - self.prefix_len as u32)
+                };
+                u128::from(net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for IpRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<IpRange> {
+        let invalid = || Error::from(CError::Generic(format!("invalid ip range: {}", s)));
+        let mut parts = s.splitn(2, '/');
+        let addr = IpAddr::from_str(parts.next().unwrap_or("")).map_err(|_| invalid())?;
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match parts.next() {
+            Some(len) => u8::from_str(len).map_err(|_| invalid())?,
+            None => max_len,
+        };
+        if prefix_len > max_len {
+            return Err(invalid());
+        }
+        Ok(IpRange { addr, prefix_len })
+    }
+}
+
+/// Policy accepting proofs sent from allow-listed ip ranges only. Proofs of
+/// unknown origin are rejected
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllowedRangesPolicy {
+    /// Ip ranges that proofs are accepted from
+    pub ranges: Vec<IpRange>,
+}
+
+impl ProofPolicy for AllowedRangesPolicy {
+    fn name(&self) -> String {
+        "allowed-ranges".to_owned()
+    }
+
+    fn check(&self, _proof: &ChallengeProof, context: &ProofContext) -> std::result::Result<(), String> {
+        match context.remote_addr {
+            Some(addr) => {
+                if self.ranges.iter().any(|range| range.contains(&addr)) {
+                    Ok(())
+                } else {
+                    Err(format!("address {} not allowed", addr))
+                }
+            }
+            None => Err("address unknown".to_owned()),
+        }
+    }
+}
+
+/// Policy accepting proofs that arrive within a max delay of the challenge
+/// being issued. Proofs are accepted if the challenge issuance time is unknown
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaxDelayPolicy {
+    /// Max delay of proofs after the challenge is issued
+    pub max_delay: Duration,
+}
+
+impl ProofPolicy for MaxDelayPolicy {
+    fn name(&self) -> String {
+        "max-delay".to_owned()
+    }
+
+    fn check(&self, _proof: &ChallengeProof, context: &ProofContext) -> std::result::Result<(), String> {
+        match context.challenge_age {
+            Some(age) if age > self.max_delay => Err(format!(
+                "proof {}ms after challenge exceeds max delay {}ms",
+                age.as_millis(),
+                self.max_delay.as_millis()
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Chain of challenge proof acceptance policies. Every policy is checked so
+/// that rejections record the results of the whole chain
+pub struct ProofPolicies {
+    /// Policies in the order they are checked
+    policies: Vec<Box<dyn ProofPolicy>>,
+}
+
+impl ProofPolicies {
+    /// Return new ProofPolicies chain for a list of policies
+    pub fn new(policies: Vec<Box<dyn ProofPolicy>>) -> ProofPolicies {
+        ProofPolicies { policies }
+    }
+
+    /// Return new ProofPolicies chain of the built-in policies of the config
+    pub fn from_config(configs: &[ProofPolicyConfig]) -> Result<ProofPolicies> {
+        let mut policies: Vec<Box<dyn ProofPolicy>> = vec![];
+        for config in configs.iter() {
+            match config.policy.as_str() {
+                "allowed-ranges" => {
+                    if config.ranges.is_empty() {
+                        return Err(Error::from(CError::Generic(
+                            "allowed-ranges policy without ranges".to_owned(),
+                        )));
+                    }
+                    let mut ranges = vec![];
+                    for range in config.ranges.iter() {
+                        ranges.push(IpRange::from_str(range)?);
+                    }
+                    policies.push(Box::new(AllowedRangesPolicy { ranges }));
+                }
+                "max-delay" => {
+                    if config.max_delay == 0 {
+                        return Err(Error::from(CError::Generic(
+                            "max-delay policy with max_delay of 0 seconds".to_owned(),
+                        )));
+                    }
+                    policies.push(Box::new(MaxDelayPolicy {
+                        max_delay: Duration::from_secs(config.max_delay),
+                    }));
+                }
+                policy => {
+                    return Err(Error::from(CError::Generic(format!(
+                        "unknown proof policy: {}",
+                        policy
+                    ))))
+                }
+            }
+        }
+        Ok(ProofPolicies::new(policies))
+    }
+
+    /// Return new ProofPolicies chain without any policies, accepting all
+    /// proofs
+    pub fn disabled() -> ProofPolicies {
+        ProofPolicies::new(vec![])
+    }
+
+    /// Append a policy to the chain
+    pub fn push(&mut self, policy: Box<dyn ProofPolicy>) {
+        self.policies.push(policy)
+    }
+
+    /// Check a challenge proof against all the policies of the chain, returning
+    /// the results of all the policies if any of them rejects the proof
+    pub fn check(&self, proof: &ChallengeProof, context: &ProofContext) -> std::result::Result<(), Vec<PolicyResult>> {
+        let results: Vec<PolicyResult> = self
+            .policies
+            .iter()
+            .map(|policy| PolicyResult {
+                policy: policy.name(),
+                rejection: policy.check(proof, context).err(),
+            })
+            .collect();
+        if results.iter().any(|result| result.rejection.is_some()) {
+            return Err(results);
+        }
+        Ok(())
+    }
+}
+
+/// Get the rejection reason returned to guardnodes for proofs rejected by the
+/// policy chain, naming the first policy that rejected the proof
+pub fn policy_rejection_reason(results: &[PolicyResult]) -> String {
+    match results.iter().find(|result| result.rejection.is_some()) {
+        Some(result) => format!(
+            "policy-rejected: {}: {}",
+            result.policy,
+            result.rejection.as_ref().unwrap()
+        ),
+        None => "policy-rejected".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bitcoin::hashes::{hex::FromHex, sha256d};
+
+    use crate::proof_vectors::proof_vectors;
+
+    fn gen_proof() -> ChallengeProof {
+        ChallengeProof::from_json(proof_vectors().unwrap()[0].proof.clone()).unwrap()
+    }
+
+    #[test]
+    fn ip_range_test() {
+        let range = IpRange::from_str("10.1.0.0/16").unwrap();
+        assert!(range.contains(&IpAddr::from_str("10.1.2.3").unwrap()));
+        assert!(range.contains(&IpAddr::from_str("::ffff:10.1.2.3").unwrap()));
+        assert!(!range.contains(&IpAddr::from_str("10.2.0.1").unwrap()));
+        assert!(!range.contains(&IpAddr::from_str("2001:db8::1").unwrap()));
+        assert_eq!("10.1.0.0/16", range.to_string());
+
+        let range = IpRange::from_str("2001:db8::/32").unwrap();
+        assert!(range.contains(&IpAddr::from_str("2001:db8:1::1").unwrap()));
+        assert!(!range.contains(&IpAddr::from_str("2001:db9::1").unwrap()));
+        assert!(IpRange::from_str("::1/128")
+            .unwrap()
+            .contains(&IpAddr::from_str("::1").unwrap()));
+        assert!(!IpRange::from_str("0.0.0.1")
+            .unwrap()
+            .contains(&IpAddr::from_str("::1").unwrap()));
+
+        let range = IpRange::from_str("127.0.0.1").unwrap();
+        assert_eq!(32, range.prefix_len);
+        assert!(range.contains(&IpAddr::from_str("127.0.0.1").unwrap()));
+        assert!(!range.contains(&IpAddr::from_str("127.0.0.2").unwrap()));
+        assert!(IpRange::from_str("0.0.0.0/0")
+            .unwrap()
+            .contains(&IpAddr::from_str("8.8.8.8").unwrap()));
+
+        assert!(IpRange::from_str("10.0.0.0/33").is_err());
+        assert!(IpRange::from_str("10.0.0/8").is_err());
+        assert!(IpRange::from_str("10.0.0.0/x").is_err());
+    }
+
+    #[test]
+    fn proof_policies_test() {
+        let proof = gen_proof();
+        let mut context = ProofContext {
+            remote_addr: Some(IpAddr::from_str("10.0.0.1").unwrap()),
+            challenge_age: Some(Duration::from_secs(5)),
+        };
+
+        // no policies accept all proofs
+        assert_eq!(Ok(()), ProofPolicies::disabled().check(&proof, &context));
+
+        let configs = vec![
+            ProofPolicyConfig {
+                policy: "allowed-ranges".to_owned(),
+                ranges: vec!["10.0.0.0/8".to_owned()],
+                max_delay: 0,
+            },
+            ProofPolicyConfig {
+                policy: "max-delay".to_owned(),
+                ranges: vec![],
+                max_delay: 10,
+            },
+        ];
+        let policies = ProofPolicies::from_config(&configs).unwrap();
+        assert_eq!(Ok(()), policies.check(&proof, &context));
+
+        // all policy results are returned on rejection
+        context.challenge_age = Some(Duration::from_secs(11));
+        let results = policies.check(&proof, &context).unwrap_err();
+        assert_eq!(2, results.len());
+        assert_eq!(None, results[0].rejection);
+        assert_eq!("max-delay", results[1].policy);
+        assert_eq!(
+            "policy-rejected: max-delay: proof 11000ms after challenge exceeds max delay 10000ms",
+            policy_rejection_reason(&results)
+        );
+
+        // unknown challenge time accepted but unknown address rejected
+        context.challenge_age = None;
+        context.remote_addr = None;
+        let results = policies.check(&proof, &context).unwrap_err();
+        assert_eq!(
+            vec![Some("address unknown".to_owned()), None],
+            results.into_iter().map(|result| result.rejection).collect::<Vec<_>>()
+        );
+
+        // custom policies
+        struct BidPolicy;
+        impl ProofPolicy for BidPolicy {
+            fn name(&self) -> String {
+                "bid".to_owned()
+            }
+            fn check(&self, proof: &ChallengeProof, _context: &ProofContext) -> std::result::Result<(), String> {
+                if proof.bid.txid == sha256d::Hash::from_hex(&"00".repeat(32)).unwrap() {
+                    return Err("zero bid".to_owned());
+                }
+                Ok(())
+            }
+        }
+        let mut policies = ProofPolicies::disabled();
+        policies.push(Box::new(BidPolicy));
+        assert_eq!(Ok(()), policies.check(&proof, &context));
+
+        // bad configs
+        let mut config = configs[0].clone();
+        config.ranges = vec![];
+        assert!(ProofPolicies::from_config(&[config.clone()]).is_err());
+        config.ranges = vec!["10.0.0.0/40".to_owned()];
+        assert!(ProofPolicies::from_config(&[config.clone()]).is_err());
+        config.policy = "geo".to_owned();
+        assert!(ProofPolicies::from_config(&[config]).is_err());
+        let mut config = configs[1].clone();
+        config.max_delay = 0;
+        assert!(ProofPolicies::from_config(&[config]).is_err());
+    }
+}
@@ -0,0 +1,244 @@
+//! Proof vectors
+//!
+//! Deterministic challenge proof test vectors for guardnode implementations.
+//! Each vector lists the proof inputs, the preimage and message signed, the
+//! expected DER encoded signature and the json proof body as sent to the
+//! listener /challengeproof endpoint, along with whether the listener accepts
+//! the proof signature. Signatures are deterministic (RFC6979) and low-S
+//! normalized, so implementations signing the same message with the same key
+//! are expected to produce the exact same signatures
+
+use bitcoin::hashes::hex::{FromHex, ToHex};
+use bitcoin::hashes::sha256d;
+use bitcoin::secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+use serde_json::Value;
+
+use crate::error::Result;
+use crate::interfaces::bid::Bid;
+use crate::proof::{ChallengeProof, PROOF_V2_SIGTYPE};
+
+/// Secret key of the bid of the vectors
+pub const VECTOR_BID_KEY: [u8; 32] = [0xaa; 32];
+/// Secret key other than the bid key
+pub const VECTOR_OTHER_KEY: [u8; 32] = [0xbb; 32];
+/// Bid txid of the vectors
+pub const VECTOR_BID_TXID: &str = "5b3a0c9e1f2d4b6a8c7e9f0a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e";
+/// Request txid of the v2 vectors
+pub const VECTOR_REQUEST_TXID: &str = "e1d2c3b4a5968778695a4b3c2d1e0f00112233445566778899aabbccddeeff01";
+/// Challenge hash of the vectors
+pub const VECTOR_CHALLENGE_HASH: &str = "0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20";
+
+/// Challenge proof test vector. Hashes are in their hex (rpc) representation
+/// while byte strings are in the order they are signed
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ProofVector {
+    /// Description of the case covered by the vector
+    pub description: String,
+    /// Proof version
+    pub version: u64,
+    /// Secret key that signed the proof
+    pub secret_key: String,
+    /// Request txid, for v2 proofs
+    pub request: Option<String>,
+    /// Challenge hash
+    pub hash: String,
+    /// Bid txid
+    pub txid: String,
+    /// Bid pubkey
+    pub pubkey: String,
+    /// Signature type, for v2 proofs
+    pub sigtype: Option<String>,
+    /// Preimage of the signed message
+    pub preimage: String,
+    /// Message signed
+    pub message: String,
+    /// DER encoded signature
+    pub sig: String,
+    /// Proof json body
+    pub proof: Value,
+    /// Whether the proof signature is valid
+    pub valid: bool,
+}
+
+/// Build a test vector for the bid proof of a challenge signed over message
+/// with the secret key. The preimage is that of the correct proof message,
+/// regardless of the message actually signed
+fn vector(
+    description: &str,
+    request: Option<sha256d::Hash>,
+    hash: &sha256d::Hash,
+    bid: &Bid,
+    message: &Message,
+    secret_key: &[u8; 32],
+) -> Result<ProofVector> {
+    let secp = Secp256k1::new();
+    let sig = secp
+        .sign(message, &SecretKey::from_slice(secret_key)?)
+        .serialize_der()
+        .to_hex();
+    let sigtype = request.map(|_| PROOF_V2_SIGTYPE.to_owned());
+    let mut proof = serde_json::json!({
+        "txid": bid.txid.to_string(),
+        "pubkey": bid.pubkey.to_string(),
+        "hash": hash.to_string(),
+        "sig": sig,
+    });
+    if let Some(request) = request {
+        proof["version"] = 2.into();
+        proof["request"] = request.to_string().into();
+        proof["sigtype"] = PROOF_V2_SIGTYPE.into();
+    }
+    let valid = verify_proof(proof.clone()).is_ok();
+    Ok(ProofVector {
+        description: description.to_owned(),
+        version: request.map_or(1, |_| 2),
+        secret_key: secret_key[..].to_hex(),
+        request: request.map(|request| request.to_string()),
+        hash: hash.to_string(),
+        txid: bid.txid.to_string(),
+        pubkey: bid.pubkey.to_string(),
+        sigtype,
+        preimage: ChallengeProof::signed_preimage(request.as_ref(), hash, bid, None).to_hex(),
+        message: message[..].to_hex(),
+        sig,
+        proof,
+        valid,
+    })
+}
+
+/// Generate the canonical challenge proof test vectors, covering valid v1 and
+/// v2 proofs and the invalid proofs of common signing mistakes
+pub fn proof_vectors() -> Result<Vec<ProofVector>> {
+    let bid = Bid {
+        txid: sha256d::Hash::from_hex(VECTOR_BID_TXID)?,
+        pubkey: vector_bid_pubkey()?,
+        payment: None,
+    };
+    let request = sha256d::Hash::from_hex(VECTOR_REQUEST_TXID)?;
+    let hash = sha256d::Hash::from_hex(VECTOR_CHALLENGE_HASH)?;
+    let v1_message = ChallengeProof::signed_message(None, &hash, &bid, None)?;
+    let v2_message = ChallengeProof::signed_message(Some(&request), &hash, &bid, None)?;
+    // challenge hash bytes in hex order instead of internal byte order
+    let rpc_order_message = Message::from_slice(&Vec::<u8>::from_hex(VECTOR_CHALLENGE_HASH)?)?;
+
+    Ok(vec![
+        vector(
+            "v1 proof signing the challenge hash",
+            None,
+            &hash,
+            &bid,
+            &v1_message,
+            &VECTOR_BID_KEY,
+        )?,
+        vector(
+            "v2 proof signing the hash of the request, challenge, bid and sigtype",
+            Some(request),
+            &hash,
+            &bid,
+            &v2_message,
+            &VECTOR_BID_KEY,
+        )?,
+        vector(
+            "v1 proof signed with a key other than the bid key",
+            None,
+            &hash,
+            &bid,
+            &v1_message,
+            &VECTOR_OTHER_KEY,
+        )?,
+        vector(
+            "v1 proof signing the challenge hash bytes in hex order",
+            None,
+            &hash,
+            &bid,
+            &rpc_order_message,
+            &VECTOR_BID_KEY,
+        )?,
+        vector(
+            "v2 proof signing the v1 message",
+            Some(request),
+            &hash,
+            &bid,
+            &v1_message,
+            &VECTOR_BID_KEY,
+        )?,
+    ])
+}
+
+/// Verify the signature of a json challenge proof as the listener does,
+/// without requiring an active challenge
+pub fn verify_proof(proof: Value) -> Result<()> {
+    ChallengeProof::verify(&ChallengeProof::from_json(proof)?)
+}
+
+/// Pubkey of the bid of the vectors
+pub fn vector_bid_pubkey() -> Result<PublicKey> {
+    let secp = Secp256k1::new();
+    Ok(PublicKey::from_secret_key(
+        &secp,
+        &SecretKey::from_slice(&VECTOR_BID_KEY)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use bitcoin::hashes::Hash;
+
+    use crate::util::testing::setup_logger;
+
+    #[test]
+    fn proof_vectors_test() {
+        setup_logger();
+        let vectors = proof_vectors().unwrap();
+        // vectors are deterministic
+        assert_eq!(vectors, proof_vectors().unwrap());
+        assert_eq!(
+            vec![true, true, false, false, false],
+            vectors.iter().map(|vector| vector.valid).collect::<Vec<bool>>()
+        );
+
+        let pubkey = vector_bid_pubkey().unwrap();
+        assert_eq!(
+            PublicKey::from_str("026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3").unwrap(),
+            pubkey
+        );
+
+        // v1 message is the challenge hash in internal byte order
+        let v1 = &vectors[0];
+        assert_eq!(v1.preimage, v1.message);
+        assert_eq!(
+            "201f1e1d1c1b1a191817161514131211100f0e0d0c0b0a090807060504030201",
+            v1.message
+        );
+        assert!(v1.proof.get("version").is_none());
+
+        // v2 message is the sha256d of request, challenge, bid txid, pubkey
+        // and sigtype
+        let v2 = &vectors[1];
+        let mut preimage = Vec::<u8>::from_hex(VECTOR_REQUEST_TXID).unwrap();
+        preimage.reverse();
+        let mut hash = Vec::<u8>::from_hex(VECTOR_CHALLENGE_HASH).unwrap();
+        hash.reverse();
+        preimage.extend(hash);
+        let mut txid = Vec::<u8>::from_hex(VECTOR_BID_TXID).unwrap();
+        txid.reverse();
+        preimage.extend(txid);
+        preimage.extend(&pubkey.serialize()[..]);
+        preimage.extend(b"ecdsa");
+        assert_eq!(preimage.to_hex(), v2.preimage);
+        assert_eq!(sha256d::Hash::hash(&preimage)[..].to_hex(), v2.message);
+        assert_eq!(2, v2.proof["version"]);
+        assert_eq!(VECTOR_REQUEST_TXID, v2.proof["request"]);
+        assert_eq!("ecdsa", v2.proof["sigtype"]);
+
+        // the invalid sigs are rejected by the verify helper
+        for vector in vectors.iter() {
+            assert_eq!(vector.valid, verify_proof(vector.proof.clone()).is_ok());
+            assert_eq!(VECTOR_CHALLENGE_HASH, vector.proof["hash"]);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Reconciliation
+//!
+//! Reconciliation of request responses against the challenge proofs published
+//! by guardnodes on the client chain
+
+use std::collections::{HashMap, HashSet};
+
+use bitcoin::hashes::sha256d;
+
+use crate::error::Result;
+use crate::interfaces::clientchain::ClientChain;
+use crate::interfaces::request::Request;
+use crate::interfaces::response::{BidReconciliation, ResponseReconciliation};
+use crate::interfaces::storage::Storage;
+
+/// Reconcile the stored response of a request against the proofs published on
+/// the client chain from the request client chain start height up to the
+/// current height. Only proofs for challenges of the request and bids of the
+/// request are counted, with at most one proof per challenge for each bid. The
+/// reconciliation is stored and returned, or None if the request has no
+/// response
+pub fn reconcile_response<K: ClientChain, D: Storage>(
+    clientchain: &K,
+    storage: &D,
+    request: &Request,
+) -> Result<Option<ResponseReconciliation>> {
+    let response = match storage.get_response(request.txid)? {
+        Some(response) => response,
+        None => return Ok(None),
+    };
+    info!("Reconciling request {} response with on-chain proofs", request.txid);
+
+    let clientchain_height = clientchain.get_blockheight()?;
+    let challenges: HashSet<&sha256d::Hash> = response.challenges.iter().collect();
+    let mut onchain: HashMap<sha256d::Hash, HashSet<sha256d::Hash>> = HashMap::new();
+    for proof in clientchain.get_published_proofs(request.start_blockheight_clientchain, clientchain_height)? {
+        if challenges.contains(&proof.challenge) {
+            let _ = onchain
+                .entry(proof.bid)
+                .or_insert(HashSet::new())
+                .insert(proof.challenge);
+        }
+    }
+
+    let bids = storage
+        .get_bids(request.txid)?
+        .iter()
+        .map(|bid| {
+            BidReconciliation::new(
+                bid.txid,
+                response.bid_responses.get(&bid.txid).cloned().unwrap_or(0),
+                onchain.get(&bid.txid).map_or(0, |challenges| challenges.len() as u32),
+            )
+        })
+        .collect();
+    let reconciliation = ResponseReconciliation {
+        clientchain_height,
+        bids,
+    };
+    for bid in reconciliation.divergent() {
+        warn!(
+            "Bid {} responses diverge: {} received by listener, {} published on-chain",
+            bid.txid, bid.listener_responses, bid.onchain_responses
+        );
+    }
+    info!(
+        "Reconciliation found {} divergent bids",
+        reconciliation.divergent().len()
+    );
+    storage.save_response_reconciliation(request.txid, &reconciliation)?;
+    Ok(Some(reconciliation))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::interfaces::clientchain::PublishedProof;
+    use crate::interfaces::mocks::clientchain::MockClientChain;
+    use crate::interfaces::mocks::storage::MockStorage;
+    use crate::interfaces::response::Response;
+    use crate::util::testing::{gen_challenge_state, gen_dummy_hash, setup_logger};
+
+    #[test]
+    fn reconcile_response_test() {
+        setup_logger();
+        let clientchain = MockClientChain::new();
+        let storage = MockStorage::new();
+        let _ = clientchain.height.replace(10);
+
+        let mut state = gen_challenge_state(&gen_dummy_hash(1));
+        state.request.start_blockheight_clientchain = 2;
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let bid = state.bids.iter().next().unwrap().txid;
+
+        // no response
+        assert_eq!(
+            None,
+            reconcile_response(&clientchain, &storage, &state.request).unwrap()
+        );
+
+        let mut response = Response::new();
+        response.num_challenges = 2;
+        response.challenges = vec![gen_dummy_hash(6), gen_dummy_hash(7)];
+        let _ = response.bid_responses.insert(bid, 2);
+        storage.save_response(state.request.txid, &response).unwrap();
+
+        // no proofs published
+        let reconciliation = reconcile_response(&clientchain, &storage, &state.request)
+            .unwrap()
+            .unwrap();
+        assert_eq!(10, reconciliation.clientchain_height);
+        assert_eq!(vec![BidReconciliation::new(bid, 2, 0)], reconciliation.bids);
+        assert!(reconciliation.bids[0].is_divergent);
+        assert_eq!(
+            Some(reconciliation),
+            storage.get_response_reconciliation(state.request.txid).unwrap()
+        );
+
+        // duplicate proofs, proofs of other challenges and proofs before the
+        // request start are not counted
+        let proof = |height: u32, challenge: sha256d::Hash| PublishedProof {
+            txid: gen_dummy_hash(height as u8),
+            height,
+            challenge,
+            bid,
+        };
+        *clientchain.published_proofs.borrow_mut() = vec![
+            proof(1, gen_dummy_hash(7)),
+            proof(3, gen_dummy_hash(6)),
+            proof(4, gen_dummy_hash(6)),
+            proof(5, gen_dummy_hash(8)),
+        ];
+        let reconciliation = reconcile_response(&clientchain, &storage, &state.request)
+            .unwrap()
+            .unwrap();
+        assert_eq!(vec![BidReconciliation::new(bid, 2, 1)], reconciliation.bids);
+
+        // all challenge proofs published
+        clientchain
+            .published_proofs
+            .borrow_mut()
+            .push(proof(6, gen_dummy_hash(7)));
+        let reconciliation = reconcile_response(&clientchain, &storage, &state.request)
+            .unwrap()
+            .unwrap();
+        assert_eq!(0, reconciliation.divergent().len());
+        assert_eq!(
+            Some(reconciliation),
+            storage.get_response_reconciliation(state.request.txid).unwrap()
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! Refunds
+//!
+//! Refunds of the service chain bid locks of finished requests. Once the lock
+//! of a request bid expires, a refund transaction paying the locked amount
+//! back to the bid lock key is either signed by the service chain wallet and
+//! broadcast or exported unsigned for the bid owner to sign. Refunds are
+//! stored separately from the fee payments of the bids
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use bitcoin::hashes::{hex::FromHex, sha256d};
+use bitcoin::secp256k1::PublicKey;
+use futures::sync::oneshot;
+use serde_json::Value;
+
+use crate::config::{RefundsConfig, ServiceConfig};
+use crate::error::{CError, Error, Result};
+use crate::interfaces::bid::{BidLock, BidRefund, BidRefundState};
+use crate::interfaces::request::Request;
+use crate::interfaces::service::{RpcService, Service};
+use crate::interfaces::storage::Storage;
+use crate::util::scheduler::{JobStatus, Schedule, Scheduler};
+use crate::util::{handler::Handle, ocean::OceanClient};
+
+/// Sequence of refund transaction inputs, below final so that the transaction
+/// locktime is enforced as required to spend the bid lock
+const REFUND_INPUT_SEQUENCE: u32 = 0xffff_fffe;
+
+/// Get the expired locks of the bids of a finished request that have not been
+/// refunded yet, given the service chain height
+pub fn due_bid_locks<T: Service>(
+    service: &T,
+    storage: &dyn Storage,
+    request: &Request,
+    service_height: u64,
+    refunded: &HashSet<sha256d::Hash>,
+) -> Result<Vec<BidLock>> {
+    let mut locks = vec![];
+    if request.end_blockheight as u64 >= service_height {
+        return Ok(locks);
+    }
+    for bid in storage.get_bids(request.txid)? {
+        if refunded.contains(&bid.txid) {
+            continue;
+        }
+        match service.get_bid_lock(&bid.txid)? {
+            Some(lock) => {
+                if lock.is_expired(service_height) {
+                    locks.push(lock);
+                }
+            }
+            None => warn!("bid {} lock not found", bid.txid),
+        }
+    }
+    Ok(locks)
+}
+
+/// Refunds struct holding data and logic required to refund the expired bid
+/// locks of finished requests
+pub struct Refunds {
+    /// Ocean rpc connectivity to the service chain wallet
+    client: OceanClient,
+    /// Service chain connectivity for bid locks and the service chain height
+    service: RpcService,
+    /// Thread safe storage instance
+    storage: Arc<dyn Storage + Send + Sync>,
+    /// Refunds config
+    config: RefundsConfig,
+    /// Genesis hash of the clientchain
+    genesis_hash: sha256d::Hash,
+}
+
+impl Refunds {
+    /// Return new Refunds instance for the service chain wallet
+    pub fn new(
+        config: RefundsConfig,
+        service_config: &ServiceConfig,
+        storage: Arc<dyn Storage + Send + Sync>,
+        genesis_hash: sha256d::Hash,
+    ) -> Result<Refunds> {
+        let client = OceanClient::with_credentials(service_config.host.clone(), service_config.rpc_credentials())?;
+        Ok(Refunds {
+            client,
+            service: RpcService::new(service_config)?,
+            storage,
+            config,
+            genesis_hash,
+        })
+    }
+
+    /// Get the service chain pay to pubkey hash address of a bid lock key
+    fn refund_address(&self, pubkey: &PublicKey) -> Result<String> {
+        // pay to pubkey script of the compressed lock key
+        let script = format!("21{}ac", pubkey);
+        let decoded: Value = self.client.call("decodescript", &[Value::from(script)])?;
+        match decoded["addresses"][0].as_str() {
+            Some(address) => Ok(address.to_owned()),
+            None => Err(Error::from(CError::Generic(format!(
+                "no refund address for lock key {}",
+                pubkey
+            )))),
+        }
+    }
+
+    /// Build the refund transaction of an expired bid lock, signing and
+    /// broadcasting it if configured. Refunds that the wallet cannot sign are
+    /// exported unsigned
+    fn refund_lock(&self, request: &Request, lock: &BidLock) -> Result<BidRefund> {
+        let address = self.refund_address(&lock.pubkey)?;
+        let mut outs = HashMap::new();
+        let _ = outs.insert(address.clone(), lock.value.as_btc());
+        let mut outs_assets = HashMap::new();
+        let _ = outs_assets.insert(address.clone(), lock.asset.clone());
+        // no fees as with challenge transactions of the policy asset
+        let tx_hex: String = self.client.call(
+            "createrawtransaction",
+            &[
+                serde_json::json!([{
+                    "txid": lock.txid.to_string(),
+                    "vout": lock.vout,
+                    "sequence": REFUND_INPUT_SEQUENCE,
+                }]),
+                serde_json::json!(outs),
+                Value::from(lock.lock_height),
+                serde_json::json!(outs_assets),
+            ],
+        )?;
+
+        let mut refund = BidRefund {
+            txid: lock.txid,
+            request: request.txid,
+            genesis_blockhash: request.genesis_blockhash,
+            lock_height: lock.lock_height,
+            amount: lock.value,
+            address,
+            state: BidRefundState::Exported,
+            refund_txid: None,
+            tx_hex,
+        };
+        if self.config.broadcast {
+            let signed: Value = self
+                .client
+                .call("signrawtransaction", &[Value::from(refund.tx_hex.clone())])?;
+            match (signed["complete"].as_bool(), signed["hex"].as_str()) {
+                (Some(true), Some(signed_hex)) => {
+                    let refund_txid: String = self.client.call("sendrawtransaction", &[Value::from(signed_hex)])?;
+                    refund.state = BidRefundState::Broadcast;
+                    refund.refund_txid = Some(sha256d::Hash::from_hex(&refund_txid)?);
+                    refund.tx_hex = signed_hex.to_owned();
+                }
+                _ => warn!("bid {} refund not signed by wallet, exporting unsigned", lock.txid),
+            }
+        }
+        Ok(refund)
+    }
+
+    /// Refund the expired bid locks of a finished request that have not been
+    /// refunded yet, storing each refund
+    pub fn refund_request(&self, request: &Request, service_height: u64) -> Result<Vec<BidRefund>> {
+        let refunded: HashSet<sha256d::Hash> = self
+            .storage
+            .get_bid_refunds(Some(request.genesis_blockhash))?
+            .into_iter()
+            .filter(|refund| refund.request == request.txid)
+            .map(|refund| refund.txid)
+            .collect();
+        let mut refunds = vec![];
+        for lock in due_bid_locks(&self.service, self.storage.as_ref(), request, service_height, &refunded)? {
+            let refund = self.refund_lock(request, &lock)?;
+            self.storage.save_bid_refund(&refund)?;
+            info!("bid {} lock refund {}", refund.txid, refund.state);
+            refunds.push(refund);
+        }
+        Ok(refunds)
+    }
+
+    /// Refund the expired bid locks of all finished requests. Failures of a
+    /// request are logged and retried on the next check
+    fn do_refunds(&self) -> Result<()> {
+        let service_height = self.service.get_blockheight()?;
+        for request in self.storage.get_requests(None, Some(self.genesis_hash), None, None)? {
+            if let Err(e) = self.refund_request(&request, service_height) {
+                warn!("request {} refunds failed: {}", request.txid, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Main refunds method checking for expired bid locks every refunds
+    /// interval until a shutdown signal is received
+    fn run(&self, mut kill_recv: oneshot::Receiver<()>) -> Result<()> {
+        let mut scheduler = Scheduler::new();
+        let _ = scheduler.add(
+            "bid refunds",
+            Schedule::Interval(Duration::from_secs(self.config.interval)),
+            || {
+                if let Err(e) = self.do_refunds() {
+                    warn!("bid refunds failed: {}", e);
+                }
+                Ok(JobStatus::Continue)
+            },
+        );
+        scheduler.run(Some(&mut kill_recv))
+    }
+}
+
+/// Run bid lock refunds in a separate thread, if the refunds interval has been
+/// set
+pub fn run_refunds<'a>(
+    config: RefundsConfig,
+    service_config: &ServiceConfig,
+    storage: Arc<dyn Storage + Send + Sync>,
+    genesis_hash: sha256d::Hash,
+) -> Result<Option<Handle<'a>>> {
+    if config.interval == 0 {
+        return Ok(None);
+    }
+    let refunds = Refunds::new(config, service_config, storage, genesis_hash)?;
+    let (tx, rx) = oneshot::channel();
+    Ok(Some(Handle::new(
+        tx,
+        None,
+        thread::spawn(move || {
+            if let Err(err) = refunds.run(rx) {
+                error! {"refunds error: {}", err};
+            }
+        }),
+        "REFUNDS",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::interfaces::bid::{Bid, BidSet};
+    use crate::interfaces::mocks::service::MockService;
+    use crate::interfaces::mocks::storage::MockStorage;
+    use crate::util::testing::{gen_challenge_state, gen_dummy_hash, setup_logger};
+
+    #[test]
+    fn due_bid_locks_test() {
+        setup_logger();
+        let mut service = MockService::new();
+        let storage = MockStorage::new();
+        let state = gen_challenge_state(&gen_dummy_hash(1));
+        let pubkey = state.bids.iter().next().unwrap().pubkey;
+        let mut bids = BidSet::new();
+        for i in 1..3 {
+            let _ = bids.insert(Bid {
+                txid: gen_dummy_hash(i),
+                pubkey,
+                payment: None,
+            });
+        }
+        storage.save_challenge_request_state(&state.request, &bids).unwrap();
+        // mock bid locks expire at the mock request end height
+        service.request.borrow_mut().end_blockheight = 8;
+
+        // request not finished
+        let refunded = HashSet::new();
+        assert_eq!(
+            0,
+            due_bid_locks(&service, &storage, &state.request, 5, &refunded)
+                .unwrap()
+                .len()
+        );
+
+        // request finished but locks not expired
+        assert_eq!(
+            0,
+            due_bid_locks(&service, &storage, &state.request, 7, &refunded)
+                .unwrap()
+                .len()
+        );
+
+        // expired locks
+        let locks = due_bid_locks(&service, &storage, &state.request, 8, &refunded).unwrap();
+        assert_eq!(2, locks.len());
+        assert!(locks.iter().all(|lock| lock.lock_height == 8));
+
+        // refunded bids are skipped
+        let refunded: HashSet<sha256d::Hash> = vec![gen_dummy_hash(1)].into_iter().collect();
+        let locks = due_bid_locks(&service, &storage, &state.request, 8, &refunded).unwrap();
+        assert_eq!(
+            vec![gen_dummy_hash(2)],
+            locks.iter().map(|lock| lock.txid).collect::<Vec<_>>()
+        );
+
+        // locks not found
+        service.return_none = true;
+        assert_eq!(
+            0,
+            due_bid_locks(&service, &storage, &state.request, 8, &refunded)
+                .unwrap()
+                .len()
+        );
+        service.return_none = false;
+        service.return_err = true;
+        assert!(due_bid_locks(&service, &storage, &state.request, 8, &refunded).is_err());
+    }
+}
@@ -0,0 +1,186 @@
+//! Registry
+//!
+//! Registry of the challenge states of the requests being challenged, shared
+//! by the coordinator loop registering each request as it starts and the
+//! listener routing challenge proofs to the challenge state of their request
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+use bitcoin::hashes::sha256d;
+
+use crate::challenger::ChallengeState;
+
+/// Registry of the challenge states of the requests being challenged, by
+/// request txid
+pub struct ChallengeRegistry {
+    /// Challenge states by request txid, along with the id of their
+    /// registration
+    states: RwLock<HashMap<sha256d::Hash, (usize, Arc<RwLock<Option<ChallengeState>>>)>>,
+    /// Id of the next registration
+    next_id: AtomicUsize,
+}
+
+impl ChallengeRegistry {
+    /// Return new ChallengeRegistry instance without any registered requests
+    pub fn new() -> ChallengeRegistry {
+        ChallengeRegistry {
+            states: RwLock::new(HashMap::new()),
+            next_id: AtomicUsize::new(0),
+        }
+    }
+
+    /// Register the challenge state of a request, replacing any challenge
+    /// state registered for the request. The request is unregistered once the
+    /// returned registration is dropped, unless it was registered again in
+    /// the meantime, e.g. by the challenger loop replacing a stalled loop
+    pub fn register(
+        &self,
+        request: sha256d::Hash,
+        challenge: Arc<RwLock<Option<ChallengeState>>>,
+    ) -> ChallengeRegistration {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let _ = self.states.write().unwrap().insert(request, (id, challenge));
+        ChallengeRegistration {
+            registry: self,
+            request,
+            id,
+        }
+    }
+
+    /// Get the challenge state registered for a request
+    pub fn get(&self, request: &sha256d::Hash) -> Option<Arc<RwLock<Option<ChallengeState>>>> {
+        self.states
+            .read()
+            .unwrap()
+            .get(request)
+            .map(|(_, challenge)| challenge.clone())
+    }
+
+    /// Get the txids of the registered requests
+    pub fn requests(&self) -> Vec<sha256d::Hash> {
+        self.states.read().unwrap().keys().cloned().collect()
+    }
+
+    /// Route a challenge proof to the challenge state of its request. Proofs
+    /// routed by request txid, via the listener path or the v2 proof request,
+    /// are only routed to that request, while unrouted v1 proofs are routed to
+    /// the request whose latest challenge is the proof challenge hash or else
+    /// to the only registered request. Returns the rejection reason if the
+    /// proof cannot be routed
+    pub fn route(
+        &self,
+        request: Option<sha256d::Hash>,
+        hash: &sha256d::Hash,
+    ) -> std::result::Result<Arc<RwLock<Option<ChallengeState>>>, String> {
+        let states = self.states.read().unwrap();
+        if states.is_empty() {
+            return Err("no-active-challenge".to_owned());
+        }
+        if let Some(request) = request {
+            return states
+                .get(&request)
+                .map(|(_, challenge)| challenge.clone())
+                .ok_or_else(|| "bad-request".to_owned());
+        }
+        let challenged = states.values().map(|(_, challenge)| challenge).find(|challenge| {
+            challenge
+                .read()
+                .unwrap()
+                .as_ref()
+                .map_or(false, |ch| ch.latest_challenge.hash() == Some(*hash))
+        });
+        match challenged {
+            Some(challenge) => Ok(challenge.clone()),
+            None if states.len() == 1 => Ok(states.values().next().unwrap().1.clone()),
+            None => Err("bad-hash".to_owned()),
+        }
+    }
+}
+
+/// Registration of the challenge state of a request, unregistering the request
+/// when dropped
+pub struct ChallengeRegistration<'a> {
+    /// Registry that the request is registered in
+    registry: &'a ChallengeRegistry,
+    /// Request txid
+    request: sha256d::Hash,
+    /// Registration id
+    id: usize,
+}
+
+impl<'a> Drop for ChallengeRegistration<'a> {
+    fn drop(&mut self) {
+        let mut states = self.registry.states.write().unwrap();
+        if states.get(&self.request).map_or(false, |(id, _)| *id == self.id) {
+            let _ = states.remove(&self.request);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::util::testing::{gen_challenge_state, gen_challenge_state_with_challenge, gen_dummy_hash};
+
+    #[test]
+    fn route_test() {
+        let registry = ChallengeRegistry::new();
+        let chl_hash = gen_dummy_hash(5);
+        assert_eq!("no-active-challenge", registry.route(None, &chl_hash).err().unwrap());
+
+        let state = gen_challenge_state_with_challenge(&gen_dummy_hash(0), &chl_hash);
+        let request = state.request.txid;
+        let challenge = Arc::new(RwLock::new(Some(state)));
+        {
+            let _registration = registry.register(request, challenge.clone());
+            assert_eq!(vec![request], registry.requests());
+
+            // routed by request txid
+            assert!(Arc::ptr_eq(
+                &challenge,
+                &registry.route(Some(request), &chl_hash).unwrap()
+            ));
+            assert_eq!(
+                "bad-request",
+                registry.route(Some(gen_dummy_hash(9)), &chl_hash).err().unwrap()
+            );
+
+            // unrouted proofs go to the only request regardless of hash
+            assert!(Arc::ptr_eq(&challenge, &registry.route(None, &chl_hash).unwrap()));
+            assert!(Arc::ptr_eq(
+                &challenge,
+                &registry.route(None, &gen_dummy_hash(9)).unwrap()
+            ));
+
+            // or to the request whose latest challenge they respond to
+            let other_state = gen_challenge_state(&gen_dummy_hash(1));
+            let other_request = other_state.request.txid;
+            let other_challenge = Arc::new(RwLock::new(Some(other_state)));
+            let _other_registration = registry.register(other_request, other_challenge.clone());
+            assert_eq!(2, registry.requests().len());
+            assert!(Arc::ptr_eq(&challenge, &registry.route(None, &chl_hash).unwrap()));
+            assert!(Arc::ptr_eq(
+                &other_challenge,
+                &registry.route(None, &gen_dummy_hash(0)).unwrap()
+            ));
+            assert_eq!("bad-hash", registry.route(None, &gen_dummy_hash(9)).err().unwrap());
+            assert!(Arc::ptr_eq(
+                &other_challenge,
+                &registry.route(Some(other_request), &chl_hash).unwrap()
+            ));
+        }
+
+        // requests are unregistered once their registration is dropped
+        assert!(registry.requests().is_empty());
+        assert!(registry.get(&request).is_none());
+
+        // but not by a registration replaced by registering the request again
+        let registration = registry.register(request, challenge.clone());
+        let _new_registration = registry.register(request, challenge.clone());
+        drop(registration);
+        assert_eq!(vec![request], registry.requests());
+    }
+}
@@ -0,0 +1,285 @@
+//! Rerun
+//!
+//! Recomputation of the stored response of a request from the append-only per
+//! challenge records, or from the responses saved in the journal for requests
+//! without records, reporting where the stored aggregate diverges from the
+//! recomputed one so that disputed results can be resolved without editing the
+//! stored response by hand
+
+use std::collections::BTreeSet;
+
+use bitcoin::hashes::sha256d;
+use serde::Serialize;
+
+use crate::error::{CError, Result};
+use crate::interfaces::response::Response;
+use crate::interfaces::storage::Storage;
+use crate::journal::{Journal, JournalEntry, JournalEvent};
+
+/// Source that a response is recomputed from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RerunSource {
+    /// Per challenge records of the request
+    Records,
+    /// Responses saved for the request in the journal
+    Journal,
+}
+
+/// Number of challenges responded to by a bid in the stored and the
+/// recomputed response
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BidRerun {
+    /// Bid txid
+    pub txid: sha256d::Hash,
+    /// Number of responses in the stored response
+    pub stored_responses: u32,
+    /// Number of responses in the recomputed response
+    pub recomputed_responses: u32,
+}
+
+/// Report of the differences between the stored response of a request and the
+/// response recomputed from its records or the journal
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RerunReport {
+    /// Request txid
+    pub request: sha256d::Hash,
+    /// Source that the response was recomputed from
+    pub source: RerunSource,
+    /// Number of challenges of the stored response
+    pub stored_challenges: u32,
+    /// Number of challenges of the recomputed response
+    pub recomputed_challenges: u32,
+    /// Recomputed challenges missing from the stored response
+    pub missing_challenges: Vec<sha256d::Hash>,
+    /// Stored challenges not found in the recomputed response
+    pub unknown_challenges: Vec<sha256d::Hash>,
+    /// Bids whose number of responses differs, sorted by txid
+    pub bids: Vec<BidRerun>,
+}
+
+impl RerunReport {
+    /// Compare the stored response of a request with the recomputed response
+    pub fn new(request: sha256d::Hash, source: RerunSource, stored: &Response, recomputed: &Response) -> RerunReport {
+        let txids: BTreeSet<&sha256d::Hash> = stored
+            .bid_responses
+            .keys()
+            .chain(recomputed.bid_responses.keys())
+            .collect();
+        let bids = txids
+            .into_iter()
+            .map(|txid| BidRerun {
+                txid: *txid,
+                stored_responses: stored.bid_responses.get(txid).cloned().unwrap_or(0),
+                recomputed_responses: recomputed.bid_responses.get(txid).cloned().unwrap_or(0),
+            })
+            .filter(|bid| bid.stored_responses != bid.recomputed_responses)
+            .collect();
+        RerunReport {
+            request,
+            source,
+            stored_challenges: stored.num_challenges,
+            recomputed_challenges: recomputed.num_challenges,
+            missing_challenges: recomputed
+                .challenges
+                .iter()
+                .filter(|challenge| !stored.challenges.contains(challenge))
+                .cloned()
+                .collect(),
+            unknown_challenges: stored
+                .challenges
+                .iter()
+                .filter(|challenge| !recomputed.challenges.contains(challenge))
+                .cloned()
+                .collect(),
+            bids,
+        }
+    }
+
+    /// Check whether the stored response matches the recomputed response
+    pub fn matches(&self) -> bool {
+        self.stored_challenges == self.recomputed_challenges
+            && self.missing_challenges.is_empty()
+            && self.unknown_challenges.is_empty()
+            && self.bids.is_empty()
+    }
+}
+
+/// Recompute the response of a request from the responses saved for it in the
+/// journal entries, in the order they were recorded. Responses saved again for
+/// the same challenge are only counted once
+pub fn journal_response(request: &sha256d::Hash, entries: &[JournalEntry]) -> Response {
+    let mut response = Response::new();
+    for entry in entries {
+        if let JournalEvent::ResponseSaved {
+            request: saved_request,
+            challenge,
+            bids,
+            ..
+        } = &entry.event
+        {
+            if saved_request != request || response.challenges.contains(challenge) {
+                continue;
+            }
+            response.challenges.push(*challenge);
+            response.update(&bids.iter().cloned().collect());
+        }
+    }
+    response
+}
+
+/// Recompute the stored response of a request from its per challenge records,
+/// or from the journal at journal_path if the request has no records, and
+/// report the differences. Nothing is written to storage. Returns None if the
+/// request has no stored response
+pub fn rerun_response<D: Storage + ?Sized>(
+    storage: &D,
+    request: sha256d::Hash,
+    journal_path: &Option<String>,
+) -> Result<Option<RerunReport>> {
+    let stored = match storage.get_response(request)? {
+        Some(response) => response,
+        None => return Ok(None),
+    };
+    let records = storage.get_challenge_records(request)?;
+    let (source, recomputed) = if !records.is_empty() {
+        let mut recomputed = Response::new();
+        let _ = recomputed.apply_records(&records);
+        (RerunSource::Records, recomputed)
+    } else {
+        match journal_path {
+            Some(path) => (RerunSource::Journal, journal_response(&request, &Journal::read(path)?)),
+            None => {
+                return Err(CError::Generic(format!(
+                    "request {} has no challenge records and no journal is configured",
+                    request
+                ))
+                .into())
+            }
+        }
+    };
+    let report = RerunReport::new(request, source, &stored, &recomputed);
+    if report.matches() {
+        info!("Request {} response matches its {:?} rerun", request, source);
+    } else {
+        warn!(
+            "Request {} response diverges from its {:?} rerun: {} stored and {} recomputed challenges, {} divergent \
+             bids",
+            request,
+            source,
+            report.stored_challenges,
+            report.recomputed_challenges,
+            report.bids.len()
+        );
+    }
+    Ok(Some(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+
+    use crate::interfaces::mocks::storage::MockStorage;
+    use crate::interfaces::response::ChallengeRecord;
+    use crate::util::testing::{gen_dummy_hash, setup_logger};
+
+    #[test]
+    fn rerun_response_test() {
+        setup_logger();
+        let storage = MockStorage::new();
+        let request = gen_dummy_hash(1);
+        let (bid_a, bid_b) = (gen_dummy_hash(2), gen_dummy_hash(3));
+
+        // no response
+        assert_eq!(None, rerun_response(&storage, request, &None).unwrap());
+
+        let mut stored = Response::new();
+        stored.challenges = vec![gen_dummy_hash(6), gen_dummy_hash(7)];
+        stored.update(&vec![bid_a, bid_b].into_iter().collect());
+        stored.update(&vec![bid_a].into_iter().collect());
+        storage.save_response(request, &stored).unwrap();
+
+        // no records or journal to recompute from
+        assert!(rerun_response(&storage, request, &None).is_err());
+
+        // matching records
+        let responders: HashSet<sha256d::Hash> = vec![bid_a, bid_b].into_iter().collect();
+        storage
+            .save_challenge_record(request, &ChallengeRecord::new(gen_dummy_hash(6), &responders))
+            .unwrap();
+        storage
+            .save_challenge_record(
+                request,
+                &ChallengeRecord::new(gen_dummy_hash(7), &vec![bid_a].into_iter().collect()),
+            )
+            .unwrap();
+        let report = rerun_response(&storage, request, &None).unwrap().unwrap();
+        assert_eq!(RerunSource::Records, report.source);
+        assert!(report.matches());
+
+        // record missing from the stored aggregate
+        storage
+            .save_challenge_record(request, &ChallengeRecord::new(gen_dummy_hash(8), &responders))
+            .unwrap();
+        let report = rerun_response(&storage, request, &None).unwrap().unwrap();
+        assert!(!report.matches());
+        assert_eq!(2, report.stored_challenges);
+        assert_eq!(3, report.recomputed_challenges);
+        assert_eq!(vec![gen_dummy_hash(8)], report.missing_challenges);
+        assert!(report.unknown_challenges.is_empty());
+        assert_eq!(
+            vec![
+                BidRerun {
+                    txid: bid_a,
+                    stored_responses: 2,
+                    recomputed_responses: 3,
+                },
+                BidRerun {
+                    txid: bid_b,
+                    stored_responses: 1,
+                    recomputed_responses: 2,
+                },
+            ],
+            report.bids
+        );
+    }
+
+    #[test]
+    fn journal_response_test() {
+        let request = gen_dummy_hash(1);
+        let bid = gen_dummy_hash(2);
+        let saved = |seq: u64, request: sha256d::Hash, challenge: sha256d::Hash| JournalEntry {
+            seq,
+            time: 0,
+            event: JournalEvent::ResponseSaved {
+                request,
+                challenge,
+                bids: vec![bid],
+                num_challenges: 0,
+            },
+        };
+        let entries = vec![
+            saved(0, request, gen_dummy_hash(6)),
+            saved(1, gen_dummy_hash(9), gen_dummy_hash(7)),
+            saved(2, request, gen_dummy_hash(8)),
+            // saved again for the same challenge
+            saved(3, request, gen_dummy_hash(8)),
+        ];
+        let response = journal_response(&request, &entries);
+        assert_eq!(2, response.num_challenges);
+        assert_eq!(vec![gen_dummy_hash(6), gen_dummy_hash(8)], response.challenges);
+        assert_eq!(Some(&2), response.bid_responses.get(&bid));
+
+        let mut stored = response.clone();
+        stored.num_challenges = 1;
+        let _ = stored.challenges.pop();
+        let _ = stored.bid_responses.insert(bid, 1);
+        let report = RerunReport::new(request, RerunSource::Journal, &stored, &response);
+        assert!(!report.matches());
+        assert_eq!(vec![gen_dummy_hash(8)], report.missing_challenges);
+        assert_eq!(1, report.bids.len());
+        assert!(RerunReport::new(request, RerunSource::Journal, &response, &response).matches());
+    }
+}
@@ -0,0 +1,429 @@
+//! Rotation
+//!
+//! Rotation of guardnode bid keys within a request. A guardnode whose bid key
+//! is compromised mid-request sends a key rotation message that replaces the
+//! pubkey of its bid in the live challenge state and in storage, signed as
+//! required by the key rotation policy. Rotations are stored along with their
+//! signatures, so that each rotation can be verified again when audited
+
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bitcoin::consensus::serialize;
+use bitcoin::hashes::hex::{FromHex, ToHex};
+use bitcoin::hashes::{sha256d, Hash};
+use bitcoin::secp256k1::{Message, PublicKey, Secp256k1, Signature};
+use serde_json::Value;
+
+use crate::challenger::ChallengeState;
+use crate::error::{CError, Error, Result};
+use crate::interfaces::bid::{Bid, BidKeyRotation};
+use crate::interfaces::storage::Storage;
+
+/// Policy of accepting bid key rotation messages
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyRotationPolicy {
+    /// Bid keys cannot be rotated
+    Disabled,
+    /// Rotations are signed by the current bid key
+    OldKey,
+    /// Rotations are signed by both the current and the new bid key
+    BothKeys,
+    /// Rotations are sent by api callers without a tenant scope and signed by
+    /// the new bid key, for bid keys that are lost or can no longer be trusted
+    Admin,
+}
+
+impl KeyRotationPolicy {
+    /// Get the policy name as used in config
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyRotationPolicy::Disabled => "disabled",
+            KeyRotationPolicy::OldKey => "old-key",
+            KeyRotationPolicy::BothKeys => "both-keys",
+            KeyRotationPolicy::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for KeyRotationPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<KeyRotationPolicy> {
+        match s {
+            "disabled" => Ok(KeyRotationPolicy::Disabled),
+            "old-key" => Ok(KeyRotationPolicy::OldKey),
+            "both-keys" => Ok(KeyRotationPolicy::BothKeys),
+            "admin" => Ok(KeyRotationPolicy::Admin),
+            _ => Err(Error::from(CError::Generic(format!(
+                "unknown key rotation policy: {}",
+                s
+            )))),
+        }
+    }
+}
+
+/// Key rotation message replacing the pubkey of a bid of a request, signed by
+/// the current and/or the new bid key
+#[derive(Debug)]
+pub struct KeyRotation {
+    /// Request txid of the bid
+    pub request: sha256d::Hash,
+    /// Bid txid
+    pub txid: sha256d::Hash,
+    /// New bid pubkey
+    pub pubkey: PublicKey,
+    /// Signature of the rotation message by the current bid key
+    pub sig: Option<Signature>,
+    /// Signature of the rotation message by the new bid key
+    pub new_sig: Option<Signature>,
+}
+
+impl KeyRotation {
+    /// Parse serde json value into KeyRotation struct result. The signatures
+    /// are optional as the signatures required depend on the policy
+    pub fn from_json(val: Value) -> Result<KeyRotation> {
+        let parse_sig = |field: &str| -> Result<Option<Signature>> {
+            match val[field].as_str() {
+                Some(sig) => Ok(Some(Signature::from_der(&Vec::<u8>::from_hex(sig)?)?)),
+                None => Ok(None),
+            }
+        };
+        Ok(KeyRotation {
+            request: sha256d::Hash::from_hex(val["request"].as_str().unwrap_or(""))?,
+            txid: sha256d::Hash::from_hex(val["txid"].as_str().unwrap_or(""))?,
+            pubkey: PublicKey::from_str(val["pubkey"].as_str().unwrap_or(""))?,
+            sig: parse_sig("sig")?,
+            new_sig: parse_sig("new_sig")?,
+        })
+    }
+
+    /// Get the message signed by key rotations; the sha256d hash of the
+    /// concatenation of the request txid, bid txid and compressed new bid
+    /// pubkey. Hashes are serialized in internal byte order, i.e. reversed
+    /// with respect to their hex representation
+    pub fn signed_message(request: &sha256d::Hash, txid: &sha256d::Hash, pubkey: &PublicKey) -> Result<Message> {
+        let mut preimage = serialize(request);
+        preimage.extend(serialize(txid));
+        preimage.extend(&pubkey.serialize()[..]);
+        Ok(Message::from_slice(&sha256d::Hash::hash(&preimage)[..])?)
+    }
+}
+
+/// Verify the signatures of a bid key rotation, requiring the signatures of
+/// the policy that the rotation was accepted under. Any other signatures are
+/// also verified if set
+pub fn verify_bid_key_rotation(rotation: &BidKeyRotation) -> Result<()> {
+    let policy = KeyRotationPolicy::from_str(&rotation.policy)?;
+    let (sig_required, new_sig_required) = match policy {
+        KeyRotationPolicy::Disabled => {
+            return Err(CError::Generic("bid key rotation is disabled".to_owned()).into());
+        }
+        KeyRotationPolicy::OldKey => (true, false),
+        KeyRotationPolicy::BothKeys => (true, true),
+        KeyRotationPolicy::Admin => (false, true),
+    };
+    let message = KeyRotation::signed_message(&rotation.request, &rotation.txid, &rotation.new_pubkey)?;
+    let secp = Secp256k1::new();
+    for &(sig, pubkey, required, key) in [
+        (&rotation.sig, &rotation.old_pubkey, sig_required, "old"),
+        (&rotation.new_sig, &rotation.new_pubkey, new_sig_required, "new"),
+    ]
+    .iter()
+    {
+        match sig {
+            Some(sig) => secp.verify(&message, &Signature::from_der(&Vec::<u8>::from_hex(sig)?)?, pubkey)?,
+            None if required => {
+                return Err(CError::Generic(format!("missing signature by the {} key", key)).into());
+            }
+            None => (),
+        }
+    }
+    Ok(())
+}
+
+/// Rotate the key of a bid of the request being challenged, as allowed by the
+/// key rotation policy. Rotations under the admin policy are only accepted
+/// from admin callers. The rotation is verified and stored, updating the
+/// stored bid, before the bid is replaced in the live challenge state, so that
+/// proofs signed by the new key are accepted from then on. Returns the stored
+/// rotation or the rejection reason
+pub fn rotate_bid_key<D: Storage + ?Sized>(
+    rotation: KeyRotation,
+    policy: KeyRotationPolicy,
+    admin: bool,
+    challenge: &Arc<RwLock<Option<ChallengeState>>>,
+    storage: &D,
+) -> std::result::Result<BidKeyRotation, String> {
+    match policy {
+        KeyRotationPolicy::Disabled => return Err("key-rotation-disabled".to_owned()),
+        KeyRotationPolicy::Admin if !admin => return Err("key-rotation-admin-only".to_owned()),
+        _ => (),
+    }
+
+    // find the current key of the bid, in memory or else among the bids
+    // spilled to storage
+    let (genesis_blockhash, bid) = match challenge.read().unwrap().as_ref() {
+        Some(ch) if ch.request.txid == rotation.request => (
+            ch.request.genesis_blockhash,
+            ch.bids.iter().find(|bid| bid.txid == rotation.txid).cloned(),
+        ),
+        Some(_) => return Err("bad-request".to_owned()),
+        None => return Err("no-active-request".to_owned()),
+    };
+    let old_pubkey = match bid {
+        Some(bid) => bid.pubkey,
+        None => match storage.get_bid(rotation.request, rotation.txid) {
+            Ok(Some(bid)) => bid.pubkey,
+            Ok(None) => return Err("bad-bid".to_owned()),
+            Err(e) => return Err(format!("storage-error: {}", e)),
+        },
+    };
+    if old_pubkey == rotation.pubkey {
+        return Err("same-key".to_owned());
+    }
+
+    let record = BidKeyRotation {
+        txid: rotation.txid,
+        request: rotation.request,
+        genesis_blockhash,
+        old_pubkey,
+        new_pubkey: rotation.pubkey,
+        sig: rotation.sig.map(|sig| sig.serialize_der().to_hex()),
+        new_sig: rotation.new_sig.map(|sig| sig.serialize_der().to_hex()),
+        policy: policy.as_str().to_owned(),
+        time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    };
+    verify_bid_key_rotation(&record).map_err(|e| format!("bad-sig: {}", e))?;
+    storage
+        .save_bid_key_rotation(&record)
+        .map_err(|e| format!("storage-error: {}", e))?;
+
+    // replace the bid in memory unless the request has ended meanwhile
+    if let Some(ch) = challenge.write().unwrap().as_mut() {
+        if ch.request.txid == record.request {
+            if let Some(bid) = ch.bids.iter().find(|bid| bid.txid == record.txid).cloned() {
+                let bids = Arc::make_mut(&mut ch.bids);
+                let _ = bids.remove(&bid);
+                let _ = bids.insert(Bid {
+                    pubkey: record.new_pubkey,
+                    ..bid
+                });
+            }
+        }
+    }
+    info!(
+        "Rotated key of bid {} of request {} to {}",
+        record.txid, record.request, record.new_pubkey
+    );
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bitcoin::secp256k1::SecretKey;
+
+    use crate::interfaces::mocks::storage::MockStorage;
+    use crate::util::testing::{gen_challenge_state, gen_dummy_hash, setup_logger};
+
+    /// Sign a key rotation message with the secret key of the given byte
+    fn sign(message: &Message, key: u8) -> Signature {
+        Secp256k1::new().sign(message, &SecretKey::from_slice(&[key; 32]).unwrap())
+    }
+
+    /// Pubkey of the secret key of the given byte
+    fn pubkey(key: u8) -> PublicKey {
+        PublicKey::from_secret_key(&Secp256k1::new(), &SecretKey::from_slice(&[key; 32]).unwrap())
+    }
+
+    #[test]
+    fn key_rotation_from_json_test() {
+        let request = gen_dummy_hash(1);
+        let txid = gen_dummy_hash(2);
+        let message = KeyRotation::signed_message(&request, &txid, &pubkey(0xbb)).unwrap();
+        let sig = sign(&message, 0xaa);
+        let json = serde_json::json!({
+            "request": request.to_string(),
+            "txid": txid.to_string(),
+            "pubkey": pubkey(0xbb).to_string(),
+            "sig": sig.serialize_der().to_hex(),
+        });
+        let rotation = KeyRotation::from_json(json.clone()).unwrap();
+        assert_eq!(request, rotation.request);
+        assert_eq!(txid, rotation.txid);
+        assert_eq!(pubkey(0xbb), rotation.pubkey);
+        assert_eq!(Some(sig), rotation.sig);
+        assert_eq!(None, rotation.new_sig);
+
+        let mut bad_json = json.clone();
+        bad_json["new_sig"] = Value::from("00");
+        assert!(KeyRotation::from_json(bad_json).is_err());
+        let mut bad_json = json;
+        bad_json["pubkey"] = Value::from("02");
+        assert!(KeyRotation::from_json(bad_json).is_err());
+    }
+
+    #[test]
+    fn rotate_bid_key_test() {
+        setup_logger();
+        let storage = MockStorage::new();
+        let state = gen_challenge_state(&gen_dummy_hash(1));
+        storage
+            .save_challenge_request_state(&state.request, &state.bids)
+            .unwrap();
+        let request = state.request.txid;
+        let bid = state.bids.iter().next().unwrap().clone();
+        let challenge = Arc::new(RwLock::new(Some(state)));
+        let message = KeyRotation::signed_message(&request, &bid.txid, &pubkey(0xbb)).unwrap();
+        let rotation = |sig_key: Option<u8>, new_sig_key: Option<u8>| KeyRotation {
+            request,
+            txid: bid.txid,
+            pubkey: pubkey(0xbb),
+            sig: sig_key.map(|key| sign(&message, key)),
+            new_sig: new_sig_key.map(|key| sign(&message, key)),
+        };
+
+        // policies rejecting rotations
+        let res = rotate_bid_key(
+            rotation(Some(0xaa), None),
+            KeyRotationPolicy::Disabled,
+            true,
+            &challenge,
+            &storage,
+        );
+        assert_eq!("key-rotation-disabled", res.unwrap_err());
+        let res = rotate_bid_key(
+            rotation(None, Some(0xbb)),
+            KeyRotationPolicy::Admin,
+            false,
+            &challenge,
+            &storage,
+        );
+        assert_eq!("key-rotation-admin-only", res.unwrap_err());
+
+        // unknown request or bid
+        let mut other = rotation(Some(0xaa), None);
+        other.request = gen_dummy_hash(9);
+        let res = rotate_bid_key(other, KeyRotationPolicy::OldKey, false, &challenge, &storage);
+        assert_eq!("bad-request", res.unwrap_err());
+        let mut other = rotation(Some(0xaa), None);
+        other.txid = gen_dummy_hash(9);
+        let res = rotate_bid_key(other, KeyRotationPolicy::OldKey, false, &challenge, &storage);
+        assert_eq!("bad-bid", res.unwrap_err());
+
+        // missing or invalid signatures
+        let res = rotate_bid_key(
+            rotation(None, Some(0xbb)),
+            KeyRotationPolicy::OldKey,
+            false,
+            &challenge,
+            &storage,
+        );
+        assert!(res.unwrap_err().starts_with("bad-sig"));
+        let res = rotate_bid_key(
+            rotation(Some(0xcc), None),
+            KeyRotationPolicy::OldKey,
+            false,
+            &challenge,
+            &storage,
+        );
+        assert!(res.unwrap_err().starts_with("bad-sig"));
+        let res = rotate_bid_key(
+            rotation(Some(0xaa), None),
+            KeyRotationPolicy::BothKeys,
+            false,
+            &challenge,
+            &storage,
+        );
+        assert!(res.unwrap_err().starts_with("bad-sig"));
+        assert_eq!(0, storage.get_bid_key_rotations(request).unwrap().len());
+
+        // rotation signed by both keys
+        let record = rotate_bid_key(
+            rotation(Some(0xaa), Some(0xbb)),
+            KeyRotationPolicy::BothKeys,
+            false,
+            &challenge,
+            &storage,
+        )
+        .unwrap();
+        assert_eq!(bid.pubkey, record.old_pubkey);
+        assert_eq!(pubkey(0xbb), record.new_pubkey);
+        assert_eq!("both-keys", record.policy);
+        assert!(verify_bid_key_rotation(&record).is_ok());
+        assert_eq!(vec![record.clone()], storage.get_bid_key_rotations(request).unwrap());
+        assert_eq!(
+            pubkey(0xbb),
+            storage.get_bid(request, bid.txid).unwrap().unwrap().pubkey
+        );
+        let rotated = Bid {
+            pubkey: pubkey(0xbb),
+            ..bid.clone()
+        };
+        {
+            let state = challenge.read().unwrap();
+            let bids = &state.as_ref().unwrap().bids;
+            assert!(bids.contains(&rotated));
+            assert!(!bids.contains(&bid));
+        }
+
+        // same key
+        let res = rotate_bid_key(
+            rotation(Some(0xbb), None),
+            KeyRotationPolicy::OldKey,
+            false,
+            &challenge,
+            &storage,
+        );
+        assert_eq!("same-key", res.unwrap_err());
+
+        // no active request
+        *challenge.write().unwrap() = None;
+        let res = rotate_bid_key(
+            rotation(Some(0xaa), None),
+            KeyRotationPolicy::OldKey,
+            false,
+            &challenge,
+            &storage,
+        );
+        assert_eq!("no-active-request", res.unwrap_err());
+    }
+
+    #[test]
+    fn verify_bid_key_rotation_test() {
+        let request = gen_dummy_hash(1);
+        let txid = gen_dummy_hash(2);
+        let message = KeyRotation::signed_message(&request, &txid, &pubkey(0xbb)).unwrap();
+        let mut rotation = BidKeyRotation {
+            txid,
+            request,
+            genesis_blockhash: gen_dummy_hash(0),
+            old_pubkey: pubkey(0xaa),
+            new_pubkey: pubkey(0xbb),
+            sig: None,
+            new_sig: Some(sign(&message, 0xbb).serialize_der().to_hex()),
+            policy: "admin".to_owned(),
+            time: 1600000000,
+        };
+        assert!(verify_bid_key_rotation(&rotation).is_ok());
+
+        // signatures required by the policy
+        rotation.policy = "old-key".to_owned();
+        assert!(verify_bid_key_rotation(&rotation).is_err());
+        rotation.sig = Some(sign(&message, 0xaa).serialize_der().to_hex());
+        assert!(verify_bid_key_rotation(&rotation).is_ok());
+
+        // signatures set are verified regardless of the policy
+        rotation.new_sig = Some(sign(&message, 0xcc).serialize_der().to_hex());
+        assert!(verify_bid_key_rotation(&rotation).is_err());
+
+        rotation.policy = "disabled".to_owned();
+        assert!(verify_bid_key_rotation(&rotation).is_err());
+    }
+}
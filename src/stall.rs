@@ -0,0 +1,212 @@
+//! Stall
+//!
+//! Detection of client chain stalls, when the client chain produces no new
+//! blocks for longer than a threshold. Challenges sent during a stall can not
+//! be included in the client chain, so challenge rounds are skipped while the
+//! client chain is stalled and resume once it produces a new block. Each stall
+//! period is recorded for the request along with the client chain blocks that
+//! were missed, so that skipped challenges can be told from coordinator
+//! failures and the client chain end height of the request accounts for them
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock::Clock;
+
+/// Period that the client chain was stalled for while a request was running
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientChainStall {
+    /// Client chain height that the client chain stalled at
+    pub clientchain_height: u32,
+    /// Service chain height that the stall was detected at
+    pub start_height: u64,
+    /// Service chain height that the stall ended at
+    pub end_height: u64,
+    /// Unix timestamp in seconds that the last client chain block before the
+    /// stall was first seen at
+    pub start_time: u64,
+    /// Unix timestamp in seconds that the stall ended at
+    pub end_time: u64,
+    /// Number of client chain blocks expected between the last block before
+    /// the stall and the end of the stall
+    pub missed_blocks: u32,
+}
+
+/// Client chain status on a stall check
+#[derive(Debug, Clone, PartialEq)]
+pub enum StallStatus {
+    /// Client chain producing blocks
+    Producing,
+    /// Client chain stalled
+    Stalled,
+    /// Client chain producing blocks again after the stall
+    Resumed(ClientChainStall),
+}
+
+/// Detector of client chain stalls from the client chain heights seen on each
+/// check
+#[derive(Debug, Clone)]
+pub struct StallDetector {
+    /// Duration without a new client chain block after which the client chain
+    /// is stalled; stalls are never detected if zero
+    threshold: Duration,
+    /// Client chain block time in seconds
+    block_time: u64,
+    /// Latest client chain height seen and the instant it was first seen at
+    latest: Option<(u32, Instant)>,
+    /// Service chain height and unix time that the ongoing stall started at
+    stall: Option<(u64, u64)>,
+}
+
+impl StallDetector {
+    /// Create a stall detector with a threshold and the client chain block
+    /// time in seconds
+    pub fn new(threshold: Duration, block_time: u64) -> StallDetector {
+        StallDetector {
+            threshold,
+            block_time,
+            latest: None,
+            stall: None,
+        }
+    }
+
+    /// Stall detector that never detects stalls
+    pub fn disabled() -> StallDetector {
+        StallDetector::new(Duration::from_secs(0), 0)
+    }
+
+    /// Check whether stalls are detected at all
+    pub fn is_enabled(&self) -> bool {
+        self.threshold > Duration::from_secs(0)
+    }
+
+    /// Check whether the client chain is stalled
+    pub fn is_stalled(&self) -> bool {
+        self.stall.is_some()
+    }
+
+    /// Check the client chain for a stall given the current client and service
+    /// chain heights. The client chain is stalled once its height has not
+    /// changed for the threshold and resumes on the next new height, returning
+    /// the stall period
+    pub fn check(&mut self, clientchain_height: u32, service_height: u64, clock: &dyn Clock) -> StallStatus {
+        if !self.is_enabled() {
+            return StallStatus::Producing;
+        }
+        let now = clock.now();
+        let latest = self.latest;
+        match latest {
+            Some((height, since)) if height == clientchain_height => {
+                if self.stall.is_none() && now.duration_since(since) >= self.threshold {
+                    let stalled_for = now.duration_since(since);
+                    warn!(
+                        "client chain stalled at height {} for {} sec, skipping challenges",
+                        height,
+                        stalled_for.as_secs()
+                    );
+                    let start_time = clock.unix_time().checked_sub(stalled_for).unwrap_or_default();
+                    self.stall = Some((service_height, start_time.as_secs()));
+                }
+                if self.is_stalled() {
+                    StallStatus::Stalled
+                } else {
+                    StallStatus::Producing
+                }
+            }
+            _ => {
+                let stall = self.end(service_height, clock);
+                self.latest = Some((clientchain_height, now));
+                match stall {
+                    Some(stall) => {
+                        info!(
+                            "client chain resumed at height {} after {} sec, resuming challenges",
+                            clientchain_height,
+                            stall.end_time - stall.start_time
+                        );
+                        StallStatus::Resumed(stall)
+                    }
+                    None => StallStatus::Producing,
+                }
+            }
+        }
+    }
+
+    /// End the ongoing stall, if any, at a service chain height, e.g. when the
+    /// request ends while the client chain is stalled, returning the stall
+    /// period
+    pub fn end(&mut self, service_height: u64, clock: &dyn Clock) -> Option<ClientChainStall> {
+        let (start_height, start_time) = self.stall.take()?;
+        let end_time = clock.unix_time().as_secs().max(start_time);
+        Some(ClientChainStall {
+            clientchain_height: self.latest.map_or(0, |(height, _)| height),
+            start_height,
+            end_height: service_height,
+            start_time,
+            end_time,
+            missed_blocks: ((end_time - start_time) / self.block_time.max(1)).saturating_sub(1) as u32,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::interfaces::mocks::clock::{MockClock, MOCK_CLOCK_UNIX_START};
+
+    #[test]
+    fn stall_detector_test() {
+        let clock = MockClock::new();
+
+        // disabled detector never stalls
+        let mut detector = StallDetector::disabled();
+        assert_eq!(StallStatus::Producing, detector.check(10, 1, &clock));
+        clock.advance(Duration::from_secs(3600));
+        assert_eq!(StallStatus::Producing, detector.check(10, 2, &clock));
+        assert_eq!(None, detector.end(3, &clock));
+
+        // no stall within the threshold
+        let clock = MockClock::new();
+        let mut detector = StallDetector::new(Duration::from_secs(300), 60);
+        assert_eq!(StallStatus::Producing, detector.check(10, 1, &clock));
+        clock.advance(Duration::from_secs(299));
+        assert_eq!(StallStatus::Producing, detector.check(10, 2, &clock));
+        assert!(!detector.is_stalled());
+
+        // stalled from the threshold until the next block
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(StallStatus::Stalled, detector.check(10, 3, &clock));
+        assert!(detector.is_stalled());
+        clock.advance(Duration::from_secs(300));
+        assert_eq!(StallStatus::Stalled, detector.check(10, 8, &clock));
+        let stall = match detector.check(11, 9, &clock) {
+            StallStatus::Resumed(stall) => stall,
+            status => panic!("unexpected status {:?}", status),
+        };
+        assert_eq!(
+            ClientChainStall {
+                clientchain_height: 10,
+                start_height: 3,
+                end_height: 9,
+                start_time: MOCK_CLOCK_UNIX_START,
+                end_time: MOCK_CLOCK_UNIX_START + 600,
+                missed_blocks: 9,
+            },
+            stall
+        );
+        assert!(!detector.is_stalled());
+        assert_eq!(StallStatus::Producing, detector.check(11, 10, &clock));
+
+        // stall ended without a new block
+        clock.advance(Duration::from_secs(400));
+        assert_eq!(StallStatus::Stalled, detector.check(11, 12, &clock));
+        let stall = detector.end(13, &clock).unwrap();
+        assert_eq!(11, stall.clientchain_height);
+        assert_eq!(12, stall.start_height);
+        assert_eq!(13, stall.end_height);
+        assert_eq!(400, stall.end_time - stall.start_time);
+        assert_eq!(5, stall.missed_blocks);
+        assert_eq!(None, detector.end(13, &clock));
+    }
+}
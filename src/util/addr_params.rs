@@ -0,0 +1,106 @@
+//! # Address params
+//!
+//! Registry of address params by chain name, containing the ocean chains known
+//! to the coordinator as well as any chains supplied via config or registered
+//! programmatically
+
+use std::collections::HashMap;
+
+use ocean::AddressParams;
+
+use crate::config::AddrParamsConfig;
+
+/// Registry mapping chain names to the address params of the chain. Chain
+/// names are case insensitive and any unknown chain uses the elements params
+pub struct AddrParamsRegistry {
+    /// Address params by lowercase chain name
+    params: HashMap<String, &'static AddressParams>,
+}
+
+impl AddrParamsRegistry {
+    /// Create a registry containing the address params of known ocean chains
+    pub fn new() -> AddrParamsRegistry {
+        let mut params: HashMap<String, &'static AddressParams> = HashMap::new();
+        let _ = params.insert(String::from("ocean_main"), &AddressParams::OCEAN);
+        let _ = params.insert(String::from("gold_main"), &AddressParams::GOLD);
+        AddrParamsRegistry { params }
+    }
+
+    /// Create a registry containing the address params of known ocean chains
+    /// and those supplied in the config by chain name
+    pub fn from_config(config: &HashMap<String, AddrParamsConfig>) -> AddrParamsRegistry {
+        let mut registry = AddrParamsRegistry::new();
+        for (chain, params) in config.iter() {
+            registry.register_config(chain, params);
+        }
+        registry
+    }
+
+    /// Register the address params of a chain, replacing any existing params.
+    /// Params are kept for the lifetime of the program, so registration is
+    /// expected to happen once on startup
+    pub fn register(&mut self, chain: &str, params: AddressParams) {
+        let _ = self.params.insert(chain.to_lowercase(), Box::leak(Box::new(params)));
+    }
+
+    /// Register the address params of a chain from config numeric prefixes
+    pub fn register_config(&mut self, chain: &str, config: &AddrParamsConfig) {
+        self.register(
+            chain,
+            AddressParams {
+                p2pkh_prefix: config.p2pkh_prefix,
+                p2sh_prefix: config.p2sh_prefix,
+                blinded_prefix: config.blinded_prefix,
+                bech_hrp: Box::leak(config.bech_hrp.clone().into_boxed_str()),
+            },
+        );
+    }
+
+    /// Get the address params of a chain, defaulting to the elements params
+    /// for unknown chains
+    pub fn get(&self, chain: &str) -> &'static AddressParams {
+        match self.params.get(&chain.to_lowercase()) {
+            Some(params) => params,
+            None => &AddressParams::ELEMENTS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::util::testing::setup_logger;
+
+    #[test]
+    fn addr_params_registry_test() {
+        setup_logger();
+        let mut config = HashMap::new();
+        let _ = config.insert(
+            String::from("New_Chain"),
+            AddrParamsConfig {
+                p2pkh_prefix: 28,
+                p2sh_prefix: 87,
+                blinded_prefix: 12,
+                bech_hrp: String::from("nc"),
+            },
+        );
+        let mut registry = AddrParamsRegistry::from_config(&config);
+
+        // known chains
+        assert_eq!(&AddressParams::OCEAN, registry.get("ocean_main"));
+        assert_eq!(&AddressParams::GOLD, registry.get("GOLD_MAIN"));
+        assert_eq!(&AddressParams::ELEMENTS, registry.get("supersilverhazechain"));
+
+        // config chain
+        let params = registry.get("new_chain");
+        assert_eq!(28, params.p2pkh_prefix);
+        assert_eq!(87, params.p2sh_prefix);
+        assert_eq!(12, params.blinded_prefix);
+        assert_eq!("nc", params.bech_hrp);
+
+        // programmatically registered chain replacing known chain
+        registry.register("ocean_main", AddressParams::GOLD.clone());
+        assert_eq!(&AddressParams::GOLD, registry.get("ocean_main"));
+    }
+}
@@ -0,0 +1,132 @@
+//! # Compression
+//!
+//! Http response compression negotiated via the Accept-Encoding header of the
+//! request, supporting gzip and deflate content encodings
+
+use std::io::Write;
+
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+
+use crate::error::{CError, Result};
+
+/// Content encoding of compressed responses
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    /// Gzip content encoding
+    Gzip,
+    /// Deflate content encoding, which is zlib wrapped deflate data
+    Deflate,
+}
+
+impl Encoding {
+    /// Get the content encoding name used in http headers
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+}
+
+/// Negotiate the content encoding of a response from the Accept-Encoding
+/// header value of the request. Gzip is preferred over deflate at the same
+/// quality and encodings with a zero quality value are never used. Returns
+/// None if the response should not be compressed
+pub fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+    let mut best: Option<(Encoding, u32)> = None;
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or("").trim().to_lowercase();
+        // quality value in thousandths
+        let quality = parts
+            .filter_map(|param| {
+                let param = param.trim();
+                if param.starts_with("q=") {
+                    param[2..]
+                        .trim()
+                        .parse::<f32>()
+                        .ok()
+                        .map(|q| (q.max(0.0).min(1.0) * 1000.0) as u32)
+                } else {
+                    None
+                }
+            })
+            .next()
+            .unwrap_or(1000);
+        let encoding = match name.as_str() {
+            "gzip" | "x-gzip" | "*" => Encoding::Gzip,
+            "deflate" => Encoding::Deflate,
+            _ => continue,
+        };
+        if quality == 0 {
+            continue;
+        }
+        let preferred = best.map_or(true, |(best_encoding, best_quality)| {
+            quality > best_quality || (quality == best_quality && best_encoding != Encoding::Gzip)
+        });
+        if preferred {
+            best = Some((encoding, quality));
+        }
+    }
+    best.map(|(encoding, _)| encoding)
+}
+
+/// Compress response content with the content encoding
+pub fn compress(encoding: Encoding, content: &[u8]) -> Result<Vec<u8>> {
+    let compressed = match encoding {
+        Encoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(content).and_then(|_| encoder.finish())
+        }
+        Encoding::Deflate => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(content).and_then(|_| encoder.finish())
+        }
+    };
+    compressed.map_err(|e| CError::Generic(format!("response compression failed: {}", e)).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Read;
+
+    use flate2::read::{GzDecoder, ZlibDecoder};
+
+    #[test]
+    fn negotiate_test() {
+        assert_eq!(None, negotiate(""));
+        assert_eq!(None, negotiate("identity"));
+        assert_eq!(Some(Encoding::Gzip), negotiate("gzip"));
+        assert_eq!(Some(Encoding::Gzip), negotiate("deflate, gzip"));
+        assert_eq!(Some(Encoding::Gzip), negotiate("br, *"));
+        assert_eq!(Some(Encoding::Deflate), negotiate("Deflate"));
+        // quality values
+        assert_eq!(Some(Encoding::Deflate), negotiate("gzip;q=0.5, deflate"));
+        assert_eq!(Some(Encoding::Deflate), negotiate("gzip;q=0, deflate;q=0.1"));
+        assert_eq!(None, negotiate("gzip;q=0, deflate; q=0.0"));
+    }
+
+    #[test]
+    fn compress_test() {
+        let content = "{\"jsonrpc\":\"2.0\",\"result\":[]}".repeat(100);
+
+        let compressed = compress(Encoding::Gzip, content.as_bytes()).unwrap();
+        assert!(compressed.len() < content.len());
+        let mut decompressed = String::new();
+        let _ = GzDecoder::new(&compressed[..])
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(content, decompressed);
+
+        let compressed = compress(Encoding::Deflate, content.as_bytes()).unwrap();
+        assert!(compressed.len() < content.len());
+        let mut decompressed = String::new();
+        let _ = ZlibDecoder::new(&compressed[..])
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(content, decompressed);
+    }
+}
@@ -12,15 +12,46 @@ use bitcoin::Amount;
 use mongodb::{ordered::OrderedDocument, Bson};
 use ocean::Address;
 
-use crate::interfaces::response::Response;
+use crate::coverage::{ChallengeCoverage, CoverageGap};
+use crate::dead_letter::DeadLetter;
+use crate::error::{CError, Error, ErrorCategory, Result};
+use crate::interfaces::response::{
+    BidProof, BidReconciliation, ChallengeLatency, ChallengeRecord, ChallengeStats, LatencyPercentiles, Response,
+    ResponseReconciliation, ResponseSnapshot, ResponseSummary,
+};
 use crate::interfaces::{
-    bid::{Bid, BidPayment},
-    request::Request,
+    bid::{
+        Bid, BidBlacklisting, BidKeyRotation, BidPayment, BidPaymentBasis, BidPaymentTx, BidRefund, BidRefundState,
+        LatencyWeighting, PaymentLiability, PayoutAddressType,
+    },
+    clientchain::ChallengeAmountTag,
+    request::{
+        AssetFees, BlockFees, FeeFilter, FeePool, PaymentState, Request, RequestAnnotation, RequestDeposit,
+        RequestFailure, RequestRejection,
+    },
 };
+use crate::stall::ClientChainStall;
+use crate::util::encryption::{field_cipher, is_encrypted, FieldCipher};
+
+/// Util method that generates an amount document value as integer satoshis
+pub fn amount_to_bson(amount: &Amount) -> Bson {
+    Bson::I64(amount.as_sat() as i64)
+}
+
+/// Util method that generates an amount from an amount document value. Integer
+/// values are satoshis while floating point values are btc amounts stored by
+/// earlier versions and are rounded to the nearest satoshi
+pub fn bson_to_amount(bson: &Bson) -> Amount {
+    match bson {
+        Bson::I64(sat) => Amount::from_sat(*sat as u64),
+        Bson::I32(sat) => Amount::from_sat(*sat as u64),
+        _ => Amount::from_sat((bson.as_f64().unwrap() * 100_000_000.0).round() as u64),
+    }
+}
 
 /// Util method that generates a Request document from a request
 pub fn request_to_doc(request: &Request) -> OrderedDocument {
-    doc! {
+    let mut doc = doc! {
         "txid": request.txid.to_string(),
         "start_blockheight": request.start_blockheight,
         "end_blockheight": request.end_blockheight,
@@ -30,11 +61,28 @@ pub fn request_to_doc(request: &Request) -> OrderedDocument {
         "start_blockheight_clientchain": request.start_blockheight_clientchain,
         "end_blockheight_clientchain": request.end_blockheight_clientchain,
         "is_payment_complete": request.is_payment_complete,
+        "payment_state": request.payment_state.as_str(),
+    };
+    if let Some(summary) = &request.response_summary {
+        let _ = doc.insert("response_summary", response_summary_to_doc(summary));
+    }
+    if request.fees.len() > 0 {
+        let _ = doc.insert("fees", asset_fees_to_bson(&request.fees));
     }
+    if let Some(filter) = &request.fee_filter {
+        let _ = doc.insert("fee_filter", fee_filter_to_doc(filter));
+    }
+    if let Some(failure) = &request.failure {
+        let _ = doc.insert("failure", request_failure_to_doc(failure));
+    }
+    doc
 }
 
-/// Util method that generates a request from a Request document
+/// Util method that generates a request from a Request document. The payment
+/// state of documents stored before payment states were recorded is derived
+/// from the payment complete flag
 pub fn doc_to_request(doc: &OrderedDocument) -> Request {
+    let is_payment_complete = doc.get("is_payment_complete").unwrap().as_bool().unwrap();
     Request {
         txid: sha256d::Hash::from_hex(doc.get("txid").unwrap().as_str().unwrap()).unwrap(),
         start_blockheight: doc.get("start_blockheight").unwrap().as_i32().unwrap() as u32,
@@ -44,69 +92,429 @@ pub fn doc_to_request(doc: &OrderedDocument) -> Request {
         num_tickets: doc.get("num_tickets").unwrap().as_i32().unwrap() as u32,
         start_blockheight_clientchain: doc.get("start_blockheight_clientchain").unwrap().as_i32().unwrap() as u32,
         end_blockheight_clientchain: doc.get("end_blockheight_clientchain").unwrap().as_i32().unwrap() as u32,
-        is_payment_complete: doc.get("is_payment_complete").unwrap().as_bool().unwrap(),
+        is_payment_complete,
+        payment_state: doc
+            .get("payment_state")
+            .map_or(PaymentState::from_legacy(is_payment_complete), |state| {
+                PaymentState::from_str(state.as_str().unwrap()).unwrap()
+            }),
+        response_summary: doc
+            .get("response_summary")
+            .map(|summary| doc_to_response_summary(summary.as_document().unwrap())),
+        fees: doc
+            .get_array("fees")
+            .ok()
+            .map_or(vec![], |fees| bson_to_asset_fees(fees)),
+        fee_filter: doc
+            .get("fee_filter")
+            .map(|filter| doc_to_fee_filter(filter.as_document().unwrap())),
+        failure: doc
+            .get("failure")
+            .map(|failure| doc_to_request_failure(failure.as_document().unwrap())),
+    }
+}
+
+/// Util method that generates a request failure document
+fn request_failure_to_doc(failure: &RequestFailure) -> OrderedDocument {
+    let mut doc = doc! {
+        "category": failure.category.as_str(),
+        "message": failure.message.clone(),
+        "challenges": failure.challenges,
+    };
+    if let Some(height) = failure.height {
+        let _ = doc.insert("height", height as i64);
+    }
+    doc
+}
+
+/// Util method that generates a request failure from a request document
+/// failure entry
+fn doc_to_request_failure(doc: &OrderedDocument) -> RequestFailure {
+    RequestFailure {
+        category: ErrorCategory::from_name(doc.get("category").unwrap().as_str().unwrap()).unwrap(),
+        message: doc.get("message").unwrap().as_str().unwrap().to_owned(),
+        height: doc.get_i64("height").ok().map(|height| height as u64),
+        challenges: doc.get("challenges").unwrap().as_i32().unwrap() as u32,
+    }
+}
+
+/// Util method that generates the fee entries of a document from fees per fee
+/// asset
+fn asset_fees_to_bson(fees: &[AssetFees]) -> Vec<Bson> {
+    fees.iter()
+        .map(|fee| {
+            Bson::Document(doc! {
+                "asset": fee.asset.clone(),
+                "amount": amount_to_bson(&fee.amount),
+            })
+        })
+        .collect()
+}
+
+/// Util method that generates fees per fee asset from the fee entries of a
+/// document
+fn bson_to_asset_fees(fees: &[Bson]) -> Vec<AssetFees> {
+    fees.iter()
+        .map(|fee| {
+            let fee = fee.as_document().unwrap();
+            AssetFees {
+                asset: fee.get("asset").unwrap().as_str().unwrap().to_owned(),
+                amount: bson_to_amount(fee.get("amount").unwrap()),
+            }
+        })
+        .collect()
+}
+
+/// Util method that generates a FeePool document from the fee pool of a
+/// request
+pub fn fee_pool_to_doc(request_hash: &sha256d::Hash, pool: &FeePool) -> OrderedDocument {
+    let blocks: Vec<Bson> = pool
+        .blocks
+        .iter()
+        .map(|block| {
+            Bson::Document(doc! {
+                "height": block.height,
+                "fees": asset_fees_to_bson(&block.fees),
+            })
+        })
+        .collect();
+    let mut doc = doc! {
+        "txid": request_hash.to_string(),
+        "start_height": pool.start_height,
+        "end_height": pool.end_height,
+        "blocks": blocks,
+        "fees": asset_fees_to_bson(&pool.fees),
+        "total": amount_to_bson(&pool.total),
+        "fee_percentage": pool.fee_percentage,
+        "guardnode_amount": amount_to_bson(&pool.guardnode_amount),
+    };
+    if let Some(filter) = &pool.fee_filter {
+        let _ = doc.insert("fee_filter", fee_filter_to_doc(filter));
     }
+    doc
 }
 
-/// Util method that generates a Bid document from a request bid
+/// Util method that generates the fee pool of a request from a FeePool document
+pub fn doc_to_fee_pool(doc: &OrderedDocument) -> FeePool {
+    FeePool {
+        start_height: doc.get("start_height").unwrap().as_i32().unwrap() as u32,
+        end_height: doc.get("end_height").unwrap().as_i32().unwrap() as u32,
+        blocks: doc
+            .get_array("blocks")
+            .unwrap()
+            .iter()
+            .map(|block| {
+                let block = block.as_document().unwrap();
+                BlockFees {
+                    height: block.get("height").unwrap().as_i32().unwrap() as u32,
+                    fees: bson_to_asset_fees(block.get_array("fees").unwrap()),
+                }
+            })
+            .collect(),
+        fees: bson_to_asset_fees(doc.get_array("fees").unwrap()),
+        total: bson_to_amount(doc.get("total").unwrap()),
+        fee_percentage: doc.get("fee_percentage").unwrap().as_i32().unwrap() as u32,
+        guardnode_amount: bson_to_amount(doc.get("guardnode_amount").unwrap()),
+        fee_filter: doc
+            .get("fee_filter")
+            .map(|filter| doc_to_fee_filter(filter.as_document().unwrap())),
+    }
+}
+
+/// Util method that generates a fee filter document
+fn fee_filter_to_doc(filter: &FeeFilter) -> OrderedDocument {
+    let to_bson = |values: &[String]| -> Vec<Bson> { values.iter().map(|value| Bson::String(value.clone())).collect() };
+    doc! {
+        "exclude_addresses": to_bson(&filter.exclude_addresses),
+        "exclude_script_types": to_bson(&filter.exclude_script_types),
+        "include_addresses": to_bson(&filter.include_addresses),
+    }
+}
+
+/// Util method that generates a fee filter from a request document fee filter
+/// entry
+fn doc_to_fee_filter(doc: &OrderedDocument) -> FeeFilter {
+    let from_bson = |key: &str| -> Vec<String> {
+        doc.get_array(key).ok().map_or(vec![], |values| {
+            values.iter().map(|value| value.as_str().unwrap().to_owned()).collect()
+        })
+    };
+    FeeFilter {
+        exclude_addresses: from_bson("exclude_addresses"),
+        exclude_script_types: from_bson("exclude_script_types"),
+        include_addresses: from_bson("include_addresses"),
+    }
+}
+
+/// Util method that generates a request response summary document
+pub fn response_summary_to_doc(summary: &ResponseSummary) -> OrderedDocument {
+    doc! {
+        "num_challenges": summary.num_challenges,
+        "num_bids": summary.num_bids,
+        "num_responses": summary.num_responses,
+        "response_rate": summary.response_rate,
+    }
+}
+
+/// Util method that generates a response summary from a request document
+/// response summary entry
+fn doc_to_response_summary(doc: &OrderedDocument) -> ResponseSummary {
+    ResponseSummary {
+        num_challenges: doc.get("num_challenges").unwrap().as_i32().unwrap() as u32,
+        num_bids: doc.get("num_bids").unwrap().as_i32().unwrap() as u32,
+        num_responses: doc.get("num_responses").unwrap().as_i32().unwrap() as u32,
+        response_rate: doc.get("response_rate").unwrap().as_f64().unwrap(),
+    }
+}
+
+/// Util method that generates a BidPaymentBasis document from a bid payment
+/// basis
+fn bid_payment_basis_to_doc(basis: &BidPaymentBasis) -> OrderedDocument {
+    let mut doc = doc! {
+        "formula_version": basis.formula_version,
+        "fees_amount": amount_to_bson(&basis.fees_amount),
+        "fee_percentage": basis.fee_percentage,
+        "num_bids": basis.num_bids,
+        "num_challenges": basis.num_challenges,
+        "num_responses": basis.num_responses,
+    };
+    if let Some(weighting) = &basis.latency_weighting {
+        let _ = doc.insert("latency_weighting", latency_weighting_to_doc(weighting));
+    }
+    doc
+}
+
+/// Util method that generates a bid payment basis from a BidPaymentBasis
+/// document
+fn doc_to_bid_payment_basis(doc: &OrderedDocument) -> BidPaymentBasis {
+    BidPaymentBasis {
+        formula_version: doc.get("formula_version").unwrap().as_i32().unwrap() as u32,
+        fees_amount: bson_to_amount(doc.get("fees_amount").unwrap()),
+        fee_percentage: doc.get("fee_percentage").unwrap().as_i32().unwrap() as u32,
+        num_bids: doc.get("num_bids").unwrap().as_i32().unwrap() as u32,
+        num_challenges: doc.get("num_challenges").unwrap().as_i32().unwrap() as u32,
+        num_responses: doc.get("num_responses").unwrap().as_i32().unwrap() as u32,
+        latency_weighting: doc.get_document("latency_weighting").ok().map(doc_to_latency_weighting),
+    }
+}
+
+/// Util method that generates a LatencyWeighting document from the latency
+/// weighting of a bid payment basis
+fn latency_weighting_to_doc(weighting: &LatencyWeighting) -> OrderedDocument {
+    let bucket_max_ms: Vec<Bson> = weighting.bucket_max_ms.iter().map(|ms| Bson::I64(*ms as i64)).collect();
+    let bucket_weights: Vec<Bson> = weighting.bucket_weights.iter().map(|w| Bson::I32(*w as i32)).collect();
+    let bucket_responses: Vec<Bson> = weighting
+        .bucket_responses
+        .iter()
+        .map(|responses| Bson::I32(*responses as i32))
+        .collect();
+    doc! {
+        "bucket_max_ms": bucket_max_ms,
+        "bucket_weights": bucket_weights,
+        "bucket_responses": bucket_responses,
+        "unmeasured_responses": weighting.unmeasured_responses,
+    }
+}
+
+/// Util method that generates the latency weighting of a bid payment basis
+/// from a LatencyWeighting document
+fn doc_to_latency_weighting(doc: &OrderedDocument) -> LatencyWeighting {
+    let to_u32s = |key: &str| -> Vec<u32> {
+        doc.get_array(key)
+            .unwrap()
+            .iter()
+            .map(|val| val.as_i32().unwrap() as u32)
+            .collect()
+    };
+    LatencyWeighting {
+        bucket_max_ms: doc
+            .get_array("bucket_max_ms")
+            .unwrap()
+            .iter()
+            .map(|ms| ms.as_i64().unwrap() as u64)
+            .collect(),
+        bucket_weights: to_u32s("bucket_weights"),
+        bucket_responses: to_u32s("bucket_responses"),
+        unmeasured_responses: doc.get("unmeasured_responses").unwrap().as_i32().unwrap() as u32,
+    }
+}
+
+/// Util method that generates a sensitive string document value, encrypted
+/// if a field cipher is set
+fn sensitive_to_bson(value: String, cipher: Option<&FieldCipher>) -> Bson {
+    match cipher {
+        Some(cipher) => Bson::String(cipher.encrypt(&value)),
+        None => Bson::String(value),
+    }
+}
+
+/// Util method that gets a sensitive string document value, decrypting it if
+/// encrypted. Fails for encrypted values without a cipher or under another key
+fn bson_to_sensitive(bson: &Bson, cipher: Option<&FieldCipher>) -> Result<String> {
+    let value = bson.as_str().unwrap();
+    if !is_encrypted(value) {
+        return Ok(value.to_owned());
+    }
+    match cipher {
+        Some(cipher) => cipher.decrypt(value),
+        None => Err(Error::from(CError::Generic(
+            "encrypted field without encryption key".to_owned(),
+        ))),
+    }
+}
+
+/// Util method that generates a sensitive amount document value, as integer
+/// satoshis or encrypted if a field cipher is set
+fn sensitive_amount_to_bson(amount: &Amount, cipher: Option<&FieldCipher>) -> Bson {
+    match cipher {
+        Some(_) => sensitive_to_bson(amount.as_sat().to_string(), cipher),
+        None => amount_to_bson(amount),
+    }
+}
+
+/// Util method that gets a sensitive amount document value, decrypting it if
+/// encrypted
+fn bson_to_sensitive_amount(bson: &Bson, cipher: Option<&FieldCipher>) -> Result<Amount> {
+    match bson {
+        Bson::String(_) => {
+            let sats = bson_to_sensitive(bson, cipher)?;
+            let sats = sats
+                .parse()
+                .map_err(|_| Error::from(CError::Generic(format!("invalid sensitive amount {}", sats))))?;
+            Ok(Amount::from_sat(sats))
+        }
+        _ => Ok(bson_to_amount(bson)),
+    }
+}
+
+/// Util method that generates a BidPaymentTx document from a bid payment
+/// transaction
+fn bid_payment_tx_to_doc(tx: &BidPaymentTx, cipher: Option<&FieldCipher>) -> OrderedDocument {
+    doc! {
+        "txid": tx.txid.to_string(),
+        "amount": sensitive_amount_to_bson(&tx.amount, cipher),
+        "confirmations": tx.confirmations,
+    }
+}
+
+/// Util method that generates a bid payment transaction from a BidPaymentTx
+/// document
+fn doc_to_bid_payment_tx(doc: &OrderedDocument, cipher: Option<&FieldCipher>) -> Result<BidPaymentTx> {
+    Ok(BidPaymentTx {
+        txid: sha256d::Hash::from_hex(doc.get("txid").unwrap().as_str().unwrap()).unwrap(),
+        amount: bson_to_sensitive_amount(doc.get("amount").unwrap(), cipher)?,
+        confirmations: doc.get("confirmations").unwrap().as_i32().unwrap() as u32,
+    })
+}
+
+/// Util method that generates bid payment transactions from the txid and extra
+/// txids of payment documents stored by earlier versions. The amount paid by
+/// each transaction was not recorded, so the full payment amount is attributed
+/// to the first transaction
+fn legacy_doc_to_bid_payment_txs(doc: &OrderedDocument, amount: Amount) -> Vec<BidPaymentTx> {
+    let mut txs = vec![];
+    if let Some(txid) = doc.get("txid") {
+        txs.push(BidPaymentTx {
+            txid: sha256d::Hash::from_hex(txid.as_str().unwrap()).unwrap(),
+            amount,
+            confirmations: 0,
+        });
+        if let Ok(extra_txids) = doc.get_array("extra_txids") {
+            for extra_txid in extra_txids {
+                txs.push(BidPaymentTx {
+                    txid: sha256d::Hash::from_hex(extra_txid.as_str().unwrap()).unwrap(),
+                    amount: Amount::ZERO,
+                    confirmations: 0,
+                });
+            }
+        }
+    }
+    txs
+}
+
+/// Util method that generates a Bid document from a request bid, encrypting
+/// the payment address and amounts if field encryption is configured
 pub fn bid_to_doc(request_id: &Bson, bid: &Bid) -> OrderedDocument {
+    bid_to_doc_with_cipher(request_id, bid, field_cipher())
+}
+
+/// Util method that generates a Bid document from a request bid, encrypting
+/// the payment address and amounts with the field cipher if set
+pub fn bid_to_doc_with_cipher(request_id: &Bson, bid: &Bid, cipher: Option<&FieldCipher>) -> OrderedDocument {
     let mut bid_doc = doc! {
         "request_id": request_id.clone(),
         "txid": bid.txid.to_string(),
         "pubkey": bid.pubkey.to_string(),
     };
     if let Some(payment) = &bid.payment {
+        let txs: Vec<Bson> = payment
+            .txs
+            .iter()
+            .map(|tx| Bson::Document(bid_payment_tx_to_doc(tx, cipher)))
+            .collect();
         let mut bid_payment_doc = doc! {
-            "address": payment.address.to_string(),
-            "amount": payment.amount.as_btc(),
+            "address": sensitive_to_bson(payment.address.to_string(), cipher),
+            "address_type": payment.address_type.as_str(),
+            "amount": sensitive_amount_to_bson(&payment.amount, cipher),
+            "txs": txs,
         };
-        if let Some(txid) = payment.txid {
-            let _ = bid_payment_doc.insert("txid", txid.to_string());
+        if let Some(intent) = &payment.intent {
+            let _ = bid_payment_doc.insert("intent", intent.clone());
         }
-        if let Some(extra_txids) = &payment.extra_txids {
-            let _ = bid_payment_doc.insert(
-                "extra_txids",
-                extra_txids
-                    .iter()
-                    .map(|x| Bson::String(x.to_string()))
-                    .collect::<Vec<_>>(),
-            );
+        if let Some(basis) = &payment.basis {
+            let _ = bid_payment_doc.insert("basis", bid_payment_basis_to_doc(basis));
         }
         let _ = bid_doc.insert("payment", bid_payment_doc);
     }
     bid_doc
 }
 
-/// Util method that generates a request bid from a Bid document
-pub fn doc_to_bid(doc: &OrderedDocument) -> Bid {
+/// Util method that generates a request bid from a Bid document, decrypting
+/// any encrypted payment fields
+pub fn doc_to_bid(doc: &OrderedDocument) -> Result<Bid> {
+    doc_to_bid_with_cipher(doc, field_cipher())
+}
+
+/// Util method that generates a request bid from a Bid document, decrypting
+/// any encrypted payment fields with the field cipher. Fails if encrypted
+/// fields can not be decrypted
+pub fn doc_to_bid_with_cipher(doc: &OrderedDocument, cipher: Option<&FieldCipher>) -> Result<Bid> {
     let mut payment: Option<BidPayment> = None;
     if let Some(doc_payment) = doc.get("payment") {
         let doc_doc_payment = doc_payment.as_document().unwrap();
-        let mut payment_txid: Option<sha256d::Hash> = None;
-        if let Some(doc_payment_txid) = doc_doc_payment.get("txid") {
-            payment_txid = Some(sha256d::Hash::from_hex(doc_payment_txid.as_str().unwrap()).unwrap())
-        }
-        let mut extra_payment_txids: Option<Vec<sha256d::Hash>> = None;
-        if let Ok(doc_extra_payment_txids) = doc_doc_payment.get_array("extra_txids") {
-            extra_payment_txids = Some(
-                doc_extra_payment_txids
-                    .iter()
-                    .map(|x| sha256d::Hash::from_hex(x.as_str().unwrap()).unwrap())
-                    .collect(),
-            );
+        let amount = bson_to_sensitive_amount(doc_doc_payment.get("amount").unwrap(), cipher)?;
+        let txs = match doc_doc_payment.get_array("txs") {
+            Ok(doc_txs) => doc_txs
+                .iter()
+                .map(|tx| doc_to_bid_payment_tx(tx.as_document().unwrap(), cipher))
+                .collect::<Result<Vec<_>>>()?,
+            Err(_) => legacy_doc_to_bid_payment_txs(doc_doc_payment, amount),
+        };
+        let mut payment_intent: Option<String> = None;
+        if let Some(doc_payment_intent) = doc_doc_payment.get("intent") {
+            payment_intent = Some(doc_payment_intent.as_str().unwrap().to_owned())
         }
         payment = Some(BidPayment {
-            txid: payment_txid,
-            extra_txids: extra_payment_txids,
-            address: Address::from_str(doc_doc_payment.get("address").unwrap().as_str().unwrap()).unwrap(),
-            amount: Amount::from_btc(doc_doc_payment.get("amount").unwrap().as_f64().unwrap()).unwrap(),
+            txs,
+            address: Address::from_str(&bson_to_sensitive(doc_doc_payment.get("address").unwrap(), cipher)?).unwrap(),
+            // legacy documents without an address type paid p2pkh addresses
+            address_type: doc_doc_payment
+                .get("address_type")
+                .map_or(PayoutAddressType::P2pkh, |address_type| {
+                    PayoutAddressType::from_str(address_type.as_str().unwrap()).unwrap()
+                }),
+            amount,
+            intent: payment_intent,
+            basis: doc_doc_payment
+                .get("basis")
+                .map(|basis| doc_to_bid_payment_basis(basis.as_document().unwrap())),
         });
     }
-    Bid {
+    Ok(Bid {
         txid: sha256d::Hash::from_hex(doc.get("txid").unwrap().as_str().unwrap()).unwrap(),
         pubkey: PublicKey::from_str(doc.get("pubkey").unwrap().as_str().unwrap()).unwrap(),
         payment: payment,
-    }
+    })
 }
 
 /// Util method that generates a Response document from request response
@@ -116,11 +524,32 @@ pub fn response_to_doc(request_id: &Bson, response: &Response) -> OrderedDocumen
         .iter()
         .map(|(key, val)| (key.to_string(), Bson::I32(*val as i32)))
         .collect();
-    doc! {
+    let mut doc = doc! {
         "request_id": request_id.clone(),
         "num_challenges": response.num_challenges,
         "bid_responses": bid_resps_doc
+    };
+    if response.challenges.len() > 0 {
+        let challenges: Vec<Bson> = response
+            .challenges
+            .iter()
+            .map(|hash| Bson::String(hash.to_string()))
+            .collect();
+        let _ = doc.insert("challenges", challenges);
+    }
+    doc
+}
+
+/// Util method that gets the response integrity hash chain from a Response
+/// document
+pub fn doc_to_response_hashes(doc: &OrderedDocument) -> Vec<sha256d::Hash> {
+    let mut hashes = vec![];
+    if let Ok(hashes_bson) = doc.get_array("hashes") {
+        for hash in hashes_bson.iter() {
+            hashes.push(sha256d::Hash::from_hex(hash.as_str().unwrap()).unwrap());
+        }
     }
+    hashes
 }
 
 /// Util method that generates request response from a Response document
@@ -138,9 +567,568 @@ pub fn doc_to_response(doc: &OrderedDocument) -> Response {
             )
         })
         .collect();
+    let mut challenges = vec![];
+    if let Some(challenges_bson) = doc.get("challenges") {
+        for challenge in challenges_bson.as_array().unwrap().iter() {
+            challenges.push(sha256d::Hash::from_hex(challenge.as_str().unwrap()).unwrap());
+        }
+    }
     Response {
         num_challenges: doc.get("num_challenges").unwrap().as_i32().unwrap() as u32,
         bid_responses: bid_resps,
+        challenges,
+    }
+}
+
+/// Util method that generates a ResponseSnapshot document from a request
+/// response snapshot
+pub fn response_snapshot_to_doc(request_hash: &sha256d::Hash, snapshot: &ResponseSnapshot) -> OrderedDocument {
+    let request_id = Bson::String(request_hash.to_string());
+    let bids: Vec<Bson> = snapshot
+        .bids
+        .iter()
+        .map(|bid| Bson::Document(bid_to_doc(&request_id, bid)))
+        .collect();
+    doc! {
+        "txid": request_hash.to_string(),
+        "epoch": snapshot.epoch,
+        "service_height": snapshot.service_height,
+        "clientchain_height": snapshot.clientchain_height,
+        "response": response_to_doc(&request_id, &snapshot.response),
+        "bids": bids,
+        "is_payment_complete": snapshot.is_payment_complete,
+    }
+}
+
+/// Util method that generates a request response snapshot from a
+/// ResponseSnapshot document
+pub fn doc_to_response_snapshot(doc: &OrderedDocument) -> Result<ResponseSnapshot> {
+    Ok(ResponseSnapshot {
+        epoch: doc.get("epoch").unwrap().as_i32().unwrap() as u32,
+        service_height: doc.get("service_height").unwrap().as_i32().unwrap() as u32,
+        clientchain_height: doc.get("clientchain_height").unwrap().as_i32().unwrap() as u32,
+        response: doc_to_response(doc.get_document("response").unwrap()),
+        bids: doc
+            .get_array("bids")
+            .unwrap()
+            .iter()
+            .map(|bid| doc_to_bid(bid.as_document().unwrap()))
+            .collect::<Result<Vec<_>>>()?,
+        is_payment_complete: doc.get("is_payment_complete").unwrap().as_bool().unwrap(),
+    })
+}
+
+/// Util method that generates a latency percentiles document
+fn latency_percentiles_to_doc(percentiles: &LatencyPercentiles) -> OrderedDocument {
+    doc! {
+        "count": percentiles.count,
+        "p50": percentiles.p50 as i64,
+        "p90": percentiles.p90 as i64,
+        "p99": percentiles.p99 as i64,
+        "max": percentiles.max as i64,
+    }
+}
+
+/// Util method that generates a ChallengeRecord document from the record of a
+/// single challenge of a request
+pub fn challenge_record_to_doc(request_hash: &sha256d::Hash, record: &ChallengeRecord) -> OrderedDocument {
+    let responders: Vec<Bson> = record
+        .responders
+        .iter()
+        .map(|txid| Bson::String(txid.to_string()))
+        .collect();
+    doc! {
+        "txid": request_hash.to_string(),
+        "challenge": record.challenge.to_string(),
+        "responders": responders,
+    }
+}
+
+/// Util method that generates the record of a single challenge of a request
+/// from a ChallengeRecord document
+pub fn doc_to_challenge_record(doc: &OrderedDocument) -> ChallengeRecord {
+    ChallengeRecord {
+        challenge: sha256d::Hash::from_hex(doc.get_str("challenge").unwrap()).unwrap(),
+        responders: doc
+            .get_array("responders")
+            .unwrap()
+            .iter()
+            .map(|txid| sha256d::Hash::from_hex(txid.as_str().unwrap()).unwrap())
+            .collect(),
+    }
+}
+
+/// Util method that generates a ChallengeStats document from the response
+/// stats of a single challenge of a request
+pub fn challenge_stats_to_doc(request_hash: &sha256d::Hash, stats: &ChallengeStats) -> OrderedDocument {
+    let mut doc = doc! {
+        "txid": request_hash.to_string(),
+        "challenge": stats.challenge.to_string(),
+        "height": stats.height as i64,
+        "num_bids": stats.num_bids,
+        "num_responses": stats.num_responses,
+    };
+    if let Some(tag) = &stats.amount_tag {
+        let _ = doc.insert(
+            "amount_tag",
+            doc! {
+                "sequence": tag.sequence,
+                "amount": tag.amount as i64,
+            },
+        );
+    }
+    if let Some(wallet) = &stats.wallet {
+        let _ = doc.insert("wallet", wallet.clone());
+    }
+    doc
+}
+
+/// Util method that generates the response stats of a single challenge of a
+/// request from a ChallengeStats document
+pub fn doc_to_challenge_stats(doc: &OrderedDocument) -> ChallengeStats {
+    ChallengeStats {
+        challenge: sha256d::Hash::from_hex(doc.get_str("challenge").unwrap()).unwrap(),
+        height: doc.get_i64("height").unwrap() as u64,
+        num_bids: doc.get_i32("num_bids").unwrap() as u32,
+        num_responses: doc.get_i32("num_responses").unwrap() as u32,
+        amount_tag: doc.get_document("amount_tag").ok().map(|tag| ChallengeAmountTag {
+            sequence: tag.get_i32("sequence").unwrap() as u32,
+            amount: tag.get_i64("amount").unwrap() as u64,
+        }),
+        wallet: doc.get_str("wallet").ok().map(|wallet| wallet.to_owned()),
+    }
+}
+
+/// Util method that generates a BidProof document from the accepted challenge
+/// proof of a bid of a request
+pub fn bid_proof_to_doc(request_hash: &sha256d::Hash, proof: &BidProof) -> OrderedDocument {
+    doc! {
+        "txid": request_hash.to_string(),
+        "bid": proof.bid.to_string(),
+        "challenge": proof.challenge.to_string(),
+        "clientchain_height": proof.clientchain_height,
+        "timestamp": proof.timestamp as i64,
+    }
+}
+
+/// Util method that generates the accepted challenge proof of a bid of a
+/// request from a BidProof document
+pub fn doc_to_bid_proof(doc: &OrderedDocument) -> BidProof {
+    BidProof {
+        bid: sha256d::Hash::from_hex(doc.get_str("bid").unwrap()).unwrap(),
+        challenge: sha256d::Hash::from_hex(doc.get_str("challenge").unwrap()).unwrap(),
+        clientchain_height: doc.get_i32("clientchain_height").unwrap() as u32,
+        timestamp: doc.get_i64("timestamp").unwrap() as u64,
+    }
+}
+
+/// Util method that generates a ChallengeLatency document from the challenge
+/// latency samples of a request, along with their percentiles
+pub fn challenge_latency_to_doc(request_hash: &sha256d::Hash, latency: &ChallengeLatency) -> OrderedDocument {
+    let (verify, proof) = latency.percentiles();
+    let verify_ms: Vec<Bson> = latency.verify_ms.iter().map(|ms| Bson::I64(*ms as i64)).collect();
+    let proof_ms: Vec<Bson> = latency.proof_ms.iter().map(|ms| Bson::I64(*ms as i64)).collect();
+    let bid_proof_ms: OrderedDocument = latency
+        .bid_proof_ms
+        .iter()
+        .map(|(txid, samples)| {
+            let samples: Vec<Bson> = samples.iter().map(|ms| Bson::I64(*ms as i64)).collect();
+            (txid.to_string(), Bson::Array(samples))
+        })
+        .collect();
+    doc! {
+        "txid": request_hash.to_string(),
+        "verify_ms": verify_ms,
+        "proof_ms": proof_ms,
+        "bid_proof_ms": bid_proof_ms,
+        "verify": latency_percentiles_to_doc(&verify),
+        "proof": latency_percentiles_to_doc(&proof),
+    }
+}
+
+/// Util method that generates the challenge latency samples of a request from
+/// a ChallengeLatency document. Documents saved before per bid samples were
+/// recorded have no bid proof samples
+pub fn doc_to_challenge_latency(doc: &OrderedDocument) -> ChallengeLatency {
+    let to_samples = |array: &Vec<Bson>| -> Vec<u64> { array.iter().map(|ms| ms.as_i64().unwrap() as u64).collect() };
+    let mut bid_proof_ms = HashMap::new();
+    if let Ok(bids_doc) = doc.get_document("bid_proof_ms") {
+        for (key, val) in bids_doc.iter() {
+            let _ = bid_proof_ms.insert(
+                sha256d::Hash::from_hex(key.as_str()).unwrap(),
+                to_samples(val.as_array().unwrap()),
+            );
+        }
+    }
+    ChallengeLatency {
+        verify_ms: to_samples(doc.get_array("verify_ms").unwrap()),
+        proof_ms: to_samples(doc.get_array("proof_ms").unwrap()),
+        bid_proof_ms,
+    }
+}
+
+/// Util method that generates a ChallengeCoverage document from the challenge
+/// coverage of a request
+pub fn challenge_coverage_to_doc(request_hash: &sha256d::Hash, coverage: &ChallengeCoverage) -> OrderedDocument {
+    let gaps: Vec<Bson> = coverage
+        .gaps
+        .iter()
+        .map(|gap| {
+            Bson::Document(doc! {
+                "start_height": gap.start_height as i64,
+                "end_height": gap.end_height as i64,
+                "missed": gap.missed,
+            })
+        })
+        .collect();
+    doc! {
+        "txid": request_hash.to_string(),
+        "frequency": coverage.frequency as i64,
+        "height": coverage.height as i64,
+        "scheduled": coverage.scheduled,
+        "issued": coverage.issued,
+        "coverage": coverage.coverage,
+        "gaps": gaps,
+    }
+}
+
+/// Util method that generates the challenge coverage of a request from a
+/// ChallengeCoverage document
+pub fn doc_to_challenge_coverage(doc: &OrderedDocument) -> ChallengeCoverage {
+    ChallengeCoverage {
+        frequency: doc.get_i64("frequency").unwrap() as u64,
+        height: doc.get_i64("height").unwrap() as u64,
+        scheduled: doc.get_i32("scheduled").unwrap() as u32,
+        issued: doc.get_i32("issued").unwrap() as u32,
+        coverage: doc.get_f64("coverage").unwrap(),
+        gaps: doc
+            .get_array("gaps")
+            .unwrap()
+            .iter()
+            .map(|gap| {
+                let gap = gap.as_document().unwrap();
+                CoverageGap {
+                    start_height: gap.get_i64("start_height").unwrap() as u64,
+                    end_height: gap.get_i64("end_height").unwrap() as u64,
+                    missed: gap.get_i32("missed").unwrap() as u32,
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Util method that generates a ClientChainStall document from a client chain
+/// stall period of a request
+pub fn clientchain_stall_to_doc(request_hash: &sha256d::Hash, stall: &ClientChainStall) -> OrderedDocument {
+    doc! {
+        "txid": request_hash.to_string(),
+        "clientchain_height": stall.clientchain_height,
+        "start_height": stall.start_height as i64,
+        "end_height": stall.end_height as i64,
+        "start_time": stall.start_time as i64,
+        "end_time": stall.end_time as i64,
+        "missed_blocks": stall.missed_blocks,
+    }
+}
+
+/// Util method that generates a client chain stall period of a request from a
+/// ClientChainStall document
+pub fn doc_to_clientchain_stall(doc: &OrderedDocument) -> ClientChainStall {
+    ClientChainStall {
+        clientchain_height: doc.get_i32("clientchain_height").unwrap() as u32,
+        start_height: doc.get_i64("start_height").unwrap() as u64,
+        end_height: doc.get_i64("end_height").unwrap() as u64,
+        start_time: doc.get_i64("start_time").unwrap() as u64,
+        end_time: doc.get_i64("end_time").unwrap() as u64,
+        missed_blocks: doc.get_i32("missed_blocks").unwrap() as u32,
+    }
+}
+
+/// Util method that generates a ResponseReconciliation document from the
+/// reconciliation of a request response against on-chain proofs
+pub fn response_reconciliation_to_doc(
+    request_hash: &sha256d::Hash,
+    reconciliation: &ResponseReconciliation,
+) -> OrderedDocument {
+    let bids: Vec<Bson> = reconciliation
+        .bids
+        .iter()
+        .map(|bid| {
+            Bson::Document(doc! {
+                "txid": bid.txid.to_string(),
+                "listener_responses": bid.listener_responses,
+                "onchain_responses": bid.onchain_responses,
+                "is_divergent": bid.is_divergent,
+            })
+        })
+        .collect();
+    doc! {
+        "txid": request_hash.to_string(),
+        "clientchain_height": reconciliation.clientchain_height,
+        "bids": bids,
+    }
+}
+
+/// Util method that generates the reconciliation of a request response from a
+/// ResponseReconciliation document
+pub fn doc_to_response_reconciliation(doc: &OrderedDocument) -> ResponseReconciliation {
+    ResponseReconciliation {
+        clientchain_height: doc.get("clientchain_height").unwrap().as_i32().unwrap() as u32,
+        bids: doc
+            .get_array("bids")
+            .unwrap()
+            .iter()
+            .map(|bid| {
+                let bid = bid.as_document().unwrap();
+                BidReconciliation {
+                    txid: sha256d::Hash::from_hex(bid.get("txid").unwrap().as_str().unwrap()).unwrap(),
+                    listener_responses: bid.get("listener_responses").unwrap().as_i32().unwrap() as u32,
+                    onchain_responses: bid.get("onchain_responses").unwrap().as_i32().unwrap() as u32,
+                    is_divergent: bid.get("is_divergent").unwrap().as_bool().unwrap(),
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Util method that generates a RequestDeposit document from a request deposit
+pub fn request_deposit_to_doc(deposit: &RequestDeposit) -> OrderedDocument {
+    doc! {
+        "txid": deposit.txid.to_string(),
+        "genesis_blockhash": deposit.genesis_blockhash.to_string(),
+        "promised": amount_to_bson(&deposit.promised),
+        "locked": amount_to_bson(&deposit.locked),
+        "is_verified": deposit.is_verified(),
+    }
+}
+
+/// Util method that generates a request deposit from a RequestDeposit document
+pub fn doc_to_request_deposit(doc: &OrderedDocument) -> RequestDeposit {
+    RequestDeposit {
+        txid: sha256d::Hash::from_hex(doc.get("txid").unwrap().as_str().unwrap()).unwrap(),
+        genesis_blockhash: sha256d::Hash::from_hex(doc.get("genesis_blockhash").unwrap().as_str().unwrap()).unwrap(),
+        promised: bson_to_amount(doc.get("promised").unwrap()),
+        locked: bson_to_amount(doc.get("locked").unwrap()),
+    }
+}
+
+/// Util method that generates a RequestRejection document from a request
+/// rejection
+pub fn request_rejection_to_doc(rejection: &RequestRejection) -> OrderedDocument {
+    doc! {
+        "txid": rejection.txid.to_string(),
+        "genesis_blockhash": rejection.genesis_blockhash.to_string(),
+        "reason": rejection.reason.clone(),
+    }
+}
+
+/// Util method that generates a request rejection from a RequestRejection
+/// document
+pub fn doc_to_request_rejection(doc: &OrderedDocument) -> RequestRejection {
+    RequestRejection {
+        txid: sha256d::Hash::from_hex(doc.get("txid").unwrap().as_str().unwrap()).unwrap(),
+        genesis_blockhash: sha256d::Hash::from_hex(doc.get("genesis_blockhash").unwrap().as_str().unwrap()).unwrap(),
+        reason: doc.get("reason").unwrap().as_str().unwrap().to_owned(),
+    }
+}
+
+/// Util method that generates a RequestAnnotation document from the operator
+/// annotation of a request. Notes are always set, so that cleared notes replace
+/// any previous notes of the request
+pub fn request_annotation_to_doc(request_hash: &sha256d::Hash, annotation: &RequestAnnotation) -> OrderedDocument {
+    let labels: Vec<Bson> = annotation.labels.iter().cloned().map(Bson::String).collect();
+    doc! {
+        "txid": request_hash.to_string(),
+        "labels": labels,
+        "notes": annotation.notes.clone().map_or(Bson::Null, Bson::String),
+    }
+}
+
+/// Util method that generates the operator annotation of a request from a
+/// RequestAnnotation document
+pub fn doc_to_request_annotation(doc: &OrderedDocument) -> RequestAnnotation {
+    RequestAnnotation {
+        labels: doc
+            .get_array("labels")
+            .unwrap()
+            .iter()
+            .map(|label| label.as_str().unwrap().to_owned())
+            .collect(),
+        notes: doc.get_str("notes").ok().map(|notes| notes.to_owned()),
+    }
+}
+
+/// Util method that generates a DeadLetter document from a dead letter
+pub fn dead_letter_to_doc(letter: &DeadLetter) -> OrderedDocument {
+    let mut doc = doc! {
+        "time": letter.time as i64,
+        "reason": letter.reason.clone(),
+        "size": letter.size as i64,
+        "payload": letter.payload.clone(),
+        "truncated": letter.truncated,
+    };
+    if let Some(ref remote_addr) = letter.remote_addr {
+        let _ = doc.insert("remote_addr", remote_addr.clone());
+    }
+    if let Some(route) = letter.route {
+        let _ = doc.insert("route", route.to_string());
+    }
+    doc
+}
+
+/// Util method that generates a dead letter from a DeadLetter document
+pub fn doc_to_dead_letter(doc: &OrderedDocument) -> DeadLetter {
+    DeadLetter {
+        time: doc.get("time").unwrap().as_i64().unwrap() as u64,
+        reason: doc.get("reason").unwrap().as_str().unwrap().to_owned(),
+        remote_addr: doc
+            .get("remote_addr")
+            .and_then(|remote_addr| remote_addr.as_str())
+            .map(|remote_addr| remote_addr.to_owned()),
+        route: doc
+            .get("route")
+            .and_then(|route| route.as_str())
+            .map(|route| sha256d::Hash::from_hex(route).unwrap()),
+        size: doc.get("size").unwrap().as_i64().unwrap() as u64,
+        payload: doc.get("payload").unwrap().as_str().unwrap().to_owned(),
+        truncated: doc.get("truncated").unwrap().as_bool().unwrap(),
+    }
+}
+
+/// Util method that generates a BidBlacklisting document from a bid
+/// blacklisting
+pub fn bid_blacklisting_to_doc(blacklisting: &BidBlacklisting) -> OrderedDocument {
+    doc! {
+        "txid": blacklisting.txid.to_string(),
+        "request": blacklisting.request.to_string(),
+        "genesis_blockhash": blacklisting.genesis_blockhash.to_string(),
+        "strikes": blacklisting.strikes,
+        "since": blacklisting.since as i64,
+        "until": blacklisting.until as i64,
+    }
+}
+
+/// Util method that generates a bid blacklisting from a BidBlacklisting
+/// document
+pub fn doc_to_bid_blacklisting(doc: &OrderedDocument) -> BidBlacklisting {
+    BidBlacklisting {
+        txid: sha256d::Hash::from_hex(doc.get("txid").unwrap().as_str().unwrap()).unwrap(),
+        request: sha256d::Hash::from_hex(doc.get("request").unwrap().as_str().unwrap()).unwrap(),
+        genesis_blockhash: sha256d::Hash::from_hex(doc.get("genesis_blockhash").unwrap().as_str().unwrap()).unwrap(),
+        strikes: doc.get("strikes").unwrap().as_i32().unwrap() as u32,
+        since: doc.get("since").unwrap().as_i64().unwrap() as u64,
+        until: doc.get("until").unwrap().as_i64().unwrap() as u64,
+    }
+}
+
+/// Util method that generates a BidKeyRotation document from a bid key
+/// rotation
+pub fn bid_key_rotation_to_doc(rotation: &BidKeyRotation) -> OrderedDocument {
+    let mut doc = doc! {
+        "txid": rotation.txid.to_string(),
+        "request": rotation.request.to_string(),
+        "genesis_blockhash": rotation.genesis_blockhash.to_string(),
+        "old_pubkey": rotation.old_pubkey.to_string(),
+        "new_pubkey": rotation.new_pubkey.to_string(),
+        "policy": rotation.policy.clone(),
+        "time": rotation.time as i64,
+    };
+    if let Some(ref sig) = rotation.sig {
+        let _ = doc.insert("sig", sig.clone());
+    }
+    if let Some(ref new_sig) = rotation.new_sig {
+        let _ = doc.insert("new_sig", new_sig.clone());
+    }
+    doc
+}
+
+/// Util method that generates a bid key rotation from a BidKeyRotation
+/// document
+pub fn doc_to_bid_key_rotation(doc: &OrderedDocument) -> BidKeyRotation {
+    BidKeyRotation {
+        txid: sha256d::Hash::from_hex(doc.get("txid").unwrap().as_str().unwrap()).unwrap(),
+        request: sha256d::Hash::from_hex(doc.get("request").unwrap().as_str().unwrap()).unwrap(),
+        genesis_blockhash: sha256d::Hash::from_hex(doc.get("genesis_blockhash").unwrap().as_str().unwrap()).unwrap(),
+        old_pubkey: PublicKey::from_str(doc.get("old_pubkey").unwrap().as_str().unwrap()).unwrap(),
+        new_pubkey: PublicKey::from_str(doc.get("new_pubkey").unwrap().as_str().unwrap()).unwrap(),
+        sig: doc.get("sig").map(|sig| sig.as_str().unwrap().to_owned()),
+        new_sig: doc.get("new_sig").map(|sig| sig.as_str().unwrap().to_owned()),
+        policy: doc.get("policy").unwrap().as_str().unwrap().to_owned(),
+        time: doc.get("time").unwrap().as_i64().unwrap() as u64,
+    }
+}
+
+/// Util method that generates a BidRefund document from a bid lock refund
+pub fn bid_refund_to_doc(refund: &BidRefund) -> OrderedDocument {
+    let mut doc = doc! {
+        "txid": refund.txid.to_string(),
+        "request": refund.request.to_string(),
+        "genesis_blockhash": refund.genesis_blockhash.to_string(),
+        "lock_height": refund.lock_height,
+        "amount": amount_to_bson(&refund.amount),
+        "address": refund.address.clone(),
+        "state": refund.state.as_str(),
+        "tx_hex": refund.tx_hex.clone(),
+    };
+    if let Some(refund_txid) = refund.refund_txid {
+        let _ = doc.insert("refund_txid", refund_txid.to_string());
+    }
+    doc
+}
+
+/// Util method that generates a bid lock refund from a BidRefund document
+pub fn doc_to_bid_refund(doc: &OrderedDocument) -> BidRefund {
+    BidRefund {
+        txid: sha256d::Hash::from_hex(doc.get("txid").unwrap().as_str().unwrap()).unwrap(),
+        request: sha256d::Hash::from_hex(doc.get("request").unwrap().as_str().unwrap()).unwrap(),
+        genesis_blockhash: sha256d::Hash::from_hex(doc.get("genesis_blockhash").unwrap().as_str().unwrap()).unwrap(),
+        lock_height: doc.get("lock_height").unwrap().as_i32().unwrap() as u32,
+        amount: bson_to_amount(doc.get("amount").unwrap()),
+        address: doc.get("address").unwrap().as_str().unwrap().to_owned(),
+        state: BidRefundState::from_str(doc.get("state").unwrap().as_str().unwrap()).unwrap(),
+        refund_txid: doc
+            .get("refund_txid")
+            .map(|txid| sha256d::Hash::from_hex(txid.as_str().unwrap()).unwrap()),
+        tx_hex: doc.get("tx_hex").unwrap().as_str().unwrap().to_owned(),
+    }
+}
+
+/// Util method that generates a PaymentLiability document from a payment
+/// liability. The epoch is only set for liabilities of epoch payments
+pub fn payment_liability_to_doc(liability: &PaymentLiability) -> OrderedDocument {
+    let mut doc = doc! {
+        "request": liability.request.to_string(),
+        "genesis_blockhash": liability.genesis_blockhash.to_string(),
+        "bids": liability.bids.iter().map(|txid| Bson::String(txid.to_string())).collect::<Vec<Bson>>(),
+        "amount": amount_to_bson(&liability.amount),
+        "balance": amount_to_bson(&liability.balance),
+        "time": liability.time as i64,
+    };
+    if let Some(epoch) = liability.epoch {
+        let _ = doc.insert("epoch", epoch);
+    }
+    doc
+}
+
+/// Util method that generates a payment liability from a PaymentLiability
+/// document
+pub fn doc_to_payment_liability(doc: &OrderedDocument) -> PaymentLiability {
+    PaymentLiability {
+        request: sha256d::Hash::from_hex(doc.get("request").unwrap().as_str().unwrap()).unwrap(),
+        genesis_blockhash: sha256d::Hash::from_hex(doc.get("genesis_blockhash").unwrap().as_str().unwrap()).unwrap(),
+        epoch: doc
+            .get("epoch")
+            .and_then(|epoch| epoch.as_i32())
+            .map(|epoch| epoch as u32),
+        bids: doc
+            .get_array("bids")
+            .unwrap()
+            .iter()
+            .map(|txid| sha256d::Hash::from_hex(txid.as_str().unwrap()).unwrap())
+            .collect(),
+        amount: bson_to_amount(doc.get("amount").unwrap()),
+        balance: bson_to_amount(doc.get("balance").unwrap()),
+        time: doc.get("time").unwrap().as_i64().unwrap() as u64,
     }
 }
 
@@ -169,6 +1157,11 @@ mod tests {
             start_blockheight_clientchain: 0,
             end_blockheight_clientchain: 0,
             is_payment_complete: false,
+            payment_state: PaymentState::Pending,
+            response_summary: None,
+            fees: vec![],
+            fee_filter: None,
+            failure: None,
         };
 
         let doc = request_to_doc(&request);
@@ -183,10 +1176,103 @@ mod tests {
                 "start_blockheight_clientchain":0,
                 "end_blockheight_clientchain":0,
                 "is_payment_complete": false,
+                "payment_state": "pending",
+            },
+            doc
+        );
+        assert_eq!(request, doc_to_request(&doc));
+
+        let mut request = request.clone();
+        request.is_payment_complete = true;
+        request.payment_state = PaymentState::NotRequired;
+        request.response_summary = Some(ResponseSummary {
+            num_challenges: 4,
+            num_bids: 2,
+            num_responses: 6,
+            response_rate: 0.75,
+        });
+        request.fees = vec![
+            AssetFees {
+                asset: "CBT".to_owned(),
+                amount: Amount::from_sat(150000),
+            },
+            AssetFees {
+                asset: "FEE".to_owned(),
+                amount: Amount::from_sat(2500),
+            },
+        ];
+        let doc = request_to_doc(&request);
+        assert_eq!(
+            doc! {
+                "txid": request_hash.to_string(),
+                "start_blockheight": 2,
+                "end_blockheight": 5,
+                "genesis_blockhash": genesis_hash,
+                "fee_percentage": 5,
+                "num_tickets": 10,
+                "start_blockheight_clientchain":0,
+                "end_blockheight_clientchain":0,
+                "is_payment_complete": true,
+                "payment_state": "not-required",
+                "response_summary": {
+                    "num_challenges": 4,
+                    "num_bids": 2,
+                    "num_responses": 6,
+                    "response_rate": 0.75,
+                },
+                "fees": [
+                    { "asset": "CBT", "amount": 150000i64 },
+                    { "asset": "FEE", "amount": 2500i64 },
+                ],
             },
             doc
         );
         assert_eq!(request, doc_to_request(&doc));
+
+        // legacy documents without a payment state
+        let mut legacy_doc = doc.clone();
+        let _ = legacy_doc.remove("payment_state");
+        assert_eq!(PaymentState::Paid, doc_to_request(&legacy_doc).payment_state);
+        let _ = legacy_doc.insert("is_payment_complete", false);
+        assert_eq!(PaymentState::Pending, doc_to_request(&legacy_doc).payment_state);
+
+        // fees calculated with a fee filter
+        request.fee_filter = Some(FeeFilter {
+            exclude_addresses: vec!["2dZRkPX3hrPtuBrmMkbGtxTxsuYYgAaFrXZ".to_owned()],
+            exclude_script_types: vec![],
+            include_addresses: vec!["2dt74vJqpNTM12pbiNpHGvry7tyvUu9tJR8".to_owned()],
+        });
+        let doc = request_to_doc(&request);
+        assert_eq!(
+            &doc! {
+                "exclude_addresses": ["2dZRkPX3hrPtuBrmMkbGtxTxsuYYgAaFrXZ"],
+                "exclude_script_types": [],
+                "include_addresses": ["2dt74vJqpNTM12pbiNpHGvry7tyvUu9tJR8"],
+            },
+            doc.get_document("fee_filter").unwrap()
+        );
+        assert_eq!(request, doc_to_request(&doc));
+
+        // request that failed to be challenged
+        request.failure = Some(RequestFailure {
+            category: ErrorCategory::Transient,
+            message: "coordinator error: Storage operation timed out: get_response".to_owned(),
+            height: Some(4),
+            challenges: 2,
+        });
+        let doc = request_to_doc(&request);
+        assert_eq!(
+            &doc! {
+                "category": "transient",
+                "message": "coordinator error: Storage operation timed out: get_response",
+                "challenges": 2,
+                "height": 4i64,
+            },
+            doc.get_document("failure").unwrap()
+        );
+        assert_eq!(request, doc_to_request(&doc));
+        request.failure.as_mut().unwrap().height = None;
+        assert_eq!(request, doc_to_request(&request_to_doc(&request)));
     }
 
     #[test]
@@ -210,15 +1296,17 @@ mod tests {
             },
             doc
         );
-        assert_eq!(bid, doc_to_bid(&doc));
+        assert_eq!(bid, doc_to_bid(&doc).unwrap());
 
         let addr = "CMAMyHorv18WKKTvg5Kifi8ap8CuSJUxXT";
-        let amount = 56.123;
+        let amount = 5612300000i64;
         let mut bid_payment = BidPayment {
-            txid: None,
-            extra_txids: None,
+            txs: vec![],
             address: Address::from_str(addr).unwrap(),
-            amount: Amount::from_btc(amount).unwrap(),
+            address_type: PayoutAddressType::P2pkh,
+            amount: Amount::from_sat(amount as u64),
+            intent: None,
+            basis: None,
         };
         bid.payment = Some(bid_payment.clone());
         let doc = bid_to_doc(&Bson::ObjectId(id.clone()), &bid);
@@ -229,15 +1317,17 @@ mod tests {
                 "pubkey": pubkey_hex,
                 "payment": doc!{
                     "address": addr,
-                    "amount": amount
+                    "address_type": "p2pkh",
+                    "amount": amount,
+                    "txs": []
                 }
             },
             doc
         );
-        assert_eq!(bid, doc_to_bid(&doc));
+        assert_eq!(bid, doc_to_bid(&doc).unwrap());
 
-        let payment_txid = gen_dummy_hash(123);
-        bid_payment.txid = Some(payment_txid);
+        let intent = BidPayment::new_intent(&hash, &bid_payment.amount, 1);
+        bid_payment.intent = Some(intent.clone());
         bid.payment = Some(bid_payment.clone());
         let doc = bid_to_doc(&Bson::ObjectId(id.clone()), &bid);
         assert_eq!(
@@ -247,16 +1337,29 @@ mod tests {
                 "pubkey": pubkey_hex,
                 "payment": doc!{
                     "address": addr,
+                    "address_type": "p2pkh",
                     "amount": amount,
-                    "txid": payment_txid.to_string()
+                    "txs": [],
+                    "intent": intent
                 }
             },
             doc
         );
-        assert_eq!(bid, doc_to_bid(&doc));
+        assert_eq!(bid, doc_to_bid(&doc).unwrap());
+        bid_payment.intent = None;
 
-        let payment_extra_txids = vec![gen_dummy_hash(2), gen_dummy_hash(6), gen_dummy_hash(7)];
-        bid_payment.extra_txids = Some(payment_extra_txids);
+        bid_payment.txs = vec![
+            BidPaymentTx {
+                txid: gen_dummy_hash(123),
+                amount: Amount::from_sat(5000000000),
+                confirmations: 2,
+            },
+            BidPaymentTx {
+                txid: gen_dummy_hash(2),
+                amount: Amount::from_sat(612300000),
+                confirmations: 0,
+            },
+        ];
         bid.payment = Some(bid_payment.clone());
         let doc = bid_to_doc(&Bson::ObjectId(id.clone()), &bid);
         assert_eq!(
@@ -266,16 +1369,136 @@ mod tests {
                 "pubkey": pubkey_hex,
                 "payment": doc!{
                     "address": addr,
+                    "address_type": "p2pkh",
                     "amount": amount,
-                    "txid": "7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b",
-                    "extra_txids": ["0202020202020202020202020202020202020202020202020202020202020202",
-                                    "0606060606060606060606060606060606060606060606060606060606060606",
-                                    "0707070707070707070707070707070707070707070707070707070707070707"]
+                    "txs": [{
+                        "txid": "7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b",
+                        "amount": 5000000000i64,
+                        "confirmations": 2
+                    }, {
+                        "txid": "0202020202020202020202020202020202020202020202020202020202020202",
+                        "amount": 612300000i64,
+                        "confirmations": 0
+                    }]
                 }
             },
             doc
         );
-        assert_eq!(bid, doc_to_bid(&doc));
+        assert_eq!(bid, doc_to_bid(&doc).unwrap());
+
+        // legacy documents store the payment txid and extra txids
+        let legacy_doc = doc! {
+            "request_id": id.clone(),
+            "txid": hash.to_string(),
+            "pubkey": pubkey_hex,
+            "payment": doc!{
+                "address": addr,
+                "amount": amount,
+                "txid": "7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b7b",
+                "extra_txids": ["0202020202020202020202020202020202020202020202020202020202020202"]
+            }
+        };
+        let legacy_payment = doc_to_bid(&legacy_doc).unwrap().payment.unwrap();
+        assert_eq!(PayoutAddressType::P2pkh, legacy_payment.address_type);
+        let legacy_txs = legacy_payment.txs;
+        assert_eq!(2, legacy_txs.len());
+        assert_eq!(gen_dummy_hash(123), legacy_txs[0].txid);
+        assert_eq!(Amount::from_sat(amount as u64), legacy_txs[0].amount);
+        assert_eq!(gen_dummy_hash(2), legacy_txs[1].txid);
+        assert_eq!(Amount::ZERO, legacy_txs[1].amount);
+
+        bid_payment.basis = Some(BidPaymentBasis {
+            formula_version: 1,
+            fees_amount: Amount::from_sat(7),
+            fee_percentage: 50,
+            num_bids: 2,
+            num_challenges: 4,
+            num_responses: 3,
+            latency_weighting: None,
+        });
+        bid.payment = Some(bid_payment.clone());
+        let doc = bid_to_doc(&Bson::ObjectId(id.clone()), &bid);
+        assert_eq!(
+            &doc! {
+                "formula_version": 1,
+                "fees_amount": 7i64,
+                "fee_percentage": 50,
+                "num_bids": 2,
+                "num_challenges": 4,
+                "num_responses": 3,
+            },
+            doc.get_document("payment").unwrap().get_document("basis").unwrap()
+        );
+        assert_eq!(bid, doc_to_bid(&doc).unwrap());
+
+        let mut basis = bid_payment.basis.clone().unwrap();
+        basis.formula_version = 2;
+        basis.latency_weighting = Some(LatencyWeighting::new(vec![1000, 5000], vec![100, 50], &[200, 6000], 3));
+        bid_payment.basis = Some(basis);
+        bid.payment = Some(bid_payment.clone());
+        let doc = bid_to_doc(&Bson::ObjectId(id.clone()), &bid);
+        assert_eq!(
+            &doc! {
+                "bucket_max_ms": [1000i64, 5000i64],
+                "bucket_weights": [100, 50],
+                "bucket_responses": [1, 1],
+                "unmeasured_responses": 1,
+            },
+            doc.get_document("payment")
+                .unwrap()
+                .get_document("basis")
+                .unwrap()
+                .get_document("latency_weighting")
+                .unwrap()
+        );
+        assert_eq!(bid, doc_to_bid(&doc).unwrap());
+    }
+
+    #[test]
+    fn bid_doc_encryption_test() {
+        let request_id = Bson::String(gen_dummy_hash(9).to_string());
+        let addr = "CMAMyHorv18WKKTvg5Kifi8ap8CuSJUxXT";
+        let bid = Bid {
+            txid: gen_dummy_hash(1),
+            pubkey: PublicKey::from_str("026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3").unwrap(),
+            payment: Some(BidPayment {
+                txs: vec![BidPaymentTx {
+                    txid: gen_dummy_hash(2),
+                    amount: Amount::from_sat(5612300000),
+                    confirmations: 1,
+                }],
+                address: Address::from_str(addr).unwrap(),
+                address_type: PayoutAddressType::P2pkh,
+                amount: Amount::from_sat(5612300000),
+                intent: None,
+                basis: None,
+            }),
+        };
+        let cipher = FieldCipher::from_hex(&"ab".repeat(32)).unwrap();
+
+        // payment address and amounts are encrypted
+        let doc = bid_to_doc_with_cipher(&request_id, &bid, Some(&cipher));
+        let payment_doc = doc.get_document("payment").unwrap();
+        assert!(is_encrypted(payment_doc.get_str("address").unwrap()));
+        assert!(is_encrypted(payment_doc.get_str("amount").unwrap()));
+        let tx_doc = payment_doc.get_array("txs").unwrap()[0].as_document().unwrap();
+        assert!(is_encrypted(tx_doc.get_str("amount").unwrap()));
+        assert_eq!("p2pkh", payment_doc.get_str("address_type").unwrap());
+        assert_eq!(bid, doc_to_bid_with_cipher(&doc, Some(&cipher)).unwrap());
+
+        // encrypted documents fail to be read without the cipher or under
+        // another key
+        assert_eq!(
+            "coordinator error: generic Error: encrypted field without encryption key",
+            doc_to_bid_with_cipher(&doc, None).unwrap_err().to_string()
+        );
+        let other = FieldCipher::from_hex(&"cd".repeat(32)).unwrap();
+        assert!(doc_to_bid_with_cipher(&doc, Some(&other)).is_err());
+
+        // plaintext documents are still read with the cipher set
+        let doc = bid_to_doc_with_cipher(&request_id, &bid, None);
+        assert_eq!(addr, doc.get_document("payment").unwrap().get_str("address").unwrap());
+        assert_eq!(bid, doc_to_bid_with_cipher(&doc, Some(&cipher)).unwrap());
     }
 
     #[test]
@@ -327,5 +1550,541 @@ mod tests {
         }
         assert_eq!(4, doc.get_document("bid_responses").unwrap().len());
         assert_eq!(resp, doc_to_response(&doc));
+
+        resp.challenges = vec![gen_dummy_hash(7), gen_dummy_hash(8)];
+        let doc = response_to_doc(&Bson::ObjectId(id.clone()), &resp);
+        assert_eq!(
+            &vec![
+                Bson::String(gen_dummy_hash(7).to_string()),
+                Bson::String(gen_dummy_hash(8).to_string())
+            ],
+            doc.get_array("challenges").unwrap()
+        );
+        assert_eq!(resp, doc_to_response(&doc));
+    }
+
+    #[test]
+    fn request_deposit_doc_test() {
+        setup_logger();
+        let deposit = RequestDeposit {
+            txid: gen_dummy_hash(1),
+            genesis_blockhash: gen_dummy_hash(2),
+            promised: Amount::from_btc(2.5).unwrap(),
+            locked: Amount::from_btc(1.0).unwrap(),
+        };
+        let doc = request_deposit_to_doc(&deposit);
+        assert_eq!(
+            doc! {
+                "txid": gen_dummy_hash(1).to_string(),
+                "genesis_blockhash": gen_dummy_hash(2).to_string(),
+                "promised": 250000000i64,
+                "locked": 100000000i64,
+                "is_verified": false,
+            },
+            doc
+        );
+        assert_eq!(deposit, doc_to_request_deposit(&doc));
+
+        // legacy documents store btc amounts
+        let legacy_doc = doc! {
+            "txid": gen_dummy_hash(1).to_string(),
+            "genesis_blockhash": gen_dummy_hash(2).to_string(),
+            "promised": 2.5,
+            "locked": 1.0,
+            "is_verified": false,
+        };
+        assert_eq!(deposit, doc_to_request_deposit(&legacy_doc));
+    }
+
+    #[test]
+    fn amount_doc_test() {
+        setup_logger();
+        for sat in &[0, 1, 7, 99_999_999, 123_456_789_012, 21_000_000 * 100_000_000] {
+            let amount = Amount::from_sat(*sat);
+            assert_eq!(Bson::I64(*sat as i64), amount_to_bson(&amount));
+            assert_eq!(amount, bson_to_amount(&amount_to_bson(&amount)));
+        }
+        assert_eq!(Amount::from_sat(5), bson_to_amount(&Bson::I32(5)));
+
+        // legacy btc amounts are rounded to the nearest satoshi
+        assert_eq!(Amount::from_sat(1), bson_to_amount(&Bson::FloatingPoint(0.00000001)));
+        assert_eq!(
+            Amount::from_sat(30000000),
+            bson_to_amount(&Bson::FloatingPoint(0.1 + 0.2))
+        );
+        assert_eq!(
+            Amount::from_sat(5612300000),
+            bson_to_amount(&Bson::FloatingPoint(56.123))
+        );
+    }
+
+    #[test]
+    fn request_rejection_doc_test() {
+        setup_logger();
+        let rejection = RequestRejection {
+            txid: gen_dummy_hash(1),
+            genesis_blockhash: gen_dummy_hash(2),
+            reason: "no tickets".to_owned(),
+        };
+        let doc = request_rejection_to_doc(&rejection);
+        assert_eq!(
+            doc! {
+                "txid": gen_dummy_hash(1).to_string(),
+                "genesis_blockhash": gen_dummy_hash(2).to_string(),
+                "reason": "no tickets",
+            },
+            doc
+        );
+        assert_eq!(rejection, doc_to_request_rejection(&doc));
+    }
+
+    #[test]
+    fn request_annotation_doc_test() {
+        setup_logger();
+        let mut annotation = RequestAnnotation {
+            labels: vec!["trial".to_owned(), "disputed".to_owned()],
+            notes: Some("client X trial".to_owned()),
+        };
+        let doc = request_annotation_to_doc(&gen_dummy_hash(1), &annotation);
+        assert_eq!(
+            doc! {
+                "txid": gen_dummy_hash(1).to_string(),
+                "labels": ["trial", "disputed"],
+                "notes": "client X trial",
+            },
+            doc
+        );
+        assert_eq!(annotation, doc_to_request_annotation(&doc));
+
+        // cleared notes
+        annotation.notes = None;
+        let doc = request_annotation_to_doc(&gen_dummy_hash(1), &annotation);
+        assert_eq!(Some(&Bson::Null), doc.get("notes"));
+        assert_eq!(annotation, doc_to_request_annotation(&doc));
+    }
+
+    #[test]
+    fn bid_blacklisting_doc_test() {
+        setup_logger();
+        let blacklisting = BidBlacklisting {
+            txid: gen_dummy_hash(1),
+            request: gen_dummy_hash(2),
+            genesis_blockhash: gen_dummy_hash(3),
+            strikes: 5,
+            since: 1600000000,
+            until: 1600003600,
+        };
+        let doc = bid_blacklisting_to_doc(&blacklisting);
+        assert_eq!(
+            doc! {
+                "txid": gen_dummy_hash(1).to_string(),
+                "request": gen_dummy_hash(2).to_string(),
+                "genesis_blockhash": gen_dummy_hash(3).to_string(),
+                "strikes": 5,
+                "since": 1600000000i64,
+                "until": 1600003600i64,
+            },
+            doc
+        );
+        assert_eq!(blacklisting, doc_to_bid_blacklisting(&doc));
+    }
+
+    #[test]
+    fn dead_letter_doc_test() {
+        setup_logger();
+        let mut letter = DeadLetter {
+            time: 1600000000,
+            reason: "bad-json-data".to_owned(),
+            remote_addr: None,
+            route: None,
+            size: 5,
+            payload: "{\"tx".to_owned(),
+            truncated: true,
+        };
+        let doc = dead_letter_to_doc(&letter);
+        assert_eq!(
+            doc! {
+                "time": 1600000000i64,
+                "reason": "bad-json-data",
+                "size": 5i64,
+                "payload": "{\"tx",
+                "truncated": true,
+            },
+            doc
+        );
+        assert_eq!(letter, doc_to_dead_letter(&doc));
+
+        letter.remote_addr = Some("127.0.0.1".to_owned());
+        letter.route = Some(gen_dummy_hash(1));
+        assert_eq!(letter, doc_to_dead_letter(&dead_letter_to_doc(&letter)));
+    }
+
+    #[test]
+    fn bid_key_rotation_doc_test() {
+        setup_logger();
+        let old_pubkey = "026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3";
+        let new_pubkey = "0268680737c76dabb801cb2204f57dbe4e4579e4f710cd67dc1b4227592c81e9b5";
+        let mut rotation = BidKeyRotation {
+            txid: gen_dummy_hash(1),
+            request: gen_dummy_hash(2),
+            genesis_blockhash: gen_dummy_hash(3),
+            old_pubkey: PublicKey::from_str(old_pubkey).unwrap(),
+            new_pubkey: PublicKey::from_str(new_pubkey).unwrap(),
+            sig: Some("3044".to_owned()),
+            new_sig: None,
+            policy: "old-key".to_owned(),
+            time: 1600000000,
+        };
+        let doc = bid_key_rotation_to_doc(&rotation);
+        assert_eq!(
+            doc! {
+                "txid": gen_dummy_hash(1).to_string(),
+                "request": gen_dummy_hash(2).to_string(),
+                "genesis_blockhash": gen_dummy_hash(3).to_string(),
+                "old_pubkey": old_pubkey,
+                "new_pubkey": new_pubkey,
+                "policy": "old-key",
+                "time": 1600000000i64,
+                "sig": "3044",
+            },
+            doc
+        );
+        assert_eq!(rotation, doc_to_bid_key_rotation(&doc));
+
+        rotation.new_sig = Some("3045".to_owned());
+        assert_eq!(rotation, doc_to_bid_key_rotation(&bid_key_rotation_to_doc(&rotation)));
+    }
+
+    #[test]
+    fn bid_refund_doc_test() {
+        setup_logger();
+        let mut refund = BidRefund {
+            txid: gen_dummy_hash(1),
+            request: gen_dummy_hash(2),
+            genesis_blockhash: gen_dummy_hash(3),
+            lock_height: 1440,
+            amount: Amount::from_sat(1000),
+            address: "2dt74vJqpNTM12pbiNpHGvry7tyvUu9tJR8".to_owned(),
+            state: BidRefundState::Exported,
+            refund_txid: None,
+            tx_hex: "0200".to_owned(),
+        };
+        let doc = bid_refund_to_doc(&refund);
+        assert_eq!(None, doc.get("refund_txid"));
+        assert_eq!("exported", doc.get("state").unwrap().as_str().unwrap());
+        assert_eq!(refund, doc_to_bid_refund(&doc));
+
+        refund.state = BidRefundState::Broadcast;
+        refund.refund_txid = Some(gen_dummy_hash(4));
+        let doc = bid_refund_to_doc(&refund);
+        assert_eq!(
+            gen_dummy_hash(4).to_string(),
+            doc.get("refund_txid").unwrap().as_str().unwrap()
+        );
+        assert_eq!(refund, doc_to_bid_refund(&doc));
+    }
+
+    #[test]
+    fn payment_liability_doc_test() {
+        setup_logger();
+        let mut liability = PaymentLiability {
+            request: gen_dummy_hash(1),
+            genesis_blockhash: gen_dummy_hash(2),
+            epoch: None,
+            bids: vec![gen_dummy_hash(3), gen_dummy_hash(4)],
+            amount: Amount::from_sat(2000),
+            balance: Amount::from_sat(500),
+            time: 1600000000000,
+        };
+        let doc = payment_liability_to_doc(&liability);
+        assert_eq!(None, doc.get("epoch"));
+        assert_eq!(2, doc.get_array("bids").unwrap().len());
+        assert_eq!(liability, doc_to_payment_liability(&doc));
+
+        liability.epoch = Some(3);
+        let doc = payment_liability_to_doc(&liability);
+        assert_eq!(3, doc.get_i32("epoch").unwrap());
+        assert_eq!(liability, doc_to_payment_liability(&doc));
+    }
+
+    #[test]
+    fn response_snapshot_doc_test() {
+        setup_logger();
+        let request_hash = gen_dummy_hash(1);
+        let mut response = Response::new();
+        response.num_challenges = 2;
+        response.challenges = vec![gen_dummy_hash(7), gen_dummy_hash(8)];
+        let _ = response.bid_responses.insert(gen_dummy_hash(3), 2);
+        let snapshot = ResponseSnapshot {
+            epoch: 1,
+            service_height: 20,
+            clientchain_height: 120,
+            response: response.clone(),
+            bids: vec![Bid {
+                txid: gen_dummy_hash(3),
+                pubkey: PublicKey::from_str("026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3")
+                    .unwrap(),
+                payment: Some(BidPayment {
+                    txs: vec![BidPaymentTx {
+                        txid: gen_dummy_hash(9),
+                        amount: Amount::from_btc(1.5).unwrap(),
+                        confirmations: 1,
+                    }],
+                    address: Address::from_str("CMAMyHorv18WKKTvg5Kifi8ap8CuSJUxXT").unwrap(),
+                    address_type: PayoutAddressType::P2pkh,
+                    amount: Amount::from_btc(1.5).unwrap(),
+                    intent: None,
+                    basis: None,
+                }),
+            }],
+            is_payment_complete: true,
+        };
+
+        let doc = response_snapshot_to_doc(&request_hash, &snapshot);
+        let request_id = Bson::String(request_hash.to_string());
+        assert_eq!(request_hash.to_string(), doc.get_str("txid").unwrap());
+        assert_eq!(1, doc.get_i32("epoch").unwrap());
+        assert_eq!(
+            &response_to_doc(&request_id, &response),
+            doc.get_document("response").unwrap()
+        );
+        assert_eq!(
+            &vec![Bson::Document(bid_to_doc(&request_id, &snapshot.bids[0]))],
+            doc.get_array("bids").unwrap()
+        );
+        assert_eq!(snapshot, doc_to_response_snapshot(&doc).unwrap());
+    }
+
+    #[test]
+    fn challenge_record_doc_test() {
+        let request_hash = gen_dummy_hash(1);
+        let mut responses = HashSet::new();
+        let _ = responses.insert(gen_dummy_hash(3));
+        let _ = responses.insert(gen_dummy_hash(2));
+        let record = ChallengeRecord::new(gen_dummy_hash(9), &responses);
+
+        let doc = challenge_record_to_doc(&request_hash, &record);
+        let responders = vec![
+            Bson::String(gen_dummy_hash(2).to_string()),
+            Bson::String(gen_dummy_hash(3).to_string()),
+        ];
+        assert_eq!(
+            doc! {
+                "txid": request_hash.to_string(),
+                "challenge": gen_dummy_hash(9).to_string(),
+                "responders": responders,
+            },
+            doc
+        );
+        assert_eq!(record, doc_to_challenge_record(&doc));
+
+        let record = ChallengeRecord::new(gen_dummy_hash(9), &HashSet::new());
+        assert_eq!(
+            record,
+            doc_to_challenge_record(&challenge_record_to_doc(&request_hash, &record))
+        );
+    }
+
+    #[test]
+    fn challenge_stats_doc_test() {
+        let request_hash = gen_dummy_hash(1);
+        let stats = ChallengeStats {
+            challenge: gen_dummy_hash(9),
+            height: 120,
+            num_bids: 4,
+            num_responses: 3,
+            amount_tag: None,
+            wallet: None,
+        };
+
+        let doc = challenge_stats_to_doc(&request_hash, &stats);
+        assert_eq!(
+            doc! {
+                "txid": request_hash.to_string(),
+                "challenge": gen_dummy_hash(9).to_string(),
+                "height": 120i64,
+                "num_bids": 4,
+                "num_responses": 3,
+            },
+            doc
+        );
+        assert_eq!(stats, doc_to_challenge_stats(&doc));
+
+        let mut stats = stats;
+        stats.amount_tag = Some(ChallengeAmountTag { sequence: 7, amount: 8 });
+        stats.wallet = Some("challenges1".to_owned());
+        let doc = challenge_stats_to_doc(&request_hash, &stats);
+        assert_eq!(
+            Some(&Bson::from(doc! { "sequence": 7, "amount": 8i64 })),
+            doc.get("amount_tag")
+        );
+        assert_eq!("challenges1", doc.get_str("wallet").unwrap());
+        assert_eq!(stats, doc_to_challenge_stats(&doc));
+    }
+
+    #[test]
+    fn bid_proof_doc_test() {
+        setup_logger();
+        let request_hash = gen_dummy_hash(1);
+        let proof = BidProof {
+            bid: gen_dummy_hash(2),
+            challenge: gen_dummy_hash(3),
+            clientchain_height: 120,
+            timestamp: 1_600_000_000_123,
+        };
+
+        let doc = bid_proof_to_doc(&request_hash, &proof);
+        assert_eq!(request_hash.to_string(), doc.get_str("txid").unwrap());
+        assert_eq!(gen_dummy_hash(2).to_string(), doc.get_str("bid").unwrap());
+        assert_eq!(120, doc.get_i32("clientchain_height").unwrap());
+        assert_eq!(proof, doc_to_bid_proof(&doc));
+    }
+
+    #[test]
+    fn challenge_latency_doc_test() {
+        setup_logger();
+        let request_hash = gen_dummy_hash(1);
+        let mut latency = ChallengeLatency::new();
+        latency.verify_ms = vec![1200, 800];
+        latency.add_proof(gen_dummy_hash(2), 50);
+        latency.add_proof(gen_dummy_hash(3), 70);
+        latency.add_proof(gen_dummy_hash(2), 60);
+
+        let doc = challenge_latency_to_doc(&request_hash, &latency);
+        assert_eq!(request_hash.to_string(), doc.get_str("txid").unwrap());
+        assert_eq!(
+            &doc! {
+                "count": 2,
+                "p50": 800i64,
+                "p90": 1200i64,
+                "p99": 1200i64,
+                "max": 1200i64,
+            },
+            doc.get_document("verify").unwrap()
+        );
+        assert_eq!(60, doc.get_document("proof").unwrap().get_i64("p50").unwrap());
+        assert_eq!(latency, doc_to_challenge_latency(&doc));
+
+        let mut legacy_doc = doc.clone();
+        let _ = legacy_doc.remove("bid_proof_ms");
+        assert!(doc_to_challenge_latency(&legacy_doc).bid_proof_ms.is_empty());
+    }
+
+    #[test]
+    fn challenge_coverage_doc_test() {
+        setup_logger();
+        let request_hash = gen_dummy_hash(1);
+        let coverage = ChallengeCoverage {
+            frequency: 2,
+            height: 120,
+            scheduled: 4,
+            issued: 3,
+            coverage: 0.75,
+            gaps: vec![CoverageGap {
+                start_height: 104,
+                end_height: 105,
+                missed: 1,
+            }],
+        };
+
+        let doc = challenge_coverage_to_doc(&request_hash, &coverage);
+        assert_eq!(request_hash.to_string(), doc.get_str("txid").unwrap());
+        assert_eq!(
+            &doc! {
+                "start_height": 104i64,
+                "end_height": 105i64,
+                "missed": 1,
+            },
+            doc.get_array("gaps").unwrap()[0].as_document().unwrap()
+        );
+        assert_eq!(coverage, doc_to_challenge_coverage(&doc));
+    }
+
+    #[test]
+    fn clientchain_stall_doc_test() {
+        setup_logger();
+        let request_hash = gen_dummy_hash(1);
+        let stall = ClientChainStall {
+            clientchain_height: 50,
+            start_height: 104,
+            end_height: 110,
+            start_time: 1_600_000_000,
+            end_time: 1_600_000_600,
+            missed_blocks: 9,
+        };
+
+        let doc = clientchain_stall_to_doc(&request_hash, &stall);
+        assert_eq!(request_hash.to_string(), doc.get_str("txid").unwrap());
+        assert_eq!(50, doc.get_i32("clientchain_height").unwrap());
+        assert_eq!(1_600_000_600, doc.get_i64("end_time").unwrap());
+        assert_eq!(stall, doc_to_clientchain_stall(&doc));
+    }
+
+    #[test]
+    fn response_reconciliation_doc_test() {
+        setup_logger();
+        let request_hash = gen_dummy_hash(1);
+        let reconciliation = ResponseReconciliation {
+            clientchain_height: 120,
+            bids: vec![
+                BidReconciliation::new(gen_dummy_hash(3), 2, 2),
+                BidReconciliation::new(gen_dummy_hash(4), 2, 1),
+            ],
+        };
+
+        let doc = response_reconciliation_to_doc(&request_hash, &reconciliation);
+        assert_eq!(request_hash.to_string(), doc.get_str("txid").unwrap());
+        assert_eq!(120, doc.get_i32("clientchain_height").unwrap());
+        assert_eq!(
+            &Bson::Document(doc! {
+                "txid": gen_dummy_hash(4).to_string(),
+                "listener_responses": 2,
+                "onchain_responses": 1,
+                "is_divergent": true,
+            }),
+            &doc.get_array("bids").unwrap()[1]
+        );
+        assert_eq!(reconciliation, doc_to_response_reconciliation(&doc));
+    }
+
+    #[test]
+    fn fee_pool_doc_test() {
+        setup_logger();
+        let request_hash = gen_dummy_hash(1);
+        let fee = AssetFees {
+            asset: "CBT".to_owned(),
+            amount: Amount::from_sat(1500),
+        };
+        let mut pool = FeePool::new(
+            100,
+            110,
+            vec![BlockFees {
+                height: 104,
+                fees: vec![fee.clone()],
+            }],
+            25,
+            None,
+        );
+
+        let doc = fee_pool_to_doc(&request_hash, &pool);
+        assert_eq!(request_hash.to_string(), doc.get_str("txid").unwrap());
+        assert_eq!(1500, doc.get_i64("total").unwrap());
+        assert_eq!(375, doc.get_i64("guardnode_amount").unwrap());
+        assert_eq!(
+            &Bson::Document(doc! {
+                "asset": "CBT",
+                "amount": 1500i64,
+            }),
+            &doc.get_array("fees").unwrap()[0]
+        );
+        assert!(doc.get("fee_filter").is_none());
+        assert_eq!(pool, doc_to_fee_pool(&doc));
+
+        pool.fee_filter = Some(FeeFilter {
+            exclude_addresses: vec![],
+            exclude_script_types: vec!["nulldata".to_owned()],
+            include_addresses: vec![],
+        });
+        assert_eq!(pool, doc_to_fee_pool(&fee_pool_to_doc(&request_hash, &pool)));
     }
 }
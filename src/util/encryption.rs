@@ -0,0 +1,216 @@
+//! # Encryption
+//!
+//! Field level encryption of sensitive values stored in documents, such as the
+//! addresses and amounts of bid payments. Values are encrypted with AES-256-GCM
+//! under the key set in the storage config or read from a keyfile, and stored
+//! as tagged strings so that plaintext values of earlier documents are still
+//! read. The cipher is process wide, as documents are converted throughout the
+//! storage implementations, and is set once when storage is initialised
+
+use std::fs;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+use aes_gcm::aead::{generic_array::GenericArray, Aead, NewAead};
+use aes_gcm::Aes256Gcm;
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::hashes::{sha256, Hash};
+
+use crate::config::StorageConfig;
+use crate::error::{CError, Error, Result};
+
+/// Prefix of encrypted field values
+pub const ENCRYPTED_FIELD_PREFIX: &str = "enc1:";
+
+/// Length of the encryption key in bytes
+const KEY_LEN: usize = 32;
+
+/// Length of the random nonce prepended to each ciphertext in bytes
+const NONCE_LEN: usize = 12;
+
+/// AES-256-GCM cipher of sensitive document fields
+pub struct FieldCipher {
+    /// Cipher instance
+    cipher: Aes256Gcm,
+    /// Digest of the key, identifying the key without keeping it
+    key_id: sha256::Hash,
+}
+
+impl FieldCipher {
+    /// Return new FieldCipher instance from a 32 byte key
+    pub fn new(key: &[u8]) -> Result<FieldCipher> {
+        if key.len() != KEY_LEN {
+            return Err(Error::from(CError::Generic(format!(
+                "field encryption key must be {} bytes, got {}",
+                KEY_LEN,
+                key.len()
+            ))));
+        }
+        Ok(FieldCipher {
+            cipher: Aes256Gcm::new(GenericArray::clone_from_slice(key)),
+            key_id: sha256::Hash::hash(key),
+        })
+    }
+
+    /// Return new FieldCipher instance from a hex key
+    pub fn from_hex(key_hex: &str) -> Result<FieldCipher> {
+        FieldCipher::new(&Vec::<u8>::from_hex(key_hex.trim())?)
+    }
+
+    /// Get the cipher of the key set in the storage config or the hex key read
+    /// from the keyfile, or None if field encryption is not configured
+    pub fn from_config(config: &StorageConfig) -> Result<Option<FieldCipher>> {
+        if let Some(key) = &config.encryption_key {
+            return Ok(Some(FieldCipher::from_hex(key)?));
+        }
+        if let Some(keyfile) = &config.encryption_keyfile {
+            let key = fs::read_to_string(keyfile).map_err(|e| {
+                Error::from(CError::Generic(format!(
+                    "failed reading encryption keyfile {}: {}",
+                    keyfile, e
+                )))
+            })?;
+            return Ok(Some(FieldCipher::from_hex(&key)?));
+        }
+        Ok(None)
+    }
+
+    /// Encrypt a field value under a random nonce, returning the prefixed
+    /// base64 encoding of the nonce and ciphertext
+    pub fn encrypt(&self, value: &str) -> String {
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let mut bytes = nonce.to_vec();
+        bytes.extend(
+            self.cipher
+                .encrypt(GenericArray::from_slice(&nonce), value.as_bytes())
+                .expect("field encryption failed"),
+        );
+        format!("{}{}", ENCRYPTED_FIELD_PREFIX, base64::encode(&bytes))
+    }
+
+    /// Decrypt a field value encrypted by the cipher
+    pub fn decrypt(&self, value: &str) -> Result<String> {
+        let invalid = |reason: &str| Error::from(CError::Generic(format!("failed decrypting field: {}", reason)));
+        if !is_encrypted(value) {
+            return Err(invalid("value not encrypted"));
+        }
+        let bytes = base64::decode(&value[ENCRYPTED_FIELD_PREFIX.len()..]).map_err(|_| invalid("invalid encoding"))?;
+        if bytes.len() < NONCE_LEN {
+            return Err(invalid("value too short"));
+        }
+        let plaintext = self
+            .cipher
+            .decrypt(GenericArray::from_slice(&bytes[..NONCE_LEN]), &bytes[NONCE_LEN..])
+            .map_err(|_| invalid("wrong key or corrupted value"))?;
+        String::from_utf8(plaintext).map_err(|_| invalid("invalid plaintext"))
+    }
+}
+
+/// Check whether a stored field value is encrypted
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_FIELD_PREFIX)
+}
+
+/// Process wide cipher of sensitive fields
+static FIELD_CIPHER: AtomicPtr<FieldCipher> = AtomicPtr::new(ptr::null_mut());
+
+/// Set the process wide field cipher from the storage config if field
+/// encryption is configured. The cipher is only set once, so that documents
+/// are converted under the same key throughout the process, and setting it
+/// again under a different key fails
+pub fn init_field_cipher(config: &StorageConfig) -> Result<()> {
+    match FieldCipher::from_config(config)? {
+        Some(cipher) => set_field_cipher(&FIELD_CIPHER, cipher),
+        None => Ok(()),
+    }
+}
+
+/// Set a field cipher once, failing if already set under a different key
+fn set_field_cipher(slot: &AtomicPtr<FieldCipher>, cipher: FieldCipher) -> Result<()> {
+    let cipher = Box::into_raw(Box::new(cipher));
+    let current = slot.compare_and_swap(ptr::null_mut(), cipher, Ordering::SeqCst);
+    if current.is_null() {
+        return Ok(());
+    }
+    // already set, drop the new cipher. The current cipher is never freed
+    let cipher = unsafe { Box::from_raw(cipher) };
+    if unsafe { (*current).key_id } != cipher.key_id {
+        return Err(Error::from(CError::Generic(
+            "field cipher already set under a different encryption key".to_owned(),
+        )));
+    }
+    Ok(())
+}
+
+/// Get the process wide field cipher, if field encryption is configured
+pub fn field_cipher() -> Option<&'static FieldCipher> {
+    let cipher = FIELD_CIPHER.load(Ordering::SeqCst);
+    if cipher.is_null() {
+        return None;
+    }
+    // the cipher is never freed once set
+    unsafe { Some(&*cipher) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_cipher_test() {
+        let cipher = FieldCipher::from_hex(&"ab".repeat(32)).unwrap();
+        let encrypted = cipher.encrypt("2dj1dQvmsaAXR1oCGe8fWfX2rn9Xrw6kwLn");
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("2dj1dQvmsaAXR1oCGe8fWfX2rn9Xrw6kwLn"));
+        assert_eq!(
+            "2dj1dQvmsaAXR1oCGe8fWfX2rn9Xrw6kwLn",
+            cipher.decrypt(&encrypted).unwrap()
+        );
+
+        // random nonces give different ciphertexts of the same value
+        assert!(encrypted != cipher.encrypt("2dj1dQvmsaAXR1oCGe8fWfX2rn9Xrw6kwLn"));
+
+        // wrong key, tampered and plaintext values
+        let other = FieldCipher::from_hex(&"cd".repeat(32)).unwrap();
+        assert!(other.decrypt(&encrypted).is_err());
+        let mut tampered = encrypted.clone();
+        let _ = tampered.pop();
+        tampered.push(if encrypted.ends_with('A') { 'B' } else { 'A' });
+        assert!(cipher.decrypt(&tampered).is_err());
+        assert!(cipher.decrypt("2dj1dQvmsaAXR1oCGe8fWfX2rn9Xrw6kwLn").is_err());
+
+        // invalid keys
+        assert!(FieldCipher::from_hex("abcd").is_err());
+        assert!(FieldCipher::from_hex("zz").is_err());
+    }
+
+    #[test]
+    fn set_field_cipher_test() {
+        let slot = AtomicPtr::new(ptr::null_mut());
+        set_field_cipher(&slot, FieldCipher::from_hex(&"ab".repeat(32)).unwrap()).unwrap();
+        set_field_cipher(&slot, FieldCipher::from_hex(&"ab".repeat(32)).unwrap()).unwrap();
+        assert_eq!(
+            "coordinator error: generic Error: field cipher already set under a different encryption key",
+            set_field_cipher(&slot, FieldCipher::from_hex(&"cd".repeat(32)).unwrap())
+                .unwrap_err()
+                .to_string()
+        );
+        let cipher = unsafe { Box::from_raw(slot.load(Ordering::SeqCst)) };
+        let expected = FieldCipher::from_hex(&"ab".repeat(32)).unwrap();
+        assert_eq!("value", expected.decrypt(&cipher.encrypt("value")).unwrap());
+    }
+
+    #[test]
+    fn field_cipher_from_config_test() {
+        let mut config = StorageConfig::default();
+        assert!(FieldCipher::from_config(&config).unwrap().is_none());
+
+        config.encryption_keyfile = Some("/nonexistent/coordinator.key".to_owned());
+        assert!(FieldCipher::from_config(&config).is_err());
+
+        config.encryption_key = Some("ab".repeat(32));
+        let cipher = FieldCipher::from_config(&config).unwrap().unwrap();
+        let expected = FieldCipher::from_hex(&"ab".repeat(32)).unwrap();
+        assert_eq!("value", expected.decrypt(&cipher.encrypt("value")).unwrap());
+    }
+}
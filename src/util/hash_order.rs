@@ -0,0 +1,121 @@
+//! # Hash Order
+//!
+//! Byte order of the hashes returned and accepted by the api. Hashes are hex
+//! encoded in rpc byte order, i.e. reversed as displayed by ocean rpc calls,
+//! unless the internal byte order used by some explorers is requested
+
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+
+use crate::error::{CError, Error};
+
+/// Byte order of hex encoded hashes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HashOrder {
+    /// Reversed byte order used by ocean rpc calls
+    Rpc,
+    /// Internal byte order of the hash
+    Internal,
+}
+
+impl FromStr for HashOrder {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<HashOrder, Error> {
+        match s {
+            "rpc" => Ok(HashOrder::Rpc),
+            "internal" => Ok(HashOrder::Internal),
+            _ => Err(CError::Generic(format!("unknown hash order: {}", s)).into()),
+        }
+    }
+}
+
+/// Check whether a string is a hex encoded 32 byte hash
+fn is_hash_hex(s: &str) -> bool {
+    s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Reverse the byte order of a hex encoded hash
+fn reverse_hash_hex(s: &str) -> String {
+    let bytes = s.as_bytes();
+    (0..bytes.len() / 2)
+        .rev()
+        .map(|i| String::from_utf8_lossy(&bytes[i * 2..i * 2 + 2]).into_owned())
+        .collect()
+}
+
+/// Reverse the byte order of all the hashes in a json value, including hashes
+/// used as object keys. Converting a value twice returns the original value
+pub fn reverse_hashes(value: Value) -> Value {
+    match value {
+        Value::String(ref s) if is_hash_hex(s) => Value::String(reverse_hash_hex(s)),
+        Value::Array(items) => Value::Array(items.into_iter().map(reverse_hashes).collect()),
+        Value::Object(obj) => Value::Object(
+            obj.into_iter()
+                .map(|(key, val)| {
+                    let key = if is_hash_hex(&key) { reverse_hash_hex(&key) } else { key };
+                    (key, reverse_hashes(val))
+                })
+                .collect::<Map<String, Value>>(),
+        ),
+        value => value,
+    }
+}
+
+impl HashOrder {
+    /// Convert the hashes of a json value from rpc byte order to this order
+    pub fn from_rpc(&self, value: Value) -> Value {
+        match self {
+            HashOrder::Rpc => value,
+            HashOrder::Internal => reverse_hashes(value),
+        }
+    }
+
+    /// Convert the hashes of a json value from this order to rpc byte order
+    pub fn to_rpc(&self, value: Value) -> Value {
+        // reversing is its own inverse
+        self.from_rpc(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bitcoin::hashes::{sha256d, Hash};
+
+    #[test]
+    fn reverse_hashes_test() {
+        let hash = sha256d::Hash::from_slice(&(0..32).collect::<Vec<u8>>()).unwrap();
+        let rpc_hex = hash.to_string();
+        let internal_hex = (0..32).map(|b| format!("{:02x}", b)).collect::<String>();
+        assert_eq!(internal_hex, reverse_hash_hex(&rpc_hex));
+
+        let mut bid_responses = Map::new();
+        let _ = bid_responses.insert(rpc_hex.clone(), Value::from(1));
+        let value = serde_json::json!({
+            "txid": rpc_hex.clone(),
+            "pubkey": "026a04ab98d9e4774ad806e302dddeb63bea16b5cb5f223ee77478e861bb583eb3",
+            "height": 1,
+            "challenges": [rpc_hex.clone()],
+            "bid_responses": bid_responses,
+            "label": "not a hash",
+        });
+        let internal = HashOrder::Internal.from_rpc(value.clone());
+        assert_eq!(internal_hex, internal["txid"]);
+        assert_eq!(value["pubkey"], internal["pubkey"]);
+        assert_eq!(value["label"], internal["label"]);
+        assert_eq!(internal_hex, internal["challenges"][0]);
+        assert_eq!(1, internal["bid_responses"][internal_hex.as_str()]);
+        assert_eq!(value, HashOrder::Internal.to_rpc(internal));
+        assert_eq!(value, HashOrder::Rpc.from_rpc(value.clone()));
+    }
+
+    #[test]
+    fn hash_order_from_str_test() {
+        assert_eq!(HashOrder::Rpc, HashOrder::from_str("rpc").unwrap());
+        assert_eq!(HashOrder::Internal, HashOrder::from_str("internal").unwrap());
+        assert!(HashOrder::from_str("reversed").is_err());
+    }
+}
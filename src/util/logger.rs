@@ -0,0 +1,201 @@
+//! # Logger
+//!
+//! Logger that tags log lines with the request being processed in the current
+//! thread and buffers them for storing per request
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bitcoin::hashes::sha256d;
+use env_logger::{Builder, Logger};
+use log::{Log, Metadata, Record};
+
+use crate::interfaces::storage::{Storage, STORAGE_REQUEST_LOGS_LIMIT};
+
+thread_local! {
+    /// Request txid of the logging context of the current thread
+    static REQUEST_CONTEXT: Cell<Option<sha256d::Hash>> = Cell::new(None);
+    /// Log lines of the current thread buffered until flushed to storage
+    static REQUEST_LOGS: RefCell<Vec<(sha256d::Hash, String)>> = RefCell::new(vec![]);
+}
+
+/// Buffer a log line for a request dropping the oldest lines if the buffer
+/// limit has been reached
+fn buffer_request_log(txid: sha256d::Hash, line: String) {
+    REQUEST_LOGS.with(|logs| {
+        let mut logs = logs.borrow_mut();
+        if logs.len() >= STORAGE_REQUEST_LOGS_LIMIT {
+            let _ = logs.remove(0);
+        }
+        logs.push((txid, line));
+    });
+}
+
+/// Flush log lines buffered in the current thread to storage
+pub fn flush_request_logs(storage: &dyn Storage) {
+    let logs = REQUEST_LOGS.with(|logs| logs.replace(vec![]));
+    let mut request_logs: HashMap<sha256d::Hash, Vec<String>> = HashMap::new();
+    for (txid, line) in logs {
+        request_logs.entry(txid).or_insert(vec![]).push(line);
+    }
+    for (txid, lines) in request_logs.iter() {
+        if let Err(e) = storage.save_request_logs(*txid, lines) {
+            warn!("failed storing request logs: {}", e);
+        }
+    }
+}
+
+/// Logging context for a request. All log lines emitted in the current thread
+/// while the context is alive are tagged with the request txid and are flushed
+/// to storage when the context is dropped
+pub struct RequestLogContext<'a> {
+    /// Storage that buffered log lines are flushed to
+    storage: &'a dyn Storage,
+}
+
+impl<'a> RequestLogContext<'a> {
+    /// Set the logging context of the current thread to a request
+    pub fn new(txid: sha256d::Hash, storage: &'a dyn Storage) -> RequestLogContext<'a> {
+        REQUEST_CONTEXT.with(|context| context.set(Some(txid)));
+        RequestLogContext { storage }
+    }
+}
+
+impl<'a> Drop for RequestLogContext<'a> {
+    fn drop(&mut self) {
+        REQUEST_CONTEXT.with(|context| context.set(None));
+        flush_request_logs(self.storage);
+    }
+}
+
+/// Logger wrapping the env logger and prefixing log lines with the txid of the
+/// request in the logging context of the current thread, if any
+pub struct RequestLogger {
+    /// Underlying env logger
+    inner: Logger,
+}
+
+impl Log for RequestLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.inner.matches(record) {
+            return;
+        }
+        match REQUEST_CONTEXT.with(|context| context.get()) {
+            Some(txid) => {
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                buffer_request_log(
+                    txid,
+                    format!(
+                        "{} {} {}: {}",
+                        timestamp,
+                        record.level(),
+                        record.target(),
+                        record.args()
+                    ),
+                );
+                self.inner.log(
+                    &Record::builder()
+                        .args(format_args!("[{}] {}", txid, record.args()))
+                        .metadata(record.metadata().clone())
+                        .module_path(record.module_path())
+                        .file(record.file())
+                        .line(record.line())
+                        .build(),
+                );
+            }
+            None => self.inner.log(record),
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush()
+    }
+}
+
+/// Init the request logger using the env logger filters set by RUST_LOG
+pub fn init() {
+    let inner = Builder::from_default_env().build();
+    log::set_max_level(inner.filter());
+    if let Err(e) = log::set_boxed_logger(Box::new(RequestLogger { inner })) {
+        eprintln!("logger init failure: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use log::{Level, LevelFilter};
+
+    use crate::interfaces::mocks::storage::MockStorage;
+    use crate::util::testing::gen_dummy_hash;
+
+    #[test]
+    fn request_logger_test() {
+        let storage = MockStorage::new();
+        let logger = RequestLogger {
+            inner: Builder::new().filter(None, LevelFilter::Info).build(),
+        };
+        let txid = gen_dummy_hash(1);
+
+        // no context
+        logger.log(
+            &Record::builder()
+                .args(format_args!("no context"))
+                .level(Level::Info)
+                .build(),
+        );
+        flush_request_logs(&storage);
+        assert_eq!(0, storage.request_logs.borrow().len());
+
+        // context set and log lines flushed on drop
+        {
+            let _context = RequestLogContext::new(txid, &storage);
+            logger.log(&Record::builder().args(format_args!("first")).level(Level::Info).build());
+            logger.log(
+                &Record::builder()
+                    .args(format_args!("filtered"))
+                    .level(Level::Debug)
+                    .build(),
+            );
+            logger.log(
+                &Record::builder()
+                    .args(format_args!("second"))
+                    .level(Level::Warn)
+                    .build(),
+            );
+            assert_eq!(0, storage.get_request_logs(txid).unwrap().len());
+        }
+        let logs = storage.get_request_logs(txid).unwrap();
+        assert_eq!(2, logs.len());
+        assert!(logs[0].ends_with("INFO : first"));
+        assert!(logs[1].ends_with("WARN : second"));
+
+        // context cleared
+        logger.log(
+            &Record::builder()
+                .args(format_args!("cleared"))
+                .level(Level::Info)
+                .build(),
+        );
+        flush_request_logs(&storage);
+        assert_eq!(2, storage.get_request_logs(txid).unwrap().len());
+
+        // buffer limit
+        for i in 0..STORAGE_REQUEST_LOGS_LIMIT + 5 {
+            buffer_request_log(txid, format!("{}", i));
+        }
+        flush_request_logs(&storage);
+        let logs = storage.get_request_logs(txid).unwrap();
+        assert_eq!(STORAGE_REQUEST_LOGS_LIMIT, logs.len());
+        assert_eq!(format!("{}", STORAGE_REQUEST_LOGS_LIMIT + 4), logs[logs.len() - 1]);
+    }
+}
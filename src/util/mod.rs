@@ -2,9 +2,18 @@
 //!
 //! Util functionality required by the coordinator library
 
+pub mod addr_params;
 pub mod checks;
+pub mod compression;
 pub mod doc_format;
+pub mod encryption;
 pub mod handler;
+pub mod hash_order;
+pub mod http;
+pub mod logger;
 pub mod ocean;
+pub mod rate_limit;
+pub mod scheduler;
+pub mod schema;
 #[cfg(test)]
 pub mod testing;
@@ -2,29 +2,268 @@
 //!
 //! Ocean node communication implementations
 
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::ptr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, Once, RwLock};
+use std::time::Instant;
+
 use ocean_rpc::{Auth, Client, RpcApi};
+use serde::Serialize;
 
-use crate::error::Result;
+use crate::error::{CError, Error, Result};
 
-/// Extension of ocean_rpc::Client that retries rpc calls
-pub struct OceanClient {
+/// Aggregated stats of the rpc calls of a method
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct RpcMethodStats {
+    /// Number of calls
+    pub calls: u64,
+    /// Number of calls that failed
+    pub errors: u64,
+    /// Number of calls slower than the slow call threshold
+    pub slow: u64,
+    /// Total duration of all calls in ms
+    pub total_ms: u64,
+    /// Max duration of a call in ms
+    pub max_ms: u64,
+}
+
+/// Rpc call stats of all ocean clients by rpc method, along with the duration
+/// above which calls are logged as slow
+pub struct RpcStats {
+    /// Slow call threshold in ms; calls are never slow if 0
+    slow_call_ms: AtomicU64,
+    /// Stats by rpc method
+    methods: Mutex<BTreeMap<String, RpcMethodStats>>,
+}
+
+impl RpcStats {
+    /// Create empty rpc stats with the given slow call threshold
+    pub fn new(slow_call_ms: u64) -> RpcStats {
+        RpcStats {
+            slow_call_ms: AtomicU64::new(slow_call_ms),
+            methods: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Get the slow call threshold in ms
+    pub fn slow_call_ms(&self) -> u64 {
+        self.slow_call_ms.load(Ordering::Relaxed)
+    }
+
+    /// Set the slow call threshold in ms; 0 disables slow call warnings
+    pub fn set_slow_call_ms(&self, slow_call_ms: u64) {
+        self.slow_call_ms.store(slow_call_ms, Ordering::Relaxed)
+    }
+
+    /// Record a call of an rpc method with its duration and outcome. Returns
+    /// whether the call was slow
+    pub fn record(&self, method: &str, duration_ms: u64, success: bool) -> bool {
+        let slow_call_ms = self.slow_call_ms();
+        let slow = slow_call_ms > 0 && duration_ms > slow_call_ms;
+        let mut methods = self.methods.lock().unwrap();
+        let stats = methods.entry(method.to_owned()).or_insert_with(RpcMethodStats::default);
+        stats.calls += 1;
+        stats.total_ms += duration_ms;
+        stats.max_ms = stats.max_ms.max(duration_ms);
+        if !success {
+            stats.errors += 1;
+        }
+        if slow {
+            stats.slow += 1;
+        }
+        slow
+    }
+
+    /// Get the stats of all rpc methods called
+    pub fn methods(&self) -> BTreeMap<String, RpcMethodStats> {
+        self.methods.lock().unwrap().clone()
+    }
+}
+
+/// Default slow call threshold in ms of the rpc stats
+pub const OCEAN_CLIENT_SLOW_CALL_MS: u64 = 2000;
+
+/// Get the rpc stats shared by all ocean clients of the process. The stats are
+/// process wide, as ocean clients are created throughout the coordinator
+/// components, and are initialised on first use
+pub fn rpc_stats() -> &'static RpcStats {
+    static INIT: Once = Once::new();
+    static mut STATS: *const RpcStats = ptr::null();
+    unsafe {
+        INIT.call_once(|| {
+            STATS = Box::into_raw(Box::new(RpcStats::new(OCEAN_CLIENT_SLOW_CALL_MS)));
+        });
+        &*STATS
+    }
+}
+
+/// Generation of the rpc credentials of all ocean clients, bumped on each
+/// credentials reload request
+static CREDENTIALS_GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// Request all ocean clients of the process to reload their rpc credentials
+/// before their next call, e.g. on SIGHUP. Only an atomic counter is bumped,
+/// so that this is safe to call from a signal handler
+pub fn reload_credentials() {
+    let _ = CREDENTIALS_GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Reload the rpc credentials of all ocean clients of the process on SIGHUP
+pub fn reload_credentials_on_sighup() -> Result<()> {
+    // the handler only bumps the credentials generation, which is signal safe
+    let _ = unsafe { signal_hook::register(signal_hook::SIGHUP, reload_credentials) }
+        .map_err(|e| Error::from(CError::Generic(format!("failed registering SIGHUP handler: {}", e))))?;
+    Ok(())
+}
+
+/// Source of the rpc credentials of an ocean client. Credentials are read from
+/// a `user:pass` credentials file if set, such as the rpc cookie file of the
+/// node, or else from the user and pass environment variables if set, falling
+/// back to the configured user and pass. Sources are re-read on each reload so
+/// that rotated credentials are applied without a restart
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcCredentials {
+    /// Configured rpc user
+    pub user: Option<String>,
+    /// Configured rpc pass
+    pub pass: Option<String>,
+    /// Path of the credentials file, if any
+    pub file: Option<String>,
+    /// Prefix of the `_USER` and `_PASS` environment variables, if any
+    pub env_prefix: Option<String>,
+}
+
+impl RpcCredentials {
+    /// Read the current rpc user and pass, or None if no credentials are set
+    pub fn resolve(&self) -> Result<Option<(String, String)>> {
+        if let Some(file) = &self.file {
+            let contents = fs::read_to_string(file).map_err(|e| {
+                Error::from(CError::Generic(format!(
+                    "failed reading rpc credentials file {}: {}",
+                    file, e
+                )))
+            })?;
+            let mut parts = contents.trim().splitn(2, ':');
+            return match (parts.next(), parts.next()) {
+                (Some(user), Some(pass)) if !user.is_empty() => Ok(Some((user.to_owned(), pass.to_owned()))),
+                _ => Err(Error::from(CError::Generic(format!(
+                    "invalid rpc credentials file {}, expected user:pass",
+                    file
+                )))),
+            };
+        }
+        let mut user = self.user.clone();
+        let mut pass = self.pass.clone();
+        if let Some(prefix) = &self.env_prefix {
+            if let Ok(v) = env::var(format!("{}_USER", prefix)) {
+                user = Some(v);
+            }
+            if let Ok(v) = env::var(format!("{}_PASS", prefix)) {
+                pass = Some(v);
+            }
+        }
+        Ok(match (user, pass) {
+            (Some(user), Some(pass)) => Some((user, pass)),
+            _ => None,
+        })
+    }
+}
+
+/// Connect an rpc client to the url with the given credentials
+fn connect(url: &str, credentials: &Option<(String, String)>) -> Result<Client> {
+    let auth = match credentials {
+        Some((user, pass)) => Auth::UserPass(user.clone(), pass.clone()),
+        None => Auth::None,
+    };
+    Ok(Client::new(format!("http://{}", url), auth)?)
+}
+
+/// Rpc connection of an ocean client
+struct Connection {
     /// Ocean rpc client instance
-    pub client: Client,
+    client: Client,
+    /// Credentials the client was connected with
+    credentials: Option<(String, String)>,
+    /// Credentials generation the credentials were last read at
+    generation: usize,
+}
+
+/// Extension of ocean_rpc::Client that retries rpc calls and records the
+/// duration and outcome of each call in the process wide rpc stats. The rpc
+/// connection is re-established whenever the credentials change, checked on
+/// each reload request and whenever a call fails
+pub struct OceanClient {
+    /// Rpc url
+    url: String,
+    /// Source of the rpc credentials
+    credentials: RpcCredentials,
+    /// Current rpc connection
+    connection: RwLock<Connection>,
 }
 
 impl OceanClient {
     /// Create an OceanClient with underlying rpc client connectivity
     pub fn new(url: String, user: Option<String>, pass: Option<String>) -> Result<Self> {
-        let mut auth = Auth::None;
-        if let Some(ref _user) = user {
-            if let Some(ref _pass) = pass {
-                auth = Auth::UserPass(_user.clone(), _pass.clone());
-            }
-        }
+        OceanClient::with_credentials(
+            url,
+            RpcCredentials {
+                user,
+                pass,
+                file: None,
+                env_prefix: None,
+            },
+        )
+    }
+
+    /// Create an OceanClient with underlying rpc client connectivity using the
+    /// credentials read from a credentials source
+    pub fn with_credentials(url: String, credentials: RpcCredentials) -> Result<Self> {
+        let generation = CREDENTIALS_GENERATION.load(Ordering::SeqCst);
+        let resolved = credentials.resolve()?;
+        let client = connect(&url, &resolved)?;
         Ok(OceanClient {
-            client: Client::new(format!("http://{}", url), auth)?,
+            url,
+            credentials,
+            connection: RwLock::new(Connection {
+                client,
+                credentials: resolved,
+                generation,
+            }),
         })
     }
+
+    /// Re-read the credentials and re-establish the rpc connection if they
+    /// have changed, returning whether the connection was re-established.
+    /// Failures are logged and the current connection is kept
+    fn refresh(&self) -> bool {
+        let mut connection = self.connection.write().unwrap();
+        connection.generation = CREDENTIALS_GENERATION.load(Ordering::SeqCst);
+        let resolved = match self.credentials.resolve() {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                warn!("failed reloading rpc credentials of {}: {}", self.url, e);
+                return false;
+            }
+        };
+        if resolved == connection.credentials {
+            return false;
+        }
+        match connect(&self.url, &resolved) {
+            Ok(client) => {
+                info!("rpc credentials of {} changed, reconnecting", self.url);
+                connection.client = client;
+                connection.credentials = resolved;
+                true
+            }
+            Err(e) => {
+                warn!("failed reconnecting to {}: {}", self.url, e);
+                false
+            }
+        }
+    }
 }
 
 /// Interval between retry attempts of rpc client
@@ -33,14 +272,15 @@ pub const OCEAN_CLIENT_RETRY_INTERVAL: u64 = 10;
 /// Number of retry attemps for rpc client calls
 pub const OCEAN_CLIENT_RETRY_ATTEMPTS: u8 = 5;
 
-impl RpcApi for OceanClient {
-    fn call<T: for<'b> serde::de::Deserialize<'b>>(
+impl OceanClient {
+    /// Make an rpc call, retrying calls that fail with a json rpc error
+    fn call_with_retries<T: for<'b> serde::de::Deserialize<'b>>(
         &self,
         cmd: &str,
         args: &[serde_json::Value],
     ) -> ocean_rpc::Result<T> {
         for _ in 0..OCEAN_CLIENT_RETRY_ATTEMPTS {
-            match self.client.call(cmd, args) {
+            match self.connection.read().unwrap().client.call(cmd, args) {
                 Ok(ret) => return Ok(ret),
                 Err(ocean_rpc::Error::JsonRpc(e)) => {
                     warn!("rpc error: {}, retrying...", e);
@@ -50,6 +290,123 @@ impl RpcApi for OceanClient {
                 Err(e) => return Err(e),
             }
         }
-        self.client.call(cmd, args)
+        self.connection.read().unwrap().client.call(cmd, args)
+    }
+}
+
+impl RpcApi for OceanClient {
+    /// Make an rpc call, logging the call duration and outcome at debug level,
+    /// or as a warning if slower than the slow call threshold of the rpc stats.
+    /// Retries count towards the duration of the call. Call arguments are
+    /// never logged as they may carry keys. Credentials are reloaded before
+    /// the call if a reload was requested, and failed calls are retried once
+    /// if the credentials have changed, e.g. after a rotation
+    fn call<T: for<'b> serde::de::Deserialize<'b>>(
+        &self,
+        cmd: &str,
+        args: &[serde_json::Value],
+    ) -> ocean_rpc::Result<T> {
+        let start = Instant::now();
+        let generation = self.connection.read().unwrap().generation;
+        if generation != CREDENTIALS_GENERATION.load(Ordering::SeqCst) {
+            let _ = self.refresh();
+        }
+        let mut res = self.call_with_retries(cmd, args);
+        if res.is_err() && self.refresh() {
+            res = self.call_with_retries(cmd, args);
+        }
+        let duration_ms = start.elapsed().as_millis() as u64;
+        let outcome = match &res {
+            Ok(_) => "ok".to_owned(),
+            Err(e) => format!("error: {}", e),
+        };
+        if rpc_stats().record(cmd, duration_ms, res.is_ok()) {
+            warn!("slow rpc {} took {}ms ({})", cmd, duration_ms, outcome);
+        } else {
+            debug!("rpc {} took {}ms ({})", cmd, duration_ms, outcome);
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rpc_credentials_test() {
+        let mut credentials = RpcCredentials {
+            user: Some("user1".to_owned()),
+            pass: Some("password1".to_owned()),
+            file: None,
+            env_prefix: Some("CO_TEST_RPC_CREDENTIALS".to_owned()),
+        };
+        assert_eq!(
+            Some(("user1".to_owned(), "password1".to_owned())),
+            credentials.resolve().unwrap()
+        );
+
+        // env variables override the configured credentials
+        env::set_var("CO_TEST_RPC_CREDENTIALS_PASS", "password2");
+        assert_eq!(
+            Some(("user1".to_owned(), "password2".to_owned())),
+            credentials.resolve().unwrap()
+        );
+
+        // credentials file overrides both, and is re-read on each resolve
+        let path = env::temp_dir().join("coordinator_rpc_credentials_test");
+        credentials.file = Some(path.to_str().unwrap().to_owned());
+        fs::write(&path, "user3:pass:word3\n").unwrap();
+        assert_eq!(
+            Some(("user3".to_owned(), "pass:word3".to_owned())),
+            credentials.resolve().unwrap()
+        );
+        fs::write(&path, "user4:password4").unwrap();
+        assert_eq!(
+            Some(("user4".to_owned(), "password4".to_owned())),
+            credentials.resolve().unwrap()
+        );
+
+        // invalid and missing files
+        fs::write(&path, "password5").unwrap();
+        assert!(credentials.resolve().is_err());
+        fs::remove_file(&path).unwrap();
+        assert!(credentials.resolve().is_err());
+
+        // no credentials without user or pass
+        credentials.file = None;
+        credentials.env_prefix = None;
+        credentials.pass = None;
+        assert_eq!(None, credentials.resolve().unwrap());
+    }
+
+    #[test]
+    fn rpc_stats_test() {
+        let stats = RpcStats::new(100);
+        assert!(!stats.record("getblockcount", 20, true));
+        assert!(stats.record("getblockcount", 150, true));
+        assert!(!stats.record("getblockcount", 30, false));
+        assert!(!stats.record("sendrawtransaction", 100, true));
+        let methods = stats.methods();
+        assert_eq!(
+            vec!["getblockcount", "sendrawtransaction"],
+            methods.keys().map(|method| method.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            RpcMethodStats {
+                calls: 3,
+                errors: 1,
+                slow: 1,
+                total_ms: 200,
+                max_ms: 150,
+            },
+            methods["getblockcount"]
+        );
+
+        // slow calls disabled
+        stats.set_slow_call_ms(0);
+        assert!(!stats.record("sendrawtransaction", 5000, true));
+        assert_eq!(0, stats.methods()["sendrawtransaction"].slow);
+        assert_eq!(5000, stats.methods()["sendrawtransaction"].max_ms);
     }
 }
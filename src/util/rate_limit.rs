@@ -0,0 +1,171 @@
+//! # Rate Limit
+//!
+//! Fixed window rate limits of api calls by caller, such as the api key or the
+//! client ip of the call, along with the quota left to each caller in the
+//! current window
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default max number of callers with a window tracked by a rate limiter
+pub const RATE_LIMIT_MAX_CALLERS: usize = 100_000;
+
+/// Quota of a caller in the current rate limit window
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quota {
+    /// Max number of calls per window
+    pub limit: u64,
+    /// Number of calls left in the current window
+    pub remaining: u64,
+    /// Seconds until the current window ends
+    pub reset: u64,
+}
+
+/// Windows of the callers of a rate limiter
+struct Windows {
+    /// Start and number of calls of the current window of each caller
+    callers: HashMap<String, (Instant, u64)>,
+    /// Time ended windows were last dropped
+    pruned: Instant,
+}
+
+/// Rate limiter counting the calls of each caller in fixed windows
+pub struct RateLimiter {
+    /// Window length
+    window: Duration,
+    /// Caller windows
+    windows: Mutex<Windows>,
+    /// Max number of callers with a window, evicting the callers with the
+    /// earliest windows first once reached
+    max_callers: usize,
+    /// Number of calls rejected
+    limited: AtomicU64,
+}
+
+impl RateLimiter {
+    /// Return new RateLimiter instance with the given window length
+    pub fn new(window: Duration) -> RateLimiter {
+        RateLimiter::with_max_callers(window, RATE_LIMIT_MAX_CALLERS)
+    }
+
+    /// Return new RateLimiter instance with the given window length, tracking
+    /// at most the given number of callers
+    pub fn with_max_callers(window: Duration, max_callers: usize) -> RateLimiter {
+        RateLimiter {
+            window,
+            windows: Mutex::new(Windows {
+                callers: HashMap::new(),
+                pruned: Instant::now(),
+            }),
+            max_callers,
+            limited: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a call of a caller against a limit of calls per window, returning
+    /// the quota left to the caller or, if the limit is reached, the quota of
+    /// the rejected call. Windows that have ended are dropped once per window,
+    /// or when a new caller is seen with the max number of callers reached
+    pub fn check(&self, caller: &str, limit: u64) -> Result<Quota, Quota> {
+        let now = Instant::now();
+        let window = self.window;
+        let mut windows = self.windows.lock().unwrap();
+        let full = windows.callers.len() >= self.max_callers && !windows.callers.contains_key(caller);
+        if full || now.duration_since(windows.pruned) >= window {
+            windows
+                .callers
+                .retain(|_, (start, _)| now.duration_since(*start) < window);
+            windows.pruned = now;
+        }
+        if windows.callers.len() >= self.max_callers && !windows.callers.contains_key(caller) {
+            let earliest = windows
+                .callers
+                .iter()
+                .min_by_key(|(_, (start, _))| *start)
+                .map(|(earliest, _)| earliest.clone());
+            if let Some(earliest) = earliest {
+                let _ = windows.callers.remove(&earliest);
+            }
+        }
+        let (start, calls) = windows.callers.entry(caller.to_owned()).or_insert((now, 0));
+        if now.duration_since(*start) >= window {
+            *start = now;
+            *calls = 0;
+        }
+        // seconds until the window ends, rounded up
+        let left = window - now.duration_since(*start);
+        let reset = left.as_secs() + if left.subsec_nanos() > 0 { 1 } else { 0 };
+        if *calls >= limit {
+            let _ = self.limited.fetch_add(1, Ordering::SeqCst);
+            return Err(Quota {
+                limit,
+                remaining: 0,
+                reset,
+            });
+        }
+        *calls += 1;
+        Ok(Quota {
+            limit,
+            remaining: limit - *calls,
+            reset,
+        })
+    }
+
+    /// Get the number of calls rejected
+    pub fn limited(&self) -> u64 {
+        self.limited.load(Ordering::SeqCst)
+    }
+
+    /// Get the number of callers with a window, including ended windows not
+    /// yet dropped
+    pub fn callers(&self) -> u64 {
+        self.windows.lock().unwrap().callers.len() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    #[test]
+    fn check_test() {
+        let limiter = RateLimiter::new(Duration::from_millis(100));
+        let quota = limiter.check("key", 2).unwrap();
+        assert_eq!(2, quota.limit);
+        assert_eq!(1, quota.remaining);
+        assert_eq!(1, quota.reset);
+        assert_eq!(0, limiter.check("key", 2).unwrap().remaining);
+        assert_eq!(0, limiter.check("key", 2).unwrap_err().remaining);
+        assert_eq!(1, limiter.limited());
+
+        // callers are limited separately
+        assert_eq!(1, limiter.check("other", 2).unwrap().remaining);
+        assert_eq!(2, limiter.callers());
+
+        // limits reset once the window ends, dropping ended windows
+        thread::sleep(Duration::from_millis(110));
+        assert_eq!(1, limiter.check("key", 2).unwrap().remaining);
+        assert_eq!(1, limiter.callers());
+        assert_eq!(1, limiter.limited());
+    }
+
+    #[test]
+    fn max_callers_test() {
+        let limiter = RateLimiter::with_max_callers(Duration::from_secs(60), 2);
+        assert_eq!(0, limiter.check("a", 1).unwrap().remaining);
+        thread::sleep(Duration::from_millis(5));
+        assert!(limiter.check("b", 1).is_ok());
+        assert!(limiter.check("a", 1).is_err());
+
+        // callers with the earliest windows evicted for new callers
+        assert!(limiter.check("c", 1).is_ok());
+        assert_eq!(2, limiter.callers());
+        assert!(limiter.check("b", 1).is_err());
+        assert!(limiter.check("a", 1).is_ok());
+        assert_eq!(2, limiter.callers());
+    }
+}
@@ -0,0 +1,506 @@
+//! # Scheduler
+//!
+//! Scheduler running jobs at fixed intervals or at the times matching a
+//! cron-like expression, used by the coordinator components instead of their
+//! own sleep loops. Jobs run on the thread running the scheduler, one at a
+//! time, and can finish themselves or be cancelled via their handle. Jobs are
+//! timed by the clock of the scheduler, so that tests can fast-forward through
+//! schedules with a mock clock
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use futures::sync::oneshot;
+
+use crate::clock::{Clock, SystemClock};
+use crate::error::{CError, Error, Result};
+
+/// Max interval between checks for due jobs and shutdown signals
+pub const SCHEDULER_TICK_MS: u64 = 100;
+
+/// Number of days searched for the next time matching a cron schedule
+const CRON_SEARCH_DAYS: u64 = 4 * 366;
+
+/// Cron-like schedule of the minutes, hours, days of month, months and days of
+/// week that a job runs at, in UTC. Each field is parsed from the usual cron
+/// syntax of `*`, values, ranges, steps and comma separated lists of these,
+/// e.g. `*/15 * * * *` or `30 2 * * 1-5`. As with cron, if both the days of
+/// month and the days of week are restricted a day matching either runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    /// Minutes bitmask, 0-59
+    minutes: u64,
+    /// Hours bitmask, 0-23
+    hours: u64,
+    /// Days of month bitmask, 1-31
+    days: u64,
+    /// Months bitmask, 1-12
+    months: u64,
+    /// Days of week bitmask, 0-6 starting on Sunday
+    weekdays: u64,
+    /// Flag set if the days of month field is restricted
+    days_restricted: bool,
+    /// Flag set if the days of week field is restricted
+    weekdays_restricted: bool,
+}
+
+/// Parse a cron field into a bitmask of the values within min and max
+fn parse_cron_field(field: &str, min: u64, max: u64) -> Result<u64> {
+    let invalid = || Error::from(CError::Generic(format!("invalid cron field: {}", field)));
+    let mut mask = 0;
+    for item in field.split(',') {
+        let (range, step) = match item.find('/') {
+            Some(i) => (&item[..i], item[i + 1..].parse::<u64>().map_err(|_| invalid())?),
+            None => (item, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else {
+            match range.find('-') {
+                Some(i) => (
+                    range[..i].parse::<u64>().map_err(|_| invalid())?,
+                    range[i + 1..].parse::<u64>().map_err(|_| invalid())?,
+                ),
+                None => {
+                    let value = range.parse::<u64>().map_err(|_| invalid())?;
+                    (value, value)
+                }
+            }
+        };
+        if step == 0 || start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        let mut value = start;
+        while value <= end {
+            mask |= 1 << value;
+            value += step;
+        }
+    }
+    Ok(mask)
+}
+
+/// Get the month (1-12) and day of month (1-31) of days since the unix epoch
+fn month_day(days: u64) -> (u64, u64) {
+    let z = days + 719468;
+    let doe = z % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (month, day)
+}
+
+impl CronSchedule {
+    /// Parse a cron schedule from an expression of five space separated
+    /// fields for minutes, hours, days of month, months and days of week
+    pub fn parse(expr: &str) -> Result<CronSchedule> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(Error::from(CError::Generic(format!(
+                "invalid cron expression: {}",
+                expr
+            ))));
+        }
+        let mut weekdays = parse_cron_field(fields[4], 0, 7)?;
+        // both 0 and 7 are Sunday
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(CronSchedule {
+            minutes: parse_cron_field(fields[0], 0, 59)?,
+            hours: parse_cron_field(fields[1], 0, 23)?,
+            days: parse_cron_field(fields[2], 1, 31)?,
+            months: parse_cron_field(fields[3], 1, 12)?,
+            weekdays,
+            days_restricted: fields[2] != "*",
+            weekdays_restricted: fields[4] != "*",
+        })
+    }
+
+    /// Check whether the schedule runs on the day of days since the unix epoch
+    fn matches_day(&self, days: u64) -> bool {
+        let (month, day) = month_day(days);
+        if self.months & (1 << month) == 0 {
+            return false;
+        }
+        // 1970-01-01 was a Thursday
+        let day_match = self.days & (1 << day) != 0;
+        let weekday_match = self.weekdays & (1 << ((days + 4) % 7)) != 0;
+        if self.days_restricted && self.weekdays_restricted {
+            day_match || weekday_match
+        } else {
+            day_match && weekday_match
+        }
+    }
+
+    /// Get the first unix timestamp in seconds after the timestamp matching
+    /// the schedule, if any within the next few years
+    pub fn next_after(&self, timestamp: u64) -> Option<u64> {
+        let mut minute = timestamp / 60 + 1;
+        let end = minute + CRON_SEARCH_DAYS * 1440;
+        while minute < end {
+            if !self.matches_day(minute / 1440) {
+                minute = (minute / 1440 + 1) * 1440;
+            } else if self.hours & (1 << (minute / 60 % 24)) == 0 {
+                minute = (minute / 60 + 1) * 60;
+            } else if self.minutes & (1 << (minute % 60)) == 0 {
+                minute += 1;
+            } else {
+                return Some(minute * 60);
+            }
+        }
+        None
+    }
+}
+
+/// Schedule of a job
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schedule {
+    /// Run immediately and then every interval
+    Interval(Duration),
+    /// Run every interval, starting one interval from now
+    Delayed(Duration),
+    /// Run at the times matching a cron schedule
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    /// Get the next run of the schedule after a run started at an instant, or
+    /// the first run if no run has started yet. Intervals are measured from the
+    /// start of the previous run, so runs taking longer than the interval are
+    /// followed immediately by the next run
+    fn next_run(&self, started: Option<Instant>, clock: &dyn Clock) -> Option<Instant> {
+        let now = clock.now();
+        match self {
+            Schedule::Interval(interval) => Some(started.map_or(now, |started| started + *interval)),
+            Schedule::Delayed(interval) => Some(started.unwrap_or(now) + *interval),
+            Schedule::Cron(cron) => {
+                let unix_now = clock.unix_time();
+                cron.next_after(unix_now.as_secs())
+                    .map(|next| now + (Duration::from_secs(next) - unix_now))
+            }
+        }
+    }
+}
+
+/// Status returned by a job run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobStatus {
+    /// Keep running the job on its schedule
+    Continue,
+    /// Job finished and is removed from the scheduler
+    Done,
+}
+
+/// Handle to cancel a scheduled job
+#[derive(Debug, Clone)]
+pub struct JobHandle {
+    /// Cancellation flag shared with the scheduler
+    cancelled: Rc<Cell<bool>>,
+}
+
+impl JobHandle {
+    /// Cancel the job, which is removed from the scheduler before its next run
+    pub fn cancel(&self) {
+        self.cancelled.set(true)
+    }
+
+    /// Check whether the job has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.get()
+    }
+}
+
+/// Scheduled job
+struct Job<'a> {
+    /// Job name used in logs
+    name: String,
+    /// Job schedule
+    schedule: Schedule,
+    /// Next run of the job; None if the schedule has no more runs
+    next: Option<Instant>,
+    /// Job task
+    task: Box<dyn FnMut() -> Result<JobStatus> + 'a>,
+    /// Cancellation flag
+    cancelled: Rc<Cell<bool>>,
+}
+
+/// Scheduler of jobs run on the thread running the scheduler. Job failures
+/// are returned to the caller, leaving any retries to the job itself
+pub struct Scheduler<'a> {
+    /// Scheduled jobs
+    jobs: Vec<Job<'a>>,
+    /// Clock that jobs are timed by
+    clock: &'a dyn Clock,
+}
+
+impl<'a> Scheduler<'a> {
+    /// Create a scheduler without any jobs, timed by the system clock
+    pub fn new() -> Scheduler<'a> {
+        Scheduler::with_clock(&SystemClock)
+    }
+
+    /// Create a scheduler without any jobs, timed by the given clock
+    pub fn with_clock(clock: &'a dyn Clock) -> Scheduler<'a> {
+        Scheduler { jobs: vec![], clock }
+    }
+
+    /// Add a job running the task on the schedule, returning its handle
+    pub fn add<F>(&mut self, name: &str, schedule: Schedule, task: F) -> JobHandle
+    where
+        F: FnMut() -> Result<JobStatus> + 'a,
+    {
+        let cancelled = Rc::new(Cell::new(false));
+        self.jobs.push(Job {
+            name: name.to_owned(),
+            next: schedule.next_run(None, self.clock),
+            schedule,
+            task: Box::new(task),
+            cancelled: cancelled.clone(),
+        });
+        JobHandle { cancelled }
+    }
+
+    /// Check whether there are any jobs left to run
+    pub fn is_empty(&self) -> bool {
+        self.jobs.len() == 0
+    }
+
+    /// Remove finished and cancelled jobs and jobs without any more runs
+    fn remove_finished(&mut self) {
+        self.jobs.retain(|job| {
+            if job.cancelled.get() || job.next.is_none() {
+                debug!("Removing job {}", job.name);
+                return false;
+            }
+            true
+        });
+    }
+
+    /// Run the jobs that are due, in the order they were added. Returns the
+    /// error of the first failed job
+    pub fn run_pending(&mut self) -> Result<()> {
+        self.remove_finished();
+        for job in self.jobs.iter_mut() {
+            if job.cancelled.get() || job.next.map_or(true, |next| next > self.clock.now()) {
+                continue;
+            }
+            let started = self.clock.now();
+            match (job.task)()? {
+                JobStatus::Continue => job.next = job.schedule.next_run(Some(started), self.clock),
+                JobStatus::Done => job.next = None,
+            }
+        }
+        self.remove_finished();
+        Ok(())
+    }
+
+    /// Get the duration until the next job is due; None if there are no jobs
+    pub fn next_due(&self) -> Option<Duration> {
+        let now = self.clock.now();
+        self.jobs.iter().filter_map(|job| job.next).min().map(|next| {
+            if next > now {
+                next - now
+            } else {
+                Duration::from_secs(0)
+            }
+        })
+    }
+
+    /// Run jobs as they become due until all jobs are finished or cancelled or
+    /// a shutdown signal is received. Returns the error of the first failed job
+    pub fn run(&mut self, mut kill_recv: Option<&mut oneshot::Receiver<()>>) -> Result<()> {
+        loop {
+            self.run_pending()?;
+            let due = match self.next_due() {
+                Some(due) => due,
+                None => return Ok(()),
+            };
+            match kill_recv {
+                Some(ref mut kill_recv) => {
+                    self.clock.sleep(due.min(Duration::from_millis(SCHEDULER_TICK_MS)));
+                    if kill_recv
+                        .try_recv()
+                        .map_err(|_| Error::from(CError::ReceiverDisconnected))?
+                        .is_some()
+                    {
+                        info!("Shutting down...");
+                        return Ok(());
+                    }
+                }
+                None => self.clock.sleep(due),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    use crate::interfaces::mocks::clock::MockClock;
+    use crate::util::testing::setup_logger;
+
+    #[test]
+    fn cron_schedule_test() {
+        setup_logger();
+        // 2020-09-13 12:26:40 UTC, a Sunday
+        let timestamp = 1600000000;
+
+        let cron = CronSchedule::parse("* * * * *").unwrap();
+        assert_eq!(Some(1600000020), cron.next_after(timestamp));
+
+        let cron = CronSchedule::parse("*/15 * * * *").unwrap();
+        assert_eq!(Some(1600000800), cron.next_after(timestamp)); // 12:40
+
+        let cron = CronSchedule::parse("30 2 * * *").unwrap();
+        assert_eq!(Some(1600050600), cron.next_after(timestamp)); // 14th 02:30
+
+        // weekdays only, next Monday
+        let cron = CronSchedule::parse("0 0 * * 1-5").unwrap();
+        assert_eq!(Some(1600041600), cron.next_after(timestamp));
+
+        // first of the month or Sundays
+        let cron = CronSchedule::parse("0 13 1 * 0").unwrap();
+        assert_eq!(Some(1600002000), cron.next_after(timestamp)); // 13th 13:00
+        let cron = CronSchedule::parse("0 13 1 * 7").unwrap();
+        assert_eq!(Some(1600002000), cron.next_after(timestamp));
+        let cron = CronSchedule::parse("0 0 1 * 3").unwrap();
+        assert_eq!(Some(1600214400), cron.next_after(timestamp)); // 16th Wednesday
+
+        // yearly on the 29th of February
+        let cron = CronSchedule::parse("0 0 29 2 *").unwrap();
+        assert_eq!(Some(1709164800), cron.next_after(timestamp)); // 2024-02-29
+
+        // invalid expressions
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("* * 0 * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("5-1 * * * *").is_err());
+        assert!(CronSchedule::parse("a * * * *").is_err());
+
+        // no matching day
+        let cron = CronSchedule::parse("0 0 31 2 *").unwrap();
+        assert_eq!(None, cron.next_after(timestamp));
+    }
+
+    #[test]
+    fn scheduler_test() {
+        setup_logger();
+        let runs = RefCell::new(vec![]);
+        let clock = MockClock::new();
+        let mut scheduler = Scheduler::with_clock(&clock);
+        assert!(scheduler.is_empty());
+        assert_eq!(None, scheduler.next_due());
+
+        // interval job running three times, delayed job and cancelled job
+        let mut count = 0;
+        let _ = scheduler.add("interval", Schedule::Interval(Duration::from_millis(10)), || {
+            count += 1;
+            runs.borrow_mut().push("interval");
+            Ok(if count == 3 {
+                JobStatus::Done
+            } else {
+                JobStatus::Continue
+            })
+        });
+        let delayed = scheduler.add("delayed", Schedule::Delayed(Duration::from_millis(15)), || {
+            runs.borrow_mut().push("delayed");
+            Ok(JobStatus::Continue)
+        });
+        let cancelled = scheduler.add("cancelled", Schedule::Interval(Duration::from_millis(10)), || {
+            runs.borrow_mut().push("cancelled");
+            Ok(JobStatus::Continue)
+        });
+        cancelled.cancel();
+        assert!(cancelled.is_cancelled());
+
+        // only the interval job is due immediately
+        scheduler.run_pending().unwrap();
+        assert_eq!(vec!["interval"], *runs.borrow());
+        assert_eq!(Some(Duration::from_millis(10)), scheduler.next_due());
+
+        // interval job finishes after its third run
+        clock.advance(Duration::from_millis(20));
+        scheduler.run_pending().unwrap();
+        assert_eq!(vec!["interval", "interval", "delayed"], *runs.borrow());
+        clock.advance(Duration::from_millis(10));
+        scheduler.run_pending().unwrap();
+        assert_eq!(3, runs.borrow().iter().filter(|run| **run == "interval").count());
+        assert!(runs.borrow().contains(&"delayed"));
+        assert!(!runs.borrow().contains(&"cancelled"));
+
+        // run until a job is cancelled by another job
+        let mut scheduler = Scheduler::new();
+        let forever = scheduler.add("forever", Schedule::Interval(Duration::from_millis(1)), || {
+            Ok(JobStatus::Continue)
+        });
+        let mut count = 0;
+        let _ = scheduler.add("cancelling", Schedule::Interval(Duration::from_millis(1)), || {
+            count += 1;
+            if count < 5 {
+                return Ok(JobStatus::Continue);
+            }
+            forever.cancel();
+            Ok(JobStatus::Done)
+        });
+        scheduler.run(None).unwrap();
+        assert!(scheduler.is_empty());
+        assert!(!delayed.is_cancelled());
+
+        // cron job due at the next minute of the clock unix time
+        let mut scheduler = Scheduler::with_clock(&clock);
+        let _ = scheduler.add(
+            "cron",
+            Schedule::Cron(CronSchedule::parse("* * * * *").unwrap()),
+            || Ok(JobStatus::Done),
+        );
+        let unix_time = clock.unix_time().as_secs();
+        assert_eq!(Some(Duration::from_secs(60 - unix_time % 60)), scheduler.next_due());
+        scheduler.run(None).unwrap();
+        assert_eq!(0, clock.unix_time().as_secs() % 60);
+    }
+
+    #[test]
+    fn scheduler_run_test() {
+        setup_logger();
+        // run until all jobs finish
+        let mut count = 0;
+        {
+            let mut scheduler = Scheduler::new();
+            let _ = scheduler.add("count", Schedule::Interval(Duration::from_millis(1)), || {
+                count += 1;
+                Ok(if count == 5 {
+                    JobStatus::Done
+                } else {
+                    JobStatus::Continue
+                })
+            });
+            scheduler.run(None).unwrap();
+            assert!(scheduler.is_empty());
+        }
+        assert_eq!(5, count);
+
+        // job errors stop the scheduler
+        let mut scheduler = Scheduler::new();
+        let _ = scheduler.add("fail", Schedule::Interval(Duration::from_millis(1)), || {
+            Err(Error::from(CError::Generic("job failed".to_owned())))
+        });
+        assert_eq!("job failed", scheduler.run(None).unwrap_err().to_string());
+
+        // shutdown signal stops the scheduler
+        let (tx, mut rx) = oneshot::channel();
+        tx.send(()).unwrap();
+        let mut scheduler = Scheduler::new();
+        let _ = scheduler.add("forever", Schedule::Delayed(Duration::from_secs(60)), || {
+            Ok(JobStatus::Continue)
+        });
+        scheduler.run(Some(&mut rx)).unwrap();
+        assert!(!scheduler.is_empty());
+    }
+}
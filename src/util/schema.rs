@@ -0,0 +1,122 @@
+//! # Schema
+//!
+//! Json schema generation for api types. Schemas are inferred from the serde
+//! serialization of sample values so that they always reflect the types
+//! actually returned by the api
+
+use bitcoin::hashes::{hex::FromHex, sha256d};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Check whether all keys of an object are hashes, in which case the object is
+/// a serialized map keyed by hash rather than a struct
+fn is_hash_map(obj: &Map<String, Value>) -> bool {
+    obj.len() > 0 && obj.keys().all(|key| sha256d::Hash::from_hex(key).is_ok())
+}
+
+/// Infer the json schema of a json value. Objects are described by their
+/// properties, arrays by the schema of their first item and maps keyed by hash
+/// by the schema of their first value
+pub fn infer_schema(value: &Value) -> Value {
+    let mut schema = Map::new();
+    match value {
+        Value::Null => {
+            let _ = schema.insert("type".to_owned(), "null".into());
+        }
+        Value::Bool(_) => {
+            let _ = schema.insert("type".to_owned(), "boolean".into());
+        }
+        Value::Number(number) => {
+            let number_type = if number.is_f64() { "number" } else { "integer" };
+            let _ = schema.insert("type".to_owned(), number_type.into());
+        }
+        Value::String(_) => {
+            let _ = schema.insert("type".to_owned(), "string".into());
+        }
+        Value::Array(items) => {
+            let _ = schema.insert("type".to_owned(), "array".into());
+            let _ = schema.insert(
+                "items".to_owned(),
+                items.first().map_or(Value::Object(Map::new()), infer_schema),
+            );
+        }
+        Value::Object(obj) if is_hash_map(obj) => {
+            let _ = schema.insert("type".to_owned(), "object".into());
+            let _ = schema.insert(
+                "additionalProperties".to_owned(),
+                obj.values().next().map_or(Value::Object(Map::new()), infer_schema),
+            );
+        }
+        Value::Object(obj) => {
+            let _ = schema.insert("type".to_owned(), "object".into());
+            let _ = schema.insert(
+                "properties".to_owned(),
+                Value::Object(obj.iter().map(|(key, val)| (key.clone(), infer_schema(val))).collect()),
+            );
+            let _ = schema.insert(
+                "required".to_owned(),
+                Value::Array(obj.keys().map(|key| Value::String(key.clone())).collect()),
+            );
+        }
+    }
+    Value::Object(schema)
+}
+
+/// Generate the json schema of a serializable type from a sample value. All
+/// optional fields of the sample should be set for their schema to be included
+pub fn schema_of<T: Serialize>(sample: &T) -> Value {
+    infer_schema(&serde_json::to_value(sample).unwrap_or(Value::Null))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use crate::util::testing::gen_dummy_hash;
+
+    #[derive(Serialize)]
+    struct Sample {
+        txid: sha256d::Hash,
+        height: u32,
+        rate: f64,
+        flag: bool,
+        opt: Option<u32>,
+        items: Vec<u32>,
+        map: HashMap<sha256d::Hash, u32>,
+    }
+
+    #[test]
+    fn schema_of_test() {
+        let mut map = HashMap::new();
+        let _ = map.insert(gen_dummy_hash(1), 1);
+        let schema = schema_of(&Sample {
+            txid: gen_dummy_hash(0),
+            height: 1,
+            rate: 0.5,
+            flag: true,
+            opt: None,
+            items: vec![],
+            map,
+        });
+
+        assert_eq!("object", schema["type"]);
+        assert_eq!(7, schema["required"].as_array().unwrap().len());
+        let properties = &schema["properties"];
+        assert_eq!("string", properties["txid"]["type"]);
+        assert_eq!("integer", properties["height"]["type"]);
+        assert_eq!("number", properties["rate"]["type"]);
+        assert_eq!("boolean", properties["flag"]["type"]);
+        assert_eq!("null", properties["opt"]["type"]);
+        assert_eq!("array", properties["items"]["type"]);
+        assert_eq!(Value::Object(Map::new()), properties["items"]["items"]);
+        assert_eq!("object", properties["map"]["type"]);
+        assert_eq!("integer", properties["map"]["additionalProperties"]["type"]);
+        assert_eq!(Value::Null, properties["map"]["properties"]);
+
+        // empty object is a struct with no properties
+        let schema = infer_schema(&Value::Object(Map::new()));
+        assert_eq!(Value::Object(Map::new()), schema["properties"]);
+    }
+}
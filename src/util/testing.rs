@@ -3,16 +3,19 @@
 //! Colleciton of helper functions used in tests module
 
 use std::env;
+use std::io::{Read, Write};
+use std::net::TcpListener;
 use std::str::FromStr;
-use std::sync::Once;
+use std::sync::{Arc, Once};
+use std::thread;
 
 use bitcoin::hashes::{hex::FromHex, sha256d, Hash};
 use bitcoin::secp256k1::PublicKey;
 
-use crate::challenger::ChallengeState;
+use crate::challenger::{ChallengeState, LatestChallenge};
 use crate::interfaces::{
     bid::{Bid, BidSet},
-    request::Request as ServiceRequest,
+    request::{PaymentState, Request as ServiceRequest},
 };
 
 static INIT: Once = Once::new();
@@ -42,6 +45,11 @@ pub fn gen_challenge_state(request_hash: &sha256d::Hash) -> ChallengeState {
         start_blockheight_clientchain: 0,
         end_blockheight_clientchain: 0,
         is_payment_complete: false,
+        payment_state: PaymentState::Pending,
+        response_summary: None,
+        fees: vec![],
+        fee_filter: None,
+        failure: None,
     };
     let mut bids = BidSet::new();
     let _ = bids.insert(Bid {
@@ -51,8 +59,11 @@ pub fn gen_challenge_state(request_hash: &sha256d::Hash) -> ChallengeState {
     });
     ChallengeState {
         request,
-        bids,
-        latest_challenge: Some(gen_dummy_hash(0)),
+        bids: Arc::new(bids),
+        spilled_bids: None,
+        latest_challenge: Arc::new(LatestChallenge::new(Some(gen_dummy_hash(0)))),
+        payload: None,
+        commit_reveal: false,
     }
 }
 
@@ -71,6 +82,11 @@ pub fn gen_challenge_state_with_challenge(
         start_blockheight_clientchain: 0,
         end_blockheight_clientchain: 0,
         is_payment_complete: false,
+        payment_state: PaymentState::Pending,
+        response_summary: None,
+        fees: vec![],
+        fee_filter: None,
+        failure: None,
     };
     let mut bids = BidSet::new();
     let _ = bids.insert(Bid {
@@ -81,7 +97,25 @@ pub fn gen_challenge_state_with_challenge(
     });
     ChallengeState {
         request,
-        bids,
-        latest_challenge: Some(*challenge_hash),
+        bids: Arc::new(bids),
+        spilled_bids: None,
+        latest_challenge: Arc::new(LatestChallenge::new(Some(*challenge_hash))),
+        payload: None,
+        commit_reveal: false,
     }
 }
+
+/// Serve a single canned http response on a local port returning the url
+/// served and a handle resolving to the raw request received
+pub fn serve_once(response: &'static str) -> (String, thread::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/path", listener.local_addr().unwrap());
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0; 1024];
+        let len = stream.read(&mut buf).unwrap();
+        stream.write_all(response.as_bytes()).unwrap();
+        String::from_utf8_lossy(&buf[..len]).into_owned()
+    });
+    (url, handle)
+}
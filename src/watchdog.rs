@@ -0,0 +1,194 @@
+//! Watchdog
+//!
+//! Liveness watchdog of the challenger loop. The loop beats a heartbeat on
+//! each iteration and challenge refresh, and a loop that has not beaten the
+//! heartbeat for longer than the stall timeout, e.g. while stuck in an rpc
+//! call that never returns, is abandoned with its request left unfinished and
+//! replaced by a new loop resuming the request from storage. Challenge
+//! responses are relayed to the loop currently running, so that the abandoned
+//! loop receives none
+
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bitcoin::hashes::sha256d;
+use serde::Serialize;
+
+use crate::challenger::ChallengeResponse;
+
+/// Latest heartbeat of the challenger loop
+struct Beat {
+    /// Time of the latest beat
+    time: Instant,
+    /// Stage of the loop at the latest beat
+    stage: &'static str,
+    /// Request being challenged, if any
+    request: Option<sha256d::Hash>,
+    /// Whether the loop is idle until the next beat, e.g. while waiting for
+    /// unreachable chains, in which case no stall is detected
+    idle: bool,
+}
+
+/// Heartbeat of the challenger loop, beaten by the loop and checked for stalls
+/// by the coordinator
+pub struct Heartbeat {
+    /// Latest beat
+    beat: Mutex<Beat>,
+}
+
+impl Heartbeat {
+    /// Return new Heartbeat instance beaten now
+    pub fn new() -> Heartbeat {
+        Heartbeat {
+            beat: Mutex::new(Beat {
+                time: Instant::now(),
+                stage: "start",
+                request: None,
+                idle: false,
+            }),
+        }
+    }
+
+    /// Beat the heartbeat at a stage of the loop
+    pub fn beat(&self, stage: &'static str) {
+        let mut beat = self.beat.lock().unwrap();
+        beat.time = Instant::now();
+        beat.stage = stage;
+        beat.idle = false;
+    }
+
+    /// Set the request being challenged, or None between requests
+    pub fn set_request(&self, request: Option<sha256d::Hash>) {
+        self.beat.lock().unwrap().request = request;
+    }
+
+    /// Mark the loop idle at a stage until the next beat
+    pub fn idle(&self, stage: &'static str) {
+        let mut beat = self.beat.lock().unwrap();
+        beat.stage = stage;
+        beat.idle = true;
+    }
+
+    /// Get the stall of the loop if the heartbeat has not been beaten for
+    /// longer than the timeout while not idle
+    pub fn stall(&self, timeout: Duration) -> Option<Stall> {
+        let beat = self.beat.lock().unwrap();
+        let elapsed = beat.time.elapsed();
+        if beat.idle || elapsed <= timeout {
+            return None;
+        }
+        Some(Stall {
+            stage: beat.stage,
+            request: beat.request,
+            elapsed_secs: elapsed.as_secs(),
+        })
+    }
+}
+
+/// Diagnostics of a stalled challenger loop
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Stall {
+    /// Stage of the loop at the latest beat
+    pub stage: &'static str,
+    /// Request being challenged, if any
+    pub request: Option<sha256d::Hash>,
+    /// Seconds since the latest beat
+    pub elapsed_secs: u64,
+}
+
+/// Relay of challenge responses from the listener and api to the challenger
+/// loop currently running
+pub struct ResponseRelay {
+    /// Sender of the responses to the current loop
+    target: Arc<Mutex<Sender<ChallengeResponse>>>,
+}
+
+impl ResponseRelay {
+    /// Relay the responses received in a separate thread until all their
+    /// senders are dropped. Responses are discarded until a loop attaches
+    pub fn new(verify_rx: Receiver<ChallengeResponse>) -> ResponseRelay {
+        let (tx, _) = channel();
+        let target = Arc::new(Mutex::new(tx));
+        let relay_target = target.clone();
+        let _ = thread::spawn(move || {
+            for response in verify_rx.iter() {
+                let _ = relay_target.lock().unwrap().send(response);
+            }
+        });
+        ResponseRelay { target }
+    }
+
+    /// Attach a new loop, returning the receiver of its responses. The
+    /// receiver of any previous loop is disconnected
+    pub fn attach(&self) -> Receiver<ChallengeResponse> {
+        let (tx, rx) = channel();
+        *self.target.lock().unwrap() = tx;
+        rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc::RecvTimeoutError;
+
+    use crate::interfaces::mocks::service::MockService;
+    use crate::interfaces::service::Service;
+    use crate::util::testing::gen_dummy_hash;
+
+    #[test]
+    fn heartbeat_test() {
+        let heartbeat = Heartbeat::new();
+        assert_eq!(None, heartbeat.stall(Duration::from_secs(60)));
+
+        heartbeat.set_request(Some(gen_dummy_hash(1)));
+        heartbeat.beat("refresh");
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(
+            Some(Stall {
+                stage: "refresh",
+                request: Some(gen_dummy_hash(1)),
+                elapsed_secs: 0,
+            }),
+            heartbeat.stall(Duration::from_millis(10))
+        );
+
+        // no stall while idle, until the next beat
+        heartbeat.idle("degraded");
+        assert_eq!(None, heartbeat.stall(Duration::from_millis(10)));
+        heartbeat.beat("loop");
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!("loop", heartbeat.stall(Duration::from_millis(10)).unwrap().stage);
+    }
+
+    #[test]
+    fn response_relay_test() {
+        let (verify_tx, verify_rx) = channel();
+        let relay = ResponseRelay::new(verify_rx);
+        let bid = MockService::new()
+            .get_request_bids(&gen_dummy_hash(3))
+            .unwrap()
+            .unwrap()
+            .iter()
+            .next()
+            .unwrap()
+            .clone();
+        let response = ChallengeResponse(gen_dummy_hash(1), bid);
+
+        let rx = relay.attach();
+        verify_tx.send(response.clone()).unwrap();
+        assert_eq!(response, rx.recv_timeout(Duration::from_secs(1)).unwrap());
+
+        // responses go to the latest loop attached only
+        let new_rx = relay.attach();
+        verify_tx.send(response.clone()).unwrap();
+        assert_eq!(response, new_rx.recv_timeout(Duration::from_secs(1)).unwrap());
+        assert_eq!(
+            Err(RecvTimeoutError::Disconnected),
+            rx.recv_timeout(Duration::from_millis(10))
+        );
+    }
+}